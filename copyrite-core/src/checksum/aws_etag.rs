@@ -3,9 +3,9 @@
 //!
 
 use crate::checksum::standard::StandardCtx;
-use crate::error::Error::ParseError;
+use crate::error::Error::{GenerateError, ParseError};
 use crate::error::{Error, Result};
-use crate::io::Provider;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
@@ -92,7 +92,6 @@ pub struct AWSETagCtx {
     current_part_size: u64,
     current_bytes: u64,
     total_bytes: u64,
-    remainder: Option<Arc<[u8]>>,
     part_checksums: Vec<(u64, Vec<u8>)>,
     n_checksums: u64,
     ctx: StandardCtx,
@@ -145,6 +144,17 @@ impl Hash for AWSETagCtx {
     }
 }
 
+/// A range of consecutive parts that have the same size.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PartRange {
+    /// The first part number of the range, starting from 1.
+    pub first_part: u64,
+    /// The last part number of the range, inclusive.
+    pub last_part: u64,
+    /// The size of each part in the range.
+    pub part_size: u64,
+}
+
 /// The mode to operate aws etags in. Part numbers calculate parts using the total file size.
 /// Part sizes can operate without the file size.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
@@ -162,7 +172,6 @@ impl AWSETagCtx {
             current_part_size: 0,
             current_bytes: 0,
             total_bytes: 0,
-            remainder: None,
             part_checksums: vec![],
             n_checksums: 0,
             ctx,
@@ -270,26 +279,27 @@ impl AWSETagCtx {
             let (data, remainder) = data.split_at(usize::try_from(
                 self.current_part_size
                     .checked_sub(self.current_bytes)
-                    .ok_or_else(|| Error::aws_error("part size too large".to_string()))?,
+                    .ok_or_else(|| GenerateError("part size too large".to_string()))?,
             )?);
 
             self.ctx.update(Arc::from(data))?;
+            self.total_bytes += u64::try_from(data.len())?;
             self.part_checksums
                 .push((self.current_part_size, self.ctx.finalize()?));
 
-            // Reset the current bytes and any remainder bytes.
-            self.current_bytes = u64::try_from(remainder.len())?;
-            self.remainder = Some(Arc::from(remainder));
-
-            // Reset the context for next chunk.
+            // Reset the current bytes and the context for next chunk.
+            self.current_bytes = 0;
             self.ctx = self.ctx.reset();
 
             // Update the part size.
             self.current_part_size = self.next_part_size()?;
+
+            // The remainder can span multiple parts if the data is larger than the part size.
+            if !remainder.is_empty() {
+                self.update(Arc::from(remainder))?;
+            }
         } else {
             // Otherwise update as usual, tracking the byte position.
-            self.update_with_remainder()?;
-
             self.current_bytes += len;
             self.total_bytes += len;
 
@@ -299,26 +309,13 @@ impl AWSETagCtx {
         Ok(())
     }
 
-    /// Update the checksummer context with remainder bytes.
-    fn update_with_remainder(&mut self) -> Result<()> {
-        let remainder = self.remainder.take();
-        if let Some(remainder) = remainder {
-            self.ctx.update(remainder)?;
-            self.remainder = None;
-        }
-        Ok(())
-    }
-
     /// Finalize the checksum.
     pub fn finalize(&mut self) -> Result<Vec<u8>> {
         // Add the last part checksum.
-        if self.remainder.is_some() || self.current_bytes != 0 {
-            self.update_with_remainder()?;
+        if self.current_bytes != 0 {
             self.part_checksums
                 .push((self.current_bytes, self.ctx.finalize()?));
 
-            self.total_bytes += self.current_bytes;
-
             // Reset the context for merged chunks.
             self.ctx = self.ctx.reset();
         }
@@ -338,6 +335,30 @@ impl AWSETagCtx {
         self.ctx.finalize()
     }
 
+    /// Create a context from the sizes and digests of parts that were checksummed separately,
+    /// such as the segments of a Swift large object, and combine the part digests in the same
+    /// way as `finalize`. Returns the context with the combined digest.
+    pub fn from_part_checksums(
+        ctx: StandardCtx,
+        part_checksums: Vec<(u64, Vec<u8>)>,
+    ) -> Result<(Self, Vec<u8>)> {
+        if part_checksums.is_empty() {
+            return Err(GenerateError(
+                "expected at least one part checksum".to_string(),
+            ));
+        }
+
+        let part_sizes = part_checksums.iter().map(|(size, _)| *size).collect();
+        let file_size = part_checksums.iter().map(|(size, _)| size).sum();
+
+        let mut aws_ctx = Self::new(ctx, PartMode::PartSizes(part_sizes), Some(file_size));
+        aws_ctx.total_bytes = file_size;
+        aws_ctx.part_checksums = part_checksums;
+        let digest = aws_ctx.finalize()?;
+
+        Ok((aws_ctx, digest))
+    }
+
     /// Parse into a `ChecksumCtx` for values that use endianness. Parses an -aws-<n> suffix,
     /// where n represents the part size to calculate.
     pub fn parse_part_size(s: &str) -> Result<(String, PartMode)> {
@@ -450,16 +471,80 @@ impl AWSETagCtx {
             .join("-")
     }
 
+    /// Get the layout of the parts for a file of `file_size` bytes, as ranges of consecutive
+    /// parts with the same size. An empty file has a single empty part.
+    pub fn part_layout(&self, file_size: u64) -> Result<Vec<PartRange>> {
+        let mut part_sizes = match &self.part_mode {
+            PartMode::PartNumber(part_number) => {
+                vec![Self::part_number_to_size(*part_number, file_size)]
+            }
+            PartMode::PartSizes(part_sizes) => part_sizes.clone(),
+        };
+        if file_size > 0 && part_sizes.contains(&0) {
+            return Err(ParseError("cannot use zero part size".to_string()));
+        }
+
+        Self::iterate_part_sizes(file_size, &mut part_sizes);
+
+        let mut layout: Vec<PartRange> = vec![];
+        for (part_number, part_size) in (1..).zip(part_sizes) {
+            match layout.last_mut() {
+                Some(range) if range.part_size == part_size => range.last_part = part_number,
+                _ => layout.push(PartRange {
+                    first_part: part_number,
+                    last_part: part_number,
+                    part_size,
+                }),
+            }
+        }
+
+        Ok(layout)
+    }
+
     /// Convert a part number to a part size using the file size.
     pub fn part_number_to_size(part_number: u64, file_size: u64) -> u64 {
         file_size.div_ceil(part_number)
     }
 
+    /// Infer the preferred part sizes that split a file of `file_size` bytes into exactly
+    /// `n_parts` parts. The part sizes are returned in order of preference.
+    pub fn infer_part_sizes(n_parts: u64, file_size: u64) -> Vec<u64> {
+        PREFERRED_PART_SIZES
+            .iter()
+            .copied()
+            .filter(|part_size| file_size.div_ceil(*part_size) == n_parts)
+            .collect()
+    }
+
+    /// Get the smallest and largest part sizes that split a file of `file_size` bytes into
+    /// exactly `n_parts` parts. The largest part size is `None` if there is a single part, as
+    /// any part size at least as large as the file produces one part. Returns `None` if no part
+    /// size produces `n_parts` parts.
+    pub fn part_size_range(n_parts: u64, file_size: u64) -> Option<(u64, Option<u64>)> {
+        if n_parts == 0 || file_size < n_parts {
+            return None;
+        }
+
+        let min = Self::part_number_to_size(n_parts, file_size);
+        if n_parts == 1 {
+            return Some((min, None));
+        }
+
+        // A part size produces `n_parts` parts if `(n_parts - 1) * part_size < file_size`.
+        let max = (file_size - 1) / (n_parts - 1);
+        (min <= max).then_some((min, Some(max)))
+    }
+
     /// Set the file size.
     pub fn set_file_size(&mut self, file_size: Option<u64>) {
         self.file_size = file_size;
     }
 
+    /// Whether the parts are defined by a part number, which requires the file size.
+    pub fn is_part_number(&self) -> bool {
+        matches!(self.part_mode, PartMode::PartNumber(_))
+    }
+
     /// Get the encoded part checksums and their part sizes.
     pub fn part_checksums(&self) -> Vec<(u64, String)> {
         self.part_checksums
@@ -470,10 +555,11 @@ impl AWSETagCtx {
 
     /// Does this context represent a valid and preferred multipart checksum. All multipart
     /// checksums that AWS can use are preferred except for those with different sized part sizes.
-    /// Returns the preferred part size.
-    pub fn is_preferred_multipart(&self, provider: &Provider) -> Option<u64> {
+    /// Returns the preferred part size. `s3` is whether the checksum is used with an S3
+    /// destination.
+    pub fn is_preferred_multipart(&self, s3: bool) -> Option<u64> {
         let part_sizes = self.get_part_sizes();
-        if part_sizes.len() == 1 && self.ctx.is_preferred_cloud_ctx(provider) {
+        if part_sizes.len() == 1 && self.ctx.is_preferred_cloud_ctx(s3) {
             Some(part_sizes[0])
         } else {
             None
@@ -504,28 +590,12 @@ impl Display for AWSETagCtx {
 }
 
 #[cfg(test)]
-pub(crate) mod test {
-    use crate::checksum::aws_etag::{AWSETagCtx, PartMode};
+mod test {
+    use crate::checksum::aws_etag::{AWSETagCtx, MIB, PartMode, PartRange};
     use crate::checksum::standard::StandardCtx;
-    use crate::checksum::test::test_checksum;
     use anyhow::Result;
     use std::str::FromStr;
-
-    pub(crate) fn expected_md5_1gib() -> &'static str {
-        "6c434b38867bbd608ba2f06e92ed4e43-1073741824b"
-    }
-
-    pub(crate) fn expected_md5_100mib() -> &'static str {
-        "e5727bb1cb678220f6782ff6cb927569-104857600b"
-    }
-
-    pub(crate) fn expected_md5_10() -> &'static str {
-        "9a9666a5c313c53fbc3a3ea1d43cc981-107374183b"
-    }
-
-    pub(crate) fn expected_sha256_100mib() -> &'static str {
-        "a9ed6c4b6aadf887f90a3d483b5c5b79bc08075af2a1718e3e15c63b9904ebf7-104857600b"
-    }
+    use std::sync::Arc;
 
     #[test]
     fn test_ordering() -> Result<()> {
@@ -610,31 +680,130 @@ pub(crate) mod test {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_aws_etag_single_part() -> Result<()> {
-        test_checksum("md5-aws-1gib", expected_md5_1gib()).await?;
-        test_checksum("aws-etag-1gib", expected_md5_1gib()).await?;
+    #[test]
+    fn test_update_larger_than_part_size() -> Result<()> {
+        let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let digest = |chunk_size: usize| -> Result<(String, usize)> {
+            let mut ctx = AWSETagCtx::new(
+                StandardCtx::md5(),
+                PartMode::PartSizes(vec![1000]),
+                Some(10000),
+            );
+            for chunk in data.chunks(chunk_size) {
+                ctx.update(Arc::from(chunk))?;
+            }
+            let digest = ctx.finalize()?;
+            Ok((ctx.digest_to_string(&digest), ctx.part_checksums().len()))
+        };
+
+        // Chunks spanning many parts must split into the same parts as small chunks.
+        let expected = digest(100)?;
+        assert_eq!(expected.1, 10);
+        assert_eq!(digest(3500)?, expected);
+        assert_eq!(digest(10000)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_part_checksums() -> Result<()> {
+        let data = vec![1; 13];
+        let mut expected = AWSETagCtx::new(StandardCtx::md5(), PartMode::PartSizes(vec![5]), None);
+        expected.update(Arc::from(data.as_slice()))?;
+        let expected_digest = expected.finalize()?;
+
+        let part_checksums = data
+            .chunks(5)
+            .map(|chunk| {
+                let mut ctx = StandardCtx::md5();
+                ctx.update(Arc::from(chunk))?;
+                Ok((u64::try_from(chunk.len())?, ctx.finalize()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (ctx, digest) = AWSETagCtx::from_part_checksums(StandardCtx::md5(), part_checksums)?;
+
+        assert_eq!(digest, expected_digest);
+        assert_eq!(ctx.get_part_sizes(), vec![5]);
+        assert_eq!(ctx.to_string(), "md5-aws-5b");
+        assert!(AWSETagCtx::from_part_checksums(StandardCtx::md5(), vec![]).is_err());
 
-        // Larger part sizes should also work.
-        test_checksum("md5-aws-2gib", expected_md5_1gib()).await?;
-        test_checksum("aws-etag-2gib", expected_md5_1gib()).await
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_aws_etag_md5() -> Result<()> {
-        test_checksum("md5-aws-100mib", expected_md5_100mib()).await?;
-        test_checksum("aws-etag-100mib", expected_md5_100mib()).await
+    #[test]
+    fn test_infer_part_sizes() {
+        assert_eq!(AWSETagCtx::infer_part_sizes(13, 100 * MIB), vec![8 * MIB]);
+        assert_eq!(
+            AWSETagCtx::infer_part_sizes(2, 18 * MIB),
+            vec![16 * MIB, 15 * MIB, 10 * MIB]
+        );
+        assert!(AWSETagCtx::infer_part_sizes(3, 100).is_empty());
     }
 
-    #[tokio::test]
-    async fn test_aws_etag_sha256() -> Result<()> {
-        test_checksum("sha256-aws-100mib", expected_sha256_100mib()).await
+    #[test]
+    fn test_part_size_range() {
+        assert_eq!(AWSETagCtx::part_size_range(1, 10), Some((10, None)));
+        assert_eq!(AWSETagCtx::part_size_range(3, 10), Some((4, Some(4))));
+        assert_eq!(AWSETagCtx::part_size_range(2, 10), Some((5, Some(9))));
+        assert_eq!(AWSETagCtx::part_size_range(0, 10), None);
+        assert_eq!(AWSETagCtx::part_size_range(11, 10), None);
+        assert_eq!(AWSETagCtx::part_size_range(4, 10), Some((3, Some(3))));
+        // No part size splits 10 bytes into exactly 6 parts.
+        assert_eq!(AWSETagCtx::part_size_range(6, 10), None);
     }
 
-    #[tokio::test]
-    async fn test_aws_etag_part_number() -> Result<()> {
-        test_checksum("md5-aws-10", expected_md5_10()).await?;
-        test_checksum("aws-etag-10", expected_md5_10()).await
+    #[test]
+    fn test_part_layout() -> Result<()> {
+        let ctx = AWSETagCtx::from_str("md5-aws-8mib-16mib")?;
+        assert_eq!(
+            ctx.part_layout(100 * MIB)?,
+            vec![
+                PartRange {
+                    first_part: 1,
+                    last_part: 1,
+                    part_size: 8 * MIB
+                },
+                PartRange {
+                    first_part: 2,
+                    last_part: 6,
+                    part_size: 16 * MIB
+                },
+                PartRange {
+                    first_part: 7,
+                    last_part: 7,
+                    part_size: 12 * MIB
+                },
+            ]
+        );
+
+        let ctx = AWSETagCtx::from_str("md5-aws-4")?;
+        assert_eq!(
+            ctx.part_layout(10)?,
+            vec![
+                PartRange {
+                    first_part: 1,
+                    last_part: 3,
+                    part_size: 3
+                },
+                PartRange {
+                    first_part: 4,
+                    last_part: 4,
+                    part_size: 1
+                },
+            ]
+        );
+
+        assert_eq!(
+            ctx.part_layout(0)?,
+            vec![PartRange {
+                first_part: 1,
+                last_part: 1,
+                part_size: 0
+            }]
+        );
+        assert!(AWSETagCtx::from_str("md5-aws-0b")?.part_layout(1).is_err());
+
+        Ok(())
     }
 
     fn assert_update_part_sizes(part_sizes: Vec<u64>, file_size: u64, expected: Vec<u64>) {
@@ -0,0 +1,133 @@
+//! Combine CRC checksums of adjacent data without reading the data again.
+//!
+//! This uses the same approach as zlib's `crc32_combine`, where appending zeros to the first
+//! CRC is represented as a matrix over GF(2), and applies to any reflected CRC whose initial
+//! value and final XOR are equal.
+
+/// The reflected polynomial of CRC32.
+pub const CRC32_POLYNOMIAL: u64 = 0xedb88320;
+/// The reflected polynomial of CRC32C.
+pub const CRC32C_POLYNOMIAL: u64 = 0x82f63b78;
+/// The reflected polynomial of CRC64NVME.
+pub const CRC64NVME_POLYNOMIAL: u64 = 0x9a6c9329ac4bc9b5;
+
+/// A matrix over GF(2) with one column per bit of the CRC.
+type Matrix = [u64; 64];
+
+/// Multiply a matrix by a vector.
+fn times(matrix: &Matrix, mut vector: u64) -> u64 {
+    let mut sum = 0;
+    let mut row = 0;
+    while vector != 0 {
+        if vector & 1 != 0 {
+            sum ^= matrix[row];
+        }
+        vector >>= 1;
+        row += 1;
+    }
+
+    sum
+}
+
+/// Square a matrix, with `width` columns.
+fn square(matrix: &Matrix, width: usize) -> Matrix {
+    let mut squared = [0; 64];
+    for (n, column) in squared.iter_mut().take(width).enumerate() {
+        *column = times(matrix, matrix[n]);
+    }
+
+    squared
+}
+
+/// Combine the CRC of some data with the CRC of the data that follows it, where `len2` is the
+/// length in bytes of the second part. `polynomial` is the reflected polynomial of a CRC that
+/// is `width` bits wide.
+pub fn crc_combine(polynomial: u64, width: usize, mut crc1: u64, crc2: u64, mut len2: u64) -> u64 {
+    if len2 == 0 {
+        return crc1;
+    }
+
+    // The operator for one zero bit.
+    let mut odd = [0; 64];
+    odd[0] = polynomial;
+    for (n, column) in odd.iter_mut().enumerate().take(width).skip(1) {
+        *column = 1 << (n - 1);
+    }
+
+    // The operators for two and four zero bits.
+    let mut even = square(&odd, width);
+    odd = square(&even, width);
+
+    // Apply one zero byte for each bit set in the length, doubling the operator each time.
+    loop {
+        even = square(&odd, width);
+        if len2 & 1 != 0 {
+            crc1 = times(&even, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+
+        odd = square(&even, width);
+        if len2 & 1 != 0 {
+            crc1 = times(&odd, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+    }
+
+    crc1 ^ crc2
+}
+
+/// Combine the CRC64NVME of some data with the CRC64NVME of the data that follows it.
+pub fn crc64nvme_combine(crc1: u64, crc2: u64, len2: u64) -> u64 {
+    crc_combine(CRC64NVME_POLYNOMIAL, 64, crc1, crc2, len2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn crc64nvme(data: &[u8]) -> u64 {
+        let mut digest = crc64fast_nvme::Digest::new();
+        digest.write(data);
+        digest.sum64()
+    }
+
+    #[test]
+    fn combine_crcs() {
+        let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        for split in [0, 1, 7, 4096, 9999, 10000] {
+            let (first, second) = data.split_at(split);
+
+            assert_eq!(
+                crc64nvme_combine(crc64nvme(first), crc64nvme(second), second.len() as u64),
+                crc64nvme(&data)
+            );
+            assert_eq!(
+                crc_combine(
+                    CRC32_POLYNOMIAL,
+                    32,
+                    crc32fast::hash(first) as u64,
+                    crc32fast::hash(second) as u64,
+                    second.len() as u64
+                ),
+                crc32fast::hash(&data) as u64
+            );
+            assert_eq!(
+                crc_combine(
+                    CRC32C_POLYNOMIAL,
+                    32,
+                    crc32c::crc32c(first) as u64,
+                    crc32c::crc32c(second) as u64,
+                    second.len() as u64
+                ),
+                crc32c::crc32c(&data) as u64
+            );
+        }
+    }
+}
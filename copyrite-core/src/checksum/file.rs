@@ -0,0 +1,598 @@
+//! Defines the file format that outputs checksum results
+//!
+
+use crate::checksum::Ctx;
+use crate::error::Error::SumsFileError;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{from_slice, to_string};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use std::time::SystemTime;
+
+/// The current version of the output file. Version 2 added the `state`, `last-verified-at`,
+/// `verified-version` and `rejected-checksums` keys, which version 1 readers parse as checksums.
+pub const OUTPUT_FILE_VERSION: &str = "2";
+
+/// The file ending of a sums file.
+pub const SUMS_FILE_ENDING: &str = ".sums";
+
+/// A file containing multiple checksums.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct SumsFile {
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // When the object was last read and confirmed to match the checksums, as an RFC 3339
+    // timestamp.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_timestamp",
+        deserialize_with = "deserialize_timestamp"
+    )]
+    pub last_verified_at: Option<SystemTime>,
+    // The `ETag` and version of the object when it was last verified, used to detect objects
+    // that were modified afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_version: Option<ObjectVersion>,
+    // Additional checksums that the store rejected when the object was copied to it, so they
+    // are not stored with the object and were computed from its data instead.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub rejected_checksums: BTreeSet<String>,
+    // The name of the checksum is always the most canonical form.
+    // E.g. no -be prefix for big-endian, and the part size as
+    // the suffix for AWS checksums.
+    #[serde(flatten)]
+    pub checksums: BTreeMap<Ctx, Checksum>,
+    // The intermediate hash state of checksums, which allows resuming checksums when the
+    // file is appended to.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub state: BTreeMap<Ctx, String>,
+}
+
+impl Default for SumsFile {
+    fn default() -> Self {
+        Self::new(None, BTreeMap::new())
+    }
+}
+
+impl SumsFile {
+    /// Create an output file.
+    pub fn new(size: Option<u64>, checksums: BTreeMap<Ctx, Checksum>) -> Self {
+        Self {
+            version: OUTPUT_FILE_VERSION.to_string(),
+            size,
+            last_verified_at: None,
+            verified_version: None,
+            rejected_checksums: BTreeSet::new(),
+            checksums,
+            state: BTreeMap::new(),
+        }
+    }
+
+    /// Format a sums file with the ending.
+    pub fn format_sums_file(name: &str) -> String {
+        if name.ends_with(SUMS_FILE_ENDING) {
+            name.to_string()
+        } else {
+            format!("{}{}", name, SUMS_FILE_ENDING)
+        }
+    }
+
+    /// Format the target file that the sums file is for.
+    pub fn format_target_file(name: &str) -> String {
+        name.strip_suffix(SUMS_FILE_ENDING)
+            .unwrap_or(name)
+            .to_string()
+    }
+
+    /// Convert to a JSON string.
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(to_string(&self)?)
+    }
+
+    /// Read from a slice and add the name.
+    pub async fn read_from_slice(slice: &[u8]) -> Result<Self> {
+        slice.try_into()
+    }
+
+    /// Merge with another output file, overwriting existing checksums,
+    /// taking ownership of self. Returns an error if the size of the files
+    /// do not match, and both files are not empty.
+    pub fn merge(mut self, other: Self) -> Result<Self> {
+        if self.size != other.size && !self.checksums.is_empty() && !other.checksums.is_empty() {
+            return Err(SumsFileError(
+                "the size of output files do not match".to_string(),
+            ));
+        }
+
+        self.merge_mut(other);
+        Ok(self)
+    }
+
+    /// Merge with another output file, overwriting existing checksums. Does not
+    /// check if the file name and size is the same.
+    pub fn merge_mut(&mut self, other: Self) {
+        for (key, checksum) in other.checksums {
+            // A saved state no longer belongs to a checksum that is replaced.
+            if !other.state.contains_key(&key) {
+                self.state.remove(&key);
+            }
+            self.checksums.insert(key, checksum);
+        }
+        self.state.extend(other.state);
+        if other.last_verified_at >= self.last_verified_at && other.verified_version.is_some() {
+            self.verified_version = other.verified_version;
+        }
+        self.last_verified_at = self.last_verified_at.max(other.last_verified_at);
+        self.upgrade_for_keys();
+    }
+
+    /// Upgrade the file to the current version. Files keep the version that they were read
+    /// with until this is called, or until keys added after that version are set on them.
+    pub fn upgrade(&mut self) {
+        self.version = OUTPUT_FILE_VERSION.to_string();
+    }
+
+    /// Upgrade the file if it contains any keys that were added after version 1, so that
+    /// readers which only understand version 1 reject it instead of misreading it.
+    fn upgrade_for_keys(&mut self) {
+        if self.last_verified_at.is_some()
+            || self.verified_version.is_some()
+            || !self.rejected_checksums.is_empty()
+            || !self.state.is_empty()
+        {
+            self.upgrade();
+        }
+    }
+
+    /// Split the sums file into multiple sums files, one for each checksum.
+    pub fn split(self) -> Vec<SumsFile> {
+        self.checksums
+            .iter()
+            .map(|(ctx, checksum)| {
+                let mut sums_file = Self::default().with_size(self.size);
+                sums_file.add_checksum(ctx.clone(), checksum.clone());
+
+                sums_file
+            })
+            .collect()
+    }
+
+    /// Check if the sums file is the same as another according to all available checksums
+    /// in the sums file. Returns the key value that resulted in equality if the sums are the same.
+    pub fn is_same(&self, other: &Self) -> Option<(&Ctx, &Checksum)> {
+        if self.size != other.size {
+            return None;
+        }
+
+        for (key, checksum) in &self.checksums {
+            if let Some(other_checksum) = other.checksums.get(key) {
+                // Two checksums are the same if they have the same top-level checksum. Since the
+                // top level checksum encodes part information for AWS sums, there is no need to
+                // compare the part checksums.
+                if checksum == other_checksum {
+                    return Some((key, checksum));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Check if the sums file is comparable to another sums file because it contains at least
+    /// one of the same checksum type. Returns the key value that resulted in comparability if the
+    /// sums are the same.
+    pub fn comparable(&self, other: &Self) -> Option<(&Ctx, &Checksum)> {
+        if self.size != other.size {
+            return None;
+        }
+
+        for (key, value) in &self.checksums {
+            if other.checksums.contains_key(key) {
+                return Some((key, value));
+            }
+        }
+
+        None
+    }
+
+    /// When the object was last confirmed to match the checksums.
+    pub fn last_verified_at(&self) -> Option<SystemTime> {
+        self.last_verified_at
+    }
+
+    /// Set when the object was last confirmed to match the checksums.
+    pub fn set_last_verified_at(&mut self, last_verified_at: Option<SystemTime>) {
+        self.last_verified_at = last_verified_at;
+        self.upgrade_for_keys();
+    }
+
+    /// The version of the object when it was last confirmed to match the checksums.
+    pub fn verified_version(&self) -> Option<&ObjectVersion> {
+        self.verified_version.as_ref()
+    }
+
+    /// Set the version of the object when it was last confirmed to match the checksums.
+    pub fn set_verified_version(&mut self, verified_version: Option<ObjectVersion>) {
+        self.verified_version = verified_version;
+        self.upgrade_for_keys();
+    }
+
+    /// The additional checksums that the store rejected when the object was copied to it.
+    pub fn rejected_checksums(&self) -> &BTreeSet<String> {
+        &self.rejected_checksums
+    }
+
+    /// Set the additional checksums that the store rejected when the object was copied to it.
+    pub fn set_rejected_checksums(&mut self, rejected_checksums: BTreeSet<String>) {
+        self.rejected_checksums = rejected_checksums;
+        self.upgrade_for_keys();
+    }
+
+    /// Return an error if the current version of the object at the location differs from the
+    /// version recorded when it was last verified.
+    pub fn assert_unmodified(&self, location: &str, current: Option<&ObjectVersion>) -> Result<()> {
+        match (self.verified_version(), current) {
+            (Some(verified), Some(current)) if verified.is_modified(current) => {
+                Err(Error::CheckError(format!(
+                    "{location} was modified since it was last verified: expected {verified}, found {current}"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the size.
+    pub fn with_size(mut self, size: Option<u64>) -> Self {
+        self.set_size(size);
+        self
+    }
+
+    /// Set the size from a mutable reference.
+    pub fn set_size(&mut self, size: Option<u64>) {
+        self.size = size;
+    }
+
+    /// Add a checksum to the sums file.
+    pub fn add_checksum(&mut self, ctx: Ctx, checksum: Checksum) {
+        self.checksums.insert(ctx, checksum);
+    }
+
+    /// Set the saved intermediate states of checksums.
+    pub fn with_state(mut self, state: BTreeMap<Ctx, String>) -> Self {
+        self.state = state;
+        self.upgrade_for_keys();
+        self
+    }
+
+    /// Does the sums file contain no checksums.
+    pub fn is_empty(&self) -> bool {
+        self.checksums.is_empty()
+    }
+}
+
+impl TryFrom<&[u8]> for SumsFile {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        let sums_file: Self = from_slice(value)?;
+
+        // Older versions keep their version, and are only upgraded when keys from a later
+        // version are set on them.
+        let supported = OUTPUT_FILE_VERSION.parse::<u64>().unwrap_or_default();
+        match sums_file.version.parse::<u64>() {
+            Ok(version) if version <= supported => Ok(sums_file),
+            _ => Err(SumsFileError(format!(
+                "unsupported sums file version {}, expected at most {}",
+                sums_file.version, OUTPUT_FILE_VERSION
+            ))),
+        }
+    }
+}
+
+/// The identity of an object's contents, from its `ETag` and version id.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct ObjectVersion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+}
+
+impl ObjectVersion {
+    /// Create an object version.
+    pub fn new(e_tag: Option<String>, version_id: Option<String>) -> Self {
+        Self { e_tag, version_id }
+    }
+
+    /// Check whether the current version of an object differs from this version. Only values
+    /// which are known for both versions are compared.
+    pub fn is_modified(&self, current: &ObjectVersion) -> bool {
+        fn differs(a: &Option<String>, b: &Option<String>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+
+        differs(&self.e_tag, &current.e_tag) || differs(&self.version_id, &current.version_id)
+    }
+}
+
+impl Display for ObjectVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.e_tag, &self.version_id) {
+            (Some(e_tag), Some(version_id)) => write!(f, "ETag {e_tag}, version {version_id}"),
+            (Some(e_tag), None) => write!(f, "ETag {e_tag}"),
+            (None, Some(version_id)) => write!(f, "version {version_id}"),
+            (None, None) => write!(f, "unknown version"),
+        }
+    }
+}
+
+/// Serialize a timestamp as RFC 3339 with second precision.
+fn serialize_timestamp<S>(
+    timestamp: &Option<SystemTime>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    timestamp
+        .map(|timestamp| humantime::format_rfc3339_seconds(timestamp).to_string())
+        .serialize(serializer)
+}
+
+/// Deserialize an RFC 3339 timestamp.
+fn deserialize_timestamp<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<SystemTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|timestamp| humantime::parse_rfc3339(&timestamp).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// The output of a checksum.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct Checksum(String);
+
+impl Checksum {
+    /// Create an output checksum.
+    pub fn new(checksum: String) -> Self {
+        Self(checksum)
+    }
+
+    /// Get the checksum as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{Value, from_value, json, to_value};
+
+    const EXPECTED_ETAG: &str = "1c3490f45b0cdc4299a128410def3a1d-b";
+    const EXPECTED_MD5_SUM: &str = "d93e71879054f205ede90d35c8081ca5"; // pragma: allowlist secret
+    const EXPECTED_MD5_1GIB: &str = "6c434b38867bbd608ba2f06e92ed4e43-1073741824b";
+
+    #[test]
+    fn serialize_output_file() -> Result<()> {
+        let value = expected_output_file();
+        let result = to_value(&value)?;
+        let expected = expected_output_json();
+
+        assert_eq!(result, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_output_file() -> Result<()> {
+        let value = expected_output_json();
+        let result: SumsFile = from_value(value)?;
+        let expected = expected_output_file();
+
+        assert_eq!(result, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn last_verified_at() -> Result<()> {
+        let verified_at = humantime::parse_rfc3339("2025-01-02T03:04:05Z").unwrap();
+        let mut value = expected_output_file();
+        value.set_last_verified_at(Some(verified_at));
+
+        let mut expected = expected_output_json();
+        expected["last-verified-at"] = json!("2025-01-02T03:04:05Z");
+        assert_eq!(to_value(&value)?, expected);
+        assert_eq!(from_value::<SumsFile>(expected)?, value);
+
+        let merged = expected_output_file().merge(value)?;
+        assert_eq!(merged.last_verified_at(), Some(verified_at));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verified_version() -> Result<()> {
+        let version = ObjectVersion::new(Some("etag".to_string()), Some("1".to_string()));
+        let mut value = expected_output_file();
+        value.set_verified_version(Some(version.clone()));
+
+        let mut expected = expected_output_json();
+        expected["verified-version"] = json!({"e-tag": "etag", "version-id": "1"});
+        assert_eq!(to_value(&value)?, expected);
+        assert_eq!(from_value::<SumsFile>(expected)?, value);
+
+        let merged = expected_output_file().merge(value)?;
+        assert_eq!(merged.verified_version(), Some(&version));
+
+        assert!(!version.is_modified(&ObjectVersion::new(Some("etag".to_string()), None)));
+        assert!(version.is_modified(&ObjectVersion::new(Some("other".to_string()), None)));
+        assert!(version.is_modified(&ObjectVersion::new(
+            Some("etag".to_string()),
+            Some("2".to_string())
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_same() -> Result<()> {
+        let file_one = expected_output_file();
+        let mut file_two = file_one.clone();
+        let mut aws: Ctx = "md5-aws-123b".parse()?;
+        aws.set_file_size(Some(123));
+
+        file_two
+            .checksums
+            .insert(aws, Checksum::new(EXPECTED_ETAG.to_string()));
+        assert!(file_one.is_same(&file_two).is_some());
+
+        let mut file_two = file_one.clone();
+        let mut aws: Ctx = "aws-etag-1b".parse()?;
+        aws.set_file_size(Some(1));
+        set_checksums(&mut file_two, aws);
+
+        assert!(file_one.is_same(&file_two).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn comparable() -> Result<()> {
+        let file_one = expected_output_file();
+        let mut file_two = file_one.clone();
+
+        let mut aws: Ctx = "md5-aws-1b".parse()?;
+        aws.set_file_size(Some(1));
+        file_two
+            .checksums
+            .insert(aws, Checksum::new(EXPECTED_MD5_1GIB.to_string()));
+        assert!(file_one.comparable(&file_two).is_some());
+
+        let mut file_two = file_one.clone();
+        let mut aws: Ctx = "aws-etag-1b".parse()?;
+        aws.set_file_size(Some(1));
+        set_checksums(&mut file_two, aws);
+
+        assert!(file_one.comparable(&file_two).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge() -> Result<()> {
+        let expected_md5 = EXPECTED_MD5_SUM;
+        let mut file_one = expected_output_file();
+
+        let mut aws_one: Ctx = "aws-etag-123b".parse()?;
+        aws_one.set_file_size(Some(123));
+        file_one
+            .checksums
+            .insert(aws_one.clone(), Checksum::new(expected_md5.to_string()));
+
+        let mut file_two = expected_output_file();
+        let mut aws_two: Ctx = "md5-aws-123b".parse()?;
+        aws_two.set_file_size(Some(123));
+        set_checksums(&mut file_two, aws_two.clone());
+
+        let result = file_one.clone().merge(file_two)?;
+        assert_eq!(result.size, file_one.size);
+        assert_eq!(
+            result.checksums,
+            BTreeMap::from_iter(vec![
+                (aws_two, Checksum::new(EXPECTED_MD5_1GIB.to_string()),),
+                (aws_one, Checksum::new(EXPECTED_MD5_1GIB.to_string())),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_state() -> Result<()> {
+        let md5: Ctx = "md5".parse()?;
+        let sha1: Ctx = "sha1".parse()?;
+        let checksum = Checksum::new(EXPECTED_MD5_SUM.to_string());
+
+        let file_one = SumsFile::new(
+            Some(1),
+            BTreeMap::from_iter(vec![
+                (md5.clone(), checksum.clone()),
+                (sha1.clone(), checksum.clone()),
+            ]),
+        )
+        .with_state(BTreeMap::from_iter(vec![
+            (md5.clone(), "00".to_string()),
+            (sha1.clone(), "00".to_string()),
+        ]));
+        let file_two = SumsFile::new(
+            Some(1),
+            BTreeMap::from_iter(vec![(md5.clone(), checksum.clone())]),
+        );
+
+        let result = file_one.merge(file_two)?;
+        assert_eq!(
+            result.state,
+            BTreeMap::from_iter(vec![(sha1, "00".to_string())])
+        );
+
+        let json = to_value(&result)?;
+        assert_eq!(json["state"], json!({ "sha1": "00" }));
+        assert_eq!(from_value::<SumsFile>(json)?, result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_version() -> Result<()> {
+        let mut value = expected_output_json();
+        value["version"] = json!("1");
+        let mut result = SumsFile::try_from(serde_json::to_vec(&value)?.as_slice())?;
+        assert_eq!(result.version, "1");
+        assert_eq!(result.checksums, expected_output_file().checksums);
+
+        let mut merged = result.clone().merge(expected_output_file())?;
+        assert_eq!(merged.version, "1");
+        merged.upgrade();
+        assert_eq!(merged.version, OUTPUT_FILE_VERSION);
+
+        result.set_last_verified_at(Some(SystemTime::UNIX_EPOCH));
+        assert_eq!(result.version, OUTPUT_FILE_VERSION);
+
+        value["version"] = json!("3");
+        assert!(SumsFile::try_from(serde_json::to_vec(&value)?.as_slice()).is_err());
+
+        Ok(())
+    }
+
+    fn set_checksums(file_two: &mut SumsFile, aws: Ctx) {
+        file_two.checksums =
+            BTreeMap::from_iter(vec![(aws, Checksum::new(EXPECTED_MD5_1GIB.to_string()))]);
+    }
+
+    fn expected_output_file() -> SumsFile {
+        let mut aws: Ctx = "md5-aws-123b".parse().unwrap();
+        aws.set_file_size(Some(123));
+        let checksums = vec![(aws, Checksum::new(EXPECTED_ETAG.to_string()))];
+        SumsFile::new(Some(123), BTreeMap::from_iter(checksums))
+    }
+
+    fn expected_output_json() -> Value {
+        json!({
+            "version": OUTPUT_FILE_VERSION,
+            "size": 123,
+            "md5-aws-123b": EXPECTED_ETAG,
+        })
+    }
+}
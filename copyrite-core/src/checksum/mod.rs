@@ -0,0 +1,242 @@
+//! Checksum calculation and logic.
+//!
+
+pub mod aws_etag;
+pub mod combine;
+pub mod file;
+pub mod standard;
+pub mod uncompressed;
+
+use crate::checksum::aws_etag::AWSETagCtx;
+use crate::checksum::standard::StandardCtx;
+use crate::checksum::uncompressed::{UNCOMPRESSED_SUFFIX, UncompressedCtx};
+use crate::error::{Error, Result};
+use futures_util::{Stream, StreamExt, pin_mut};
+use serde::de::Error as SerdeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use std::result;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The checksum context. This enum also determines the best order of checksums,
+/// which is useful for copy operations. AWS etag checksums are preferred over
+/// regular checksums, and checksums of uncompressed contents are least preferred.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Ctx {
+    AWSEtag(AWSETagCtx),
+    Regular(StandardCtx),
+    Uncompressed(UncompressedCtx),
+}
+
+impl Default for Ctx {
+    fn default() -> Self {
+        Self::Regular(Default::default())
+    }
+}
+
+impl<'de> Deserialize<'de> for Ctx {
+    /// Implement deserialize using `FromStr`.
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Ctx {
+    /// Implement serialize using `ToString`.
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        String::serialize(&self.to_string(), serializer)
+    }
+}
+
+impl Ctx {
+    /// Update a checksum with some data.
+    pub fn update(&mut self, data: Arc<[u8]>) -> Result<()> {
+        match self {
+            Ctx::Regular(ctx) => ctx.update(data),
+            Ctx::AWSEtag(ctx) => ctx.update(data),
+            Ctx::Uncompressed(ctx) => ctx.update(data),
+        }
+    }
+
+    /// Finalize the checksum.
+    pub fn finalize(&mut self) -> Result<Vec<u8>> {
+        match self {
+            Ctx::Regular(ctx) => ctx.finalize(),
+            Ctx::AWSEtag(ctx) => ctx.finalize(),
+            Ctx::Uncompressed(ctx) => ctx.finalize(),
+        }
+    }
+
+    /// Generate a checksum from a stream of bytes. Errors from the stream are returned as is, so
+    /// that callers can use their own error type.
+    pub async fn generate<E: From<Error>>(
+        &mut self,
+        stream: impl Stream<Item = result::Result<Arc<[u8]>, E>>,
+    ) -> result::Result<Vec<u8>, E> {
+        self.consume(stream).await?;
+        Ok(self.finalize()?)
+    }
+
+    /// Update the checksum with all the bytes in a stream without finalizing it.
+    pub async fn consume<E: From<Error>>(
+        &mut self,
+        stream: impl Stream<Item = result::Result<Arc<[u8]>, E>>,
+    ) -> result::Result<(), E> {
+        pin_mut!(stream);
+
+        while let Some(chunk) = stream.next().await {
+            self.update(chunk?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the intermediate state of the checksum if it can be resumed later. AWS and
+    /// uncompressed contexts are not resumable.
+    pub fn state(&self) -> Option<String> {
+        match self {
+            Ctx::Regular(ctx) => ctx.state(),
+            Ctx::AWSEtag(_) | Ctx::Uncompressed(_) => None,
+        }
+    }
+
+    /// Create a context of the same kind which continues from a saved state.
+    pub fn resume(&self, state: &str) -> Result<Self> {
+        match self {
+            Ctx::Regular(ctx) => Ok(Ctx::Regular(ctx.resume(state)?)),
+            Ctx::AWSEtag(ctx) => Err(Error::ParseError(format!(
+                "resuming is not supported for {}",
+                ctx
+            ))),
+            Ctx::Uncompressed(ctx) => Err(Error::ParseError(format!(
+                "resuming is not supported for {}",
+                ctx
+            ))),
+        }
+    }
+
+    /// Get the digest output.
+    pub fn digest_to_string(&self, digest: &[u8]) -> String {
+        match self {
+            Ctx::Regular(ctx) => ctx.digest_to_string(digest),
+            Ctx::AWSEtag(ctx) => ctx.digest_to_string(digest),
+            Ctx::Uncompressed(ctx) => ctx.digest_to_string(digest),
+        }
+    }
+
+    /// Set the file size if this is an AWS context.
+    pub fn set_file_size(&mut self, file_size: Option<u64>) {
+        if let Ctx::AWSEtag(ctx) = self {
+            ctx.set_file_size(file_size);
+        }
+    }
+
+    /// Whether this is an AWS context with parts defined by a part number.
+    pub fn is_part_number(&self) -> bool {
+        matches!(self, Ctx::AWSEtag(ctx) if ctx.is_part_number())
+    }
+
+    /// Get the encoded part checksums and their part sizes if this is an AWS checksum context.
+    pub fn part_checksums(&self) -> Option<Vec<(u64, String)>> {
+        match self {
+            Ctx::Regular(_) | Ctx::Uncompressed(_) => None,
+            Ctx::AWSEtag(ctx) => Some(ctx.part_checksums()),
+        }
+    }
+
+    /// Does this context represent a valid and preferred multipart checksum. All multipart
+    /// checksums are preferred except for those with different sized part sizes. Returns
+    /// the preferred part size. `s3` is whether the checksum is used with an S3 destination.
+    pub fn is_preferred_multipart(&self, s3: bool) -> Option<u64> {
+        if let Self::AWSEtag(ctx) = self {
+            ctx.is_preferred_multipart(s3)
+        } else {
+            None
+        }
+    }
+
+    /// Does this context represent an AWS-compatible single part checksum, i.e. is it a regular
+    /// checksum that AWS supports directly or as an additional checksum.
+    pub fn is_preferred_single_part(&self, s3: bool) -> bool {
+        matches!(self, Self::Regular(regular) if regular.is_preferred_cloud_ctx(s3))
+    }
+
+    /// Get the standard context that the checksum is computed with, e.g. the part checksums
+    /// of an AWS context.
+    pub fn standard_ctx(self) -> StandardCtx {
+        match self {
+            Ctx::AWSEtag(ctx) => ctx.ctx(),
+            Ctx::Regular(ctx) => ctx,
+            Ctx::Uncompressed(ctx) => ctx.ctx(),
+        }
+    }
+
+    /// Get the byte offset of the first invalid dash-separated component of a checksum, or
+    /// `None` if the checksum is valid. This is the component after the longest prefix that
+    /// parses as a checksum, or the start of the string if no prefix does.
+    pub fn invalid_position(s: &str) -> Option<usize> {
+        if Self::from_str(s).is_ok() {
+            return None;
+        }
+
+        let position = s
+            .rmatch_indices('-')
+            .map(|(i, _)| i)
+            .find(|i| Self::from_str(&s[..*i]).is_ok())
+            .map(|i| i + 1)
+            .unwrap_or_default();
+
+        Some(position)
+    }
+}
+
+impl Display for Ctx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ctx::Regular(ctx) => Display::fmt(ctx, f),
+            Ctx::AWSEtag(ctx) => Display::fmt(ctx, f),
+            Ctx::Uncompressed(ctx) => Display::fmt(ctx, f),
+        }
+    }
+}
+
+impl FromStr for Ctx {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.ends_with(UNCOMPRESSED_SUFFIX) {
+            return Ok(Self::Uncompressed(UncompressedCtx::from_str(s)?));
+        }
+
+        let aws_etag = AWSETagCtx::from_str(s);
+        if aws_etag.is_err() {
+            Ok(Self::Regular(StandardCtx::from_str(s)?))
+        } else {
+            Ok(Self::AWSEtag(aws_etag?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn invalid_position() {
+        assert_eq!(Ctx::invalid_position("md5-aws-8mib-16mib"), None);
+        assert_eq!(Ctx::invalid_position("sha257"), Some(0));
+        assert_eq!(Ctx::invalid_position("md5-aws-8mib-xyz"), Some(13));
+        assert_eq!(Ctx::invalid_position("md5-aws-0"), Some(8));
+        assert_eq!(Ctx::invalid_position("md5-uncompresed"), Some(4));
+    }
+}
@@ -1,12 +1,11 @@
 //! Standard checksum algorithms
 //!
 
-use crate::cli::{Checksum, Endianness};
 use crate::error::Error::ParseError;
 use crate::error::{Error, Result};
-use crate::io::Provider;
 use crc32c::crc32c_append;
 use md5::Digest;
+use md5::digest::common::hazmat::{SerializableState, SerializedState};
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
@@ -17,6 +16,77 @@ use std::sync::Arc;
 use xxhash_rust::xxh3::Xxh3Default;
 use xxhash_rust::xxh64::Xxh64;
 
+/// The checksum to use.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Checksum {
+    /// Calculate the MD5 checksum.
+    MD5,
+    /// Calculate the SHA1 checksum.
+    SHA1,
+    /// Calculate the SHA256 checksum.
+    SHA256,
+    /// Calculate the SHA512 checksum.
+    SHA512,
+    /// Calculate a CRC32.
+    CRC32,
+    /// Calculate a CRC32C.
+    CRC32C,
+    /// Calculate a CRC64NVME.
+    CRC64NVME,
+    /// Calculate the XXHash64 checksum.
+    #[cfg_attr(feature = "clap", value(name = "xxhash64"))]
+    XXHash64,
+    /// Calculate the XXHash3 64-bit checksum.
+    #[cfg_attr(feature = "clap", value(name = "xxhash3"))]
+    XXHash3,
+    /// Calculate the XXHash128 checksum.
+    #[cfg_attr(feature = "clap", value(name = "xxhash128"))]
+    XXHash128,
+    /// Calculate the QuickXor checksum.
+    QuickXor,
+}
+
+impl FromStr for Checksum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "md5" => Ok(Self::MD5),
+            "sha1" => Ok(Self::SHA1),
+            "sha256" => Ok(Self::SHA256),
+            "sha512" => Ok(Self::SHA512),
+            "crc32" => Ok(Self::CRC32),
+            "crc32c" => Ok(Self::CRC32C),
+            "crc64nvme" => Ok(Self::CRC64NVME),
+            "xxhash64" => Ok(Self::XXHash64),
+            "xxhash3" => Ok(Self::XXHash3),
+            "xxhash128" => Ok(Self::XXHash128),
+            "quick-xor" => Ok(Self::QuickXor),
+            _ => Err(ParseError(format!("invalid variant: {}", s))),
+        }
+    }
+}
+
+/// The endianness to use for CRC-based checksums.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy, Hash)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Endianness {
+    /// Use little-endian representation.
+    LittleEndian,
+    /// Use big-endian representation.
+    BigEndian,
+}
+
+impl Display for Endianness {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Endianness::LittleEndian => f.write_str("le"),
+            Endianness::BigEndian => f.write_str("be"),
+        }
+    }
+}
+
 /// The checksum calculator. This also defines the ordering of which checksums are preferred
 /// for generating/copying data.
 #[derive(Clone)]
@@ -311,6 +381,61 @@ impl StandardCtx {
         }
     }
 
+    /// Get the intermediate state of the checksum as a hex string, if the algorithm supports
+    /// resuming from a saved state. This must be called before finalizing.
+    pub fn state(&self) -> Option<String> {
+        match self {
+            StandardCtx::MD5(Some(ctx)) => Some(hex::encode(ctx.serialize())),
+            StandardCtx::SHA1(Some(ctx)) => Some(hex::encode(ctx.serialize())),
+            StandardCtx::SHA256(Some(ctx)) => Some(hex::encode(ctx.serialize())),
+            StandardCtx::SHA512(Some(ctx)) => Some(hex::encode(ctx.serialize())),
+            StandardCtx::CRC32(Some(ctx), _) => {
+                Some(hex::encode(ctx.clone().finalize().to_be_bytes()))
+            }
+            StandardCtx::CRC32C(ctx, _) => Some(hex::encode(ctx.to_be_bytes())),
+            _ => None,
+        }
+    }
+
+    /// Create a context of the same kind which continues from a state returned by `state`.
+    pub fn resume(&self, state: &str) -> Result<Self> {
+        let state = hex::decode(state)
+            .map_err(|err| ParseError(format!("invalid checksum state: {}", err)))?;
+
+        let ctx = match self {
+            StandardCtx::MD5(_) => Self::MD5(Some(Self::deserialize_state(&state)?)),
+            StandardCtx::SHA1(_) => Self::SHA1(Some(Self::deserialize_state(&state)?)),
+            StandardCtx::SHA256(_) => Self::SHA256(Some(Self::deserialize_state(&state)?)),
+            StandardCtx::SHA512(_) => Self::SHA512(Some(Self::deserialize_state(&state)?)),
+            StandardCtx::CRC32(_, endianness) => Self::CRC32(
+                Some(crc32fast::Hasher::new_with_initial(Self::crc32_state(
+                    &state,
+                )?)),
+                *endianness,
+            ),
+            StandardCtx::CRC32C(_, endianness) => {
+                Self::CRC32C(Self::crc32_state(&state)?, *endianness)
+            }
+            ctx => {
+                return Err(ParseError(format!("resuming is not supported for {}", ctx)));
+            }
+        };
+
+        Ok(ctx)
+    }
+
+    fn deserialize_state<T: SerializableState>(state: &[u8]) -> Result<T> {
+        let err = || ParseError("invalid checksum state".to_string());
+        let state = SerializedState::<T>::try_from(state).map_err(|_| err())?;
+        T::deserialize(&state).map_err(|_| err())
+    }
+
+    fn crc32_state(state: &[u8]) -> Result<u32> {
+        Ok(u32::from_be_bytes(state.try_into().map_err(|_| {
+            ParseError("invalid checksum state".to_string())
+        })?))
+    }
+
     /// Get the digest output.
     pub fn digest_to_string(&self, digest: &[u8]) -> String {
         hex::encode(digest)
@@ -343,13 +468,10 @@ impl StandardCtx {
         }
     }
 
-    /// Is this a preferred cloud checksum for copying files.
-    pub fn is_preferred_cloud_ctx(&self, provider: &Provider) -> bool {
-        if provider.is_s3() {
-            self.is_aws_ctx()
-        } else {
-            true
-        }
+    /// Is this a preferred cloud checksum for copying files. `s3` is whether the checksum is
+    /// used with an S3 destination.
+    pub fn is_preferred_cloud_ctx(&self, s3: bool) -> bool {
+        if s3 { self.is_aws_ctx() } else { true }
     }
 
     /// Is this an AWS-compatible checksum context.
@@ -364,91 +486,11 @@ impl StandardCtx {
 }
 
 #[cfg(test)]
-pub(crate) mod test {
-    use super::StandardCtx;
-    use crate::checksum::test::test_checksum;
+mod test {
+    use super::{Checksum, StandardCtx};
     use anyhow::Result;
     use std::str::FromStr;
-
-    pub(crate) const EXPECTED_MD5_SUM: &str = "d93e71879054f205ede90d35c8081ca5"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_SHA1_SUM: &str = "3eafdb6ad3a27167e0db70fccc40d0614307dabf"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_SHA256_SUM: &str =
-        "29ffbd53cbe43179ab2fa62dbd958c0ec30b340ab50ce7c785e8a7a4b4771e39"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_SHA512_SUM: &str = "601bda6e0b7f39f8ed92aa4d9125b34c0321b6eb36622dcf0c8ed96847693e55fdd8f083b56746629369752d5ec6566a61eca2d41796245784595b3a6cf52f1e"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_CRC32_BE_SUM: &str = "3320f39e";
-    pub(crate) const EXPECTED_CRC32_LE_SUM: &str = "9ef32033";
-    pub(crate) const EXPECTED_CRC32C_BE_SUM: &str = "4920106a";
-    pub(crate) const EXPECTED_CRC32C_LE_SUM: &str = "6a102049";
-    pub(crate) const EXPECTED_CRC64NVME_BE_SUM: &str = "8827608f74ffad7b"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_CRC64NVME_LE_SUM: &str = "7badff748f602788"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_XXHASH64_SUM: &str = "fde75bc952b2835f"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_XXHASH3_SUM: &str = "3e714f0e42a90f5f"; // pragma: allowlist secret
-    pub(crate) const EXPECTED_XXHASH128_SUM: &str = "01c124e0c0eaf1903e714f0e42a90f5f"; // pragma: allowlist secret
-
-    #[tokio::test]
-    async fn test_md5() -> Result<()> {
-        test_checksum("md5", EXPECTED_MD5_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_sha1() -> Result<()> {
-        test_checksum("sha1", EXPECTED_SHA1_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_sha256() -> Result<()> {
-        test_checksum("sha256", EXPECTED_SHA256_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc32_be() -> Result<()> {
-        test_checksum("crc32", EXPECTED_CRC32_BE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc32_le() -> Result<()> {
-        test_checksum("crc32-le", EXPECTED_CRC32_LE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc32c_be() -> Result<()> {
-        test_checksum("crc32c", EXPECTED_CRC32C_BE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc32c_le() -> Result<()> {
-        test_checksum("crc32c-le", EXPECTED_CRC32C_LE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc64nvme_be() -> Result<()> {
-        test_checksum("crc64nvme", EXPECTED_CRC64NVME_BE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_crc64nvme_le() -> Result<()> {
-        test_checksum("crc64nvme-le", EXPECTED_CRC64NVME_LE_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_sha512() -> Result<()> {
-        test_checksum("sha512", EXPECTED_SHA512_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_xxhash64() -> Result<()> {
-        test_checksum("xxhash64", EXPECTED_XXHASH64_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_xxhash3() -> Result<()> {
-        test_checksum("xxhash3", EXPECTED_XXHASH3_SUM).await
-    }
-
-    #[tokio::test]
-    async fn test_xxhash128() -> Result<()> {
-        test_checksum("xxhash128", EXPECTED_XXHASH128_SUM).await
-    }
+    use std::sync::Arc;
 
     #[test]
     fn test_xxhash64_known() -> Result<()> {
@@ -477,6 +519,40 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_resume_from_state() -> Result<()> {
+        let (first, second): (Arc<[u8]>, Arc<[u8]>) =
+            (Arc::from(&b"abc"[..]), Arc::from(&b"def"[..]));
+
+        for name in ["md5", "sha1", "sha256", "sha512", "crc32", "crc32c-le"] {
+            let mut expected = StandardCtx::from_str(name)?;
+            expected.update(first.clone())?;
+            expected.update(second.clone())?;
+
+            let mut ctx = StandardCtx::from_str(name)?;
+            ctx.update(first.clone())?;
+            let state = ctx.state().unwrap();
+
+            let mut resumed = StandardCtx::from_str(name)?.resume(&state)?;
+            resumed.update(second.clone())?;
+
+            assert_eq!(resumed.finalize()?, expected.finalize()?);
+        }
+
+        assert!(StandardCtx::crc64nvme().state().is_none());
+        assert!(StandardCtx::md5().resume("00").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_checksum() -> Result<()> {
+        assert_eq!(Checksum::from_str("SHA256")?, Checksum::SHA256);
+        assert_eq!(Checksum::from_str("quick-xor")?, Checksum::QuickXor);
+        assert!(Checksum::from_str("sha3").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_new_checksums_name_round_trip() -> Result<()> {
         for name in ["sha512", "xxhash64", "xxhash3", "xxhash128"] {
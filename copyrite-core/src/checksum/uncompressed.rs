@@ -0,0 +1,199 @@
+//! Checksums of the uncompressed contents of gzip or BGZF compressed data.
+//!
+
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::{GenerateError, ParseError};
+use crate::error::{Error, Result};
+use flate2::write::MultiGzDecoder;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The suffix of checksums that are computed on the uncompressed contents.
+pub const UNCOMPRESSED_SUFFIX: &str = "-uncompressed";
+
+/// A writer which updates a checksum with decompressed data.
+struct CtxWriter(StandardCtx);
+
+impl Write for CtxWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .update(Arc::from(buf))
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Calculates a checksum of gzip compressed data after decompressing it. BGZF files are
+/// supported because they are a series of gzip members.
+pub struct UncompressedCtx {
+    ctx: StandardCtx,
+    decoder: Option<Box<MultiGzDecoder<CtxWriter>>>,
+}
+
+impl UncompressedCtx {
+    /// Create a new context which computes `ctx` on the uncompressed data.
+    pub fn new(ctx: StandardCtx) -> Self {
+        let decoder = Some(Box::new(MultiGzDecoder::new(CtxWriter(ctx.reset()))));
+        Self { ctx, decoder }
+    }
+
+    /// Get the checksum that is computed on the uncompressed data.
+    pub fn ctx(&self) -> StandardCtx {
+        self.ctx.reset()
+    }
+
+    /// Decompress data and update the checksum with it.
+    pub fn update(&mut self, data: Arc<[u8]>) -> Result<()> {
+        self.decoder
+            .as_mut()
+            .expect("cannot call update with empty context")
+            .write_all(&data)
+            .map_err(|err| {
+                GenerateError(format!("failed to decompress data for {}: {}", self, err))
+            })
+    }
+
+    /// Finish decompressing and finalize the checksum.
+    pub fn finalize(&mut self) -> Result<Vec<u8>> {
+        let decoder = self
+            .decoder
+            .take()
+            .expect("cannot call finalize with empty context");
+        let CtxWriter(mut ctx) = decoder.finish().map_err(|err| {
+            GenerateError(format!("failed to decompress data for {}: {}", self, err))
+        })?;
+
+        ctx.finalize()
+    }
+
+    /// Get the digest output.
+    pub fn digest_to_string(&self, digest: &[u8]) -> String {
+        self.ctx.digest_to_string(digest)
+    }
+}
+
+/// Cloning starts decompressing from the beginning, as the state of the decoder cannot be
+/// copied.
+impl Clone for UncompressedCtx {
+    fn clone(&self) -> Self {
+        Self::new(self.ctx())
+    }
+}
+
+impl Debug for UncompressedCtx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for UncompressedCtx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.ctx, UNCOMPRESSED_SUFFIX)
+    }
+}
+
+impl FromStr for UncompressedCtx {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some(s) = s.strip_suffix(UNCOMPRESSED_SUFFIX) else {
+            return Err(ParseError(format!(
+                "{} is missing the {} suffix",
+                s, UNCOMPRESSED_SUFFIX
+            )));
+        };
+
+        Ok(Self::new(StandardCtx::from_str(s)?))
+    }
+}
+
+impl Eq for UncompressedCtx {}
+
+impl PartialEq for UncompressedCtx {
+    fn eq(&self, other: &Self) -> bool {
+        self.ctx == other.ctx
+    }
+}
+
+impl Hash for UncompressedCtx {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ctx.hash(state);
+    }
+}
+
+impl Ord for UncompressedCtx {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ctx.cmp(&other.ctx)
+    }
+}
+
+impl PartialOrd for UncompressedCtx {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use md5::{Digest, Md5};
+
+    fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn uncompressed_md5(chunks: &[&[u8]]) -> Result<String> {
+        let mut ctx = UncompressedCtx::from_str("md5-uncompressed")?;
+        for chunk in chunks {
+            ctx.update(Arc::from(*chunk))?;
+        }
+        let digest = ctx.finalize()?;
+        Ok(ctx.digest_to_string(&digest))
+    }
+
+    #[test]
+    fn parse_uncompressed() -> Result<()> {
+        let ctx = UncompressedCtx::from_str("sha256-uncompressed")?;
+        assert_eq!(ctx.to_string(), "sha256-uncompressed");
+        assert_eq!(ctx.ctx(), StandardCtx::sha256());
+        assert!(UncompressedCtx::from_str("sha256").is_err());
+        assert!(UncompressedCtx::from_str("md5-aws-8mib-uncompressed").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_uncompressed() -> Result<()> {
+        let data = (0..100000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let expected = hex::encode(Md5::digest(&data));
+
+        // A single gzip member split across chunks.
+        let compressed = gzip(&data)?;
+        let chunks = compressed.chunks(1000).collect::<Vec<_>>();
+        assert_eq!(uncompressed_md5(&chunks)?, expected);
+
+        // Multiple concatenated members, like BGZF blocks.
+        let (first, second) = data.split_at(40000);
+        let members = [gzip(first)?, gzip(second)?].concat();
+        assert_eq!(uncompressed_md5(&[&members])?, expected);
+
+        assert!(uncompressed_md5(&[&data]).is_err());
+        assert!(uncompressed_md5(&[&compressed[..compressed.len() / 2]]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+//! Errors related to checksum logic.
+//!
+
+use std::num::TryFromIntError;
+use std::result;
+use thiserror::Error;
+
+/// The result type.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Error types for checksum logic.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("parsing: {0}")]
+    ParseError(String),
+    #[error("overflow converting numbers: {0}")]
+    OverflowError(#[from] TryFromIntError),
+    #[error("serde: {0}")]
+    SerdeError(String),
+    #[error("output file: {0}")]
+    SumsFileError(String),
+    #[error("generate command error: {0}")]
+    GenerateError(String),
+    #[error("check command error: {0}")]
+    CheckError(String),
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::SerdeError(err.to_string())
+    }
+}
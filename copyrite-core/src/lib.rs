@@ -0,0 +1,7 @@
+//! The checksum and sums file logic of copyrite. This crate has no networking or filesystem
+//! access so that it can be compiled for `wasm32-unknown-unknown`, e.g. to validate sums files
+//! or compute digests in a browser using the same code as the CLI.
+//!
+
+pub mod checksum;
+pub mod error;
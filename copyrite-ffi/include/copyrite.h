@@ -0,0 +1,46 @@
+/*
+ * C interface for copyrite.
+ *
+ * Each command takes a JSON request and blocks until it completes. The response is written to
+ * `response` as a JSON string, which must be freed with `copyrite_free`. On success, the
+ * response is the stats of the command. On failure, it is an object with an `error` message,
+ * and the `stats` of the command if there are any.
+ *
+ * Options are the long options of the command line, with underscores or dashes. `true` is a
+ * flag, `false` and `null` are omitted, and arrays repeat the option.
+ */
+
+#ifndef COPYRITE_H
+#define COPYRITE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The command succeeded and the response contains its stats. */
+#define COPYRITE_OK 0
+/* The command failed and the response contains the error, and stats if there are any. */
+#define COPYRITE_COMMAND_FAILED 1
+/* The request could not be parsed and the response contains the error. */
+#define COPYRITE_INVALID_REQUEST 2
+
+/* Generate checksums for the inputs. The request is {"inputs": [...], "options": {...}}. */
+int copyrite_generate(const char *request, char **response);
+
+/* Check whether the inputs are identical. The request is {"inputs": [...], "options": {...}}. */
+int copyrite_check(const char *request, char **response);
+
+/*
+ * Copy the source to the destination. The request is
+ * {"source": "...", "destination": "...", "options": {...}}.
+ */
+int copyrite_copy(const char *request, char **response);
+
+/* Free a response returned by copyrite. Passing NULL does nothing. */
+void copyrite_free(char *response);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* COPYRITE_H */
@@ -0,0 +1,300 @@
+//! A C ABI for copyrite. The `generate`, `check` and `copy` commands take a JSON request and
+//! produce a JSON response, so that tools written in other languages can link against copyrite
+//! rather than spawning it as a subprocess. See `include/copyrite.h` for the interface.
+//!
+//! Options in a request are the long options of the command line, with underscores or dashes,
+//! e.g. `{"source": "s3://bucket/key", "destination": "dir/", "options": {"no_check": true}}`.
+//!
+
+use copyrite::cli::{Command, OptionValue, options_to_args};
+use copyrite::error::Error;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+/// The command succeeded and the response contains its stats.
+pub const COPYRITE_OK: c_int = 0;
+/// The command failed and the response contains the error, and stats if there are any.
+pub const COPYRITE_COMMAND_FAILED: c_int = 1;
+/// The request could not be parsed and the response contains the error.
+pub const COPYRITE_INVALID_REQUEST: c_int = 2;
+
+/// A request to generate or check the inputs.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputsRequest {
+    inputs: Vec<String>,
+    #[serde(default)]
+    options: Map<String, Value>,
+}
+
+/// A request to copy the source to the destination.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CopyRequest {
+    source: String,
+    destination: String,
+    #[serde(default)]
+    options: Map<String, Value>,
+}
+
+/// The result of a call, converted to a status code and response.
+type Response = std::result::Result<Value, (c_int, Value)>;
+
+/// The runtime that commands run on, shared between calls.
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("failed to create the tokio runtime"))
+}
+
+/// An invalid request response.
+fn invalid(err: impl ToString) -> (c_int, Value) {
+    (
+        COPYRITE_INVALID_REQUEST,
+        json!({ "error": err.to_string() }),
+    )
+}
+
+/// Convert options into command line arguments. `true` is a flag, `false` and `null` are
+/// omitted, arrays repeat the option, and strings and numbers are used as the value.
+fn options(options: Map<String, Value>) -> std::result::Result<Vec<String>, (c_int, Value)> {
+    let to_string = |key: &str, value: Value| match value {
+        Value::String(value) => Ok(value),
+        Value::Number(value) => Ok(value.to_string()),
+        _ => Err(invalid(format!("invalid value for option `{key}`"))),
+    };
+
+    let mut values = vec![];
+    for (key, value) in options {
+        let value = match value {
+            Value::Null => continue,
+            Value::Bool(flag) => OptionValue::Flag(flag),
+            Value::Array(array) => OptionValue::Values(
+                array
+                    .into_iter()
+                    .map(|value| to_string(&key, value))
+                    .collect::<std::result::Result<_, _>>()?,
+            ),
+            value => OptionValue::Value(to_string(&key, value)?),
+        };
+        values.push((key, value));
+    }
+
+    Ok(options_to_args(values))
+}
+
+/// Parse and run a command, blocking until it completes.
+fn run(subcommand: &str, options: Vec<String>, positional: Vec<String>) -> Response {
+    let args = ["copyrite".to_string(), subcommand.to_string()]
+        .into_iter()
+        .chain(options)
+        .chain(["--".to_string()])
+        .chain(positional);
+    let command = Command::try_parse_from_iter(args).map_err(invalid)?;
+
+    runtime().block_on(command.run_stats()).map_err(|err| {
+        let Error::Stats(stats) = err else {
+            return (COPYRITE_COMMAND_FAILED, json!({ "error": err.to_string() }));
+        };
+
+        let stats = serde_json::from_str::<Value>(&stats).unwrap_or(Value::String(stats));
+        let error = stats
+            .get("unrecoverable_error")
+            .map(|err| format!("command failed: {err}"))
+            .unwrap_or_else(|| "command failed".to_string());
+        (
+            COPYRITE_COMMAND_FAILED,
+            json!({ "error": error, "stats": stats }),
+        )
+    })
+}
+
+/// Read the request, call the command and write the response, returning the status code.
+///
+/// # Safety
+/// `request` must be null or a valid nul-terminated string, and `response` must be null or
+/// valid for writes.
+unsafe fn call<F>(request: *const c_char, response: *mut *mut c_char, f: F) -> c_int
+where
+    F: FnOnce(&str) -> Response,
+{
+    let result = if request.is_null() {
+        Err(invalid("request must not be null"))
+    } else {
+        // Safety: the caller guarantees that the request is a valid nul-terminated string.
+        let request = unsafe { CStr::from_ptr(request) };
+        match request.to_str() {
+            Ok(request) => catch_unwind(AssertUnwindSafe(|| f(request))).unwrap_or_else(|_| {
+                Err((
+                    COPYRITE_COMMAND_FAILED,
+                    json!({ "error": "copyrite panicked" }),
+                ))
+            }),
+            Err(err) => Err(invalid(err)),
+        }
+    };
+
+    let (status, value) = match result {
+        Ok(stats) => (COPYRITE_OK, stats),
+        Err(err) => err,
+    };
+
+    if !response.is_null() {
+        // Serialized JSON escapes control characters, so it never contains a nul byte.
+        let value = CString::new(value.to_string()).unwrap_or_default();
+        // Safety: the caller guarantees that the response is valid for writes.
+        unsafe { *response = value.into_raw() };
+    }
+
+    status
+}
+
+/// Generate checksums for the inputs. The request is `{"inputs": [...], "options": {...}}`.
+///
+/// # Safety
+/// `request` must be null or a valid nul-terminated string, and `response` must be null or valid
+/// for writes. The response must be freed with `copyrite_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn copyrite_generate(
+    request: *const c_char,
+    response: *mut *mut c_char,
+) -> c_int {
+    // Safety: the pointers are passed through from the caller.
+    unsafe {
+        call(request, response, |request| {
+            let request: InputsRequest = serde_json::from_str(request).map_err(invalid)?;
+            run("generate", options(request.options)?, request.inputs)
+        })
+    }
+}
+
+/// Check whether the inputs are identical. The request is `{"inputs": [...], "options": {...}}`.
+///
+/// # Safety
+/// `request` must be null or a valid nul-terminated string, and `response` must be null or valid
+/// for writes. The response must be freed with `copyrite_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn copyrite_check(
+    request: *const c_char,
+    response: *mut *mut c_char,
+) -> c_int {
+    // Safety: the pointers are passed through from the caller.
+    unsafe {
+        call(request, response, |request| {
+            let request: InputsRequest = serde_json::from_str(request).map_err(invalid)?;
+            run("check", options(request.options)?, request.inputs)
+        })
+    }
+}
+
+/// Copy the source to the destination. The request is
+/// `{"source": "...", "destination": "...", "options": {...}}`.
+///
+/// # Safety
+/// `request` must be null or a valid nul-terminated string, and `response` must be null or valid
+/// for writes. The response must be freed with `copyrite_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn copyrite_copy(
+    request: *const c_char,
+    response: *mut *mut c_char,
+) -> c_int {
+    // Safety: the pointers are passed through from the caller.
+    unsafe {
+        call(request, response, |request| {
+            let request: CopyRequest = serde_json::from_str(request).map_err(invalid)?;
+            run(
+                "copy",
+                options(request.options)?,
+                vec![request.source, request.destination],
+            )
+        })
+    }
+}
+
+/// Free a response returned by copyrite. Passing null does nothing.
+///
+/// # Safety
+/// `response` must be null or a response returned by copyrite that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn copyrite_free(response: *mut c_char) {
+    if !response.is_null() {
+        // Safety: the response was created by `CString::into_raw`.
+        drop(unsafe { CString::from_raw(response) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use std::ptr;
+    use tempfile::tempdir;
+
+    /// Call a command and return the status code and parsed response.
+    fn call_json(
+        f: unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> c_int,
+        request: &str,
+    ) -> Result<(c_int, Value)> {
+        let request = CString::new(request)?;
+        let mut response = ptr::null_mut();
+
+        let status = unsafe { f(request.as_ptr(), &mut response) };
+        let value = serde_json::from_str(unsafe { CStr::from_ptr(response) }.to_str()?)?;
+        unsafe { copyrite_free(response) };
+
+        Ok((status, value))
+    }
+
+    #[test]
+    fn generate_and_check() -> Result<()> {
+        let tmp = tempdir()?;
+        let input = tmp.path().join("input");
+        fs::write(&input, "copyrite")?;
+        let input = input.to_string_lossy().to_string();
+
+        let (status, response) = call_json(
+            copyrite_generate,
+            &json!({
+                "inputs": [input],
+                "options": { "checksum": ["md5", "sha1"], "force_overwrite": true }
+            })
+            .to_string(),
+        )?;
+        assert_eq!(status, COPYRITE_OK);
+        assert_eq!(
+            response["stats"][0]["checksums_generated"][0]["value"],
+            "577c6d3604ac9eb86cd465b90b98f0d4"
+        );
+
+        let (status, response) = call_json(
+            copyrite_check,
+            &json!({ "inputs": [input, "missing"] }).to_string(),
+        )?;
+        assert_eq!(status, COPYRITE_COMMAND_FAILED);
+        assert!(response["error"].is_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_request() -> Result<()> {
+        let (status, response) = call_json(copyrite_copy, "{}")?;
+        assert_eq!(status, COPYRITE_INVALID_REQUEST);
+        assert!(response["error"].as_str().unwrap().contains("source"));
+
+        let (status, _) = call_json(
+            copyrite_copy,
+            &json!({ "source": "a", "destination": "b", "options": { "bogus": true } }).to_string(),
+        )?;
+        assert_eq!(status, COPYRITE_INVALID_REQUEST);
+
+        let status = unsafe { copyrite_generate(ptr::null(), ptr::null_mut()) };
+        assert_eq!(status, COPYRITE_INVALID_REQUEST);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Python bindings for copyrite. The `generate`, `check` and `copy` commands are exposed as
+//! async functions which return their stats as a `dict`.
+//!
+//! Keyword arguments are the long options of the command line, with underscores in place of
+//! dashes, e.g. `await copyrite.copy(source, destination, no_check=True, part_size="8mib")`.
+//!
+
+use copyrite::cli::{Command, OptionValue, options_to_args};
+use copyrite::error::Error;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyTuple};
+use pyo3_async_runtimes::tokio::future_into_py;
+use serde_json::Value;
+use tokio::runtime::Handle;
+use tokio::task::spawn_blocking;
+
+create_exception!(
+    copyrite,
+    CopyriteError,
+    PyException,
+    "A copyrite command failed. If the command produced stats, they are the second argument."
+);
+
+/// Convert keyword arguments into command line options. `True` is a flag, `False` and `None`
+/// are omitted, lists and tuples repeat the option, and other values are converted with `str`.
+fn options(options: Option<&Bound<'_, PyDict>>) -> PyResult<Vec<String>> {
+    let Some(options) = options else {
+        return Ok(vec![]);
+    };
+
+    let mut values = vec![];
+    for (key, value) in options.iter() {
+        if value.is_none() {
+            continue;
+        }
+
+        let value = if let Ok(value) = value.downcast::<PyBool>() {
+            OptionValue::Flag(value.is_true())
+        } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+            OptionValue::Values(
+                value
+                    .try_iter()?
+                    .map(|value| Ok(value?.str()?.to_string()))
+                    .collect::<PyResult<_>>()?,
+            )
+        } else {
+            OptionValue::Value(value.str()?.to_string())
+        };
+        values.push((key.extract::<String>()?, value));
+    }
+
+    Ok(options_to_args(values))
+}
+
+/// Convert JSON stats into Python objects.
+fn to_py(py: Python<'_>, stats: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (stats.to_string(),))?
+        .unbind())
+}
+
+/// Convert an error into a `CopyriteError`, including the stats of a failed command.
+fn to_py_err(py: Python<'_>, err: Error) -> PyErr {
+    let Error::Stats(stats) = err else {
+        return CopyriteError::new_err(err.to_string());
+    };
+
+    let Ok(value) = serde_json::from_str::<Value>(&stats) else {
+        return CopyriteError::new_err(("command failed".to_string(), stats));
+    };
+    let message = value
+        .get("unrecoverable_error")
+        .map(|err| format!("command failed: {}", err))
+        .unwrap_or_else(|| "command failed".to_string());
+
+    match to_py(py, &value) {
+        Ok(stats) => CopyriteError::new_err((message, stats)),
+        Err(err) => err,
+    }
+}
+
+/// Parse and run a command in the tokio runtime, returning an awaitable of its stats.
+fn run<'py>(
+    py: Python<'py>,
+    subcommand: &str,
+    options: Vec<String>,
+    positional: Vec<String>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let args = ["copyrite".to_string(), subcommand.to_string()]
+        .into_iter()
+        .chain(options)
+        .chain(["--".to_string()])
+        .chain(positional);
+    let command = Command::try_parse_from_iter(args)
+        .map_err(|err| CopyriteError::new_err(err.to_string()))?;
+
+    future_into_py(py, async move {
+        // The command is not `Send`, so it runs to completion on a blocking thread.
+        let stats = spawn_blocking(move || Handle::current().block_on(command.run_stats()))
+            .await
+            .map_err(|err| CopyriteError::new_err(err.to_string()))?;
+        Python::with_gil(|py| match stats {
+            Ok(stats) => to_py(py, &stats),
+            Err(err) => Err(to_py_err(py, err)),
+        })
+    })
+}
+
+/// Generate checksums for the inputs, e.g.
+/// `await copyrite.generate("s3://bucket/key", checksum="md5,sha256")`. See
+/// `copyrite generate --help` for the options.
+#[pyfunction]
+#[pyo3(signature = (*inputs, **options))]
+fn generate<'py>(
+    py: Python<'py>,
+    inputs: &Bound<'py, PyTuple>,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    run(py, "generate", self::options(options)?, inputs.extract()?)
+}
+
+/// Check whether the inputs are identical using their sums, e.g.
+/// `await copyrite.check("s3://bucket/key", "local_file")`. See `copyrite check --help` for
+/// the options.
+#[pyfunction]
+#[pyo3(signature = (*inputs, **options))]
+fn check<'py>(
+    py: Python<'py>,
+    inputs: &Bound<'py, PyTuple>,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    run(py, "check", self::options(options)?, inputs.extract()?)
+}
+
+/// Copy the source to the destination, e.g.
+/// `await copyrite.copy("s3://bucket/key", "s3://other-bucket/key")`. A source prefix or
+/// directory copies every object under it and returns the stats of the whole prefix. See
+/// `copyrite copy --help` for the options.
+#[pyfunction]
+#[pyo3(signature = (source, destination, **options))]
+fn copy<'py>(
+    py: Python<'py>,
+    source: String,
+    destination: String,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    run(
+        py,
+        "copy",
+        self::options(options)?,
+        vec![source, destination],
+    )
+}
+
+/// The copyrite Python module.
+#[pymodule]
+#[pyo3(name = "copyrite")]
+fn copyrite_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(generate, module)?)?;
+    module.add_function(wrap_pyfunction!(check, module)?)?;
+    module.add_function(wrap_pyfunction!(copy, module)?)?;
+    module.add("CopyriteError", module.py().get_type::<CopyriteError>())?;
+    Ok(())
+}
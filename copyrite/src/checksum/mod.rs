@@ -1,188 +1,138 @@
 //! Checksum calculation and logic.
 //!
 
-pub mod aws_etag;
+pub use copyrite_core::checksum::{Ctx, aws_etag, combine, standard, uncompressed};
+
 pub mod file;
-pub mod standard;
+pub mod parallel;
 
-use crate::checksum::aws_etag::AWSETagCtx;
 use crate::checksum::standard::StandardCtx;
-use crate::error::{Error, Result};
-use crate::io::Provider;
 use aws_sdk_s3::types::ChecksumAlgorithm;
-use futures_util::{Stream, StreamExt, pin_mut};
-use serde::de::Error as SerdeError;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::fmt::{Debug, Display, Formatter};
-use std::hash::Hash;
-use std::result;
-use std::str::FromStr;
-use std::sync::Arc;
-
-/// The checksum context. This enum also determines the best order of checksums,
-/// which is useful for copy operations. AWS etag checksums are preferred over
-/// regular checksums.
-#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
-pub enum Ctx {
-    AWSEtag(AWSETagCtx),
-    Regular(StandardCtx),
-}
 
-impl Default for Ctx {
-    fn default() -> Self {
-        Self::Regular(Default::default())
+/// Get the S3 additional checksum algorithm of a context.
+pub fn checksum_algorithm(ctx: Ctx) -> ChecksumAlgorithm {
+    match ctx.standard_ctx() {
+        StandardCtx::CRC64NVME(_, _) => ChecksumAlgorithm::Crc64Nvme,
+        StandardCtx::CRC32C(_, _) => ChecksumAlgorithm::Crc32C,
+        StandardCtx::CRC32(_, _) => ChecksumAlgorithm::Crc32,
+        StandardCtx::MD5(_) => ChecksumAlgorithm::Md5,
+        StandardCtx::SHA1(_) => ChecksumAlgorithm::Sha1,
+        StandardCtx::SHA256(_) => ChecksumAlgorithm::Sha256,
+        StandardCtx::SHA512(_) => ChecksumAlgorithm::Sha512,
+        StandardCtx::XXHash64(_) => ChecksumAlgorithm::Xxhash64,
+        StandardCtx::XXHash3(_) => ChecksumAlgorithm::Xxhash3,
+        StandardCtx::XXHash128(_) => ChecksumAlgorithm::Xxhash128,
+        // By default, set some algorithm if the context doesn't line up.
+        _ => ChecksumAlgorithm::Crc64Nvme,
     }
 }
 
-impl<'de> Deserialize<'de> for Ctx {
-    /// Implement deserialize using `FromStr`.
-    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        String::deserialize(deserializer)?
-            .parse()
-            .map_err(D::Error::custom)
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::io::sums::SharedReader;
+    use crate::io::sums::channel::test::channel_reader;
+    use crate::test::{TEST_FILE_SIZE, TestFileBuilder};
+    use anyhow::Result;
+    use std::str::FromStr;
+    use tokio::fs::File;
+    use tokio::join;
+
+    pub(crate) const EXPECTED_MD5_SUM: &str = "d93e71879054f205ede90d35c8081ca5"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_SHA1_SUM: &str = "3eafdb6ad3a27167e0db70fccc40d0614307dabf"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_SHA256_SUM: &str =
+        "29ffbd53cbe43179ab2fa62dbd958c0ec30b340ab50ce7c785e8a7a4b4771e39"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_SHA512_SUM: &str = "601bda6e0b7f39f8ed92aa4d9125b34c0321b6eb36622dcf0c8ed96847693e55fdd8f083b56746629369752d5ec6566a61eca2d41796245784595b3a6cf52f1e"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_CRC32_BE_SUM: &str = "3320f39e";
+    pub(crate) const EXPECTED_CRC32_LE_SUM: &str = "9ef32033";
+    pub(crate) const EXPECTED_CRC32C_BE_SUM: &str = "4920106a";
+    pub(crate) const EXPECTED_CRC32C_LE_SUM: &str = "6a102049";
+    pub(crate) const EXPECTED_CRC64NVME_BE_SUM: &str = "8827608f74ffad7b"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_CRC64NVME_LE_SUM: &str = "7badff748f602788"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_XXHASH64_SUM: &str = "fde75bc952b2835f"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_XXHASH3_SUM: &str = "3e714f0e42a90f5f"; // pragma: allowlist secret
+    pub(crate) const EXPECTED_XXHASH128_SUM: &str = "01c124e0c0eaf1903e714f0e42a90f5f"; // pragma: allowlist secret
+
+    #[tokio::test]
+    async fn test_md5() -> Result<()> {
+        test_checksum("md5", EXPECTED_MD5_SUM).await
     }
-}
 
-impl Serialize for Ctx {
-    /// Implement serialize using `ToString`.
-    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        String::serialize(&self.to_string(), serializer)
+    #[tokio::test]
+    async fn test_sha1() -> Result<()> {
+        test_checksum("sha1", EXPECTED_SHA1_SUM).await
     }
-}
 
-impl Ctx {
-    /// Update a checksum with some data.
-    pub fn update(&mut self, data: Arc<[u8]>) -> Result<()> {
-        match self {
-            Ctx::Regular(ctx) => ctx.update(data),
-            Ctx::AWSEtag(ctx) => ctx.update(data),
-        }
+    #[tokio::test]
+    async fn test_sha256() -> Result<()> {
+        test_checksum("sha256", EXPECTED_SHA256_SUM).await
     }
 
-    /// Finalize the checksum.
-    pub fn finalize(&mut self) -> Result<Vec<u8>> {
-        match self {
-            Ctx::Regular(ctx) => ctx.finalize(),
-            Ctx::AWSEtag(ctx) => ctx.finalize(),
-        }
+    #[tokio::test]
+    async fn test_crc32_be() -> Result<()> {
+        test_checksum("crc32", EXPECTED_CRC32_BE_SUM).await
     }
 
-    /// Generate a checksum from a stream of bytes.
-    pub async fn generate(
-        &mut self,
-        stream: impl Stream<Item = Result<Arc<[u8]>>>,
-    ) -> Result<Vec<u8>> {
-        pin_mut!(stream);
+    #[tokio::test]
+    async fn test_crc32_le() -> Result<()> {
+        test_checksum("crc32-le", EXPECTED_CRC32_LE_SUM).await
+    }
 
-        while let Some(chunk) = stream.next().await {
-            self.update(chunk?)?;
-        }
+    #[tokio::test]
+    async fn test_crc32c_be() -> Result<()> {
+        test_checksum("crc32c", EXPECTED_CRC32C_BE_SUM).await
+    }
 
-        self.finalize()
+    #[tokio::test]
+    async fn test_crc32c_le() -> Result<()> {
+        test_checksum("crc32c-le", EXPECTED_CRC32C_LE_SUM).await
     }
 
-    /// Get the digest output.
-    pub fn digest_to_string(&self, digest: &[u8]) -> String {
-        match self {
-            Ctx::Regular(ctx) => ctx.digest_to_string(digest),
-            Ctx::AWSEtag(ctx) => ctx.digest_to_string(digest),
-        }
+    #[tokio::test]
+    async fn test_crc64nvme_be() -> Result<()> {
+        test_checksum("crc64nvme", EXPECTED_CRC64NVME_BE_SUM).await
     }
 
-    /// Set the file size if this is an AWS context.
-    pub fn set_file_size(&mut self, file_size: Option<u64>) {
-        if let Ctx::AWSEtag(ctx) = self {
-            ctx.set_file_size(file_size);
-        }
+    #[tokio::test]
+    async fn test_crc64nvme_le() -> Result<()> {
+        test_checksum("crc64nvme-le", EXPECTED_CRC64NVME_LE_SUM).await
     }
 
-    /// Get the encoded part checksums and their part sizes if this is an AWS checksum context.
-    pub fn part_checksums(&self) -> Option<Vec<(u64, String)>> {
-        match self {
-            Ctx::Regular(_) => None,
-            Ctx::AWSEtag(ctx) => Some(ctx.part_checksums()),
-        }
+    #[tokio::test]
+    async fn test_sha512() -> Result<()> {
+        test_checksum("sha512", EXPECTED_SHA512_SUM).await
     }
 
-    /// Does this context represent a valid and preferred multipart checksum. All multipart
-    /// checksums are preferred except for those with different sized part sizes. Returns
-    /// the preferred part size.
-    pub fn is_preferred_multipart(&self, provider: &Provider) -> Option<u64> {
-        if let Self::AWSEtag(ctx) = self {
-            ctx.is_preferred_multipart(provider)
-        } else {
-            None
-        }
+    #[tokio::test]
+    async fn test_xxhash64() -> Result<()> {
+        test_checksum("xxhash64", EXPECTED_XXHASH64_SUM).await
     }
 
-    /// Does this context represent an AWS-compatible single part checksum, i.e. is it a regular
-    /// checksum that AWS supports directly or as an additional checksum.
-    pub fn is_preferred_single_part(&self, provider: &Provider) -> bool {
-        matches!(self, Self::Regular(regular) if regular.is_preferred_cloud_ctx(provider))
+    #[tokio::test]
+    async fn test_xxhash3() -> Result<()> {
+        test_checksum("xxhash3", EXPECTED_XXHASH3_SUM).await
     }
-}
 
-impl From<Ctx> for ChecksumAlgorithm {
-    fn from(ctx: Ctx) -> Self {
-        let ctx = match ctx {
-            Ctx::AWSEtag(ctx) => ctx.ctx(),
-            Ctx::Regular(ctx) => ctx,
-        };
-
-        match ctx {
-            StandardCtx::CRC64NVME(_, _) => ChecksumAlgorithm::Crc64Nvme,
-            StandardCtx::CRC32C(_, _) => ChecksumAlgorithm::Crc32C,
-            StandardCtx::CRC32(_, _) => ChecksumAlgorithm::Crc32,
-            StandardCtx::MD5(_) => ChecksumAlgorithm::Md5,
-            StandardCtx::SHA1(_) => ChecksumAlgorithm::Sha1,
-            StandardCtx::SHA256(_) => ChecksumAlgorithm::Sha256,
-            StandardCtx::SHA512(_) => ChecksumAlgorithm::Sha512,
-            StandardCtx::XXHash64(_) => ChecksumAlgorithm::Xxhash64,
-            StandardCtx::XXHash3(_) => ChecksumAlgorithm::Xxhash3,
-            StandardCtx::XXHash128(_) => ChecksumAlgorithm::Xxhash128,
-            // By default, set some algorithm if the context doesn't line up.
-            _ => ChecksumAlgorithm::Crc64Nvme,
-        }
+    #[tokio::test]
+    async fn test_xxhash128() -> Result<()> {
+        test_checksum("xxhash128", EXPECTED_XXHASH128_SUM).await
     }
-}
 
-impl Display for Ctx {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Ctx::Regular(ctx) => Display::fmt(ctx, f),
-            Ctx::AWSEtag(ctx) => Display::fmt(ctx, f),
-        }
+    pub(crate) fn expected_md5_1gib() -> &'static str {
+        "6c434b38867bbd608ba2f06e92ed4e43-1073741824b"
     }
-}
 
-impl FromStr for Ctx {
-    type Err = Error;
+    pub(crate) fn expected_md5_100mib() -> &'static str {
+        "e5727bb1cb678220f6782ff6cb927569-104857600b"
+    }
 
-    fn from_str(s: &str) -> Result<Self> {
-        let aws_etag = AWSETagCtx::from_str(s);
-        if aws_etag.is_err() {
-            Ok(Self::Regular(StandardCtx::from_str(s)?))
-        } else {
-            Ok(Self::AWSEtag(aws_etag?))
-        }
+    pub(crate) fn expected_md5_10() -> &'static str {
+        "9a9666a5c313c53fbc3a3ea1d43cc981-107374183b"
     }
-}
 
-#[cfg(test)]
-pub(crate) mod test {
-    use super::*;
-    use crate::io::sums::SharedReader;
-    use crate::io::sums::channel::test::channel_reader;
-    use crate::test::{TEST_FILE_SIZE, TestFileBuilder};
-    use anyhow::Result;
-    use tokio::fs::File;
-    use tokio::join;
+    pub(crate) fn expected_sha256_100mib() -> &'static str {
+        "a9ed6c4b6aadf887f90a3d483b5c5b79bc08075af2a1718e3e15c63b9904ebf7-104857600b"
+    }
 
     pub(crate) async fn test_checksum(checksum: &str, expected: &str) -> Result<()> {
         let test_file = TestFileBuilder::new()?.generate_test_defaults()?;
@@ -200,4 +150,31 @@ pub(crate) mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_aws_etag_single_part() -> Result<()> {
+        test_checksum("md5-aws-1gib", expected_md5_1gib()).await?;
+        test_checksum("aws-etag-1gib", expected_md5_1gib()).await?;
+
+        // Larger part sizes should also work.
+        test_checksum("md5-aws-2gib", expected_md5_1gib()).await?;
+        test_checksum("aws-etag-2gib", expected_md5_1gib()).await
+    }
+
+    #[tokio::test]
+    async fn test_aws_etag_md5() -> Result<()> {
+        test_checksum("md5-aws-100mib", expected_md5_100mib()).await?;
+        test_checksum("aws-etag-100mib", expected_md5_100mib()).await
+    }
+
+    #[tokio::test]
+    async fn test_aws_etag_sha256() -> Result<()> {
+        test_checksum("sha256-aws-100mib", expected_sha256_100mib()).await
+    }
+
+    #[tokio::test]
+    async fn test_aws_etag_part_number() -> Result<()> {
+        test_checksum("md5-aws-10", expected_md5_10()).await?;
+        test_checksum("aws-etag-10", expected_md5_10()).await
+    }
 }
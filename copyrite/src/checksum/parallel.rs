@@ -0,0 +1,196 @@
+//! Compute CRC checksums of large local files by hashing ranges of the file on separate
+//! threads and combining the results.
+//!
+
+use crate::checksum::combine::{
+    CRC32_POLYNOMIAL, CRC32C_POLYNOMIAL, CRC64NVME_POLYNOMIAL, crc_combine,
+};
+use crate::checksum::standard::Endianness;
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::GenerateError;
+use crate::error::Result;
+use futures_util::future::join_all;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::available_parallelism;
+use tokio::task::spawn_blocking;
+
+/// Local files at least this large have their CRC checksums computed in parallel.
+pub const PARALLEL_CRC_THRESHOLD: u64 = 256 * 1024 * 1024;
+/// The smallest range of a file that is hashed by a single thread.
+pub const MIN_PARALLEL_CRC_RANGE: u64 = 64 * 1024 * 1024;
+/// The size of the buffer used to read each range.
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Get the reflected polynomial and width of a CRC context, or `None` if it is not a CRC.
+fn polynomial(ctx: &StandardCtx) -> Option<(u64, usize)> {
+    match ctx {
+        StandardCtx::CRC32(_, _) => Some((CRC32_POLYNOMIAL, 32)),
+        StandardCtx::CRC32C(_, _) => Some((CRC32C_POLYNOMIAL, 32)),
+        StandardCtx::CRC64NVME(_, _) => Some((CRC64NVME_POLYNOMIAL, 64)),
+        _ => None,
+    }
+}
+
+/// Whether a context is a CRC that can be computed in parallel.
+pub fn is_parallel_crc(ctx: &StandardCtx) -> bool {
+    polynomial(ctx).is_some()
+}
+
+/// The size of the ranges to hash so that each available thread hashes one range.
+pub fn parallel_crc_range(file_size: u64) -> u64 {
+    let threads = available_parallelism().map(|n| n.get()).unwrap_or(1);
+    file_size
+        .div_ceil(u64::try_from(threads).unwrap_or(1))
+        .max(MIN_PARALLEL_CRC_RANGE)
+}
+
+/// Compute the big-endian CRC values of the contexts for a range of a file.
+fn crc_range(path: &Path, ctxs: &[StandardCtx], start: u64, end: u64) -> Result<Vec<u64>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut file = file.take(end - start);
+
+    let mut ctxs = ctxs
+        .iter()
+        .map(|ctx| ctx.reset().with_endianness(Endianness::BigEndian))
+        .collect::<Vec<_>>();
+    let mut buf = vec![0; READ_BUFFER_SIZE];
+    let mut read = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+
+        read += u64::try_from(n)?;
+        let data: Arc<[u8]> = Arc::from(&buf[0..n]);
+        for ctx in ctxs.iter_mut() {
+            ctx.update(data.clone())?;
+        }
+    }
+
+    if read != end - start {
+        return Err(GenerateError(format!(
+            "expected to read {} bytes from {} at offset {}, but read {}",
+            end - start,
+            path.display(),
+            start,
+            read
+        )));
+    }
+
+    ctxs.iter_mut()
+        .map(|ctx| {
+            Ok(ctx
+                .finalize()?
+                .into_iter()
+                .fold(0, |crc, byte| (crc << 8) | u64::from(byte)))
+        })
+        .collect()
+}
+
+/// Compute CRC contexts of a file by hashing ranges of `range_size` bytes in parallel and
+/// combining them. Returns the digest of each context with its endianness. All contexts must
+/// be CRCs according to `is_parallel_crc`.
+pub async fn parallel_crc(
+    path: impl AsRef<Path>,
+    ctxs: Vec<StandardCtx>,
+    file_size: u64,
+    range_size: u64,
+) -> Result<Vec<(StandardCtx, Vec<u8>)>> {
+    let polynomials = ctxs
+        .iter()
+        .map(|ctx| {
+            polynomial(ctx)
+                .ok_or_else(|| GenerateError(format!("{} cannot be computed in parallel", ctx)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let path = Arc::new(PathBuf::from(path.as_ref()));
+    let shared_ctxs = Arc::new(ctxs.clone());
+    let range_size = range_size.max(1);
+    let tasks = (0..file_size.div_ceil(range_size).max(1)).map(|range| {
+        let start = range * range_size;
+        let end = (start + range_size).min(file_size);
+        let path = path.clone();
+        let ctxs = shared_ctxs.clone();
+        spawn_blocking(move || {
+            Ok::<_, crate::error::Error>((crc_range(&path, &ctxs, start, end)?, end - start))
+        })
+    });
+
+    let ranges = join_all(tasks)
+        .await
+        .into_iter()
+        .map(|range| range?)
+        .collect::<Result<Vec<_>>>()?;
+
+    ctxs.into_iter()
+        .zip(polynomials)
+        .enumerate()
+        .map(|(i, (ctx, (polynomial, width)))| {
+            let crc = ranges
+                .iter()
+                .map(|(crcs, len)| (crcs[i], *len))
+                .reduce(|(crc1, len1), (crc2, len2)| {
+                    (
+                        crc_combine(polynomial, width, crc1, crc2, len2),
+                        len1 + len2,
+                    )
+                })
+                .map(|(crc, _)| crc)
+                .unwrap_or_default();
+
+            let digest = match (width, ctx.endianness()) {
+                (32, Some(Endianness::LittleEndian)) => u32::try_from(crc)?.to_le_bytes().to_vec(),
+                (32, _) => u32::try_from(crc)?.to_be_bytes().to_vec(),
+                (_, Some(Endianness::LittleEndian)) => crc.to_le_bytes().to_vec(),
+                _ => crc.to_be_bytes().to_vec(),
+            };
+            Ok((ctx, digest))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn parallel_crc_matches_sequential() -> Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("file");
+        let data = (0..100000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        std::fs::write(&path, &data)?;
+
+        let ctxs = ["crc32", "crc32c", "crc64nvme", "crc32-le", "crc64nvme-le"]
+            .into_iter()
+            .map(StandardCtx::from_str)
+            .collect::<copyrite_core::error::Result<Vec<_>>>()?;
+
+        for range_size in [1000, 4096, 99999, 100000, 1000000] {
+            let digests = parallel_crc(&path, ctxs.clone(), 100000, range_size).await?;
+            for (ctx, digest) in digests {
+                let mut expected = ctx.reset();
+                expected.update(Arc::from(data.as_slice()))?;
+                assert_eq!(digest, expected.finalize()?, "{} with {}", ctx, range_size);
+            }
+        }
+
+        assert!(
+            parallel_crc(&path, vec![StandardCtx::md5()], 100000, 1000)
+                .await
+                .is_err()
+        );
+        // The file must be as large as expected.
+        assert!(parallel_crc(&path, ctxs, 200000, 1000).await.is_err());
+
+        Ok(())
+    }
+}
@@ -2,42 +2,162 @@
 //!
 
 use crate::checksum::Ctx;
+use crate::checksum::aws_etag::{AWSETagCtx, PartMode};
+use crate::checksum::file::SumsFile;
+use crate::checksum::standard::StandardCtx;
+pub use crate::checksum::standard::{Checksum, Endianness};
+use crate::config::{BucketOptions, Config, Remote, is_valid_name};
 use crate::error::Error;
-use crate::error::Error::{CheckError, CopyError, GenerateError, ParseError};
+use crate::error::Error::{CheckError, CopyError, GenerateError, InsufficientSpace, ParseError};
 use crate::error::Result;
 use crate::io::S3Client;
+use crate::io::connector::{BindAddress, ConnectorHttpClient, ConnectorOptions, HttpVersion};
+use crate::io::copy::filter::FilterRule;
+use crate::io::copy::{CompleteOptions, DEFAULT_COMPLETE_RETRIES};
+use crate::io::drs::{DrsClient, DrsSums, is_drs_url};
+use crate::io::fault::FaultInjection;
+use crate::io::pool::ClientPool;
+use crate::io::profile::S3CompatProfile;
+use crate::io::space::{RequiredSpace, check_space};
 use crate::io::sums::ObjectSumsBuilder;
-use crate::io::sums::channel::ChannelReader;
-use crate::io::{CredentialOverrides, Provider};
+use crate::io::sums::channel::{ChannelReader, ChunkSize};
+use crate::io::sums::external::{HttpSumsSource, SumsSource};
+use crate::io::sums::file::SpoolFile;
+use crate::io::sums::mapped::SumsMap;
+use crate::io::trace::ApiTrace;
+use crate::io::{
+    CredentialOverrides, GdsOptions, Provider, RequestParameter, SftpOptions, SwiftOptions,
+    WebDavOptions, read_credential_file, region_partition,
+};
 use crate::stats;
-use crate::stats::{CheckStats, CopyStats, CopySuccessReason, GenerateStats};
+use crate::stats::{
+    AuditStats, CheckStats, ChecksumParseStats, CompatStats, ConcatStats, CopyStats,
+    CopySuccessReason, Decision, DecisionKind, DoctorStats, ExportPartsStats, GenerateStats,
+    InferEtagStats, ManifestStats, PrefixCopyStats, SplitStats, StatsDiffStats, SumsGcStats,
+    SyncStats, TransitionStats,
+};
+use crate::task;
 use crate::task::ClientInput;
-use crate::task::check::{CheckTask, CheckTaskBuilder, GroupBy};
-use crate::task::copy::CopyTaskBuilder;
-use crate::task::generate::{GenerateTaskBuilder, SumCtxPairs};
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::task::audit::{AuditStatus, AuditTaskBuilder, RestoreTier};
+use crate::task::check::{CheckTask, CheckTaskBuilder, DEFAULT_PREFETCH_CONCURRENCY, GroupBy};
+use crate::task::compare::{compare_bytes, locate_difference};
+use crate::task::compat::Target;
+use crate::task::concat::ConcatTaskBuilder;
+use crate::task::copy::{CopyTaskBuilder, write_rejected_checksum};
+use crate::task::diff::{DEFAULT_REGRESSION_THRESHOLD, RunDiff, RunSummary};
+use crate::task::doctor::DoctorTaskBuilder;
+use crate::task::gc::GcTaskBuilder;
+use crate::task::generate::{GenerateTask, GenerateTaskBuilder, SumCtxPairs};
+use crate::task::hook::{Hook, HookObject, HookStage};
+use crate::task::manifest::{ManifestRow, ScheduleStrategy};
+use crate::task::parts::PartManifest;
+use crate::task::prefix::{PrefixTask, PrefixTaskBuilder};
+use crate::task::snapshot::SnapshotManifestBuilder;
+use crate::task::split::SplitTaskBuilder;
+use crate::task::sync::{SyncDelta, SyncTaskBuilder};
+use crate::task::transition::{TransitionObject, TransitionTask, TransitionTaskBuilder};
+use crate::task::ui::{UiLog, ui_println, ui_record};
+use crate::task::watch::WatchTaskBuilder;
+use aws_sdk_s3::types::StorageClass;
+use clap::{Args, Parser, Subcommand, ValueEnum, value_parser};
 use console::style;
+use futures_util::{StreamExt, stream};
 use humantime::Duration;
-use indicatif::HumanDuration;
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
 use parse_size::parse_size;
+use pastey::paste;
 use serde::{Deserialize, Serialize};
-use serde_json::{to_string, to_string_pretty};
-use std::collections::HashSet;
+use serde_json::{Value, to_string, to_string_pretty, to_value};
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::ffi::OsString;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 use tokio::io::stdin;
+use tokio::select;
+use tokio::signal::ctrl_c;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+
+/// Set the credential options of one side which are not already set from a remote.
+macro_rules! apply_remote_credentials {
+    ($credentials:ident, $remote:ident $(, $prefix:ident)?) => {
+        paste! {
+            if $credentials.[<$($prefix)? credential_provider>].is_none() {
+                $credentials.[<$($prefix)? credential_provider>] = $remote.credential_provider;
+            }
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, profile);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, secret);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, credential_process);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, region);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, endpoint_url);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, access_key_id_file);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, secret_access_key_file);
+            apply_remote_credentials!(@option $credentials, $remote, $($prefix)?, session_token_file);
+        }
+    };
+    (@option $credentials:ident, $remote:ident, $($prefix:ident)?, $name:ident) => {
+        paste! {
+            if $credentials.[<$($prefix)? $name>].is_none() {
+                $credentials.[<$($prefix)? $name>] = $remote.$name.clone();
+            }
+        }
+    };
+}
 
 /// Args for the checksum-cloud CLI.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about)]
 pub struct Command {
-    /// The amount of time to calculate checksums for. Once this timeout is reached the partial
-    /// checksum will be saved to the partial checksum file.
+    /// The amount of time to run `generate`, `check`, `copy`, `sync`, `manifest` or `watch`
+    /// for, e.g. `2h`.
+    ///
+    /// Once this timeout is reached, `generate` with `--incremental` stops reading and saves the
+    /// partial checksums to the sums file, so that the next `--incremental` run continues where
+    /// it stopped. Without `--incremental`, the generate is cancelled without writing a sums
+    /// file. A `copy` is cancelled and an incomplete multipart upload is aborted, unless
+    /// `--no-abort` is used, in which case the upload is kept and its id is reported. The other
+    /// commands cancel the copies or generates that they run in the same way, and `watch` stops
+    /// watching.
     #[arg(global = true, short, long, env = "COPYRITE_TIMEOUT")]
     pub timeout: Option<Duration>,
+    /// The config file which defines named remotes. Defaults to
+    /// `$XDG_CONFIG_HOME/copyrite/config.toml` or `~/.config/copyrite/config.toml` if it exists.
+    ///
+    /// Remotes are defined in `[remotes.<name>]` sections and are used as
+    /// `<name>:<bucket>/<key>` in place of an S3 URL. Each remote can set the
+    /// `credential_provider`, `profile`, `secret`, `credential_process`, `region`,
+    /// `endpoint_url`, `access_key_id_file`, `secret_access_key_file`, `session_token_file`,
+    /// `s3_compatible` and `force_path_style` options, which apply to the side of the copy that
+    /// uses the remote, unless the options are also set on the command line. Remotes can be used
+    /// with every command that takes S3 locations except for the rows of a `manifest`, e.g.:
+    ///
+    /// [remotes.prod]
+    /// credential_provider = "aws-profile"
+    /// profile = "prod"
+    /// region = "ap-southeast-2"
+    ///
+    /// Options for S3 locations under a bucket or prefix are defined in
+    /// `[buckets."<bucket>[/<prefix>]"]` sections, and are enabled automatically for the side of
+    /// the command which uses a matching location. These can set the `s3_compatible`,
+    /// `force_path_style`, `no_get_object_attributes`, `no_checksum_mode`,
+    /// `no_request_checksum` and `requester_pays` options, e.g.:
+    ///
+    /// [buckets."open-data/requester"]
+    /// requester_pays = true
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_CONFIG",
+        hide_short_help = true,
+        verbatim_doc_comment
+    )]
+    pub config: Option<PathBuf>,
     /// The subcommands for copyrite.
     #[command(subcommand)]
     pub commands: Subcommands,
@@ -53,12 +173,16 @@ pub struct Command {
     /// Options related to S3-compatible storage compatibility.
     #[command(flatten)]
     pub compatibility: Compatibility,
+    /// Options related to reading and storing sums using an external API or other locations.
+    #[command(flatten)]
+    pub sums_api: SumsApi,
 }
 
 impl Command {
     /// Parse args and set default values.
     pub fn parse_args() -> Result<Self> {
-        let args = Self::parse();
+        let mut args = Self::parse();
+        args.apply_config()?;
         Self::validate(&args)?;
         Ok(args)
     }
@@ -69,26 +193,249 @@ impl Command {
         I: IntoIterator<Item = T>,
         T: Into<OsString> + Clone,
     {
-        let args = Self::parse_from(iter);
+        let mut args = Self::parse_from(iter);
+        args.apply_config()?;
+        Self::validate(&args)?;
+        Ok(args)
+    }
+
+    /// Parse the command from an iterator, returning an error rather than exiting if the
+    /// arguments are invalid.
+    pub fn try_parse_from_iter<I, T>(iter: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut args = Self::try_parse_from(iter)?;
+        args.apply_config()?;
         Self::validate(&args)?;
         Ok(args)
     }
 
+    /// Resolve `<remote>:<bucket>/<key>` and `gs://` inputs using the remotes in the config
+    /// file, and use the options of each remote and any matching buckets for its side of the
+    /// command. Checksum presets are expanded into their checksums.
+    pub fn apply_config(&mut self) -> Result<()> {
+        // Without a config file, only the built-in `gs` remote is resolved.
+        let config = Config::load(self.config.as_deref())?;
+        self.expand_checksum_presets(config.as_ref())?;
+        let config = config.unwrap_or_default();
+
+        let inputs: Vec<&mut String> = match &mut self.commands {
+            Subcommands::Copy(copy) => {
+                let default_checksum = Self::apply_sides(
+                    &config,
+                    &mut self.credentials,
+                    &mut self.compatibility,
+                    Some(&mut copy.source),
+                    &mut copy.destination,
+                )?;
+
+                // The command line takes precedence over buckets, which take precedence over
+                // the remote.
+                if copy.default_checksum.is_none() {
+                    copy.default_checksum = config
+                        .bucket_options(&copy.destination)
+                        .find_map(|options| options.default_checksum.clone())
+                        .or(default_checksum)
+                        .map(Box::new);
+                }
+                return Ok(());
+            }
+            Subcommands::Sync(sync) => {
+                Self::apply_sides(
+                    &config,
+                    &mut self.credentials,
+                    &mut self.compatibility,
+                    Some(&mut sync.source),
+                    &mut sync.destination,
+                )?;
+                return Ok(());
+            }
+            Subcommands::Split(split) => {
+                Self::apply_sides(
+                    &config,
+                    &mut self.credentials,
+                    &mut self.compatibility,
+                    Some(&mut split.source),
+                    &mut split.destination,
+                )?;
+                return Ok(());
+            }
+            // The source of `watch` is always a local directory.
+            Subcommands::Watch(watch) => {
+                Self::apply_sides(
+                    &config,
+                    &mut self.credentials,
+                    &mut self.compatibility,
+                    None,
+                    &mut watch.destination,
+                )?;
+                return Ok(());
+            }
+            Subcommands::Doctor(doctor) => {
+                Self::apply_sides(
+                    &config,
+                    &mut self.credentials,
+                    &mut self.compatibility,
+                    None,
+                    &mut doctor.destination,
+                )?;
+                return Ok(());
+            }
+            Subcommands::Generate(generate) => generate
+                .input
+                .iter_mut()
+                .chain(
+                    generate
+                        .input_checksum
+                        .iter_mut()
+                        .map(|input| &mut input.input),
+                )
+                .collect(),
+            Subcommands::Check(check) => check.input.iter_mut().collect(),
+            Subcommands::Retag(retag) => vec![&mut retag.object],
+            Subcommands::Transition(transition) => vec![&mut transition.prefix],
+            Subcommands::Audit(audit) => vec![&mut audit.prefix],
+            Subcommands::Concat(concat) => concat
+                .sources
+                .iter_mut()
+                .chain([&mut concat.destination])
+                .collect(),
+            Subcommands::Sums(sums) => match &mut sums.commands {
+                SumsSubcommands::Gc(gc) => vec![&mut gc.location],
+                SumsSubcommands::Compat(compat) => vec![&mut compat.input],
+                SumsSubcommands::ExportParts(export_parts) => vec![&mut export_parts.input],
+                _ => vec![],
+            },
+            _ => vec![],
+        };
+
+        // Commands other than `copy` use a single set of options for all inputs.
+        let mut remotes = vec![];
+        for input in inputs {
+            remotes.extend(config.resolve(input));
+            config
+                .bucket_options(input)
+                .for_each(|options| self.compatibility.apply_bucket_options(options));
+        }
+        remotes.sort();
+        remotes.dedup();
+        match remotes.as_slice() {
+            [] => Ok(()),
+            [remote] => {
+                let remote = config.try_remote(remote)?;
+                self.credentials.apply_remote(&remote);
+                self.compatibility.apply_remote(&remote);
+                Ok(())
+            }
+            _ => Err(ParseError(format!(
+                "inputs from different remotes ({}) are only supported by `copy`",
+                remotes.join(", ")
+            ))),
+        }
+    }
+
+    /// Resolve the source and destination of a command that has options for each side, using
+    /// the options of each remote and any matching buckets for its own side. Returns the default
+    /// checksum of the destination remote.
+    fn apply_sides(
+        config: &Config,
+        credentials: &mut Credentials,
+        compatibility: &mut Compatibility,
+        source: Option<&mut String>,
+        destination: &mut String,
+    ) -> Result<Option<Ctx>> {
+        if let Some(source) = source {
+            if let Some(remote) = config.resolve(source) {
+                let remote = config.try_remote(remote)?;
+                credentials.apply_source_remote(&remote);
+                compatibility.apply_source_remote(&remote);
+            }
+            config
+                .bucket_options(source)
+                .for_each(|options| compatibility.apply_source_bucket_options(options));
+        }
+
+        let mut default_checksum = None;
+        if let Some(remote) = config.resolve(destination) {
+            let remote = config.try_remote(remote)?;
+            credentials.apply_destination_remote(&remote);
+            compatibility.apply_destination_remote(&remote);
+            default_checksum = remote.default_checksum.clone();
+        }
+        config
+            .bucket_options(destination)
+            .for_each(|options| compatibility.apply_destination_bucket_options(options));
+
+        Ok(default_checksum)
+    }
+
+    /// Expand checksum presets from the config file into their checksums.
+    fn expand_checksum_presets(&mut self, config: Option<&Config>) -> Result<()> {
+        match &mut self.commands {
+            Subcommands::Generate(generate) => {
+                ChecksumArg::expand(&mut generate.checksum, config)?;
+                for input_checksum in &mut generate.input_checksum {
+                    ChecksumArg::expand(&mut input_checksum.checksum, config)?;
+                }
+            }
+            Subcommands::Split(split) => ChecksumArg::expand(&mut split.checksum, config)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Validate commands.
     pub fn validate(args: &Self) -> Result<()> {
         if let Subcommands::Generate(generate) = &args.commands {
-            if generate.input[0] == "-" && args.output.ui {
+            if generate.is_stdin() && args.output.ui {
                 return Err(ParseError(
                     "cannot use ui mode with an stdout generate command".to_string(),
                 ));
             }
 
-            // For S3 objects, passing no checksums is valid as metadata can be used, otherwise
-            // it's an error if not verifying the data.
-            if generate.checksum.is_empty()
-                && !generate.verify
-                && !generate.input.iter().all(|input| {
-                    Provider::try_from(input.as_str()).is_ok_and(|provider| provider.is_s3())
+            if generate.spool && !generate.is_stdin() {
+                return Err(ParseError(
+                    "spooling is only supported when taking input from stdin".to_string(),
+                ));
+            }
+            if !generate.spool && (generate.temp_dir.is_some() || generate.max_temp_usage.is_some())
+            {
+                return Err(ParseError(
+                    "`--temp-dir` and `--max-temp-usage` are only used with `--spool`".to_string(),
+                ));
+            }
+            if generate.file_concurrency == 0 {
+                return Err(ParseError(
+                    "file concurrency must be at least 1".to_string(),
+                ));
+            }
+            if generate.incremental && generate.is_stdin() {
+                return Err(ParseError(
+                    "incremental checksums are not supported when taking input from stdin"
+                        .to_string(),
+                ));
+            }
+            if generate.is_stdin()
+                && !generate.spool
+                && generate.checksums_for("-").iter().any(Ctx::is_part_number)
+            {
+                return Err(ParseError(
+                    "part number checksums require the input size, use `--spool` with stdin"
+                        .to_string(),
+                ));
+            }
+
+            // For S3 and Azure objects, passing no checksums is valid as metadata can be used,
+            // otherwise it's an error if not verifying the data.
+            if !generate.verify
+                && !generate.incremental
+                && !generate.inputs().iter().all(|input| {
+                    !generate.checksums_for(input).is_empty()
+                        || Provider::try_from(input.as_str())
+                            .is_ok_and(|provider| provider.is_s3() || provider.is_azure())
                 })
             {
                 return Err(ParseError(
@@ -97,12 +444,35 @@ impl Command {
             }
         }
 
-        if !matches!(args.commands, Subcommands::Copy(_))
-            && (args.credentials.has_prefixed_options()
-                || args.compatibility.has_prefixed_options())
+        if let Subcommands::Check(check) = &args.commands
+            && check.prefetch_concurrency == 0
+        {
+            return Err(ParseError(
+                "prefetch concurrency must be at least 1".to_string(),
+            ));
+        }
+
+        if let Subcommands::Retag(retag) = &args.commands
+            && !Provider::try_from(retag.object.as_str())?.is_s3()
+        {
+            return Err(ParseError(
+                "only S3 objects can be updated in place".to_string(),
+            ));
+        }
+
+        if !matches!(
+            args.commands,
+            Subcommands::Copy(_)
+                | Subcommands::Watch(_)
+                | Subcommands::Sync(_)
+                | Subcommands::Manifest(_)
+                | Subcommands::Doctor(_)
+                | Subcommands::Split(_)
+        ) && (args.credentials.has_prefixed_options()
+            || args.compatibility.has_prefixed_options())
         {
             return Err(ParseError(
-                "source and destination options are only available for the `copy` command, use the unprefixed versions instead (e.g. `--credential-provider`)"
+                "source and destination options are only available for the `copy`, `watch`, `sync`, `manifest`, `doctor` and `split` commands, use the unprefixed versions instead (e.g. `--credential-provider`)"
                     .to_string(),
             ));
         }
@@ -110,24 +480,204 @@ impl Command {
         Ok(())
     }
 
+    /// Create the client pool and the source client, and split any `<object>#<sums>` inputs
+    /// into the sums map. Returns the pool, the source client, the client used to read and store
+    /// sums, and the sums map.
+    async fn clients(&mut self) -> Result<(ClientPool, S3Client, S3Client, Option<Arc<SumsMap>>)> {
+        let pool = ClientPool::default()
+            .set_trace(ApiTrace::from_options(
+                self.compatibility.record_trace.as_ref(),
+                self.compatibility.replay_trace.as_ref(),
+            )?)
+            .set_http_client(
+                Some(self.compatibility.connector_options())
+                    .filter(ConnectorOptions::is_set)
+                    .map(|options| ConnectorHttpClient::new(&options))
+                    .transpose()?,
+            );
+        let client = self
+            .credentials
+            .source_client(&self.compatibility, &pool)
+            .await?;
+
+        // Sums files can be mapped to other locations using the sums map or `<object>#<sums>`
+        // inputs.
+        let mut sums_map = self.sums_api.sums_map().await?;
+        self.commands.split_sums_inputs(&mut sums_map);
+        let drs_sums = self
+            .commands
+            .resolve_drs_inputs(&self.credentials.drs_client(), &mut sums_map)
+            .await?;
+        let sums_map = (!sums_map.is_empty()).then(|| Arc::new(sums_map));
+        let client = match &sums_map {
+            Some(sums_map) => client.with_sums_map(sums_map.clone()),
+            None => client,
+        };
+
+        // The sums from DRS metadata are used for resolved objects by every command, and are
+        // merged with the sums API for commands that use it.
+        let (client, sums_source) = if drs_sums.is_empty() {
+            (client, self.sums_api.sums_source())
+        } else {
+            let sums_source: Arc<dyn SumsSource + Send + std::marker::Sync> =
+                Arc::new(drs_sums.clone().with_source(self.sums_api.sums_source()));
+            (
+                client.with_sums_source(Arc::new(drs_sums)),
+                Some(sums_source),
+            )
+        };
+
+        // Only `generate`, `check` and `sums compat` read and store sums using the sums API.
+        let sums_client = match sums_source {
+            Some(sums_source) => client.clone().with_sums_source(sums_source),
+            None => client.clone(),
+        };
+
+        Ok((pool, client, sums_client, sums_map))
+    }
+
+    /// Create the destination client of a copy.
+    async fn copy_destination_client(
+        &self,
+        pool: &ClientPool,
+        sums_map: Option<Arc<SumsMap>>,
+    ) -> Result<S3Client> {
+        let destination_client = self
+            .credentials
+            .destination_client(&self.compatibility, pool)
+            .await?;
+
+        Ok(match sums_map {
+            Some(sums_map) => destination_client.with_sums_map(sums_map),
+            None => destination_client,
+        })
+    }
+
+    /// Get a token that is cancelled once the `--timeout` is reached. The token is never
+    /// cancelled if there is no timeout.
+    fn deadline(&self) -> CancellationToken {
+        let deadline = CancellationToken::new();
+        if let Some(timeout) = self.timeout {
+            let deadline = deadline.clone();
+            tokio::spawn(async move {
+                sleep(timeout.into()).await;
+                deadline.cancel();
+            });
+        }
+        deadline
+    }
+
+    /// Run a `generate`, `check` or `copy` command and return its stats as JSON rather than
+    /// printing them. This is used by the library bindings. If the command fails, its stats are
+    /// returned in an `Error::Stats`.
+    pub async fn run_stats(mut self) -> Result<Value> {
+        let deadline = self.deadline();
+        let (pool, client, sums_client, sums_map) = self.clients().await?;
+        let write_sums_file = self.output.write_sums_file;
+        let destination_client = match &self.commands {
+            Subcommands::Copy(_) => Some(self.copy_destination_client(&pool, sums_map).await?),
+            _ => None,
+        };
+
+        let stats = match self.commands {
+            Subcommands::Generate(mut generate_args) => {
+                generate_args.deadline = deadline;
+                let inputs = generate_args
+                    .inputs()
+                    .into_iter()
+                    .map(|input| ClientInput::new(input, Some(sums_client.clone())))
+                    .collect();
+                to_value(
+                    generate_args
+                        .generate(self.optimization, inputs, true, false)
+                        .await?,
+                )?
+            }
+            Subcommands::Check(mut check_args) => {
+                check_args.deadline = deadline;
+                let inputs = check_args
+                    .input
+                    .iter()
+                    .map(|input| ClientInput::new(input.clone(), Some(sums_client.clone())))
+                    .collect();
+                to_value(
+                    check_args
+                        .check(self.optimization, write_sums_file, false, inputs)
+                        .await?,
+                )?
+            }
+            Subcommands::Copy(mut copy_args) => {
+                copy_args.deadline = deadline;
+                let destination_client = destination_client
+                    .ok_or_else(|| CopyError("copy requires a destination client".to_string()))?;
+
+                if PrefixTask::is_prefix(&copy_args.source).await? {
+                    to_value(
+                        copy_args
+                            .copy_prefix(
+                                client,
+                                destination_client,
+                                self.credentials,
+                                self.optimization,
+                                write_sums_file,
+                                false,
+                                false,
+                                false,
+                            )
+                            .await?,
+                    )?
+                } else {
+                    copy_args.check_single_object()?;
+                    to_value(
+                        copy_args
+                            .copy(
+                                client,
+                                destination_client,
+                                self.credentials,
+                                self.optimization,
+                                write_sums_file,
+                                false,
+                            )
+                            .await?,
+                    )?
+                }
+            }
+            _ => {
+                return Err(ParseError(
+                    "only generate, check and copy commands return stats".to_string(),
+                ));
+            }
+        };
+
+        Ok(stats)
+    }
+
     /// Execute the command from the args.
-    pub async fn execute(self) -> Result<()> {
+    pub async fn execute(mut self) -> Result<()> {
         let now = Instant::now();
-        let client = self.credentials.source_client(&self.compatibility).await?;
+        if let Some(ui_log) = &self.output.ui_log {
+            UiLog::install(ui_log)?;
+            // The arguments are not recorded as they can contain credentials.
+            ui_record(format!("copyrite {} started", env!("CARGO_PKG_VERSION")));
+        }
+        let deadline = self.deadline();
+        let (pool, client, sums_client, sums_map) = self.clients().await?;
+        let commands = self.commands;
 
         let pretty_json = self.output.pretty_json;
         let write_sums_file = self.output.write_sums_file;
         let ui = self.output.ui;
 
-        match self.commands {
-            Subcommands::Generate(generate_args) => {
+        match commands {
+            Subcommands::Generate(mut generate_args) => {
+                generate_args.deadline = deadline;
                 let inputs = generate_args
-                    .input
-                    .iter()
-                    .map(|input| ClientInput::new(input.clone(), Some(client.clone())))
+                    .inputs()
+                    .into_iter()
+                    .map(|input| ClientInput::new(input, Some(sums_client.clone())))
                     .collect();
                 match generate_args
-                    .generate(self.optimization, inputs, true)
+                    .generate(self.optimization, inputs, true, ui)
                     .await
                 {
                     Ok(stats) => {
@@ -144,11 +694,12 @@ impl Command {
                     }
                 }
             }
-            Subcommands::Check(check_args) => {
+            Subcommands::Check(mut check_args) => {
+                check_args.deadline = deadline;
                 let inputs = check_args
                     .input
                     .iter()
-                    .map(|input| ClientInput::new(input.clone(), Some(client.clone())))
+                    .map(|input| ClientInput::new(input.clone(), Some(sums_client.clone())))
                     .collect();
                 match check_args
                     .check(self.optimization, write_sums_file, false, inputs)
@@ -160,12 +711,34 @@ impl Command {
                     }
                 }
             }
-            Subcommands::Copy(copy_args) => {
+            Subcommands::Copy(mut copy_args) => {
+                copy_args.deadline = deadline;
                 let destination_client = self
                     .credentials
-                    .destination_client(&self.compatibility)
+                    .destination_client(&self.compatibility, &pool)
                     .await?;
+                let destination_client = match sums_map {
+                    Some(sums_map) => destination_client.with_sums_map(sums_map),
+                    None => destination_client,
+                };
+
+                if PrefixTask::is_prefix(&copy_args.source).await? {
+                    return copy_args
+                        .copy_prefix(
+                            client,
+                            destination_client,
+                            self.credentials,
+                            self.optimization,
+                            write_sums_file,
+                            true,
+                            pretty_json,
+                            ui,
+                        )
+                        .await
+                        .map(|_| ());
+                }
 
+                copy_args.check_single_object()?;
                 match copy_args
                     .copy(
                         client,
@@ -183,6 +756,130 @@ impl Command {
                     }
                 }
             }
+            Subcommands::Retag(retag_args) => {
+                match retag_args.retag(client, self.optimization, ui).await {
+                    Ok(output) => Self::print_stats(&output, pretty_json, ui)?,
+                    Err(err) => {
+                        Self::report_failure(err.with_elapsed(now.elapsed()), pretty_json, ui)?
+                    }
+                }
+            }
+            Subcommands::Transition(transition_args) => {
+                transition_args
+                    .transition(client, self.optimization, pretty_json, ui)
+                    .await?;
+            }
+            Subcommands::Doctor(doctor_args) => {
+                let destination_client = self
+                    .credentials
+                    .destination_client(&self.compatibility, &pool)
+                    .await?;
+
+                let stats = doctor_args.doctor(destination_client).await?;
+                Self::print_stats(&stats.with_elapsed(now.elapsed()), pretty_json, false)?;
+            }
+            Subcommands::Split(split_args) => {
+                let destination_client = self
+                    .credentials
+                    .destination_client(&self.compatibility, &pool)
+                    .await?;
+
+                let stats = split_args
+                    .split(client, destination_client, self.optimization)
+                    .await?;
+                Self::print_stats(&stats.with_elapsed(now.elapsed()), pretty_json, false)?;
+            }
+            Subcommands::Concat(concat_args) => {
+                let stats = concat_args.concat(client, self.optimization).await?;
+                Self::print_stats(&stats.with_elapsed(now.elapsed()), pretty_json, false)?;
+            }
+            Subcommands::Audit(audit_args) => {
+                audit_args
+                    .audit(client, self.optimization, pretty_json)
+                    .await?;
+            }
+            Subcommands::Sums(sums_args) => match sums_args.commands {
+                SumsSubcommands::InferEtag(infer_args) => {
+                    Self::print_stats(&infer_args.infer()?, pretty_json, false)?
+                }
+                SumsSubcommands::Compat(compat_args) => {
+                    Self::print_stats(&compat_args.compat(sums_client).await?, pretty_json, false)?
+                }
+                SumsSubcommands::Gc(gc_args) => {
+                    Self::print_stats(&gc_args.gc(client).await?, pretty_json, false)?
+                }
+                SumsSubcommands::ExportParts(export_args) => {
+                    let stats = export_args.export(client, self.optimization).await?;
+                    Self::print_stats(&stats.with_elapsed(now.elapsed()), pretty_json, false)?
+                }
+            },
+            Subcommands::Checksum(checksum_args) => match checksum_args.commands {
+                ChecksumSubcommands::Parse(parse_args) => {
+                    Self::print_stats(&parse_args.parse()?, pretty_json, false)?
+                }
+            },
+            Subcommands::Stats(stats_args) => match stats_args.commands {
+                StatsSubcommands::Diff(diff_args) => {
+                    Self::print_stats(&diff_args.diff().await?, pretty_json, false)?
+                }
+            },
+            Subcommands::Manifest(mut manifest_args) => {
+                manifest_args.deadline = deadline;
+                let destination_client = self
+                    .credentials
+                    .destination_client(&self.compatibility, &pool)
+                    .await?;
+
+                manifest_args
+                    .copy(
+                        client,
+                        destination_client,
+                        self.credentials,
+                        self.optimization,
+                        write_sums_file,
+                        pretty_json,
+                        ui,
+                    )
+                    .await?;
+            }
+            Subcommands::Watch(mut watch_args) => {
+                watch_args.deadline = deadline;
+                let destination_client = self
+                    .credentials
+                    .destination_client(&self.compatibility, &pool)
+                    .await?;
+
+                watch_args
+                    .watch(
+                        client,
+                        destination_client,
+                        self.credentials,
+                        self.optimization,
+                        write_sums_file,
+                        pretty_json,
+                        ui,
+                    )
+                    .await?;
+            }
+            Subcommands::Sync(mut sync_args) => {
+                sync_args.deadline = deadline;
+                let destination_client = self
+                    .credentials
+                    .destination_client(&self.compatibility, &pool)
+                    .await?;
+
+                sync_args
+                    .sync(
+                        client,
+                        destination_client,
+                        self.credentials,
+                        self.optimization,
+                        write_sums_file,
+                        pretty_json,
+                        ui,
+                    )
+                    .await?;
+            }
         }
 
         Ok(())
@@ -193,6 +890,7 @@ impl Command {
     where
         T: Serialize,
     {
+        ui_record(format!("stats: {}", to_string(stats)?));
         if !ui {
             if pretty_json {
                 println!("{}", to_string_pretty(stats)?);
@@ -213,6 +911,7 @@ impl Command {
         Box<T>: Into<Error>,
     {
         if ui {
+            ui_record(format!("stats: {}", to_string(&stats)?));
             return Err(Box::new(stats).into());
         }
 
@@ -221,14 +920,52 @@ impl Command {
     }
 }
 
-/// The generate subcommand components.provenance: false
+/// The value of an option passed to a command from another language, such as a keyword
+/// argument of the Python bindings or a JSON option of the C ABI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    /// A flag, which is only passed if it is set.
+    Flag(bool),
+    /// An option with a value.
+    Value(String),
+    /// An option which is repeated for each value.
+    Values(Vec<String>),
+}
+
+/// Convert options into command line arguments for `Command::try_parse_from_iter`. Option names
+/// are the long options of the command line, with underscores or dashes.
+pub fn options_to_args<I, K>(options: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (K, OptionValue)>,
+    K: AsRef<str>,
+{
+    let mut args = vec![];
+    for (key, value) in options {
+        let flag = format!("--{}", key.as_ref().replace('_', "-"));
+        match value {
+            OptionValue::Flag(true) => args.push(flag),
+            OptionValue::Flag(false) => {}
+            OptionValue::Value(value) => args.push(format!("{flag}={value}")),
+            OptionValue::Values(values) => {
+                args.extend(values.into_iter().map(|value| format!("{flag}={value}")))
+            }
+        }
+    }
+
+    args
+}
+
+/// The generate subcommand components.
 #[derive(Debug, Args)]
 pub struct Generate {
     /// The input file to calculate the checksum for.
     ///
     /// By default, accepts a file name. use - to accept input from stdin. If using stdin,
     /// the output will be written to stdout. Multiple files can be specified.
-    #[arg(value_delimiter = ',', required = true)]
+    ///
+    /// Block devices, such as raw disk images, and named pipes are also accepted. The size of a
+    /// named pipe is not known, so `<part-number>` checksums cannot be used with it.
+    #[arg(value_delimiter = ',', required_unless_present = "input_checksum")]
     pub input: Vec<String>,
     /// Checksums to use. Can be specified multiple times or comma-separated.
     ///
@@ -237,7 +974,7 @@ pub struct Generate {
     /// this is interpreted as a `<part-number>` where the input file is split evenly into the
     /// number of parts (where the last part can be smaller). For example `md5-aws-10` splits
     /// the file into 10 parts. `<part-number>` is not supported when the file size is not
-    /// known, such as when taking input from stdin.
+    /// known, such as when taking input from stdin, unless `--spool` is used.
     ///
     /// It is possible to specify different part sizes by appending additional parts separated
     /// by a `-`. In this case, if the file is bigger than the number of parts, the last part
@@ -245,13 +982,29 @@ pub struct Generate {
     /// example, `md5-aws-8mib-16mib` will create one 8 MiB part and the rest will be 16 MiB
     /// parts.
     ///
+    /// Use an `-uncompressed` suffix to compute a checksum of the decompressed contents of
+    /// gzip or BGZF data, e.g. `md5-uncompressed` or `sha256-uncompressed`. This allows
+    /// comparing compressed files against checksums published for their uncompressed contents.
+    ///
     /// This option supports file-based objects and objects in S3 by using the
     /// `S3://bucket/object` syntax. This option must be specified for file-based objects. It
     /// does not need to be specified for S3 objects as it will use metadata by default. This
     /// means that if no checksums are specified with S3 objects, the object will not be read
     /// to compute the checksum, and will instead use existing ETags and additional checksums.
+    ///
+    /// Checksum presets defined in the `[checksums]` table of the config file can be used by
+    /// name, and expand to their checksums, e.g. `--checksum clinical` with
+    /// `clinical = "sha256,md5-aws-8mib,crc64nvme"`.
     #[arg(value_delimiter = ',', short, long)]
-    pub checksum: Vec<Ctx>,
+    pub checksum: Vec<ChecksumArg>,
+    /// Checksums to use for a single input, specified as `<input>=<checksums>` where
+    /// `<checksums>` is comma-separated. Can be specified multiple times.
+    ///
+    /// These checksums are used for the input instead of the checksums in `--checksum`, e.g.
+    /// `--input-checksum a.bam=md5 --input-checksum b.bam=sha256-aws-8mib`. The input does not
+    /// need to be specified separately. Checksum presets can also be used.
+    #[arg(long)]
+    pub input_checksum: Vec<InputChecksum>,
     /// Generate any missing checksums that would be required to confirm whether two files are
     /// identical using the `check` subcommand.
     ///
@@ -284,46 +1037,179 @@ pub struct Generate {
         conflicts_with = "force_overwrite"
     )]
     pub verify: bool,
+    /// Buffer stdin to a temporary file before generating checksums.
+    ///
+    /// This makes the size of the input known, so that `<part-number>` checksums such as
+    /// `md5-aws-10` can be used with piped input. The temporary file is created in `--temp-dir`,
+    /// or the system temporary directory which can be set using `TMPDIR`, and is removed
+    /// afterwards, including when interrupted with Ctrl-C. This option is only valid when taking
+    /// input from stdin.
+    #[arg(long, env = "COPYRITE_SPOOL")]
+    pub spool: bool,
+    /// The directory to create temporary files in when using `--spool`.
+    ///
+    /// Use this to spool to a volume with enough space rather than the system temporary
+    /// directory, which may be small or memory-backed.
+    #[arg(long, env = "COPYRITE_TEMP_DIR")]
+    pub temp_dir: Option<PathBuf>,
+    /// The maximum number of bytes that `--spool` writes to the temporary directory, e.g. 10gib.
+    ///
+    /// Spooling fails and removes the temporary file if the input is larger than this.
+    #[arg(long, env = "COPYRITE_MAX_TEMP_USAGE", value_parser = |s: &str| parse_size(s))]
+    pub max_temp_usage: Option<u64>,
+    /// Update the checksums of files that have grown by only reading the appended data.
+    ///
+    /// This saves the intermediate state of checksums in the sums file. When the file is larger
+    /// than the size recorded in its sums file, all existing checksums are updated by resuming
+    /// from the saved state and reading only the data after the previous size. This is useful
+    /// for files that are only appended to, such as logs or FASTQ files being written. Checksums
+    /// that cannot be resumed, such as AWS ETags or CRC64NVME, or files that have changed in
+    /// size without a saved state, cause all checksums to be recomputed from the whole file.
+    ///
+    /// Note that data before the previous size is assumed to be unchanged and is not read.
+    #[arg(
+        long,
+        env = "COPYRITE_INCREMENTAL",
+        conflicts_with_all = ["force_overwrite", "verify"]
+    )]
+    pub incremental: bool,
+    /// The number of input files to generate checksums for at the same time.
+    ///
+    /// By default, inputs are processed one at a time. The output stats are always in the same
+    /// order as the inputs. When using `--ui`, a combined progress bar shows the number of
+    /// inputs completed.
+    #[arg(long, env = "COPYRITE_FILE_CONCURRENCY", default_value_t = 1)]
+    pub file_concurrency: usize,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
 }
 
 impl Generate {
+    /// Whether the input is taken from stdin.
+    pub fn is_stdin(&self) -> bool {
+        self.inputs().first().is_some_and(|input| input == "-")
+    }
+
+    /// All inputs, including those only specified with `--input-checksum`.
+    pub fn inputs(&self) -> Vec<String> {
+        let mut inputs = self.input.clone();
+        for input_checksum in &self.input_checksum {
+            if !inputs.contains(&input_checksum.input) {
+                inputs.push(input_checksum.input.to_string());
+            }
+        }
+        inputs
+    }
+
+    /// The checksums to generate for an input. Checksums specified for the input using
+    /// `--input-checksum` take precedence over `--checksum`.
+    pub fn checksums_for(&self, input: &str) -> Vec<Ctx> {
+        let checksums = self
+            .input_checksum
+            .iter()
+            .filter(|input_checksum| input_checksum.input == input)
+            .flat_map(|input_checksum| ChecksumArg::ctxs(&input_checksum.checksum))
+            .collect::<Vec<_>>();
+
+        if checksums.is_empty() {
+            ChecksumArg::ctxs(&self.checksum)
+        } else {
+            checksums
+        }
+    }
+
+    /// Spool stdin to the temporary directory and generate checksums from the spooled file.
+    async fn generate_spooled(
+        &self,
+        builder: GenerateTaskBuilder,
+        optimization: &Optimization,
+    ) -> Result<SumsFile> {
+        let temp_dir = self.temp_dir.clone().unwrap_or_else(env::temp_dir);
+        // The spool must outlive the task so that the file is not removed while reading.
+        let spool = SpoolFile::spool(stdin(), &temp_dir, self.max_temp_usage).await?;
+
+        Ok(builder
+            .with_reader(
+                ChannelReader::new(spool.reader().await?, optimization.channel_capacity)
+                    .with_chunk_size(optimization.reader_chunk_size, true)
+                    .set_max_buffer_bytes(optimization.max_buffer_bytes()?),
+            )
+            .with_file_size(spool.size())
+            .build()
+            .await?
+            .run()
+            .await?
+            .into_inner()
+            .0)
+    }
+
     /// Perform the generate sub command from the args.
     pub async fn generate(
         self,
         optimization: Optimization,
         inputs: Vec<ClientInput>,
         write_sums_file: bool,
+        ui: bool,
     ) -> stats::Result<GenerateStats> {
-        if self.input[0] == "-" {
-            let reader = ChannelReader::new(stdin(), optimization.channel_capacity);
-
-            let output = GenerateTaskBuilder::default()
+        if self.is_stdin() {
+            let builder = GenerateTaskBuilder::default()
                 .with_overwrite(self.force_overwrite)
                 .with_verify(self.verify)
-                .with_context(self.checksum)
-                .with_reader(reader)
-                .set_client(inputs.first().and_then(ClientInput::client))
-                .build()
-                .await?
-                .run()
-                .await?
-                .into_inner()
-                .0;
+                .with_context(self.checksums_for("-"))
+                .with_deadline(self.deadline.clone())
+                .set_client(inputs.first().and_then(ClientInput::client));
 
-            Ok(GenerateStats::from_sums(vec![(
-                self.input[0].to_string(),
-                output,
-            )]))
-        } else {
-            let now = Instant::now();
-            let mut check_stats = None;
-            let mut generate_stats = GenerateStats::default();
-            let mut sums_files = vec![];
-            let mut errors = HashSet::new();
-
-            if self.missing {
+            let output = if self.spool {
+                // The spool is removed when it is dropped, so an interrupt cancels the generate
+                // rather than exiting with the spooled file left behind.
+                select! {
+                    output = self.generate_spooled(builder, &optimization) => output?,
+                    _ = ctrl_c() => {
+                        return Err(GenerateError(
+                            "interrupted while generating from spooled input".to_string(),
+                        )
+                        .into());
+                    }
+                }
+            } else {
+                builder
+                    .with_reader(
+                        ChannelReader::new(stdin(), optimization.channel_capacity)
+                            .with_chunk_size(optimization.reader_chunk_size, false)
+                            .set_max_buffer_bytes(optimization.max_buffer_bytes()?),
+                    )
+                    .build()
+                    .await?
+                    .run()
+                    .await?
+                    .into_inner()
+                    .0
+            };
+
+            Ok(GenerateStats::from_sums(vec![("-".to_string(), output)]))
+        } else {
+            let now = Instant::now();
+            let mut check_stats = None;
+            let mut generate_stats = GenerateStats::default();
+            let mut sums_files = vec![];
+            let mut errors = HashSet::new();
+
+            // The checksums to generate for each location. Missing checksums are merged with
+            // the requested checksums so that each object is only read once. Incremental
+            // generation updates existing checksums even if none are requested.
+            let mut targets: Vec<(ClientInput, Vec<Ctx>)> = inputs
+                .iter()
+                .filter_map(|input| {
+                    let ctxs = self.checksums_for(input.location());
+                    (!ctxs.is_empty() || self.incremental).then(|| (input.clone(), ctxs))
+                })
+                .collect();
+
+            if self.missing {
                 let now = Instant::now();
-                let (ctxs, group_by) = Check::comparable_check(inputs.clone()).await?;
+                let (ctxs, group_by) =
+                    Check::comparable_check(inputs.clone(), DEFAULT_PREFETCH_CONCURRENCY).await?;
                 let (objects, compared, updated, api_errors) = ctxs.into_inner();
                 check_stats = Some(
                     CheckStats::new(
@@ -338,22 +1224,61 @@ impl Generate {
                 );
 
                 let ctxs = SumCtxPairs::from_comparable(objects)?;
-                if let Some(ctxs) = ctxs {
-                    for ctx in ctxs.into_inner() {
-                        let (input, ctx) = ctx.into_inner();
-                        // Select the client that belongs to this location by matching it against
-                        // the input list.
-                        let client = inputs
-                            .iter()
-                            .find(|client_input| client_input.location() == input.as_str())
-                            .and_then(ClientInput::client)
-                            .or_else(|| inputs.first().and_then(ClientInput::client));
+                for ctx in ctxs.map(SumCtxPairs::into_inner).unwrap_or_default() {
+                    let (input, ctx) = ctx.into_inner();
+
+                    if let Some((_, target_ctxs)) = targets
+                        .iter_mut()
+                        .find(|(target, _)| target.location() == input.as_str())
+                    {
+                        target_ctxs.push(ctx);
+                        continue;
+                    }
+
+                    // Select the client that belongs to this location by matching it against
+                    // the input list.
+                    let client = inputs
+                        .iter()
+                        .find(|client_input| client_input.location() == input.as_str())
+                        .and_then(ClientInput::client)
+                        .or_else(|| inputs.first().and_then(ClientInput::client));
+                    targets.push((ClientInput::new(input, client), vec![ctx]));
+                }
+            };
+
+            let pb = if ui {
+                let pb = ProgressBar::new(u64::try_from(targets.len()).map_err(Error::from)?);
+                pb.set_style(
+                    ProgressStyle::with_template(
+                        "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} files ({eta})",
+                    )
+                    .map_err(|err| GenerateError(err.to_string()))?
+                    .progress_chars("#>-"),
+                );
+                Some(pb)
+            } else {
+                None
+            };
+
+            // Generate up to `file_concurrency` inputs at the same time. Results are returned in
+            // the order of the inputs so that the stats are stable.
+            let max_buffer_bytes = optimization.max_buffer_bytes()?;
+            let deadline = &self.deadline;
+            let mut tasks = stream::iter(targets)
+                .map(|(client_input, ctxs)| {
+                    let pb = pb.clone();
+                    async move {
+                        let (input, client) = client_input.into_inner();
                         let task = GenerateTaskBuilder::default()
                             .with_overwrite(self.force_overwrite)
                             .with_verify(self.verify)
+                            .with_incremental(self.incremental)
                             .with_input_file_name(input.to_string())
-                            .with_context(vec![ctx])
+                            .with_context(ctxs)
                             .with_capacity(optimization.channel_capacity)
+                            .with_chunk_size(optimization.reader_chunk_size)
+                            .set_max_buffer_bytes(max_buffer_bytes)
+                            .with_deadline(deadline.clone())
                             .set_client(client)
                             .set_write(write_sums_file)
                             .build()
@@ -361,42 +1286,34 @@ impl Generate {
                             .run()
                             .await;
 
-                        if let Ok(ref task) = task {
-                            sums_files.push((input, task.sums_file().clone()));
-                            errors.extend(task.api_errors());
+                        if let Some(pb) = pb {
+                            pb.inc(1);
                         }
-                        generate_stats = generate_stats.add_stats(task)?;
+                        Ok::<_, Error>((input, task))
                     }
-                }
-
-                if self.checksum.is_empty() {
-                    generate_stats.set_check_stats(check_stats);
-                    generate_stats.set_recoverable_errors(errors);
-                    generate_stats.set_sums_files(sums_files);
-                    return Ok(generate_stats);
-                }
-            };
-
-            for client_input in inputs {
-                let (input, client) = client_input.into_inner();
-                let task = GenerateTaskBuilder::default()
-                    .with_overwrite(self.force_overwrite)
-                    .with_verify(self.verify)
-                    .with_input_file_name(input.to_string())
-                    .with_context(self.checksum.clone())
-                    .with_capacity(optimization.channel_capacity)
-                    .set_client(client)
-                    .set_write(write_sums_file)
-                    .build()
-                    .await?
-                    .run()
-                    .await;
+                })
+                .buffered(self.file_concurrency);
 
+            while let Some(result) = tasks.next().await {
+                let (input, task) = result?;
                 if let Ok(ref task) = task {
                     sums_files.push((input, task.sums_file().clone()));
                     errors.extend(task.api_errors());
                 }
-                generate_stats = generate_stats.add_stats(task)?;
+                generate_stats = match generate_stats.add_stats(task) {
+                    Ok(generate_stats) => generate_stats,
+                    Err(generate_stats) => {
+                        // Let the other inputs that reached the timeout save their progress.
+                        if deadline.is_cancelled() {
+                            while tasks.next().await.is_some() {}
+                        }
+                        return Err(generate_stats);
+                    }
+                };
+            }
+
+            if let Some(pb) = &pb {
+                pb.finish();
             }
 
             generate_stats.set_check_stats(check_stats);
@@ -408,6 +1325,108 @@ impl Generate {
     }
 }
 
+/// Checksums to generate for a single input, parsed from `<input>=<checksums>`.
+#[derive(Debug, Clone)]
+pub struct InputChecksum {
+    /// The input location.
+    pub input: String,
+    /// The checksums to generate for the input.
+    pub checksum: Vec<ChecksumArg>,
+}
+
+impl FromStr for InputChecksum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Checksums never contain a `=`, so split on the last one to support any input name.
+        let Some((input, checksum)) = s.rsplit_once('=') else {
+            return Err(ParseError(format!(
+                "invalid input checksum `{}`, expected `<input>=<checksums>`",
+                s
+            )));
+        };
+        if input.is_empty() {
+            return Err(ParseError(format!(
+                "input checksum `{}` is missing an input",
+                s
+            )));
+        }
+
+        let checksum = checksum
+            .split(',')
+            .map(ChecksumArg::from_str)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            input: input.to_string(),
+            checksum,
+        })
+    }
+}
+
+/// A checksum, or the name of a checksum preset defined in the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumArg {
+    /// A single checksum.
+    Ctx(Box<Ctx>),
+    /// A preset which expands to the checksums defined in the config file.
+    Preset(String),
+}
+
+impl FromStr for ChecksumArg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Ctx::from_str(s) {
+            Ok(ctx) => Ok(Self::Ctx(Box::new(ctx))),
+            Err(_) if is_valid_name(s) => Ok(Self::Preset(s.to_string())),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl ChecksumArg {
+    /// Expand any presets into their checksums using the config file, removing duplicate
+    /// checksums. Fails if a preset is not defined.
+    pub fn expand(args: &mut Vec<Self>, config: Option<&Config>) -> Result<()> {
+        let mut expanded: Vec<Self> = Vec::with_capacity(args.len());
+        for arg in args.drain(..) {
+            let ctxs = match arg {
+                Self::Ctx(ctx) => vec![*ctx],
+                Self::Preset(name) => config
+                    .and_then(|config| config.checksum_preset(&name))
+                    .map(<[Ctx]>::to_vec)
+                    .ok_or_else(|| {
+                        ParseError(format!(
+                            "`{}` is not a checksum or a checksum preset in the config file",
+                            name
+                        ))
+                    })?,
+            };
+
+            for ctx in ctxs.into_iter().map(|ctx| Self::Ctx(Box::new(ctx))) {
+                if !expanded.contains(&ctx) {
+                    expanded.push(ctx);
+                }
+            }
+        }
+
+        *args = expanded;
+        Ok(())
+    }
+
+    /// Get the checksums. Presets are expanded when the command is parsed, so any remaining
+    /// presets are ignored.
+    pub fn ctxs(args: &[Self]) -> Vec<Ctx> {
+        args.iter()
+            .filter_map(|arg| match arg {
+                Self::Ctx(ctx) => Some(*ctx.clone()),
+                Self::Preset(_) => None,
+            })
+            .collect()
+    }
+}
+
 /// The check subcommand components.
 #[derive(Debug, Args)]
 pub struct Check {
@@ -432,15 +1451,67 @@ pub struct Check {
     /// .sums to the input location unless `--write-sums-file` is also specified.
     #[arg(short, long, env = "COPYRITE_MISSING")]
     pub missing: bool,
+    /// Read and compare the tags and metadata of S3 objects in addition to their sums.
+    ///
+    /// This reports the tags and metadata of each object and whether they match across all
+    /// objects. A tag mismatch does not affect the groups of sums files that are equal. Whether
+    /// the tags match is left unknown if the tags of any input could not be read, such as for
+    /// files or when access to the tags is denied.
+    #[arg(long, env = "COPYRITE_COMPARE_TAGS")]
+    pub compare_tags: bool,
+    /// The number of inputs to fetch sizes, sums and tags for at the same time before
+    /// comparing them.
+    ///
+    /// Increasing this reduces the time taken to check many inputs on S3.
+    #[arg(
+        long,
+        env = "COPYRITE_PREFETCH_CONCURRENCY",
+        default_value_t = DEFAULT_PREFETCH_CONCURRENCY
+    )]
+    pub prefetch_concurrency: usize,
+    /// Print the differences between sums files that are not equal to stderr.
+    ///
+    /// For each pair of groups, this shows which checksums matched, which differed with both
+    /// values, and the differing part numbers if the part checksums are known.
+    #[arg(long, env = "COPYRITE_EXPLAIN")]
+    pub explain: bool,
+    /// Fail if an input was modified since it was last verified.
+    ///
+    /// This compares the current `ETag` and version id of each input with the ones recorded in
+    /// its sums file by `audit`. Inputs without a recorded version are not affected.
+    #[arg(long, env = "COPYRITE_ASSERT_IMMUTABLE")]
+    pub assert_immutable: bool,
+    /// Pass the check if at least this many inputs are equal, and report the other inputs as
+    /// outliers.
+    ///
+    /// This is useful for checking three or more replicas, where one replica may be degraded. The
+    /// check fails if fewer inputs are equal, or if there is more than one largest group of equal
+    /// inputs. Requires `--group-by equality`.
+    #[arg(long, env = "COPYRITE_QUORUM", value_parser = value_parser!(u64).range(1..))]
+    pub quorum: Option<u64>,
+    /// Compare the bytes of each input with the first input, in addition to their sums.
+    ///
+    /// The inputs are read at the same time and compared directly, which is useful when sums
+    /// are missing or not trusted. Unlike a comparison of sums, the check fails if any input
+    /// differs, and reports the offset of the first byte that differs.
+    #[arg(long, env = "COPYRITE_BYTE_COMPARE")]
+    pub byte_compare: bool,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
 }
 
 impl Check {
     /// Perform a check for comparability on the input files.
-    pub async fn comparable_check(inputs: Vec<ClientInput>) -> Result<(CheckTask, GroupBy)> {
+    pub async fn comparable_check(
+        inputs: Vec<ClientInput>,
+        prefetch_concurrency: usize,
+    ) -> Result<(CheckTask, GroupBy)> {
         Ok((
             CheckTaskBuilder::default()
                 .with_inputs(inputs)
                 .with_group_by(GroupBy::Comparability)
+                .with_prefetch_concurrency(prefetch_concurrency)
                 .build()
                 .await?
                 .run()
@@ -468,24 +1539,52 @@ impl Check {
     ) -> stats::Result<CheckStats> {
         let now = Instant::now();
         let group_by = self.group_by;
+        if let Some(quorum) = self.quorum {
+            if !matches!(group_by, GroupBy::Equality) {
+                return Err(
+                    CheckError("`--quorum` requires `--group-by equality`".to_string()).into(),
+                );
+            }
+            if quorum > u64::try_from(inputs.len()).map_err(Error::from)? {
+                return Err(CheckError(format!(
+                    "`--quorum` of {} is larger than the number of inputs",
+                    quorum
+                ))
+                .into());
+            }
+        }
 
         let mut builder = CheckTaskBuilder::default()
             .with_group_by(group_by)
             .with_inputs(inputs.clone())
-            .with_update(self.update);
+            .with_update(self.update)
+            .with_compare_tags(self.compare_tags)
+            .with_prefetch_concurrency(self.prefetch_concurrency)
+            .with_assert_immutable(self.assert_immutable);
         let mut generate_stats = None;
         if self.missing {
-            let (ctxs, _) = Check::comparable_check(inputs.clone()).await?;
+            let (ctxs, _) =
+                Check::comparable_check(inputs.clone(), self.prefetch_concurrency).await?;
             let checksum = Check::generate_sums(ctxs);
 
             let mut stats = Generate {
                 input: self.input.clone(),
-                checksum,
+                checksum: checksum
+                    .into_iter()
+                    .map(|ctx| ChecksumArg::Ctx(Box::new(ctx)))
+                    .collect(),
+                input_checksum: vec![],
                 missing: true,
                 force_overwrite: false,
                 verify,
+                spool: false,
+                temp_dir: None,
+                max_temp_usage: None,
+                incremental: false,
+                file_concurrency: 1,
+                deadline: self.deadline.clone(),
             }
-            .generate(optimization, inputs.clone(), write_sums_file)
+            .generate(optimization, inputs.clone(), write_sums_file, false)
             .await
             .map_err(|stats| CheckStats::from_generate_task(group_by, *stats))?;
             let sums = stats
@@ -498,19 +1597,60 @@ impl Check {
         }
 
         let check = builder.build().await?.run().await?;
-        if check.compared_directly().is_empty() {
+        if self.explain {
+            check.explain().iter().for_each(|diff| eprint!("{}", diff));
+        }
+        if check.compared_directly().is_empty() && !self.byte_compare {
             return Err(CheckError(
                 "nothing to compare in checksums, use `generate` or `--missing` first".to_string(),
             )
             .into());
         }
 
-        Ok(CheckStats::from_task(check, generate_stats).with_elapsed(now.elapsed()))
+        let mut stats = CheckStats::from_task(check, generate_stats);
+        if self.byte_compare {
+            let mut comparisons = vec![];
+            for input in inputs.iter().skip(1) {
+                comparisons.push(compare_bytes(inputs[0].clone(), input.clone()).await?);
+            }
+
+            stats = stats.with_byte_comparisons(comparisons);
+            if let Some(comparison) = stats
+                .byte_comparisons
+                .iter()
+                .find(|comparison| !comparison.equal)
+            {
+                stats.unrecoverable_error = Some(CheckError(format!(
+                    "{} and {} differ at byte {}",
+                    comparison.locations[0],
+                    comparison.locations[1],
+                    comparison.first_difference.unwrap_or_default()
+                )));
+                return Err(Box::new(stats.with_elapsed(now.elapsed())));
+            }
+        }
+
+        if let Some(quorum) = self.quorum {
+            stats = stats.with_quorum(quorum);
+            if stats
+                .quorum
+                .as_ref()
+                .is_some_and(|quorum| !quorum.is_reached())
+            {
+                stats.unrecoverable_error = Some(CheckError(format!(
+                    "fewer than {} inputs are equal",
+                    quorum
+                )));
+                return Err(Box::new(stats.with_elapsed(now.elapsed())));
+            }
+        }
+
+        Ok(stats.with_elapsed(now.elapsed()))
     }
 }
 
 /// The tag mode to use when copying files.
-#[derive(Debug, Clone, ValueEnum, Copy, Default)]
+#[derive(Debug, Clone, ValueEnum, Copy, Default, PartialEq, Eq)]
 pub enum MetadataCopy {
     #[default]
     /// Copy all tags or metadata and fail if it could not be copied.
@@ -533,6 +1673,28 @@ impl MetadataCopy {
     }
 }
 
+/// The directive for tags or metadata on server-side copies.
+#[derive(Debug, Clone, ValueEnum, Copy, PartialEq, Eq)]
+pub enum Directive {
+    /// Copy the tags or metadata from the source object.
+    Copy,
+    /// Replace the tags or metadata with the values read from the source, after applying any
+    /// filters.
+    Replace,
+}
+
+impl Directive {
+    /// Is this a copy directive.
+    pub fn is_copy(&self) -> bool {
+        matches!(self, Directive::Copy)
+    }
+
+    /// Is this a replace directive.
+    pub fn is_replace(&self) -> bool {
+        matches!(self, Directive::Replace)
+    }
+}
+
 /// Mode to execute copy task in.
 #[derive(Debug, Clone, ValueEnum, Copy, Default, Deserialize, Serialize)]
 pub enum CopyMode {
@@ -544,6 +1706,22 @@ pub enum CopyMode {
     DownloadUpload,
 }
 
+/// Which missing parts of the destination to create before copying.
+#[derive(Debug, Clone, ValueEnum, Copy, PartialEq, Eq)]
+pub enum CreateDestination {
+    /// Create missing parent directories of local destinations.
+    Directories,
+    /// Create missing parent directories of local destinations and missing S3 buckets.
+    Buckets,
+}
+
+impl CreateDestination {
+    /// Whether missing buckets are created.
+    pub fn is_buckets(&self) -> bool {
+        matches!(self, Self::Buckets)
+    }
+}
+
 impl Display for CopyMode {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -596,7 +1774,7 @@ impl StalledStreamProtection {
 }
 
 /// Details of how to locate credentials or specify no credentials needed
-#[derive(Debug, Clone, ValueEnum, Copy, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, ValueEnum, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum CredentialProvider {
     /// Use the default mechanism of the SDK that obtains credentials from the system
     #[default]
@@ -607,6 +1785,14 @@ pub enum CredentialProvider {
     AwsProfile,
     /// An AWS Secrets Manager secret containing credentials.
     AwsSecret,
+    /// Run an external command which prints credentials in the AWS `credential_process` JSON
+    /// format, such as a custom short-lived token broker.
+    Process,
+    /// Only use the credentials passed with `--access-key-id`, `--secret-access-key` and
+    /// `--session-token` or their `-file` variants. This never reads credentials from
+    /// environment variables, profiles or instance metadata, which is useful for air-gapped
+    /// batch systems.
+    Static,
 }
 
 impl CredentialProvider {
@@ -631,15 +1817,36 @@ impl CredentialProvider {
     }
 }
 
+/// How to check the host keys of SFTP servers.
+#[derive(Debug, Clone, ValueEnum, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SftpKnownHosts {
+    /// Only connect to hosts that are in the known hosts file.
+    #[default]
+    Strict,
+    /// Add the keys of new hosts to the known hosts file, but fail if a known host's key has
+    /// changed.
+    Add,
+    /// Connect to any host, adding new keys to the known hosts file. This should only be used
+    /// on trusted networks.
+    Accept,
+}
+
 /// The copy subcommand components.
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct Copy {
     /// The source file to copy from. By default, accepts a file name, use - to accept input from
     /// stdin.
+    ///
+    /// If the source is a local directory or an S3 prefix ending with `/`, e.g.
+    /// `s3://bucket/prefix/`, every object underneath it is copied recursively and checked
+    /// individually. Existing sums files under the source are not copied.
     #[arg(required = true)]
     pub source: String,
     /// The destination to copy files to. If the input contains multiple files, then this must
     /// be a directory.
+    ///
+    /// For recursive copies, each object is copied to the destination joined with its path
+    /// relative to the source.
     #[arg(required = true)]
     pub destination: String,
     /// Controls how tags are copied.
@@ -652,10 +1859,40 @@ pub struct Copy {
     /// By default, this will copy all metadata and fail if the metadata could not be copied.
     #[arg(long, env = "COPYRITE_METADATA_MODE", default_value = "copy")]
     pub metadata_mode: MetadataCopy,
+    /// Filter or rewrite tags before they are written to the destination. Can be specified
+    /// multiple times or comma-separated.
+    ///
+    /// Rules are `include:<pattern>` to only keep matching keys, `exclude:<pattern>` to drop
+    /// matching keys, and `add:<key>=<value>` to set a tag. Patterns support `*` as a wildcard,
+    /// e.g. `exclude:temp-*,add:migrated=true`. Filters apply to the tags read from the source,
+    /// so with `--tag-mode best-effort` only added tags are written.
+    #[arg(long, value_delimiter = ',', env = "COPYRITE_TAG_FILTER")]
+    pub tag_filter: Vec<FilterRule>,
+    /// Filter or rewrite metadata before it is written to the destination. Can be specified
+    /// multiple times or comma-separated.
+    ///
+    /// This uses the same rules as `--tag-filter`.
+    #[arg(long, value_delimiter = ',', env = "COPYRITE_METADATA_FILTER")]
+    pub metadata_filter: Vec<FilterRule>,
+    /// Explicitly set the tagging directive for server-side single part copies.
+    ///
+    /// By default, tags are copied with the `COPY` directive unless they are rewritten by
+    /// `--tag-filter` or suppressed. `replace` writes the tags read from the source in the same
+    /// `CopyObject` call, and `copy` fails if the tags would need to be replaced. Other copies
+    /// always write the tags when the object is created.
+    #[arg(long, ignore_case = true, env = "COPYRITE_TAGGING_DIRECTIVE")]
+    pub tagging_directive: Option<Directive>,
+    /// Explicitly set the metadata directive for server-side single part copies.
+    ///
+    /// This behaves the same as `--tagging-directive` for metadata. Note that S3 does not keep
+    /// system metadata such as the `Content-Type` when metadata is replaced.
+    #[arg(long, ignore_case = true, env = "COPYRITE_METADATA_DIRECTIVE")]
+    pub metadata_directive: Option<Directive>,
     /// The copy mode.
     ///
     /// By default, this will attempt server-side copy if the source and destination credentials
-    /// are the same.
+    /// are the same. A source using `no-credentials` can also be copied server-side if it is in
+    /// the same partition as the destination and the destination credentials can read it.
     #[arg(long, env = "COPYRITE_COPY_MODE", default_value = "server-side")]
     pub copy_mode: CopyMode,
     /// The threshold at which a file uses multipart uploads when copying to S3. This can be
@@ -683,15 +1920,387 @@ pub struct Copy {
     /// the copy was correct.
     #[arg(long, env = "COPYRITE_NO_CHECK")]
     pub no_check: bool,
+    /// Compare the bytes of the source and destination after copying, in addition to their
+    /// checksums.
+    ///
+    /// This reads both objects in full, so it is useful when checksums are missing or not
+    /// trusted. The copy fails at the first differing byte.
+    #[arg(long, env = "COPYRITE_BYTE_COMPARE", conflicts_with = "no_check")]
+    pub byte_compare: bool,
+    /// Locate where the source and destination diverge if the check after copying fails.
+    ///
+    /// If the sums files of the source and destination have part checksums with the same part
+    /// sizes, the first part that differs is reported without reading any data. Otherwise, the
+    /// bytes of the source and destination are compared to find the first byte that differs.
+    /// The range is reported in the check stats. This can read both objects in full, so it is
+    /// only useful to diagnose a failed copy.
+    #[arg(long, env = "COPYRITE_LOCATE_DIFFERENCE", conflicts_with = "no_check")]
+    pub locate_difference: bool,
     /// Always perform the copy and do not skip if sums match.
     ///
     /// By default, a copy is performed only if the file is not at the destination or if the sums
     /// of the source and destination do not match.
     #[arg(long, env = "COPYRITE_NO_SKIP")]
     pub no_skip: bool,
+    /// Only upload the parts of the object that differ from an existing destination.
+    ///
+    /// Each part of the source is compared to the checksum of the same part of the existing
+    /// destination, or to a SHA256 hash of the same range of the destination if it does not have
+    /// part checksums. Parts that are identical are kept rather than uploaded again. On S3,
+    /// unchanged parts are copied server-side from the existing destination object, and local
+    /// files are updated in place. This is useful when uploading is more expensive than reading,
+    /// such as for large files with small modifications or appended data. This only applies to
+    /// `download-upload` multipart copies to S3 or local destinations that already exist.
+    #[arg(long, env = "COPYRITE_DIFFERENTIAL")]
+    pub differential: bool,
+    /// The expected hex-encoded SHA256 checksum of the source, e.g. from a manifest.
+    ///
+    /// The source is verified against this checksum before copying and the destination is
+    /// verified against it after copying. The copy fails if either does not match. Existing
+    /// sums files and object metadata are used if they contain the checksum. This cannot be
+    /// used when the source is a prefix or directory.
+    #[arg(long, env = "COPYRITE_EXPECT_SHA256")]
+    pub expect_sha256: Option<String>,
+    /// The expected hex-encoded MD5 checksum of the source, e.g. from a manifest.
+    ///
+    /// This is verified in the same way as `--expect-sha256`.
+    #[arg(long, env = "COPYRITE_EXPECT_MD5")]
+    pub expect_md5: Option<String>,
+    /// A replica of the source to read from if reading from the source fails, e.g.
+    /// `s3://mirror-bucket/key` in another region.
+    ///
+    /// If a part fails to be read from the source repeatedly, including when a read fails
+    /// partway through the part, it is read from the next fallback and the rest of the copy
+    /// continues from that fallback. This can be specified multiple times, and fallbacks are
+    /// tried in order. Fallbacks must have the same size as the source, and the same ETag if
+    /// both have one. The destination is checked against the checksums of the source after
+    /// copying.
+    /// Specifying a fallback uses the `download-upload` copy mode and the source credentials.
+    #[arg(long, env = "COPYRITE_SOURCE_FALLBACK")]
+    pub source_fallback: Vec<String>,
+    /// Validate each downloaded part and fetch it again up to this many times if it is
+    /// truncated or does not match the checksum of the source part.
+    ///
+    /// Parts are checked against the size of their range and, for S3 sources, the additional
+    /// checksum of the same source part if there is one. Parts are checked while they are
+    /// streamed to the destination, and a part that fails is fetched and uploaded again.
+    /// Re-fetched parts are counted in `n_download_retries`. By default, parts are not
+    /// validated. This only applies to multipart copies using the `download-upload` mode.
+    #[arg(long, env = "COPYRITE_DOWNLOAD_RETRIES")]
+    pub download_retries: Option<usize>,
+    /// The number of times to retry completing a multipart upload if it fails with a transient
+    /// error, with an exponential backoff between retries.
+    #[arg(long, env = "COPYRITE_COMPLETE_RETRIES", default_value_t = DEFAULT_COMPLETE_RETRIES)]
+    pub complete_retries: usize,
+    /// Do not abort a multipart upload if it fails to complete.
+    ///
+    /// By default, a multipart upload that cannot be completed is aborted so that the uploaded
+    /// parts are not left behind. The error contains the upload id of the incomplete upload.
+    #[arg(long, env = "COPYRITE_NO_ABORT")]
+    pub no_abort: bool,
+    /// The storage class of the destination object when copying to S3, e.g. `STANDARD_IA`.
+    ///
+    /// By default, the destination uses the `STANDARD` storage class.
+    #[arg(long, env = "COPYRITE_STORAGE_CLASS")]
+    pub storage_class: Option<String>,
+    /// The additional checksum to set on the destination if the source does not have any
+    /// existing sums to match, e.g. `crc32c` for storage that does not support `crc64nvme`.
+    ///
+    /// By default, this is `crc64nvme`. This can also be set for a destination remote or
+    /// bucket using `default_checksum` in the config file.
+    #[arg(long, env = "COPYRITE_DEFAULT_CHECKSUM", value_parser = |s: &str| Ctx::from_str(s).map(Box::new))]
+    pub default_checksum: Option<Box<Ctx>>,
+    /// Write a snapshot of the metadata of an S3 source next to the destination sums file after
+    /// copying, as `<destination>.metadata.json`.
+    ///
+    /// The snapshot records the headers, user-defined metadata, tags, ACL, storage class,
+    /// object lock settings and version id of the source, so that the destination keeps a
+    /// complete record even if some of these cannot be applied to it.
+    #[arg(long, env = "COPYRITE_METADATA_SNAPSHOT")]
+    pub metadata_snapshot: bool,
+    /// Create the destination if it does not exist rather than failing part way through the
+    /// copy.
+    ///
+    /// `directories` creates the missing parent directories of a local destination. `buckets`
+    /// also creates a missing destination bucket in the region of the destination credentials,
+    /// using the default S3 settings which block public access and disable ACLs. If no value is
+    /// given, only directories are created.
+    #[arg(
+        long,
+        env = "COPYRITE_CREATE_DESTINATION",
+        num_args = 0..=1,
+        default_missing_value = "directories",
+        require_equals = true
+    )]
+    pub create_destination: Option<CreateDestination>,
+    /// For recursive copies of a local directory, copy hard links from the destination of the
+    /// file they link to rather than reading the same contents again.
+    ///
+    /// Hard links are always recorded in the stats of each copy. See `watch --help` for
+    /// details.
+    #[arg(long, env = "COPYRITE_DEDUPE_HARD_LINKS")]
+    pub dedupe_hard_links: bool,
+    /// A shell command to run before each object is copied, e.g. to validate it. The copy
+    /// fails if the command exits unsuccessfully.
+    ///
+    /// The command runs with `COPYRITE_HOOK_STAGE`, `COPYRITE_HOOK_SOURCE`,
+    /// `COPYRITE_HOOK_DESTINATION` and `COPYRITE_HOOK_SIZE` environment variables, and a
+    /// `COPYRITE_HOOK_CHECKSUM_<NAME>` variable for each known checksum of the source, e.g.
+    /// `COPYRITE_HOOK_CHECKSUM_SHA256`. Hooks do not run if the copy is skipped, and their output
+    /// is written to stderr.
+    #[arg(long, env = "COPYRITE_PRE_COPY_CMD")]
+    pub pre_copy_cmd: Option<String>,
+    /// A shell command to run after each object is copied and checked, e.g. to register it in
+    /// another system. The copy fails if the command exits unsuccessfully.
+    ///
+    /// This uses the same environment variables as `--pre-copy-cmd`, with the checksums of the
+    /// destination.
+    #[arg(long, env = "COPYRITE_POST_COPY_CMD")]
+    pub post_copy_cmd: Option<String>,
+    #[command(flatten)]
+    pub snapshot: SnapshotArgs,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
 }
 
 impl Copy {
+    /// Create a copy of one object with the defaults of the `copy` command, for commands which
+    /// copy many objects. These set the options that they support using struct update syntax.
+    pub fn for_object(source: String, destination: String, deadline: CancellationToken) -> Copy {
+        Copy {
+            source,
+            destination,
+            tag_mode: MetadataCopy::Copy,
+            metadata_mode: MetadataCopy::Copy,
+            tag_filter: vec![],
+            metadata_filter: vec![],
+            tagging_directive: None,
+            metadata_directive: None,
+            copy_mode: CopyMode::ServerSide,
+            multipart_threshold: None,
+            part_size: None,
+            concurrency: 10,
+            no_check: false,
+            byte_compare: false,
+            locate_difference: false,
+            no_skip: false,
+            differential: false,
+            expect_sha256: None,
+            expect_md5: None,
+            source_fallback: vec![],
+            download_retries: None,
+            complete_retries: DEFAULT_COMPLETE_RETRIES,
+            no_abort: false,
+            storage_class: None,
+            default_checksum: None,
+            metadata_snapshot: false,
+            create_destination: None,
+            dedupe_hard_links: false,
+            pre_copy_cmd: None,
+            post_copy_cmd: None,
+            snapshot: Default::default(),
+            deadline,
+        }
+    }
+
+    /// Copy a hard link from the destination of the file that it links to, if that file has
+    /// already been copied. A hard link has the same contents as the file it links to, so this
+    /// avoids reading the same bytes again. Returns the client to read the source with.
+    pub fn dedupe_hard_link(
+        &mut self,
+        original_destination: Option<String>,
+        source_client: &S3Client,
+        destination_client: &S3Client,
+    ) -> S3Client {
+        match original_destination {
+            Some(original_destination) => {
+                self.source = original_destination;
+                destination_client.clone()
+            }
+            None => source_client.clone(),
+        }
+    }
+
+    /// Check that the options can be used to copy a single object rather than a prefix.
+    pub fn check_single_object(&self) -> Result<()> {
+        if self.snapshot.snapshot_manifest.is_some() {
+            return Err(ParseError(format!(
+                "--snapshot-manifest can only be used when copying a prefix or directory, and {} \
+                is a single object",
+                self.source
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Create the copy for an object relative to a recursively copied source. Local
+    /// destinations always create the directory structure of the source.
+    pub fn copy_for(&self, relative: &str) -> Copy {
+        Copy {
+            source: format!("{}/{}", self.source.trim_end_matches('/'), relative),
+            destination: format!("{}/{}", self.destination.trim_end_matches('/'), relative),
+            create_destination: self
+                .create_destination
+                .or(Some(CreateDestination::Directories)),
+            ..self.clone()
+        }
+    }
+
+    /// Perform a recursive copy of every object under the source prefix or directory. If `print`
+    /// is set, each copy prints its own stats, followed by the stats of the whole prefix. Returns
+    /// an error if any object fails to copy.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn copy_prefix(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        credentials: Credentials,
+        optimization: Optimization,
+        write_sums_file: bool,
+        print: bool,
+        pretty_json: bool,
+        ui: bool,
+    ) -> Result<PrefixCopyStats> {
+        // The expected checksums are of a single object, so they cannot match every object
+        // under a prefix.
+        if self.expect_sha256.is_some() || self.expect_md5.is_some() {
+            return Err(ParseError(format!(
+                "--expect-sha256 and --expect-md5 cannot be used with {}, which is a prefix or \
+                directory",
+                self.source
+            )));
+        }
+
+        let now = Instant::now();
+        let objects = PrefixTaskBuilder::default()
+            .with_location(self.source.to_string())
+            .set_client(Some(source_client.clone()))
+            .build()?
+            .list_objects()
+            .await?;
+
+        let mut stats = PrefixCopyStats::new(
+            self.source.to_string(),
+            self.destination.to_string(),
+            u64::try_from(objects.len())?,
+        );
+        let mut copied = HashSet::new();
+        for object in &objects {
+            let mut copy = self.copy_for(&object.path);
+            let copy_source_client = copy.dedupe_hard_link(
+                object
+                    .hard_link_of
+                    .as_ref()
+                    .filter(|original| self.dedupe_hard_links && copied.contains(*original))
+                    .map(|original| self.copy_for(original).destination),
+                &source_client,
+                &destination_client,
+            );
+            let hard_link_of = object
+                .hard_link_of
+                .as_ref()
+                .map(|original| self.copy_for(original).source);
+
+            let result = copy
+                .copy(
+                    copy_source_client,
+                    destination_client.clone(),
+                    credentials.clone(),
+                    optimization.clone(),
+                    write_sums_file,
+                    ui,
+                )
+                .await
+                .map(|copy| copy.set_hard_link_of(hard_link_of.clone()))
+                .map_err(|err| Box::new(err.set_hard_link_of(hard_link_of)));
+
+            match &result {
+                Ok(copy) => {
+                    if print {
+                        Command::print_stats(copy, pretty_json, ui)?;
+                    }
+                    copied.insert(object.path.to_string());
+                }
+                Err(err) if print => Command::print_stats(err, pretty_json, false)?,
+                Err(_) => {}
+            }
+            stats.push_copy(&result);
+        }
+
+        let mut stats = stats.with_elapsed(now.elapsed());
+        if stats.n_failed == 0
+            && let Some(path) = self
+                .snapshot
+                .write(
+                    &destination_client,
+                    objects
+                        .iter()
+                        .map(|object| self.copy_for(&object.path).destination)
+                        .collect(),
+                )
+                .await?
+        {
+            stats = stats.with_snapshot_manifest(path);
+        }
+
+        if print {
+            Command::print_stats(&stats, pretty_json, false)?;
+        }
+        if stats.n_failed == 0 {
+            Ok(stats)
+        } else {
+            Err(CopyError(format!(
+                "failed to copy {} of {} objects under {}",
+                stats.n_failed,
+                objects.len(),
+                self.source
+            )))
+        }
+    }
+
+    /// Create the parent directories or bucket of the destination if requested.
+    async fn create_destination(&self, destination_client: &S3Client, ui: bool) -> Result<()> {
+        let Some(create) = self.create_destination else {
+            return Ok(());
+        };
+
+        match Provider::try_from(self.destination.as_str())? {
+            Provider::File { file } => {
+                if let Some(parent) = Path::new(&file)
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+            }
+            Provider::S3 { bucket, .. } if create.is_buckets() => {
+                if destination_client.create_bucket_if_missing(&bucket).await? {
+                    ui_println(
+                        ui,
+                        format!(
+                            "{} created destination bucket {}",
+                            style("·").bold(),
+                            style(bucket).green()
+                        ),
+                    );
+                }
+            }
+            Provider::S3 { .. }
+            | Provider::Gcs { .. }
+            | Provider::Azure { .. }
+            | Provider::Http { .. }
+            | Provider::Sftp { .. }
+            | Provider::Gds { .. }
+            | Provider::Swift { .. }
+            | Provider::WebDav { .. }
+            | Provider::Custom { .. } => {}
+        }
+
+        Ok(())
+    }
+
     pub async fn copy_check(
         &self,
         source_client: S3Client,
@@ -710,6 +2319,13 @@ impl Copy {
             update: write_sums_file,
             group_by: GroupBy::Equality,
             missing: true,
+            compare_tags: false,
+            prefetch_concurrency: DEFAULT_PREFETCH_CONCURRENCY,
+            explain: false,
+            assert_immutable: false,
+            quorum: None,
+            byte_compare: self.byte_compare,
+            deadline: self.deadline.clone(),
         }
         .check(optimization, write_sums_file, verify, inputs)
         .await?;
@@ -717,69 +2333,258 @@ impl Copy {
         Ok(result)
     }
 
-    /// Perform the copy sub command from the args.
-    pub async fn copy(
-        self,
-        source_client: S3Client,
-        destination_client: S3Client,
-        credentials: Credentials,
-        optimization: Optimization,
+    /// The caller-supplied checksums that the source and destination are expected to have.
+    fn expected_checksums(&self) -> Vec<(Ctx, &str)> {
+        [
+            (StandardCtx::sha256(), &self.expect_sha256),
+            (StandardCtx::md5(), &self.expect_md5),
+        ]
+        .into_iter()
+        .filter_map(|(ctx, expected)| Some((Ctx::Regular(ctx), expected.as_deref()?)))
+        .collect()
+    }
+
+    /// Verify that an object has the expected checksums, generating any that are missing.
+    async fn verify_expected(
+        &self,
+        location: &str,
+        client: S3Client,
+        optimization: &Optimization,
         write_sums_file: bool,
         ui: bool,
-    ) -> stats::Result<CopyStats> {
-        let now = Instant::now();
+    ) -> Result<()> {
+        let expected = self.expected_checksums();
+        if expected.is_empty() {
+            return Ok(());
+        }
 
-        // Verify the source exists before continuing.
-        let source_exists = ObjectSumsBuilder::default()
-            .set_client(Some(source_client.clone()))
-            .build(self.source.to_string())
+        let task = GenerateTaskBuilder::default()
+            .with_input_file_name(location.to_string())
+            .with_context(expected.iter().map(|(ctx, _)| ctx.clone()).collect())
+            .with_capacity(optimization.channel_capacity)
+            .with_chunk_size(optimization.reader_chunk_size)
+            .set_max_buffer_bytes(optimization.max_buffer_bytes()?)
+            .with_client(client)
+            .set_write(write_sums_file)
+            .build()
             .await?
-            .file_size()
-            .await
-            .is_ok_and(|size| size.is_some());
+            .run()
+            .await?;
 
-        if !source_exists {
-            let err_msg = format!("source does not exist: {}", self.source);
-            return Err(Box::new(CopyStats {
-                elapsed_seconds: 0.0,
-                source: self.source,
-                destination: self.destination,
-                bytes_transferred: 0,
-                copy_mode: self.copy_mode,
-                success_reason: None,
-                skipped: false,
-                sums_mismatch: false,
-                n_retries: 0,
-                api_errors: HashSet::new(),
-                check_stats: None,
-                unrecoverable_error: Some(CopyError(err_msg)),
-            }));
-        }
+        for (ctx, expected) in expected {
+            let checksum = task.sums_file().checksums.get(&ctx).ok_or_else(|| {
+                CopyError(format!(
+                    "failed to generate {} checksum for {}",
+                    ctx, location
+                ))
+            })?;
 
-        // If source and destination refer to the same object, treat as a no-op.
-        if Provider::try_from(self.source.as_str())?
-            .is_same_location(&Provider::try_from(self.destination.as_str())?)
-        {
-            if ui {
-                println!(
-                    "{} source and destination are the same ({}), nothing to copy",
-                    style("warning:").yellow().bold(),
-                    self.source
-                );
+            if !checksum.as_str().eq_ignore_ascii_case(expected) {
+                return Err(CopyError(format!(
+                    "{} checksum of {} is {}, expected {}",
+                    ctx,
+                    location,
+                    checksum.as_str(),
+                    expected
+                )));
             }
 
-            let copy_stats = CopyStats {
+            ui_println(
+                ui,
+                format!(
+                    "  {} {} matches expected checksum for {}",
+                    style("·").bold(),
+                    style(&ctx).green(),
+                    location
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the destination credentials can read the source, which is required for a
+    /// server-side copy.
+    async fn destination_can_read_source(&self, destination_client: &S3Client) -> bool {
+        let Ok(mut sums) = ObjectSumsBuilder::default()
+            .set_client(Some(destination_client.clone()))
+            .build(self.source.to_string())
+            .await
+        else {
+            return false;
+        };
+
+        sums.file_size().await.is_ok_and(|size| size.is_some())
+    }
+
+    /// Write a snapshot of the source metadata next to the destination sums file. This does
+    /// nothing if the source does not have any metadata to record, e.g. a local file.
+    async fn write_metadata_snapshot(
+        &self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        ui: bool,
+    ) -> Result<()> {
+        let Some(snapshot) = ObjectSumsBuilder::default()
+            .set_client(Some(source_client))
+            .build(self.source.to_string())
+            .await?
+            .object_snapshot()
+            .await?
+        else {
+            return Ok(());
+        };
+
+        ObjectSumsBuilder::default()
+            .set_client(Some(destination_client))
+            .build(self.destination.to_string())
+            .await?
+            .write_snapshot(&snapshot)
+            .await?;
+
+        ui_println(
+            ui,
+            format!("  {} metadata snapshot written", style("·").bold()),
+        );
+
+        Ok(())
+    }
+
+    /// Run a copy hook with the size of the source and the sums of the object at `location`.
+    async fn run_hook(
+        &self,
+        stage: HookStage,
+        command: &str,
+        size: u64,
+        location: &str,
+        client: S3Client,
+    ) -> Result<()> {
+        let sums = ObjectSumsBuilder::default()
+            .set_client(Some(client))
+            .build(location.to_string())
+            .await?
+            .sums_file()
+            .await?;
+        let object = HookObject::new(self.source.to_string(), self.destination.to_string())
+            .with_size(size)
+            .set_sums(sums);
+
+        Hook::new(stage, command.to_string()).run(&object).await
+    }
+
+    /// Check that a local destination has enough free space for the source before copying.
+    pub async fn check_destination_space(&self, source_size: u64) -> Result<()> {
+        let Provider::File { file } = Provider::try_from(self.destination.as_str())? else {
+            return Ok(());
+        };
+
+        let required = RequiredSpace::new(Path::new(&file), source_size).await?;
+        check_space(required.iter())
+    }
+
+    /// Perform the copy sub command from the args.
+    pub async fn copy(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        credentials: Credentials,
+        optimization: Optimization,
+        write_sums_file: bool,
+        ui: bool,
+    ) -> stats::Result<CopyStats> {
+        let now = Instant::now();
+
+        // Verify the source exists before continuing.
+        let source_size = ObjectSumsBuilder::default()
+            .set_client(Some(source_client.clone()))
+            .build(self.source.to_string())
+            .await?
+            .file_size()
+            .await
+            .ok()
+            .flatten();
+
+        let Some(source_size) = source_size else {
+            let err_msg = format!("source does not exist: {}", self.source);
+            return Err(Box::new(CopyStats {
+                elapsed_seconds: 0.0,
+                source: self.source,
+                destination: self.destination,
+                bytes_transferred: 0,
+                bytes_reused: 0,
+                content_md5_validated: false,
+                rejected_checksum: None,
+                hard_link_of: None,
+                copy_mode: self.copy_mode,
+                success_reason: None,
+                decisions: vec![],
+                skipped: false,
+                sums_mismatch: false,
+                n_retries: 0,
+                n_download_retries: 0,
+                api_errors: HashSet::new(),
+                check_stats: None,
+                unrecoverable_error: Some(CopyError(err_msg)),
+            }));
+        };
+
+        // Verify the source against any expected checksums before copying anything.
+        if let Err(err) = self
+            .verify_expected(
+                &self.source,
+                source_client.clone(),
+                &optimization,
+                write_sums_file,
+                ui,
+            )
+            .await
+        {
+            return Err(Box::new(
+                CopyStats::from_error(
+                    self.source.to_string(),
+                    self.destination.to_string(),
+                    self.copy_mode,
+                    err,
+                )
+                .with_elapsed(now.elapsed()),
+            ));
+        }
+
+        // If source and destination refer to the same object, treat as a no-op.
+        if Provider::try_from(self.source.as_str())?
+            .is_same_location(&Provider::try_from(self.destination.as_str())?)
+        {
+            ui_println(
+                ui,
+                format!(
+                    "{} source and destination are the same ({}), nothing to copy",
+                    style("warning:").yellow().bold(),
+                    self.source
+                ),
+            );
+
+            let copy_stats = CopyStats {
                 elapsed_seconds: 0.0,
                 source: self.source,
                 destination: self.destination,
                 bytes_transferred: 0,
+                bytes_reused: 0,
+                content_md5_validated: false,
+                rejected_checksum: None,
+                hard_link_of: None,
                 copy_mode: self.copy_mode,
                 success_reason: Some(CopySuccessReason::message(
                     "source and destination are the same object",
                 )),
+                decisions: vec![Decision::new(
+                    DecisionKind::Skip,
+                    "skipped",
+                    "the source and destination are the same object",
+                )],
                 skipped: true,
                 sums_mismatch: false,
                 n_retries: 0,
+                n_download_retries: 0,
                 api_errors: HashSet::new(),
                 check_stats: None,
                 unrecoverable_error: None,
@@ -788,11 +2593,43 @@ impl Copy {
             return Ok(copy_stats.with_elapsed(now.elapsed()));
         }
 
+        if let Err(err) = self.check_destination_space(source_size).await {
+            return Err(Box::new(
+                CopyStats::from_error(
+                    self.source.to_string(),
+                    self.destination.to_string(),
+                    self.copy_mode,
+                    err,
+                )
+                .with_elapsed(now.elapsed()),
+            ));
+        }
+
+        if let Err(err) = self.create_destination(&destination_client, ui).await {
+            return Err(Box::new(
+                CopyStats::from_error(
+                    self.source.to_string(),
+                    self.destination.to_string(),
+                    self.copy_mode,
+                    err,
+                )
+                .with_elapsed(now.elapsed()),
+            ));
+        }
+
         let mut exists = false;
-        if !self.no_skip {
-            if ui {
-                println!("{} Checking before copying...", style("[1/3]").bold().dim(),);
-            }
+        let mut decisions = vec![];
+        if self.no_skip {
+            decisions.push(Decision::new(
+                DecisionKind::Skip,
+                "copied",
+                "skipping was disabled",
+            ));
+        } else {
+            ui_println(
+                ui,
+                format!("{} Checking before copying...", style("[1/3]").bold().dim()),
+            );
 
             // Check if it exists in the first place.
             let file_size = ObjectSumsBuilder::default()
@@ -839,65 +2676,132 @@ impl Copy {
                         source: self.source,
                         destination: self.destination,
                         bytes_transferred: 0,
+                        bytes_reused: 0,
+                        content_md5_validated: false,
+                        rejected_checksum: None,
+                        hard_link_of: None,
                         copy_mode: self.copy_mode,
                         success_reason: reason.clone(),
+                        decisions: vec![Decision::new(
+                            DecisionKind::Skip,
+                            "skipped",
+                            "the destination exists and its sums match the source",
+                        )],
                         skipped: true,
                         sums_mismatch: false,
                         n_retries: 0,
+                        n_download_retries: 0,
                         api_errors: HashSet::new(),
                         check_stats: Some(check_stats),
                         unrecoverable_error: None,
                     };
 
                     let elapsed = now.elapsed();
-                    if ui {
-                        if let Some(reason) = reason
-                            && let Some(checksum_match) = reason.checksum_match
-                        {
-                            println!(
+                    if let Some(reason) = reason
+                        && let Some(checksum_match) = reason.checksum_match
+                    {
+                        ui_println(
+                            ui,
+                            format!(
                                 "  {} {} sums match, skipping copy!",
                                 style("·").bold(),
                                 style(checksum_match.kind).green()
-                            );
-                        }
-                        println!("Done in {}", HumanDuration(elapsed));
+                            ),
+                        );
                     }
+                    ui_println(ui, format!("Done in {}", HumanDuration(elapsed)));
 
                     return Ok(copy_stats.with_elapsed(now.elapsed()));
                 }
             }
 
-            if ui {
-                if exists {
-                    println!(
+            if exists {
+                ui_println(
+                    ui,
+                    format!(
                         "  {} file exists at source but sums do not match!",
                         style("·").bold(),
-                    );
-                } else {
-                    println!("  {} file does not exist", style("·").bold(),);
-                }
+                    ),
+                );
+                decisions.push(Decision::new(
+                    DecisionKind::Skip,
+                    "copied",
+                    "the destination exists but its sums do not match the source",
+                ));
+            } else {
+                ui_println(ui, format!("  {} file does not exist", style("·").bold()));
+                decisions.push(Decision::new(
+                    DecisionKind::Skip,
+                    "copied",
+                    "the destination does not exist",
+                ));
             }
         }
 
-        // The copy mode must be download-upload if not using default credential providers.
-        let copy_mode = if credentials.is_default() {
+        // The copy mode must be download-upload if not using default credential providers,
+        // unless the source is public and the destination credentials can read it directly.
+        let copy_mode = if credentials.is_default()
+            || (credentials.is_anonymous_source_in_partition()
+                && self.destination_can_read_source(&destination_client).await)
+        {
             self.copy_mode
         } else {
+            if self.copy_mode.is_server_side() {
+                decisions.push(Decision::new(
+                    DecisionKind::CopyMode,
+                    CopyMode::DownloadUpload.to_string(),
+                    "the source and destination use different credentials",
+                ));
+            }
             CopyMode::DownloadUpload
         };
 
+        if let Some(command) = &self.pre_copy_cmd
+            && let Err(err) = self
+                .run_hook(
+                    HookStage::PreCopy,
+                    command,
+                    source_size,
+                    &self.source,
+                    source_client.clone(),
+                )
+                .await
+        {
+            return Err(Box::new(
+                CopyStats::from_error(
+                    self.source.to_string(),
+                    self.destination.to_string(),
+                    self.copy_mode,
+                    err,
+                )
+                .with_elapsed(now.elapsed()),
+            ));
+        }
+
         let result = CopyTaskBuilder::default()
             .with_source(self.source.to_string())
             .with_destination(self.destination.to_string())
             .with_metadata_mode(self.metadata_mode)
             .with_tag_mode(self.tag_mode)
+            .with_tag_filter(self.tag_filter.clone().into())
+            .with_metadata_filter(self.metadata_filter.clone().into())
+            .with_tagging_directive(self.tagging_directive)
+            .with_metadata_directive(self.metadata_directive)
             .with_multipart_threshold(self.multipart_threshold)
             .with_concurrency(self.concurrency)
             .with_part_size(self.part_size)
             .with_ui(ui)
             .with_copy_mode(copy_mode)
+            .with_differential(self.differential)
+            .with_source_fallbacks(self.source_fallback.clone())
+            .with_download_retries(self.download_retries)
+            .with_complete_options(CompleteOptions::new(self.complete_retries, !self.no_abort))
+            .set_storage_class(self.storage_class.as_deref().map(StorageClass::from))
+            .set_default_checksum(self.default_checksum.as_deref().cloned())
             .with_source_client(source_client.clone())
             .with_destination_client(destination_client.clone())
+            .with_cancellation_token(self.deadline.clone())
+            .with_decisions(decisions)
             .build()
             .await?
             .run()
@@ -905,12 +2809,26 @@ impl Copy {
 
         // If the file existed at the start there must be a sums mismatch.
         let mismatch = exists;
-        let copy_stats = if !self.no_check {
-            if ui {
-                println!("{} Checking after copying...", style("[3/3]").bold().dim(),);
-            }
+        let rejected_checksum = result.rejected_checksum();
+        let rejected_client = destination_client.clone();
+        let expected_client = destination_client.clone();
+        let hook_client = destination_client.clone();
+        let snapshot_clients = (source_client.clone(), destination_client.clone());
+        let expected_optimization = optimization.clone();
+        let mut copy_stats = if !self.no_check {
+            ui_println(
+                ui,
+                format!("{} Checking after copying...", style("[3/3]").bold().dim()),
+            );
 
-            let check_stats = self
+            let locate_inputs = [
+                ClientInput::new(self.source.to_string(), Some(source_client.clone())),
+                ClientInput::new(
+                    self.destination.to_string(),
+                    Some(destination_client.clone()),
+                ),
+            ];
+            let check_stats = match self
                 .copy_check(
                     source_client,
                     destination_client,
@@ -919,121 +2837,1840 @@ impl Copy {
                     write_sums_file,
                 )
                 .await
-                .map_err(|err| {
-                    CopyStats::from_check_stats(
-                        self.source.to_string(),
-                        self.destination.to_string(),
-                        self.copy_mode,
-                        *err,
-                        false,
-                        false,
-                    )
-                    .with_elapsed(now.elapsed())
-                })?;
+            {
+                Ok(check_stats) => check_stats,
+                Err(mut err) => {
+                    // The difference is only a diagnostic, so the check error is reported even
+                    // if it cannot be located.
+                    if self.locate_difference {
+                        let [source, destination] = locate_inputs;
+                        err.difference_range =
+                            locate_difference(source, destination).await.ok().flatten();
+                    }
+
+                    return Err(Box::new(
+                        CopyStats::from_check_stats(
+                            self.source.to_string(),
+                            self.destination.to_string(),
+                            self.copy_mode,
+                            *err,
+                            false,
+                            false,
+                        )
+                        .with_elapsed(now.elapsed()),
+                    ));
+                }
+            };
 
             let reason = Option::<CopySuccessReason>::from(&check_stats)
                 .or_else(|| Some(CopySuccessReason::message("copy verified")));
 
-            if ui
-                && let Some(reason) = &reason
+            if let Some(reason) = &reason
                 && let Some(checksum_match) = &reason.checksum_match
             {
-                println!(
-                    "  {} {} sums match!",
-                    style("·").bold(),
-                    style(checksum_match.kind.clone()).green()
+                ui_println(
+                    ui,
+                    format!(
+                        "  {} {} sums match!",
+                        style("·").bold(),
+                        style(checksum_match.kind.clone()).green()
+                    ),
                 );
             }
 
             CopyStats::from_task(result, Some(check_stats), false, mismatch, reason)
         } else {
             let reason = CopySuccessReason::message("copied without checksum verification");
-            if ui {
-                println!("  {} {}", style("·").bold(), reason.message);
-            }
+            ui_println(ui, format!("  {} {}", style("·").bold(), reason.message));
 
             CopyStats::from_task(result, None, false, mismatch, Some(reason))
         };
 
-        let elapsed = now.elapsed();
-        if ui {
-            println!("Done in {}", HumanDuration(elapsed));
+        // Record in the destination sums that the rejected checksum is not stored with it.
+        if write_sums_file
+            && let Some(rejected_checksum) = &rejected_checksum
+            && let Err(err) =
+                write_rejected_checksum(&self.destination, rejected_client, rejected_checksum).await
+        {
+            copy_stats.unrecoverable_error = Some(err);
+            return Err(Box::new(copy_stats.with_elapsed(now.elapsed())));
+        }
+
+        // Verify the destination against any expected checksums after copying.
+        if let Err(err) = self
+            .verify_expected(
+                &self.destination,
+                expected_client,
+                &expected_optimization,
+                write_sums_file,
+                ui,
+            )
+            .await
+        {
+            copy_stats.unrecoverable_error = Some(err);
+            return Err(Box::new(copy_stats.with_elapsed(now.elapsed())));
+        }
+
+        if self.metadata_snapshot
+            && let Err(err) = self
+                .write_metadata_snapshot(snapshot_clients.0, snapshot_clients.1, ui)
+                .await
+        {
+            copy_stats.unrecoverable_error = Some(err);
+            return Err(Box::new(copy_stats.with_elapsed(now.elapsed())));
         }
 
+        if let Some(command) = &self.post_copy_cmd
+            && let Err(err) = self
+                .run_hook(
+                    HookStage::PostCopy,
+                    command,
+                    source_size,
+                    &self.destination,
+                    hook_client,
+                )
+                .await
+        {
+            copy_stats.unrecoverable_error = Some(err);
+            return Err(Box::new(copy_stats.with_elapsed(now.elapsed())));
+        }
+
+        let elapsed = now.elapsed();
+        ui_println(ui, format!("Done in {}", HumanDuration(elapsed)));
+
         Ok(copy_stats.with_elapsed(elapsed))
     }
 }
 
-/// The subcommands for copyrite.
-#[derive(Subcommand, Debug)]
-pub enum Subcommands {
-    /// Generate a checksum.
-    Generate(#[arg(flatten)] Generate),
-    /// Confirm a set of files is identical. This returns sets of files that are identical.
-    /// Which means that more than two files can be checked at the same time.
-    Check(#[arg(flatten)] Check),
-    /// Copy a file to a location. This command can also simultaneously generate checksums, and
-    /// supports all options for generate.
-    Copy(#[arg(flatten)] Copy),
+/// The retag subcommand components.
+#[derive(Debug, Args)]
+pub struct Retag {
+    /// The S3 object to update.
+    #[arg(required = true)]
+    pub object: String,
+    /// Filter or rewrite the tags of the object. Can be specified multiple times or
+    /// comma-separated.
+    ///
+    /// This uses the same rules as `--tag-filter` for the `copy` command, e.g.
+    /// `exclude:temp-*,add:reviewed=true`.
+    #[arg(long, value_delimiter = ',', env = "COPYRITE_TAG_FILTER")]
+    pub tag_filter: Vec<FilterRule>,
+    /// Filter or rewrite the metadata of the object. Can be specified multiple times or
+    /// comma-separated.
+    ///
+    /// This uses the same rules as `--tag-filter`.
+    #[arg(long, value_delimiter = ',', env = "COPYRITE_METADATA_FILTER")]
+    pub metadata_filter: Vec<FilterRule>,
+    /// The storage class to move the object to, e.g. `STANDARD_IA` or `GLACIER_IR`.
+    ///
+    /// By default, the storage class is reset to `STANDARD`. Note that S3 does not keep
+    /// system metadata such as the `Content-Type` unless only the storage class is changed.
+    #[arg(long, env = "COPYRITE_STORAGE_CLASS")]
+    pub storage_class: Option<String>,
+    /// The number of simultaneous copy tasks to run if the object is updated using a multipart
+    /// copy.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+    /// Do not re-verify the existing checksums of the object after updating it.
+    ///
+    /// By default, the checksums from the existing sums file and object metadata are generated
+    /// again after the update and compared to the checksums before the update. This reads the
+    /// whole object.
+    #[arg(long, env = "COPYRITE_NO_CHECK")]
+    pub no_check: bool,
 }
 
-/// The checksum to use.
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq, PartialOrd, Ord, Copy)]
-pub enum Checksum {
-    /// Calculate the MD5 checksum.
-    MD5,
-    /// Calculate the SHA1 checksum.
-    SHA1,
-    /// Calculate the SHA256 checksum.
-    SHA256,
-    /// Calculate the SHA512 checksum.
-    SHA512,
-    /// Calculate a CRC32.
-    CRC32,
-    /// Calculate a CRC32C.
-    CRC32C,
-    /// Calculate a CRC64NVME.
-    CRC64NVME,
-    /// Calculate the XXHash64 checksum.
-    #[value(name = "xxhash64")]
-    XXHash64,
-    /// Calculate the XXHash3 64-bit checksum.
-    #[value(name = "xxhash3")]
-    XXHash3,
-    /// Calculate the XXHash128 checksum.
-    #[value(name = "xxhash128")]
-    XXHash128,
-    /// Calculate the QuickXor checksum.
-    QuickXor,
-}
-
-impl FromStr for Checksum {
-    type Err = Error;
+impl Retag {
+    /// Regenerate the checksums that existed before the update and check that they still match.
+    async fn verify_existing(
+        &self,
+        existing: &SumsFile,
+        client: S3Client,
+        optimization: &Optimization,
+        ui: bool,
+    ) -> Result<()> {
+        GenerateTask::verify_existing(
+            self.object.to_string(),
+            existing,
+            Some(client),
+            optimization.channel_capacity,
+        )
+        .await?;
 
-    fn from_str(s: &str) -> Result<Self> {
-        <Checksum as ValueEnum>::from_str(s, true).map_err(ParseError)
+        ui_println(
+            ui,
+            format!(
+                "  {} {} existing sums match!",
+                style("·").bold(),
+                style(existing.checksums.len()).green()
+            ),
+        );
+
+        Ok(())
+    }
+
+    /// Perform the retag sub command from the args.
+    pub async fn retag(
+        self,
+        client: S3Client,
+        optimization: Optimization,
+        ui: bool,
+    ) -> stats::Result<CopyStats> {
+        let now = Instant::now();
+
+        // The sums are read before updating the object so that they can be verified afterwards.
+        let existing = ObjectSumsBuilder::default()
+            .set_client(Some(client.clone()))
+            .build(self.object.to_string())
+            .await?
+            .sums_file()
+            .await?
+            .filter(|existing| !existing.checksums.is_empty());
+
+        // S3 only allows copying an object to itself if the metadata or storage class changes,
+        // so the metadata is always rewritten unless the storage class is set.
+        let storage_class = self.storage_class.as_deref().map(StorageClass::from);
+        let metadata_directive = storage_class.is_none().then_some(Directive::Replace);
+
+        let result = CopyTaskBuilder::default()
+            .with_source(self.object.to_string())
+            .with_destination(self.object.to_string())
+            .with_tag_filter(self.tag_filter.clone().into())
+            .with_metadata_filter(self.metadata_filter.clone().into())
+            .with_metadata_directive(metadata_directive)
+            .set_storage_class(storage_class)
+            .with_concurrency(self.concurrency)
+            .with_ui(ui)
+            .with_copy_mode(CopyMode::ServerSide)
+            .with_source_client(client.clone())
+            .with_destination_client(client.clone())
+            .build()
+            .await?
+            .run()
+            .await?;
+
+        let reason = match existing {
+            Some(existing) if !self.no_check => {
+                ui_println(
+                    ui,
+                    format!("{} Checking after updating...", style("[3/3]").bold().dim()),
+                );
+
+                if let Err(err) = self
+                    .verify_existing(&existing, client, &optimization, ui)
+                    .await
+                {
+                    let mut stats = CopyStats::from_task(result, None, false, true, None);
+                    stats.unrecoverable_error = Some(err);
+                    return Err(Box::new(stats.with_elapsed(now.elapsed())));
+                }
+
+                CopySuccessReason::message("existing checksums re-verified")
+            }
+            Some(_) => CopySuccessReason::message("updated without checksum verification"),
+            None => CopySuccessReason::message("no existing checksums to verify"),
+        };
+
+        let elapsed = now.elapsed();
+        ui_println(ui, format!("Done in {}", HumanDuration(elapsed)));
+
+        Ok(CopyStats::from_task(result, None, false, false, Some(reason)).with_elapsed(elapsed))
     }
 }
 
-/// The endianness to use for CRC-based checksums.
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq, PartialOrd, Ord, Copy, Hash)]
-pub enum Endianness {
-    /// Use little-endian representation.
-    LittleEndian,
-    /// Use big-endian representation.
-    BigEndian,
+/// The transition subcommand components.
+#[derive(Debug, Args)]
+pub struct Transition {
+    /// The S3 prefix of the objects to transition, e.g. `s3://bucket/prefix/`.
+    #[arg(required = true)]
+    pub prefix: String,
+    /// The storage class to move the objects to, e.g. `DEEP_ARCHIVE`.
+    ///
+    /// Objects that already have the storage class are skipped. Sums files are not
+    /// transitioned so that they can still be read.
+    #[arg(long, required = true, env = "COPYRITE_STORAGE_CLASS")]
+    pub storage_class: String,
+    /// The number of simultaneous copy tasks to run if an object is transitioned using a
+    /// multipart copy.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
 }
 
-impl Display for Endianness {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Endianness::LittleEndian => f.write_str("le"),
-            Endianness::BigEndian => f.write_str("be"),
+impl Transition {
+    /// Transition a single object, recording the result in the stats.
+    async fn transition_object(
+        &self,
+        task: &TransitionTask,
+        object: &TransitionObject,
+        client: S3Client,
+        optimization: &Optimization,
+        stats: &mut TransitionStats,
+        ui: bool,
+    ) -> Result<()> {
+        let location = object.location();
+        let sums = |client| async {
+            ObjectSumsBuilder::default()
+                .set_client(Some(client))
+                .build(location.to_string())
+                .await?
+                .sums_file()
+                .await
+        };
+        let before = sums(client.clone()).await?.unwrap_or_default();
+
+        // Only the storage class changes, so the metadata is copied as is.
+        let retag = Retag {
+            object: location.to_string(),
+            tag_filter: vec![],
+            metadata_filter: vec![],
+            storage_class: Some(self.storage_class.to_string()),
+            concurrency: self.concurrency,
+            no_check: true,
+        };
+        match retag.retag(client.clone(), optimization.clone(), ui).await {
+            Ok(copy_stats) => stats.copy_stats = Some(copy_stats),
+            Err(mut copy_stats) => {
+                let err = copy_stats
+                    .unrecoverable_error
+                    .take()
+                    .unwrap_or_else(|| CopyError(format!("failed to transition {}", location)));
+                stats.copy_stats = Some(*copy_stats);
+                return Err(err);
+            }
+        }
+
+        let after = sums(client).await?.unwrap_or_default();
+        stats.checksum_match = TransitionTask::confirm_sums(&location, &before, &after)?;
+
+        let e_tag = task.e_tag(object).await?;
+        if e_tag.as_deref() != object.e_tag() {
+            return Err(CopyError(format!(
+                "ETag of {} changed from {} to {} after the transition",
+                location,
+                object.e_tag().unwrap_or("none"),
+                e_tag.as_deref().unwrap_or("none")
+            )));
+        }
+        stats.e_tag = e_tag;
+
+        Ok(())
+    }
+
+    /// Perform the transition sub command from the args. Each object prints its own stats.
+    pub async fn transition(
+        self,
+        client: S3Client,
+        optimization: Optimization,
+        pretty_json: bool,
+        ui: bool,
+    ) -> Result<()> {
+        let task = TransitionTaskBuilder::default()
+            .with_prefix(self.prefix.to_string())
+            .with_client(client.clone())
+            .build()?;
+
+        let mut failed = 0;
+        for object in task.list().await? {
+            let now = Instant::now();
+            let mut stats = TransitionStats::new(
+                object.location(),
+                object.storage_class().to_string(),
+                self.storage_class.to_string(),
+            );
+
+            if object.storage_class() == self.storage_class {
+                stats.skipped = true;
+                stats.e_tag = object.e_tag().map(str::to_string);
+            } else if let Err(err) = self
+                .transition_object(
+                    &task,
+                    &object,
+                    client.clone(),
+                    &optimization,
+                    &mut stats,
+                    ui,
+                )
+                .await
+            {
+                stats.unrecoverable_error = Some(err);
+                failed += 1;
+            }
+
+            Command::print_stats(&stats.with_elapsed(now.elapsed()), pretty_json, false)?;
+        }
+
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(CopyError(format!(
+                "failed to transition {} objects",
+                failed
+            )))
         }
     }
 }
 
-/// Commands related to optimizing IO and CPU tasks.
+/// The doctor subcommand components.
+#[derive(Debug, Args)]
+pub struct Doctor {
+    /// The destination to inspect before copying, e.g. `s3://bucket/prefix/`.
+    ///
+    /// This uses the destination credentials, so the same options as `copy` can be used.
+    #[arg(required = true)]
+    pub destination: String,
+}
+
+impl Doctor {
+    /// Perform the doctor sub command from the args.
+    pub async fn doctor(self, client: S3Client) -> Result<DoctorStats> {
+        let mut task = DoctorTaskBuilder::default()
+            .with_destination(self.destination.to_string())
+            .with_client(client)
+            .build()?;
+
+        let bucket = task.bucket_config().await;
+        Ok(DoctorStats::new(
+            self.destination,
+            bucket,
+            task.api_errors(),
+        ))
+    }
+}
+
+/// The split subcommand components.
+#[derive(Debug, Args)]
+pub struct Split {
+    /// The object to split.
+    #[arg(required = true)]
+    pub source: String,
+    /// The destination of the pieces. Pieces are named by appending `.partNN` to this, and a
+    /// manifest listing the pieces in order is written to `<DESTINATION>.manifest.json`.
+    #[arg(required = true)]
+    pub destination: String,
+    /// The maximum size of each piece, e.g. `4gib`. Every piece except the last has this size.
+    #[arg(long, required = true, env = "COPYRITE_PIECE_SIZE", value_parser = |s: &str| parse_size(s))]
+    pub piece_size: u64,
+    /// The checksums to record for each piece in the manifest. The checksums are read back
+    /// from the written pieces.
+    #[arg(value_delimiter = ',', short, long, default_value = "md5")]
+    pub checksum: Vec<ChecksumArg>,
+    /// The number of pieces to copy at the same time.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+}
+
+impl Split {
+    /// Perform the split sub command from the args.
+    pub async fn split(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        optimization: Optimization,
+    ) -> Result<SplitStats> {
+        let (manifest, split) = SplitTaskBuilder::default()
+            .with_source(self.source)
+            .with_destination(self.destination)
+            .with_source_client(source_client)
+            .with_destination_client(destination_client)
+            .with_piece_size(self.piece_size)
+            .with_checksums(ChecksumArg::ctxs(&self.checksum))
+            .with_concurrency(self.concurrency)
+            .with_capacity(optimization.channel_capacity)
+            .build()?
+            .run()
+            .await?;
+
+        Ok(SplitStats::new(manifest, split))
+    }
+}
+
+/// The concat subcommand components.
+#[derive(Debug, Args)]
+pub struct Concat {
+    /// The S3 objects to concatenate, in order, e.g. `s3://bucket/file.part01`. Every source
+    /// except the last must be at least 5 MiB.
+    #[arg(required = true, num_args = 1..)]
+    pub sources: Vec<String>,
+    /// The S3 object to write the concatenated sources to.
+    #[arg(required = true)]
+    pub destination: String,
+    /// The number of parts to copy at the same time.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+}
+
+impl Concat {
+    /// Perform the concat sub command from the args.
+    pub async fn concat(self, client: S3Client, optimization: Optimization) -> Result<ConcatStats> {
+        let output = ConcatTaskBuilder::default()
+            .with_sources(self.sources.clone())
+            .with_destination(self.destination.to_string())
+            .with_client(client)
+            .with_concurrency(self.concurrency)
+            .with_capacity(optimization.channel_capacity)
+            .build()?
+            .run()
+            .await?;
+
+        Ok(ConcatStats::new(self.sources, self.destination, output))
+    }
+}
+
+/// The audit subcommand components.
+#[derive(Debug, Args)]
+pub struct Audit {
+    /// The S3 prefix of the objects to audit, e.g. `s3://bucket/prefix/`.
+    #[arg(required = true)]
+    pub prefix: String,
+    /// The file that the progress of the audit is saved to.
+    ///
+    /// If the file exists, the audit resumes from it rather than listing the prefix again.
+    #[arg(long, required = true, env = "COPYRITE_STATE_FILE")]
+    pub state_file: PathBuf,
+    /// The maximum number of restores to request each time the audit runs or polls.
+    #[arg(long, env = "COPYRITE_BATCH_SIZE", default_value_t = 100)]
+    pub batch_size: usize,
+    /// The number of days that restored copies of archived objects remain available.
+    #[arg(long, env = "COPYRITE_RESTORE_DAYS", default_value_t = 1)]
+    pub restore_days: i32,
+    /// The retrieval tier of restores, which trades off how quickly archived objects can be
+    /// verified against the cost of restoring them.
+    ///
+    /// `DEEP_ARCHIVE` objects do not support the `expedited` tier. The tier of each restore is
+    /// recorded in the state file along with the status of the request.
+    #[arg(long, env = "COPYRITE_RESTORE_TIER", default_value = "standard")]
+    pub restore_tier: RestoreTier,
+    /// Keep polling until all objects have been verified rather than exiting after one pass.
+    #[arg(long, env = "COPYRITE_WAIT")]
+    pub wait: bool,
+    /// How often to check whether restores have completed when using `--wait`.
+    #[arg(long, env = "COPYRITE_POLL_INTERVAL", default_value = "1h")]
+    pub poll_interval: Duration,
+    /// Only audit objects that have not been verified within this duration, e.g. `90d`.
+    ///
+    /// Verified objects record the time in the `last-verified-at` field of their sums file, so
+    /// periodic audits skip objects that were verified recently. This only applies when listing
+    /// the prefix, and not when resuming from an existing state file.
+    #[arg(long, env = "COPYRITE_OLDER_THAN")]
+    pub older_than: Option<Duration>,
+    /// Fail objects that were modified since they were last verified.
+    ///
+    /// Verified objects record their `ETag` and version id in the `verified-version` field of
+    /// their sums file. With this option, an object whose current `ETag` or version id differs
+    /// is failed rather than verified again.
+    #[arg(long, env = "COPYRITE_ASSERT_IMMUTABLE")]
+    pub assert_immutable: bool,
+    /// Compare verified CSV and JSON objects with their copies under another S3 prefix using
+    /// S3 Select, e.g. `s3://replica-bucket/prefix/`.
+    ///
+    /// The row counts of the objects are compared without downloading them, which is a
+    /// lightweight check that the copies have the same contents. CSV objects must have a header
+    /// row. Objects are recognized by a `.csv`, `.json`, `.jsonl` or `.ndjson` extension,
+    /// optionally followed by `.gz` or `.bz2`.
+    #[arg(long, env = "COPYRITE_SELECT_COMPARE")]
+    pub select_compare: Option<String>,
+    /// The columns to hash when comparing objects with `--select-compare`, in addition to
+    /// counting their rows. Can be specified multiple times or comma-separated.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires = "select_compare",
+        env = "COPYRITE_SELECT_COLUMNS"
+    )]
+    pub select_columns: Vec<String>,
+}
+
+impl Audit {
+    /// Perform the audit sub command from the args. Each object prints its own stats once
+    /// its audit completes.
+    pub async fn audit(
+        self,
+        client: S3Client,
+        optimization: Optimization,
+        pretty_json: bool,
+    ) -> Result<()> {
+        let mut builder = AuditTaskBuilder::default()
+            .with_prefix(self.prefix.to_string())
+            .with_client(client)
+            .with_state_file(self.state_file.clone())
+            .with_batch_size(self.batch_size)
+            .with_restore_days(self.restore_days)
+            .with_restore_tier(self.restore_tier)
+            .with_capacity(optimization.channel_capacity)
+            .with_assert_immutable(self.assert_immutable)
+            .set_select_compare(self.select_compare.clone())
+            .with_select_columns(self.select_columns.clone());
+        if let Some(older_than) = self.older_than {
+            builder = builder.with_older_than(*older_than);
+        }
+        let mut task = builder.build().await?;
+
+        loop {
+            for index in task.step().await? {
+                let stats = AuditStats::from(&task.state().objects()[index]);
+                Command::print_stats(&stats, pretty_json, false)?;
+            }
+
+            if task.is_complete() {
+                let failed = task
+                    .state()
+                    .objects()
+                    .iter()
+                    .filter(|object| object.status() == AuditStatus::Failed)
+                    .count();
+
+                return if failed == 0 {
+                    Ok(())
+                } else {
+                    Err(CopyError(format!("failed to verify {} objects", failed)))
+                };
+            }
+
+            // Restores can take hours, so the audit can be resumed later from the state file.
+            if !self.wait {
+                return Ok(());
+            }
+
+            sleep(*self.poll_interval).await;
+        }
+    }
+}
+
+/// The watch subcommand components.
+#[derive(Debug, Args)]
+pub struct Watch {
+    /// The local directory to watch for new or modified files.
+    #[arg(required = true)]
+    pub directory: String,
+    /// The destination prefix to copy files to. Each file is copied to the prefix joined with
+    /// its path relative to the watched directory.
+    #[arg(required = true)]
+    pub destination: String,
+    /// How long a file's size and modification time must remain unchanged before it is copied.
+    ///
+    /// This avoids copying files that are still being written, e.g. by a sequencing instrument.
+    #[arg(long, env = "COPYRITE_STABLE_FOR", default_value = "30s")]
+    pub stable_for: Duration,
+    /// How often to scan the directory for new or modified files.
+    ///
+    /// The directory is polled rather than using filesystem events so that network mounts are
+    /// supported.
+    #[arg(long, env = "COPYRITE_POLL_INTERVAL", default_value = "5s")]
+    pub poll_interval: Duration,
+    /// The number of times to copy a file before giving up on it until it changes.
+    ///
+    /// A file that fails to copy is retried after `--retry-backoff`, and the wait doubles after
+    /// each failure, up to an hour. With `--once`, a file that fails is not retried.
+    #[arg(long, env = "COPYRITE_MAX_ATTEMPTS", default_value_t = 5, value_parser = value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+    /// How long to wait before retrying a file that failed to copy for the first time.
+    #[arg(long, env = "COPYRITE_RETRY_BACKOFF", default_value = "30s")]
+    pub retry_backoff: Duration,
+    /// Exit once all files in the directory have been copied rather than watching indefinitely.
+    #[arg(long, env = "COPYRITE_ONCE")]
+    pub once: bool,
+    /// The copy mode.
+    #[arg(long, env = "COPYRITE_COPY_MODE", default_value = "server-side")]
+    pub copy_mode: CopyMode,
+    /// The number of simultaneous copy tasks to run when using multipart copies.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+    /// Do not check the checksums of the copied files after copying.
+    #[arg(long, env = "COPYRITE_NO_CHECK")]
+    pub no_check: bool,
+    /// Copy hard links from the destination of the file they link to rather than reading the
+    /// same contents again.
+    ///
+    /// Hard links are always recorded in the stats of each copy. With this option, only the
+    /// first link to a file is read from the directory, and other links are copied from its
+    /// destination, which is a server-side copy for S3 destinations. This is only supported on
+    /// Unix platforms.
+    #[arg(long, env = "COPYRITE_DEDUPE_HARD_LINKS")]
+    pub dedupe_hard_links: bool,
+    /// A shell command to run before each object is copied. See `copy --help` for the
+    /// environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_PRE_COPY_CMD")]
+    pub pre_copy_cmd: Option<String>,
+    /// A shell command to run after each object is copied and checked. See `copy --help` for
+    /// the environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_POST_COPY_CMD")]
+    pub post_copy_cmd: Option<String>,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
+}
+
+impl Watch {
+    /// The destination for a file relative to the watched directory.
+    pub fn destination_for(&self, relative: &Path) -> String {
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        format!("{}/{}", self.destination.trim_end_matches('/'), relative)
+    }
+
+    /// Perform the watch sub command from the args. Each copy prints its own stats.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn watch(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        credentials: Credentials,
+        optimization: Optimization,
+        write_sums_file: bool,
+        pretty_json: bool,
+        ui: bool,
+    ) -> Result<()> {
+        let mut task = WatchTaskBuilder::default()
+            .with_directory(PathBuf::from(&self.directory))
+            .with_stable_for(*self.stable_for)
+            .with_max_attempts(self.max_attempts)
+            .with_retry_backoff(*self.retry_backoff)
+            .build()
+            .await?;
+
+        let mut failed = 0;
+        loop {
+            for relative in task.scan().await? {
+                let hard_link_of = task.hard_link_of(&relative).map(Path::to_path_buf);
+                let mut copy = Copy {
+                    copy_mode: self.copy_mode,
+                    concurrency: self.concurrency,
+                    no_check: self.no_check,
+                    // Local destinations need the same directory structure as the watched
+                    // directory.
+                    create_destination: Some(CreateDestination::Directories),
+                    pre_copy_cmd: self.pre_copy_cmd.clone(),
+                    post_copy_cmd: self.post_copy_cmd.clone(),
+                    ..Copy::for_object(
+                        task.directory()
+                            .join(&relative)
+                            .to_string_lossy()
+                            .to_string(),
+                        self.destination_for(&relative),
+                        self.deadline.clone(),
+                    )
+                };
+                let copy_source_client = copy.dedupe_hard_link(
+                    hard_link_of
+                        .as_ref()
+                        .filter(|_| self.dedupe_hard_links)
+                        .map(|original| self.destination_for(original)),
+                    &source_client,
+                    &destination_client,
+                );
+                let hard_link_of = hard_link_of
+                    .map(|original| task.directory().join(original).display().to_string());
+
+                match copy
+                    .copy(
+                        copy_source_client,
+                        destination_client.clone(),
+                        credentials.clone(),
+                        optimization.clone(),
+                        write_sums_file,
+                        ui,
+                    )
+                    .await
+                {
+                    Ok(stats) => {
+                        let stats = stats.set_hard_link_of(hard_link_of);
+                        Command::print_stats(&stats, pretty_json, ui)?;
+                        task.mark_processed(&relative);
+                    }
+                    Err(err) => {
+                        let err = err.set_hard_link_of(hard_link_of);
+                        Command::print_stats(&err, pretty_json, false)?;
+                        // Failed copies are retried after a backoff unless only running once.
+                        let gave_up = if self.once {
+                            task.mark_failed(&relative);
+                            true
+                        } else {
+                            !task.retry_later(&relative)
+                        };
+                        if gave_up {
+                            failed += 1;
+                        }
+                    }
+                }
+            }
+
+            // Files that are still pending are not copied once the timeout is reached.
+            if (self.once && !task.has_pending()) || self.deadline.is_cancelled() {
+                return if failed == 0 {
+                    Ok(())
+                } else {
+                    Err(CopyError(format!("failed to copy {} files", failed)))
+                };
+            }
+
+            sleep(*self.poll_interval).await;
+        }
+    }
+}
+
+/// The sync subcommand components.
+#[derive(Debug, Args)]
+pub struct Sync {
+    /// The source prefix or directory to sync from, e.g. `s3://bucket/prefix/`.
+    #[arg(required = true)]
+    pub source: String,
+    /// The destination prefix or directory to sync to. Each object is copied to the destination
+    /// joined with its path relative to the source.
+    #[arg(required = true)]
+    pub destination: String,
+    /// Only compare the sizes of objects that exist at the destination, rather than also
+    /// comparing their checksums.
+    ///
+    /// By default, objects with the same size are compared using existing sums files or
+    /// checksums read from the objects, which may require reading both objects.
+    #[arg(long, env = "COPYRITE_SIZE_ONLY")]
+    pub size_only: bool,
+    /// Delete objects at the destination that do not exist at the source, along with their
+    /// sums files. Orphaned sums files at the destination whose object no longer exists are then
+    /// collected as with `sums gc --delete`. They are listed separately in the stats and do not
+    /// count towards `--max-delete`.
+    ///
+    /// Objects are only deleted once every changed object has been copied and checked
+    /// successfully, so nothing is deleted if any copy fails.
+    #[arg(long, env = "COPYRITE_DELETE")]
+    pub delete: bool,
+    /// The maximum number of objects that `--delete` may delete. If more objects would be
+    /// deleted, the sync fails before copying or deleting anything.
+    #[arg(long, env = "COPYRITE_MAX_DELETE", requires = "delete")]
+    pub max_delete: Option<usize>,
+    /// Compare the source and destination and report what would change without copying,
+    /// deleting or writing sums files. With `--delete`, the objects and orphaned sums files that
+    /// would be deleted are listed in the stats.
+    #[arg(long, env = "COPYRITE_DRY_RUN")]
+    pub dry_run: bool,
+    /// Controls how tags are copied.
+    #[arg(long, env = "COPYRITE_TAG_MODE", default_value = "copy")]
+    pub tag_mode: MetadataCopy,
+    /// Controls how metadata is copied.
+    #[arg(long, env = "COPYRITE_METADATA_MODE", default_value = "copy")]
+    pub metadata_mode: MetadataCopy,
+    /// The copy mode.
+    #[arg(long, env = "COPYRITE_COPY_MODE", default_value = "server-side")]
+    pub copy_mode: CopyMode,
+    /// The part size to use when copying files using multipart uploads. This can be specified with
+    /// a size unit, e.g. 8mib.
+    #[arg(short, long, env = "COPYRITE_PART_SIZE", value_parser = |s: &str| parse_size(s))]
+    pub part_size: Option<u64>,
+    /// The number of simultaneous copy tasks to run when using multipart copies.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+    /// The storage class of destination objects when copying to S3.
+    #[arg(long, env = "COPYRITE_STORAGE_CLASS")]
+    pub storage_class: Option<String>,
+    /// Do not check the checksums of the copied files after copying.
+    #[arg(long, env = "COPYRITE_NO_CHECK")]
+    pub no_check: bool,
+    /// Copy hard links in a local source directory from the destination of the file they link
+    /// to rather than reading the same contents again. See `watch --help` for details.
+    #[arg(long, env = "COPYRITE_DEDUPE_HARD_LINKS")]
+    pub dedupe_hard_links: bool,
+    /// A shell command to run before each object is copied. See `copy --help` for the
+    /// environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_PRE_COPY_CMD")]
+    pub pre_copy_cmd: Option<String>,
+    /// A shell command to run after each object is copied and checked. See `copy --help` for
+    /// the environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_POST_COPY_CMD")]
+    pub post_copy_cmd: Option<String>,
+    #[command(flatten)]
+    pub snapshot: SnapshotArgs,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
+}
+
+impl Sync {
+    /// Create the copy for an object relative to the source. Changed objects have already been
+    /// compared, so the copy does not check the destination again before copying.
+    pub fn copy_for(&self, source: &str, destination: &str) -> Copy {
+        Copy {
+            tag_mode: self.tag_mode,
+            metadata_mode: self.metadata_mode,
+            copy_mode: self.copy_mode,
+            part_size: self.part_size,
+            concurrency: self.concurrency,
+            no_check: self.no_check,
+            no_skip: true,
+            storage_class: self.storage_class.clone(),
+            // Local destinations need the same directory structure as the source.
+            create_destination: Some(CreateDestination::Directories),
+            pre_copy_cmd: self.pre_copy_cmd.clone(),
+            post_copy_cmd: self.post_copy_cmd.clone(),
+            ..Copy::for_object(
+                source.to_string(),
+                destination.to_string(),
+                self.deadline.clone(),
+            )
+        }
+    }
+
+    /// Compare the checksums of an object that has the same size at the source and destination.
+    /// Objects that cannot be shown to be the same are treated as changed, so that they are
+    /// copied and checked again afterwards.
+    async fn compare_checksums(
+        copy: &Copy,
+        source_client: S3Client,
+        destination_client: S3Client,
+        optimization: Optimization,
+        write_sums_file: bool,
+    ) -> SyncDelta {
+        match copy
+            .copy_check(
+                source_client,
+                destination_client,
+                optimization,
+                false,
+                write_sums_file,
+            )
+            .await
+        {
+            Ok(check) if check.groups.len() == 1 => SyncDelta::Unchanged,
+            _ => SyncDelta::ChecksumMismatch,
+        }
+    }
+
+    /// Perform the sync sub command from the args. Each copy prints its own stats, followed by
+    /// the stats of the whole sync.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn sync(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        credentials: Credentials,
+        optimization: Optimization,
+        write_sums_file: bool,
+        pretty_json: bool,
+        ui: bool,
+    ) -> Result<()> {
+        let now = Instant::now();
+        let task = SyncTaskBuilder::default()
+            .with_source(self.source.to_string())
+            .with_destination(self.destination.to_string())
+            .set_source_client(Some(source_client.clone()))
+            .set_destination_client(Some(destination_client.clone()))
+            .build()?;
+
+        let plan = task.plan().await?;
+        if self.delete {
+            plan.check_max_delete(self.max_delete)?;
+        }
+
+        let source_paths = plan
+            .objects
+            .iter()
+            .map(|object| format!("{}{}", task.destination(), object.path))
+            .collect::<HashSet<_>>();
+        let mut stats = SyncStats::new(self.source.to_string(), self.destination.to_string())
+            .with_dry_run(self.dry_run);
+        // The objects which are the same at the destination as the source, which hard links
+        // can be copied from.
+        let mut synced = HashSet::new();
+        for object in plan.objects {
+            let mut copy = self.copy_for(
+                &format!("{}{}", task.source(), object.path),
+                &format!("{}{}", task.destination(), object.path),
+            );
+
+            let delta = match object.delta {
+                SyncDelta::SameSize if self.size_only => SyncDelta::Unchanged,
+                SyncDelta::SameSize => {
+                    Self::compare_checksums(
+                        &copy,
+                        source_client.clone(),
+                        destination_client.clone(),
+                        optimization.clone(),
+                        write_sums_file && !self.dry_run,
+                    )
+                    .await
+                }
+                delta => delta,
+            };
+            stats.push_delta(delta);
+            if !delta.is_changed() {
+                synced.insert(object.path);
+                continue;
+            }
+            if self.dry_run {
+                continue;
+            }
+
+            let copy_source_client = copy.dedupe_hard_link(
+                object
+                    .hard_link_of
+                    .as_ref()
+                    .filter(|original| self.dedupe_hard_links && synced.contains(*original))
+                    .map(|original| format!("{}{}", task.destination(), original)),
+                &source_client,
+                &destination_client,
+            );
+            let hard_link_of = object
+                .hard_link_of
+                .as_ref()
+                .map(|original| format!("{}{}", task.source(), original));
+
+            let result = copy
+                .copy(
+                    copy_source_client,
+                    destination_client.clone(),
+                    credentials.clone(),
+                    optimization.clone(),
+                    write_sums_file,
+                    ui,
+                )
+                .await
+                .map(|copy| copy.set_hard_link_of(hard_link_of.clone()))
+                .map_err(|err| Box::new(err.set_hard_link_of(hard_link_of)));
+            match &result {
+                Ok(copy) => {
+                    Command::print_stats(copy, pretty_json, ui)?;
+                    synced.insert(object.path);
+                }
+                Err(err) => Command::print_stats(err, pretty_json, false)?,
+            }
+            stats.push_copy(&result);
+        }
+
+        // Deleting is the last step so that a failed copy never leaves the destination with
+        // fewer objects than before.
+        let mut delete_error = None;
+        if self.delete && stats.n_failed == 0 {
+            for path in &plan.extraneous {
+                if self.dry_run {
+                    stats.push_deleted(format!("{}{}", task.destination(), path));
+                    continue;
+                }
+
+                match task.delete(path).await {
+                    Ok(location) => stats.push_deleted(location),
+                    Err(err) => {
+                        delete_error = Some(err);
+                        break;
+                    }
+                }
+            }
+
+            if delete_error.is_none() {
+                match GcTaskBuilder::default()
+                    .with_location(task.destination().to_string())
+                    .set_client(Some(destination_client.clone()))
+                    .with_delete(!self.dry_run)
+                    .build()?
+                    .run()
+                    .await
+                {
+                    // In a dry run, the objects of sums files that are copied from the source
+                    // do not exist yet, so their sums files are not orphaned.
+                    Ok(orphaned) => {
+                        stats = stats.with_deleted_sums_files(
+                            orphaned
+                                .into_iter()
+                                .filter(|sums_file| {
+                                    !source_paths.contains(&SumsFile::format_target_file(sums_file))
+                                })
+                                .collect(),
+                        )
+                    }
+                    Err(err) => delete_error = Some(err),
+                }
+            }
+        }
+
+        let mut stats = stats.with_elapsed(now.elapsed());
+        if !self.dry_run && delete_error.is_none() && stats.n_failed == 0 {
+            let mut synced = synced
+                .into_iter()
+                .map(|path| format!("{}{}", task.destination(), path))
+                .collect::<Vec<_>>();
+            synced.sort();
+            if let Some(path) = self.snapshot.write(&destination_client, synced).await? {
+                stats = stats.with_snapshot_manifest(path);
+            }
+        }
+
+        Command::print_stats(&stats, pretty_json, false)?;
+        if let Some(err) = delete_error {
+            Err(err)
+        } else if stats.n_failed == 0 {
+            Ok(())
+        } else {
+            Err(CopyError(format!(
+                "failed to sync {} of {} objects under {}{}",
+                stats.n_failed,
+                stats.n_objects,
+                self.source,
+                if self.delete {
+                    ", so no objects were deleted"
+                } else {
+                    ""
+                }
+            )))
+        }
+    }
+}
+
+/// Options for a snapshot manifest of the destinations, written once every object has been
+/// copied.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SnapshotArgs {
+    /// Write a snapshot manifest to this path after every object has been copied. The snapshot
+    /// lists the location, size, checksums, `ETag` and version id of each destination, and a
+    /// SHA-256 digest of its contents, so that it can be archived alongside the data.
+    ///
+    /// It is not written if any object fails. `copy` only supports this for a prefix or
+    /// directory, and `sync` does not write it in a dry run.
+    #[arg(long, env = "COPYRITE_SNAPSHOT_MANIFEST")]
+    pub snapshot_manifest: Option<PathBuf>,
+    /// Sign the snapshot manifest with an HMAC-SHA256 of its contents using this key.
+    #[arg(long, env = "COPYRITE_SNAPSHOT_KEY", requires = "snapshot_manifest")]
+    pub snapshot_key: Option<String>,
+    /// A file containing the key used to sign the snapshot manifest. This is an alternative to
+    /// `--snapshot-key` which avoids passing the value on the command line.
+    #[arg(
+        long,
+        env = "COPYRITE_SNAPSHOT_KEY_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        requires = "snapshot_manifest",
+        conflicts_with = "snapshot_key"
+    )]
+    pub snapshot_key_file: Option<String>,
+}
+
+impl SnapshotArgs {
+    /// Write the snapshot manifest of the destination locations if a path is set, returning
+    /// the path that it was written to.
+    pub async fn write(&self, client: &S3Client, locations: Vec<String>) -> Result<Option<String>> {
+        let Some(path) = &self.snapshot_manifest else {
+            return Ok(None);
+        };
+
+        SnapshotManifestBuilder::default()
+            .set_client(Some(client.clone()))
+            .set_key(self.snapshot_key.clone().or(self.snapshot_key_file.clone()))
+            .with_locations(locations)
+            .build()
+            .await?
+            .write(path)
+            .await?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    }
+}
+
+/// The manifest subcommand components.
+#[derive(Debug, Args)]
+pub struct Manifest {
+    /// The manifest file listing the objects to copy.
+    ///
+    /// This is a CSV file with a header row containing `source` and `destination` columns, and
+    /// optionally `part_size`, `concurrency`, `storage_class` and `tag_mode` columns. Values in
+    /// the optional columns override the options for that row, and empty values use the
+    /// options below, e.g.:
+    ///
+    /// source,destination,part_size,storage_class
+    /// s3://bucket/a,s3://archive/a,64mib,DEEP_ARCHIVE
+    /// s3://bucket/b,s3://archive/b,,
+    #[arg(required = true, verbatim_doc_comment)]
+    pub manifest: PathBuf,
+    /// Controls how tags are copied.
+    #[arg(long, env = "COPYRITE_TAG_MODE", default_value = "copy")]
+    pub tag_mode: MetadataCopy,
+    /// Controls how metadata is copied.
+    #[arg(long, env = "COPYRITE_METADATA_MODE", default_value = "copy")]
+    pub metadata_mode: MetadataCopy,
+    /// The copy mode.
+    #[arg(long, env = "COPYRITE_COPY_MODE", default_value = "server-side")]
+    pub copy_mode: CopyMode,
+    /// The part size to use when copying files using multipart uploads. This can be specified with
+    /// a size unit, e.g. 8mib.
+    #[arg(short, long, env = "COPYRITE_PART_SIZE", value_parser = |s: &str| parse_size(s))]
+    pub part_size: Option<u64>,
+    /// The number of simultaneous copy tasks to run when using multipart copies.
+    #[arg(long, env = "COPYRITE_CONCURRENCY", default_value_t = 10)]
+    pub concurrency: usize,
+    /// The storage class of destination objects when copying to S3.
+    #[arg(long, env = "COPYRITE_STORAGE_CLASS")]
+    pub storage_class: Option<String>,
+    /// Do not check the checksums of the copied files after copying.
+    #[arg(long, env = "COPYRITE_NO_CHECK")]
+    pub no_check: bool,
+    /// Always perform the copy and do not skip if sums match.
+    #[arg(long, env = "COPYRITE_NO_SKIP")]
+    pub no_skip: bool,
+    /// Create the destination of each row if it does not exist. See `copy --help` for the
+    /// values.
+    #[arg(
+        long,
+        env = "COPYRITE_CREATE_DESTINATION",
+        num_args = 0..=1,
+        default_missing_value = "directories",
+        require_equals = true
+    )]
+    pub create_destination: Option<CreateDestination>,
+    /// A shell command to run before each object is copied. See `copy --help` for the
+    /// environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_PRE_COPY_CMD")]
+    pub pre_copy_cmd: Option<String>,
+    /// A shell command to run after each object is copied and checked. See `copy --help` for
+    /// the environment variables that describe the object.
+    #[arg(long, env = "COPYRITE_POST_COPY_CMD")]
+    pub post_copy_cmd: Option<String>,
+    /// The order in which rows are copied. Scheduling by size reads the size of every source
+    /// before starting, and rows with sources that cannot be read are copied last.
+    #[arg(long, env = "COPYRITE_SCHEDULE_STRATEGY", default_value = "fifo")]
+    pub schedule_strategy: ScheduleStrategy,
+    /// The number of rows to copy at the same time. Rows start in the order of the schedule
+    /// strategy, and a new row starts as soon as any running copy finishes.
+    #[arg(long, env = "COPYRITE_OBJECT_CONCURRENCY", default_value_t = 1, value_parser = value_parser!(u64).range(1..))]
+    pub object_concurrency: u64,
+    #[command(flatten)]
+    pub snapshot: SnapshotArgs,
+    /// Cancelled when the `--timeout` is reached.
+    #[arg(skip)]
+    pub deadline: CancellationToken,
+}
+
+impl Manifest {
+    /// Create the copy for a manifest row, using the options from the row if they are set.
+    pub fn copy_for(&self, row: &ManifestRow) -> Copy {
+        Copy {
+            tag_mode: row.tag_mode.unwrap_or(self.tag_mode),
+            metadata_mode: self.metadata_mode,
+            copy_mode: self.copy_mode,
+            part_size: row.part_size.or(self.part_size),
+            concurrency: row.concurrency.unwrap_or(self.concurrency),
+            no_check: self.no_check,
+            no_skip: self.no_skip,
+            storage_class: row.storage_class.clone().or(self.storage_class.clone()),
+            create_destination: self.create_destination,
+            pre_copy_cmd: self.pre_copy_cmd.clone(),
+            post_copy_cmd: self.post_copy_cmd.clone(),
+            ..Copy::for_object(
+                row.source.to_string(),
+                row.destination.to_string(),
+                self.deadline.clone(),
+            )
+        }
+    }
+
+    /// The source size of each row of the manifest, if it is needed to schedule the row or to
+    /// check the space of a local destination.
+    async fn source_sizes(
+        &self,
+        manifest: &task::manifest::Manifest,
+        source_client: &S3Client,
+    ) -> Result<Vec<Option<u64>>> {
+        let mut sizes = Vec::with_capacity(manifest.rows().len());
+        for row in manifest.rows() {
+            if self.schedule_strategy == ScheduleStrategy::Fifo
+                && !Provider::try_from(row.destination.as_str())?.is_file()
+            {
+                sizes.push(None);
+                continue;
+            }
+
+            // Sources that cannot be read fail when their row is copied instead.
+            let size = match ObjectSumsBuilder::default()
+                .set_client(Some(source_client.clone()))
+                .build(row.source.to_string())
+                .await
+            {
+                Ok(mut sums) => sums.file_size().await.ok().flatten(),
+                Err(_) => None,
+            };
+            sizes.push(size);
+        }
+
+        Ok(sizes)
+    }
+
+    /// The space required by each row of the manifest which copies to a local destination.
+    async fn required_space(
+        manifest: &task::manifest::Manifest,
+        sizes: &[Option<u64>],
+    ) -> Result<Vec<Option<RequiredSpace>>> {
+        let mut required = Vec::with_capacity(manifest.rows().len());
+        for (row, size) in manifest.rows().iter().zip(sizes) {
+            let (Provider::File { file }, Some(size)) =
+                (Provider::try_from(row.destination.as_str())?, size)
+            else {
+                required.push(None);
+                continue;
+            };
+
+            required.push(RequiredSpace::new(Path::new(&file), *size).await?);
+        }
+
+        Ok(required)
+    }
+
+    /// Perform the manifest sub command from the args. Each copy prints its own stats, followed
+    /// by the stats of the whole schedule.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn copy(
+        self,
+        source_client: S3Client,
+        destination_client: S3Client,
+        credentials: Credentials,
+        optimization: Optimization,
+        write_sums_file: bool,
+        pretty_json: bool,
+        ui: bool,
+    ) -> Result<()> {
+        let now = Instant::now();
+        let manifest = task::manifest::Manifest::read(&self.manifest).await?;
+        let sizes = self.source_sizes(&manifest, &source_client).await?;
+        let required = Self::required_space(&manifest, &sizes).await?;
+        let order = self.schedule_strategy.order(&sizes);
+
+        let mut stats = ManifestStats::new(
+            self.schedule_strategy,
+            self.object_concurrency,
+            u64::try_from(manifest.rows().len())?,
+        );
+        let (this, order, required, stopped) = (&self, &order, &required, &AtomicBool::new(false));
+        let mut copies = stream::iter(order.iter().enumerate())
+            .map(|(position, index)| {
+                let row = &manifest.rows()[*index];
+                let (source_client, destination_client, credentials, optimization) = (
+                    source_client.clone(),
+                    destination_client.clone(),
+                    credentials.clone(),
+                    optimization.clone(),
+                );
+                async move {
+                    if stopped.load(Ordering::Relaxed) {
+                        return None;
+                    }
+
+                    // Check the space for all rows that have not started before each copy, so
+                    // that the manifest fails early rather than after filling up the destination.
+                    if let Err(err) =
+                        check_space(order[position..].iter().flat_map(|index| &required[*index]))
+                    {
+                        stopped.store(true, Ordering::Relaxed);
+                        return Some(Err(err));
+                    }
+
+                    let result = this
+                        .copy_for(row)
+                        .copy(
+                            source_client,
+                            destination_client,
+                            credentials,
+                            optimization,
+                            write_sums_file,
+                            ui,
+                        )
+                        .await;
+                    Some(Ok((row, result)))
+                }
+            })
+            .buffer_unordered(usize::try_from(self.object_concurrency)?);
+
+        // Copies that are already running when a row stops the manifest are allowed to finish.
+        let mut copied = vec![];
+        let mut stop = None;
+        while let Some(result) = copies.next().await {
+            match result {
+                Some(Ok((row, result))) => {
+                    match &result {
+                        Ok(copy) => {
+                            Command::print_stats(copy, pretty_json, ui)?;
+                            copied.push(row.destination.to_string());
+                        }
+                        Err(err) => Command::print_stats(err, pretty_json, false)?,
+                    }
+                    stats.push_copy(&result);
+                }
+                Some(Err(err)) => {
+                    stop.get_or_insert(err);
+                }
+                None => {}
+            }
+        }
+
+        let mut stats = stats.with_elapsed(now.elapsed());
+        if stop.is_none()
+            && stats.n_failed == 0
+            && let Some(path) = self
+                .snapshot
+                .write(&destination_client, copied.clone())
+                .await?
+        {
+            stats = stats.with_snapshot_manifest(path);
+        }
+
+        Command::print_stats(&stats, pretty_json, false)?;
+        match stop {
+            Some(InsufficientSpace(err)) => Err(InsufficientSpace(format!(
+                "{}, stopping after copying {} of {} manifest rows{}",
+                err,
+                copied.len(),
+                manifest.rows().len(),
+                if copied.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(": {}", copied.join(", "))
+                }
+            ))),
+            Some(err) => Err(err),
+            None if stats.n_failed == 0 => Ok(()),
+            None => Err(CopyError(format!(
+                "failed to copy {} of {} manifest rows",
+                stats.n_failed,
+                manifest.rows().len()
+            ))),
+        }
+    }
+}
+
+/// The sums subcommand components.
+#[derive(Debug, Args)]
+pub struct Sums {
+    /// The sums subcommands.
+    #[command(subcommand)]
+    pub commands: SumsSubcommands,
+}
+
+/// The subcommands for working with checksums directly.
+#[derive(Subcommand, Debug)]
+pub enum SumsSubcommands {
+    /// Infer the part sizes that could have produced an S3 ETag. This is useful when only the
+    /// ETag and size of an object are known, e.g. when provided by a third party.
+    InferEtag(#[arg(flatten)] InferEtag),
+    /// Report which checksums of an object's sums can be reproduced natively by a target
+    /// provider after migrating the object, and which would have to be computed by reading it.
+    /// This helps to choose the checksum to trust after a migration.
+    Compat(#[arg(flatten)] Compat),
+    /// Find sums files whose target object no longer exists under an S3 prefix or local
+    /// directory, e.g. after objects were deleted manually. The orphaned sums files are listed,
+    /// and deleted with `--delete`.
+    Gc(#[arg(flatten)] Gc),
+    /// Write the MD5 and byte range of each part of an object to a part manifest, for legacy
+    /// submission portals which validate uploads part by part, e.g. EGA-style part manifests.
+    /// The object is read to compute the part MD5s.
+    ExportParts(#[arg(flatten)] ExportParts),
+}
+
+/// The export-parts subcommand components.
+#[derive(Debug, Args)]
+pub struct ExportParts {
+    /// The object to export the parts of.
+    #[arg(required = true)]
+    pub input: String,
+    /// The local path to write the part manifest to.
+    ///
+    /// The manifest has comment lines describing the object, followed by one tab-separated
+    /// line per part with the part number, first byte, last byte and MD5.
+    #[arg(long, required = true, env = "COPYRITE_PARTS_OUTPUT")]
+    pub output: PathBuf,
+    /// The size of each part. This can be specified with a size unit, e.g. 8mib.
+    #[arg(short, long, env = "COPYRITE_PART_SIZE", default_value = "8mib", value_parser = |s: &str| parse_size(s))]
+    pub part_size: u64,
+}
+
+impl ExportParts {
+    /// Compute the part MD5s of the object and write the part manifest.
+    pub async fn export(
+        self,
+        client: S3Client,
+        optimization: Optimization,
+    ) -> Result<ExportPartsStats> {
+        if self.part_size == 0 {
+            return Err(ParseError(
+                "part size must be greater than zero".to_string(),
+            ));
+        }
+
+        let manifest = PartManifest::generate(
+            self.input,
+            self.part_size,
+            Some(client),
+            optimization.channel_capacity,
+        )
+        .await?;
+        tokio::fs::write(&self.output, manifest.to_legacy_string()).await?;
+
+        ExportPartsStats::new(
+            self.output.to_string_lossy().to_string(),
+            self.part_size,
+            manifest,
+        )
+    }
+}
+
+/// The gc subcommand components.
+#[derive(Debug, Args)]
+pub struct Gc {
+    /// The S3 prefix or local directory to scan for sums files, e.g. `s3://bucket/prefix/`.
+    #[arg(required = true)]
+    pub location: String,
+    /// Delete the orphaned sums files rather than only listing them.
+    #[arg(long, env = "COPYRITE_DELETE")]
+    pub delete: bool,
+}
+
+impl Gc {
+    /// Find and optionally delete the orphaned sums files.
+    pub async fn gc(self, client: S3Client) -> Result<SumsGcStats> {
+        let orphaned = GcTaskBuilder::default()
+            .with_location(self.location.to_string())
+            .set_client(Some(client))
+            .with_delete(self.delete)
+            .build()?
+            .run()
+            .await?;
+
+        Ok(SumsGcStats {
+            location: self.location,
+            orphaned,
+            deleted: self.delete,
+        })
+    }
+}
+
+/// The checksum subcommand components.
+#[derive(Debug, Args)]
+pub struct Checksums {
+    /// The checksum subcommands.
+    #[command(subcommand)]
+    pub commands: ChecksumSubcommands,
+}
+
+/// The subcommands for working with checksum names.
+#[derive(Subcommand, Debug)]
+pub enum ChecksumSubcommands {
+    /// Parse a checksum such as `md5-aws-8mib-16mib`, printing its canonical form and the parts
+    /// that it splits a file into. Invalid checksums report the position of the component that
+    /// could not be parsed.
+    Parse(#[arg(flatten)] ParseChecksum),
+}
+
+/// The checksum parse subcommand components.
+#[derive(Debug, Args)]
+pub struct ParseChecksum {
+    /// The checksum to parse.
+    #[arg(required = true)]
+    pub spec: String,
+    /// The size of the file to compute the part layout for. This is required for checksums that
+    /// specify a number of parts, e.g. `md5-aws-10`.
+    #[arg(long, env = "COPYRITE_FILE_SIZE", value_parser = |s: &str| parse_size(s))]
+    pub file_size: Option<u64>,
+}
+
+impl ParseChecksum {
+    /// Parse the checksum and compute its part layout if the file size is known.
+    pub fn parse(self) -> Result<ChecksumParseStats> {
+        let mut ctx = Ctx::from_str(&self.spec).map_err(|err| {
+            let position = Ctx::invalid_position(&self.spec).unwrap_or_default();
+            // Report why the part sizes are invalid rather than the fallback to a regular
+            // checksum.
+            let err = if self.spec.contains("-aws") || self.spec.starts_with("aws-etag") {
+                AWSETagCtx::from_str(&self.spec).err().unwrap_or(err)
+            } else {
+                err
+            };
+            let err = match err {
+                copyrite_core::error::Error::ParseError(err) => err,
+                err => err.to_string(),
+            };
+            ParseError(format!(
+                "invalid checksum `{}` at position {} (`{}`): {}",
+                self.spec,
+                position,
+                &self.spec[position..],
+                err
+            ))
+        })?;
+
+        if ctx.is_part_number() && self.file_size.is_none() {
+            return Err(ParseError(
+                "`--file-size` is required for checksums that specify a number of parts"
+                    .to_string(),
+            ));
+        }
+
+        let parts = match (&mut ctx, self.file_size) {
+            (Ctx::AWSEtag(aws_etag), Some(file_size)) => {
+                let parts = aws_etag.part_layout(file_size)?;
+                aws_etag.set_file_size(Some(file_size));
+                aws_etag.update_part_sizes();
+                Some(parts)
+            }
+            _ => None,
+        };
+
+        Ok(ChecksumParseStats {
+            canonical: ctx.to_string(),
+            spec: self.spec,
+            file_size: self.file_size,
+            n_parts: parts
+                .as_ref()
+                .and_then(|parts| parts.last())
+                .map(|range| range.last_part),
+            parts,
+        })
+    }
+}
+
+/// The stats subcommand components.
+#[derive(Debug, Args)]
+pub struct Stats {
+    /// The stats subcommands.
+    #[command(subcommand)]
+    pub commands: StatsSubcommands,
+}
+
+/// The subcommands for working with stats output.
+#[derive(Subcommand, Debug)]
+pub enum StatsSubcommands {
+    /// Compare the stats output of two runs, e.g. nightly syncs, reporting throughput
+    /// regressions, new and resolved errors, and changed object counts.
+    Diff(#[arg(flatten)] Diff),
+}
+
+/// The diff subcommand components.
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// The stats output of the earlier run.
+    #[arg(required = true)]
+    pub before: PathBuf,
+    /// The stats output of the later run.
+    #[arg(required = true)]
+    pub after: PathBuf,
+    /// The fraction that throughput can decrease by before it is reported as a regression,
+    /// e.g. `0.1` for 10%.
+    #[arg(long, default_value_t = DEFAULT_REGRESSION_THRESHOLD, env = "COPYRITE_REGRESSION_THRESHOLD")]
+    pub regression_threshold: f64,
+}
+
+impl Diff {
+    /// Compare the stats of the two runs.
+    pub async fn diff(self) -> Result<StatsDiffStats> {
+        if self.regression_threshold.is_nan() || self.regression_threshold < 0.0 {
+            return Err(ParseError(
+                "the regression threshold must not be negative".to_string(),
+            ));
+        }
+
+        let before = RunSummary::read(&self.before).await?;
+        let after = RunSummary::read(&self.after).await?;
+
+        Ok(StatsDiffStats {
+            regression_threshold: self.regression_threshold,
+            diff: RunDiff::new(before, after, self.regression_threshold),
+        })
+    }
+}
+
+/// The compat subcommand components.
+#[derive(Debug, Args)]
+pub struct Compat {
+    /// The object or sums file to check. Sums are read in the same way as `check`, so S3 objects
+    /// use their metadata as well as any `.sums` file.
+    #[arg(required = true)]
+    pub input: String,
+    /// The provider that the object is migrated to.
+    #[arg(long, required = true, env = "COPYRITE_TARGET")]
+    pub target: Target,
+}
+
+impl Compat {
+    /// Determine the compatibility of the sums with the target.
+    pub async fn compat(self, client: S3Client) -> Result<CompatStats> {
+        let input = SumsFile::format_target_file(&self.input);
+        let sums = ObjectSumsBuilder::default()
+            .set_client(Some(client))
+            .build(input.to_string())
+            .await?
+            .sums_file()
+            .await?
+            .ok_or_else(|| CheckError(format!("no sums found for {}", input)))?;
+
+        Ok(CompatStats::new(input, self.target, &sums))
+    }
+}
+
+/// The infer-etag subcommand components.
+#[derive(Debug, Args)]
+pub struct InferEtag {
+    /// The ETag of the object, including the `-N` suffix which specifies the number of parts.
+    #[arg(required = true)]
+    pub etag: String,
+    /// The size of the object.
+    #[arg(required = true, value_parser = |s: &str| parse_size(s))]
+    pub size: u64,
+}
+
+impl InferEtag {
+    /// Infer the candidate `md5-aws-*` contexts for the ETag. Part sizes from
+    /// `PREFERRED_PART_SIZES` are tried first, followed by the smallest part size that
+    /// produces the number of parts.
+    pub fn infer(self) -> Result<InferEtagStats> {
+        let (_, n_parts) = self
+            .etag
+            .trim_matches('"')
+            .rsplit_once('-')
+            .ok_or_else(|| {
+                ParseError("expected a multipart ETag with a `-N` part suffix".to_string())
+            })?;
+        let n_parts = u64::from_str(n_parts)
+            .map_err(|err| ParseError(format!("failed to parse parts from ETag: {}", err)))?;
+
+        let (min_part_size, max_part_size) = AWSETagCtx::part_size_range(n_parts, self.size)
+            .ok_or_else(|| {
+                ParseError(format!(
+                    "no part size splits {} bytes into {} parts",
+                    self.size, n_parts
+                ))
+            })?;
+
+        let mut part_sizes = AWSETagCtx::infer_part_sizes(n_parts, self.size);
+        if !part_sizes.contains(&min_part_size) {
+            part_sizes.push(min_part_size);
+        }
+
+        let candidates = part_sizes
+            .into_iter()
+            .map(|part_size| {
+                AWSETagCtx::new(
+                    StandardCtx::md5(),
+                    PartMode::PartSizes(vec![part_size]),
+                    Some(self.size),
+                )
+                .to_string()
+            })
+            .collect();
+
+        Ok(InferEtagStats {
+            etag: self.etag,
+            size: self.size,
+            n_parts,
+            min_part_size,
+            max_part_size,
+            candidates,
+        })
+    }
+}
+
+/// The subcommands for copyrite.
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Generate a checksum.
+    Generate(#[arg(flatten)] Generate),
+    /// Confirm a set of files is identical. This returns sets of files that are identical.
+    /// Which means that more than two files can be checked at the same time.
+    Check(#[arg(flatten)] Check),
+    /// Copy a file to a location. This command can also simultaneously generate checksums, and
+    /// supports all options for generate.
+    Copy(#[arg(flatten)] Copy),
+    /// Watch a local directory and copy new or modified files to a destination prefix once
+    /// they have stopped changing.
+    Watch(#[arg(flatten)] Watch),
+    /// Copy the objects under a source prefix or directory that are missing or different at a
+    /// destination prefix or directory. Objects with the same size are compared using their
+    /// checksums.
+    Sync(#[arg(flatten)] Sync),
+    /// Update the tags, metadata or storage class of an S3 object in place without copying its
+    /// data to another location. The existing checksums of the object are re-verified afterwards.
+    #[command(alias = "touch")]
+    Retag(#[arg(flatten)] Retag),
+    /// Move the objects under an S3 prefix to another storage class, confirming that their
+    /// ETags and sums are unchanged. This is a verified alternative to lifecycle rules.
+    Transition(#[arg(flatten)] Transition),
+    /// Copy the objects listed in a manifest file. Each row can override the options used to
+    /// copy it.
+    Manifest(#[arg(flatten)] Manifest),
+    /// Report the configuration of a destination that affects how copies are stored, such as
+    /// bucket versioning, default encryption, object ownership and public access blocks.
+    Doctor(#[arg(flatten)] Doctor),
+    /// Copy an object into fixed-size pieces with a manifest of per-piece checksums, for
+    /// destinations with object size limits.
+    Split(#[arg(flatten)] Split),
+    /// Concatenate S3 objects into one destination object without downloading them, e.g. to
+    /// assemble the pieces of a split upload. The full-object checksum of the destination is
+    /// confirmed against the combined checksums of the sources.
+    Concat(#[arg(flatten)] Concat),
+    /// Verify the objects under an S3 prefix against their sums files, restoring archived
+    /// objects in batches. Progress is saved so that the audit can be resumed as restores
+    /// complete.
+    Audit(#[arg(flatten)] Audit),
+    /// Work with checksums directly without reading any objects.
+    Sums(#[arg(flatten)] Sums),
+    /// Work with checksum names, e.g. to validate them before use.
+    Checksum(#[arg(flatten)] Checksums),
+    /// Work with the stats output of previous commands.
+    Stats(#[arg(flatten)] Stats),
+}
+
+impl Subcommands {
+    /// Split `<object>#<sums>` inputs of `check` and `copy` into the object, adding the sums
+    /// file location to the sums map.
+    pub fn split_sums_inputs(&mut self, sums_map: &mut SumsMap) {
+        match self {
+            Subcommands::Check(check) => check
+                .input
+                .iter_mut()
+                .for_each(|input| *input = sums_map.split_input(input)),
+            Subcommands::Copy(copy) => {
+                copy.source = sums_map.split_input(&copy.source);
+                copy.destination = sums_map.split_input(&copy.destination);
+            }
+            _ => {}
+        }
+    }
+
+    /// Resolve `drs://` copy sources and generate inputs into their access URLs, returning the
+    /// sums from the DRS metadata of each resolved object. Sums map entries for a DRS URI are
+    /// moved to its access URL.
+    pub async fn resolve_drs_inputs(
+        &mut self,
+        client: &DrsClient,
+        sums_map: &mut SumsMap,
+    ) -> Result<DrsSums> {
+        let inputs: Vec<&mut String> = match self {
+            Subcommands::Copy(copy) => vec![&mut copy.source],
+            Subcommands::Generate(generate) => generate
+                .input
+                .iter_mut()
+                .chain(
+                    generate
+                        .input_checksum
+                        .iter_mut()
+                        .map(|input| &mut input.input),
+                )
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut resolved = HashMap::new();
+        let mut drs_sums = DrsSums::default();
+        for input in inputs.into_iter().filter(|input| is_drs_url(input)) {
+            if !resolved.contains_key(input.as_str()) {
+                let object = client.resolve(input).await?;
+                if let Some(sums) = sums_map.get(input).map(str::to_string) {
+                    sums_map.insert(object.url(), &sums);
+                }
+                drs_sums.insert(&object);
+                resolved.insert(input.clone(), object.url().to_string());
+            }
+
+            *input = resolved[input.as_str()].clone();
+        }
+
+        Ok(drs_sums)
+    }
+}
+
+/// Commands related to optimizing IO and CPU tasks.
 #[derive(Args, Debug, Clone)]
 #[group(required = false)]
 #[command(next_help_heading = "Optimization")]
@@ -1050,17 +4687,42 @@ pub struct Optimization {
         hide_short_help = true
     )]
     pub channel_capacity: usize,
-    /// The chunk size of the channel reader in bytes.
+    /// The chunk size of the channel reader in bytes, or `auto`.
     ///
     /// This controls how many bytes are read by the reader before they are passed into the channel.
+    /// With `auto`, the chunk size starts from a smaller size for local files than for streams,
+    /// such as S3, and is adjusted based on the latency of reads. The chunk size that was used is
+    /// recorded in the generate stats.
     #[arg(
         global = true,
         long,
         env = "COPYRITE_READER_CHUNK_SIZE",
-        default_value_t = 1048576,
+        default_value = "auto",
         hide_short_help = true
     )]
-    pub reader_chunk_size: usize,
+    pub reader_chunk_size: ChunkSize,
+    /// The maximum number of bytes that the channel reader buffers for each checksum.
+    ///
+    /// Without this, the reader buffers up to the channel capacity multiplied by the chunk size
+    /// for each checksum when the checksums are slower than the reader. Chunks are no larger than
+    /// this value, and the reader waits for checksums to catch up before buffering more, so that
+    /// objects larger than memory can be read with bounded memory. The most bytes that were
+    /// buffered is recorded in the generate stats.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_MAX_BUFFER_BYTES",
+        hide_short_help = true,
+        value_parser = |s: &str| parse_size(s)
+    )]
+    pub max_buffer_bytes: Option<u64>,
+}
+
+impl Optimization {
+    /// The maximum number of bytes that the channel reader buffers for each checksum.
+    pub fn max_buffer_bytes(&self) -> Result<Option<usize>> {
+        Ok(self.max_buffer_bytes.map(usize::try_from).transpose()?)
+    }
 }
 
 /// Options related to outputting information from the CLI.
@@ -1079,6 +4741,13 @@ pub struct Output {
     /// Print output using a UI-mode for copy operations rather than JSON.
     #[arg(global = true, long, env = "COPYRITE_UI", hide_short_help = true)]
     pub ui: bool,
+    /// Record the human-readable UI output to a log file, in addition to printing the JSON stats.
+    ///
+    /// The steps of each command, and the reasons for decisions such as skipping a copy, are
+    /// appended to the file with a timestamp whether or not `--ui` is used. The stats are also
+    /// recorded, so the log can be attached to a ticket when a transfer behaves unexpectedly.
+    #[arg(global = true, long, env = "COPYRITE_UI_LOG", hide_short_help = true)]
+    pub ui_log: Option<PathBuf>,
     /// Write sums files at the location when copying or checking.
     ///
     /// By default, `copy` operations and `check` operations with `--missing` will not write any
@@ -1092,6 +4761,78 @@ pub struct Output {
     pub write_sums_file: bool,
 }
 
+/// Options for reading and storing sums using an external API, such as an organizational file
+/// catalog service.
+#[derive(Args, Debug, Clone, Default)]
+pub struct SumsApi {
+    /// Read existing sums from, and store written sums in, an HTTP API at this URL.
+    ///
+    /// Sums for an object are fetched using `GET <url>?location=<location>`, where a `404`
+    /// response means that there are no sums. Sums are stored using `PUT <url>?location=<location>`
+    /// whenever a sums file is written. Both use the JSON format of a `.sums` file as the body.
+    /// Existing sums files take precedence over sums from the API. This applies to `generate`
+    /// and `check`.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SUMS_API_URL",
+        hide_short_help = true
+    )]
+    pub sums_api_url: Option<String>,
+    /// Send additional headers or query parameters with sums API requests, e.g. for
+    /// authentication.
+    ///
+    /// Headers are specified as `<name>:<value>` and query parameters as `?<name>=<value>`.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SUMS_API_HEADER",
+        value_delimiter = ',',
+        requires = "sums_api_url",
+        hide_short_help = true
+    )]
+    pub sums_api_header: Vec<RequestParameter>,
+    /// Read and store the sums files of objects at the locations listed in this file, for
+    /// sums files which are not next to their object, e.g. in another bucket.
+    ///
+    /// This is a CSV file with a header row containing `object` and `sums` columns, e.g.:
+    ///
+    /// object,sums
+    /// s3://bucket/key,s3://sums-bucket/key.sums
+    ///
+    /// Single inputs of `check` and `copy` can also specify their sums file as
+    /// `<object>#<sums>`, where the sums file ends in `.sums`. Sums files are still read from
+    /// the object's metadata, but only written to the mapped location.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SUMS_MAP",
+        hide_short_help = true,
+        verbatim_doc_comment
+    )]
+    pub sums_map: Option<PathBuf>,
+}
+
+impl SumsApi {
+    /// Read the sums map file, if any.
+    pub async fn sums_map(&self) -> Result<SumsMap> {
+        match &self.sums_map {
+            Some(path) => SumsMap::read(path).await,
+            None => Ok(SumsMap::default()),
+        }
+    }
+
+    /// Create the sums source if a sums API is configured.
+    pub fn sums_source(&self) -> Option<Arc<dyn SumsSource + Send + std::marker::Sync>> {
+        self.sums_api_url.as_ref().map(|url| {
+            Arc::new(HttpSumsSource::new(
+                url.to_string(),
+                self.sums_api_header.clone(),
+            )) as Arc<dyn SumsSource + Send + std::marker::Sync>
+        })
+    }
+}
+
 /// Options related to increasing compatibility with S3-compatible storage. For
 /// `copy`, options can be prefixed with `source_` or `destination_` to target one side.
 /// `generate` and `check` only support the unprefixed version of options. Prefixed
@@ -1118,6 +4859,21 @@ pub struct Compatibility {
         hide_short_help = true
     )]
     pub s3_compatible: bool,
+    /// Adjust requests for the known incompatibilities of an S3-compatible store.
+    ///
+    /// `r2` and `b2` avoid `GetObjectAttributes` calls, only send additional checksums that
+    /// the store supports, and plan multipart copies using the limits of the store. `b2` also
+    /// disables checksum mode and request checksums. `minio` uses path-style addressing, and
+    /// `generic` enables all compatibility options and does not send additional checksums.
+    /// Copies to a store that does not support the additional checksum are verified using
+    /// the `ETag` or by computing the sums instead.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_S3_COMPAT_PROFILE",
+        hide_short_help = true
+    )]
+    pub s3_compat_profile: Option<S3CompatProfile>,
     /// Use path-style addressing for S3 endpoints.
     ///
     /// By default, the S3 client uses virtual-hosted-style addressing. Some S3-compatible
@@ -1168,6 +4924,31 @@ pub struct Compatibility {
         hide_short_help = true
     )]
     pub no_request_checksum: bool,
+    /// Send `x-amz-request-payer: requester` with S3 requests, which is required to access
+    /// buckets that have requester pays enabled.
+    ///
+    /// The requester is charged for the requests and data transfer. Server-side copies are sent
+    /// by the destination client, so they also send the header if the source uses requester pays.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_REQUESTER_PAYS",
+        hide_short_help = true
+    )]
+    pub requester_pays: bool,
+    /// The account id that must own the buckets accessed with S3 requests.
+    ///
+    /// This is sent as `x-amz-expected-bucket-owner` with every S3 request, so that requests to
+    /// a bucket owned by another account fail with an access denied error, rather than reading
+    /// from or writing into a similarly named bucket in the wrong account. Server-side copies
+    /// also send the source owner as `x-amz-source-expected-bucket-owner`.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_EXPECTED_BUCKET_OWNER",
+        hide_short_help = true
+    )]
+    pub expected_bucket_owner: Option<String>,
     /// Controls overriding the AWS SDK's stalled stream protection.
     ///
     /// SSP is useful to prevent dead TCP connections from hanging if the SDK detects that no bytes are
@@ -1182,6 +4963,20 @@ pub struct Compatibility {
         hide_short_help = true
     )]
     pub stalled_stream_protection: StalledStreamProtection,
+    /// Send additional headers or query parameters with `GetObject` requests to the source.
+    ///
+    /// Headers are specified as `<name>:<value>` and query parameters as `?<name>=<value>`.
+    /// This allows reading from endpoints that transform objects, such as S3 Object Lambda
+    /// access points, so that the transformed data can still be checksummed and copied. For
+    /// `generate` and `check`, this applies to all inputs. Values can contain commas, so repeat
+    /// the flag to send multiple headers or parameters.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_HEADER",
+        hide_short_help = true
+    )]
+    pub source_header: Vec<RequestParameter>,
     #[arg(
         global = true,
         long,
@@ -1189,6 +4984,13 @@ pub struct Compatibility {
         hide = true
     )]
     pub source_s3_compatible: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_S3_COMPAT_PROFILE",
+        hide = true
+    )]
+    pub source_s3_compat_profile: Option<S3CompatProfile>,
     #[arg(
         global = true,
         long,
@@ -1217,6 +5019,20 @@ pub struct Compatibility {
         hide = true
     )]
     pub source_no_request_checksum: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_REQUESTER_PAYS",
+        hide = true
+    )]
+    pub source_requester_pays: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_EXPECTED_BUCKET_OWNER",
+        hide = true
+    )]
+    pub source_expected_bucket_owner: Option<String>,
     #[arg(
         global = true,
         long,
@@ -1231,6 +5047,13 @@ pub struct Compatibility {
         hide = true
     )]
     pub destination_s3_compatible: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_S3_COMPAT_PROFILE",
+        hide = true
+    )]
+    pub destination_s3_compat_profile: Option<S3CompatProfile>,
     #[arg(
         global = true,
         long,
@@ -1259,6 +5082,20 @@ pub struct Compatibility {
         hide = true
     )]
     pub destination_no_request_checksum: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_REQUESTER_PAYS",
+        hide = true
+    )]
+    pub destination_requester_pays: bool,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_EXPECTED_BUCKET_OWNER",
+        hide = true
+    )]
+    pub destination_expected_bucket_owner: Option<String>,
     #[arg(
         global = true,
         long,
@@ -1266,9 +5103,78 @@ pub struct Compatibility {
         hide = true
     )]
     pub destination_stalled_stream_protection: Option<StalledStreamProtection>,
+    /// Randomly inject simulated faults into S3 responses with this probability, e.g. `0.05`.
+    ///
+    /// Faults are 500 internal errors, slow responses and truncated `GetObject` streams. This
+    /// is a developer option for exercising retry and resume logic, and should not be used for
+    /// real copies.
+    #[arg(global = true, long, env = "COPYRITE_FAULT_INJECT", hide = true)]
+    pub fault_inject: Option<FaultInjection>,
+    /// Record the metadata of every S3 request and response to a JSON lines trace file.
+    ///
+    /// Object data is not recorded. The trace can be shared to reproduce how a copy was
+    /// planned using `--replay-trace`, without access to the buckets.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_RECORD_TRACE",
+        hide_short_help = true,
+        conflicts_with = "replay_trace"
+    )]
+    pub record_trace: Option<PathBuf>,
+    /// Replay S3 responses from a trace file recorded with `--record-trace` instead of sending
+    /// requests.
+    ///
+    /// Requests are matched by method, URL and range, so the same command and region should be
+    /// used as when the trace was recorded. Object data is replaced with zeros, and no
+    /// credentials are required.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_REPLAY_TRACE",
+        hide_short_help = true
+    )]
+    pub replay_trace: Option<PathBuf>,
+    /// Bind outbound S3 connections to a local IP address or network interface, e.g. `10.1.0.5`
+    /// or `eth1`.
+    ///
+    /// This forces transfers through a specific link on hosts with multiple networks. Binding
+    /// to an interface by name is supported on Linux and macOS, and may require elevated
+    /// privileges on Linux.
+    #[arg(global = true, long, env = "COPYRITE_BIND_ADDRESS")]
+    pub bind_address: Option<BindAddress>,
+    /// The maximum number of requests to each S3 host that wait for a response at the same
+    /// time, e.g. to avoid overloading an endpoint.
+    ///
+    /// Requests wait once the limit is reached, and up to this many idle connections are kept
+    /// open for reuse. This only lowers the number of connections that are used, as a response
+    /// body is still read after its request stops counting towards the limit.
+    #[arg(global = true, long, env = "COPYRITE_MAX_CONNECTIONS_PER_HOST", value_parser = value_parser!(u64).range(1..))]
+    pub max_connections_per_host: Option<u64>,
+    /// The HTTP version to use for S3 connections.
+    #[arg(global = true, long, env = "COPYRITE_HTTP_VERSION")]
+    pub http_version: Option<HttpVersion>,
+    /// Send TCP keepalive probes on idle S3 connections at this interval, e.g. `30s`.
+    ///
+    /// This keeps connections open through firewalls and NAT gateways that drop idle
+    /// connections.
+    #[arg(global = true, long, env = "COPYRITE_TCP_KEEPALIVE")]
+    pub tcp_keepalive: Option<Duration>,
 }
 
 impl Compatibility {
+    /// The options for the HTTP connector of S3 clients.
+    pub fn connector_options(&self) -> ConnectorOptions {
+        ConnectorOptions::default()
+            .set_bind_address(self.bind_address.clone())
+            .set_max_connections_per_host(
+                self.max_connections_per_host
+                    .map(|max| usize::try_from(max).unwrap_or(usize::MAX)),
+            )
+            .set_http_version(self.http_version)
+            .set_tcp_keepalive(self.tcp_keepalive.map(Into::into))
+    }
+
     /// Whether to force path-style addressing.
     pub fn force_path_style(&self) -> bool {
         self.s3_compatible || self.force_path_style
@@ -1289,9 +5195,25 @@ impl Compatibility {
         self.s3_compatible || self.no_request_checksum
     }
 
+    /// The profile of the S3-compatible store of the source.
+    pub fn source_s3_compat_profile(&self) -> Option<S3CompatProfile> {
+        self.source_s3_compat_profile.or(self.s3_compat_profile)
+    }
+
+    /// The profile of the S3-compatible store of the destination.
+    pub fn destination_s3_compat_profile(&self) -> Option<S3CompatProfile> {
+        self.destination_s3_compat_profile
+            .or(self.s3_compat_profile)
+    }
+
     /// Whether to force path-style addressing for the source.
     pub fn source_force_path_style(&self) -> bool {
-        self.source_s3_compatible || self.source_force_path_style || self.force_path_style()
+        self.source_s3_compatible
+            || self.source_force_path_style
+            || self.force_path_style()
+            || self
+                .source_s3_compat_profile()
+                .is_some_and(|profile| profile.force_path_style())
     }
 
     /// Whether to force path-style addressing for the destination.
@@ -1299,6 +5221,9 @@ impl Compatibility {
         self.destination_s3_compatible
             || self.destination_force_path_style
             || self.force_path_style()
+            || self
+                .destination_s3_compat_profile()
+                .is_some_and(|profile| profile.force_path_style())
     }
 
     /// Whether to avoid `GetObjectAttributes` calls for the source.
@@ -1306,6 +5231,9 @@ impl Compatibility {
         self.source_s3_compatible
             || self.source_no_get_object_attributes
             || self.no_get_object_attributes()
+            || self
+                .source_s3_compat_profile()
+                .is_some_and(|profile| profile.no_get_object_attributes())
     }
 
     /// Whether to avoid `GetObjectAttributes` calls for the destination.
@@ -1313,16 +5241,29 @@ impl Compatibility {
         self.destination_s3_compatible
             || self.destination_no_get_object_attributes
             || self.no_get_object_attributes()
+            || self
+                .destination_s3_compat_profile()
+                .is_some_and(|profile| profile.no_get_object_attributes())
     }
 
     /// Whether to disable checksum mode for the source.
     pub fn source_no_checksum_mode(&self) -> bool {
-        self.source_s3_compatible || self.source_no_checksum_mode || self.no_checksum_mode()
+        self.source_s3_compatible
+            || self.source_no_checksum_mode
+            || self.no_checksum_mode()
+            || self
+                .source_s3_compat_profile()
+                .is_some_and(|profile| profile.no_checksum_mode())
     }
 
     /// Whether to disable automatic request checksum calculation for the source.
     pub fn source_no_request_checksum(&self) -> bool {
-        self.source_s3_compatible || self.source_no_request_checksum || self.no_request_checksum()
+        self.source_s3_compatible
+            || self.source_no_request_checksum
+            || self.no_request_checksum()
+            || self
+                .source_s3_compat_profile()
+                .is_some_and(|profile| profile.no_request_checksum())
     }
 
     /// Whether to disable checksum mode for the destination.
@@ -1330,6 +5271,9 @@ impl Compatibility {
         self.destination_s3_compatible
             || self.destination_no_checksum_mode
             || self.no_checksum_mode()
+            || self
+                .destination_s3_compat_profile()
+                .is_some_and(|profile| profile.no_checksum_mode())
     }
 
     /// Whether to disable automatic request checksum calculation for the destination.
@@ -1337,6 +5281,34 @@ impl Compatibility {
         self.destination_s3_compatible
             || self.destination_no_request_checksum
             || self.no_request_checksum()
+            || self
+                .destination_s3_compat_profile()
+                .is_some_and(|profile| profile.no_request_checksum())
+    }
+
+    /// Whether to send the requester pays header for the source.
+    pub fn source_requester_pays(&self) -> bool {
+        self.source_requester_pays || self.requester_pays
+    }
+
+    /// Whether to send the requester pays header for the destination. This includes a source
+    /// that uses requester pays because server-side copies are sent by the destination client.
+    pub fn destination_requester_pays(&self) -> bool {
+        self.destination_requester_pays || self.source_requester_pays()
+    }
+
+    /// The account id that must own the source bucket.
+    pub fn source_expected_bucket_owner(&self) -> Option<&str> {
+        self.source_expected_bucket_owner
+            .as_deref()
+            .or(self.expected_bucket_owner.as_deref())
+    }
+
+    /// The account id that must own the destination bucket.
+    pub fn destination_expected_bucket_owner(&self) -> Option<&str> {
+        self.destination_expected_bucket_owner
+            .as_deref()
+            .or(self.expected_bucket_owner.as_deref())
     }
 
     /// The SSP configuration for the source.
@@ -1351,19 +5323,78 @@ impl Compatibility {
             .unwrap_or(self.stalled_stream_protection)
     }
 
+    /// Use the compatibility options of a remote for both sides.
+    pub fn apply_remote(&mut self, remote: &Remote) {
+        self.s3_compatible |= remote.s3_compatible;
+        self.force_path_style |= remote.force_path_style;
+        self.s3_compat_profile = self.s3_compat_profile.or(remote.s3_compat_profile);
+    }
+
+    /// Use the compatibility options of a remote for the source.
+    pub fn apply_source_remote(&mut self, remote: &Remote) {
+        self.source_s3_compatible |= remote.s3_compatible;
+        self.source_force_path_style |= remote.force_path_style;
+        self.source_s3_compat_profile = self.source_s3_compat_profile.or(remote.s3_compat_profile);
+    }
+
+    /// Use the compatibility options of a remote for the destination.
+    pub fn apply_destination_remote(&mut self, remote: &Remote) {
+        self.destination_s3_compatible |= remote.s3_compatible;
+        self.destination_force_path_style |= remote.force_path_style;
+        self.destination_s3_compat_profile = self
+            .destination_s3_compat_profile
+            .or(remote.s3_compat_profile);
+    }
+
+    /// Use the options of a matching bucket for both sides.
+    pub fn apply_bucket_options(&mut self, options: &BucketOptions) {
+        self.s3_compatible |= options.s3_compatible;
+        self.force_path_style |= options.force_path_style;
+        self.no_get_object_attributes |= options.no_get_object_attributes;
+        self.no_checksum_mode |= options.no_checksum_mode;
+        self.no_request_checksum |= options.no_request_checksum;
+        self.requester_pays |= options.requester_pays;
+    }
+
+    /// Use the options of a matching bucket for the source.
+    pub fn apply_source_bucket_options(&mut self, options: &BucketOptions) {
+        self.source_s3_compatible |= options.s3_compatible;
+        self.source_force_path_style |= options.force_path_style;
+        self.source_no_get_object_attributes |= options.no_get_object_attributes;
+        self.source_no_checksum_mode |= options.no_checksum_mode;
+        self.source_no_request_checksum |= options.no_request_checksum;
+        self.source_requester_pays |= options.requester_pays;
+    }
+
+    /// Use the options of a matching bucket for the destination.
+    pub fn apply_destination_bucket_options(&mut self, options: &BucketOptions) {
+        self.destination_s3_compatible |= options.s3_compatible;
+        self.destination_force_path_style |= options.force_path_style;
+        self.destination_no_get_object_attributes |= options.no_get_object_attributes;
+        self.destination_no_checksum_mode |= options.no_checksum_mode;
+        self.destination_no_request_checksum |= options.no_request_checksum;
+        self.destination_requester_pays |= options.requester_pays;
+    }
+
     /// Check if any source or destination options are set.
     pub fn has_prefixed_options(&self) -> bool {
         self.source_s3_compatible
+            || self.source_s3_compat_profile.is_some()
             || self.source_force_path_style
             || self.source_no_get_object_attributes
             || self.source_no_checksum_mode
             || self.source_no_request_checksum
+            || self.source_requester_pays
+            || self.source_expected_bucket_owner.is_some()
             || self.source_stalled_stream_protection.is_some()
             || self.destination_s3_compatible
+            || self.destination_s3_compat_profile.is_some()
             || self.destination_force_path_style
             || self.destination_no_get_object_attributes
             || self.destination_no_checksum_mode
             || self.destination_no_request_checksum
+            || self.destination_requester_pays
+            || self.destination_expected_bucket_owner.is_some()
             || self.destination_stalled_stream_protection.is_some()
     }
 }
@@ -1372,7 +5403,7 @@ impl Compatibility {
 /// `copy`, options can be prefixed with `source_` or `destination_` to target one side. `generate`
 /// and `check` only support the unprefixed version of options. Prefixed options take precedence
 /// over unprefixed options in copies.
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Clone)]
 #[group(required = false)]
 #[command(next_help_heading = "Credentials")]
 pub struct Credentials {
@@ -1389,6 +5420,7 @@ pub struct Credentials {
         env = "COPYRITE_CREDENTIAL_PROVIDER",
         requires_if("aws-profile", "profile"),
         requires_if("aws-secret", "secret"),
+        requires_if("process", "credential_process"),
         hide_short_help = true
     )]
     pub credential_provider: Option<CredentialProvider>,
@@ -1417,6 +5449,28 @@ pub struct Credentials {
         verbatim_doc_comment
     )]
     pub secret: Option<String>,
+    /// The command to run if the credential provider is `process`.
+    ///
+    /// The command is run with the system shell and must print credentials to stdout in the
+    /// same JSON format as the AWS CLI `credential_process` setting:
+    ///
+    ///   {
+    ///     "Version": 1,
+    ///     "AccessKeyId": "...",
+    ///     "SecretAccessKey": "...",
+    ///     "SessionToken": "...",
+    ///     "Expiration": "..."
+    ///   }
+    ///
+    /// The `SessionToken` and `Expiration` are optional.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_CREDENTIAL_PROCESS",
+        hide_short_help = true,
+        verbatim_doc_comment
+    )]
+    pub credential_process: Option<String>,
     /// Set the region for the credential provider.
     #[arg(global = true, long, env = "COPYRITE_REGION", hide_short_help = true)]
     pub region: Option<String>,
@@ -1453,12 +5507,92 @@ pub struct Credentials {
         hide_short_help = true
     )]
     pub session_token: Option<String>,
+    /// A file containing the AWS access key ID. This is an alternative to `--access-key-id`
+    /// which avoids passing the value on the command line.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_ACCESS_KEY_ID_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide_short_help = true
+    )]
+    pub access_key_id_file: Option<String>,
+    /// A file containing the AWS secret access key. This is an alternative to `--secret-access-key`
+    /// which avoids passing the value on the command line.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SECRET_ACCESS_KEY_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide_short_help = true
+    )]
+    pub secret_access_key_file: Option<String>,
+    /// A file containing the AWS session token. This is an alternative to `--session-token`
+    /// which avoids passing the value on the command line.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SESSION_TOKEN_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide_short_help = true
+    )]
+    pub session_token_file: Option<String>,
+    /// The private key used to authenticate to `sftp://` locations.
+    ///
+    /// Connections use the system `ssh` client, so keys, users and ports configured in
+    /// `~/.ssh/config` or loaded in an SSH agent also apply. A user and port can be given in the
+    /// location, e.g. `sftp://user@host:2222/path/to/file`.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SFTP_IDENTITY_FILE",
+        hide_short_help = true
+    )]
+    pub sftp_identity_file: Option<PathBuf>,
+    /// How to check the host keys of `sftp://` locations against the known hosts file.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SFTP_KNOWN_HOSTS",
+        default_value = "strict",
+        hide_short_help = true
+    )]
+    pub sftp_known_hosts: SftpKnownHosts,
+    /// The base URL of the Illumina Connected Analytics API used to access `gds://` locations.
+    #[arg(global = true, long, env = "ICA_BASE_URL", hide_short_help = true)]
+    pub ica_base_url: Option<String>,
+    /// The access token used to authorize requests to the Illumina Connected Analytics API.
+    #[arg(global = true, long, env = "ICA_ACCESS_TOKEN", hide_short_help = true)]
+    pub ica_access_token: Option<String>,
+    /// The storage URL of the OpenStack Swift account used to access `swift://` locations, as
+    /// printed by `swift auth`.
+    #[arg(global = true, long, env = "OS_STORAGE_URL", hide_short_help = true)]
+    pub swift_storage_url: Option<String>,
+    /// The auth token used to authorize requests to OpenStack Swift.
+    #[arg(global = true, long, env = "OS_AUTH_TOKEN", hide_short_help = true)]
+    pub swift_auth_token: Option<String>,
+    /// The user used to authorize requests to `dav://` and `davs://` locations.
+    #[arg(global = true, long, env = "WEBDAV_USER", hide_short_help = true)]
+    pub webdav_user: Option<String>,
+    /// The password used to authorize requests to WebDAV servers, such as a Nextcloud app
+    /// password.
+    #[arg(global = true, long, env = "WEBDAV_PASSWORD", hide_short_help = true)]
+    pub webdav_password: Option<String>,
+    /// The bearer token used to authorize requests to resolve `drs://` locations.
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DRS_BEARER_TOKEN",
+        hide_short_help = true
+    )]
+    pub drs_bearer_token: Option<String>,
     #[arg(
         global = true,
         long,
         env = "COPYRITE_SOURCE_CREDENTIAL_PROVIDER",
         requires_if("aws-profile", "source_profile"),
         requires_if("aws-secret", "source_secret"),
+        requires_if("process", "source_credential_process"),
         hide = true
     )]
     pub source_credential_provider: Option<CredentialProvider>,
@@ -1466,6 +5600,13 @@ pub struct Credentials {
     pub source_profile: Option<String>,
     #[arg(global = true, long, env = "COPYRITE_SOURCE_SECRET", hide = true)]
     pub source_secret: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_CREDENTIAL_PROCESS",
+        hide = true
+    )]
+    pub source_credential_process: Option<String>,
     #[arg(global = true, long, env = "COPYRITE_SOURCE_REGION", hide = true)]
     pub source_region: Option<String>,
     #[arg(global = true, long, env = "COPYRITE_SOURCE_ENDPOINT_URL", hide = true)]
@@ -1491,12 +5632,37 @@ pub struct Credentials {
         hide = true
     )]
     pub source_session_token: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_ACCESS_KEY_ID_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub source_access_key_id_file: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_SECRET_ACCESS_KEY_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub source_secret_access_key_file: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_SOURCE_SESSION_TOKEN_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub source_session_token_file: Option<String>,
     #[arg(
         global = true,
         long,
         env = "COPYRITE_DESTINATION_CREDENTIAL_PROVIDER",
         requires_if("aws-profile", "destination_profile"),
         requires_if("aws-secret", "destination_secret"),
+        requires_if("process", "destination_credential_process"),
         hide = true
     )]
     pub destination_credential_provider: Option<CredentialProvider>,
@@ -1504,6 +5670,13 @@ pub struct Credentials {
     pub destination_profile: Option<String>,
     #[arg(global = true, long, env = "COPYRITE_DESTINATION_SECRET", hide = true)]
     pub destination_secret: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_CREDENTIAL_PROCESS",
+        hide = true
+    )]
+    pub destination_credential_process: Option<String>,
     #[arg(global = true, long, env = "COPYRITE_DESTINATION_REGION", hide = true)]
     pub destination_region: Option<String>,
     #[arg(
@@ -1534,6 +5707,30 @@ pub struct Credentials {
         hide = true
     )]
     pub destination_session_token: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_ACCESS_KEY_ID_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub destination_access_key_id_file: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_SECRET_ACCESS_KEY_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub destination_secret_access_key_file: Option<String>,
+    #[arg(
+        global = true,
+        long,
+        env = "COPYRITE_DESTINATION_SESSION_TOKEN_FILE",
+        value_parser = |s: &str| read_credential_file(s),
+        hide = true
+    )]
+    pub destination_session_token_file: Option<String>,
 }
 
 impl Credentials {
@@ -1575,6 +5772,20 @@ impl Credentials {
             .or(self.secret.as_deref())
     }
 
+    /// Resolve the effective source credential process.
+    pub fn effective_source_credential_process(&self) -> Option<&str> {
+        self.source_credential_process
+            .as_deref()
+            .or(self.credential_process.as_deref())
+    }
+
+    /// Resolve the effective destination credential process.
+    pub fn effective_destination_credential_process(&self) -> Option<&str> {
+        self.destination_credential_process
+            .as_deref()
+            .or(self.credential_process.as_deref())
+    }
+
     /// Resolve the effective source region.
     pub fn effective_source_region(&self) -> Option<&str> {
         self.source_region.as_deref().or(self.region.as_deref())
@@ -1601,14 +5812,52 @@ impl Credentials {
             .or(self.endpoint_url.as_deref())
     }
 
-    /// Construct the source client from the credentials.
-    pub async fn source_client(&self, compatibility: &Compatibility) -> Result<S3Client> {
-        S3Client::new_from_cli_source(self, compatibility).await
+    /// The options used to connect to SFTP locations.
+    pub fn sftp_options(&self) -> SftpOptions {
+        SftpOptions::new(self.sftp_identity_file.clone(), self.sftp_known_hosts)
+    }
+
+    /// The options used to access GDS locations.
+    pub fn gds_options(&self) -> GdsOptions {
+        GdsOptions::new(self.ica_base_url.clone(), self.ica_access_token.clone())
+    }
+
+    /// The options used to access Swift locations.
+    pub fn swift_options(&self) -> SwiftOptions {
+        SwiftOptions::new(
+            self.swift_storage_url.clone(),
+            self.swift_auth_token.clone(),
+        )
+    }
+
+    /// The options used to access WebDAV locations.
+    pub fn webdav_options(&self) -> WebDavOptions {
+        WebDavOptions::new(self.webdav_user.clone(), self.webdav_password.clone())
+    }
+
+    /// The client used to resolve `drs://` locations.
+    pub fn drs_client(&self) -> DrsClient {
+        DrsClient::new(self.drs_bearer_token.clone())
+    }
+
+    /// Construct the source client from the credentials, reusing a client from the pool if
+    /// possible.
+    pub async fn source_client(
+        &self,
+        compatibility: &Compatibility,
+        pool: &ClientPool,
+    ) -> Result<S3Client> {
+        S3Client::new_from_cli_source(self, compatibility, pool).await
     }
 
-    /// Construct the destination client from the credentials.
-    pub async fn destination_client(&self, compatibility: &Compatibility) -> Result<S3Client> {
-        S3Client::new_from_cli_destination(self, compatibility).await
+    /// Construct the destination client from the credentials, reusing a client from the pool if
+    /// possible.
+    pub async fn destination_client(
+        &self,
+        compatibility: &Compatibility,
+        pool: &ClientPool,
+    ) -> Result<S3Client> {
+        S3Client::new_from_cli_destination(self, compatibility, pool).await
     }
 
     /// Check if the default credentials are being used without any overrides.
@@ -1623,6 +5872,43 @@ impl Credentials {
             && !self.destination_overrides().any()
     }
 
+    /// Check if the source uses anonymous credentials without any overrides, and the source and
+    /// destination regions are in the same partition. A server-side copy with the destination
+    /// credentials is possible in this case if the source is publicly readable.
+    pub fn is_anonymous_source_in_partition(&self) -> bool {
+        let same_partition = match (
+            self.effective_source_region(),
+            self.effective_destination_region(),
+        ) {
+            (Some(source), Some(destination)) => {
+                region_partition(source) == region_partition(destination)
+            }
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.effective_source_credential_provider().is_anonymous()
+            && self.effective_source_endpoint_url().is_none()
+            && self.effective_destination_endpoint_url().is_none()
+            && !self.source_overrides().any()
+            && same_partition
+    }
+
+    /// Use the options of a remote that are not already set for both sides.
+    pub fn apply_remote(&mut self, remote: &Remote) {
+        apply_remote_credentials!(self, remote);
+    }
+
+    /// Use the options of a remote that are not already set for the source.
+    pub fn apply_source_remote(&mut self, remote: &Remote) {
+        apply_remote_credentials!(self, remote, source_);
+    }
+
+    /// Use the options of a remote that are not already set for the destination.
+    pub fn apply_destination_remote(&mut self, remote: &Remote) {
+        apply_remote_credentials!(self, remote, destination_);
+    }
+
     /// Check if any source or destination options are set.
     pub fn has_prefixed_options(&self) -> bool {
         self.source_credential_provider.is_some()
@@ -1631,6 +5917,8 @@ impl Credentials {
             || self.destination_profile.is_some()
             || self.source_secret.is_some()
             || self.destination_secret.is_some()
+            || self.source_credential_process.is_some()
+            || self.destination_credential_process.is_some()
             || self.source_region.is_some()
             || self.destination_region.is_some()
             || self.source_endpoint_url.is_some()
@@ -1641,19 +5929,31 @@ impl Credentials {
             || self.destination_secret_access_key.is_some()
             || self.source_session_token.is_some()
             || self.destination_session_token.is_some()
+            || self.source_access_key_id_file.is_some()
+            || self.destination_access_key_id_file.is_some()
+            || self.source_secret_access_key_file.is_some()
+            || self.destination_secret_access_key_file.is_some()
+            || self.source_session_token_file.is_some()
+            || self.destination_session_token_file.is_some()
     }
 
     pub fn source_overrides(&self) -> CredentialOverrides {
         CredentialOverrides::new(
             self.source_access_key_id
                 .clone()
-                .or(self.access_key_id.clone()),
+                .or(self.source_access_key_id_file.clone())
+                .or(self.access_key_id.clone())
+                .or(self.access_key_id_file.clone()),
             self.source_secret_access_key
                 .clone()
-                .or(self.secret_access_key.clone()),
+                .or(self.source_secret_access_key_file.clone())
+                .or(self.secret_access_key.clone())
+                .or(self.secret_access_key_file.clone()),
             self.source_session_token
                 .clone()
-                .or(self.session_token.clone()),
+                .or(self.source_session_token_file.clone())
+                .or(self.session_token.clone())
+                .or(self.session_token_file.clone()),
         )
     }
 
@@ -1661,13 +5961,19 @@ impl Credentials {
         CredentialOverrides::new(
             self.destination_access_key_id
                 .clone()
-                .or(self.access_key_id.clone()),
+                .or(self.destination_access_key_id_file.clone())
+                .or(self.access_key_id.clone())
+                .or(self.access_key_id_file.clone()),
             self.destination_secret_access_key
                 .clone()
-                .or(self.secret_access_key.clone()),
+                .or(self.destination_secret_access_key_file.clone())
+                .or(self.secret_access_key.clone())
+                .or(self.secret_access_key_file.clone()),
             self.destination_session_token
                 .clone()
-                .or(self.session_token.clone()),
+                .or(self.destination_session_token_file.clone())
+                .or(self.session_token.clone())
+                .or(self.session_token_file.clone()),
         )
     }
 }
@@ -0,0 +1,461 @@
+//! The copyrite config file, which defines named remotes that resolve to S3 locations with their
+//! own credentials, endpoint and region, similar to rclone remotes, options that apply to
+//! S3 locations under a bucket or prefix, and named checksum presets. There is also a built-in
+//! `gs` remote for `gs://bucket/object` locations on Google Cloud Storage.
+//!
+
+use crate::checksum::Ctx;
+use crate::cli::CredentialProvider;
+use crate::error::Error::ConfigError;
+use crate::error::Result;
+use crate::io::gcs::{GCS_ENDPOINT_URL, GCS_REGION, GCS_SCHEME};
+use crate::io::profile::S3CompatProfile;
+use crate::io::registry::{BUILT_IN_SCHEMES, ProviderRegistry};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Whether a name cannot be used for a remote because it is the URL scheme of a built-in
+/// provider, whose URLs would otherwise resolve to the remote. The `gs` scheme is the name of
+/// the built-in GCS remote, so it can be configured.
+fn is_reserved_name(name: &str) -> bool {
+    name != GCS_SCHEME && BUILT_IN_SCHEMES.contains(&name.to_lowercase().as_str())
+}
+
+/// The config file.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Named remotes, used as `<remote>:<bucket>/<key>`.
+    #[serde(default)]
+    remotes: BTreeMap<String, Remote>,
+    /// Options for S3 locations under a bucket or prefix, keyed by `<bucket>[/<prefix>]`.
+    #[serde(default)]
+    buckets: BTreeMap<String, BucketOptions>,
+    /// Named checksum presets, used as `--checksum <name>`, e.g.
+    /// `clinical = "sha256,md5-aws-8mib,crc64nvme"`.
+    #[serde(default, deserialize_with = "deserialize_checksum_presets")]
+    checksums: BTreeMap<String, Vec<Ctx>>,
+}
+
+/// The settings of a remote. Any settings that are not set use the command line options.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Remote {
+    #[serde(default, deserialize_with = "deserialize_credential_provider")]
+    pub(crate) credential_provider: Option<CredentialProvider>,
+    pub(crate) profile: Option<String>,
+    pub(crate) secret: Option<String>,
+    pub(crate) credential_process: Option<String>,
+    pub(crate) region: Option<String>,
+    pub(crate) endpoint_url: Option<String>,
+    pub(crate) access_key_id_file: Option<String>,
+    pub(crate) secret_access_key_file: Option<String>,
+    pub(crate) session_token_file: Option<String>,
+    #[serde(default)]
+    pub(crate) s3_compatible: bool,
+    #[serde(default)]
+    pub(crate) force_path_style: bool,
+    /// The profile of the S3-compatible store of this remote, e.g. `r2`.
+    pub(crate) s3_compat_profile: Option<S3CompatProfile>,
+    /// The additional checksum to set when copying to this remote if the source has no sums.
+    pub(crate) default_checksum: Option<Ctx>,
+}
+
+/// Options which are applied to S3 locations under a bucket or prefix. These enable options in
+/// addition to those set on the command line.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct BucketOptions {
+    pub(crate) s3_compatible: bool,
+    pub(crate) force_path_style: bool,
+    pub(crate) no_get_object_attributes: bool,
+    pub(crate) no_checksum_mode: bool,
+    pub(crate) no_request_checksum: bool,
+    pub(crate) requester_pays: bool,
+    /// The additional checksum to set when copying to this location if the source has no sums.
+    pub(crate) default_checksum: Option<Ctx>,
+}
+
+/// Parse the credential provider using the same names as the command line.
+fn deserialize_credential_provider<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<CredentialProvider>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|provider| {
+            CredentialProvider::from_str(&provider, true).map_err(serde::de::Error::custom)
+        })
+        .transpose()
+}
+
+/// Parse each checksum preset from a comma-separated list of checksums.
+fn deserialize_checksum_presets<'de, D>(
+    deserializer: D,
+) -> std::result::Result<BTreeMap<String, Vec<Ctx>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, checksums)| {
+            let checksums = checksums
+                .split(',')
+                .map(|checksum| Ctx::from_str(checksum.trim()))
+                .collect::<copyrite_core::error::Result<Vec<_>>>()
+                .map_err(|err| {
+                    serde::de::Error::custom(format!("checksum preset `{}`: {}", name, err))
+                })?;
+            Ok((name, checksums))
+        })
+        .collect()
+}
+
+/// Whether the name can be used for a remote or checksum preset.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Config {
+    /// Parse and validate a config file.
+    pub fn parse(config: &str) -> Result<Self> {
+        let mut config: Self = toml::from_str(config)?;
+
+        for name in config.remotes.keys() {
+            if is_reserved_name(name) || !is_valid_name(name) {
+                return Err(ConfigError(format!("invalid remote name `{}`", name)));
+            }
+        }
+
+        // A preset with the same name as a checksum would never be used.
+        for name in config.checksums.keys() {
+            if !is_valid_name(name) || Ctx::from_str(name).is_ok() {
+                return Err(ConfigError(format!(
+                    "invalid checksum preset name `{}`",
+                    name
+                )));
+            }
+        }
+
+        config.buckets = config
+            .buckets
+            .into_iter()
+            .map(|(location, options)| {
+                let location = location.strip_prefix("s3://").unwrap_or(&location);
+                if location.is_empty() || location.starts_with('/') {
+                    return Err(ConfigError(format!(
+                        "invalid bucket location `{}`",
+                        location
+                    )));
+                }
+                Ok((location.to_string(), options))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(config)
+    }
+
+    /// Read a config file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path)
+            .map_err(|err| ConfigError(format!("failed to read {}: {}", path.display(), err)))?;
+        Self::parse(&config)
+    }
+
+    /// The default location of the config file, `$XDG_CONFIG_HOME/copyrite/config.toml` or
+    /// `~/.config/copyrite/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("copyrite").join("config.toml"))
+    }
+
+    /// Load the config file at the path, or at the default path if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Option<Self>> {
+        match path {
+            Some(path) => Ok(Some(Self::read(path)?)),
+            None => Self::default_path()
+                .filter(|path| path.is_file())
+                .map(Self::read)
+                .transpose(),
+        }
+    }
+
+    /// Get a remote by name. The built-in `gs` remote uses the GCS XML API, and can be
+    /// configured with a `[remotes.gs]` remote, e.g. to set the credentials of HMAC keys.
+    pub fn remote(&self, name: &str) -> Option<Remote> {
+        if name == GCS_SCHEME {
+            let mut remote = self.remotes.get(name).cloned().unwrap_or_default();
+            remote
+                .endpoint_url
+                .get_or_insert_with(|| GCS_ENDPOINT_URL.to_string());
+            remote.region.get_or_insert_with(|| GCS_REGION.to_string());
+            // Copies to GCS cannot add the default crc64nvme checksum.
+            if remote.default_checksum.is_none() {
+                remote.default_checksum = "crc32c".parse().ok();
+            }
+            // GCS does not support `GetObjectAttributes` or S3 additional checksums.
+            remote.s3_compatible = true;
+            return Some(remote);
+        }
+
+        self.remotes.get(name).cloned()
+    }
+
+    /// Get a remote that was resolved by name, returning an error if it does not exist.
+    pub fn try_remote(&self, name: &str) -> Result<Remote> {
+        self.remote(name)
+            .ok_or_else(|| ConfigError(format!("remote `{}` is not configured", name)))
+    }
+
+    /// Get the checksums of a checksum preset by name.
+    pub fn checksum_preset(&self, name: &str) -> Option<&[Ctx]> {
+        self.checksums.get(name).map(Vec::as_slice)
+    }
+
+    /// Get the options of every configured bucket or prefix which contains the S3 URL. Locations
+    /// that are not S3 URLs do not match any options.
+    pub fn bucket_options<'a>(&'a self, url: &str) -> impl Iterator<Item = &'a BucketOptions> {
+        let object = url
+            .strip_prefix("s3://")
+            .map(|object| object.split_once('/').unwrap_or((object, "")));
+
+        self.buckets
+            .iter()
+            .filter(move |(location, _)| {
+                let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+                object.is_some_and(|(object_bucket, key)| {
+                    object_bucket == bucket && Self::is_under_prefix(key, prefix)
+                })
+            })
+            .map(|(_, options)| options)
+    }
+
+    /// Whether a key is equal to or under a prefix, matching only whole path segments so that
+    /// `data` matches `data/key` but not `database/key`.
+    fn is_under_prefix(key: &str, prefix: &str) -> bool {
+        let prefix = prefix.trim_end_matches('/');
+        prefix.is_empty()
+            || key
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// Resolve a `<remote>:<bucket>/<key>` location into an S3 URL, returning the name of the
+    /// remote. Locations of the `gs` remote stay GCS URLs, which are accessed through the S3 API
+    /// with the options of the remote. Locations that do not start with a configured remote or
+    /// `gs://` are left unchanged.
+    pub fn resolve<'a>(&'a self, location: &mut String) -> Option<&'a str> {
+        // URLs of schemes registered by downstream crates are not remotes.
+        if ProviderRegistry::scheme_of(location).is_some() {
+            return None;
+        }
+
+        let (name, path) = location.split_once(':')?;
+        let name = match self.remotes.get_key_value(name) {
+            Some((name, _)) => name.as_str(),
+            None if name == GCS_SCHEME => GCS_SCHEME,
+            None => return None,
+        };
+
+        let scheme = if name == GCS_SCHEME { GCS_SCHEME } else { "s3" };
+        *location = format!("{}://{}", scheme, path.trim_start_matches('/'));
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    const CONFIG: &str = r#"
+        [remotes.prod]
+        credential_provider = "aws-profile"
+        profile = "prod"
+        region = "ap-southeast-2"
+
+        [remotes.r2]
+        endpoint_url = "https://account.r2.cloudflarestorage.com"
+        s3_compat_profile = "r2"
+
+        [remotes.backup]
+        endpoint_url = "https://storage.example.com"
+        force_path_style = true
+        default_checksum = "crc32c"
+
+        [buckets.legacy]
+        no_get_object_attributes = true
+
+        [buckets."s3://open-data/requester"]
+        requester_pays = true
+
+        [checksums]
+        clinical = "sha256, md5-aws-8mib,crc64nvme"
+    "#;
+
+    #[test]
+    fn parse_config() -> Result<()> {
+        let config = Config::parse(CONFIG)?;
+
+        let prod = config.remote("prod").unwrap();
+        assert_eq!(
+            prod.credential_provider,
+            Some(CredentialProvider::AwsProfile)
+        );
+        assert_eq!(prod.profile.as_deref(), Some("prod"));
+        assert_eq!(prod.region.as_deref(), Some("ap-southeast-2"));
+        assert!(!prod.force_path_style);
+
+        let backup = config.remote("backup").unwrap();
+        assert_eq!(
+            backup.endpoint_url.as_deref(),
+            Some("https://storage.example.com")
+        );
+        assert!(backup.force_path_style);
+        assert_eq!(backup.default_checksum, Some("crc32c".parse()?));
+        assert_eq!(prod.default_checksum, None);
+
+        let r2 = config.remote("r2").unwrap();
+        assert_eq!(r2.s3_compat_profile, Some(S3CompatProfile::R2));
+        assert_eq!(prod.s3_compat_profile, None);
+        assert!(Config::parse("[remotes.r2]\ns3_compat_profile = \"unknown\"").is_err());
+
+        assert!(Config::parse("[remotes.prod]\nunknown = 1").is_err());
+        assert!(Config::parse("[remotes.prod]\ncredential_provider = \"unknown\"").is_err());
+        assert!(Config::parse("[remotes.s3]").is_err());
+        assert!(Config::parse("[remotes.https]").is_err());
+        assert!(Config::parse("[remotes.az]").is_err());
+        assert!(Config::parse("[remotes.gs]").is_ok());
+        assert!(config.try_remote("unknown").is_err());
+        assert!(Config::parse("[remotes.\"a/b\"]").is_err());
+        assert!(Config::parse("[buckets.\"s3://\"]").is_err());
+        assert!(Config::parse("[buckets.legacy]\nunknown = true").is_err());
+        assert_eq!(Config::parse("")?, Config::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_remote() -> Result<()> {
+        let config = Config::parse(CONFIG)?;
+
+        let mut location = "prod:/bucket/key".to_string();
+        assert_eq!(config.resolve(&mut location), Some("prod"));
+        assert_eq!(location, "s3://bucket/key");
+
+        let mut location = "backup:bucket2/key".to_string();
+        assert_eq!(config.resolve(&mut location), Some("backup"));
+        assert_eq!(location, "s3://bucket2/key");
+
+        let mut location = "gs://bucket/object".to_string();
+        assert_eq!(config.resolve(&mut location), Some("gs"));
+        assert_eq!(location, "gs://bucket/object");
+        let mut location = "gs:bucket/object".to_string();
+        assert_eq!(config.resolve(&mut location), Some("gs"));
+        assert_eq!(location, "gs://bucket/object");
+        let gcs = config.remote("gs").unwrap();
+        assert_eq!(gcs.endpoint_url.as_deref(), Some(GCS_ENDPOINT_URL));
+        assert!(gcs.s3_compatible);
+        assert_eq!(gcs.default_checksum, Some("crc32c".parse()?));
+
+        let config = Config::parse(
+            "[remotes.gs]
+profile = \"hmac\"",
+        )?;
+        let gcs = config.remote("gs").unwrap();
+        assert_eq!(gcs.profile.as_deref(), Some("hmac"));
+        assert_eq!(gcs.region.as_deref(), Some(GCS_REGION));
+
+        for unresolved in ["s3://bucket/key", "other:bucket/key", "file", "./prod:key"] {
+            let mut location = unresolved.to_string();
+            assert_eq!(config.resolve(&mut location), None);
+            assert_eq!(location, unresolved);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_presets() -> Result<()> {
+        let config = Config::parse(CONFIG)?;
+
+        assert_eq!(
+            config.checksum_preset("clinical"),
+            Some(
+                [
+                    "sha256".parse()?,
+                    "md5-aws-8mib".parse()?,
+                    "crc64nvme".parse()?
+                ]
+                .as_slice()
+            )
+        );
+        assert_eq!(config.checksum_preset("other"), None);
+
+        assert!(
+            Config::parse(
+                "[checksums]
+md5 = \"sha256\""
+            )
+            .is_err()
+        );
+        assert!(
+            Config::parse(
+                "[checksums]
+\"a/b\" = \"sha256\""
+            )
+            .is_err()
+        );
+        assert!(
+            Config::parse(
+                "[checksums]
+clinical = \"sha256,unknown\""
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bucket_options() -> Result<()> {
+        let config = Config::parse(CONFIG)?;
+
+        let options = config.bucket_options("s3://legacy/key").collect::<Vec<_>>();
+        assert_eq!(options.len(), 1);
+        assert!(options[0].no_get_object_attributes);
+        assert!(!options[0].requester_pays);
+
+        let options = config
+            .bucket_options("s3://open-data/requester/key")
+            .collect::<Vec<_>>();
+        assert_eq!(options.len(), 1);
+        assert!(options[0].requester_pays);
+        assert_eq!(config.bucket_options("s3://open-data/requester").count(), 1);
+
+        for unmatched in [
+            "s3://legacy2/key",
+            "s3://open-data/other/key",
+            "s3://open-data/requester-pays/key",
+            "s3://open-data/requesters",
+            "s3://open-data",
+            "legacy/key",
+        ] {
+            assert_eq!(config.bucket_options(unmatched).count(), 0);
+        }
+
+        Ok(())
+    }
+}
@@ -4,14 +4,27 @@
 use crate::error::Error::AwsError;
 use crate::stats::{CheckStats, CopyStats, GenerateStats};
 use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadError;
 use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError;
 use aws_sdk_s3::operation::copy_object::CopyObjectError;
+use aws_sdk_s3::operation::create_bucket::CreateBucketError;
 use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
+use aws_sdk_s3::operation::delete_object::DeleteObjectError;
+use aws_sdk_s3::operation::get_bucket_encryption::GetBucketEncryptionError;
+use aws_sdk_s3::operation::get_bucket_ownership_controls::GetBucketOwnershipControlsError;
+use aws_sdk_s3::operation::get_bucket_versioning::GetBucketVersioningError;
 use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::get_object_acl::GetObjectAclError;
 use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesError;
 use aws_sdk_s3::operation::get_object_tagging::GetObjectTaggingError;
+use aws_sdk_s3::operation::get_public_access_block::GetPublicAccessBlockError;
+use aws_sdk_s3::operation::head_bucket::HeadBucketError;
 use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error;
+use aws_sdk_s3::operation::list_parts::ListPartsError;
 use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::operation::restore_object::RestoreObjectError;
+use aws_sdk_s3::operation::select_object_content::SelectObjectContentError;
 use aws_sdk_s3::operation::upload_part::UploadPartError;
 use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyError;
 use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
@@ -56,14 +69,38 @@ pub enum Error {
     CheckError(String),
     #[error("copy command error: {0}")]
     CopyError(String),
+    #[error("insufficient space: {0}")]
+    InsufficientSpace(String),
+    #[error("planning error: {0}")]
+    PlanningError(String),
     #[serde(serialize_with = "serialize_aws_error")]
     #[error("aws error: {message}")]
     AwsError {
         message: String,
         api_error: Option<ApiError>,
     },
+    #[error("sums api error: {0}")]
+    SumsApiError(String),
+    #[error("azure error: {0}")]
+    AzureError(String),
+    #[error("http error: {0}")]
+    HttpError(String),
+    #[error("sftp error: {0}")]
+    SftpError(String),
+    #[error("drs error: {0}")]
+    DrsError(String),
+    #[error("gds error: {0}")]
+    GdsError(String),
+    #[error("swift error: {0}")]
+    SwiftError(String),
+    #[error("webdav error: {0}")]
+    WebDavError(String),
     #[error("ui error: {0}")]
     UiError(String),
+    #[error("config file: {0}")]
+    ConfigError(String),
+    #[error("cancelled: {0}")]
+    Cancelled(String),
     #[error("{0}")]
     Stats(String),
 }
@@ -116,6 +153,25 @@ where
     err.to_string().serialize(serializer)
 }
 
+impl From<copyrite_core::error::Error> for Error {
+    fn from(err: copyrite_core::error::Error) -> Self {
+        match err {
+            copyrite_core::error::Error::ParseError(err) => Self::ParseError(err),
+            copyrite_core::error::Error::OverflowError(err) => Self::OverflowError(err),
+            copyrite_core::error::Error::SerdeError(err) => Self::SerdeError(err),
+            copyrite_core::error::Error::SumsFileError(err) => Self::SumsFileError(err),
+            copyrite_core::error::Error::GenerateError(err) => Self::GenerateError(err),
+            copyrite_core::error::Error::CheckError(err) => Self::CheckError(err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::SumsApiError(err.to_string())
+    }
+}
+
 impl From<JoinError> for Error {
     fn from(err: JoinError) -> Self {
         Self::ConcurrencyError(err.to_string())
@@ -140,6 +196,12 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Self::ConfigError(err.to_string())
+    }
+}
+
 impl From<byte_stream::error::Error> for Error {
     fn from(err: byte_stream::error::Error) -> Self {
         Self::IOError(io::Error::other(err))
@@ -189,6 +251,34 @@ impl ApiError {
     pub fn is_access_denied(&self) -> bool {
         self.code == "AccessDenied"
     }
+
+    /// Check if the error was caused by a multipart upload that no longer exists, because it was
+    /// completed or aborted.
+    pub fn is_no_such_upload(&self) -> bool {
+        self.code == "NoSuchUpload"
+    }
+
+    /// Check if the error was caused by expired credentials, which can succeed if retried with
+    /// refreshed credentials.
+    pub fn is_expired_credentials(&self) -> bool {
+        matches!(
+            self.code.as_str(),
+            "ExpiredToken" | "ExpiredTokenException" | "TokenRefreshRequired" | "RequestExpired"
+        )
+    }
+
+    /// Check if the error was caused by the store rejecting the additional checksum of a
+    /// request, as older S3-compatible stores do for `x-amz-checksum-*` headers. Stores that do
+    /// not implement a header reject it without mentioning checksums.
+    pub fn is_unsupported_checksum(&self) -> bool {
+        match self.code.as_str() {
+            "NotImplemented" => true,
+            "InvalidArgument" | "InvalidRequest" | "BadRequest" => {
+                self.message.to_lowercase().contains("checksum")
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<T> From<(&SdkError<T, HttpResponse>, String)> for ApiError
@@ -246,9 +336,22 @@ generate_aws_error_impl!(HeadObjectError);
 generate_aws_error_impl!(GetObjectAttributesError);
 generate_aws_error_impl!(PutObjectError);
 generate_aws_error_impl!(GetObjectTaggingError);
+generate_aws_error_impl!(GetObjectAclError);
+generate_aws_error_impl!(SelectObjectContentError);
 generate_aws_error_impl!(CreateMultipartUploadError);
 generate_aws_error_impl!(CompleteMultipartUploadError);
 generate_aws_error_impl!(CopyObjectError);
 generate_aws_error_impl!(UploadPartCopyError);
 generate_aws_error_impl!(GetObjectError);
 generate_aws_error_impl!(UploadPartError);
+generate_aws_error_impl!(ListPartsError);
+generate_aws_error_impl!(AbortMultipartUploadError);
+generate_aws_error_impl!(ListObjectsV2Error);
+generate_aws_error_impl!(RestoreObjectError);
+generate_aws_error_impl!(DeleteObjectError);
+generate_aws_error_impl!(HeadBucketError);
+generate_aws_error_impl!(CreateBucketError);
+generate_aws_error_impl!(GetBucketVersioningError);
+generate_aws_error_impl!(GetBucketEncryptionError);
+generate_aws_error_impl!(GetBucketOwnershipControlsError);
+generate_aws_error_impl!(GetPublicAccessBlockError);
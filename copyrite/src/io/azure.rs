@@ -0,0 +1,940 @@
+//! Support for Azure Blob Storage through its REST API. `az://container/blob` locations are
+//! accessed in the storage account given by `AZURE_STORAGE_ACCOUNT`, or at the endpoint given by
+//! `AZURE_STORAGE_BLOB_ENDPOINT`. Requests are authorized with the shared access signature in
+//! `AZURE_STORAGE_SAS_TOKEN`, the account key in `AZURE_STORAGE_KEY`, or an Entra ID service
+//! principal from `AZURE_TENANT_ID`, `AZURE_CLIENT_ID` and `AZURE_CLIENT_SECRET`.
+//!
+
+use crate::error::Error::AzureError;
+use crate::error::{Error, Result};
+use crate::io::copy::ObjectCopy;
+use crate::io::registry::ProviderFactory;
+use crate::io::rest::Rest;
+use crate::io::sums::ObjectSums;
+use crate::io::{Provider, copy, sums};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use hmac::{Hmac, KeyInit, Mac};
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use reqwest::header::{AUTHORIZATION, HeaderValue};
+use reqwest::{Body, Client, Method, Request, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt::{Debug, Formatter, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use std::{io, mem};
+use tokio::io::AsyncRead;
+use tokio::sync::Mutex;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// The URL scheme of Azure Blob Storage locations.
+pub const AZURE_SCHEME: &str = "az";
+
+/// The version of the Blob Storage REST API that requests use.
+pub const AZURE_API_VERSION: &str = "2021-08-06";
+
+/// The environment variable with the name of the storage account.
+pub const AZURE_ACCOUNT_ENV: &str = "AZURE_STORAGE_ACCOUNT";
+
+/// The environment variable with the shared access signature used to authorize requests.
+pub const AZURE_SAS_TOKEN_ENV: &str = "AZURE_STORAGE_SAS_TOKEN";
+
+/// The environment variable with a blob endpoint that overrides the endpoint of the account,
+/// e.g. for an emulator.
+pub const AZURE_ENDPOINT_ENV: &str = "AZURE_STORAGE_BLOB_ENDPOINT";
+
+/// The environment variable with the base64 encoded access key of the storage account, used to
+/// authorize requests with Shared Key.
+pub const AZURE_ACCOUNT_KEY_ENV: &str = "AZURE_STORAGE_KEY";
+
+/// The environment variable with the tenant of an Entra ID service principal.
+pub const AZURE_TENANT_ID_ENV: &str = "AZURE_TENANT_ID";
+
+/// The environment variable with the client id of an Entra ID service principal.
+pub const AZURE_CLIENT_ID_ENV: &str = "AZURE_CLIENT_ID";
+
+/// The environment variable with the client secret of an Entra ID service principal.
+pub const AZURE_CLIENT_SECRET_ENV: &str = "AZURE_CLIENT_SECRET";
+
+/// The environment variable with the Entra ID authority, e.g. for sovereign clouds.
+pub const AZURE_AUTHORITY_HOST_ENV: &str = "AZURE_AUTHORITY_HOST";
+
+/// The Entra ID authority of the Azure public cloud.
+pub const AZURE_DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+
+/// The scope of Entra ID tokens for Blob Storage.
+const STORAGE_SCOPE: &str = "https://storage.azure.com/.default";
+
+/// Entra ID tokens are refreshed this long before they expire.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// The prefix of the headers containing user-defined metadata.
+const METADATA_PREFIX: &str = "x-ms-meta-";
+
+/// Retries requests to Blob Storage, converting failures into Azure errors.
+const REST: Rest = Rest::new(AzureError);
+
+/// An Azure blob reader.
+pub type BlobReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// Whether the location is an Azure URL.
+pub fn is_azure_url(location: &str) -> bool {
+    location
+        .strip_prefix(AZURE_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// Format the block id of a part. Block ids must be base64 encoded and the same length for all
+/// blocks of a blob.
+pub fn block_id(part_number: u64) -> String {
+    BASE64_STANDARD.encode(format!("{:010}", part_number))
+}
+
+/// Format the body of a `Put Block List` request which commits the blocks in order.
+pub fn block_list(block_ids: &[String]) -> String {
+    let mut body = r#"<?xml version="1.0" encoding="utf-8"?><BlockList>"#.to_string();
+    for block_id in block_ids {
+        // Writing to a string cannot fail.
+        let _ = write!(body, "<Latest>{}</Latest>", block_id);
+    }
+    body.push_str("</BlockList>");
+
+    body
+}
+
+/// Percent-encode a blob name, leaving path separators and unreserved characters.
+pub(crate) fn encode_path(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The properties of a blob.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlobProperties {
+    pub(crate) size: u64,
+    pub(crate) content_md5: Option<String>,
+    pub(crate) e_tag: Option<String>,
+    pub(crate) version_id: Option<String>,
+    pub(crate) metadata: HashMap<String, String>,
+}
+
+impl BlobProperties {
+    /// Read the properties from the headers of a `Get Blob Properties` response.
+    fn from_response(response: &Response) -> Result<Self> {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        let size = header("content-length")
+            .ok_or_else(|| AzureError("missing blob size".to_string()))?
+            .parse()
+            .map_err(|err| AzureError(format!("invalid blob size: {}", err)))?;
+        let metadata = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = name.as_str().strip_prefix(METADATA_PREFIX)?;
+                Some((name.to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+
+        Ok(Self {
+            size,
+            content_md5: header("content-md5"),
+            e_tag: header("etag").map(|e_tag| e_tag.trim_matches('"').to_string()),
+            version_id: header("x-ms-version-id"),
+            metadata,
+        })
+    }
+
+    /// The hex-encoded MD5 of the blob, if the blob has a `Content-MD5`.
+    pub fn md5(&self) -> Option<String> {
+        let md5 = BASE64_STANDARD.decode(self.content_md5.as_ref()?).ok()?;
+        Some(hex::encode(md5))
+    }
+}
+
+/// The headers set on a blob when it is written.
+#[derive(Debug, Clone, Default)]
+pub struct BlobHeaders {
+    pub(crate) content_md5: Option<String>,
+    pub(crate) metadata: Option<HashMap<String, String>>,
+    pub(crate) tags: Option<String>,
+    pub(crate) content_type: Option<String>,
+}
+
+impl BlobHeaders {
+    /// Add the headers to a request.
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(content_md5) = &self.content_md5 {
+            request = request.header("x-ms-blob-content-md5", content_md5);
+        }
+        if let Some(content_type) = &self.content_type {
+            request = request.header("x-ms-blob-content-type", content_type);
+        }
+        if let Some(tags) = self.tags.as_ref().filter(|tags| !tags.is_empty()) {
+            request = request.header("x-ms-tags", tags);
+        }
+        for (key, value) in self.metadata.iter().flatten() {
+            request = request.header(format!("{}{}", METADATA_PREFIX, key), value);
+        }
+
+        request
+    }
+}
+
+/// An Entra ID access token and when it expires.
+#[derive(Debug, Clone)]
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// The response of the Entra ID token endpoint.
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// An Entra ID service principal which authenticates with a client secret. Access tokens are
+/// cached until they are about to expire.
+pub struct ClientSecretCredential {
+    authority_host: String,
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl ClientSecretCredential {
+    /// Get an access token for Blob Storage, requesting a new one if there is no cached token
+    /// or it is about to expire.
+    async fn token(&self, client: &Client) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached
+            .as_ref()
+            .filter(|token| Instant::now() + TOKEN_REFRESH_MARGIN < token.expires_at)
+        {
+            return Ok(token.token.to_string());
+        }
+
+        let url = format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.authority_host.trim_end_matches('/'),
+            self.tenant_id
+        );
+        let response = client
+            .post(url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("scope", STORAGE_SCOPE),
+            ])
+            .send()
+            .await
+            .map_err(|err| AzureError(format!("requesting an Entra ID token failed: {}", err)))?;
+        if !response.status().is_success() {
+            return Err(AzureError(format!(
+                "requesting an Entra ID token failed with {}",
+                response.status()
+            )));
+        }
+
+        let response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|err| AzureError(format!("invalid Entra ID token response: {}", err)))?;
+        let token = AccessToken {
+            token: response.access_token,
+            expires_at: Instant::now() + Duration::from_secs(response.expires_in),
+        };
+        *cached = Some(token.clone());
+
+        Ok(token.token)
+    }
+}
+
+/// The credentials used to authorize requests to Blob Storage.
+#[derive(Clone, Default)]
+pub enum AzureCredentials {
+    /// Send requests without authorization, for public containers.
+    #[default]
+    Anonymous,
+    /// Append a shared access signature to the query of each request.
+    SasToken(String),
+    /// Sign each request with the name and access key of the storage account.
+    SharedKey { account: String, key: Vec<u8> },
+    /// Send an Entra ID access token with each request.
+    ClientSecret(Arc<ClientSecretCredential>),
+}
+
+impl Debug for AzureCredentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Secrets are never printed.
+        match self {
+            Self::Anonymous => write!(f, "Anonymous"),
+            Self::SasToken(_) => write!(f, "SasToken"),
+            Self::SharedKey { account, .. } => write!(f, "SharedKey({})", account),
+            Self::ClientSecret(credential) => write!(f, "ClientSecret({})", credential.client_id),
+        }
+    }
+}
+
+impl AzureCredentials {
+    /// Create credentials from a shared access signature, with or without a leading `?`.
+    pub fn sas_token(sas_token: &str) -> Self {
+        Self::SasToken(sas_token.trim_start_matches('?').to_string())
+    }
+
+    /// Create Shared Key credentials from the account name and its base64 encoded access key.
+    pub fn shared_key(account: String, key: &str) -> Result<Self> {
+        let key = BASE64_STANDARD
+            .decode(key.trim())
+            .map_err(|err| AzureError(format!("invalid storage account key: {}", err)))?;
+        Ok(Self::SharedKey { account, key })
+    }
+
+    /// Create credentials for an Entra ID service principal with a client secret.
+    pub fn client_secret(
+        authority_host: String,
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    ) -> Self {
+        Self::ClientSecret(Arc::new(ClientSecretCredential {
+            authority_host,
+            tenant_id,
+            client_id,
+            client_secret,
+            token: Mutex::new(None),
+        }))
+    }
+
+    /// Find credentials in the environment. A shared access signature takes precedence over an
+    /// account key, which takes precedence over a service principal. Without any of these,
+    /// requests are anonymous.
+    pub fn from_env() -> Result<Self> {
+        if let Ok(sas_token) = env::var(AZURE_SAS_TOKEN_ENV) {
+            return Ok(Self::sas_token(&sas_token));
+        }
+
+        if let Ok(key) = env::var(AZURE_ACCOUNT_KEY_ENV) {
+            let account = env::var(AZURE_ACCOUNT_ENV).map_err(|_| {
+                AzureError(format!(
+                    "{} must be set to use {}",
+                    AZURE_ACCOUNT_ENV, AZURE_ACCOUNT_KEY_ENV
+                ))
+            })?;
+            return Self::shared_key(account, &key);
+        }
+
+        match (
+            env::var(AZURE_TENANT_ID_ENV),
+            env::var(AZURE_CLIENT_ID_ENV),
+            env::var(AZURE_CLIENT_SECRET_ENV),
+        ) {
+            (Ok(tenant_id), Ok(client_id), Ok(client_secret)) => Ok(Self::client_secret(
+                env::var(AZURE_AUTHORITY_HOST_ENV)
+                    .unwrap_or_else(|_| AZURE_DEFAULT_AUTHORITY_HOST.to_string()),
+                tenant_id,
+                client_id,
+                client_secret,
+            )),
+            _ => Ok(Self::Anonymous),
+        }
+    }
+
+    /// Add the authorization of the credentials to a request.
+    async fn authorize(&self, client: &Client, request: &mut Request) -> Result<()> {
+        let authorization = match self {
+            Self::Anonymous | Self::SasToken(_) => return Ok(()),
+            Self::SharedKey { account, key } => {
+                let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(key)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(string_to_sign(account, request).as_bytes());
+                let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+                format!("SharedKey {}:{}", account, signature)
+            }
+            Self::ClientSecret(credential) => {
+                format!("Bearer {}", credential.token(client).await?)
+            }
+        };
+
+        let authorization = HeaderValue::from_str(&authorization)
+            .map_err(|err| AzureError(format!("invalid authorization header: {}", err)))?;
+        request.headers_mut().insert(AUTHORIZATION, authorization);
+        Ok(())
+    }
+}
+
+/// Format the string that is signed to authorize a request with Shared Key. This contains the
+/// standard headers of the request, its `x-ms-` headers, and the account, path and query
+/// parameters of the resource.
+fn string_to_sign(account: &str, request: &Request) -> String {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    // The content length is empty rather than zero for requests without a body.
+    let content_length = match header("content-length") {
+        length if length.is_empty() => request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| body.len().to_string())
+            .unwrap_or_default(),
+        length => length,
+    };
+    let content_length = if content_length == "0" {
+        String::new()
+    } else {
+        content_length
+    };
+
+    let mut lines = vec![
+        request.method().to_string(),
+        header("content-encoding"),
+        header("content-language"),
+        content_length,
+        header("content-md5"),
+        header("content-type"),
+        header("date"),
+        header("if-modified-since"),
+        header("if-match"),
+        header("if-none-match"),
+        header("if-unmodified-since"),
+        header("range"),
+    ];
+
+    let mut ms_headers: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, value) in request.headers() {
+        if name.as_str().starts_with("x-ms-") {
+            ms_headers
+                .entry(name.as_str())
+                .or_default()
+                .push(value.to_str().unwrap_or_default().trim());
+        }
+    }
+    lines.extend(
+        ms_headers
+            .into_iter()
+            .map(|(name, values)| format!("{}:{}", name, values.join(","))),
+    );
+
+    let mut resource = format!("/{}{}", account, request.url().path());
+    let mut query: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, value) in request.url().query_pairs() {
+        query
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(value.to_string());
+    }
+    for (name, mut values) in query {
+        values.sort();
+        let _ = write!(resource, "\n{}:{}", name, values.join(","));
+    }
+    lines.push(resource);
+
+    lines.join("\n")
+}
+
+/// A client for the Blob Storage REST API of a storage account.
+#[derive(Debug, Clone)]
+pub struct AzureClient {
+    client: Client,
+    endpoint: String,
+    credentials: AzureCredentials,
+}
+
+impl AzureClient {
+    /// Create a client for a blob endpoint, e.g. `https://account.blob.core.windows.net`.
+    pub fn new(endpoint: String, credentials: AzureCredentials) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            credentials,
+        }
+    }
+
+    /// Create a client from the `AZURE_STORAGE_*` environment variables, using the credentials
+    /// found by `AzureCredentials::from_env`.
+    pub fn from_env() -> Result<Self> {
+        let endpoint = match (env::var(AZURE_ENDPOINT_ENV), env::var(AZURE_ACCOUNT_ENV)) {
+            (Ok(endpoint), _) => endpoint,
+            (_, Ok(account)) => format!("https://{}.blob.core.windows.net", account),
+            _ => {
+                return Err(AzureError(format!(
+                    "{} or {} must be set to access Azure locations",
+                    AZURE_ACCOUNT_ENV, AZURE_ENDPOINT_ENV
+                )));
+            }
+        };
+
+        Ok(Self::new(endpoint, AzureCredentials::from_env()?))
+    }
+
+    /// Create a request for a blob with the query parameters, and the shared access signature if
+    /// the credentials use one.
+    fn request(
+        &self,
+        method: Method,
+        container: &str,
+        blob: &str,
+        query: &[(&str, &str)],
+    ) -> RequestBuilder {
+        let mut url = format!("{}/{}/{}", self.endpoint, container, encode_path(blob));
+        let query = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .chain(match &self.credentials {
+                AzureCredentials::SasToken(sas_token) => Some(sas_token.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query.join("&"));
+        }
+
+        self.client
+            .request(method, url)
+            .header("x-ms-version", AZURE_API_VERSION)
+            .header("x-ms-date", httpdate::fmt_http_date(SystemTime::now()))
+    }
+
+    /// Convert an unsuccessful response into an error.
+    async fn check(response: Response, operation: &str) -> Result<Response> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let code = response
+            .headers()
+            .get("x-ms-error-code")
+            .and_then(|code| code.to_str().ok())
+            .map(ToString::to_string);
+        Err(AzureError(format!(
+            "{} failed with {}{}",
+            operation,
+            status,
+            code.map(|code| format!(": {}", code)).unwrap_or_default()
+        )))
+    }
+
+    /// Authorize and send a request, retrying transient failures and mapping transport errors
+    /// to Azure errors. Each attempt is dated and authorized again.
+    async fn send(&self, request: RequestBuilder, operation: &str) -> Result<Response> {
+        let mut request = Some(
+            request
+                .build()
+                .map_err(|err| AzureError(format!("{} failed: {}", operation, err)))?,
+        );
+        REST.retry(operation, || {
+            let next = request.as_ref().and_then(Request::try_clone);
+            let mut request = mem::replace(&mut request, next)?;
+            Some(async move {
+                let date = HeaderValue::from_str(&httpdate::fmt_http_date(SystemTime::now()))
+                    .map_err(|err| AzureError(format!("invalid date header: {}", err)))?;
+                request.headers_mut().insert("x-ms-date", date);
+                self.credentials
+                    .authorize(&self.client, &mut request)
+                    .await?;
+
+                Ok(self.client.execute(request).await)
+            })
+        })
+        .await
+    }
+
+    /// Get the properties of a blob, or `None` if it does not exist.
+    pub async fn get_properties(
+        &self,
+        container: &str,
+        blob: &str,
+    ) -> Result<Option<BlobProperties>> {
+        let request = self.request(Method::HEAD, container, blob, &[]);
+        let response = self.send(request, "Get Blob Properties").await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, "Get Blob Properties").await?;
+        Ok(Some(BlobProperties::from_response(&response)?))
+    }
+
+    /// Read a blob from `start` up to, but not including, `end`, or to the end of the blob if
+    /// `end` is not set. Returns `None` if the blob does not exist.
+    pub async fn get_blob(
+        &self,
+        container: &str,
+        blob: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Option<BlobReader>> {
+        let mut request = self.request(Method::GET, container, blob, &[]);
+        match end {
+            Some(end) if end <= start => return Ok(Some(Box::new(tokio::io::empty()))),
+            Some(end) => {
+                request = request.header("x-ms-range", format!("bytes={}-{}", start, end - 1))
+            }
+            None if start > 0 => {
+                request = request.header("x-ms-range", format!("bytes={}-", start))
+            }
+            None => {}
+        }
+
+        let response = self.send(request, "Get Blob").await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, "Get Blob").await?;
+        let stream = Body::from(response)
+            .into_data_stream()
+            .map_err(io::Error::other);
+        Ok(Some(Box::new(StreamReader::new(stream))))
+    }
+
+    /// Read a whole blob into memory, or `None` if it does not exist.
+    pub async fn get_blob_bytes(&self, container: &str, blob: &str) -> Result<Option<Bytes>> {
+        let request = self.request(Method::GET, container, blob, &[]);
+        let response = self.send(request, "Get Blob").await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, "Get Blob").await?;
+        Ok(Some(response.bytes().await.map_err(Error::from)?))
+    }
+
+    /// Write a block blob in a single request. The `Content-MD5` of the headers is also sent as
+    /// the MD5 of the request so that the service validates the data.
+    pub async fn put_blob(
+        &self,
+        container: &str,
+        blob: &str,
+        body: Body,
+        content_length: u64,
+        headers: &BlobHeaders,
+    ) -> Result<()> {
+        let mut request = self
+            .request(Method::PUT, container, blob, &[])
+            .header("x-ms-blob-type", "BlockBlob")
+            .header("content-length", content_length);
+        if let Some(content_md5) = &headers.content_md5 {
+            request = request.header("content-md5", content_md5);
+        }
+
+        let response = self
+            .send(headers.apply(request).body(body), "Put Blob")
+            .await?;
+        Self::check(response, "Put Blob").await?;
+        Ok(())
+    }
+
+    /// Upload an uncommitted block of a blob.
+    pub async fn put_block(
+        &self,
+        container: &str,
+        blob: &str,
+        block_id: &str,
+        body: Body,
+        content_length: u64,
+    ) -> Result<()> {
+        let block_id = encode_path(block_id);
+        let request = self
+            .request(
+                Method::PUT,
+                container,
+                blob,
+                &[("comp", "block"), ("blockid", &block_id)],
+            )
+            .header("content-length", content_length)
+            .body(body);
+
+        let response = self.send(request, "Put Block").await?;
+        Self::check(response, "Put Block").await?;
+        Ok(())
+    }
+
+    /// Commit the blocks of a blob in order.
+    pub async fn put_block_list(
+        &self,
+        container: &str,
+        blob: &str,
+        block_ids: &[String],
+        headers: &BlobHeaders,
+    ) -> Result<()> {
+        let request = self
+            .request(Method::PUT, container, blob, &[("comp", "blocklist")])
+            .header("content-type", "application/xml")
+            .body(block_list(block_ids));
+
+        let response = self.send(headers.apply(request), "Put Block List").await?;
+        Self::check(response, "Put Block List").await?;
+        Ok(())
+    }
+
+    /// Create a request body that streams from a reader.
+    pub fn body(reader: BlobReader) -> Body {
+        Body::wrap(StreamBody::new(
+            ReaderStream::new(reader).map_ok(Frame::data),
+        ))
+    }
+}
+
+/// Builds the copy and sums objects of Azure locations. This is registered in the provider
+/// registry for the `az` scheme, and authenticates with the credentials in the environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AzureFactory;
+
+#[async_trait::async_trait]
+impl ProviderFactory for AzureFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        let mut builder =
+            copy::azure::AzureBuilder::default().with_client(AzureClient::from_env()?);
+        if let Some(source) = source {
+            let (container, blob) = Provider::try_from(source)?.into_azure()?;
+            builder = builder.with_source(&container, &blob);
+        }
+        if let Some(destination) = destination {
+            let (container, blob) = Provider::try_from(destination)?.into_azure()?;
+            builder = builder.with_destination(&container, &blob);
+        }
+
+        Ok(Box::new(builder.build()?))
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let (container, blob) = Provider::try_from(url)?.into_azure()?;
+        Ok(Box::new(
+            sums::azure::AzureBuilder::default()
+                .with_container(container)
+                .with_blob(blob)
+                .with_client(AzureClient::from_env()?)
+                .build()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn azure_urls() {
+        assert!(is_azure_url("az://container/blob"));
+        assert!(!is_azure_url("azure/blob"));
+        assert!(!is_azure_url("s3://bucket/key"));
+    }
+
+    #[test]
+    fn block_ids() {
+        assert_eq!(block_id(1).len(), block_id(50000).len());
+        assert_ne!(block_id(1), block_id(2));
+        assert_eq!(
+            block_list(&[block_id(1), block_id(2)]),
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8"?><BlockList><Latest>{}</Latest><Latest>{}</Latest></BlockList>"#,
+                block_id(1),
+                block_id(2)
+            )
+        );
+        assert_eq!(encode_path("dir/a b+c"), "dir/a%20b%2Bc");
+    }
+
+    #[tokio::test]
+    async fn get_properties() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = [0; 4096];
+            let n = stream.read(&mut request).await?;
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\ncontent-md5: 1B2M2Y8AsgTpgAmY7PhCfg==\r\n\
+                    etag: \"0x1\"\r\nx-ms-meta-project: umccr\r\n\r\n",
+                )
+                .await?;
+            anyhow::Ok(String::from_utf8_lossy(&request[..n]).to_string())
+        });
+
+        let client = AzureClient::new(endpoint, AzureCredentials::sas_token("?sv=1&sig=abc"));
+        let properties = client
+            .get_properties("container", "dir/blob")
+            .await?
+            .expect("blob properties");
+        assert_eq!(properties.size, 5);
+        assert_eq!(
+            properties.md5().as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e") // pragma: allowlist secret
+        );
+        assert_eq!(properties.e_tag.as_deref(), Some("0x1"));
+        assert_eq!(properties.metadata["project"], "umccr");
+
+        let request = server.await??;
+        assert!(request.starts_with("HEAD /container/dir/blob?sv=1&sig=abc HTTP/1.1"));
+        assert!(request.contains(&format!("x-ms-version: {}", AZURE_API_VERSION)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_retries_with_new_authorization() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            for response in [
+                &b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"[..],
+                b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = [0; 4096];
+                let n = stream.read(&mut request).await?;
+                stream.write_all(response).await?;
+                requests.push(String::from_utf8_lossy(&request[..n]).to_string());
+            }
+            anyhow::Ok(requests)
+        });
+
+        let credentials = AzureCredentials::shared_key("account".to_string(), "a2V5")?;
+        let client = AzureClient::new(endpoint, credentials);
+        let properties = client
+            .get_properties("container", "blob")
+            .await?
+            .expect("blob properties");
+        assert_eq!(properties.size, 5);
+
+        let requests = server.await??;
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.contains("authorization: SharedKey account:"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shared_key() -> anyhow::Result<()> {
+        let credentials = AzureCredentials::shared_key("account".to_string(), "a2V5")?;
+        let client = Client::new();
+        let mut request = client
+            .request(
+                Method::PUT,
+                "http://127.0.0.1/container/dir/blob?comp=block&blockid=MQ%3D%3D",
+            )
+            .header("x-ms-version", AZURE_API_VERSION)
+            .header("x-ms-date", "Mon, 01 Jan 2024 00:00:00 GMT")
+            .header("content-type", "application/xml")
+            .body("block")
+            .build()?;
+
+        let mut expected = vec!["PUT", "", "", "5", "", "application/xml"];
+        expected.extend([""; 6]);
+        expected.extend([
+            "x-ms-date:Mon, 01 Jan 2024 00:00:00 GMT",
+            "x-ms-version:2021-08-06",
+            "/account/container/dir/blob",
+            "blockid:MQ==",
+            "comp:block",
+        ]);
+        assert_eq!(string_to_sign("account", &request), expected.join("\n"));
+
+        credentials.authorize(&client, &mut request).await?;
+        let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(b"key")?;
+        mac.update(expected.join("\n").as_bytes());
+        assert_eq!(
+            request.headers()[AUTHORIZATION],
+            format!(
+                "SharedKey account:{}",
+                BASE64_STANDARD.encode(mac.finalize().into_bytes())
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_secret() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let token = r#"{"access_token":"token","expires_in":3600,"token_type":"Bearer"}"#;
+            let responses = [
+                format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+                    connection: close\r\n\r\n{}",
+                    token.len(),
+                    token
+                ),
+                "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\n".to_string(),
+                "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\n".to_string(),
+            ];
+
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = [0; 4096];
+                let n = stream.read(&mut request).await?;
+                stream.write_all(response.as_bytes()).await?;
+                requests.push(String::from_utf8_lossy(&request[..n]).to_string());
+            }
+            anyhow::Ok(requests)
+        });
+
+        let credentials = AzureCredentials::client_secret(
+            endpoint.to_string(),
+            "tenant".to_string(),
+            "client".to_string(),
+            "secret".to_string(),
+        );
+        let client = AzureClient::new(endpoint, credentials);
+        for _ in 0..2 {
+            let properties = client.get_properties("container", "blob").await?;
+            assert_eq!(properties.map(|properties| properties.size), Some(5));
+        }
+
+        // The token is only requested once.
+        let requests = server.await??;
+        assert!(requests[0].starts_with("POST /tenant/oauth2/v2.0/token HTTP/1.1"));
+        assert!(requests[0].contains("grant_type=client_credentials"));
+        for request in &requests[1..] {
+            assert!(request.starts_with("HEAD /container/blob HTTP/1.1"));
+            assert!(request.contains("authorization: Bearer token"));
+        }
+
+        Ok(())
+    }
+}
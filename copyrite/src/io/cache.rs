@@ -0,0 +1,202 @@
+//! A read-through cache of S3 object metadata shared across the phases of a run.
+//!
+
+use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesOutput;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Identifies a metadata request for an object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    bucket: String,
+    key: String,
+    version_id: Option<String>,
+    part_number: Option<i32>,
+    options: Vec<String>,
+}
+
+impl CacheKey {
+    /// Create a new cache key for an object. The part number and options distinguish requests
+    /// for the same object that return different outputs, such as `HeadObject` calls with
+    /// checksum mode enabled or `GetObjectAttributes` calls with different attributes.
+    pub fn new(
+        bucket: Option<&str>,
+        key: Option<&str>,
+        version_id: Option<&str>,
+        part_number: Option<i32>,
+        options: Vec<String>,
+    ) -> Self {
+        Self {
+            bucket: bucket.unwrap_or_default().to_string(),
+            key: key.unwrap_or_default().to_string(),
+            version_id: version_id.map(|version_id| version_id.to_string()),
+            part_number,
+            options,
+        }
+    }
+
+    /// Whether this key refers to the bucket and key.
+    fn is_object(&self, bucket: &str, key: &str) -> bool {
+        self.bucket == bucket && self.key == key
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    head_object: HashMap<CacheKey, HeadObjectOutput>,
+    get_object_attributes: HashMap<CacheKey, GetObjectAttributesOutput>,
+    missing_sums: HashSet<(String, String)>,
+}
+
+/// A per-run cache of `HeadObject` and `GetObjectAttributes` outputs keyed by bucket, key and
+/// version id. Cloning the cache shares the underlying entries, so it can be passed to each
+/// phase of a run, such as the pre-copy check, copy and post-copy check, without repeating
+/// metadata queries for the same object. Only successful outputs are cached, apart from sums
+/// files which are recorded as missing so that they are not requested again until written.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataCache(Arc<Mutex<CacheState>>);
+
+impl MetadataCache {
+    /// Create a new empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a cached `HeadObject` output.
+    pub fn head_object(&self, key: &CacheKey) -> Option<HeadObjectOutput> {
+        self.lock().head_object.get(key).cloned()
+    }
+
+    /// Cache a `HeadObject` output.
+    pub fn insert_head_object(&self, key: CacheKey, output: HeadObjectOutput) {
+        self.lock().head_object.insert(key, output);
+    }
+
+    /// Get a cached `GetObjectAttributes` output.
+    pub fn get_object_attributes(&self, key: &CacheKey) -> Option<GetObjectAttributesOutput> {
+        self.lock().get_object_attributes.get(key).cloned()
+    }
+
+    /// Cache a `GetObjectAttributes` output.
+    pub fn insert_get_object_attributes(&self, key: CacheKey, output: GetObjectAttributesOutput) {
+        self.lock().get_object_attributes.insert(key, output);
+    }
+
+    /// Whether a sums file was previously found to not exist.
+    pub fn is_missing_sums(&self, bucket: &str, key: &str) -> bool {
+        self.lock()
+            .missing_sums
+            .contains(&(bucket.to_string(), key.to_string()))
+    }
+
+    /// Record that a sums file does not exist.
+    pub fn insert_missing_sums(&self, bucket: &str, key: &str) {
+        self.lock()
+            .missing_sums
+            .insert((bucket.to_string(), key.to_string()));
+    }
+
+    /// Remove all entries for an object, e.g. after it has been written.
+    pub fn invalidate(&self, bucket: &str, key: &str) {
+        let mut state = self.lock();
+        state
+            .missing_sums
+            .remove(&(bucket.to_string(), key.to_string()));
+        state
+            .head_object
+            .retain(|cache_key, _| !cache_key.is_object(bucket, key));
+        state
+            .get_object_attributes
+            .retain(|cache_key, _| !cache_key.is_object(bucket, key));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // The state is always left consistent, so a poisoned lock can still be used.
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::file::SumsFile;
+    use crate::io::S3Client;
+    use crate::io::sums::aws::S3Builder;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::get_object::GetObjectError;
+    use aws_sdk_s3::operation::put_object::PutObjectOutput;
+    use aws_sdk_s3::types::error::NoSuchKey;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    #[tokio::test]
+    async fn head_object_cached_until_written() -> Result<()> {
+        let head_object = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| HeadObjectOutput::builder().content_length(1).build());
+        let put_object = mock!(Client::put_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| PutObjectOutput::builder().build());
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&head_object, &put_object]);
+
+        let client =
+            S3Client::new(Arc::new(client), false, false).with_metadata_cache(MetadataCache::new());
+        let other_phase = client.clone();
+
+        client
+            .head_object(|b| b.bucket("bucket").key("key"))
+            .await?;
+        other_phase
+            .head_object(|b| b.bucket("bucket").key("key"))
+            .await?;
+        assert_eq!(head_object.num_calls(), 1);
+
+        other_phase
+            .head_object(|b| b.bucket("bucket").key("key").part_number(1))
+            .await?;
+        assert_eq!(head_object.num_calls(), 2);
+
+        client.put_object(|b| b.bucket("bucket").key("key")).await?;
+        client
+            .head_object(|b| b.bucket("bucket").key("key"))
+            .await?;
+        assert_eq!(head_object.num_calls(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_sums_cached_until_written() -> Result<()> {
+        let get_object = mock!(Client::get_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key.sums"))
+            .then_error(|| GetObjectError::NoSuchKey(NoSuchKey::builder().build()));
+        let put_object = mock!(Client::put_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key.sums"))
+            .then_output(|| PutObjectOutput::builder().build());
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&get_object, &put_object]);
+
+        let client =
+            S3Client::new(Arc::new(client), false, false).with_metadata_cache(MetadataCache::new());
+        let sums = S3Builder::default()
+            .with_bucket("bucket".to_string())
+            .with_key("key".to_string())
+            .with_client(client.clone())
+            .build()?;
+        let other_phase = S3Builder::default()
+            .with_bucket("bucket".to_string())
+            .with_key("key".to_string())
+            .with_client(client)
+            .build()?;
+
+        assert_eq!(sums.get_existing_sums().await?, None);
+        assert_eq!(other_phase.get_existing_sums().await?, None);
+        assert_eq!(get_object.num_calls(), 1);
+
+        sums.put_sums(&SumsFile::default()).await?;
+        assert_eq!(other_phase.get_existing_sums().await?, None);
+        assert_eq!(get_object.num_calls(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,555 @@
+//! An HTTP connector for the S3 SDK with tuning options that the default connector does not
+//! expose, such as binding connections to a local address, limiting requests per host and
+//! choosing the HTTP version.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::{Error, Result};
+use aws_sdk_s3::config::RuntimeComponents;
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use clap::ValueEnum;
+use reqwest::redirect::Policy;
+use reqwest::{Body, Client, ClientBuilder, Request};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::result;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The local address that outbound connections are bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindAddress {
+    /// Bind connections to a local IP address.
+    Ip(IpAddr),
+    /// Bind connections to a network interface by name, e.g. `eth1`.
+    Interface(String),
+}
+
+impl FromStr for BindAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(ip) = IpAddr::from_str(s) {
+            return Ok(Self::Ip(ip));
+        }
+
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            return Err(ParseError(format!(
+                "invalid bind address `{}`: expected an IP address or interface name",
+                s
+            )));
+        }
+        Ok(Self::Interface(s.to_string()))
+    }
+}
+
+impl BindAddress {
+    /// Configure a client builder to bind connections to this address.
+    fn configure(&self, builder: ClientBuilder) -> Result<ClientBuilder> {
+        match self {
+            Self::Ip(ip) => Ok(builder.local_address(*ip)),
+            #[cfg(any(target_os = "android", target_os = "linux", target_os = "macos"))]
+            Self::Interface(interface) => Ok(builder.interface(interface)),
+            #[cfg(not(any(target_os = "android", target_os = "linux", target_os = "macos")))]
+            Self::Interface(interface) => Err(ParseError(format!(
+                "binding to interface `{}` is not supported on this platform, use an IP address \
+                instead",
+                interface
+            ))),
+        }
+    }
+}
+
+/// The HTTP version used for S3 connections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HttpVersion {
+    /// Use HTTP/2 if the endpoint offers it when connecting, and HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+    /// Always use HTTP/1.1.
+    Http1,
+    /// Always use HTTP/2. Connections fail if the endpoint does not support it.
+    Http2,
+}
+
+/// Options for the HTTP connector of S3 clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectorOptions {
+    bind_address: Option<BindAddress>,
+    max_connections_per_host: Option<usize>,
+    http_version: Option<HttpVersion>,
+    tcp_keepalive: Option<Duration>,
+}
+
+impl ConnectorOptions {
+    /// Bind connections to a local address.
+    pub fn set_bind_address(mut self, bind_address: Option<BindAddress>) -> Self {
+        self.bind_address = bind_address;
+        self
+    }
+
+    /// Limit the number of simultaneous connections to each host.
+    pub fn set_max_connections_per_host(mut self, max_connections: Option<usize>) -> Self {
+        self.max_connections_per_host = max_connections;
+        self
+    }
+
+    /// Set the HTTP version of connections.
+    pub fn set_http_version(mut self, http_version: Option<HttpVersion>) -> Self {
+        self.http_version = http_version;
+        self
+    }
+
+    /// Send TCP keepalive probes on idle connections at this interval.
+    pub fn set_tcp_keepalive(mut self, tcp_keepalive: Option<Duration>) -> Self {
+        self.tcp_keepalive = tcp_keepalive;
+        self
+    }
+
+    /// Whether any option is set, so that the default connector of the SDK cannot be used.
+    pub fn is_set(&self) -> bool {
+        self != &Self::default()
+    }
+
+    /// Create a client builder with these options and the timeouts of the SDK. Redirects are
+    /// not followed, the same as the default connector of the SDK, so that signed requests
+    /// are never sent to another location.
+    fn client_builder(&self, timeouts: Timeouts) -> Result<ClientBuilder> {
+        let mut builder = Client::builder().redirect(Policy::none());
+        let (connect_timeout, read_timeout) = timeouts;
+        if let Some(connect_timeout) = connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(read_timeout) = read_timeout {
+            builder = builder.read_timeout(read_timeout);
+        }
+        if let Some(bind_address) = &self.bind_address {
+            builder = bind_address.configure(builder)?;
+        }
+        if let Some(max_connections) = self.max_connections_per_host {
+            builder = builder.pool_max_idle_per_host(max_connections);
+        }
+        builder = match self.http_version.unwrap_or_default() {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+
+        Ok(builder.tcp_keepalive(self.tcp_keepalive))
+    }
+}
+
+/// The connect and read timeouts of the SDK.
+type Timeouts = (Option<Duration>, Option<Duration>);
+
+/// An HTTP client for the S3 SDK which applies the connector options to every connection.
+/// Connections are pooled and shared between all clients created from the same
+/// `ConnectorHttpClient` which use the same timeouts.
+#[derive(Debug, Clone)]
+pub struct ConnectorHttpClient {
+    options: ConnectorOptions,
+    clients: Arc<Mutex<HashMap<Timeouts, Client>>>,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl ConnectorHttpClient {
+    /// Create a client with the connector options. This returns an error if the options are
+    /// not supported.
+    pub fn new(options: &ConnectorOptions) -> Result<Self> {
+        let client = options.client_builder((None, None))?.build()?;
+
+        Ok(Self {
+            options: options.clone(),
+            clients: Arc::new(Mutex::new(HashMap::from([((None, None), client)]))),
+            hosts: Default::default(),
+        })
+    }
+
+    /// Get the client for the timeouts of the SDK, creating it if it does not exist.
+    fn client(&self, timeouts: Timeouts) -> Client {
+        let mut clients = self.clients.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(client) = clients.get(&timeouts) {
+            return client.clone();
+        }
+
+        // The options were already validated when creating the default client, so this only
+        // falls back to it if the client cannot be built for another reason.
+        let client = match self
+            .options
+            .client_builder(timeouts)
+            .and_then(|builder| Ok(builder.build()?))
+        {
+            Ok(client) => client,
+            Err(_) => clients[&(None, None)].clone(),
+        };
+        clients.insert(timeouts, client.clone());
+        client
+    }
+}
+
+/// The connector for a client with the timeouts of the SDK.
+#[derive(Debug, Clone)]
+struct Connector {
+    client: Client,
+    max_connections_per_host: Option<usize>,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl Connector {
+    /// Wait for the host of the request to accept another request, if the number of requests
+    /// per host is limited.
+    async fn acquire(&self, request: &HttpRequest) -> Option<OwnedSemaphorePermit> {
+        let max_connections = self.max_connections_per_host?;
+        let host = request
+            .uri()
+            .split('/')
+            .nth(2)
+            .unwrap_or_default()
+            .to_string();
+        let semaphore = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(max_connections)))
+            .clone();
+
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Send a request and convert the response for the SDK.
+    async fn send(self, request: HttpRequest) -> result::Result<HttpResponse, ConnectorError> {
+        let permit = self.acquire(&request).await;
+
+        let request = request
+            .try_into_http1x()
+            .map_err(|err| ConnectorError::user(err.into()))?;
+        let request = Request::try_from(request.map(Body::wrap))
+            .map_err(|err| ConnectorError::user(err.into()))?;
+
+        // Errors other than timeouts are treated as IO errors, such as dropped connections, so
+        // that the SDK retries them.
+        let response = self.client.execute(request).await.map_err(|err| {
+            if err.is_timeout() {
+                ConnectorError::timeout(err.into())
+            } else {
+                ConnectorError::io(err.into())
+            }
+        })?;
+
+        // The permit is released once the headers arrive rather than with the body, as the body
+        // of a download can be the body of an upload to the same host, which would otherwise
+        // wait for the permit that the download holds.
+        drop(permit);
+
+        let response = http::Response::<Body>::from(response).map(SdkBody::from_body_1_x);
+        HttpResponse::try_from(response).map_err(|err| ConnectorError::other(err.into(), None))
+    }
+}
+
+impl HttpConnector for Connector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        HttpConnectorFuture::new(self.clone().send(request))
+    }
+}
+
+impl HttpClient for ConnectorHttpClient {
+    fn http_connector(
+        &self,
+        settings: &HttpConnectorSettings,
+        _components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(Connector {
+            client: self.client((settings.connect_timeout(), settings.read_timeout())),
+            max_connections_per_host: self.options.max_connections_per_host,
+            hosts: self.hosts.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aws_sdk_s3::Client as S3Client;
+    use aws_sdk_s3::config::retry::RetryConfig;
+    use aws_sdk_s3::config::timeout::TimeoutConfig;
+    use aws_sdk_s3::config::{BehaviorVersion, Builder, Region, RequestChecksumCalculation};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::time::timeout;
+
+    /// Create an S3 client that sends requests to the endpoint through the connector.
+    fn s3_client(endpoint: String, config: Builder) -> anyhow::Result<S3Client> {
+        let config = config
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .http_client(ConnectorHttpClient::new(
+                &ConnectorOptions::default().set_http_version(Some(HttpVersion::Http1)),
+            )?)
+            .build();
+
+        Ok(S3Client::from_conf(config))
+    }
+
+    #[test]
+    fn parse_bind_address() {
+        assert_eq!(
+            BindAddress::from_str("10.0.0.5").unwrap(),
+            BindAddress::Ip(IpAddr::from([10, 0, 0, 5]))
+        );
+        assert_eq!(
+            BindAddress::from_str("::1").unwrap(),
+            BindAddress::Ip("::1".parse().unwrap())
+        );
+        assert_eq!(
+            BindAddress::from_str("eth1").unwrap(),
+            BindAddress::Interface("eth1".to_string())
+        );
+        assert!(BindAddress::from_str("").is_err());
+        assert!(BindAddress::from_str("eth 1").is_err());
+    }
+
+    #[test]
+    fn connector_options_are_set() {
+        assert!(!ConnectorOptions::default().is_set());
+        assert!(
+            ConnectorOptions::default()
+                .set_http_version(Some(HttpVersion::Http1))
+                .is_set()
+        );
+        assert!(
+            ConnectorOptions::default()
+                .set_max_connections_per_host(Some(64))
+                .is_set()
+        );
+    }
+
+    #[tokio::test]
+    async fn connector_client_sends_requests() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, peer) = listener.accept().await?;
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).await?;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\netag: \"abc\"\r\n\r\n")
+                .await?;
+            anyhow::Ok(peer.ip())
+        });
+
+        let options = ConnectorOptions::default()
+            .set_bind_address(Some(BindAddress::Ip(IpAddr::from([127, 0, 0, 1]))))
+            .set_max_connections_per_host(Some(1))
+            .set_http_version(Some(HttpVersion::Http1))
+            .set_tcp_keepalive(Some(Duration::from_secs(30)));
+        let config = Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .http_client(ConnectorHttpClient::new(&options)?)
+            .build();
+        let output = S3Client::from_conf(config)
+            .head_object()
+            .bucket("bucket")
+            .key("key")
+            .send()
+            .await?;
+
+        assert_eq!(output.e_tag(), Some("\"abc\""));
+        assert_eq!(output.content_length(), Some(5));
+        assert_eq!(server.await??, IpAddr::from([127, 0, 0, 1]));
+
+        Ok(())
+    }
+
+    /// Serve `GET` requests with a body of `hello` and `PUT` requests with an empty response,
+    /// handling each connection separately so that requests can overlap.
+    async fn serve_get_put() -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut head = String::new();
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await? == 0 {
+                                return anyhow::Ok(());
+                            }
+                            if let Some(length) =
+                                line.to_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = length.trim().parse()?;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            head.push_str(&line);
+                        }
+
+                        stream.read_exact(&mut vec![0; content_length]).await?;
+                        let response: &[u8] = if head.starts_with("GET") {
+                            b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello"
+                        } else {
+                            b"HTTP/1.1 200 OK\r\netag: \"abc\"\r\ncontent-length: 0\r\n\r\n"
+                        };
+                        stream.write_all(response).await?;
+                    }
+                });
+            }
+        });
+
+        Ok(endpoint)
+    }
+
+    #[tokio::test]
+    async fn connector_client_download_upload_same_host() -> anyhow::Result<()> {
+        let endpoint = serve_get_put().await?;
+        let options = ConnectorOptions::default()
+            .set_max_connections_per_host(Some(1))
+            .set_http_version(Some(HttpVersion::Http1));
+        let config = Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .http_client(ConnectorHttpClient::new(&options)?)
+            .build();
+        let client = S3Client::from_conf(config);
+
+        // The upload reads from the body of the download, so it must be able to send its
+        // request while the download body is still open.
+        let copy = async {
+            let download = client.get_object().bucket("bucket").key("a").send().await?;
+            let upload = client
+                .put_object()
+                .bucket("bucket")
+                .key("b")
+                .content_length(5)
+                .body(download.body)
+                .send()
+                .await?;
+            anyhow::Ok(upload)
+        };
+        let upload = timeout(Duration::from_secs(10), copy).await??;
+
+        assert_eq!(upload.e_tag(), Some("\"abc\""));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connector_client_retries_dropped_connections() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            // The first connection is dropped after reading the request.
+            let (mut stream, _) = listener.accept().await?;
+            let _ = stream.read(&mut [0; 4096]).await?;
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await?;
+            let _ = stream.read(&mut [0; 4096]).await?;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n")
+                .await?;
+            anyhow::Ok(())
+        });
+
+        let output = s3_client(endpoint, Builder::new())?
+            .head_object()
+            .bucket("bucket")
+            .key("key")
+            .send()
+            .await?;
+
+        assert_eq!(output.content_length(), Some(5));
+        server.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connector_client_does_not_follow_redirects() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let _ = stream.read(&mut [0; 4096]).await?;
+            stream
+                .write_all(
+                    b"HTTP/1.1 307 Temporary Redirect\r\nlocation: /other/key\r\n\
+                    content-length: 0\r\n\r\n",
+                )
+                .await?;
+
+            // A followed redirect would send another request.
+            let mut request = [0; 4096];
+            let n = timeout(Duration::from_millis(500), stream.read(&mut request))
+                .await
+                .unwrap_or(Ok(0))?;
+            anyhow::Ok(n)
+        });
+
+        let result = s3_client(
+            endpoint,
+            Builder::new().retry_config(RetryConfig::disabled()),
+        )?
+        .head_object()
+        .bucket("bucket")
+        .key("key")
+        .send()
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(server.await??, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connector_client_applies_sdk_timeouts() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let _server = tokio::spawn(async move {
+            // Accept the connection but never respond.
+            let (_stream, _) = listener.accept().await?;
+            std::future::pending::<anyhow::Result<()>>().await
+        });
+
+        let client = s3_client(
+            endpoint,
+            Builder::new()
+                .retry_config(RetryConfig::disabled())
+                .timeout_config(
+                    TimeoutConfig::builder()
+                        .read_timeout(Duration::from_millis(100))
+                        .build(),
+                ),
+        )?;
+        let result = timeout(
+            Duration::from_secs(10),
+            client.head_object().bucket("bucket").key("key").send(),
+        )
+        .await?;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
@@ -2,20 +2,23 @@
 //!
 
 use crate::checksum::file::SumsFile;
+use crate::checksum::{Ctx, checksum_algorithm};
 use crate::cli::MetadataCopy;
-use crate::error::Error::{CopyError, ParseError};
+use crate::error::Error::{Cancelled, CopyError, ParseError};
 use crate::error::{ApiError, Error, Result};
-use crate::io::S3Client;
+use crate::io::copy::limits::ProviderLimits;
 use crate::io::copy::{
-    CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part, Reopen,
+    CompleteOptions, CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part, Reopen,
 };
+use crate::io::{Provider, S3Client};
 use aws_sdk_s3::operation::get_object_tagging::{GetObjectTaggingError, GetObjectTaggingOutput};
 use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
 use aws_sdk_s3::operation::put_object::{PutObjectError, PutObjectOutput};
 use aws_sdk_s3::operation::upload_part::UploadPartOutput;
+use aws_sdk_s3::types;
 use aws_sdk_s3::types::{
-    ChecksumAlgorithm, CompletedMultipartUpload, CompletedPart, CopyPartResult, MetadataDirective,
-    TaggingDirective,
+    ChecksumAlgorithm, ChecksumMode, CompletedMultipartUpload, CompletedPart, CopyPartResult,
+    MetadataDirective, ObjectAttributes, ObjectPart, StorageClass, TaggingDirective,
 };
 use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
 use aws_smithy_runtime_api::client::result::SdkError;
@@ -26,14 +29,17 @@ use futures_util::stream::poll_fn;
 use futures_util::{StreamExt, TryStreamExt};
 use http_body::Frame;
 use http_body_util::StreamBody;
-use std::collections::HashMap;
+use md5::{Digest, Md5};
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::result;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::io::AsyncRead;
-use tokio::sync::mpsc;
+use tokio::sync::{OnceCell, mpsc};
+use tokio::time::sleep;
 use tokio_util::io::ReaderStream;
 
 /// The number of chunks buffered when re-trying an SDK body.
@@ -42,6 +48,20 @@ const REOPEN_CHANNEL_CAPACITY: usize = 16;
 /// The read buffer capacity used when streaming a reader into an upload body.
 const READER_STREAM_CAPACITY: usize = 64 * 1024;
 
+/// The number of times a multipart upload request is retried if it fails because the credentials
+/// expired. Each retry resolves the credentials again, so long transfers can continue the same
+/// upload with refreshed credentials.
+const EXPIRED_CREDENTIALS_RETRIES: usize = 2;
+/// The initial backoff before retrying a request that failed because the credentials expired.
+const EXPIRED_CREDENTIALS_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The number of times a part copy is retried if its checksum does not match the checksum of the
+/// same part in the source object.
+const PART_MISMATCH_RETRIES: usize = 2;
+
+/// The maximum number of parts returned by a single `GetObjectAttributes` call.
+const MAX_ATTRIBUTE_PARTS: i32 = 1000;
+
 /// Build an S3 sums object.
 #[derive(Debug, Default)]
 pub struct S3Builder {
@@ -50,6 +70,8 @@ pub struct S3Builder {
     tag_mode: MetadataCopy,
     source: Option<BucketKey>,
     destination: Option<BucketKey>,
+    complete_options: CompleteOptions,
+    storage_class: Option<StorageClass>,
 }
 
 impl S3Builder {
@@ -89,6 +111,18 @@ impl S3Builder {
         self
     }
 
+    /// Set the options for completing multipart uploads.
+    pub fn with_complete_options(mut self, complete_options: CompleteOptions) -> Self {
+        self.complete_options = complete_options;
+        self
+    }
+
+    /// Set the storage class of objects written to the destination.
+    pub fn set_storage_class(mut self, storage_class: Option<StorageClass>) -> Self {
+        self.storage_class = storage_class;
+        self
+    }
+
     /// Build using the client, bucket and key.
     pub fn build(self) -> Result<S3> {
         let error_fn = || {
@@ -97,14 +131,18 @@ impl S3Builder {
             )
         };
 
-        Ok((
+        let mut s3: S3 = (
             self.client.ok_or_else(error_fn)?,
             self.metadata_mode,
             self.tag_mode,
             self.source,
             self.destination,
         )
-            .into())
+            .into();
+        s3.complete_options = self.complete_options;
+        s3.storage_class = self.storage_class;
+
+        Ok(s3)
     }
 }
 
@@ -146,6 +184,7 @@ impl From<(CopyPartResult, u64, String)> for CopyResult {
                 md5: part.checksum_md5,
                 e_tag: part.e_tag,
                 part_number,
+                size: None,
             },
             upload_id,
         )
@@ -169,6 +208,7 @@ impl From<(UploadPartOutput, u64, String)> for CopyResult {
                 md5: part.checksum_md5,
                 e_tag: part.e_tag,
                 part_number,
+                size: None,
             },
             upload_id,
         )
@@ -197,8 +237,62 @@ impl TryFrom<Part> for CompletedPart {
     }
 }
 
-/// Represents an S3 bucket and key.
+impl Part {
+    /// Get the part checksum for the checksum algorithm.
+    pub fn checksum(&self, algorithm: &ChecksumAlgorithm) -> Option<&str> {
+        match algorithm {
+            ChecksumAlgorithm::Crc32 => self.crc32.as_deref(),
+            ChecksumAlgorithm::Crc32C => self.crc32_c.as_deref(),
+            ChecksumAlgorithm::Crc64Nvme => self.crc64_nvme.as_deref(),
+            ChecksumAlgorithm::Md5 => self.md5.as_deref(),
+            ChecksumAlgorithm::Sha1 => self.sha1.as_deref(),
+            ChecksumAlgorithm::Sha256 => self.sha256.as_deref(),
+            ChecksumAlgorithm::Sha512 => self.sha512.as_deref(),
+            ChecksumAlgorithm::Xxhash3 => self.xxhash3.as_deref(),
+            ChecksumAlgorithm::Xxhash64 => self.xxhash64.as_deref(),
+            ChecksumAlgorithm::Xxhash128 => self.xxhash128.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// A part of the source object with its offset in the object.
 #[derive(Debug, Clone)]
+struct SourcePart {
+    start: u64,
+    part: ObjectPart,
+}
+
+impl SourcePart {
+    /// Get the checksum of this part if it covers exactly the range of the multipart options.
+    fn checksum(
+        &self,
+        multi_part: &MultiPartOptions,
+        algorithm: &ChecksumAlgorithm,
+    ) -> Option<&str> {
+        let size = self.part.size.and_then(|size| u64::try_from(size).ok());
+        if self.start != multi_part.start || size != Some(multi_part.bytes_transferred()) {
+            return None;
+        }
+
+        match algorithm {
+            ChecksumAlgorithm::Crc32 => self.part.checksum_crc32(),
+            ChecksumAlgorithm::Crc32C => self.part.checksum_crc32_c(),
+            ChecksumAlgorithm::Crc64Nvme => self.part.checksum_crc64_nvme(),
+            ChecksumAlgorithm::Md5 => self.part.checksum_md5(),
+            ChecksumAlgorithm::Sha1 => self.part.checksum_sha1(),
+            ChecksumAlgorithm::Sha256 => self.part.checksum_sha256(),
+            ChecksumAlgorithm::Sha512 => self.part.checksum_sha512(),
+            ChecksumAlgorithm::Xxhash3 => self.part.checksum_xxhash3(),
+            ChecksumAlgorithm::Xxhash64 => self.part.checksum_xxhash64(),
+            ChecksumAlgorithm::Xxhash128 => self.part.checksum_xxhash128(),
+            _ => None,
+        }
+    }
+}
+
+/// Represents an S3 bucket and key.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BucketKey {
     bucket: String,
     key: String,
@@ -212,6 +306,9 @@ pub struct S3 {
     tag_mode: MetadataCopy,
     source: Option<BucketKey>,
     destination: Option<BucketKey>,
+    complete_options: CompleteOptions,
+    storage_class: Option<StorageClass>,
+    source_parts: Arc<OnceCell<Option<HashMap<u64, SourcePart>>>>,
 }
 
 impl S3 {
@@ -239,18 +336,29 @@ impl S3 {
             .map(u64::try_from)
             .transpose()?
             .ok_or_else(|| Error::aws_error("missing size".to_string()))?;
-        let metadata = head.metadata;
+        let mut state = CopyState::new(size, tags, head.metadata);
+        state.set_e_tag(head.e_tag);
 
-        Ok(CopyState::new(size, tags, metadata))
+        Ok(state)
     }
 
-    /// Get the head object output.
+    /// Get the head object output. This uses the same checksum mode as reading sums so that
+    /// the output can be shared through the client's metadata cache.
     pub async fn head_object(
         &self,
         key: &str,
         bucket: &str,
     ) -> result::Result<HeadObjectOutput, SdkError<HeadObjectError, HttpResponse>> {
-        self.client.head_object(|b| b.bucket(bucket).key(key)).await
+        self.retry_expired_credentials(|| {
+            self.client.head_object(|mut b| {
+                b = b.bucket(bucket).key(key);
+                if !self.client.no_checksum_mode() {
+                    b = b.checksum_mode(ChecksumMode::Enabled);
+                }
+                b
+            })
+        })
+        .await
     }
 
     /// Get the object tagging.
@@ -278,9 +386,80 @@ impl S3 {
             tag_mode,
             source,
             destination,
+            complete_options: Default::default(),
+            storage_class: None,
+            source_parts: Default::default(),
         }
     }
 
+    /// Get the parts of the source object from `GetObjectAttributes`, which are fetched once
+    /// and shared between clones. Returns `None` if the source was not uploaded using multipart
+    /// uploads or the parts are not available.
+    async fn source_parts(&self) -> Option<&HashMap<u64, SourcePart>> {
+        self.source_parts
+            .get_or_init(|| async {
+                let source = self.source.as_ref()?;
+                if self.client.no_get_object_attributes() {
+                    return None;
+                }
+
+                let mut parts = HashMap::new();
+                let mut start = 0;
+                let mut marker = None;
+                loop {
+                    let output = self
+                        .client
+                        .get_object_attributes(|b| {
+                            b.bucket(&source.bucket)
+                                .key(&source.key)
+                                .object_attributes(ObjectAttributes::ObjectParts)
+                                .max_parts(MAX_ATTRIBUTE_PARTS)
+                                .set_part_number_marker(marker.take())
+                        })
+                        .await
+                        .ok()?;
+                    let object_parts = output.object_parts?;
+
+                    for part in object_parts.parts() {
+                        let part_number = u64::try_from(part.part_number?).ok()?;
+                        parts.insert(
+                            part_number,
+                            SourcePart {
+                                start,
+                                part: part.clone(),
+                            },
+                        );
+                        start += u64::try_from(part.size?).ok()?;
+                    }
+
+                    marker = object_parts.next_part_number_marker;
+                    if object_parts.is_truncated != Some(true) || marker.is_none() {
+                        break;
+                    }
+                }
+
+                Some(parts)
+            })
+            .await
+            .as_ref()
+    }
+
+    /// Get the expected checksum of a part copied from the source. This is the checksum of the
+    /// source part with the same part number, if the source part has a checksum for the
+    /// algorithm and covers the same range.
+    async fn expected_part_checksum(
+        &self,
+        part_number: u64,
+        multi_part: &MultiPartOptions,
+        algorithm: &ChecksumAlgorithm,
+    ) -> Option<String> {
+        self.source_parts()
+            .await?
+            .get(&part_number)?
+            .checksum(multi_part, algorithm)
+            .map(str::to_string)
+    }
+
     /// Create a new multipart upload.
     pub async fn get_multipart_upload(
         &self,
@@ -296,32 +475,37 @@ impl S3 {
                     b.set_tagging(tagging)
                         .set_metadata(metadata)
                         .set_checksum_algorithm(additional_checksum)
+                        .set_storage_class(self.storage_class.clone())
                         .bucket(bucket)
                         .key(key)
                 })
                 .await
         };
 
-        let result = do_upload(
-            tagging.clone(),
-            metadata.clone(),
-            additional_checksum.clone(),
-        )
-        .await;
+        let result = self
+            .retry_without_checksum(additional_checksum.clone(), |additional_checksum| {
+                do_upload(tagging.clone(), metadata.clone(), additional_checksum)
+            })
+            .await;
 
         // Retry if this is a best effort copy and the error was access denied.
         let (upload, err) = if let Err(ref err) = result {
             let err = ApiError::from(err);
             if self.tag_mode.is_best_effort() && err.is_access_denied() {
-                (
-                    do_upload(None, metadata, additional_checksum).await?,
-                    vec![err],
-                )
+                let additional_checksum =
+                    additional_checksum.filter(|_| !self.client.checksums_rejected());
+                let (upload, mut errs) = self
+                    .retry_without_checksum(additional_checksum, |additional_checksum| {
+                        do_upload(None, metadata.clone(), additional_checksum)
+                    })
+                    .await?;
+                errs.push(err);
+                (upload, errs)
             } else {
-                (result?, vec![])
+                result?
             }
         } else {
-            (result?, vec![])
+            result?
         };
 
         Ok((
@@ -348,17 +532,26 @@ impl S3 {
     pub async fn copy_object(&self, state: &CopyState) -> Result<CopyResult> {
         let size = state.size();
 
-        let (tagging, tagging_set) = self.tagging_directive();
-        let (metadata, metadata_set) = self.metadata_directive();
+        let (tagging, tagging_set) = self.tagging_directive(state);
+        let (metadata, metadata_set) = self.metadata_directive(state);
 
         let source = self.get_source()?;
         let destination = self.get_destination()?;
 
-        let additional_checksum = state.additional_ctx().map(ChecksumAlgorithm::from);
-        let do_copy = |tagging, tagging_set, metadata, metadata_set, additional_checksum| async {
-            self.client
-                .copy_object(move |b| {
+        let additional_checksum = self.additional_checksum(state);
+        let do_copy = |tagging: TaggingDirective,
+                       tagging_set: Option<String>,
+                       metadata: MetadataDirective,
+                       metadata_set: Option<HashMap<String, String>>,
+                       additional_checksum: Option<ChecksumAlgorithm>| async move {
+            self.retry_expired_credentials(|| {
+                let storage_class = self.storage_class.clone();
+                let (tagging, tagging_set) = (tagging.clone(), tagging_set.clone());
+                let (metadata, metadata_set) = (metadata.clone(), metadata_set.clone());
+                let additional_checksum = additional_checksum.clone();
+                self.client.copy_object(move |b| {
                     b.tagging_directive(tagging)
+                        .set_storage_class(storage_class)
                         .set_tagging(tagging_set)
                         .metadata_directive(metadata)
                         .set_metadata(metadata_set)
@@ -367,51 +560,91 @@ impl S3 {
                         .key(&destination.key)
                         .bucket(&destination.bucket)
                 })
-                .await
+            })
+            .await
         };
 
-        let result = do_copy(
-            tagging,
-            tagging_set,
-            metadata.clone(),
-            metadata_set.clone(),
-            additional_checksum.clone(),
-        )
-        .await;
+        let result = self
+            .retry_without_checksum(additional_checksum, |additional_checksum| {
+                do_copy(
+                    tagging.clone(),
+                    tagging_set.clone(),
+                    metadata.clone(),
+                    metadata_set.clone(),
+                    additional_checksum,
+                )
+            })
+            .await;
 
         // Retry if this is a best effort copy and the error was access denied.
         let (_, err) = if let Err(ref err) = result {
             let err = ApiError::from(err);
             if self.tag_mode.is_best_effort() && err.is_access_denied() {
-                let result = do_copy(
-                    TaggingDirective::Replace,
-                    Some("".to_string()),
-                    metadata,
-                    metadata_set.clone(),
-                    additional_checksum,
-                )
-                .await?;
-                (result, vec![err])
+                let (result, mut errs) = self
+                    .retry_without_checksum(
+                        self.additional_checksum(state),
+                        |additional_checksum| {
+                            do_copy(
+                                TaggingDirective::Replace,
+                                Some("".to_string()),
+                                metadata.clone(),
+                                metadata_set.clone(),
+                                additional_checksum,
+                            )
+                        },
+                    )
+                    .await?;
+                errs.push(err);
+                (result, errs)
             } else {
-                (result?, vec![])
+                result?
             }
         } else {
-            (result?, vec![])
+            result?
         };
 
         CopyResult::new(None, None, size, err)
     }
 
+    /// The additional checksum to send with uploads and copies, unless the destination does not
+    /// support it, in which case the copy is verified using the `ETag` and computed sums instead.
+    fn additional_checksum(&self, state: &CopyState) -> Option<ChecksumAlgorithm> {
+        self.supported_checksum(state)
+            .filter(|_| !self.client.checksums_rejected())
+    }
+
+    /// The additional checksum of the copy if the destination supports it.
+    fn supported_checksum(&self, state: &CopyState) -> Option<ChecksumAlgorithm> {
+        state
+            .additional_ctx()
+            .map(checksum_algorithm)
+            .filter(|algorithm| self.client.supports_checksum(algorithm))
+    }
+
+    /// Whether the additional checksum of the copy was not sent because the destination
+    /// rejected additional checksums.
+    fn checksum_rejected(&self, state: &CopyState) -> bool {
+        self.supported_checksum(state).is_some() && self.client.checksums_rejected()
+    }
+
     /// Get the copy source.
     fn copy_source(key: &str, bucket: &str) -> String {
         format!("{}/{}", bucket, key)
     }
 
-    /// Extract the metadata directive and metadata to be set.
-    fn metadata_directive(&self) -> (MetadataDirective, Option<HashMap<String, String>>) {
+    /// Extract the metadata directive and metadata to be set. Filtered metadata replaces the
+    /// source metadata.
+    fn metadata_directive(
+        &self,
+        state: &CopyState,
+    ) -> (MetadataDirective, Option<HashMap<String, String>>) {
         let (metadata, metadata_set) =
             if self.metadata_mode.is_copy() || self.metadata_mode.is_best_effort() {
-                (MetadataDirective::Copy, None)
+                if state.replace_metadata() {
+                    (MetadataDirective::Replace, state.metadata())
+                } else {
+                    (MetadataDirective::Copy, None)
+                }
             } else {
                 (MetadataDirective::Replace, Some(HashMap::new()))
             };
@@ -419,10 +652,14 @@ impl S3 {
         (metadata, metadata_set)
     }
 
-    /// Extract the tagging directive and tags to be set.
-    fn tagging_directive(&self) -> (TaggingDirective, Option<String>) {
+    /// Extract the tagging directive and tags to be set. Filtered tags replace the source tags.
+    fn tagging_directive(&self, state: &CopyState) -> (TaggingDirective, Option<String>) {
         let (tagging, tagging_set) = if self.tag_mode.is_copy() || self.tag_mode.is_best_effort() {
-            (TaggingDirective::Copy, None)
+            if state.replace_tags() {
+                (TaggingDirective::Replace, state.tags())
+            } else {
+                (TaggingDirective::Copy, None)
+            }
         } else {
             (TaggingDirective::Replace, Some("".to_string()))
         };
@@ -434,13 +671,39 @@ impl S3 {
         &self,
         multi_part: MultiPartOptions,
         state: &CopyState,
+    ) -> Result<CopyResult> {
+        self.copy_part_from(self.get_source()?, multi_part, state)
+            .await
+    }
+
+    /// Reuse a range of the existing destination object as a part of a multipart upload.
+    pub async fn reuse_part(
+        &self,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let mut result = self
+            .copy_part_from(self.get_destination()?, multi_part, state)
+            .await?;
+
+        result.bytes_reused = result.bytes_transferred;
+        result.bytes_transferred = 0;
+        Ok(result)
+    }
+
+    /// Copy a part from the source object into the multipart upload of the destination, or
+    /// complete the upload if there is no part number.
+    async fn copy_part_from(
+        &self,
+        source: &BucketKey,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
     ) -> Result<CopyResult> {
         let tagging = state.tags();
 
-        let source = self.get_source()?;
         let destination = self.get_destination()?;
 
-        let additional_checksum = state.additional_ctx().map(ChecksumAlgorithm::from);
+        let additional_checksum = self.additional_checksum(state);
 
         // Create the upload id if it doesn't exist or use the existing one.
         let (upload_id, api_errors) = if let Some(upload_id) = &multi_part.upload_id {
@@ -451,7 +714,7 @@ impl S3 {
                 &destination.bucket,
                 tagging,
                 state.metadata(),
-                additional_checksum,
+                additional_checksum.clone(),
             )
             .await?
         };
@@ -461,40 +724,79 @@ impl S3 {
             let range = multi_part
                 .format_range()
                 .ok_or_else(|| Error::aws_error("invalid range".to_string()))?;
-            let response = self
-                .client
-                .upload_part_copy(|b| {
-                    b.upload_id(&upload_id)
-                        .part_number(part_number_i32)
-                        .key(&destination.key)
-                        .bucket(&destination.bucket)
-                        .copy_source(Self::copy_source(&source.key, &source.bucket))
-                        .copy_source_range(range)
-                })
-                .await?;
+            // Parts copied from the source are checked against the source part checksums, if the
+            // source has parts with the same ranges.
+            let expected = match &additional_checksum {
+                Some(algorithm) if self.source.as_ref() == Some(source) => self
+                    .expected_part_checksum(part_number, &multi_part, algorithm)
+                    .await
+                    .map(|checksum| (algorithm, checksum)),
+                _ => None,
+            };
 
-            let part = response
-                .copy_part_result
-                .ok_or_else(|| Error::aws_error("missing copy part result".to_string()))?;
+            let mut mismatches = 0;
+            let mut result = loop {
+                let response = self
+                    .retry_expired_credentials(|| {
+                        self.client.upload_part_copy(|b| {
+                            b.upload_id(&upload_id)
+                                .part_number(part_number_i32)
+                                .key(&destination.key)
+                                .bucket(&destination.bucket)
+                                .copy_source(Self::copy_source(&source.key, &source.bucket))
+                                .copy_source_range(&range)
+                        })
+                    })
+                    .await?;
+
+                let part = response
+                    .copy_part_result
+                    .ok_or_else(|| Error::aws_error("missing copy part result".to_string()))?;
+                let result: CopyResult = (part, part_number, upload_id.clone()).into();
+
+                let copied = result
+                    .part
+                    .as_ref()
+                    .zip(expected.as_ref())
+                    .and_then(|(part, (algorithm, _))| part.checksum(algorithm));
+                match (copied, &expected) {
+                    (Some(copied), Some((algorithm, expected))) if copied != expected => {
+                        if mismatches >= PART_MISMATCH_RETRIES {
+                            return Err(CopyError(format!(
+                                "part {} copied from `{}` has {} checksum `{}` but the source part has `{}`",
+                                part_number,
+                                Provider::format_s3(&source.bucket, &source.key),
+                                algorithm,
+                                copied,
+                                expected
+                            )));
+                        }
+                        mismatches += 1;
+                    }
+                    _ => break result,
+                }
+            };
 
-            let mut result: CopyResult = (part, part_number, upload_id).into();
             result.bytes_transferred = multi_part.bytes_transferred();
+            result.set_part_size(multi_part.bytes_transferred());
             result = result.with_api_errors(api_errors)?;
+            result.n_retries += u64::try_from(mismatches)?;
 
             Ok(result)
         } else {
             let parts = multi_part.parts.ok_or_else(|| {
                 Error::aws_error("missing parts for multipart completion".to_string())
             })?;
-            self.complete_multipart_upload(
-                &destination.key,
-                &destination.bucket,
-                upload_id.to_string(),
-                parts,
-            )
-            .await?;
+            let api_errors = self
+                .complete_multipart_upload(
+                    &destination.key,
+                    &destination.bucket,
+                    upload_id.to_string(),
+                    parts,
+                )
+                .await?;
 
-            CopyResult::new(None, Some(upload_id), 0, vec![])
+            CopyResult::new(None, Some(upload_id), 0, api_errors)
         }
     }
 
@@ -514,8 +816,11 @@ impl S3 {
             .and_then(|multi_part| multi_part.format_range());
 
         let result = self
-            .client
-            .get_object(|b| b.bucket(&source.bucket).key(&source.key).set_range(range))
+            .retry_expired_credentials(|| {
+                let range = range.clone();
+                self.client
+                    .get_object(|b| b.bucket(&source.bucket).key(&source.key).set_range(range))
+            })
             .await?;
 
         let self_clone = self.clone();
@@ -580,11 +885,6 @@ impl S3 {
         }))
     }
 
-    /// Build the retryable upload body for a copy content.
-    fn upload_body(content: CopyContent) -> ByteStream {
-        Self::retryable_body(Some(content.data), Arc::new(content.reopen))
-    }
-
     /// Put the object to S3 by streaming the content directly to the destination.
     pub async fn put_object(&self, content: CopyContent, state: &CopyState) -> Result<CopyResult> {
         // Best effort tagging needs to reissue the upload without tags.
@@ -593,36 +893,50 @@ impl S3 {
         }
 
         let destination = self.get_destination()?;
-        self.send_put_object(
-            destination,
-            Self::upload_body(content),
-            state.tags(),
-            state.metadata(),
-            state.additional_ctx().map(ChecksumAlgorithm::from),
-            i64::try_from(state.size())?,
-        )
-        .await?;
+        let content_length = i64::try_from(state.size())?;
+
+        // Retries after the first attempt re-read the object using the reopen function.
+        let CopyContent { data, reopen } = content;
+        let (mut data, reopen) = (Some(data), Arc::new(reopen));
+        let (_, api_errors) = self
+            .retry_without_checksum(self.additional_checksum(state), |additional_checksum| {
+                self.send_put_object(
+                    destination,
+                    Self::retryable_body(data.take(), Arc::clone(&reopen)),
+                    state.tags(),
+                    state,
+                    content_length,
+                    additional_checksum,
+                )
+            })
+            .await?;
 
-        CopyResult::new(None, None, state.size(), vec![])
+        Self::put_object_result(state, api_errors)
     }
 
-    /// Send a streaming `PutObject` request to the destination.
+    /// Send a streaming `PutObject` request to the destination. The `Content-MD5` is sent if
+    /// it is known so that S3 validates the uploaded data.
     async fn send_put_object(
         &self,
         destination: &BucketKey,
         body: ByteStream,
         tags: Option<String>,
-        metadata: Option<HashMap<String, String>>,
-        additional_checksum: Option<ChecksumAlgorithm>,
+        state: &CopyState,
         content_length: i64,
+        additional_checksum: Option<ChecksumAlgorithm>,
     ) -> result::Result<PutObjectOutput, SdkError<PutObjectError, HttpResponse>> {
         let bucket = destination.bucket.clone();
         let key = destination.key.clone();
+        let storage_class = self.storage_class.clone();
+        let metadata = state.metadata();
+        let content_md5 = state.content_md5();
         self.client
             .put_object(move |b| {
                 b.set_tagging(tags)
+                    .set_storage_class(storage_class)
                     .set_metadata(metadata)
                     .set_checksum_algorithm(additional_checksum)
+                    .set_content_md5(content_md5)
                     .content_length(content_length)
                     .bucket(bucket)
                     .key(key)
@@ -631,6 +945,14 @@ impl S3 {
             .await
     }
 
+    /// The result of a successful `PutObject`, which records whether the data was validated
+    /// using the `Content-MD5`.
+    fn put_object_result(state: &CopyState, api_errors: Vec<ApiError>) -> Result<CopyResult> {
+        let mut result = CopyResult::new(None, None, state.size(), api_errors)?;
+        result.content_md5_validated = state.content_md5().is_some();
+        Ok(result)
+    }
+
     /// Put the object to S3 for best effort tagging. This will take into account access denied
     /// errors and re-try if needed.
     async fn put_object_best_effort(
@@ -639,25 +961,26 @@ impl S3 {
         state: &CopyState,
     ) -> Result<CopyResult> {
         let destination = self.get_destination()?;
-        let additional_checksum = state.additional_ctx().map(ChecksumAlgorithm::from);
         let content_length = i64::try_from(state.size())?;
 
         let CopyContent { data, reopen } = content;
-        let reopen = Arc::new(reopen);
+        let (mut data, reopen) = (Some(data), Arc::new(reopen));
 
         let result = self
-            .send_put_object(
-                destination,
-                Self::retryable_body(Some(data), Arc::clone(&reopen)),
-                state.tags(),
-                state.metadata(),
-                additional_checksum.clone(),
-                content_length,
-            )
+            .retry_without_checksum(self.additional_checksum(state), |additional_checksum| {
+                self.send_put_object(
+                    destination,
+                    Self::retryable_body(data.take(), Arc::clone(&reopen)),
+                    state.tags(),
+                    state,
+                    content_length,
+                    additional_checksum,
+                )
+            })
             .await;
 
         let err = match result {
-            Ok(_) => return CopyResult::new(None, None, state.size(), vec![]),
+            Ok((_, api_errors)) => return Self::put_object_result(state, api_errors),
             Err(err) => err,
         };
 
@@ -667,17 +990,21 @@ impl S3 {
             return Err(err.into());
         }
 
-        self.send_put_object(
-            destination,
-            Self::retryable_body(None, reopen),
-            None,
-            state.metadata(),
-            additional_checksum,
-            content_length,
-        )
-        .await?;
+        let (_, mut api_errors) = self
+            .retry_without_checksum(self.additional_checksum(state), |additional_checksum| {
+                self.send_put_object(
+                    destination,
+                    Self::retryable_body(None, Arc::clone(&reopen)),
+                    None,
+                    state,
+                    content_length,
+                    additional_checksum,
+                )
+            })
+            .await?;
+        api_errors.push(api_error);
 
-        CopyResult::new(None, None, state.size(), vec![api_error])
+        Self::put_object_result(state, api_errors)
     }
 
     /// Upload objects using multi part uploads.
@@ -689,9 +1016,8 @@ impl S3 {
     ) -> Result<CopyResult> {
         let destination = self.get_destination()?;
 
-        let additional_checksum = state.additional_ctx().map(ChecksumAlgorithm::from);
         // Create the upload id if it doesn't exist or use the existing one.
-        let (upload_id, err) = if let Some(upload_id) = multi_part.upload_id.as_ref() {
+        let (upload_id, mut err) = if let Some(upload_id) = multi_part.upload_id.as_ref() {
             (upload_id.to_string(), vec![])
         } else {
             self.get_multipart_upload(
@@ -699,7 +1025,7 @@ impl S3 {
                 &destination.bucket,
                 state.tags(),
                 state.metadata(),
-                additional_checksum.clone(),
+                self.additional_checksum(state),
             )
             .await?
         };
@@ -707,21 +1033,34 @@ impl S3 {
         if let Some(part_number) = multi_part.part_number {
             let part_number_i32 = i32::try_from(part_number)?;
             let content_length = i64::try_from(multi_part.bytes_transferred())?;
-            let part = self
-                .client
-                .upload_part(|b| {
-                    b.upload_id(&upload_id)
-                        .set_checksum_algorithm(additional_checksum)
-                        .content_length(content_length)
-                        .part_number(part_number_i32)
-                        .key(&destination.key)
-                        .bucket(&destination.bucket)
-                        .body(Self::upload_body(content))
+            let upload_id_ref = upload_id.as_str();
+            // Retries after the first attempt re-read the part using the reopen function.
+            let CopyContent { data, reopen } = content;
+            let (mut data, reopen) = (Some(data), Arc::new(reopen));
+            let (part, rejected) = self
+                .retry_without_checksum(self.additional_checksum(state), |additional_checksum| {
+                    let mut data = data.take();
+                    let reopen = Arc::clone(&reopen);
+                    self.retry_expired_credentials(move || {
+                        let body = Self::retryable_body(data.take(), Arc::clone(&reopen));
+                        let additional_checksum = additional_checksum.clone();
+                        self.client.upload_part(move |b| {
+                            b.upload_id(upload_id_ref)
+                                .set_checksum_algorithm(additional_checksum)
+                                .content_length(content_length)
+                                .part_number(part_number_i32)
+                                .key(&destination.key)
+                                .bucket(&destination.bucket)
+                                .body(body)
+                        })
+                    })
                 })
                 .await?;
+            err.extend(rejected);
 
             let mut result: CopyResult = (part, part_number, upload_id).into();
             result.bytes_transferred = multi_part.bytes_transferred();
+            result.set_part_size(multi_part.bytes_transferred());
             result = result.with_api_errors(err)?;
 
             Ok(result)
@@ -729,122 +1068,514 @@ impl S3 {
             let parts = multi_part.parts.ok_or_else(|| {
                 Error::aws_error("missing parts for multipart completion".to_string())
             })?;
-            self.complete_multipart_upload(
-                &destination.key,
-                &destination.bucket,
-                upload_id.to_string(),
-                parts,
-            )
-            .await?;
+            let mut err = err;
+            err.extend(
+                self.complete_multipart_upload(
+                    &destination.key,
+                    &destination.bucket,
+                    upload_id.to_string(),
+                    parts,
+                )
+                .await?,
+            );
 
             CopyResult::new(None, Some(upload_id), 0, err)
         }
     }
 
-    /// Complete a multipart upload.
+    /// Complete a multipart upload. If the upload cannot be completed, it is aborted so that
+    /// no incomplete upload is left behind, unless aborting is disabled.
     async fn complete_multipart_upload(
         &self,
         key: &str,
         bucket: &str,
         upload_id: String,
+        parts: Vec<Part>,
+    ) -> Result<Vec<ApiError>> {
+        match self
+            .try_complete_multipart_upload(key, bucket, &upload_id, parts)
+            .await
+        {
+            Ok(api_errors) => Ok(api_errors),
+            Err(err) => Err(self
+                .abort_multipart_upload(key, bucket, &upload_id, err)
+                .await),
+        }
+    }
+
+    /// Abort a multipart upload that failed to complete, returning an error that describes the
+    /// failure and what happened to the upload.
+    async fn abort_multipart_upload(
+        &self,
+        key: &str,
+        bucket: &str,
+        upload_id: &str,
+        err: Error,
+    ) -> Error {
+        let failed = format!(
+            "failed to complete multipart upload `{}` for `{}`: {}",
+            upload_id,
+            Provider::format_s3(bucket, key),
+            err
+        );
+        CopyError(
+            self.abort_incomplete_upload(key, bucket, upload_id, failed)
+                .await,
+        )
+    }
+
+    /// Abort an incomplete multipart upload unless aborting is disabled, returning the message
+    /// with what happened to the upload appended.
+    async fn abort_incomplete_upload(
+        &self,
+        key: &str,
+        bucket: &str,
+        upload_id: &str,
+        failed: String,
+    ) -> String {
+        let abort_command = format!(
+            "`aws s3api abort-multipart-upload --bucket {} --key {} --upload-id {}`",
+            bucket, key, upload_id
+        );
+
+        if !self.complete_options.abort_on_failure {
+            return format!(
+                "{}; the incomplete upload was kept and can be removed with {}",
+                failed, abort_command
+            );
+        }
+
+        let result = self
+            .client
+            .abort_multipart_upload(|b| b.bucket(bucket).key(key).upload_id(upload_id))
+            .await;
+        match result {
+            Ok(_) => format!("{}; the upload was aborted", failed),
+            Err(abort_err) => format!(
+                "{}; the upload could not be aborted ({}) and should be removed with {}",
+                failed,
+                ApiError::from(&abort_err),
+                abort_command
+            ),
+        }
+    }
+
+    /// Complete a multipart upload, retrying transient errors with backoff. The parts are
+    /// checked against the parts that were received by S3 before completing, and any
+    /// recoverable API errors from the check are returned.
+    async fn try_complete_multipart_upload(
+        &self,
+        key: &str,
+        bucket: &str,
+        upload_id: &str,
         mut parts: Vec<Part>,
-    ) -> Result<()> {
+    ) -> Result<Vec<ApiError>> {
         // Parts must be ordered.
         parts.sort_by_key(|a| a.part_number);
 
+        let api_errors = self.verify_parts(key, bucket, upload_id, &parts).await?;
+
         let parts = parts
             .into_iter()
             .map(|part| part.try_into())
             .collect::<Result<Vec<_>>>()?;
-        self.client
-            .complete_multipart_upload(|b| {
-                b.bucket(bucket)
-                    .key(key)
-                    .multipart_upload(
-                        CompletedMultipartUpload::builder()
-                            .set_parts(Some(parts))
-                            .build(),
-                    )
-                    .upload_id(upload_id)
-            })
-            .await?;
-
-        Ok(())
-    }
-}
+        let mut attempt = 0;
+        let mut backoff = self.complete_options.backoff;
+        loop {
+            let result = self
+                .client
+                .complete_multipart_upload(|b| {
+                    b.bucket(bucket)
+                        .key(key)
+                        .multipart_upload(
+                            CompletedMultipartUpload::builder()
+                                .set_parts(Some(parts.clone()))
+                                .build(),
+                        )
+                        .upload_id(upload_id)
+                })
+                .await;
 
-#[async_trait::async_trait]
-impl ObjectCopy for S3 {
-    async fn copy(
-        &self,
-        multi_part: Option<MultiPartOptions>,
-        state: &CopyState,
-    ) -> Result<CopyResult> {
-        if let Some(multi_part) = multi_part {
-            self.copy_object_multipart(multi_part, state).await
-        } else {
-            self.copy_object(state).await
+            match result {
+                Err(err) if attempt < self.complete_options.retries && Self::is_transient(&err) => {
+                    sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                // An earlier attempt, including one retried by the SDK, may have completed the
+                // upload even though its response was lost, in which case the upload no longer
+                // exists.
+                Err(err)
+                    if ApiError::from(&err).is_no_such_upload()
+                        && self.is_completed(key, bucket, &parts).await? =>
+                {
+                    return Ok(api_errors);
+                }
+                result => {
+                    result?;
+                    return Ok(api_errors);
+                }
+            }
         }
     }
 
-    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
-        self.get_object(multi_part).await
+    /// Check whether the destination object is the completed multipart upload of the parts, by
+    /// comparing its ETag to the multipart ETag of the parts, which includes the part count.
+    async fn is_completed(&self, key: &str, bucket: &str, parts: &[CompletedPart]) -> Result<bool> {
+        // Part ETags that are not MD5s, such as those of encrypted objects, cannot be checked.
+        let Some(expected) = parts
+            .iter()
+            .map(|part| part.e_tag().map(|e_tag| e_tag.to_string()))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|e_tags| Self::multipart_e_tag(&e_tags).ok())
+        else {
+            return Ok(false);
+        };
+
+        let head = match self.head_object(key, bucket).await {
+            Ok(head) => head,
+            Err(err) if matches!(err.as_service_error(), Some(HeadObjectError::NotFound(_))) => {
+                return Ok(false);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(head.e_tag.as_deref().map(|e_tag| e_tag.trim_matches('"'))
+            == Some(expected.trim_matches('"')))
     }
 
-    async fn upload(
-        &self,
-        data: CopyContent,
-        multi_part: Option<MultiPartOptions>,
-        state: &CopyState,
-    ) -> Result<CopyResult> {
-        if let Some(multi_part) = multi_part {
-            self.put_object_multipart(data, multi_part, state).await
-        } else {
-            self.put_object(data, state).await
+    /// Compute the multipart ETag from the ETags of the parts.
+    pub fn multipart_e_tag(part_e_tags: &[String]) -> Result<String> {
+        let mut md5 = Md5::new();
+        for e_tag in part_e_tags {
+            md5.update(hex::decode(e_tag.trim_matches('"')).map_err(|err| {
+                CopyError(format!("part ETag `{}` is not an MD5: {}", e_tag, err))
+            })?);
         }
-    }
 
-    fn max_part_size(&self) -> u64 {
-        5368709120
+        Ok(format!(
+            "\"{}-{}\"",
+            hex::encode(md5.finalize()),
+            part_e_tags.len()
+        ))
     }
 
-    fn max_parts(&self) -> u64 {
-        10000
+    /// Whether a request failed with an error that could succeed if retried, such as a timeout,
+    /// a server error or expired credentials.
+    fn is_transient<E>(err: &SdkError<E, HttpResponse>) -> bool
+    where
+        for<'a> ApiError: From<&'a SdkError<E, HttpResponse>>,
+    {
+        let api_error = ApiError::from(err);
+        match err {
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => true,
+            SdkError::ServiceError(service_err) => {
+                service_err.raw().status().is_server_error()
+                    || api_error.is_expired_credentials()
+                    || matches!(
+                        api_error.code.as_str(),
+                        "InternalError" | "ServiceUnavailable" | "SlowDown" | "RequestTimeout"
+                    )
+            }
+            _ => false,
+        }
     }
 
-    fn min_part_size(&self) -> u64 {
-        5242880
-    }
+    /// Check the parts of a multipart upload against a `ListParts` call, which catches parts that
+    /// were silently lost or replaced by retried uploads. If listing parts is not allowed, the
+    /// check is skipped and the access denied error is returned.
+    async fn verify_parts(
+        &self,
+        key: &str,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<Vec<ApiError>> {
+        let mut listed = Vec::with_capacity(parts.len());
+        let mut marker = None;
+        loop {
+            let result = self
+                .client
+                .list_parts(|b| {
+                    b.bucket(bucket)
+                        .key(key)
+                        .upload_id(upload_id)
+                        .set_part_number_marker(marker.take())
+                })
+                .await;
 
-    fn max_object_size(&self) -> u64 {
-        // S3 objects can be at most 50 TiB.
-        54975581388800
-    }
+            let output = match result {
+                Ok(output) => output,
+                Err(err) => {
+                    let api_error = ApiError::from(&err);
+                    if api_error.is_access_denied() {
+                        return Ok(vec![api_error]);
+                    }
+                    return Err(err.into());
+                }
+            };
 
-    async fn initialize_state(&self) -> Result<CopyState> {
-        let source = self.get_source()?;
+            listed.extend(output.parts.unwrap_or_default());
+            marker = output.next_part_number_marker;
+            if output.is_truncated != Some(true) || marker.is_none() {
+                break;
+            }
+        }
 
-        self.initialize_state(source.key.to_string(), source.bucket.to_string())
-            .await
+        let differences = Self::part_differences(parts, &listed);
+        if !differences.is_empty() {
+            return Err(CopyError(format!(
+                "parts of multipart upload `{}` do not match the uploaded parts: {}",
+                upload_id,
+                differences.join("; ")
+            )));
+        }
+
+        Ok(vec![])
+    }
+
+    /// Compare the expected parts with the listed parts, returning a description of each
+    /// difference in the count, part numbers, sizes or ETags.
+    fn part_differences(parts: &[Part], listed: &[types::Part]) -> Vec<String> {
+        let mut differences = vec![];
+        if parts.len() != listed.len() {
+            differences.push(format!(
+                "expected {} parts but found {}",
+                parts.len(),
+                listed.len()
+            ));
+        }
+
+        let listed: HashMap<_, _> = listed
+            .iter()
+            .filter_map(|part| Some((u64::try_from(part.part_number?).ok()?, part)))
+            .collect();
+        for part in parts {
+            let Some(found) = listed.get(&part.part_number) else {
+                differences.push(format!("part {} is missing", part.part_number));
+                continue;
+            };
+
+            let found_size = found.size.and_then(|size| u64::try_from(size).ok());
+            if let Some(size) = part.size
+                && found_size != Some(size)
+            {
+                differences.push(format!(
+                    "part {} has size {} but expected {}",
+                    part.part_number,
+                    found_size.map(|size| size.to_string()).unwrap_or_default(),
+                    size
+                ));
+            }
+
+            let trim = |e_tag: &str| e_tag.trim_matches('"').to_string();
+            let found_e_tag = found.e_tag.as_deref().map(trim);
+            if let Some(e_tag) = part.e_tag.as_deref().map(trim)
+                && found_e_tag.as_ref() != Some(&e_tag)
+            {
+                differences.push(format!(
+                    "part {} has ETag {} but expected {}",
+                    part.part_number,
+                    found_e_tag.unwrap_or_default(),
+                    e_tag
+                ));
+            }
+        }
+
+        let expected: HashSet<_> = parts.iter().map(|part| part.part_number).collect();
+        let mut unexpected: Vec<_> = listed
+            .keys()
+            .filter(|part_number| !expected.contains(part_number))
+            .collect();
+        unexpected.sort();
+        for part_number in unexpected {
+            differences.push(format!("part {} was not expected", part_number));
+        }
+
+        differences
+    }
+
+    /// Send a request with an additional checksum, retrying it without the checksum if the
+    /// destination rejects it. The client records the rejection so that later requests are sent
+    /// without additional checksums, and the rejection is returned as a recoverable error.
+    async fn retry_without_checksum<T, E, F, Fut>(
+        &self,
+        additional_checksum: Option<ChecksumAlgorithm>,
+        mut call: F,
+    ) -> result::Result<(T, Vec<ApiError>), SdkError<E, HttpResponse>>
+    where
+        F: FnMut(Option<ChecksumAlgorithm>) -> Fut,
+        Fut: Future<Output = result::Result<T, SdkError<E, HttpResponse>>>,
+        for<'a> ApiError: From<&'a SdkError<E, HttpResponse>>,
+    {
+        let has_checksum = additional_checksum.is_some();
+        match call(additional_checksum).await {
+            Err(err) if has_checksum && ApiError::from(&err).is_unsupported_checksum() => {
+                self.client.reject_checksums();
+                Ok((call(None).await?, vec![ApiError::from(&err)]))
+            }
+            result => result.map(|output| (output, vec![])),
+        }
+    }
+
+    /// Retry a request if it failed because the credentials expired. The client is forced to
+    /// resolve its credentials again before each retry, so that an in-flight transfer can
+    /// continue rather than failing.
+    async fn retry_expired_credentials<T, E, F, Fut>(
+        &self,
+        mut call: F,
+    ) -> result::Result<T, SdkError<E, HttpResponse>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = result::Result<T, SdkError<E, HttpResponse>>>,
+        for<'a> ApiError: From<&'a SdkError<E, HttpResponse>>,
+    {
+        let mut attempt = 0;
+        let mut backoff = EXPIRED_CREDENTIALS_BACKOFF;
+        loop {
+            match call().await {
+                Err(err)
+                    if attempt < EXPIRED_CREDENTIALS_RETRIES
+                        && ApiError::from(&err).is_expired_credentials() =>
+                {
+                    attempt += 1;
+                    self.client.refresh_credentials();
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for S3 {
+    async fn copy(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let mut result = if let Some(multi_part) = multi_part {
+            self.copy_object_multipart(multi_part, state).await?
+        } else {
+            self.copy_object(state).await?
+        };
+        result.checksum_rejected = self.checksum_rejected(state);
+        Ok(result)
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_object(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let mut result = if let Some(multi_part) = multi_part {
+            self.put_object_multipart(data, multi_part, state).await?
+        } else {
+            self.put_object(data, state).await?
+        };
+        result.checksum_rejected = self.checksum_rejected(state);
+        Ok(result)
+    }
+
+    async fn reuse(&self, multi_part: MultiPartOptions, state: &CopyState) -> Result<CopyResult> {
+        self.reuse_part(multi_part, state).await
+    }
+
+    async fn part_checksum(
+        &self,
+        multi_part: &MultiPartOptions,
+        state: &CopyState,
+    ) -> Option<(Ctx, String)> {
+        let ctx = state.additional_ctx()?;
+        let checksum = self
+            .expected_part_checksum(
+                multi_part.part_number?,
+                multi_part,
+                &checksum_algorithm(ctx.clone()),
+            )
+            .await?;
+
+        Some((ctx, checksum))
+    }
+
+    async fn abort_upload(&self, upload_id: &str, err: Error) -> Error {
+        let (Cancelled(cancelled), Ok(destination)) = (&err, self.get_destination()) else {
+            return err;
+        };
+
+        let cancelled = format!(
+            "{} with multipart upload `{}` for `{}` in progress",
+            cancelled,
+            upload_id,
+            Provider::format_s3(&destination.bucket, &destination.key)
+        );
+        Cancelled(
+            self.abort_incomplete_upload(
+                &destination.key,
+                &destination.bucket,
+                upload_id,
+                cancelled,
+            )
+            .await,
+        )
+    }
+
+    fn supports_reuse(&self) -> bool {
+        true
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        self.client.limits()
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let source = self.get_source()?;
+
+        self.initialize_state(source.key.to_string(), source.bucket.to_string())
+            .await
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::checksum::Ctx;
     use crate::io::copy::CopyContent;
+    use crate::io::copy::filter::MetadataFilter;
+    use aws_credential_types::Credentials;
+    use aws_credential_types::provider::{ProvideCredentials, future};
     use aws_sdk_s3::Client;
     use aws_sdk_s3::config::SharedAsyncSleep;
     use aws_sdk_s3::config::retry::RetryConfig;
+    use aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadOutput;
+    use aws_sdk_s3::operation::complete_multipart_upload::{
+        CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+    };
+    use aws_sdk_s3::operation::copy_object::CopyObjectOutput;
     use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadOutput;
-    use aws_sdk_s3::operation::get_object::GetObjectOutput;
+    use aws_sdk_s3::operation::get_object::{GetObjectError, GetObjectOutput};
+    use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesOutput;
+    use aws_sdk_s3::operation::list_parts::ListPartsOutput;
     use aws_sdk_s3::operation::put_object::{PutObjectError, PutObjectOutput};
-    use aws_sdk_s3::operation::upload_part::UploadPartOutput;
+    use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+    use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyOutput;
     use aws_smithy_async::rt::sleep::TokioSleep;
     use aws_smithy_mocks::{MockResponseInterceptor, Rule, RuleMode, mock};
     use aws_smithy_types::byte_stream::ByteStream;
     use aws_smithy_types::error::ErrorMetadata;
+    use std::str::FromStr;
     use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
     use tokio::io::AsyncReadExt;
 
     const BUCKET: &str = "bucket";
@@ -996,6 +1727,36 @@ mod test {
         assert!(result.api_errors[0].is_access_denied());
     }
 
+    #[tokio::test]
+    async fn put_object_content_md5() {
+        const CONTENT_MD5: &str = "CY9rzUYh03PK3k6DJie09g==";
+
+        let get_object = get_object_rule();
+        let put_object = mock!(Client::put_object)
+            .match_requests(|req| req.content_md5() == Some(CONTENT_MD5))
+            .then_output(|| PutObjectOutput::builder().build());
+
+        let result = download(&get_object, |content| {
+            let destination =
+                s3_destination(retrying_mock_client(&[&put_object]), MetadataCopy::Copy);
+            let mut state = copy_state();
+            state.set_content_md5(Some(CONTENT_MD5.to_string()));
+            async move { destination.put_object(content, &state).await }
+        })
+        .await
+        .unwrap();
+        assert_eq!(put_object.num_calls(), 1);
+        assert!(result.content_md5_validated);
+
+        // Without the MD5, the upload is not validated.
+        let put_object = mock!(Client::put_object)
+            .match_requests(|req| req.content_md5().is_none())
+            .then_output(|| PutObjectOutput::builder().build());
+        let result = test_download(&get_object, &put_object).await.unwrap();
+        assert_eq!(put_object.num_calls(), 1);
+        assert!(!result.content_md5_validated);
+    }
+
     #[tokio::test]
     async fn put_object_best_effort_propagates() {
         let get_object = get_object_rule();
@@ -1020,6 +1781,54 @@ mod test {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn put_object_rejected_checksum() {
+        let get_object = get_object_rule();
+        let put_object = mock!(Client::put_object)
+            .match_requests(|req| req.checksum_algorithm().is_some())
+            .sequence()
+            .error(|| {
+                PutObjectError::generic(
+                    ErrorMetadata::builder()
+                        .code("InvalidArgument")
+                        .message("x-amz-checksum-sha256 is not supported")
+                        .build(),
+                )
+            })
+            .repeatedly()
+            .build();
+        let without_checksum = mock!(Client::put_object)
+            .match_requests(|req| req.checksum_algorithm().is_none())
+            .sequence()
+            .output(|| PutObjectOutput::builder().build())
+            .repeatedly()
+            .build();
+
+        let destination = s3_destination(
+            retrying_mock_client(&[&put_object, &without_checksum]),
+            MetadataCopy::Copy,
+        );
+        let mut state = copy_state();
+        state.set_additional_ctx(Ctx::from_str("sha256").unwrap());
+
+        let source = s3_source(retrying_mock_client(&[&get_object]));
+        let content = source.download(None).await.unwrap();
+        let result = destination.upload(content, None, &state).await.unwrap();
+        assert_eq!(put_object.num_calls(), 1);
+        assert_eq!(without_checksum.num_calls(), 1);
+        assert!(result.checksum_rejected);
+        assert_eq!(result.api_errors.len(), 1);
+        assert!(result.api_errors[0].is_unsupported_checksum());
+
+        // Later uploads do not send the rejected checksum.
+        let content = source.download(None).await.unwrap();
+        let result = destination.upload(content, None, &state).await.unwrap();
+        assert_eq!(put_object.num_calls(), 1);
+        assert_eq!(without_checksum.num_calls(), 2);
+        assert!(result.checksum_rejected);
+        assert!(result.api_errors.is_empty());
+    }
+
     #[tokio::test]
     async fn upload_part_retries_transient_error() {
         let get_object = get_object_rule();
@@ -1062,6 +1871,479 @@ mod test {
         assert_eq!(upload_part.num_calls(), 3);
     }
 
+    #[tokio::test]
+    async fn upload_part_retries_expired_credentials() {
+        let get_object = get_object_rule();
+        let create = mock!(Client::create_multipart_upload)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some(KEY))
+            .then_output(|| {
+                CreateMultipartUploadOutput::builder()
+                    .upload_id("upload-id")
+                    .build()
+            });
+        let upload_part = mock!(Client::upload_part)
+            .match_requests(|req| {
+                req.bucket() == Some(BUCKET)
+                    && req.key() == Some(KEY)
+                    && req.upload_id() == Some("upload-id")
+            })
+            .sequence()
+            .error(|| {
+                UploadPartError::generic(ErrorMetadata::builder().code("ExpiredToken").build())
+            })
+            .output(|| UploadPartOutput::builder().e_tag("etag").build())
+            .build();
+
+        let source = s3_source(retrying_mock_client(&[&get_object]));
+        let options = MultiPartOptions {
+            part_number: Some(1),
+            start: 0,
+            end: BODY.len() as u64,
+            ..Default::default()
+        };
+        let content = source.download(Some(options.clone())).await.unwrap();
+
+        let destination = s3_destination(
+            retrying_mock_client(&[&create, &upload_part]),
+            MetadataCopy::Copy,
+        );
+        let result = destination
+            .put_object_multipart(content, options, &copy_state())
+            .await
+            .unwrap();
+
+        assert_eq!(result.upload_id, Some("upload-id".to_string()));
+        assert_eq!(upload_part.num_calls(), 2);
+    }
+
+    /// A credentials provider that counts how many times credentials are resolved.
+    #[derive(Debug, Clone, Default)]
+    struct CountingProvider(Arc<AtomicUsize>);
+
+    impl ProvideCredentials for CountingProvider {
+        fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+        where
+            Self: 'a,
+        {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            future::ProvideCredentials::ready(Ok(Credentials::new(
+                "access_key",
+                "secret_access_key", // pragma: allowlist secret
+                None,
+                None,
+                "test",
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn get_object_refreshes_expired_credentials() {
+        let get_object = mock!(Client::get_object)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some(KEY))
+            .sequence()
+            .error(|| {
+                GetObjectError::generic(ErrorMetadata::builder().code("ExpiredToken").build())
+            })
+            .output(|| {
+                GetObjectOutput::builder()
+                    .body(ByteStream::from_static(BODY))
+                    .build()
+            })
+            .build();
+
+        let provider = CountingProvider::default();
+        let client = retrying_mock_client(&[&get_object]);
+        let client = Client::from_conf(
+            client
+                .config()
+                .to_builder()
+                .credentials_provider(provider.clone())
+                .build(),
+        );
+        let mut content = s3_source(client).download(None).await.unwrap();
+        let mut body = vec![];
+        content.data.read_to_end(&mut body).await.unwrap();
+
+        assert_eq!(body, BODY);
+        assert_eq!(get_object.num_calls(), 2);
+        // The credentials are resolved again after the expired credentials error.
+        assert_eq!(provider.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn copy_part_retries_checksum_mismatch() {
+        let attributes = mock!(Client::get_object_attributes)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some("source"))
+            .then_output(|| {
+                GetObjectAttributesOutput::builder()
+                    .object_parts(
+                        types::GetObjectAttributesParts::builder()
+                            .parts(
+                                ObjectPart::builder()
+                                    .part_number(1)
+                                    .size(BODY.len() as i64)
+                                    .checksum_sha256("expected")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .build()
+            });
+        let create = mock!(Client::create_multipart_upload)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some(KEY))
+            .then_output(|| {
+                CreateMultipartUploadOutput::builder()
+                    .upload_id("upload-id")
+                    .build()
+            });
+        let copy_part = |checksum: &'static str| {
+            move || {
+                UploadPartCopyOutput::builder()
+                    .copy_part_result(
+                        CopyPartResult::builder()
+                            .e_tag("etag")
+                            .checksum_sha256(checksum)
+                            .build(),
+                    )
+                    .build()
+            }
+        };
+        let upload_part_copy = mock!(Client::upload_part_copy)
+            .match_requests(|req| req.copy_source() == Some(format!("{BUCKET}/source").as_str()))
+            .sequence()
+            .output(copy_part("mismatch"))
+            .output(copy_part("expected"))
+            .build();
+
+        let copy = S3Builder::default()
+            .with_client(S3Client::new(
+                Arc::new(retrying_mock_client(&[
+                    &attributes,
+                    &create,
+                    &upload_part_copy,
+                ])),
+                false,
+                false,
+            ))
+            .with_source(BUCKET, "source")
+            .with_destination(BUCKET, KEY)
+            .build()
+            .unwrap();
+        let mut state = copy_state();
+        state.set_additional_ctx(Ctx::from_str("sha256").unwrap());
+
+        let result = copy
+            .copy(
+                Some(MultiPartOptions {
+                    part_number: Some(1),
+                    start: 0,
+                    end: BODY.len() as u64,
+                    ..Default::default()
+                }),
+                &state,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.part.unwrap().sha256, Some("expected".to_string()));
+        assert_eq!(result.n_retries, 1);
+        assert_eq!(upload_part_copy.num_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn reuse_part_copies_from_destination() {
+        let create = mock!(Client::create_multipart_upload)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some(KEY))
+            .then_output(|| {
+                CreateMultipartUploadOutput::builder()
+                    .upload_id("upload-id")
+                    .build()
+            });
+        let upload_part_copy = mock!(Client::upload_part_copy)
+            .match_requests(|req| {
+                req.bucket() == Some(BUCKET)
+                    && req.key() == Some(KEY)
+                    && req.copy_source() == Some(format!("{BUCKET}/{KEY}").as_str())
+            })
+            .then_output(|| {
+                UploadPartCopyOutput::builder()
+                    .copy_part_result(CopyPartResult::builder().e_tag("etag").build())
+                    .build()
+            });
+
+        let destination = s3_destination(
+            retrying_mock_client(&[&create, &upload_part_copy]),
+            MetadataCopy::Copy,
+        );
+        let mut state = copy_state();
+        state.set_differential(true);
+        let result = destination
+            .reuse(
+                MultiPartOptions {
+                    part_number: Some(1),
+                    start: 0,
+                    end: BODY.len() as u64,
+                    ..Default::default()
+                },
+                &state,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.bytes_transferred, 0);
+        assert_eq!(result.bytes_reused, BODY.len() as u64);
+        assert_eq!(upload_part_copy.num_calls(), 1);
+    }
+
+    /// Build an S3 destination that completes the multipart upload with a single part.
+    fn complete_destination(rules: &[&Rule]) -> (S3, MultiPartOptions) {
+        let destination = S3Builder::default()
+            .with_client(S3Client::new(
+                Arc::new(retrying_mock_client(rules)),
+                false,
+                false,
+            ))
+            .with_destination(BUCKET, KEY)
+            .with_complete_options(CompleteOptions::default().with_backoff(Duration::ZERO))
+            .build()
+            .unwrap();
+        let options = MultiPartOptions {
+            upload_id: Some("upload-id".to_string()),
+            parts: Some(vec![Part {
+                part_number: 1,
+                e_tag: Some("etag".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        (destination, options)
+    }
+
+    /// A `list_parts` rule that lists the single completed part.
+    fn list_parts_rule() -> Rule {
+        mock!(Client::list_parts)
+            .match_requests(|req| req.upload_id() == Some("upload-id"))
+            .sequence()
+            .output(|| {
+                ListPartsOutput::builder()
+                    .parts(types::Part::builder().part_number(1).e_tag("etag").build())
+                    .build()
+            })
+            .repeatedly()
+            .build()
+    }
+
+    #[tokio::test]
+    async fn complete_retries_transient_error() {
+        let list_parts = list_parts_rule();
+        let complete = mock!(Client::complete_multipart_upload)
+            .match_requests(|req| req.upload_id() == Some("upload-id"))
+            .sequence()
+            .error(|| {
+                CompleteMultipartUploadError::generic(
+                    ErrorMetadata::builder().code("ExpiredToken").build(),
+                )
+            })
+            .output(|| CompleteMultipartUploadOutput::builder().build())
+            .build();
+
+        let (destination, options) = complete_destination(&[&list_parts, &complete]);
+        let result = destination
+            .put_object_multipart(CopyContent::empty(), options, &copy_state())
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(complete.num_calls(), 2);
+    }
+
+    /// Complete a single part upload whose first attempt fails with a transient error and whose
+    /// retry by the SDK finds that the upload no longer exists. The destination has the ETag from `head`.
+    async fn complete_after_lost_response(head: &str) -> (Result<CopyResult>, Rule) {
+        let part_e_tag = format!("\"{}\"", hex::encode(Md5::digest(BODY)));
+        let list_part_e_tag = part_e_tag.clone();
+        let list_parts = mock!(Client::list_parts)
+            .match_requests(|req| req.upload_id() == Some("upload-id"))
+            .then_output(move || {
+                ListPartsOutput::builder()
+                    .parts(
+                        types::Part::builder()
+                            .part_number(1)
+                            .e_tag(list_part_e_tag.clone())
+                            .build(),
+                    )
+                    .build()
+            });
+        let complete = mock!(Client::complete_multipart_upload)
+            .match_requests(|req| req.upload_id() == Some("upload-id"))
+            .sequence()
+            .error(|| {
+                CompleteMultipartUploadError::generic(
+                    ErrorMetadata::builder().code("InternalError").build(),
+                )
+            })
+            .error(|| {
+                CompleteMultipartUploadError::generic(
+                    ErrorMetadata::builder().code("NoSuchUpload").build(),
+                )
+            })
+            .build();
+        let head = head.to_string();
+        let head_object = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some(BUCKET) && req.key() == Some(KEY))
+            .then_output(move || HeadObjectOutput::builder().e_tag(head.clone()).build());
+        let abort = mock!(Client::abort_multipart_upload)
+            .then_output(|| AbortMultipartUploadOutput::builder().build());
+
+        let (destination, mut options) =
+            complete_destination(&[&list_parts, &complete, &head_object, &abort]);
+        options.parts = Some(vec![Part {
+            part_number: 1,
+            e_tag: Some(part_e_tag.clone()),
+            ..Default::default()
+        }]);
+        let result = destination
+            .put_object_multipart(CopyContent::empty(), options, &copy_state())
+            .await;
+
+        (result, complete)
+    }
+
+    #[tokio::test]
+    async fn complete_accepts_upload_completed_by_lost_response() {
+        let e_tag = S3::multipart_e_tag(&[hex::encode(Md5::digest(BODY))]).unwrap();
+        let (result, complete) = complete_after_lost_response(&e_tag).await;
+
+        result.unwrap();
+        assert_eq!(complete.num_calls(), 2);
+
+        let (result, _) = complete_after_lost_response("\"other-1\"").await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn multipart_e_tag() -> Result<()> {
+        let md5 = |data: &[u8]| hex::encode(Md5::digest(data));
+        let e_tags = vec![format!("\"{}\"", md5(b"a")), md5(b"b")];
+
+        let mut expected = Md5::new();
+        expected.update(Md5::digest(b"a"));
+        expected.update(Md5::digest(b"b"));
+        assert_eq!(
+            S3::multipart_e_tag(&e_tags)?,
+            format!("\"{}-2\"", hex::encode(expected.finalize()))
+        );
+
+        assert!(S3::multipart_e_tag(&["not-md5".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn complete_failure_aborts_upload() {
+        let list_parts = list_parts_rule();
+        let complete = mock!(Client::complete_multipart_upload)
+            .match_requests(|req| req.upload_id() == Some("upload-id"))
+            .sequence()
+            .error(|| {
+                CompleteMultipartUploadError::generic(
+                    ErrorMetadata::builder().code("InvalidPart").build(),
+                )
+            })
+            .repeatedly()
+            .build();
+        let abort = mock!(Client::abort_multipart_upload)
+            .match_requests(|req| {
+                req.bucket() == Some(BUCKET)
+                    && req.key() == Some(KEY)
+                    && req.upload_id() == Some("upload-id")
+            })
+            .then_output(|| AbortMultipartUploadOutput::builder().build());
+
+        let (destination, options) = complete_destination(&[&list_parts, &complete, &abort]);
+        let err = destination
+            .put_object_multipart(CopyContent::empty(), options, &copy_state())
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("upload-id"));
+        assert!(err.contains("the upload was aborted"));
+        assert_eq!(complete.num_calls(), 1);
+        assert_eq!(abort.num_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelled_upload_is_aborted() {
+        let abort = mock!(Client::abort_multipart_upload)
+            .match_requests(|req| {
+                req.bucket() == Some(BUCKET)
+                    && req.key() == Some(KEY)
+                    && req.upload_id() == Some("upload-id")
+            })
+            .then_output(|| AbortMultipartUploadOutput::builder().build());
+
+        let (destination, _) = complete_destination(&[&abort]);
+        let err = destination
+            .abort_upload("upload-id", Cancelled("copy".to_string()))
+            .await;
+
+        assert!(matches!(err, Cancelled(_)));
+        assert!(err.to_string().contains("upload-id"));
+        assert!(err.to_string().contains("the upload was aborted"));
+        assert_eq!(abort.num_calls(), 1);
+
+        let err = destination
+            .abort_upload("upload-id", CopyError("copy".to_string()))
+            .await;
+        assert!(matches!(err, CopyError(_)));
+        assert_eq!(abort.num_calls(), 1);
+    }
+
+    #[test]
+    fn part_differences_detects_mismatches() {
+        let part = |part_number, size, e_tag: &str| Part {
+            part_number,
+            size: Some(size),
+            e_tag: Some(e_tag.to_string()),
+            ..Default::default()
+        };
+        let listed = |part_number, size, e_tag: &str| {
+            types::Part::builder()
+                .part_number(part_number)
+                .size(size)
+                .e_tag(format!("\"{e_tag}\""))
+                .build()
+        };
+
+        let parts = vec![part(1, 4, "a"), part(2, 4, "b"), part(3, 2, "c")];
+        assert!(
+            S3::part_differences(
+                &parts,
+                &[listed(1, 4, "a"), listed(2, 4, "b"), listed(3, 2, "c")]
+            )
+            .is_empty()
+        );
+
+        assert_eq!(
+            S3::part_differences(
+                &parts,
+                &[listed(1, 4, "a"), listed(2, 3, "x"), listed(4, 2, "c")]
+            ),
+            vec![
+                "part 2 has size 3 but expected 4",
+                "part 2 has ETag x but expected b",
+                "part 3 is missing",
+                "part 4 was not expected",
+            ]
+        );
+
+        assert_eq!(
+            S3::part_differences(&parts, &[listed(1, 4, "a"), listed(2, 4, "b")]),
+            vec!["expected 3 parts but found 2", "part 3 is missing"]
+        );
+    }
+
     #[test]
     fn per_part_checksums_round_trip() -> Result<()> {
         let output = UploadPartOutput::builder()
@@ -1109,6 +2391,80 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn copy_object_replaces_filtered_tags() {
+        let copy_object = mock!(Client::copy_object)
+            .match_requests(|req| {
+                req.tagging_directive() == Some(&TaggingDirective::Replace)
+                    && req.tagging() == Some("keep=value&migrated=true")
+                    && req.metadata_directive() == Some(&MetadataDirective::Copy)
+            })
+            .then_output(|| CopyObjectOutput::builder().build());
+
+        let destination = S3Builder::default()
+            .with_client(S3Client::new(
+                Arc::new(retrying_mock_client(&[&copy_object])),
+                false,
+                false,
+            ))
+            .with_source(BUCKET, KEY)
+            .with_destination(BUCKET, "destination")
+            .build()
+            .unwrap();
+
+        let mut state = CopyState::new(
+            BODY.len() as u64,
+            Some("temp-a=value&keep=value".to_string()),
+            None,
+        );
+        state.filter_tags(&MetadataFilter::new(vec![
+            "exclude:temp-*".parse().unwrap(),
+            "add:migrated=true".parse().unwrap(),
+        ]));
+        state.filter_metadata(&MetadataFilter::default());
+
+        let result = destination.copy_object(&state).await;
+        assert!(result.is_ok());
+        assert_eq!(copy_object.num_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn copy_object_in_place_sets_storage_class() {
+        let copy_object = mock!(Client::copy_object)
+            .match_requests(|req| {
+                req.storage_class() == Some(&StorageClass::StandardIa)
+                    && req.metadata_directive() == Some(&MetadataDirective::Replace)
+                    && req.metadata().and_then(|metadata| metadata.get("a"))
+                        == Some(&"b".to_string())
+                    && req.copy_source() == Some(&format!("{}/{}", BUCKET, KEY))
+                    && req.key() == Some(KEY)
+            })
+            .then_output(|| CopyObjectOutput::builder().build());
+
+        let destination = S3Builder::default()
+            .with_client(S3Client::new(
+                Arc::new(retrying_mock_client(&[&copy_object])),
+                false,
+                false,
+            ))
+            .with_source(BUCKET, KEY)
+            .with_destination(BUCKET, KEY)
+            .set_storage_class(Some(StorageClass::StandardIa))
+            .build()
+            .unwrap();
+
+        let mut state = CopyState::new(
+            BODY.len() as u64,
+            None,
+            Some(HashMap::from_iter([("a".to_string(), "b".to_string())])),
+        );
+        state.set_replace_metadata(true);
+
+        let result = destination.copy_object(&state).await;
+        assert!(result.is_ok());
+        assert_eq!(copy_object.num_calls(), 1);
+    }
+
     #[tokio::test]
     async fn reopen_reproduces_source() {
         let get_object = get_object_rule();
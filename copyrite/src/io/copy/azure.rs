@@ -0,0 +1,361 @@
+//! Azure Blob Storage copy logic. Objects are uploaded as block blobs, where each part of a
+//! multipart copy is a block that is committed in order once all parts are uploaded.
+//!
+
+use crate::error::Error::{AzureError, CopyError};
+use crate::error::Result;
+use crate::io::Provider;
+use crate::io::azure::{AzureClient, BlobHeaders, block_id};
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part};
+use std::future::Future;
+use std::pin::Pin;
+
+/// Build an Azure copy object.
+#[derive(Debug, Default)]
+pub struct AzureBuilder {
+    client: Option<AzureClient>,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl AzureBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: AzureClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source container and blob.
+    pub fn with_source(mut self, container: &str, blob: &str) -> Self {
+        self.source = Some((container.to_string(), blob.to_string()));
+        self
+    }
+
+    /// Set the destination container and blob.
+    pub fn with_destination(mut self, container: &str, blob: &str) -> Self {
+        self.destination = Some((container.to_string(), blob.to_string()));
+        self
+    }
+
+    /// Build the Azure copy object.
+    pub fn build(self) -> Result<Azure> {
+        let client = self
+            .client
+            .ok_or_else(|| CopyError("a client is required in `AzureBuilder`".to_string()))?;
+
+        Ok(Azure {
+            client,
+            source: self.source,
+            destination: self.destination,
+        })
+    }
+}
+
+/// An Azure blob copy.
+#[derive(Debug, Clone)]
+pub struct Azure {
+    client: AzureClient,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl Azure {
+    fn get_source(&self) -> Result<(&str, &str)> {
+        self.source
+            .as_ref()
+            .map(|(container, blob)| (container.as_str(), blob.as_str()))
+            .ok_or_else(|| CopyError("missing source".to_string()))
+    }
+
+    fn get_destination(&self) -> Result<(&str, &str)> {
+        self.destination
+            .as_ref()
+            .map(|(container, blob)| (container.as_str(), blob.as_str()))
+            .ok_or_else(|| CopyError("missing destination".to_string()))
+    }
+
+    /// Get the source blob, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_blob(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let (container, blob) = self.get_source()?;
+
+        if let Some(multipart) = &multi_part
+            && multipart.part_number.is_none()
+        {
+            return Ok(CopyContent::empty());
+        }
+
+        let (start, end) = multi_part.as_ref().map_or((0, None), |multipart| {
+            (multipart.start, Some(multipart.end))
+        });
+        let data = self
+            .client
+            .get_blob(container, blob, start, end)
+            .await?
+            .ok_or_else(|| {
+                AzureError(format!(
+                    "{} does not exist",
+                    Provider::format_azure(container, blob)
+                ))
+            })?;
+
+        let self_clone = self.clone();
+        CopyContent::builder(data)
+            .with_reopen(move || self_clone.reopen_get(multi_part.clone()))
+            .build()
+    }
+
+    /// Re-read the source range.
+    fn reopen_get(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+    ) -> Pin<Box<dyn Future<Output = Result<CopyContent>> + Send>> {
+        let self_clone = self.clone();
+        Box::pin(async move { self_clone.get_blob(multi_part).await })
+    }
+
+    /// The headers of the destination blob from the copy state.
+    fn headers(state: &CopyState, content_md5: Option<String>) -> BlobHeaders {
+        BlobHeaders {
+            content_md5,
+            metadata: state.metadata(),
+            tags: state.tags(),
+            ..Default::default()
+        }
+    }
+
+    /// Upload the blob in a single request, which is validated using the `Content-MD5` if it is
+    /// known.
+    pub async fn put_blob(&self, content: CopyContent, state: &CopyState) -> Result<CopyResult> {
+        let (container, blob) = self.get_destination()?;
+        let headers = Self::headers(state, state.content_md5());
+        self.client
+            .put_blob(
+                container,
+                blob,
+                AzureClient::body(content.data),
+                state.size(),
+                &headers,
+            )
+            .await?;
+
+        let mut result = CopyResult::new(None, None, state.size(), vec![])?;
+        result.content_md5_validated = headers.content_md5.is_some();
+        Ok(result)
+    }
+
+    /// Upload a part as a block, or commit the uploaded blocks if this is the completion step.
+    pub async fn put_block(
+        &self,
+        content: CopyContent,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let (container, blob) = self.get_destination()?;
+
+        let Some(part_number) = multi_part.part_number else {
+            let mut parts = multi_part.parts.ok_or_else(|| {
+                CopyError("missing parts for completing the block list".to_string())
+            })?;
+            parts.sort_by_key(|part| part.part_number);
+            let block_ids = parts
+                .iter()
+                .map(|part| block_id(part.part_number))
+                .collect::<Vec<_>>();
+
+            self.client
+                .put_block_list(container, blob, &block_ids, &Self::headers(state, None))
+                .await?;
+            return CopyResult::new(None, None, 0, vec![]);
+        };
+
+        let size = multi_part.bytes_transferred();
+        self.client
+            .put_block(
+                container,
+                blob,
+                &block_id(part_number),
+                AzureClient::body(content.data),
+                size,
+            )
+            .await?;
+
+        let part = Part {
+            part_number,
+            size: Some(size),
+            ..Default::default()
+        };
+        CopyResult::new(Some(part), None, size, vec![])
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Azure {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(CopyError(
+            "server-side copies are not supported for Azure".to_string(),
+        ))
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_blob(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        if let Some(multi_part) = multi_part {
+            self.put_block(data, multi_part, state).await
+        } else {
+            self.put_blob(data, state).await
+        }
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        ProviderLimits::AZURE
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let (container, blob) = self.get_source()?;
+        let properties = self
+            .client
+            .get_properties(container, blob)
+            .await?
+            .ok_or_else(|| {
+                AzureError(format!(
+                    "{} does not exist",
+                    Provider::format_azure(container, blob)
+                ))
+            })?;
+
+        Ok(CopyState::new(
+            properties.size,
+            None,
+            Some(properties.metadata),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::azure::AzureCredentials;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Serve requests with an empty `201 Created` response, recording the request line, headers
+    /// and body of each request.
+    async fn serve(requests: Arc<Mutex<Vec<(String, String)>>>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut head = String::new();
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await? == 0 {
+                                return anyhow::Ok(());
+                            }
+                            if let Some(length) =
+                                line.to_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = length.trim().parse()?;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            head.push_str(&line);
+                        }
+
+                        let mut body = vec![0; content_length];
+                        stream.read_exact(&mut body).await?;
+                        requests
+                            .lock()
+                            .unwrap()
+                            .push((head, String::from_utf8_lossy(&body).to_string()));
+                        stream
+                            .write_all(b"HTTP/1.1 201 Created\r\ncontent-length: 0\r\n\r\n")
+                            .await?;
+                    }
+                });
+            }
+        });
+
+        Ok(endpoint)
+    }
+
+    #[tokio::test]
+    async fn upload_blocks() -> anyhow::Result<()> {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let endpoint = serve(requests.clone()).await?;
+        let azure = AzureBuilder::default()
+            .with_client(AzureClient::new(endpoint, AzureCredentials::Anonymous))
+            .with_destination("container", "blob")
+            .build()?;
+        let state = CopyState::new(
+            6,
+            Some("a=b".to_string()),
+            Some([("project".to_string(), "umccr".to_string())].into()),
+        );
+
+        let content = |data: &'static [u8]| {
+            CopyContent::builder(Box::new(data))
+                .with_reopen(move || async move { Ok(CopyContent::empty()) })
+                .build()
+        };
+        let mut parts = vec![];
+        for (part_number, start, data) in [(2, 3, &b"def"[..]), (1, 0, b"abc")] {
+            let options = MultiPartOptions {
+                part_number: Some(part_number),
+                start,
+                end: start + 3,
+                ..Default::default()
+            };
+            let result = azure.upload(content(data)?, Some(options), &state).await?;
+            assert_eq!(result.bytes_transferred, 3);
+            parts.extend(result.part);
+        }
+
+        let options = MultiPartOptions {
+            parts: Some(parts),
+            ..Default::default()
+        };
+        azure
+            .upload(CopyContent::empty(), Some(options), &state)
+            .await?;
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].0.starts_with(&format!(
+            "PUT /container/blob?comp=block&blockid={} ",
+            block_id(2).replace('=', "%3D")
+        )));
+        assert_eq!(requests[0].1, "def");
+        assert_eq!(requests[1].1, "abc");
+
+        let (head, body) = &requests[2];
+        assert!(head.starts_with("PUT /container/blob?comp=blocklist "));
+        assert!(head.contains("x-ms-tags: a=b"));
+        assert!(head.contains("x-ms-meta-project: umccr"));
+        assert_eq!(
+            body,
+            &crate::io::azure::block_list(&[block_id(1), block_id(2)])
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Differential copies which only transfer the parts of an object that have changed.
+//!
+
+use crate::checksum::Ctx;
+use crate::error::Result;
+use crate::io::copy::{CopyContent, CopyState, MultiPartOptions, ObjectCopy};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::try_join;
+
+/// The size of the buffer used when hashing parts.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// The content of a part in a differential copy.
+pub enum PartContent {
+    /// The part is identical to the same range of the existing destination.
+    Unchanged,
+    /// The part has changed and must be uploaded.
+    Changed(CopyContent),
+}
+
+/// Compares the parts of a source object with the same ranges of an existing destination
+/// object, so only parts with changed contents need to be uploaded. If the destination has a
+/// checksum for a part with the same range, the source part is compared against it without
+/// reading the destination. Otherwise, the part is compared using a SHA256 hash of the source
+/// and destination ranges. Parts are compared at the same offsets, which covers data that was
+/// modified in place or appended to.
+#[derive(Clone)]
+pub struct Differential {
+    existing: Box<dyn ObjectCopy + Send + Sync>,
+    size: u64,
+}
+
+impl Differential {
+    /// Create a new differential comparison. `existing` must read from the destination object
+    /// and `size` is the size of the existing destination object.
+    pub fn new(existing: Box<dyn ObjectCopy + Send + Sync>, size: u64) -> Self {
+        Self { existing, size }
+    }
+
+    /// Download a part from the source, or determine that it is unchanged at the destination.
+    /// The source is only read again to upload the part if it has changed.
+    pub async fn download(
+        &self,
+        source: &(dyn ObjectCopy + Send + Sync),
+        options: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<PartContent> {
+        // The completion step and parts past the end of the existing object are always
+        // changed.
+        if options.part_number.is_none() || options.end > self.size {
+            return Ok(PartContent::Changed(source.download(Some(options)).await?));
+        }
+
+        let size = options.bytes_transferred();
+        let (unchanged, reopen) = match self.existing.part_checksum(&options, state).await {
+            Some((ctx, expected)) => {
+                let CopyContent { data, reopen } = source.download(Some(options)).await?;
+                (Self::checksum(data, size, ctx).await? == expected, reopen)
+            }
+            None => {
+                let (source_content, existing_content) = try_join!(
+                    source.download(Some(options.clone())),
+                    self.existing.download(Some(options))
+                )?;
+
+                let CopyContent { data, reopen } = source_content;
+                let (source_digest, existing_digest) = try_join!(
+                    Self::digest(data, size),
+                    Self::digest(existing_content.data, size)
+                )?;
+                (source_digest == existing_digest, reopen)
+            }
+        };
+
+        if unchanged {
+            Ok(PartContent::Unchanged)
+        } else {
+            Ok(PartContent::Changed(reopen().await?))
+        }
+    }
+
+    /// Compute the base64 encoded checksum of the first `size` bytes of the data, in the same
+    /// form as the part checksums of the destination.
+    async fn checksum(
+        data: Box<dyn AsyncRead + Sync + Send + Unpin>,
+        size: u64,
+        mut ctx: Ctx,
+    ) -> Result<String> {
+        let mut data = data.take(size);
+        let mut buf = vec![0; BUFFER_SIZE];
+
+        loop {
+            let n = data.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            ctx.update(Arc::from(&buf[..n]))?;
+        }
+
+        Ok(BASE64_STANDARD.encode(ctx.finalize()?))
+    }
+
+    /// Hash the first `size` bytes of the data.
+    async fn digest(data: Box<dyn AsyncRead + Sync + Send + Unpin>, size: u64) -> Result<Vec<u8>> {
+        let mut data = data.take(size);
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; BUFFER_SIZE];
+
+        loop {
+            let n = data.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+
+        Ok(hasher.finalize().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::copy::CopyResult;
+    use crate::io::copy::file::File;
+    use crate::io::copy::limits::ProviderLimits;
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn download_detects_changed_parts() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+        fs::write(&source, b"aaaabbbbccccdd").await?;
+        fs::write(&destination, b"aaaaxxxxcccc").await?;
+
+        let source = File::new(Some(source.to_string_lossy().to_string()), None);
+        let differential = Differential::new(
+            Box::new(File::new(
+                Some(destination.to_string_lossy().to_string()),
+                None,
+            )),
+            12,
+        );
+
+        let mut changed = vec![];
+        for (part_number, start, end) in [(1, 0, 4), (2, 4, 8), (3, 8, 12), (4, 12, 14)] {
+            let options = MultiPartOptions {
+                part_number: Some(part_number),
+                start,
+                end,
+                ..Default::default()
+            };
+
+            if let PartContent::Changed(content) = differential
+                .download(&source, options, &CopyState::new(12, None, None))
+                .await?
+            {
+                let mut buf = vec![];
+                content.data.take(end - start).read_to_end(&mut buf).await?;
+                changed.push(buf);
+            }
+        }
+
+        assert_eq!(changed, vec![b"bbbb".to_vec(), b"dd".to_vec()]);
+
+        Ok(())
+    }
+
+    /// An existing destination which only knows the checksums of its parts.
+    #[derive(Clone)]
+    struct PartChecksums(Vec<&'static [u8]>);
+
+    #[async_trait::async_trait]
+    impl ObjectCopy for PartChecksums {
+        async fn copy(
+            &self,
+            _multi_part: Option<MultiPartOptions>,
+            _state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            unimplemented!()
+        }
+
+        async fn download(
+            &self,
+            _multi_part: Option<MultiPartOptions>,
+        ) -> crate::error::Result<CopyContent> {
+            panic!("the destination should not be downloaded")
+        }
+
+        async fn upload(
+            &self,
+            _data: CopyContent,
+            _multi_part: Option<MultiPartOptions>,
+            _state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            unimplemented!()
+        }
+
+        async fn part_checksum(
+            &self,
+            multi_part: &MultiPartOptions,
+            _state: &CopyState,
+        ) -> Option<(Ctx, String)> {
+            let part = self
+                .0
+                .get(usize::try_from(multi_part.part_number? - 1).ok()?)?;
+            let mut ctx: Ctx = "sha256".parse().ok()?;
+            ctx.update(Arc::from(*part)).ok()?;
+            let checksum = BASE64_STANDARD.encode(ctx.finalize().ok()?);
+
+            Some(("sha256".parse().ok()?, checksum))
+        }
+
+        fn limits(&self) -> ProviderLimits {
+            ProviderLimits::S3
+        }
+
+        async fn initialize_state(&self) -> crate::error::Result<CopyState> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn download_compares_part_checksums() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        fs::write(&source, b"aaaabbbbcccc").await?;
+
+        let source = File::new(Some(source.to_string_lossy().to_string()), None);
+        let differential =
+            Differential::new(Box::new(PartChecksums(vec![b"aaaa", b"xxxx", b"cccc"])), 12);
+
+        let mut changed = vec![];
+        for (part_number, start, end) in [(1, 0, 4), (2, 4, 8), (3, 8, 12)] {
+            let options = MultiPartOptions {
+                part_number: Some(part_number),
+                start,
+                end,
+                ..Default::default()
+            };
+
+            if let PartContent::Changed(_) = differential
+                .download(&source, options, &CopyState::new(12, None, None))
+                .await?
+            {
+                changed.push(part_number);
+            }
+        }
+
+        assert_eq!(changed, vec![2]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+//! Copy sources which fall back to replicas if the primary source fails.
+//!
+
+use crate::error::Error::CopyError;
+use crate::error::Result;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::sleep;
+
+/// The number of times a part is read from a source before falling back to the next source.
+pub const DEFAULT_SOURCE_ATTEMPTS: usize = 3;
+
+/// The initial backoff between attempts to read a part from a source.
+const SOURCE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A copy source that reads from a list of sources in order. If reading a part from the current
+/// source fails repeatedly, the part is read from the next source and all later parts continue
+/// from that source. The sources must contain the same object, such as read replicas in other
+/// regions. All other operations use the primary source.
+#[derive(Clone)]
+pub struct Fallback {
+    sources: Vec<Box<dyn ObjectCopy + Send + Sync>>,
+    active: Arc<AtomicUsize>,
+    attempts: usize,
+    backoff: Duration,
+    size: u64,
+}
+
+impl Fallback {
+    /// Create a new fallback source from the primary source, its state and its replicas. Each
+    /// replica must have the same size as the primary, and the same ETag if both report one.
+    pub async fn try_new(
+        primary: Box<dyn ObjectCopy + Send + Sync>,
+        state: &CopyState,
+        fallbacks: Vec<(String, Box<dyn ObjectCopy + Send + Sync>)>,
+    ) -> Result<Self> {
+        let mut sources = vec![primary];
+        for (name, fallback) in fallbacks {
+            let replica = fallback.initialize_state().await?;
+            if replica.size() != state.size() {
+                return Err(CopyError(format!(
+                    "source fallback `{}` has size {} but the source has size {}",
+                    name,
+                    replica.size(),
+                    state.size()
+                )));
+            }
+            if let (Some(e_tag), Some(expected)) = (replica.e_tag(), state.e_tag())
+                && e_tag != expected
+            {
+                return Err(CopyError(format!(
+                    "source fallback `{}` has ETag {} but the source has ETag {}",
+                    name, e_tag, expected
+                )));
+            }
+            sources.push(fallback);
+        }
+
+        Ok(Self {
+            sources,
+            active: Arc::new(AtomicUsize::new(0)),
+            attempts: DEFAULT_SOURCE_ATTEMPTS,
+            backoff: SOURCE_BACKOFF,
+            size: state.size(),
+        })
+    }
+
+    /// The index of the source that parts are currently read from.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn primary(&self) -> &(dyn ObjectCopy + Send + Sync) {
+        self.sources[0].as_ref()
+    }
+
+    /// Continue reading later parts from the source after `index`. The last source is always
+    /// kept.
+    fn fall_back(&self, index: usize) {
+        self.active
+            .fetch_max((index + 1).min(self.sources.len() - 1), Ordering::Relaxed);
+    }
+
+    /// Open a part from the active source, falling back to the next source if opening it fails
+    /// repeatedly.
+    async fn open(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let mut error = None;
+        for (index, source) in self.sources.iter().enumerate().skip(self.active()) {
+            let mut backoff = self.backoff;
+            for attempt in 0..self.attempts {
+                if attempt > 0 {
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                match source.download(multi_part.clone()).await {
+                    Ok(content) => return Ok(content),
+                    Err(err) => error = Some(err),
+                }
+            }
+
+            self.fall_back(index);
+        }
+
+        Err(error.unwrap_or_else(|| CopyError("no source available".to_string())))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Fallback {
+    async fn copy(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        self.primary().copy(multi_part, state).await
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let content = self.open(multi_part.clone()).await?;
+
+        let (start, end, part_number) = match &multi_part {
+            Some(multi_part) => (multi_part.start, multi_part.end, multi_part.part_number),
+            None => (0, self.size, None),
+        };
+        let reader = FallbackReader {
+            fallback: self.clone(),
+            data: content.data,
+            start,
+            end,
+            part_number,
+            read: 0,
+            failures: 0,
+            reopening: Mutex::new(None),
+        };
+
+        let fallback = self.clone();
+        CopyContent::builder(Box::new(reader))
+            .with_reopen(move || {
+                let fallback = fallback.clone();
+                let multi_part = multi_part.clone();
+                async move { fallback.download(multi_part).await }
+            })
+            .build()
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        self.primary().upload(data, multi_part, state).await
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        self.primary().limits()
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        self.primary().initialize_state().await
+    }
+}
+
+/// The future that opens the rest of a part after a read error.
+type Reopening = Pin<Box<dyn Future<Output = Result<CopyContent>> + Send>>;
+
+/// A reader that continues a part from where it stopped if reading from a source fails
+/// partway through, falling back to the next source if it keeps failing.
+struct FallbackReader {
+    fallback: Fallback,
+    data: Box<dyn AsyncRead + Sync + Send + Unpin>,
+    start: u64,
+    end: u64,
+    part_number: Option<u64>,
+    read: u64,
+    failures: usize,
+    reopening: Mutex<Option<Reopening>>,
+}
+
+impl AsyncRead for FallbackReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let reopening = this
+                .reopening
+                .get_mut()
+                .unwrap_or_else(|err| err.into_inner());
+            if let Some(future) = reopening.as_mut() {
+                let content = ready!(future.as_mut().poll(cx));
+                *reopening = None;
+                this.data = content.map_err(io::Error::other)?.data;
+            }
+
+            let filled = buf.filled().len();
+            match ready!(Pin::new(&mut this.data).poll_read(cx, buf)) {
+                Ok(()) => {
+                    this.read += (buf.filled().len() - filled) as u64;
+                    return Poll::Ready(Ok(()));
+                }
+                Err(err) => {
+                    this.failures += 1;
+                    if this.failures >= this.fallback.attempts * this.fallback.sources.len() {
+                        return Poll::Ready(Err(err));
+                    }
+                    if this.failures.is_multiple_of(this.fallback.attempts) {
+                        this.fallback.fall_back(this.fallback.active());
+                    }
+
+                    // Read the rest of the part, which may come from the next source.
+                    let options = MultiPartOptions {
+                        part_number: this.part_number,
+                        start: this.start + this.read,
+                        end: this.end,
+                        ..Default::default()
+                    };
+                    let fallback = this.fallback.clone();
+                    *this
+                        .reopening
+                        .get_mut()
+                        .unwrap_or_else(|err| err.into_inner()) = Some(Box::pin(async move {
+                        sleep(fallback.backoff).await;
+                        fallback.open(Some(options)).await
+                    }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::copy::file::File;
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use tokio::fs;
+    use tokio::io::AsyncReadExt;
+
+    fn file(path: &std::path::Path) -> Box<dyn ObjectCopy + Send + Sync> {
+        Box::new(File::new(Some(path.to_string_lossy().to_string()), None))
+    }
+
+    /// A reader that always fails.
+    struct Broken;
+
+    impl AsyncRead for Broken {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(Err(io::Error::other("connection reset")))
+        }
+    }
+
+    /// A source whose downloads fail after reading one byte.
+    #[derive(Clone)]
+    struct BrokenSource(Box<dyn ObjectCopy + Send + Sync>);
+
+    #[async_trait::async_trait]
+    impl ObjectCopy for BrokenSource {
+        async fn copy(
+            &self,
+            multi_part: Option<MultiPartOptions>,
+            state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            self.0.copy(multi_part, state).await
+        }
+
+        async fn download(
+            &self,
+            multi_part: Option<MultiPartOptions>,
+        ) -> crate::error::Result<CopyContent> {
+            let content = self.0.download(multi_part).await?;
+            CopyContent::builder(Box::new(content.data.take(1).chain(Broken)))
+                .with_reopen(|| async { Err(CopyError("not reopened".to_string())) })
+                .build()
+        }
+
+        async fn upload(
+            &self,
+            data: CopyContent,
+            multi_part: Option<MultiPartOptions>,
+            state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            self.0.upload(data, multi_part, state).await
+        }
+
+        fn limits(&self) -> ProviderLimits {
+            self.0.limits()
+        }
+
+        async fn initialize_state(&self) -> crate::error::Result<CopyState> {
+            self.0.initialize_state().await
+        }
+    }
+
+    #[tokio::test]
+    async fn download_falls_back_to_replica() -> Result<()> {
+        let tmp = tempdir()?;
+        let primary = tmp.path().join("primary");
+        let replica = tmp.path().join("replica");
+        fs::write(&replica, b"aaaabbbb").await?;
+
+        let fallback = Fallback::try_new(
+            file(&primary),
+            &CopyState::new(8, None, None),
+            vec![("replica".to_string(), file(&replica))],
+        )
+        .await?;
+
+        let options = MultiPartOptions {
+            part_number: Some(2),
+            start: 4,
+            end: 8,
+            ..Default::default()
+        };
+        let content = fallback.download(Some(options)).await?;
+
+        let mut buf = vec![];
+        content.data.take(4).read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"bbbb");
+        assert_eq!(fallback.active(), 1);
+
+        // The last source is kept even if it also fails.
+        fs::remove_file(&replica).await?;
+        assert!(fallback.download(None).await.is_err());
+        assert_eq!(fallback.active(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_continues_from_replica_after_read_error() -> Result<()> {
+        let tmp = tempdir()?;
+        let primary = tmp.path().join("primary");
+        let replica = tmp.path().join("replica");
+        fs::write(&primary, b"aaaabbbb").await?;
+        fs::write(&replica, b"aaaaBBBB").await?;
+
+        let fallback = Fallback::try_new(
+            Box::new(BrokenSource(file(&primary))),
+            &CopyState::new(8, None, None),
+            vec![("replica".to_string(), file(&replica))],
+        )
+        .await?;
+
+        let options = MultiPartOptions {
+            part_number: Some(2),
+            start: 4,
+            end: 8,
+            ..Default::default()
+        };
+        let mut buf = vec![];
+        fallback
+            .download(Some(options))
+            .await?
+            .data
+            .read_to_end(&mut buf)
+            .await?;
+
+        // Each read error continues from the byte where the read stopped, and the rest of the
+        // part is read from the replica after the primary fails repeatedly.
+        assert_eq!(buf, b"bbbB");
+        assert_eq!(fallback.active(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replicas_must_match_the_source() -> Result<()> {
+        let tmp = tempdir()?;
+        let primary = tmp.path().join("primary");
+        let replica = tmp.path().join("replica");
+        fs::write(&replica, b"aaaa").await?;
+
+        let result = Fallback::try_new(
+            file(&primary),
+            &CopyState::new(8, None, None),
+            vec![("replica".to_string(), file(&replica))],
+        )
+        .await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
@@ -4,9 +4,12 @@
 use crate::checksum::file::SumsFile;
 use crate::error::Error::CopyError;
 use crate::error::Result;
+use crate::io::copy::limits::ProviderLimits;
 use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy};
+use crate::io::space::max_file_size;
 use std::future::Future;
 use std::io::SeekFrom;
+use std::path::Path;
 use std::pin::Pin;
 use tokio::fs::copy;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
@@ -111,11 +114,24 @@ impl File {
         &self,
         mut data: CopyContent,
         multipart: Option<MultiPartOptions>,
+        state: &CopyState,
     ) -> Result<u64> {
+        let destination = self.get_destination()?;
+
         // Determine the part number for this write. A multipart write with no part number is the
         // completion step, which writes nothing.
         let part_number = if let Some(multipart) = &multipart {
             if multipart.part_number.is_none() {
+                // Differential copies write parts in place, so anything past the end of the
+                // object is left over from the existing file.
+                if state.differential() {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .open(destination)
+                        .await?
+                        .set_len(state.size())
+                        .await?;
+                }
                 return Ok(0);
             }
             multipart.part_number
@@ -123,20 +139,29 @@ impl File {
             None
         };
 
-        let destination = self.get_destination()?;
-
         // The first part should truncate an existing file.
         let append = if let Some(part_number) = part_number {
             part_number > 1
         } else {
             false
         };
-        let mut file = if append {
-            fs::OpenOptions::new()
-                .append(true)
+        let mut file = if let Some(multipart) = multipart.as_ref().filter(|_| state.differential())
+        {
+            // Write the part at its offset so that unchanged parts of the file are kept.
+            let mut file = fs::OpenOptions::new()
                 .write(true)
+                .create(true)
+                .truncate(false)
                 .open(destination)
-                .await?
+                .await?;
+            file.seek(SeekFrom::Start(multipart.start)).await?;
+            file
+        } else if let Some(multipart) = multipart.as_ref().filter(|_| append) {
+            // Parts arrive in order, so this is the end of the file unless a failed attempt at
+            // this part is being retried, in which case its partial write is overwritten.
+            let mut file = fs::OpenOptions::new().write(true).open(destination).await?;
+            file.seek(SeekFrom::Start(multipart.start)).await?;
+            file
         } else {
             fs::File::create(destination).await?
         };
@@ -205,25 +230,44 @@ impl ObjectCopy for File {
         &self,
         data: CopyContent,
         multipart: Option<MultiPartOptions>,
-        _state: &CopyState,
+        state: &CopyState,
     ) -> Result<CopyResult> {
-        // It doesn't matter what the part number is for filesystem operations, just append to the
-        // end of the file as we assume correct ordering of parts.
-        let bytes = self.write(data, multipart).await?;
+        // Parts are written at their offset, and are assumed to arrive in order.
+        let bytes = self.write(data, multipart, state).await?;
 
         CopyResult::new(None, None, bytes, vec![])
     }
 
-    fn max_part_size(&self) -> u64 {
-        u64::MAX
+    async fn reuse(&self, multi_part: MultiPartOptions, state: &CopyState) -> Result<CopyResult> {
+        if !state.differential() {
+            return Err(CopyError(
+                "parts can only be reused for differential copies".to_string(),
+            ));
+        }
+
+        // The existing data is already in place, so there is nothing to write.
+        Ok(CopyResult {
+            bytes_reused: multi_part.bytes_transferred(),
+            ..Default::default()
+        })
     }
 
-    fn max_parts(&self) -> u64 {
-        u64::MAX
+    fn supports_reuse(&self) -> bool {
+        true
     }
 
-    fn min_part_size(&self) -> u64 {
-        u64::MIN
+    fn limits(&self) -> ProviderLimits {
+        // Some file systems limit the size of files, which is checked before copying to them.
+        match self
+            .destination
+            .as_deref()
+            .map(|destination| max_file_size(Path::new(destination)))
+        {
+            Some(Ok(Some(max_file_size))) => {
+                ProviderLimits::UNLIMITED.with_max_object_size(max_file_size)
+            }
+            _ => ProviderLimits::UNLIMITED,
+        }
     }
 
     async fn initialize_state(&self) -> Result<CopyState> {
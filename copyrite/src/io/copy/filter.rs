@@ -0,0 +1,259 @@
+//! Filtering and rewriting of tags and metadata when copying objects.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A single rule that filters or rewrites key-value pairs such as tags or metadata. Rules
+/// are parsed from strings with a `<kind>:<value>` syntax, e.g. `exclude:temp-*` or
+/// `add:migrated=true`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterRule {
+    /// Only keep keys that match the pattern.
+    Include(String),
+    /// Drop keys that match the pattern.
+    Exclude(String),
+    /// Add a key-value pair, overwriting an existing value.
+    Add(String, String),
+}
+
+impl FromStr for FilterRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || {
+            ParseError(format!(
+                "invalid filter rule `{}`, expected `include:<pattern>`, `exclude:<pattern>` or \
+                `add:<key>=<value>`",
+                s
+            ))
+        };
+
+        let (kind, value) = s.split_once(':').ok_or_else(err)?;
+        if value.is_empty() {
+            return Err(err());
+        }
+
+        match kind {
+            "include" => Ok(Self::Include(value.to_string())),
+            "exclude" => Ok(Self::Exclude(value.to_string())),
+            "add" => {
+                let (key, value) = value.split_once('=').ok_or_else(err)?;
+                if key.is_empty() {
+                    return Err(err());
+                }
+                Ok(Self::Add(key.to_string(), value.to_string()))
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Display for FilterRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterRule::Include(pattern) => write!(f, "include:{}", pattern),
+            FilterRule::Exclude(pattern) => write!(f, "exclude:{}", pattern),
+            FilterRule::Add(key, value) => write!(f, "add:{}={}", key, value),
+        }
+    }
+}
+
+/// A set of filter rules applied to tags or metadata between reading them from the source and
+/// writing them to the destination.
+///
+/// Include rules are applied first, keeping only keys that match at least one include pattern.
+/// Exclude rules then drop any matching keys, and finally add rules set new key-value pairs.
+/// Patterns match the whole key and support `*` as a wildcard for any number of characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataFilter(Vec<FilterRule>);
+
+impl MetadataFilter {
+    /// Create a new filter from a set of rules.
+    pub fn new(rules: Vec<FilterRule>) -> Self {
+        Self(rules)
+    }
+
+    /// Whether there are no rules in this filter.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the key should be kept according to the include and exclude rules.
+    fn keep(&self, key: &str) -> bool {
+        let mut includes = self
+            .0
+            .iter()
+            .filter_map(|rule| match rule {
+                FilterRule::Include(pattern) => Some(pattern),
+                _ => None,
+            })
+            .peekable();
+        let included =
+            includes.peek().is_none() || includes.any(|pattern| matches_pattern(pattern, key));
+
+        included
+            && !self.0.iter().any(
+                |rule| matches!(rule, FilterRule::Exclude(pattern) if matches_pattern(pattern, key)),
+            )
+    }
+
+    /// Apply the filter to a set of key-value pairs.
+    pub fn apply<I>(&self, pairs: I) -> BTreeMap<String, String>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut pairs: BTreeMap<_, _> = pairs
+            .into_iter()
+            .filter(|(key, _)| self.keep(key))
+            .collect();
+
+        for rule in &self.0 {
+            if let FilterRule::Add(key, value) = rule {
+                pairs.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        pairs
+    }
+
+    /// Apply the filter to tags formatted as a query string, e.g. `key1=value1&key2=value2`.
+    pub fn apply_tags(&self, tags: Option<&str>) -> String {
+        let pairs = tags.into_iter().flat_map(|tags| {
+            tags.split('&').filter(|tag| !tag.is_empty()).map(|tag| {
+                let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+                (key.to_string(), value.to_string())
+            })
+        });
+
+        self.apply(pairs)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Apply the filter to object metadata.
+    pub fn apply_metadata(
+        &self,
+        metadata: Option<HashMap<String, String>>,
+    ) -> HashMap<String, String> {
+        HashMap::from_iter(self.apply(metadata.unwrap_or_default()))
+    }
+}
+
+impl From<Vec<FilterRule>> for MetadataFilter {
+    fn from(rules: Vec<FilterRule>) -> Self {
+        Self::new(rules)
+    }
+}
+
+/// Match a key against a pattern, where `*` matches any number of characters.
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There is always at least one part, which must be a prefix of the key.
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = key.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcards, so the pattern must match exactly.
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_rules() -> Result<()> {
+        assert_eq!(
+            "include:project-*".parse::<FilterRule>()?,
+            FilterRule::Include("project-*".to_string())
+        );
+        assert_eq!(
+            "exclude:temp-*".parse::<FilterRule>()?,
+            FilterRule::Exclude("temp-*".to_string())
+        );
+        assert_eq!(
+            "add:migrated=true".parse::<FilterRule>()?,
+            FilterRule::Add("migrated".to_string(), "true".to_string())
+        );
+        assert_eq!(
+            "add:empty=".parse::<FilterRule>()?,
+            FilterRule::Add("empty".to_string(), "".to_string())
+        );
+
+        assert!("drop:temp-*".parse::<FilterRule>().is_err());
+        assert!("exclude:".parse::<FilterRule>().is_err());
+        assert!("add:migrated".parse::<FilterRule>().is_err());
+        assert!("add:=true".parse::<FilterRule>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_matching() {
+        assert!(matches_pattern("temp-*", "temp-file"));
+        assert!(matches_pattern("temp-*", "temp-"));
+        assert!(!matches_pattern("temp-*", "file-temp-"));
+        assert!(matches_pattern("*-temp", "file-temp"));
+        assert!(matches_pattern("a*b*c", "axxbyyc"));
+        assert!(!matches_pattern("a*b*c", "axxcyyb"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("exact", "exact"));
+        assert!(!matches_pattern("exact", "exactly"));
+    }
+
+    #[test]
+    fn apply_tags() -> Result<()> {
+        let filter = MetadataFilter::new(vec![
+            "exclude:temp-*".parse()?,
+            "add:migrated=true".parse()?,
+        ]);
+
+        assert_eq!(
+            filter.apply_tags(Some("temp-a=1&keep=2&temp-b=3")),
+            "keep=2&migrated=true"
+        );
+        assert_eq!(filter.apply_tags(None), "migrated=true");
+
+        Ok(())
+    }
+
+    #[test]
+    fn apply_metadata() -> Result<()> {
+        let filter = MetadataFilter::new(vec![
+            "include:project-*".parse()?,
+            "exclude:project-temp".parse()?,
+        ]);
+
+        let metadata = HashMap::from_iter(vec![
+            ("project-id".to_string(), "1".to_string()),
+            ("project-temp".to_string(), "2".to_string()),
+            ("owner".to_string(), "3".to_string()),
+        ]);
+
+        assert_eq!(
+            filter.apply_metadata(Some(metadata)),
+            HashMap::from_iter(vec![("project-id".to_string(), "1".to_string())])
+        );
+
+        Ok(())
+    }
+}
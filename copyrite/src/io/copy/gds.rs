@@ -0,0 +1,184 @@
+//! GDS copy logic. Sources are downloaded from the presigned URL of the file, and destinations
+//! are created using the ICA API and uploaded to the underlying S3 bucket with the temporary
+//! credentials that it returns, using multipart uploads for large files.
+//!
+
+use crate::error::Error::{CopyError, GdsError};
+use crate::error::{Error, Result};
+use crate::io::Provider;
+use crate::io::copy::aws::S3;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy};
+use crate::io::gds::{GdsClient, GdsFile};
+use crate::io::http::HttpClient;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Build a GDS copy object.
+#[derive(Debug, Default)]
+pub struct GdsBuilder {
+    client: Option<GdsClient>,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl GdsBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: GdsClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source volume and path.
+    pub fn with_source(mut self, volume: &str, path: &str) -> Self {
+        self.source = Some((volume.to_string(), path.to_string()));
+        self
+    }
+
+    /// Set the destination volume and path.
+    pub fn with_destination(mut self, volume: &str, path: &str) -> Self {
+        self.destination = Some((volume.to_string(), path.to_string()));
+        self
+    }
+
+    /// Build the GDS copy object.
+    pub fn build(self) -> Gds {
+        Gds {
+            client: self.client.unwrap_or_default(),
+            http: HttpClient::default(),
+            source: self.source,
+            destination: self.destination,
+            source_file: Default::default(),
+            upload: Default::default(),
+        }
+    }
+}
+
+/// A GDS object copy. The source file and the destination upload are shared by all clones, so
+/// that each is only requested from the API once.
+#[derive(Debug, Clone)]
+pub struct Gds {
+    client: GdsClient,
+    http: HttpClient,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+    source_file: Arc<OnceCell<GdsFile>>,
+    upload: Arc<OnceCell<S3>>,
+}
+
+impl Gds {
+    /// Get the source file from the API.
+    async fn get_source_file(&self) -> Result<&GdsFile> {
+        let (volume, path) = self
+            .source
+            .as_ref()
+            .ok_or_else(|| CopyError("missing source".to_string()))?;
+
+        self.source_file
+            .get_or_try_init(|| async {
+                self.client.file(volume, path).await?.ok_or_else(|| {
+                    GdsError(format!(
+                        "{} does not exist",
+                        Provider::format_gds(volume, path)
+                    ))
+                })
+            })
+            .await
+    }
+
+    /// Get the S3 object that the destination is uploaded to, creating the file the first time.
+    async fn get_upload(&self) -> Result<&S3> {
+        let (volume, path) = self
+            .destination
+            .as_ref()
+            .ok_or_else(|| CopyError("missing destination".to_string()))?;
+
+        self.upload
+            .get_or_try_init(|| async { self.client.create_file(volume, path).await?.copy() })
+            .await
+    }
+
+    /// Get the source file, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_file(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        if let Some(multipart) = &multi_part
+            && multipart.part_number.is_none()
+        {
+            return Ok(CopyContent::empty());
+        }
+
+        let url = self.get_source_file().await?.presigned_url()?;
+        let (start, end) = multi_part.as_ref().map_or((0, None), |multipart| {
+            (multipart.start, Some(multipart.end))
+        });
+        let data = self
+            .http
+            .get(url, start, end)
+            .await?
+            .ok_or_else(|| GdsError(format!("{} does not exist", url)))?;
+
+        let self_clone = self.clone();
+        CopyContent::builder(data)
+            .with_reopen(move || self_clone.reopen_get(multi_part.clone()))
+            .build()
+    }
+
+    /// Re-read the source range.
+    fn reopen_get(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+    ) -> Pin<Box<dyn Future<Output = Result<CopyContent>> + Send>> {
+        let self_clone = self.clone();
+        Box::pin(async move { self_clone.get_file(multi_part).await })
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Gds {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(CopyError(
+            "server-side copies are not supported for GDS".to_string(),
+        ))
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_file(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        self.get_upload()
+            .await?
+            .upload(data, multi_part, state)
+            .await
+    }
+
+    async fn abort_upload(&self, upload_id: &str, err: Error) -> Error {
+        match self.upload.get() {
+            Some(upload) => upload.abort_upload(upload_id, err).await,
+            None => err,
+        }
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        ProviderLimits::default()
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        Ok(CopyState::new(
+            self.get_source_file().await?.size()?,
+            None,
+            None,
+        ))
+    }
+}
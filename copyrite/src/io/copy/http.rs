@@ -0,0 +1,174 @@
+//! HTTP copy logic. HTTP locations can only be used as a source, where objects are downloaded
+//! with range requests for each part.
+//!
+
+use crate::error::Error::{CopyError, HttpError};
+use crate::error::Result;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy};
+use crate::io::http::HttpClient;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Build an HTTP copy object.
+#[derive(Debug, Default)]
+pub struct HttpBuilder {
+    client: Option<HttpClient>,
+    source: Option<String>,
+}
+
+impl HttpBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source url.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Build the HTTP copy object.
+    pub fn build(self) -> Http {
+        Http {
+            client: self.client.unwrap_or_default(),
+            source: self.source,
+        }
+    }
+}
+
+/// An HTTP object copy.
+#[derive(Debug, Clone)]
+pub struct Http {
+    client: HttpClient,
+    source: Option<String>,
+}
+
+impl Http {
+    fn get_source(&self) -> Result<&str> {
+        self.source
+            .as_deref()
+            .ok_or_else(|| CopyError("missing source".to_string()))
+    }
+
+    /// The error returned when an HTTP location is used as a destination.
+    pub fn read_only_error() -> crate::error::Error {
+        CopyError("HTTP locations are read-only and cannot be used as a destination".to_string())
+    }
+
+    /// Get the source object, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_object(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let url = self.get_source()?;
+
+        if let Some(multipart) = &multi_part
+            && multipart.part_number.is_none()
+        {
+            return Ok(CopyContent::empty());
+        }
+
+        let (start, end) = multi_part.as_ref().map_or((0, None), |multipart| {
+            (multipart.start, Some(multipart.end))
+        });
+        let data = self
+            .client
+            .get(url, start, end)
+            .await?
+            .ok_or_else(|| HttpError(format!("{} does not exist", url)))?;
+
+        let self_clone = self.clone();
+        CopyContent::builder(data)
+            .with_reopen(move || self_clone.reopen_get(multi_part.clone()))
+            .build()
+    }
+
+    /// Re-read the source range.
+    fn reopen_get(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+    ) -> Pin<Box<dyn Future<Output = Result<CopyContent>> + Send>> {
+        let self_clone = self.clone();
+        Box::pin(async move { self_clone.get_object(multi_part).await })
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Http {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(Self::read_only_error())
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_object(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        _data: CopyContent,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(Self::read_only_error())
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        ProviderLimits::UNLIMITED
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let url = self.get_source()?;
+        let properties = self
+            .client
+            .head(url)
+            .await?
+            .ok_or_else(|| HttpError(format!("{} does not exist", url)))?;
+
+        Ok(CopyState::new(properties.size(url)?, None, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::Provider;
+    use crate::io::copy::ObjectCopyBuilder;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn http_source() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/genome.fa", listener.local_addr()?);
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).await?;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n")
+                .await?;
+            anyhow::Ok(())
+        });
+
+        let source = ObjectCopyBuilder::default()
+            .set_source(Some(Provider::try_from(url.as_str())?))
+            .build()
+            .await?;
+        assert_eq!(source.initialize_state().await?.size(), 5);
+        assert_eq!(source.limits(), ProviderLimits::UNLIMITED);
+
+        let destination = ObjectCopyBuilder::default()
+            .set_source(Some(Provider::try_from("file")?))
+            .set_destination(Some(Provider::try_from(url.as_str())?))
+            .build()
+            .await;
+        assert!(destination.is_err());
+
+        Ok(())
+    }
+}
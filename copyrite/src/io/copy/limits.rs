@@ -0,0 +1,122 @@
+//! The multipart limits of each storage provider, which are used to plan the part sizes of
+//! copies to it.
+//!
+
+use crate::io::gcs::is_gcs_endpoint;
+
+/// The limits of multipart uploads and objects for a storage provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProviderLimits {
+    max_parts: u64,
+    min_part_size: u64,
+    max_part_size: u64,
+    max_object_size: u64,
+}
+
+impl ProviderLimits {
+    /// The limits of S3, which are also used by other S3-compatible stores by default.
+    pub const S3: Self = Self::new(10000, 5242880, 5368709120, 54975581388800);
+
+    /// The limits of GCS multipart uploads through the XML API. These are separate from the limit
+    /// of 32 objects in a compose request, which copies do not use.
+    pub const GCS: Self = Self::new(10000, 5242880, 5368709120, 5497558138880);
+
+    /// The limits of Azure block blobs, where each part is uploaded as a block. Single requests
+    /// are limited to the same size as a block.
+    pub const AZURE: Self = Self::new(50000, 1, 4194304000, 209715200000000);
+
+    /// The limits of Swift static large objects, where each part is uploaded as a segment. These
+    /// are the default limits of a Swift cluster, where single objects are limited to 5 GiB and
+    /// manifests to 1000 segments.
+    pub const SWIFT: Self = Self::new(1000, 1, 5368709120, 5368709120000);
+
+    /// The limits of chunked uploads to Nextcloud and ownCloud, where each part is uploaded as a
+    /// chunk. The maximum object size depends on the storage of the server.
+    pub const WEBDAV_CHUNKED: Self = Self::new(10000, 5242880, 5368709120, u64::MAX);
+
+    /// The limits of other WebDAV servers, which cannot combine parts, so files are always
+    /// uploaded in a single request.
+    pub const WEBDAV: Self = Self::new(1, 1, u64::MAX, u64::MAX);
+
+    /// The limits of providers without any, such as local files.
+    pub const UNLIMITED: Self = Self::new(u64::MAX, u64::MIN, u64::MAX, u64::MAX);
+
+    /// Create limits.
+    pub const fn new(
+        max_parts: u64,
+        min_part_size: u64,
+        max_part_size: u64,
+        max_object_size: u64,
+    ) -> Self {
+        Self {
+            max_parts,
+            min_part_size,
+            max_part_size,
+            max_object_size,
+        }
+    }
+
+    /// Set the maximum total object size, e.g. for a file system that limits the size of files.
+    pub const fn with_max_object_size(mut self, max_object_size: u64) -> Self {
+        self.max_object_size = max_object_size;
+        self
+    }
+
+    /// Get the limits of the S3-compatible store at an endpoint, where no endpoint is S3.
+    pub fn for_endpoint(endpoint_url: Option<&str>) -> Self {
+        match endpoint_url {
+            Some(endpoint_url) if is_gcs_endpoint(endpoint_url) => Self::GCS,
+            _ => Self::S3,
+        }
+    }
+
+    /// The maximum number of parts for multipart copies.
+    pub fn max_parts(&self) -> u64 {
+        self.max_parts
+    }
+
+    /// The minimum size of all parts except the last for multipart copies.
+    pub fn min_part_size(&self) -> u64 {
+        self.min_part_size
+    }
+
+    /// The maximum part size for multipart copies, which is also the maximum size of a single
+    /// part upload.
+    pub fn max_part_size(&self) -> u64 {
+        self.max_part_size
+    }
+
+    /// The maximum total object size.
+    pub fn max_object_size(&self) -> u64 {
+        self.max_object_size
+    }
+}
+
+impl Default for ProviderLimits {
+    fn default() -> Self {
+        Self::S3
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::gcs::GCS_ENDPOINT_URL;
+
+    #[test]
+    fn limits_for_endpoint() {
+        assert_eq!(ProviderLimits::for_endpoint(None), ProviderLimits::S3);
+        assert_eq!(
+            ProviderLimits::for_endpoint(Some("https://storage.example.com")),
+            ProviderLimits::S3
+        );
+        assert_eq!(
+            ProviderLimits::for_endpoint(Some(GCS_ENDPOINT_URL)),
+            ProviderLimits::GCS
+        );
+        assert_eq!(
+            ProviderLimits::for_endpoint(Some("https://storage.googleapis.com:443/")),
+            ProviderLimits::GCS
+        );
+    }
+}
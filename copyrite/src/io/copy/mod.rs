@@ -4,18 +4,36 @@
 use crate::checksum::Ctx;
 use crate::cli::MetadataCopy;
 use crate::error::Error::CopyError;
-use crate::error::{ApiError, Result};
+use crate::error::{ApiError, Error, Result};
 use crate::io::copy::aws::S3Builder;
 use crate::io::copy::file::FileBuilder;
+use crate::io::copy::filter::MetadataFilter;
+use crate::io::copy::http::Http;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::registry::ProviderRegistry;
 use crate::io::{Provider, S3Client};
+use aws_sdk_s3::types::StorageClass;
 use dyn_clone::DynClone;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 use tokio::io::{AsyncRead, empty};
 
 pub mod aws;
+pub mod azure;
+pub mod differential;
+pub mod fallback;
 pub mod file;
+pub mod filter;
+pub mod gds;
+pub mod http;
+pub mod limits;
+#[cfg(unix)]
+pub mod sftp;
+pub mod swift;
+pub mod validate;
+pub mod webdav;
 
 /// A function that re-opens the copy content stream from its source. This is lazily loaded
 /// to reread the source instead of holding bytes in memory unnecessarily when re-trying.
@@ -43,6 +61,42 @@ impl CopyContent {
             reopen: Box::new(|| Box::pin(async { Ok(CopyContent::empty()) })),
         }
     }
+
+    /// Read the source, or the range of it for a part, by calling `read` with the start and end
+    /// of the range. The content carries a reopen function that reads the same range again.
+    /// The completion step of a multipart copy has no content.
+    pub async fn ranged<F, Fut>(multi_part: Option<MultiPartOptions>, read: F) -> Result<Self>
+    where
+        F: Fn(u64, Option<u64>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<Box<dyn AsyncRead + Sync + Send + Unpin>>> + Send + 'static,
+    {
+        if let Some(multipart) = &multi_part
+            && multipart.part_number.is_none()
+        {
+            return Ok(Self::empty());
+        }
+
+        let (start, end) = multi_part.as_ref().map_or((0, None), |multipart| {
+            (multipart.start, Some(multipart.end))
+        });
+        let data = read(start, end).await?;
+
+        Self::builder(data)
+            .with_reopen(move || Self::reopen_ranged(multi_part.clone(), read.clone()))
+            .build()
+    }
+
+    /// Re-read the range of the source.
+    fn reopen_ranged<F, Fut>(
+        multi_part: Option<MultiPartOptions>,
+        read: F,
+    ) -> Pin<Box<dyn Future<Output = Result<Self>> + Send>>
+    where
+        F: Fn(u64, Option<u64>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<Box<dyn AsyncRead + Sync + Send + Unpin>>> + Send + 'static,
+    {
+        Box::pin(Self::ranged(multi_part, read))
+    }
 }
 
 /// Builder for [`CopyContent`].
@@ -74,6 +128,48 @@ impl CopyContentBuilder {
     }
 }
 
+/// The default number of times completing a multipart upload is retried.
+pub const DEFAULT_COMPLETE_RETRIES: usize = 3;
+
+/// The initial backoff between retries when completing a multipart upload.
+pub const DEFAULT_COMPLETE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Options for completing multipart uploads.
+#[derive(Debug, Clone, Copy)]
+pub struct CompleteOptions {
+    pub(crate) retries: usize,
+    pub(crate) backoff: Duration,
+    pub(crate) abort_on_failure: bool,
+}
+
+impl CompleteOptions {
+    /// Create new options with the number of retries for transient errors, and whether to abort
+    /// the upload if it cannot be completed.
+    pub fn new(retries: usize, abort_on_failure: bool) -> Self {
+        Self {
+            retries,
+            abort_on_failure,
+            ..Default::default()
+        }
+    }
+
+    /// Set the initial backoff, which doubles after each retry.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+impl Default for CompleteOptions {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_COMPLETE_RETRIES,
+            backoff: DEFAULT_COMPLETE_BACKOFF,
+            abort_on_failure: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MultiPartOptions {
     pub(crate) part_number: Option<u64>,
@@ -110,6 +206,7 @@ pub struct Part {
     pub(crate) md5: Option<String>,
     pub(crate) e_tag: Option<String>,
     pub(crate) part_number: u64,
+    pub(crate) size: Option<u64>,
 }
 
 /// The result of a copy operation.
@@ -118,8 +215,12 @@ pub struct CopyResult {
     pub(crate) part: Option<Part>,
     pub(crate) upload_id: Option<String>,
     pub(crate) bytes_transferred: u64,
+    pub(crate) bytes_reused: u64,
     pub(crate) n_retries: u64,
+    pub(crate) n_download_retries: u64,
     pub(crate) api_errors: Vec<ApiError>,
+    pub(crate) content_md5_validated: bool,
+    pub(crate) checksum_rejected: bool,
 }
 
 impl CopyResult {
@@ -140,6 +241,13 @@ impl CopyResult {
         result.with_api_errors(api_errors)
     }
 
+    /// Set the size of the uploaded part, which is checked before completing the upload.
+    pub fn set_part_size(&mut self, size: u64) {
+        if let Some(part) = self.part.as_mut() {
+            part.size = Some(size);
+        }
+    }
+
     /// Set the errors.
     pub fn with_api_errors(mut self, api_errors: Vec<ApiError>) -> Result<Self> {
         self.n_retries = u64::try_from(api_errors.len())?;
@@ -165,6 +273,11 @@ pub struct CopyState {
     tags: Option<String>,
     metadata: Option<HashMap<String, String>>,
     additional_ctx: Option<Ctx>,
+    content_md5: Option<String>,
+    e_tag: Option<String>,
+    replace_tags: bool,
+    replace_metadata: bool,
+    differential: bool,
 }
 
 impl CopyState {
@@ -195,13 +308,93 @@ impl CopyState {
             tags,
             metadata,
             additional_ctx: None,
+            content_md5: None,
+            e_tag: None,
+            replace_tags: false,
+            replace_metadata: false,
+            differential: false,
         }
     }
 
+    /// Whether this is a differential copy that keeps unchanged parts of the existing
+    /// destination object.
+    pub fn differential(&self) -> bool {
+        self.differential
+    }
+
+    /// Set whether this is a differential copy.
+    pub fn set_differential(&mut self, differential: bool) {
+        self.differential = differential;
+    }
+
+    /// Whether the tags were rewritten by a filter and should replace the source tags on
+    /// server-side copies.
+    pub fn replace_tags(&self) -> bool {
+        self.replace_tags
+    }
+
+    /// Whether the metadata was rewritten by a filter and should replace the source metadata on
+    /// server-side copies.
+    pub fn replace_metadata(&self) -> bool {
+        self.replace_metadata
+    }
+
+    /// Set whether the tags should replace the source tags on server-side copies.
+    pub fn set_replace_tags(&mut self, replace_tags: bool) {
+        self.replace_tags = replace_tags;
+    }
+
+    /// Set whether the metadata should replace the source metadata on server-side copies.
+    pub fn set_replace_metadata(&mut self, replace_metadata: bool) {
+        self.replace_metadata = replace_metadata;
+    }
+
+    /// Apply a filter to the tags read from the source. This does nothing if the filter is empty.
+    pub fn filter_tags(&mut self, filter: &MetadataFilter) {
+        if filter.is_empty() {
+            return;
+        }
+
+        self.tags = Some(filter.apply_tags(self.tags.as_deref()));
+        self.replace_tags = true;
+    }
+
+    /// Apply a filter to the metadata read from the source. This does nothing if the filter is
+    /// empty.
+    pub fn filter_metadata(&mut self, filter: &MetadataFilter) {
+        if filter.is_empty() {
+            return;
+        }
+
+        self.metadata = Some(filter.apply_metadata(self.metadata.take()));
+        self.replace_metadata = true;
+    }
+
     /// Set the additional context.
     pub fn set_additional_ctx(&mut self, additional_ctx: Ctx) {
         self.additional_ctx = Some(additional_ctx);
     }
+
+    /// Get the base64 encoded MD5 of the object to send as the `Content-MD5` of single part
+    /// uploads.
+    pub fn content_md5(&self) -> Option<String> {
+        self.content_md5.clone()
+    }
+
+    /// Set the base64 encoded MD5 of the object.
+    pub fn set_content_md5(&mut self, content_md5: Option<String>) {
+        self.content_md5 = content_md5;
+    }
+
+    /// Get the ETag of the object, if the provider reports one.
+    pub fn e_tag(&self) -> Option<&str> {
+        self.e_tag.as_deref()
+    }
+
+    /// Set the ETag of the object.
+    pub fn set_e_tag(&mut self, e_tag: Option<String>) {
+        self.e_tag = e_tag;
+    }
 }
 
 /// Write operations on file based or cloud files.
@@ -225,20 +418,39 @@ pub trait ObjectCopy: DynClone {
         state: &CopyState,
     ) -> Result<CopyResult>;
 
-    /// The maximum part size for multipart copy.
-    fn max_part_size(&self) -> u64;
+    /// Keep the part of the existing destination object described by the multipart options
+    /// instead of uploading it. This is used by differential copies for parts that have not
+    /// changed.
+    async fn reuse(&self, _multi_part: MultiPartOptions, _state: &CopyState) -> Result<CopyResult> {
+        Err(CopyError(
+            "the destination does not support reusing existing parts".to_string(),
+        ))
+    }
 
-    /// The maximum number of parts for multipart copies.
-    fn max_parts(&self) -> u64;
+    /// Whether the destination implements `reuse`, which is required for differential copies.
+    fn supports_reuse(&self) -> bool {
+        false
+    }
 
-    /// The minimum part size for multipart copies.
-    fn min_part_size(&self) -> u64;
+    /// The checksum that a downloaded part is expected to have, as the checksum context and its
+    /// base64 encoded digest. This is `None` if the source does not know the checksum of the part.
+    async fn part_checksum(
+        &self,
+        _multi_part: &MultiPartOptions,
+        _state: &CopyState,
+    ) -> Option<(Ctx, String)> {
+        None
+    }
 
-    /// The maximum total object size supported by the destination.
-    fn max_object_size(&self) -> u64 {
-        u64::MAX
+    /// Clean up an incomplete multipart upload after the copy was cancelled, returning an error
+    /// that describes what happened to the upload. By default, the error is returned unchanged.
+    async fn abort_upload(&self, _upload_id: &str, err: Error) -> Error {
+        err
     }
 
+    /// The multipart and object size limits of the destination.
+    fn limits(&self) -> ProviderLimits;
+
     /// Get the size of the object.
     async fn initialize_state(&self) -> Result<CopyState>;
 }
@@ -250,6 +462,8 @@ dyn_clone::clone_trait_object!(ObjectCopy);
 pub struct ObjectCopyBuilder {
     metadata_mode: MetadataCopy,
     tag_mode: MetadataCopy,
+    complete_options: CompleteOptions,
+    storage_class: Option<StorageClass>,
     client: Option<S3Client>,
     source: Option<Provider>,
     destination: Option<Provider>,
@@ -258,6 +472,19 @@ pub struct ObjectCopyBuilder {
 impl ObjectCopyBuilder {
     /// Build the object copy. Both the source and destination need to be of the same type.
     pub async fn build(self) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        if self.destination.as_ref().is_some_and(Provider::is_http) {
+            return Err(Http::read_only_error());
+        }
+        if let Some(scheme) = self
+            .source
+            .as_ref()
+            .or(self.destination.as_ref())
+            .and_then(Provider::registered_scheme)
+        {
+            let scheme = scheme.to_string();
+            return self.build_registered(&scheme).await;
+        }
+
         let is_s3 = match (&self.source, &self.destination) {
             (Some(source), _) => source.is_s3(),
             (_, Some(destination)) => destination.is_s3(),
@@ -277,6 +504,8 @@ impl ObjectCopyBuilder {
             let mut builder = S3Builder::default()
                 .with_copy_metadata(self.metadata_mode)
                 .with_copy_tags(self.tag_mode)
+                .with_complete_options(self.complete_options)
+                .set_storage_class(self.storage_class)
                 .with_client(client);
 
             if let Some((bucket, key)) = source {
@@ -306,6 +535,27 @@ impl ObjectCopyBuilder {
         }
     }
 
+    /// Build an object copy using the factory registered for the scheme of a custom or
+    /// registered built-in provider.
+    async fn build_registered(self, scheme: &str) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        let source = self
+            .source
+            .map(|source| source.into_registered())
+            .transpose()?;
+        let destination = self
+            .destination
+            .map(|destination| destination.into_registered())
+            .transpose()?;
+
+        ProviderRegistry::try_get(scheme)?
+            .copy_with_client(
+                self.client.as_ref(),
+                source.as_ref().map(|(_, url)| url.as_str()),
+                destination.as_ref().map(|(_, url)| url.as_str()),
+            )
+            .await
+    }
+
     /// Set the destination.
     pub fn set_destination(mut self, destination: Option<Provider>) -> Self {
         self.destination = destination;
@@ -335,4 +585,16 @@ impl ObjectCopyBuilder {
         self.tag_mode = tag_mode;
         self
     }
+
+    /// Set the options for completing multipart uploads.
+    pub fn with_complete_options(mut self, complete_options: CompleteOptions) -> Self {
+        self.complete_options = complete_options;
+        self
+    }
+
+    /// Set the storage class of objects written to an S3 destination.
+    pub fn set_storage_class(mut self, storage_class: Option<StorageClass>) -> Self {
+        self.storage_class = storage_class;
+        self
+    }
 }
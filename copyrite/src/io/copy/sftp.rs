@@ -0,0 +1,195 @@
+//! SFTP copy logic. Each part of a multipart copy is written at its offset in the destination
+//! file, which is truncated to the size of the object once all parts are written.
+//!
+
+use crate::error::Error::{CopyError, SftpError};
+use crate::error::Result;
+use crate::io::Provider;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part};
+use crate::io::sftp::SftpClient;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Build an SFTP copy object.
+#[derive(Debug, Default)]
+pub struct SftpBuilder {
+    client: Option<SftpClient>,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl SftpBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: SftpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source host and path.
+    pub fn with_source(mut self, host: &str, path: &str) -> Self {
+        self.source = Some((host.to_string(), path.to_string()));
+        self
+    }
+
+    /// Set the destination host and path.
+    pub fn with_destination(mut self, host: &str, path: &str) -> Self {
+        self.destination = Some((host.to_string(), path.to_string()));
+        self
+    }
+
+    /// Build the SFTP copy object.
+    pub fn build(self) -> Sftp {
+        Sftp {
+            client: self.client.unwrap_or_default(),
+            source: self.source,
+            destination: self.destination,
+        }
+    }
+}
+
+/// An SFTP object copy.
+#[derive(Debug, Clone)]
+pub struct Sftp {
+    client: SftpClient,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl Sftp {
+    fn get_source(&self) -> Result<(&str, &str)> {
+        self.source
+            .as_ref()
+            .map(|(host, path)| (host.as_str(), path.as_str()))
+            .ok_or_else(|| CopyError("missing source".to_string()))
+    }
+
+    fn get_destination(&self) -> Result<(&str, &str)> {
+        self.destination
+            .as_ref()
+            .map(|(host, path)| (host.as_str(), path.as_str()))
+            .ok_or_else(|| CopyError("missing destination".to_string()))
+    }
+
+    /// Get the source file, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_file(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let (host, path) = self.get_source()?;
+
+        if let Some(multipart) = &multi_part
+            && multipart.part_number.is_none()
+        {
+            return Ok(CopyContent::empty());
+        }
+
+        let (start, end) = multi_part.as_ref().map_or((0, None), |multipart| {
+            (multipart.start, Some(multipart.end))
+        });
+        let data = self
+            .client
+            .read(host, path, start, end)
+            .await?
+            .ok_or_else(|| {
+                SftpError(format!(
+                    "{} does not exist",
+                    Provider::format_sftp(host, path)
+                ))
+            })?;
+
+        let self_clone = self.clone();
+        CopyContent::builder(data)
+            .with_reopen(move || self_clone.reopen_get(multi_part.clone()))
+            .build()
+    }
+
+    /// Re-read the source range.
+    fn reopen_get(
+        &self,
+        multi_part: Option<MultiPartOptions>,
+    ) -> Pin<Box<dyn Future<Output = Result<CopyContent>> + Send>> {
+        let self_clone = self.clone();
+        Box::pin(async move { self_clone.get_file(multi_part).await })
+    }
+
+    /// Write the whole file, replacing any existing file.
+    pub async fn put_file(&self, content: CopyContent) -> Result<CopyResult> {
+        let (host, path) = self.get_destination()?;
+        let written = self.client.write(host, path, content.data, None).await?;
+
+        CopyResult::new(None, None, written, vec![])
+    }
+
+    /// Write a part at its offset, or truncate the file to the size of the object if this is
+    /// the completion step.
+    pub async fn put_part(
+        &self,
+        content: CopyContent,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let (host, path) = self.get_destination()?;
+
+        let Some(part_number) = multi_part.part_number else {
+            self.client.set_len(host, path, state.size()).await?;
+            return CopyResult::new(None, None, 0, vec![]);
+        };
+
+        let size = self
+            .client
+            .write(host, path, content.data, Some(multi_part.start))
+            .await?;
+
+        let part = Part {
+            part_number,
+            size: Some(size),
+            ..Default::default()
+        };
+        CopyResult::new(Some(part), None, size, vec![])
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Sftp {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(CopyError(
+            "server-side copies are not supported for SFTP".to_string(),
+        ))
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_file(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        if let Some(multi_part) = multi_part {
+            self.put_part(data, multi_part, state).await
+        } else {
+            self.put_file(data).await
+        }
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        ProviderLimits::UNLIMITED
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let (host, path) = self.get_source()?;
+        let size = self.client.size(host, path).await?.ok_or_else(|| {
+            SftpError(format!(
+                "{} does not exist",
+                Provider::format_sftp(host, path)
+            ))
+        })?;
+
+        Ok(CopyState::new(size, None, None))
+    }
+}
@@ -0,0 +1,435 @@
+//! OpenStack Swift copy logic. Objects are uploaded in a single request, or as a static large
+//! object, where each part of a multipart copy is a segment in the segments container that is
+//! joined by a manifest once all parts are uploaded.
+//!
+
+use crate::error::Error::{CopyError, SwiftError};
+use crate::error::Result;
+use crate::io::Provider;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part};
+use crate::io::rest::Rest;
+use crate::io::swift::{
+    ManifestSegment, ObjectHeaders, ObjectReader, SEGMENTS_CONTAINER_SUFFIX, SwiftClient,
+};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::OnceCell;
+
+/// Build a Swift copy object.
+#[derive(Debug, Default)]
+pub struct SwiftBuilder {
+    client: Option<SwiftClient>,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+}
+
+impl SwiftBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: SwiftClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source container and object.
+    pub fn with_source(mut self, container: &str, object: &str) -> Self {
+        self.source = Some((container.to_string(), object.to_string()));
+        self
+    }
+
+    /// Set the destination container and object.
+    pub fn with_destination(mut self, container: &str, object: &str) -> Self {
+        self.destination = Some((container.to_string(), object.to_string()));
+        self
+    }
+
+    /// Build the Swift copy object. Segments of the destination are uploaded under a prefix
+    /// that is unique to this copy, so that the segments of an existing object with the same
+    /// name are not overwritten.
+    pub fn build(self) -> Swift {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let segment_prefix = self
+            .destination
+            .as_ref()
+            .map(|(_, object)| format!("{}/slo/{}", object, timestamp));
+
+        Swift {
+            client: self.client.unwrap_or_default(),
+            source: self.source,
+            destination: self.destination,
+            segment_prefix,
+            segments_container: Default::default(),
+        }
+    }
+}
+
+/// A Swift object copy. The segments container is shared by all clones, so that it is only
+/// created once.
+#[derive(Debug, Clone)]
+pub struct Swift {
+    client: SwiftClient,
+    source: Option<(String, String)>,
+    destination: Option<(String, String)>,
+    segment_prefix: Option<String>,
+    segments_container: Arc<OnceCell<String>>,
+}
+
+impl Swift {
+    fn get_source(&self) -> Result<(&str, &str)> {
+        self.source
+            .as_ref()
+            .map(|(container, object)| (container.as_str(), object.as_str()))
+            .ok_or_else(|| CopyError("missing source".to_string()))
+    }
+
+    fn get_destination(&self) -> Result<(&str, &str)> {
+        self.destination
+            .as_ref()
+            .map(|(container, object)| (container.as_str(), object.as_str()))
+            .ok_or_else(|| CopyError("missing destination".to_string()))
+    }
+
+    /// Get the container that segments are uploaded to, creating it the first time.
+    async fn get_segments_container(&self) -> Result<&str> {
+        let (container, _) = self.get_destination()?;
+        let segments_container = self
+            .segments_container
+            .get_or_try_init(|| async {
+                let segments_container = format!("{}{}", container, SEGMENTS_CONTAINER_SUFFIX);
+                self.client.put_container(&segments_container).await?;
+                Ok::<_, crate::error::Error>(segments_container)
+            })
+            .await?;
+
+        Ok(segments_container)
+    }
+
+    /// The name of the segment of a part.
+    fn segment_name(&self, part_number: u64) -> Result<String> {
+        let prefix = self
+            .segment_prefix
+            .as_ref()
+            .ok_or_else(|| CopyError("missing destination".to_string()))?;
+        Ok(format!("{}/{:08}", prefix, part_number))
+    }
+
+    /// Get the source object, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_object(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let self_clone = self.clone();
+        CopyContent::ranged(multi_part, move |start, end| {
+            self_clone.clone().read(start, end)
+        })
+        .await
+    }
+
+    /// Read the source object from `start` up to, but not including, `end`.
+    async fn read(self, start: u64, end: Option<u64>) -> Result<ObjectReader> {
+        let (container, object) = self.get_source()?;
+        self.client
+            .get_object(container, object, start, end)
+            .await?
+            .ok_or_else(|| {
+                SwiftError(format!(
+                    "{} does not exist",
+                    Provider::format_swift(container, object)
+                ))
+            })
+    }
+
+    /// Upload the object in a single request, which is validated using the MD5 of the object if
+    /// it is known.
+    pub async fn put_object(&self, content: CopyContent, state: &CopyState) -> Result<CopyResult> {
+        let (container, object) = self.get_destination()?;
+        let e_tag = state
+            .content_md5()
+            .and_then(|content_md5| BASE64_STANDARD.decode(content_md5).ok())
+            .map(hex::encode);
+        let headers = ObjectHeaders {
+            e_tag,
+            metadata: state.metadata(),
+        };
+        self.client
+            .put_object(
+                container,
+                object,
+                Rest::body(content.data),
+                state.size(),
+                &headers,
+            )
+            .await?;
+
+        let mut result = CopyResult::new(None, None, state.size(), vec![])?;
+        result.content_md5_validated = headers.e_tag.is_some();
+        Ok(result)
+    }
+
+    /// Upload a part as a segment, or write the manifest that joins the uploaded segments if
+    /// this is the completion step.
+    pub async fn put_segment(
+        &self,
+        content: CopyContent,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let segments_container = self.get_segments_container().await?;
+
+        let Some(part_number) = multi_part.part_number else {
+            let (container, object) = self.get_destination()?;
+            let mut parts = multi_part.parts.ok_or_else(|| {
+                CopyError("missing parts for completing the manifest".to_string())
+            })?;
+            parts.sort_by_key(|part| part.part_number);
+            let segments = parts
+                .into_iter()
+                .map(|part| {
+                    Ok(ManifestSegment {
+                        path: format!(
+                            "/{}/{}",
+                            segments_container,
+                            self.segment_name(part.part_number)?
+                        ),
+                        etag: part.e_tag,
+                        size_bytes: part.size.ok_or_else(|| {
+                            CopyError(format!("missing size of part {}", part.part_number))
+                        })?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let headers = ObjectHeaders {
+                e_tag: None,
+                metadata: state.metadata(),
+            };
+            self.client
+                .put_manifest(container, object, &segments, &headers)
+                .await?;
+            return CopyResult::new(None, None, 0, vec![]);
+        };
+
+        let size = multi_part.bytes_transferred();
+        let e_tag = self
+            .client
+            .put_object(
+                segments_container,
+                &self.segment_name(part_number)?,
+                Rest::body(content.data),
+                size,
+                &ObjectHeaders::default(),
+            )
+            .await?;
+
+        let part = Part {
+            part_number,
+            size: Some(size),
+            e_tag,
+            ..Default::default()
+        };
+        CopyResult::new(Some(part), None, size, vec![])
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for Swift {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(CopyError(
+            "server-side copies are not supported for Swift".to_string(),
+        ))
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_object(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        if let Some(multi_part) = multi_part {
+            self.put_segment(data, multi_part, state).await
+        } else {
+            self.put_object(data, state).await
+        }
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        ProviderLimits::SWIFT
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let (container, object) = self.get_source()?;
+        let properties = self
+            .client
+            .head_object(container, object)
+            .await?
+            .ok_or_else(|| {
+                SwiftError(format!(
+                    "{} does not exist",
+                    Provider::format_swift(container, object)
+                ))
+            })?;
+
+        Ok(CopyState::new(
+            properties.size,
+            None,
+            Some(properties.metadata),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::SwiftOptions;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Serve requests with a `201 Created` response with an `ETag` of the request number,
+    /// recording the request line, headers and body of each request.
+    async fn serve(requests: Arc<Mutex<Vec<(String, String)>>>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let storage_url = format!("http://{}/v1/AUTH_project", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut head = String::new();
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await? == 0 {
+                                return anyhow::Ok(());
+                            }
+                            if let Some(length) =
+                                line.to_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = length.trim().parse()?;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            head.push_str(&line);
+                        }
+
+                        let mut body = vec![0; content_length];
+                        stream.read_exact(&mut body).await?;
+                        let n = {
+                            let mut requests = requests.lock().unwrap();
+                            requests.push((head, String::from_utf8_lossy(&body).to_string()));
+                            requests.len()
+                        };
+                        stream
+                            .write_all(
+                                format!(
+                                    "HTTP/1.1 201 Created\r\netag: \"{}\"\r\n\
+                                    content-length: 0\r\n\r\n",
+                                    n
+                                )
+                                .as_bytes(),
+                            )
+                            .await?;
+                    }
+                });
+            }
+        });
+
+        Ok(storage_url)
+    }
+
+    #[tokio::test]
+    async fn upload_segments() -> anyhow::Result<()> {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let storage_url = serve(requests.clone()).await?;
+        let swift = SwiftBuilder::default()
+            .with_client(SwiftClient::new(SwiftOptions::new(
+                Some(storage_url),
+                Some("token".to_string()),
+            )))
+            .with_destination("container", "dir/object")
+            .build();
+        let prefix = swift.segment_prefix.clone().unwrap();
+        let state = CopyState::new(
+            6,
+            None,
+            Some([("project".to_string(), "umccr".to_string())].into()),
+        );
+
+        let content = |data: &'static [u8]| {
+            CopyContent::builder(Box::new(data))
+                .with_reopen(move || async move { Ok(CopyContent::empty()) })
+                .build()
+        };
+        let mut parts = vec![];
+        for (part_number, start, data) in [(2, 3, &b"def"[..]), (1, 0, b"abc")] {
+            let options = MultiPartOptions {
+                part_number: Some(part_number),
+                start,
+                end: start + 3,
+                ..Default::default()
+            };
+            let result = swift.upload(content(data)?, Some(options), &state).await?;
+            assert_eq!(result.bytes_transferred, 3);
+            parts.extend(result.part);
+        }
+
+        let options = MultiPartOptions {
+            parts: Some(parts),
+            ..Default::default()
+        };
+        swift
+            .upload(CopyContent::empty(), Some(options), &state)
+            .await?;
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 4);
+        assert!(
+            requests[0]
+                .0
+                .starts_with("PUT /v1/AUTH_project/container_segments ")
+        );
+        assert!(requests[1].0.starts_with(&format!(
+            "PUT /v1/AUTH_project/container_segments/{}/00000002 ",
+            prefix
+        )));
+        assert!(requests[1].0.contains("x-auth-token: token"));
+        assert_eq!(requests[1].1, "def");
+        assert_eq!(requests[2].1, "abc");
+
+        let (head, body) = &requests[3];
+        assert!(
+            head.starts_with("PUT /v1/AUTH_project/container/dir/object?multipart-manifest=put ")
+        );
+        assert!(head.contains("x-object-meta-project: umccr"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(body)?,
+            serde_json::json!([
+                {
+                    "path": format!("/container_segments/{}/00000001", prefix),
+                    "etag": "3",
+                    "size_bytes": 3,
+                },
+                {
+                    "path": format!("/container_segments/{}/00000002", prefix),
+                    "etag": "2",
+                    "size_bytes": 3,
+                },
+            ])
+        );
+
+        Ok(())
+    }
+}
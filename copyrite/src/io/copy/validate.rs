@@ -0,0 +1,383 @@
+//! Validation of downloaded parts, which fetches a part again if it is truncated or does not
+//! match the checksum of the source part.
+//!
+
+use crate::checksum::Ctx;
+use crate::error::Error::CopyError;
+use crate::error::Result;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Reopen};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Validates downloaded parts while they are uploaded. Each part is compared against the size
+/// of its range and the checksum of the source part, if the source knows it, as it streams to
+/// the destination. A part that fails validation fails its upload, and is fetched again from
+/// the source and uploaded again up to `retries` times before the copy fails.
+#[derive(Debug, Clone, Copy)]
+pub struct PartValidation {
+    retries: usize,
+}
+
+impl PartValidation {
+    /// Create a new part validation with the number of times a part is fetched again.
+    pub fn new(retries: usize) -> Self {
+        Self { retries }
+    }
+
+    /// Upload a downloaded part with `upload`, validating it as it is read. The result records
+    /// the number of times the part was fetched again in `n_download_retries`.
+    pub async fn upload<F, Fut>(
+        &self,
+        source: &(dyn ObjectCopy + Send + Sync),
+        content: CopyContent,
+        options: &MultiPartOptions,
+        state: &CopyState,
+        upload: F,
+    ) -> Result<CopyResult>
+    where
+        F: Fn(CopyContent) -> Fut,
+        Fut: Future<Output = Result<CopyResult>>,
+    {
+        let validation = Arc::new(Validation {
+            part_number: options.part_number.unwrap_or_default(),
+            size: options.bytes_transferred(),
+            expected: source.part_checksum(options, state).await,
+            max_retries: u64::try_from(self.retries)?,
+            retries: Default::default(),
+            failure: Default::default(),
+        });
+
+        let CopyContent { data, reopen } = content;
+        let reopen = Arc::new(reopen);
+        let mut content = validation.content(data, reopen.clone())?;
+        loop {
+            let err = match upload(content).await {
+                Ok(mut result) => {
+                    result.n_download_retries = validation.retries.load(Ordering::SeqCst);
+                    return Ok(result);
+                }
+                Err(err) => err,
+            };
+
+            // Errors that are not caused by validation are handled by the caller.
+            if !validation.has_failed() {
+                return Err(err);
+            }
+            content = validation.clone().reopen(reopen.clone()).await?;
+        }
+    }
+}
+
+/// The validation of one part, shared by every read of the part.
+#[derive(Debug)]
+struct Validation {
+    part_number: u64,
+    size: u64,
+    expected: Option<(Ctx, String)>,
+    max_retries: u64,
+    retries: AtomicU64,
+    failure: Mutex<Option<String>>,
+}
+
+impl Validation {
+    /// Create content that validates the data as it is read. Reopening the content fetches the
+    /// part from the source again, which counts as a retry if the previous read failed.
+    fn content(
+        self: &Arc<Self>,
+        data: Box<dyn AsyncRead + Sync + Send + Unpin>,
+        reopen: Arc<Reopen>,
+    ) -> Result<CopyContent> {
+        let validation = self.clone();
+        CopyContent::builder(Box::new(ValidatingReader {
+            inner: data,
+            validation: self.clone(),
+            ctx: self.expected.as_ref().map(|(ctx, _)| ctx.clone()),
+            read: 0,
+        }))
+        .with_reopen(move || validation.clone().reopen(reopen.clone()))
+        .build()
+    }
+
+    /// Fetch the part from the source again. Destinations can also reopen content to retry
+    /// errors that are not caused by validation, which are not counted as retries.
+    async fn reopen(self: Arc<Self>, reopen: Arc<Reopen>) -> Result<CopyContent> {
+        let failure = self
+            .failure
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(failure) = failure {
+            let retries = self.retries.load(Ordering::SeqCst);
+            if retries >= self.max_retries {
+                return Err(CopyError(format!(
+                    "part {} failed validation after {} retries: {}",
+                    self.part_number, retries, failure
+                )));
+            }
+            self.retries.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let CopyContent { data, .. } = reopen().await?;
+        self.content(data, reopen)
+    }
+
+    /// Whether the last read of the part failed validation.
+    fn has_failed(&self) -> bool {
+        self.failure
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .is_some()
+    }
+
+    /// Record a validation failure and convert it to an error for the reader.
+    fn fail(&self, failure: String) -> io::Error {
+        let err = io::Error::new(io::ErrorKind::InvalidData, failure.clone());
+        *self.failure.lock().unwrap_or_else(|err| err.into_inner()) = Some(failure);
+        err
+    }
+
+    /// Check the size and checksum of a part once it has been read.
+    fn check(&self, read: u64, ctx: Option<Ctx>) -> io::Result<()> {
+        if read != self.size {
+            return Err(self.fail(format!("read {} bytes but expected {}", read, self.size)));
+        }
+
+        if let (Some(mut ctx), Some((_, expected))) = (ctx, &self.expected) {
+            let checksum = BASE64_STANDARD.encode(ctx.finalize().map_err(io::Error::other)?);
+            if &checksum != expected {
+                return Err(self.fail(format!(
+                    "{} checksum `{}` does not match the source part checksum `{}`",
+                    ctx, checksum, expected
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes a part as it is read, and fails at the end of the part if it does not validate.
+struct ValidatingReader {
+    inner: Box<dyn AsyncRead + Sync + Send + Unpin>,
+    validation: Arc<Validation>,
+    ctx: Option<Ctx>,
+    read: u64,
+}
+
+impl AsyncRead for ValidatingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let remaining = buf.remaining();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+
+        let this = &mut *self;
+        let data = &buf.filled()[filled..];
+        this.read += u64::try_from(data.len()).map_err(io::Error::other)?;
+        if this.read > this.validation.size {
+            return Poll::Ready(Err(this.validation.fail(format!(
+                "read more than the {} bytes expected",
+                this.validation.size
+            ))));
+        }
+        if let Some(ctx) = &mut this.ctx
+            && !data.is_empty()
+        {
+            ctx.update(Arc::from(data)).map_err(io::Error::other)?;
+        }
+
+        // An empty read into a buffer with space is the end of the part.
+        if data.is_empty() && remaining > 0 {
+            this.validation.check(this.read, this.ctx.take())?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::copy::limits::ProviderLimits;
+    use anyhow::Result;
+    use std::io::Cursor;
+    use std::str::FromStr;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::io::AsyncReadExt;
+
+    /// A source which returns truncated data for the first `failures` downloads.
+    #[derive(Clone)]
+    struct FlakySource {
+        data: &'static [u8],
+        failures: usize,
+        downloads: Arc<AtomicUsize>,
+        checksum: Arc<Mutex<Option<(Ctx, String)>>>,
+    }
+
+    impl FlakySource {
+        fn new(data: &'static [u8], failures: usize) -> Self {
+            Self {
+                data,
+                failures,
+                downloads: Default::default(),
+                checksum: Default::default(),
+            }
+        }
+
+        fn content(&self) -> crate::error::Result<CopyContent> {
+            let n = self.downloads.fetch_add(1, Ordering::SeqCst);
+            let data = if n < self.failures {
+                &self.data[..self.data.len() / 2]
+            } else {
+                self.data
+            };
+
+            let self_clone = self.clone();
+            CopyContent::builder(Box::new(Cursor::new(data)))
+                .with_reopen(move || {
+                    let self_clone = self_clone.clone();
+                    async move { self_clone.content() }
+                })
+                .build()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectCopy for FlakySource {
+        async fn copy(
+            &self,
+            _multi_part: Option<MultiPartOptions>,
+            _state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            unimplemented!()
+        }
+
+        async fn download(
+            &self,
+            _multi_part: Option<MultiPartOptions>,
+        ) -> crate::error::Result<CopyContent> {
+            self.content()
+        }
+
+        async fn upload(
+            &self,
+            _data: CopyContent,
+            _multi_part: Option<MultiPartOptions>,
+            _state: &CopyState,
+        ) -> crate::error::Result<CopyResult> {
+            unimplemented!()
+        }
+
+        async fn part_checksum(
+            &self,
+            _multi_part: &MultiPartOptions,
+            _state: &CopyState,
+        ) -> Option<(Ctx, String)> {
+            self.checksum.lock().unwrap().clone()
+        }
+
+        fn limits(&self) -> ProviderLimits {
+            ProviderLimits::UNLIMITED
+        }
+
+        async fn initialize_state(&self) -> crate::error::Result<CopyState> {
+            unimplemented!()
+        }
+    }
+
+    fn options() -> MultiPartOptions {
+        MultiPartOptions {
+            part_number: Some(1),
+            start: 0,
+            end: 8,
+            ..Default::default()
+        }
+    }
+
+    /// Upload a part with validation, returning the uploaded data and the number of times the
+    /// part was fetched again.
+    async fn upload(
+        validation: PartValidation,
+        source: &FlakySource,
+        state: &CopyState,
+    ) -> crate::error::Result<(Vec<u8>, u64)> {
+        let uploaded = Mutex::new(vec![]);
+        let result = validation
+            .upload(
+                source,
+                source.content()?,
+                &options(),
+                state,
+                |content| async {
+                    let mut buf = vec![];
+                    let mut data = content.data;
+                    data.read_to_end(&mut buf).await?;
+                    *uploaded.lock().unwrap() = buf;
+                    CopyResult::new(None, None, 8, vec![])
+                },
+            )
+            .await?;
+
+        Ok((uploaded.into_inner().unwrap(), result.n_download_retries))
+    }
+
+    #[tokio::test]
+    async fn validate_refetches_truncated_part() -> Result<()> {
+        let source = FlakySource::new(b"abcdefgh", 2);
+        let state = CopyState::new(8, None, None);
+
+        let (uploaded, retries) = upload(PartValidation::new(2), &source, &state).await?;
+        assert_eq!(retries, 2);
+        assert_eq!(uploaded, b"abcdefgh");
+        assert_eq!(source.downloads.load(Ordering::SeqCst), 3);
+
+        let source = FlakySource::new(b"abcdefgh", 3);
+        let err = upload(PartValidation::new(2), &source, &state)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("failed validation after 2 retries")
+        );
+        assert!(err.to_string().contains("read 4 bytes but expected 8"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validate_checks_part_checksum() -> Result<()> {
+        let source = FlakySource::new(b"abcdefgh", 0);
+        let state = CopyState::new(8, None, None);
+
+        let mut ctx = Ctx::from_str("sha256")?;
+        ctx.update(Arc::from(b"abcdefgh".as_slice()))?;
+        let checksum = BASE64_STANDARD.encode(ctx.finalize()?);
+        *source.checksum.lock().unwrap() = Some((Ctx::from_str("sha256")?, checksum));
+
+        let (uploaded, retries) = upload(PartValidation::new(0), &source, &state).await?;
+        assert_eq!(retries, 0);
+        assert_eq!(uploaded, b"abcdefgh");
+
+        *source.checksum.lock().unwrap() = Some((Ctx::from_str("sha256")?, "mismatch".to_string()));
+        let err = upload(PartValidation::new(1), &source, &state)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not match the source part checksum")
+        );
+        assert_eq!(source.downloads.load(Ordering::SeqCst), 3);
+
+        Ok(())
+    }
+}
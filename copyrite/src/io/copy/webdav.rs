@@ -0,0 +1,385 @@
+//! WebDAV copy logic. Files are uploaded in a single `PUT` request. Files under the files
+//! collection of a Nextcloud or ownCloud user can also be uploaded in chunks, where each part of
+//! a multipart copy is a chunk in an upload collection that is assembled into the file once all
+//! parts are uploaded.
+//!
+
+use crate::error::Error::{CopyError, WebDavError};
+use crate::error::Result;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::{CopyContent, CopyResult, CopyState, MultiPartOptions, ObjectCopy, Part};
+use crate::io::rest::Rest;
+use crate::io::webdav::{WebDavClient, WebDavReader, http_url, uploads_url};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::OnceCell;
+
+/// Build a WebDAV copy object.
+#[derive(Debug, Default)]
+pub struct WebDavBuilder {
+    client: Option<WebDavClient>,
+    source: Option<String>,
+    destination: Option<String>,
+}
+
+impl WebDavBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: WebDavClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the source url.
+    pub fn with_source(mut self, url: &str) -> Self {
+        self.source = Some(url.to_string());
+        self
+    }
+
+    /// Set the destination url.
+    pub fn with_destination(mut self, url: &str) -> Self {
+        self.destination = Some(url.to_string());
+        self
+    }
+
+    /// Build the WebDAV copy object. Chunks of the destination are uploaded to an upload
+    /// collection that is unique to this copy, so that concurrent copies do not share chunks.
+    pub fn build(self) -> WebDav {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let upload_collection = self
+            .destination
+            .as_deref()
+            .and_then(uploads_url)
+            .map(|uploads| format!("{}/copyrite-{}", uploads, timestamp));
+
+        WebDav {
+            client: self.client.unwrap_or_default(),
+            source: self.source,
+            destination: self.destination,
+            upload_collection,
+            created: Default::default(),
+        }
+    }
+}
+
+/// A WebDAV file copy. Whether the upload collection was created is shared by all clones, so
+/// that it is only created once.
+#[derive(Debug, Clone)]
+pub struct WebDav {
+    client: WebDavClient,
+    source: Option<String>,
+    destination: Option<String>,
+    upload_collection: Option<String>,
+    created: Arc<OnceCell<()>>,
+}
+
+impl WebDav {
+    fn get_source(&self) -> Result<&str> {
+        self.source
+            .as_deref()
+            .ok_or_else(|| CopyError("missing source".to_string()))
+    }
+
+    fn get_destination(&self) -> Result<&str> {
+        self.destination
+            .as_deref()
+            .ok_or_else(|| CopyError("missing destination".to_string()))
+    }
+
+    /// Get the collection that chunks are uploaded to, creating it the first time.
+    async fn get_upload_collection(&self) -> Result<&str> {
+        let destination = self.get_destination()?;
+        let upload_collection = self.upload_collection.as_deref().ok_or_else(|| {
+            WebDavError(format!(
+                "{} is not in the files collection of a Nextcloud or ownCloud user, so it \
+                cannot be uploaded in chunks",
+                destination
+            ))
+        })?;
+
+        self.created
+            .get_or_try_init(|| self.client.mkcol(upload_collection, Some(destination)))
+            .await?;
+
+        Ok(upload_collection)
+    }
+
+    /// Get the source file, or the range of it for a part. The returned content carries a
+    /// reopen function that reads the same range again.
+    pub async fn get_file(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        let self_clone = self.clone();
+        CopyContent::ranged(multi_part, move |start, end| {
+            self_clone.clone().read(start, end)
+        })
+        .await
+    }
+
+    /// Read the source file from `start` up to, but not including, `end`.
+    async fn read(self, start: u64, end: Option<u64>) -> Result<WebDavReader> {
+        let url = self.get_source()?;
+        self.client
+            .get(url, start, end)
+            .await?
+            .ok_or_else(|| WebDavError(format!("{} does not exist", url)))
+    }
+
+    /// Upload the file in a single request. The MD5 of the file is sent as an `OC-Checksum`
+    /// if it is known, which Nextcloud and ownCloud store with the file.
+    pub async fn put_file(&self, content: CopyContent, state: &CopyState) -> Result<CopyResult> {
+        let url = self.get_destination()?;
+        let headers = state
+            .content_md5()
+            .and_then(|content_md5| BASE64_STANDARD.decode(content_md5).ok())
+            .map(|md5| vec![("oc-checksum", format!("MD5:{}", hex::encode(md5)))])
+            .unwrap_or_default();
+        self.client
+            .put(url, Rest::body(content.data), state.size(), &headers)
+            .await?;
+
+        CopyResult::new(None, None, state.size(), vec![])
+    }
+
+    /// Upload a part as a chunk, or assemble the uploaded chunks into the destination if this
+    /// is the completion step.
+    pub async fn put_chunk(
+        &self,
+        content: CopyContent,
+        multi_part: MultiPartOptions,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        let upload_collection = self.get_upload_collection().await?;
+        let destination = self.get_destination()?;
+
+        let Some(part_number) = multi_part.part_number else {
+            self.client
+                .assemble(upload_collection, destination, state.size())
+                .await?;
+            return CopyResult::new(None, None, 0, vec![]);
+        };
+
+        let size = multi_part.bytes_transferred();
+        self.client
+            .put(
+                &format!("{}/{:05}", upload_collection, part_number),
+                Rest::body(content.data),
+                size,
+                &[("destination", http_url(destination)?)],
+            )
+            .await?;
+
+        let part = Part {
+            part_number,
+            size: Some(size),
+            ..Default::default()
+        };
+        CopyResult::new(Some(part), None, size, vec![])
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectCopy for WebDav {
+    async fn copy(
+        &self,
+        _multi_part: Option<MultiPartOptions>,
+        _state: &CopyState,
+    ) -> Result<CopyResult> {
+        Err(CopyError(
+            "server-side copies are not supported for WebDAV".to_string(),
+        ))
+    }
+
+    async fn download(&self, multi_part: Option<MultiPartOptions>) -> Result<CopyContent> {
+        self.get_file(multi_part).await
+    }
+
+    async fn upload(
+        &self,
+        data: CopyContent,
+        multi_part: Option<MultiPartOptions>,
+        state: &CopyState,
+    ) -> Result<CopyResult> {
+        if let Some(multi_part) = multi_part {
+            self.put_chunk(data, multi_part, state).await
+        } else {
+            self.put_file(data, state).await
+        }
+    }
+
+    fn limits(&self) -> ProviderLimits {
+        if self.upload_collection.is_some() {
+            ProviderLimits::WEBDAV_CHUNKED
+        } else {
+            ProviderLimits::WEBDAV
+        }
+    }
+
+    async fn initialize_state(&self) -> Result<CopyState> {
+        let url = self.get_source()?;
+        let properties = self
+            .client
+            .propfind(url)
+            .await?
+            .ok_or_else(|| WebDavError(format!("{} does not exist", url)))?;
+
+        Ok(CopyState::new(properties.size(url)?, None, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::WebDavOptions;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Serve requests with a `201 Created` response, recording the request line, headers and
+    /// body of each request.
+    async fn serve(requests: Arc<Mutex<Vec<(String, String)>>>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let server = format!("dav://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut head = String::new();
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await? == 0 {
+                                return anyhow::Ok(());
+                            }
+                            if let Some(length) =
+                                line.to_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = length.trim().parse()?;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            head.push_str(&line);
+                        }
+
+                        let mut body = vec![0; content_length];
+                        stream.read_exact(&mut body).await?;
+                        requests
+                            .lock()
+                            .unwrap()
+                            .push((head, String::from_utf8_lossy(&body).to_string()));
+                        stream
+                            .write_all(b"HTTP/1.1 201 Created\r\ncontent-length: 0\r\n\r\n")
+                            .await?;
+                    }
+                });
+            }
+        });
+
+        Ok(server)
+    }
+
+    fn content(data: &'static [u8]) -> Result<CopyContent> {
+        CopyContent::builder(Box::new(data))
+            .with_reopen(move || async move { Ok(CopyContent::empty()) })
+            .build()
+    }
+
+    #[tokio::test]
+    async fn upload_chunks() -> anyhow::Result<()> {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let server = serve(requests.clone()).await?;
+        let destination = format!("{}/remote.php/dav/files/user/dir/file", server);
+        let webdav = WebDavBuilder::default()
+            .with_client(WebDavClient::new(WebDavOptions::new(
+                Some("user".to_string()),
+                Some("password".to_string()),
+            )))
+            .with_destination(&destination)
+            .build();
+        assert_eq!(webdav.limits(), ProviderLimits::WEBDAV_CHUNKED);
+        let collection = webdav
+            .upload_collection
+            .clone()
+            .unwrap()
+            .replace(&server, "");
+        let state = CopyState::new(6, None, None);
+
+        for (part_number, start, data) in [(2, 3, &b"def"[..]), (1, 0, b"abc")] {
+            let options = MultiPartOptions {
+                part_number: Some(part_number),
+                start,
+                end: start + 3,
+                ..Default::default()
+            };
+            let result = webdav.upload(content(data)?, Some(options), &state).await?;
+            assert_eq!(result.bytes_transferred, 3);
+        }
+        webdav
+            .upload(
+                CopyContent::empty(),
+                Some(MultiPartOptions::default()),
+                &state,
+            )
+            .await?;
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[0].0.starts_with(&format!("MKCOL {} ", collection)));
+        assert!(
+            requests[0]
+                .0
+                .contains("/remote.php/dav/files/user/dir/file")
+        );
+        assert!(
+            requests[1]
+                .0
+                .starts_with(&format!("PUT {}/00002 ", collection))
+        );
+        assert_eq!(requests[1].1, "def");
+        assert_eq!(requests[2].1, "abc");
+
+        let head = requests[3].0.to_lowercase();
+        assert!(head.starts_with(&format!("move {}/.file ", collection.to_lowercase())));
+        assert!(head.contains("oc-total-length: 6"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file() -> anyhow::Result<()> {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let server = serve(requests.clone()).await?;
+        let webdav = WebDavBuilder::default()
+            .with_destination(&format!("{}/webdav/file", server))
+            .build();
+        assert_eq!(webdav.limits(), ProviderLimits::WEBDAV);
+
+        let mut state = CopyState::new(3, None, None);
+        state.set_content_md5(Some(BASE64_STANDARD.encode([0x90, 0x01])));
+        webdav.upload(content(b"abc")?, None, &state).await?;
+        assert!(
+            webdav
+                .upload(content(b"abc")?, Some(MultiPartOptions::default()), &state)
+                .await
+                .is_err()
+        );
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].0.starts_with("PUT /webdav/file "));
+        assert!(
+            requests[0]
+                .0
+                .to_lowercase()
+                .contains("oc-checksum: md5:9001")
+        );
+        assert_eq!(requests[0].1, "abc");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+//! Support for GA4GH Data Repository Service (DRS) URIs, e.g. `drs://drs.example.org/<id>`, as
+//! used by genomics data repositories. DRS URIs are resolved to the S3 or HTTPS location of the
+//! object using the `/ga4gh/drs/v1/objects/<id>` endpoint of the host, and the checksums in the
+//! DRS object metadata are used as the sums of the resolved location.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::file::{Checksum, SumsFile};
+use crate::error::Error::DrsError;
+use crate::error::Result;
+use crate::io::copy::{ObjectCopy, ObjectCopyBuilder};
+use crate::io::registry::{CustomSums, ProviderFactory};
+use crate::io::rest::Rest;
+use crate::io::sums::external::{ExternalSums, SumsSource};
+use crate::io::sums::{ObjectSums, ObjectSumsBuilder};
+use crate::io::{Provider, S3Client};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The URL scheme of DRS locations.
+pub const DRS_SCHEME: &str = "drs";
+
+/// The access method types that can be used to read a DRS object, in order of preference.
+const ACCESS_METHOD_TYPES: [&str; 2] = ["s3", "https"];
+
+/// Sends requests to DRS servers, converting failures into DRS errors.
+const REST: Rest = Rest::new(DrsError);
+
+/// Whether the location is a DRS URI.
+pub fn is_drs_url(location: &str) -> bool {
+    location
+        .strip_prefix(DRS_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// A DRS object returned by the objects endpoint.
+#[derive(Debug, Deserialize)]
+struct DrsObjectResponse {
+    size: Option<u64>,
+    #[serde(default)]
+    checksums: Vec<DrsChecksum>,
+    #[serde(default)]
+    access_methods: Vec<AccessMethod>,
+}
+
+/// A checksum of a DRS object, e.g. `{"type": "sha-256", "checksum": "..."}`.
+#[derive(Debug, Deserialize)]
+struct DrsChecksum {
+    checksum: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// A way of reading a DRS object. The URL is either given directly, or fetched from the access
+/// endpoint using the access id.
+#[derive(Debug, Deserialize)]
+struct AccessMethod {
+    #[serde(rename = "type")]
+    kind: String,
+    access_url: Option<AccessUrl>,
+    access_id: Option<String>,
+}
+
+/// The URL of a DRS object and any headers that are required to read it.
+#[derive(Debug, Deserialize)]
+struct AccessUrl {
+    url: String,
+    #[serde(default)]
+    headers: Vec<String>,
+}
+
+/// A resolved DRS object.
+#[derive(Debug, Clone)]
+pub struct DrsObject {
+    url: String,
+    sums: SumsFile,
+}
+
+impl DrsObject {
+    /// The S3 or HTTPS location of the object.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The sums from the checksums in the DRS object metadata.
+    pub fn sums(&self) -> &SumsFile {
+        &self.sums
+    }
+}
+
+/// A client for resolving DRS URIs.
+#[derive(Debug, Clone)]
+pub struct DrsClient {
+    client: Client,
+    scheme: String,
+    bearer_token: Option<String>,
+}
+
+impl Default for DrsClient {
+    fn default() -> Self {
+        Self {
+            client: Client::new(),
+            scheme: "https".to_string(),
+            bearer_token: None,
+        }
+    }
+}
+
+impl DrsClient {
+    /// Create a new client which authorizes requests with the bearer token, if set.
+    pub fn new(bearer_token: Option<String>) -> Self {
+        Self {
+            bearer_token,
+            ..Default::default()
+        }
+    }
+
+    /// Set the URL scheme used to connect to DRS servers, which is `https` by default.
+    pub fn with_scheme(mut self, scheme: String) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Split a DRS URI into its host and object id. Compact identifier URIs, e.g.
+    /// `drs://prefix:accession`, are not supported because they require a registry to resolve.
+    fn parse(uri: &str) -> Result<(&str, &str)> {
+        let parsed = uri
+            .strip_prefix(DRS_SCHEME)
+            .and_then(|uri| uri.strip_prefix("://"))
+            .and_then(|uri| uri.split_once('/'))
+            .filter(|(host, id)| !host.is_empty() && !id.is_empty());
+
+        parsed.ok_or_else(|| {
+            DrsError(format!(
+                "{} is not a DRS URI of the form `drs://<host>/<id>`",
+                uri
+            ))
+        })
+    }
+
+    /// Get a JSON document from the DRS server.
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let mut request = self.client.get(url);
+        if let Some(bearer_token) = &self.bearer_token {
+            request = request.bearer_auth(bearer_token);
+        }
+
+        let operation = format!("request to {}", url);
+        let response = REST.send(request, &operation).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(DrsError(format!("{} does not exist", url)));
+        }
+        let response = REST.check(response, &operation)?;
+
+        response
+            .json()
+            .await
+            .map_err(|err| DrsError(format!("invalid response from {}: {}", url, err)))
+    }
+
+    /// Resolve a DRS URI to the location of its object and the sums from its metadata.
+    pub async fn resolve(&self, uri: &str) -> Result<DrsObject> {
+        let (host, id) = Self::parse(uri)?;
+        let objects_url = format!("{}://{}/ga4gh/drs/v1/objects/{}", self.scheme, host, id);
+        let object: DrsObjectResponse = self.get_json(&objects_url).await?;
+
+        let mut url = None;
+        for kind in ACCESS_METHOD_TYPES {
+            for method in object.access_methods.iter().filter(|m| m.kind == kind) {
+                let access_url = match (&method.access_url, &method.access_id) {
+                    (Some(access_url), _) => access_url,
+                    (None, Some(access_id)) => {
+                        &self
+                            .get_json(&format!("{}/access/{}", objects_url, access_id))
+                            .await?
+                    }
+                    (None, None) => continue,
+                };
+
+                // Headers cannot be passed on to the copy, so these methods cannot be used.
+                if access_url.headers.is_empty() {
+                    url = Some(access_url.url.to_string());
+                    break;
+                }
+            }
+
+            if url.is_some() {
+                break;
+            }
+        }
+
+        let url = url.ok_or_else(|| {
+            DrsError(format!(
+                "{} does not have an `s3` or `https` access method without headers",
+                uri
+            ))
+        })?;
+        Ok(DrsObject {
+            url: Provider::try_from(url.as_str())?.format(),
+            sums: Self::sums(object.size, &object.checksums),
+        })
+    }
+
+    /// Convert the checksums of a DRS object into sums. Checksum types that copyrite does not
+    /// support, such as `etag`, are ignored.
+    fn sums(size: Option<u64>, checksums: &[DrsChecksum]) -> SumsFile {
+        let checksums = checksums
+            .iter()
+            .filter_map(|checksum| {
+                let name = match checksum.kind.to_lowercase().as_str() {
+                    "md5" => "md5",
+                    "sha1" | "sha-1" => "sha1",
+                    "sha256" | "sha-256" => "sha256",
+                    "sha512" | "sha-512" => "sha512",
+                    "crc32c" => "crc32c",
+                    _ => return None,
+                };
+
+                Some((
+                    Ctx::from_str(name).ok()?,
+                    Checksum::new(checksum.checksum.to_lowercase()),
+                ))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        SumsFile::new(size, checksums)
+    }
+}
+
+/// A sums source containing the sums of resolved DRS objects, keyed by their resolved location.
+/// Sums are stored in the inner source, if there is one, and its sums are merged with the DRS
+/// sums.
+#[derive(Debug, Clone, Default)]
+pub struct DrsSums {
+    sums: HashMap<String, SumsFile>,
+    source: Option<Arc<dyn SumsSource + Send + Sync>>,
+}
+
+impl DrsSums {
+    /// Add the sums of a resolved DRS object.
+    pub fn insert(&mut self, object: &DrsObject) {
+        self.sums
+            .insert(object.url().to_string(), object.sums().clone());
+    }
+
+    /// Whether there are no resolved objects.
+    pub fn is_empty(&self) -> bool {
+        self.sums.is_empty()
+    }
+
+    /// Set the inner source which sums are also read from and stored in.
+    pub fn with_source(mut self, source: Option<Arc<dyn SumsSource + Send + Sync>>) -> Self {
+        self.source = source;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl SumsSource for DrsSums {
+    async fn get(&self, location: &str) -> Result<Option<SumsFile>> {
+        let drs = self.sums.get(location).cloned();
+        let inner = match &self.source {
+            Some(source) => source.get(location).await?,
+            None => None,
+        };
+
+        match (inner, drs) {
+            (Some(inner), Some(drs)) => Ok(Some(inner.merge(drs)?)),
+            (inner, drs) => Ok(inner.or(drs)),
+        }
+    }
+
+    async fn put(&self, location: &str, sums_file: &SumsFile) -> Result<()> {
+        match &self.source {
+            Some(source) => source.put(location, sums_file).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builds the copy and sums objects of DRS URIs. This is registered in the provider registry
+/// for the `drs` scheme. The URI is resolved into its access URL, which is read using the
+/// provider of that URL, and the checksums in the DRS object metadata are added to its sums. DRS
+/// URIs are read-only, so they cannot be a copy destination.
+#[derive(Debug, Clone, Default)]
+pub struct DrsFactory {
+    client: DrsClient,
+}
+
+impl DrsFactory {
+    /// Create a factory which resolves DRS URIs with the client.
+    pub fn new(client: DrsClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderFactory for DrsFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        self.copy_with_client(None, source, destination).await
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        self.sums_with_client(None, url).await
+    }
+
+    async fn copy_with_client(
+        &self,
+        client: Option<&S3Client>,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        if let Some(destination) = destination {
+            return Err(DrsError(format!(
+                "{} is a DRS uri, which cannot be used as a destination",
+                destination
+            )));
+        }
+
+        let source = match source {
+            Some(source) => Some(Provider::try_from(
+                self.client.resolve(source).await?.url(),
+            )?),
+            None => None,
+        };
+        ObjectCopyBuilder::default()
+            .set_client(client.cloned())
+            .set_source(source)
+            .build()
+            .await
+    }
+
+    async fn sums_with_client(
+        &self,
+        client: Option<&S3Client>,
+        url: &str,
+    ) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let object = self.client.resolve(url).await?;
+        let mut drs_sums =
+            DrsSums::default().with_source(client.and_then(|client| client.sums_source().cloned()));
+        drs_sums.insert(&object);
+        let drs_sums: Arc<dyn SumsSource + Send + Sync> = Arc::new(drs_sums);
+
+        // The DRS sums replace the sums source of the client, as they also read from it.
+        let url = object.url().to_string();
+        match client {
+            Some(client) => {
+                ObjectSumsBuilder::default()
+                    .set_client(Some(client.clone().with_sums_source(drs_sums)))
+                    .build(url)
+                    .await
+            }
+            None => {
+                let sums = ObjectSumsBuilder::default().build(url).await?;
+                Ok(Box::new(ExternalSums::new(CustomSums::new(sums), drs_sums)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve the JSON responses in order, one for each request, returning the host and the
+    /// request lines that were received.
+    async fn serve(
+        responses: Vec<&'static str>,
+    ) -> Result<(String, tokio::task::JoinHandle<Result<Vec<String>>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let host = listener.local_addr()?.to_string();
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            for body in responses {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = [0; 4096];
+                let n = stream.read(&mut request).await?;
+                requests.push(
+                    String::from_utf8_lossy(&request[..n])
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                            content-length: {}\r\nconnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                        .as_bytes(),
+                    )
+                    .await?;
+            }
+
+            anyhow::Ok(requests)
+        });
+
+        Ok((host, server))
+    }
+
+    fn client() -> DrsClient {
+        DrsClient::default().with_scheme("http".to_string())
+    }
+
+    #[test]
+    fn drs_urls() {
+        assert!(is_drs_url("drs://drs.example.org/abc"));
+        assert!(!is_drs_url("drsdrs.example.org/abc"));
+        assert!(!is_drs_url("s3://bucket/key"));
+
+        assert_eq!(
+            DrsClient::parse("drs://drs.example.org/abc").unwrap(),
+            ("drs.example.org", "abc")
+        );
+        assert!(DrsClient::parse("drs://prefix:accession").is_err());
+        assert!(DrsClient::parse("drs://drs.example.org/").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_access_url() -> Result<()> {
+        let (host, server) = serve(vec![
+            r#"{
+                "id": "abc",
+                "size": 5,
+                "checksums": [
+                    {"type": "md5", "checksum": "AB56B4D92B40713ACC5AF89985D4B786"},
+                    {"type": "sha-256", "checksum": "36bbe50ed96841d10443bcb670d6554f0a34b761be67ec9c4a8ad2c0c44ca42c"},
+                    {"type": "etag", "checksum": "ignored"}
+                ],
+                "access_methods": [
+                    {"type": "gs", "access_url": {"url": "gs://bucket/abc"}},
+                    {"type": "https", "access_url": {"url": "https://example.org/abc"}},
+                    {"type": "s3", "access_url": {"url": "s3://bucket/abc"}}
+                ]
+            }"#,
+        ])
+        .await?;
+
+        let object = client().resolve(&format!("drs://{}/abc", host)).await?;
+        assert_eq!(object.url(), "s3://bucket/abc");
+        assert_eq!(object.sums().size, Some(5));
+        assert_eq!(
+            object.sums().checksums,
+            BTreeMap::from_iter([
+                (
+                    Ctx::from_str("md5")?,
+                    Checksum::new("ab56b4d92b40713acc5af89985d4b786".to_string())
+                ),
+                (
+                    Ctx::from_str("sha256")?,
+                    Checksum::new(
+                        "36bbe50ed96841d10443bcb670d6554f0a34b761be67ec9c4a8ad2c0c44ca42c"
+                            .to_string()
+                    )
+                ),
+            ])
+        );
+        assert_eq!(
+            server.await??,
+            vec!["GET /ga4gh/drs/v1/objects/abc HTTP/1.1"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_access_id() -> Result<()> {
+        let (host, server) = serve(vec![
+            r#"{
+                "id": "abc",
+                "access_methods": [
+                    {"type": "https", "access_url": {"url": "https://example.org/abc", "headers": ["Authorization: Basic x"]}},
+                    {"type": "https", "access_id": "signed"}
+                ]
+            }"#,
+            r#"{"url": "https://example.org/abc?signature=x"}"#,
+        ])
+        .await?;
+
+        let object = client().resolve(&format!("drs://{}/abc", host)).await?;
+        assert_eq!(object.url(), "https://example.org/abc?signature=x");
+        assert!(object.sums().checksums.is_empty());
+        assert_eq!(
+            server.await??,
+            vec![
+                "GET /ga4gh/drs/v1/objects/abc HTTP/1.1",
+                "GET /ga4gh/drs/v1/objects/abc/access/signed HTTP/1.1"
+            ]
+        );
+
+        let (host, _) = serve(vec![r#"{"id": "abc", "access_methods": []}"#]).await?;
+        assert!(
+            client()
+                .resolve(&format!("drs://{}/abc", host))
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drs_sums() -> Result<()> {
+        let object = DrsObject {
+            url: "s3://bucket/abc".to_string(),
+            sums: DrsClient::sums(
+                Some(5),
+                &[DrsChecksum {
+                    checksum: "ab56b4d92b40713acc5af89985d4b786".to_string(),
+                    kind: "md5".to_string(),
+                }],
+            ),
+        };
+        let mut sums = DrsSums::default();
+        sums.insert(&object);
+
+        assert_eq!(
+            sums.get("s3://bucket/abc").await?,
+            Some(object.sums().clone())
+        );
+        assert_eq!(sums.get("s3://bucket/other").await?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Inject simulated faults into S3 responses to exercise retry and resume logic.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use async_stream::stream;
+use aws_sdk_s3::config::interceptors::BeforeDeserializationInterceptorContextMut;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_runtime_api::http::StatusCode;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http_body::Frame;
+use http_body_util::{BodyStream, StreamBody};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The body of a simulated internal error, in the format returned by S3.
+const INTERNAL_ERROR_BODY: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>InternalError</Code><Message>We encountered an internal error. Please try again. (simulated fault)</Message></Error>";
+
+/// The maximum delay of a simulated slow response.
+const MAX_SLOW_RESPONSE: Duration = Duration::from_secs(5);
+
+/// A fault injected into a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Replace the response with a 500 internal error.
+    InternalError,
+    /// Delay the response body.
+    SlowResponse(Duration),
+    /// End the response body with an error after a number of bytes.
+    TruncatedStream(u64),
+}
+
+/// The probability of injecting a fault into each S3 response. This is only intended for
+/// testing how copies behave when requests fail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultInjection {
+    probability: f64,
+}
+
+impl FaultInjection {
+    /// Create fault injection with a probability between 0 and 1.
+    pub fn new(probability: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(ParseError(format!(
+                "fault injection probability must be between 0 and 1, not {}",
+                probability
+            )));
+        }
+
+        Ok(Self { probability })
+    }
+
+    /// The probability of injecting a fault into each response.
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Randomly choose a fault for a response, if any. Only `GetObject` responses are
+    /// truncated because their body is streamed to the caller.
+    pub fn choose(&self, operation: Option<&str>, content_length: Option<u64>) -> Option<Fault> {
+        if !rand::random_bool(self.probability) {
+            return None;
+        }
+
+        match rand::random_range(0..3) {
+            0 => Some(Fault::SlowResponse(Duration::from_millis(
+                rand::random_range(
+                    0..=u64::try_from(MAX_SLOW_RESPONSE.as_millis()).unwrap_or_default(),
+                ),
+            ))),
+            1 if operation == Some("GetObject") => Some(Fault::TruncatedStream(
+                rand::random_range(0..content_length.unwrap_or_default().max(1)),
+            )),
+            _ => Some(Fault::InternalError),
+        }
+    }
+
+    /// Apply a fault to a response body.
+    fn apply(fault: Fault, body: SdkBody) -> SdkBody {
+        let mut frames = BodyStream::new(body);
+        let stream = stream! {
+            let mut remaining = match fault {
+                Fault::SlowResponse(delay) => {
+                    tokio::time::sleep(delay).await;
+                    None
+                }
+                Fault::TruncatedStream(bytes) => Some(bytes),
+                Fault::InternalError => None,
+            };
+
+            while let Some(frame) = frames.next().await {
+                let frame = match (frame, remaining.as_mut()) {
+                    (Ok(frame), Some(remaining)) => match frame.into_data() {
+                        Ok(data) => {
+                            let len = u64::try_from(data.len()).unwrap_or_default();
+                            if len >= *remaining {
+                                let data = data.slice(..usize::try_from(*remaining).unwrap_or_default());
+                                yield Ok(Frame::data(data));
+                                yield Err(BoxError::from("simulated truncated stream"));
+                                return;
+                            }
+                            *remaining -= len;
+                            Ok(Frame::data(data))
+                        }
+                        Err(frame) => Ok(frame),
+                    },
+                    (frame, _) => frame,
+                };
+                yield frame;
+            }
+        };
+
+        SdkBody::from_body_1_x(StreamBody::new(stream))
+    }
+}
+
+impl FromStr for FaultInjection {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s.parse().map_err(|err| {
+            ParseError(format!(
+                "invalid fault injection probability `{}`: {}",
+                s, err
+            ))
+        })?)
+    }
+}
+
+impl Eq for FaultInjection {}
+
+impl Hash for FaultInjection {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.probability.to_bits().hash(state);
+    }
+}
+
+/// An interceptor which injects faults into S3 responses before they are deserialized, so
+/// that the SDK retries and copyrite's own resume logic handle them like real failures.
+#[derive(Debug, Clone)]
+pub struct FaultInterceptor {
+    fault_injection: FaultInjection,
+}
+
+impl FaultInterceptor {
+    /// Create a new interceptor.
+    pub fn new(fault_injection: FaultInjection) -> Self {
+        Self { fault_injection }
+    }
+}
+
+impl Intercept for FaultInterceptor {
+    fn name(&self) -> &'static str {
+        "FaultInterceptor"
+    }
+
+    fn modify_before_deserialization(
+        &self,
+        context: &mut BeforeDeserializationInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> std::result::Result<(), BoxError> {
+        let operation = cfg.load::<Metadata>().map(|metadata| metadata.name());
+        let response = context.response_mut();
+        let content_length = response
+            .headers()
+            .get("content-length")
+            .and_then(|length| length.parse().ok());
+
+        match self.fault_injection.choose(operation, content_length) {
+            Some(Fault::InternalError) => {
+                *response.status_mut() = StatusCode::try_from(500)?;
+                response.headers_mut().remove("content-length");
+                *response.body_mut() =
+                    SdkBody::from(Bytes::from_static(INTERNAL_ERROR_BODY.as_bytes()));
+            }
+            Some(fault) => {
+                let body = response.take_body();
+                *response.body_mut() = FaultInjection::apply(fault, body);
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[test]
+    fn parse_probability() {
+        assert_eq!(
+            FaultInjection::from_str("0.25").unwrap().probability(),
+            0.25
+        );
+        assert!(FaultInjection::from_str("1.5").is_err());
+        assert!(FaultInjection::from_str("often").is_err());
+    }
+
+    #[test]
+    fn choose_faults() {
+        assert_eq!(FaultInjection::new(0.0).unwrap().choose(None, None), None);
+
+        let always = FaultInjection::new(1.0).unwrap();
+        for _ in 0..100 {
+            match always.choose(Some("HeadObject"), None) {
+                Some(Fault::TruncatedStream(_)) | None => panic!("expected a fault"),
+                Some(Fault::SlowResponse(delay)) => assert!(delay <= MAX_SLOW_RESPONSE),
+                Some(Fault::InternalError) => {}
+            }
+            if let Some(Fault::TruncatedStream(bytes)) = always.choose(Some("GetObject"), Some(10))
+            {
+                assert!(bytes < 10);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_stream() {
+        let body = FaultInjection::apply(
+            Fault::TruncatedStream(4),
+            SdkBody::from(Bytes::from_static(b"0123456789")),
+        );
+
+        let mut frames = BodyStream::new(body);
+        assert_eq!(
+            frames.next().await.unwrap().unwrap().into_data().unwrap(),
+            Bytes::from_static(b"0123")
+        );
+        assert!(frames.next().await.unwrap().is_err());
+
+        let body = FaultInjection::apply(
+            Fault::SlowResponse(Duration::from_millis(1)),
+            SdkBody::from(Bytes::from_static(b"0123456789")),
+        );
+        assert_eq!(
+            body.collect().await.unwrap().to_bytes(),
+            Bytes::from_static(b"0123456789")
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Support for Google Cloud Storage through its S3-compatible XML API. `gs://bucket/object`
+//! locations resolve to a built-in `gs` remote, and the GCS-native MD5 and CRC32C hashes of
+//! objects are read from the `x-goog-hash` header so that they can be used in sums files.
+//!
+
+use aws_sdk_s3::config::interceptors::BeforeDeserializationInterceptorContextMut;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_smithy_runtime_api::http::Headers;
+
+/// The URL scheme of GCS locations, which is also the name of the built-in GCS remote.
+pub const GCS_SCHEME: &str = "gs";
+
+/// The endpoint of the GCS XML API.
+pub const GCS_ENDPOINT_URL: &str = "https://storage.googleapis.com";
+
+/// The region used to sign requests to GCS.
+pub const GCS_REGION: &str = "auto";
+
+/// The header containing the GCS-native hashes of an object, e.g.
+/// `x-goog-hash: crc32c=n03x6A==,md5=Ojk9c3dhfxgoKVVHYwFbHQ==`.
+const GOOG_HASH_HEADER: &str = "x-goog-hash";
+
+/// The S3 checksum headers that each GCS hash maps to. Both are base64 encoded.
+const GOOG_HASHES: [(&str, &str); 2] = [
+    ("crc32c", "x-amz-checksum-crc32c"),
+    ("md5", "x-amz-checksum-md5"),
+];
+
+/// Whether the location is a GCS URL.
+pub fn is_gcs_url(location: &str) -> bool {
+    location
+        .strip_prefix(GCS_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// Whether an endpoint URL is the GCS XML API.
+pub fn is_gcs_endpoint(endpoint_url: &str) -> bool {
+    let host = endpoint_url
+        .split_once("://")
+        .map_or(endpoint_url, |(_, host)| host);
+    let host = host.split(['/', ':']).next().unwrap_or_default();
+    GCS_ENDPOINT_URL.ends_with(&format!("://{}", host))
+}
+
+/// Copy the GCS-native hashes of an object into the equivalent S3 checksum headers, unless the
+/// response already has them.
+pub fn translate_goog_hash(headers: &mut Headers) {
+    // The header can be repeated or contain multiple comma-separated hashes.
+    let hashes: Vec<(String, String)> = headers
+        .get_all(GOOG_HASH_HEADER)
+        .flat_map(|goog_hash| goog_hash.split(','))
+        .filter_map(|hash| hash.trim().split_once('='))
+        .filter_map(|(name, value)| {
+            GOOG_HASHES
+                .iter()
+                .find(|(goog, _)| *goog == name)
+                .map(|(_, header)| (header.to_string(), value.to_string()))
+        })
+        .collect();
+    for (header, value) in hashes {
+        if headers.get(header.as_str()).is_none() {
+            headers.insert(header, value);
+        }
+    }
+}
+
+/// An interceptor which reads the GCS-native hashes of `HeadObject` responses. This has no
+/// effect on responses from S3, which do not have the `x-goog-hash` header.
+#[derive(Debug, Clone)]
+pub struct GoogHashInterceptor;
+
+impl Intercept for GoogHashInterceptor {
+    fn name(&self) -> &'static str {
+        "GoogHashInterceptor"
+    }
+
+    fn modify_before_deserialization(
+        &self,
+        context: &mut BeforeDeserializationInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // Only object metadata is translated so that downloaded data is not validated against
+        // hashes that do not apply to a range of the object.
+        let is_head = context
+            .inner()
+            .request()
+            .is_some_and(|request| request.method() == "HEAD");
+        if is_head {
+            translate_goog_hash(context.response_mut().headers_mut());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gcs_urls() {
+        assert!(is_gcs_url("gs://bucket/object"));
+        assert!(!is_gcs_url("gsbucket/object"));
+        assert!(!is_gcs_url("s3://bucket/key"));
+
+        assert!(is_gcs_endpoint(GCS_ENDPOINT_URL));
+        assert!(is_gcs_endpoint("storage.googleapis.com:443"));
+        assert!(!is_gcs_endpoint(
+            "https://storage.googleapis.com.example.com"
+        ));
+        assert!(!is_gcs_endpoint("https://s3.amazonaws.com"));
+    }
+
+    #[test]
+    fn translate_hashes() {
+        let mut headers = Headers::new();
+        headers.insert(GOOG_HASH_HEADER, "crc32c=n03x6A==");
+        headers.append(GOOG_HASH_HEADER, "md5=Ojk9c3dhfxgoKVVHYwFbHQ==");
+        translate_goog_hash(&mut headers);
+        assert_eq!(headers.get("x-amz-checksum-crc32c"), Some("n03x6A=="));
+        assert_eq!(
+            headers.get("x-amz-checksum-md5"),
+            Some("Ojk9c3dhfxgoKVVHYwFbHQ==")
+        );
+
+        // Existing S3 checksums take precedence.
+        let mut headers = Headers::new();
+        headers.insert(
+            GOOG_HASH_HEADER,
+            "crc32c=n03x6A==, md5=Ojk9c3dhfxgoKVVHYwFbHQ==",
+        );
+        headers.insert("x-amz-checksum-crc32c", "AAAAAA==");
+        translate_goog_hash(&mut headers);
+        assert_eq!(headers.get("x-amz-checksum-crc32c"), Some("AAAAAA=="));
+    }
+}
@@ -0,0 +1,511 @@
+//! Support for Illumina Connected Analytics (ICA) Genomic Data Store (GDS) locations, e.g.
+//! `gds://volume/path/to/file`. Files are found using the ICA files API and read from the
+//! presigned URL that it returns. Files are written by creating them using the API, which
+//! returns temporary credentials to upload the file to the underlying S3 bucket.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::aws_etag::{AWSETagCtx, PartMode};
+use crate::checksum::file::{Checksum, SumsFile};
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::GdsError;
+use crate::error::Result;
+use crate::io::copy::ObjectCopy;
+use crate::io::copy::aws::{S3, S3Builder};
+use crate::io::registry::ProviderFactory;
+use crate::io::rest::Rest;
+use crate::io::sums::ObjectSums;
+use crate::io::{GdsOptions, Provider, S3Client, construct_credentials, copy, sums};
+use aws_sdk_s3::Client as AwsClient;
+use aws_sdk_s3::config::{BehaviorVersion, Builder, Region};
+use aws_sdk_s3::primitives::ByteStream;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::Arc;
+
+/// The URL scheme of GDS locations.
+pub const GDS_SCHEME: &str = "gds";
+
+/// The ICA API used when no base URL is set.
+pub const DEFAULT_ICA_BASE_URL: &str = "https://aps2.platform.illumina.com";
+
+/// Sends requests to the ICA API, converting failures into GDS errors.
+const REST: Rest = Rest::new(GdsError);
+
+/// Whether the location is a GDS URL.
+pub fn is_gds_url(location: &str) -> bool {
+    location
+        .strip_prefix(GDS_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// A page of files returned by the files endpoint.
+#[derive(Debug, Deserialize)]
+struct FileList {
+    #[serde(default)]
+    items: Vec<GdsFile>,
+}
+
+/// A file returned by the files endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GdsFile {
+    path: String,
+    size_in_bytes: Option<u64>,
+    e_tag: Option<String>,
+    presigned_url: Option<String>,
+}
+
+impl GdsFile {
+    /// The size of the file.
+    pub fn size(&self) -> Result<u64> {
+        self.size_in_bytes
+            .ok_or_else(|| GdsError(format!("{} does not have a size", self.path)))
+    }
+
+    /// The presigned URL used to read the file.
+    pub fn presigned_url(&self) -> Result<&str> {
+        self.presigned_url
+            .as_deref()
+            .ok_or_else(|| GdsError(format!("{} does not have a presigned url", self.path)))
+    }
+
+    /// Convert the `ETag` of the file into sums. The `ETag` is the `ETag` of the object in the
+    /// underlying S3 bucket, so it is an MD5 for single part uploads and an AWS `ETag` with the
+    /// part count otherwise.
+    pub fn sums(&self) -> Result<SumsFile> {
+        let mut sums_file = SumsFile::default().with_size(self.size_in_bytes);
+        let Some(e_tag) = self.e_tag.as_deref().map(|e_tag| e_tag.trim_matches('"')) else {
+            return Ok(sums_file);
+        };
+
+        let (digest, ctx) = match e_tag.split_once('-') {
+            Some((digest, parts)) => {
+                let parts = parts
+                    .parse()
+                    .map_err(|err| GdsError(format!("invalid `ETag` `{}`: {}", e_tag, err)))?;
+                let mut ctx = AWSETagCtx::new(
+                    StandardCtx::md5(),
+                    PartMode::PartNumber(parts),
+                    self.size_in_bytes,
+                );
+                ctx.update_part_sizes();
+                (digest, Ctx::AWSEtag(ctx))
+            }
+            None => (e_tag, Ctx::Regular(StandardCtx::md5())),
+        };
+        let digest = hex::decode(digest)
+            .map_err(|err| GdsError(format!("invalid `ETag` `{}`: {}", e_tag, err)))?;
+
+        let checksum = Checksum::new(ctx.digest_to_string(&digest));
+        sums_file.add_checksum(ctx, checksum);
+        Ok(sums_file)
+    }
+}
+
+/// A file created by the files endpoint, with temporary credentials to upload it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatedFile {
+    object_store_access: ObjectStoreAccess,
+}
+
+/// The object store of a created file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectStoreAccess {
+    aws_s3_temporary_upload_credentials: UploadCredentials,
+}
+
+/// Temporary credentials for uploading a file to the underlying S3 bucket.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadCredentials {
+    #[serde(rename = "access_Key_Id")]
+    access_key_id: String,
+    #[serde(rename = "secret_Access_Key")]
+    secret_access_key: String,
+    #[serde(rename = "session_Token")]
+    session_token: Option<String>,
+    region: String,
+    bucket_name: String,
+    key_prefix: String,
+}
+
+/// The S3 object that a created file is uploaded to.
+#[derive(Debug, Clone)]
+pub struct GdsUpload {
+    client: S3Client,
+    bucket: String,
+    key: String,
+}
+
+impl GdsUpload {
+    /// Create an S3 copy object which uploads to the file.
+    pub fn copy(&self) -> Result<S3> {
+        S3Builder::default()
+            .with_client(self.client.clone())
+            .with_destination(&self.bucket, &self.key)
+            .build()
+    }
+
+    /// Upload the whole file from memory.
+    pub async fn put(&self, data: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object(|b| {
+                b.bucket(&self.bucket)
+                    .key(&self.key)
+                    .body(ByteStream::from(data))
+            })
+            .await
+            .map_err(|err| {
+                GdsError(format!(
+                    "failed to upload to {}: {}",
+                    Provider::format_s3(&self.bucket, &self.key),
+                    err
+                ))
+            })?;
+        Ok(())
+    }
+}
+
+/// A client for the ICA files API.
+#[derive(Debug, Clone, Default)]
+pub struct GdsClient {
+    client: Client,
+    options: GdsOptions,
+}
+
+impl GdsClient {
+    /// Create a new client with the API options.
+    pub fn new(options: GdsOptions) -> Self {
+        Self {
+            client: Client::new(),
+            options,
+        }
+    }
+
+    /// The URL of the files endpoint.
+    fn files_url(&self) -> String {
+        format!(
+            "{}/v1/files",
+            self.options
+                .base_url()
+                .unwrap_or(DEFAULT_ICA_BASE_URL)
+                .trim_end_matches('/')
+        )
+    }
+
+    /// Send a request to the API and parse the JSON response.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder, url: &str) -> Result<T> {
+        let request = match self.options.access_token() {
+            Some(access_token) => request.bearer_auth(access_token),
+            None => request,
+        };
+
+        let operation = format!("request to {}", url);
+        let response = REST.send(request, &operation).await?;
+        let response = REST.check(response, &operation)?;
+
+        response
+            .json()
+            .await
+            .map_err(|err| GdsError(format!("invalid response from {}: {}", url, err)))
+    }
+
+    /// Get a file with its presigned URL, or `None` if it does not exist.
+    pub async fn file(&self, volume: &str, path: &str) -> Result<Option<GdsFile>> {
+        let url = self.files_url();
+        let request = self.client.get(&url).query(&[
+            ("volume.name", volume),
+            ("path", path),
+            ("include", "presignedUrl"),
+        ]);
+
+        let files: FileList = self.send(request, &url).await?;
+        Ok(files.items.into_iter().find(|file| file.path == path))
+    }
+
+    /// Create a file, replacing any existing file, and return the S3 object to upload it to.
+    pub async fn create_file(&self, volume: &str, path: &str) -> Result<GdsUpload> {
+        let location = Provider::format_gds(volume, path);
+        let (folder, name) = path
+            .rsplit_once('/')
+            .filter(|(_, name)| !name.is_empty())
+            .ok_or_else(|| GdsError(format!("{} is missing a file", location)))?;
+
+        let url = self.files_url();
+        let request = self
+            .client
+            .post(&url)
+            .query(&[("include", "objectStoreAccess")])
+            .json(&json!({
+                "name": name,
+                "volumeName": volume,
+                "folderPath": format!("{}/", folder),
+            }));
+
+        let file: CreatedFile = self.send(request, &url).await?;
+        let credentials = file.object_store_access.aws_s3_temporary_upload_credentials;
+
+        // The key prefix is usually the key of the file, but may only be its folder.
+        let key = if credentials.key_prefix.ends_with(&format!("/{}", name)) {
+            credentials.key_prefix
+        } else {
+            format!("{}/{}", credentials.key_prefix.trim_end_matches('/'), name)
+        };
+
+        let config = Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(credentials.region))
+            .credentials_provider(construct_credentials(
+                credentials.access_key_id,
+                credentials.secret_access_key,
+                credentials.session_token,
+            ))
+            .build();
+
+        Ok(GdsUpload {
+            client: S3Client::new(Arc::new(AwsClient::from_conf(config)), true, false),
+            bucket: credentials.bucket_name,
+            key,
+        })
+    }
+}
+
+/// Builds the copy and sums objects of GDS locations. This is registered in the provider
+/// registry for the `gds` scheme, and accesses the ICA API with the GDS options of the client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GdsFactory;
+
+impl GdsFactory {
+    fn client(client: Option<&S3Client>) -> GdsClient {
+        GdsClient::new(
+            client
+                .map(|client| client.gds_options().clone())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderFactory for GdsFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        self.copy_with_client(None, source, destination).await
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        self.sums_with_client(None, url).await
+    }
+
+    async fn copy_with_client(
+        &self,
+        client: Option<&S3Client>,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        let mut builder = copy::gds::GdsBuilder::default().with_client(Self::client(client));
+        if let Some(source) = source {
+            let (volume, path) = Provider::try_from(source)?.into_gds()?;
+            builder = builder.with_source(&volume, &path);
+        }
+        if let Some(destination) = destination {
+            let (volume, path) = Provider::try_from(destination)?.into_gds()?;
+            builder = builder.with_destination(&volume, &path);
+        }
+
+        Ok(Box::new(builder.build()))
+    }
+
+    async fn sums_with_client(
+        &self,
+        client: Option<&S3Client>,
+        url: &str,
+    ) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let (volume, path) = Provider::try_from(url)?.into_gds()?;
+        Ok(Box::new(
+            sums::gds::GdsBuilder::default()
+                .with_volume(volume)
+                .with_path(path)
+                .with_client(Self::client(client))
+                .build()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve a single JSON response, returning the base URL and the request that was received.
+    async fn serve(
+        body: &'static str,
+    ) -> Result<(String, tokio::task::JoinHandle<Result<String>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = [0; 4096];
+            let n = stream.read(&mut request).await?;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                        content-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await?;
+
+            anyhow::Ok(String::from_utf8_lossy(&request[..n]).to_string())
+        });
+
+        Ok((base_url, server))
+    }
+
+    fn client(base_url: String) -> GdsClient {
+        GdsClient::new(GdsOptions::new(Some(base_url), Some("token".to_string())))
+    }
+
+    #[test]
+    fn gds_urls() {
+        assert!(is_gds_url("gds://volume/path/file"));
+        assert!(!is_gds_url("gdsvolume/path/file"));
+        assert!(!is_gds_url("s3://bucket/key"));
+    }
+
+    #[tokio::test]
+    async fn file() -> Result<()> {
+        let (base_url, server) = serve(
+            r#"{
+                "items": [{
+                    "id": "fil.1",
+                    "name": "reads.bam",
+                    "volumeName": "run",
+                    "path": "/data/reads.bam",
+                    "sizeInBytes": 5,
+                    "eTag": "\"ab56b4d92b40713acc5af89985d4b786\"",
+                    "presignedUrl": "https://bucket.s3.amazonaws.com/reads.bam?signature=x"
+                }],
+                "itemCount": 1
+            }"#,
+        )
+        .await?;
+
+        let file = client(base_url)
+            .file("run", "/data/reads.bam")
+            .await?
+            .expect("file");
+        assert_eq!(file.size()?, 5);
+        assert_eq!(
+            file.presigned_url()?,
+            "https://bucket.s3.amazonaws.com/reads.bam?signature=x"
+        );
+
+        let request = server.await??;
+        assert!(request.starts_with(
+            "GET /v1/files?volume.name=run&path=%2Fdata%2Freads.bam&include=presignedUrl HTTP/1.1"
+        ));
+        assert!(
+            request
+                .to_lowercase()
+                .contains("authorization: bearer token")
+        );
+
+        let (base_url, _) = serve(r#"{"items": [], "itemCount": 0}"#).await?;
+        assert!(
+            client(base_url)
+                .file("run", "/data/reads.bam")
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_file() -> Result<()> {
+        let (base_url, server) = serve(
+            r#"{
+                "id": "fil.1",
+                "objectStoreAccess": {
+                    "awsS3TemporaryUploadCredentials": {
+                        "access_Key_Id": "id",
+                        "secret_Access_Key": "secret",
+                        "session_Token": "token",
+                        "region": "ap-southeast-2",
+                        "bucketName": "bucket",
+                        "keyPrefix": "vol.1/data/reads.bam"
+                    }
+                }
+            }"#,
+        )
+        .await?;
+
+        let upload = client(base_url)
+            .create_file("run", "/data/reads.bam")
+            .await?;
+        assert_eq!(upload.bucket, "bucket");
+        assert_eq!(upload.key, "vol.1/data/reads.bam");
+
+        let request = server.await??;
+        assert!(request.starts_with("POST /v1/files?include=objectStoreAccess HTTP/1.1"));
+        assert!(request.contains(r#""folderPath":"/data/""#));
+        assert!(request.contains(r#""name":"reads.bam""#));
+        assert!(request.contains(r#""volumeName":"run""#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn e_tag_sums() -> Result<()> {
+        let file = GdsFile {
+            path: "/data/reads.bam".to_string(),
+            size_in_bytes: Some(5),
+            e_tag: Some("ab56b4d92b40713acc5af89985d4b786".to_string()),
+            presigned_url: None,
+        };
+        let sums = file.sums()?;
+        assert_eq!(sums.size, Some(5));
+        assert_eq!(
+            sums.checksums.get(&Ctx::from_str("md5")?),
+            Some(&Checksum::new(
+                "ab56b4d92b40713acc5af89985d4b786".to_string()
+            ))
+        );
+
+        let file = GdsFile {
+            e_tag: Some("\"ab56b4d92b40713acc5af89985d4b786-2\"".to_string()),
+            size_in_bytes: Some(16 * 1024 * 1024),
+            ..file
+        };
+        let sums = file.sums()?;
+        let (ctx, checksum) = sums.checksums.first_key_value().expect("checksum");
+        assert!(matches!(ctx, Ctx::AWSEtag(_)));
+        assert!(
+            checksum
+                .as_str()
+                .starts_with("ab56b4d92b40713acc5af89985d4b786-")
+        );
+
+        let file = GdsFile {
+            e_tag: None,
+            ..file
+        };
+        assert!(file.sums()?.checksums.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! Read-only support for `http://` and `https://` locations, so that public data such as
+//! reference genomes on web servers can be used as a source. Objects are read with `GET`
+//! requests, using `Range` requests for parts and their size comes from the `Content-Length` of
+//! a `HEAD` request.
+//!
+
+use crate::error::Error::HttpError;
+use crate::error::{Error, Result};
+use crate::io::copy::ObjectCopy;
+use crate::io::copy::http::Http;
+use crate::io::registry::ProviderFactory;
+use crate::io::rest::Rest;
+use crate::io::sums::ObjectSums;
+use crate::io::{Provider, copy, sums};
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use http_body_util::BodyExt;
+use reqwest::header::RANGE;
+use reqwest::{Body, Client, Method, RequestBuilder, Response, StatusCode};
+use std::io;
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+/// The URL schemes of HTTP locations.
+pub const HTTP_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// An HTTP response reader.
+pub type HttpReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// Whether the location is an HTTP or HTTPS URL.
+pub fn is_http_url(location: &str) -> bool {
+    HTTP_SCHEMES.iter().any(|scheme| {
+        location
+            .strip_prefix(scheme)
+            .is_some_and(|location| location.starts_with("://"))
+    })
+}
+
+/// The properties of an HTTP object from the headers of a `HEAD` response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpProperties {
+    pub(crate) size: Option<u64>,
+    pub(crate) e_tag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+impl HttpProperties {
+    /// Read the properties from the headers of a response.
+    fn from_response(response: &Response) -> Result<Self> {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        let size = header("content-length")
+            .map(|size| {
+                size.parse()
+                    .map_err(|err| HttpError(format!("invalid content length: {}", err)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            size,
+            e_tag: header("etag").map(|e_tag| e_tag.trim_matches('"').to_string()),
+            last_modified: header("last-modified"),
+        })
+    }
+
+    /// The size of the object, which is required to copy it.
+    pub fn size(&self, url: &str) -> Result<u64> {
+        self.size.ok_or_else(|| {
+            HttpError(format!(
+                "{} does not have a `Content-Length`, so its size is unknown",
+                url
+            ))
+        })
+    }
+}
+
+/// Sends requests to HTTP servers, converting failures into HTTP errors.
+const REST: Rest = Rest::new(HttpError);
+
+/// A client for reading HTTP locations.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient {
+    client: Client,
+}
+
+impl HttpClient {
+    /// Send a request, retrying transient failures and mapping transport errors to HTTP errors.
+    async fn send(request: RequestBuilder, url: &str) -> Result<Response> {
+        REST.send(request, &format!("request to {}", url)).await
+    }
+
+    /// Convert an unsuccessful response into an error.
+    fn check(response: Response, url: &str) -> Result<Response> {
+        REST.check(response, &format!("request to {}", url))
+    }
+
+    /// Get the properties of an object, or `None` if it does not exist.
+    pub async fn head(&self, url: &str) -> Result<Option<HttpProperties>> {
+        let response = Self::send(self.client.request(Method::HEAD, url), url).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, url)?;
+        Ok(Some(HttpProperties::from_response(&response)?))
+    }
+
+    /// Read an object from `start` up to, but not including, `end`, or to the end of the object
+    /// if `end` is not set. Returns `None` if the object does not exist. Reading from an offset
+    /// requires the server to support range requests.
+    pub async fn get(&self, url: &str, start: u64, end: Option<u64>) -> Result<Option<HttpReader>> {
+        let mut request = self.client.request(Method::GET, url);
+        let range = match end {
+            Some(end) if end <= start => return Ok(Some(Box::new(tokio::io::empty()))),
+            Some(end) => Some(format!("bytes={}-{}", start, end - 1)),
+            None if start > 0 => Some(format!("bytes={}-", start)),
+            None => None,
+        };
+        if let Some(range) = &range {
+            request = request.header(RANGE, range);
+        }
+
+        let response = Self::send(request, url).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, url)?;
+        // Servers without range support return the whole object, which cannot be used as a part.
+        if range.is_some() && response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(HttpError(format!(
+                "{} does not support range requests",
+                url
+            )));
+        }
+
+        let stream = Body::from(response)
+            .into_data_stream()
+            .map_err(io::Error::other);
+        Ok(Some(Box::new(StreamReader::new(stream))))
+    }
+
+    /// Read a whole object into memory, or `None` if it does not exist. Client errors, such as
+    /// a forbidden response from a bucket that hides missing objects, are also treated as the
+    /// object not existing.
+    pub async fn get_optional_bytes(&self, url: &str) -> Result<Option<Bytes>> {
+        let response = Self::send(self.client.request(Method::GET, url), url).await?;
+        if response.status().is_client_error() {
+            return Ok(None);
+        }
+
+        let response = Self::check(response, url)?;
+        Ok(Some(response.bytes().await.map_err(Error::from)?))
+    }
+}
+
+/// Builds the copy and sums objects of HTTP locations. This is registered in the provider
+/// registry for the `http` and `https` schemes. HTTP locations are read-only, so they cannot be
+/// a copy destination.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpFactory;
+
+#[async_trait::async_trait]
+impl ProviderFactory for HttpFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        if destination.is_some() {
+            return Err(Http::read_only_error());
+        }
+
+        let mut builder = copy::http::HttpBuilder::default();
+        if let Some(source) = source {
+            builder = builder.with_source(&Provider::try_from(source)?.into_http()?);
+        }
+
+        Ok(Box::new(builder.build()))
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let url = Provider::try_from(url)?.into_http()?;
+        Ok(Box::new(
+            sums::http::HttpBuilder::default().with_url(url).build()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve a single request with a response, returning the request that was received.
+    async fn serve(
+        response: &'static [u8],
+    ) -> anyhow::Result<(String, tokio::task::JoinHandle<anyhow::Result<String>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/genome.fa", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = [0; 4096];
+            let n = stream.read(&mut request).await?;
+            stream.write_all(response).await?;
+            anyhow::Ok(String::from_utf8_lossy(&request[..n]).to_string())
+        });
+
+        Ok((url, server))
+    }
+
+    #[test]
+    fn http_urls() {
+        assert!(is_http_url("https://example.com/genome.fa"));
+        assert!(is_http_url("http://example.com/genome.fa"));
+        assert!(!is_http_url("httpsexample.com/genome.fa"));
+        assert!(!is_http_url("s3://bucket/key"));
+    }
+
+    #[tokio::test]
+    async fn head() -> anyhow::Result<()> {
+        let (url, server) = serve(
+            b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\netag: \"abc\"\r\n\
+            last-modified: Wed, 21 Oct 2015 07:28:00 GMT\r\n\r\n",
+        )
+        .await?;
+
+        let properties = HttpClient::default().head(&url).await?.expect("properties");
+        assert_eq!(properties.size(&url)?, 5);
+        assert_eq!(properties.e_tag.as_deref(), Some("abc"));
+        assert_eq!(
+            properties.last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        assert!(server.await??.starts_with("HEAD /genome.fa HTTP/1.1"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_range() -> anyhow::Result<()> {
+        let (url, server) =
+            serve(b"HTTP/1.1 206 Partial Content\r\ncontent-length: 3\r\n\r\nbcd").await?;
+
+        let mut reader = HttpClient::default()
+            .get(&url, 1, Some(4))
+            .await?
+            .expect("reader");
+        let mut data = String::new();
+        reader.read_to_string(&mut data).await?;
+        assert_eq!(data, "bcd");
+        assert!(server.await??.to_lowercase().contains("range: bytes=1-3"));
+
+        // The whole object cannot be used when a range was requested.
+        let (url, _) = serve(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nabcde").await?;
+        let result = HttpClient::default().get(&url, 1, Some(4)).await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
@@ -1,33 +1,83 @@
 //! Module that handles all file IO
 //!
 
-use crate::cli::{Compatibility, CredentialProvider, Credentials, StalledStreamProtection};
+use crate::cli::{
+    Compatibility, CredentialProvider, Credentials, SftpKnownHosts, StalledStreamProtection,
+};
 use crate::error::Error::ParseError;
 use crate::error::{Error, Result};
+use crate::io::cache::{CacheKey, MetadataCache};
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::pool::{ClientKey, ClientPool};
+use crate::io::profile::S3CompatProfile;
+use crate::io::sums::external::SumsSource;
+use crate::io::sums::mapped::SumsMap;
 use aws_config::Region;
-use aws_credential_types::provider::ProvideCredentials;
+use aws_config::credential_process::CredentialProcessProvider;
+use aws_credential_types::provider::error::CredentialsError;
+use aws_credential_types::provider::{self, ProvideCredentials, SharedCredentialsProvider, future};
 use aws_sdk_s3::client::customize::CustomizableOperation;
-use aws_sdk_s3::config::{RequestChecksumCalculation, StalledStreamProtectionConfig};
-use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::config::{
+    IdentityCache, RequestChecksumCalculation, StalledStreamProtectionConfig,
+};
+use aws_sdk_s3::error::{DisplayErrorContext, SdkError};
 use aws_sdk_s3::operation;
+use aws_sdk_s3::types;
+use aws_sdk_s3::types::ChecksumAlgorithm;
 use aws_sdk_s3::{Client, config};
 use aws_smithy_runtime_api::client::behavior_version::BehaviorVersion;
+use aws_smithy_runtime_api::http::{Headers, Request};
 use pastey::paste;
 use serde::Deserialize;
-use std::path::Path;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
 use std::result;
-use std::sync::Arc;
-
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+pub mod azure;
+pub mod cache;
+pub mod connector;
 pub mod copy;
+pub mod drs;
+pub mod fault;
+pub mod gcs;
+pub mod gds;
+pub mod http;
+pub mod pool;
+pub mod profile;
+pub mod registry;
+pub mod rest;
+pub mod select;
+#[cfg(unix)]
+pub mod sftp;
+pub mod space;
 pub mod sums;
+pub mod swift;
+pub mod trace;
+pub mod webdav;
 
 /// An S3 client wrapper with compatibility settings.
 #[derive(Debug, Clone)]
 pub struct S3Client {
-    inner: Arc<Client>,
+    inner: Arc<RwLock<Arc<Client>>>,
     no_get_object_attributes: bool,
     no_checksum_mode: bool,
     stalled_stream_protection: StalledStreamProtection,
+    get_parameters: Arc<Vec<RequestParameter>>,
+    metadata_cache: Option<MetadataCache>,
+    sums_source: Option<Arc<dyn SumsSource + Send + Sync>>,
+    sums_map: Option<Arc<SumsMap>>,
+    limits: ProviderLimits,
+    sftp_options: SftpOptions,
+    gds_options: GdsOptions,
+    swift_options: SwiftOptions,
+    webdav_options: WebDavOptions,
+    compat_profile: Option<S3CompatProfile>,
+    checksums_rejected: Arc<AtomicBool>,
 }
 
 /// Generate an `S3Client` wrapper method that calls the underlying S3 operation.
@@ -46,7 +96,7 @@ macro_rules! s3_wrapper_call {
                 F: FnOnce(operation::$name::builders::[<$name:camel FluentBuilder>])
                     -> operation::$name::builders::[<$name:camel FluentBuilder>],
             {
-                let builder = configure(self.inner.$name());
+                let builder = configure(self.client().$name());
                 self.ssp_override(
                     builder.customize(),
                     self.stalled_stream_protection.$disable(),
@@ -56,6 +106,37 @@ macro_rules! s3_wrapper_call {
             }
         }
     };
+    ($name:ident, $disable:ident, invalidate) => {
+        paste! {
+            #[doc = concat!("A wrapper around `", stringify!($name), "` that overrides SSP, resolves the query and invalidates cached metadata for the written object.")]
+            pub async fn $name<F>(
+                &self,
+                configure: F,
+            ) -> result::Result<
+                operation::$name::[<$name:camel Output>],
+                SdkError<operation::$name::[<$name:camel Error>]>,
+            >
+            where
+                F: FnOnce(operation::$name::builders::[<$name:camel FluentBuilder>])
+                    -> operation::$name::builders::[<$name:camel FluentBuilder>],
+            {
+                let builder = configure(self.client().$name());
+                let object = (builder.get_bucket().clone(), builder.get_key().clone());
+                let result = self
+                    .ssp_override(
+                        builder.customize(),
+                        self.stalled_stream_protection.$disable(),
+                    )
+                    .send()
+                    .await;
+
+                if let (Some(cache), (Some(bucket), Some(key))) = (&self.metadata_cache, object) {
+                    cache.invalidate(&bucket, &key);
+                }
+                result
+            }
+        }
+    };
 }
 
 impl S3Client {
@@ -81,61 +162,214 @@ impl S3Client {
         stalled_stream_protection: StalledStreamProtection,
     ) -> Self {
         Self {
-            inner: client,
+            inner: Arc::new(RwLock::new(client)),
             no_get_object_attributes,
             no_checksum_mode,
             stalled_stream_protection,
+            get_parameters: Default::default(),
+            metadata_cache: None,
+            sums_source: None,
+            sums_map: None,
+            limits: ProviderLimits::default(),
+            sftp_options: SftpOptions::default(),
+            gds_options: GdsOptions::default(),
+            swift_options: SwiftOptions::default(),
+            webdav_options: WebDavOptions::default(),
+            compat_profile: None,
+            checksums_rejected: Default::default(),
         }
     }
 
-    /// Create a new source S3Client from CLI compatibility and credentials options.
+    /// The current underlying client.
+    fn client(&self) -> Arc<Client> {
+        Arc::clone(&self.inner.read().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    /// Force the credentials to be resolved again on the next request. The client caches
+    /// credentials until they are close to their expiry, so this replaces it with one that has an
+    /// empty identity cache. Clones of this client share the replacement.
+    pub fn refresh_credentials(&self) {
+        let config = self
+            .client()
+            .config()
+            .to_builder()
+            .identity_cache(IdentityCache::lazy().build())
+            .build();
+        *self.inner.write().unwrap_or_else(|err| err.into_inner()) =
+            Arc::new(Client::from_conf(config));
+    }
+
+    /// Cache `HeadObject` and `GetObjectAttributes` outputs in a metadata cache shared by all
+    /// clones of this client.
+    pub fn with_metadata_cache(mut self, metadata_cache: MetadataCache) -> Self {
+        self.metadata_cache = Some(metadata_cache);
+        self
+    }
+
+    /// Set additional headers or query parameters to send with `GetObject` requests.
+    pub fn with_get_parameters(mut self, get_parameters: Vec<RequestParameter>) -> Self {
+        self.get_parameters = Arc::new(get_parameters);
+        self
+    }
+
+    /// Read and store sums using an external sums source for all objects accessed with this
+    /// client.
+    pub fn with_sums_source(mut self, sums_source: Arc<dyn SumsSource + Send + Sync>) -> Self {
+        self.sums_source = Some(sums_source);
+        self
+    }
+
+    /// Read and store the sums files of mapped objects accessed with this client at their
+    /// mapped location instead of next to the object.
+    pub fn with_sums_map(mut self, sums_map: Arc<SumsMap>) -> Self {
+        self.sums_map = Some(sums_map);
+        self
+    }
+
+    /// Set the multipart limits of the store that this client accesses, which default to the
+    /// limits of S3.
+    pub fn with_limits(mut self, limits: ProviderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the profile of the S3-compatible store that this client accesses, which limits the
+    /// additional checksums that are sent to it.
+    pub fn set_compat_profile(mut self, compat_profile: Option<S3CompatProfile>) -> Self {
+        self.compat_profile = compat_profile;
+        self
+    }
+
+    /// Set the options used to connect to SFTP locations accessed alongside this client.
+    pub fn with_sftp_options(mut self, sftp_options: SftpOptions) -> Self {
+        self.sftp_options = sftp_options;
+        self
+    }
+
+    /// Set the options used to access GDS locations alongside this client.
+    pub fn with_gds_options(mut self, gds_options: GdsOptions) -> Self {
+        self.gds_options = gds_options;
+        self
+    }
+
+    /// Set the options used to access Swift locations alongside this client.
+    pub fn with_swift_options(mut self, swift_options: SwiftOptions) -> Self {
+        self.swift_options = swift_options;
+        self
+    }
+
+    /// Set the options used to access WebDAV locations alongside this client.
+    pub fn with_webdav_options(mut self, webdav_options: WebDavOptions) -> Self {
+        self.webdav_options = webdav_options;
+        self
+    }
+
+    /// Create a new source S3Client from CLI compatibility and credentials options. The
+    /// underlying client is shared with any other client in the pool that has the same options.
     pub async fn new_from_cli_source(
         credentials: &Credentials,
         compatibility: &Compatibility,
+        pool: &ClientPool,
     ) -> Result<Self> {
-        let client = Self::create_s3_client(
-            &credentials.effective_source_credential_provider(),
-            credentials.effective_source_profile(),
-            credentials.effective_source_region(),
-            credentials.effective_source_endpoint_url(),
-            credentials.effective_source_secret(),
-            credentials.source_overrides(),
-            compatibility.source_force_path_style(),
-            compatibility.source_no_request_checksum(),
-        )
-        .await?;
+        let client = pool
+            .get_or_create(ClientKey {
+                provider: credentials.effective_source_credential_provider(),
+                profile: credentials.effective_source_profile().map(str::to_string),
+                region: credentials.effective_source_region().map(str::to_string),
+                endpoint_url: credentials
+                    .effective_source_endpoint_url()
+                    .map(str::to_string),
+                secret: credentials.effective_source_secret().map(str::to_string),
+                credential_process: credentials
+                    .effective_source_credential_process()
+                    .map(str::to_string),
+                overrides: credentials.source_overrides(),
+                force_path_style: compatibility.source_force_path_style(),
+                no_request_checksum: compatibility.source_no_request_checksum(),
+                requester_pays: compatibility.source_requester_pays(),
+                expected_bucket_owner: compatibility
+                    .source_expected_bucket_owner()
+                    .map(str::to_string),
+                source_expected_bucket_owner: None,
+                fault_injection: compatibility.fault_inject,
+            })
+            .await?;
 
         Ok(Self::with_stalled_stream_protection(
-            Arc::new(client),
+            client,
             compatibility.source_no_get_object_attributes(),
             compatibility.source_no_checksum_mode(),
             compatibility.source_stalled_stream_protection(),
-        ))
+        )
+        .with_metadata_cache(MetadataCache::new())
+        .with_get_parameters(compatibility.source_header.clone())
+        .set_compat_profile(compatibility.source_s3_compat_profile())
+        .with_sftp_options(credentials.sftp_options())
+        .with_gds_options(credentials.gds_options())
+        .with_swift_options(credentials.swift_options())
+        .with_webdav_options(credentials.webdav_options()))
     }
 
-    /// Create a new destination S3Client from CLI compatibility and credentials options.
+    /// Create a new destination S3Client from CLI compatibility and credentials options. The
+    /// underlying client is shared with any other client in the pool that has the same options.
     pub async fn new_from_cli_destination(
         credentials: &Credentials,
         compatibility: &Compatibility,
+        pool: &ClientPool,
     ) -> Result<Self> {
-        let client = Self::create_s3_client(
-            &credentials.effective_destination_credential_provider(),
-            credentials.effective_destination_profile(),
-            credentials.effective_destination_region(),
-            credentials.effective_destination_endpoint_url(),
-            credentials.effective_destination_secret(),
-            credentials.destination_overrides(),
-            compatibility.destination_force_path_style(),
-            compatibility.destination_no_request_checksum(),
-        )
-        .await?;
+        let client = pool
+            .get_or_create(ClientKey {
+                provider: credentials.effective_destination_credential_provider(),
+                profile: credentials
+                    .effective_destination_profile()
+                    .map(str::to_string),
+                region: credentials
+                    .effective_destination_region()
+                    .map(str::to_string),
+                endpoint_url: credentials
+                    .effective_destination_endpoint_url()
+                    .map(str::to_string),
+                secret: credentials
+                    .effective_destination_secret()
+                    .map(str::to_string),
+                credential_process: credentials
+                    .effective_destination_credential_process()
+                    .map(str::to_string),
+                overrides: credentials.destination_overrides(),
+                force_path_style: compatibility.destination_force_path_style(),
+                no_request_checksum: compatibility.destination_no_request_checksum(),
+                requester_pays: compatibility.destination_requester_pays(),
+                expected_bucket_owner: compatibility
+                    .destination_expected_bucket_owner()
+                    .map(str::to_string),
+                // Server-side copies are sent by the destination client.
+                source_expected_bucket_owner: compatibility
+                    .source_expected_bucket_owner()
+                    .map(str::to_string),
+                fault_injection: compatibility.fault_inject,
+            })
+            .await?;
 
         Ok(Self::with_stalled_stream_protection(
-            Arc::new(client),
+            client,
             compatibility.destination_no_get_object_attributes(),
             compatibility.destination_no_checksum_mode(),
             compatibility.destination_stalled_stream_protection(),
-        ))
+        )
+        .with_metadata_cache(MetadataCache::new())
+        .with_limits(
+            compatibility
+                .destination_s3_compat_profile()
+                .map(|profile| profile.limits())
+                .unwrap_or_else(|| {
+                    ProviderLimits::for_endpoint(credentials.effective_destination_endpoint_url())
+                }),
+        )
+        .set_compat_profile(compatibility.destination_s3_compat_profile())
+        .with_sftp_options(credentials.sftp_options())
+        .with_gds_options(credentials.gds_options())
+        .with_swift_options(credentials.swift_options())
+        .with_webdav_options(credentials.webdav_options()))
     }
 
     /// Whether to avoid `GetObjectAttributes` calls.
@@ -153,6 +387,70 @@ impl S3Client {
         self.stalled_stream_protection
     }
 
+    /// The additional headers or query parameters sent with `GetObject` requests.
+    pub fn get_parameters(&self) -> &[RequestParameter] {
+        self.get_parameters.as_slice()
+    }
+
+    /// The external sums source, if any.
+    pub fn sums_source(&self) -> Option<&Arc<dyn SumsSource + Send + Sync>> {
+        self.sums_source.as_ref()
+    }
+
+    /// The map of objects to sums file locations, if any.
+    pub fn sums_map(&self) -> Option<&Arc<SumsMap>> {
+        self.sums_map.as_ref()
+    }
+
+    /// The options used to connect to SFTP locations.
+    pub fn sftp_options(&self) -> &SftpOptions {
+        &self.sftp_options
+    }
+
+    /// The options used to access GDS locations.
+    pub fn gds_options(&self) -> &GdsOptions {
+        &self.gds_options
+    }
+
+    /// The options used to access Swift locations.
+    pub fn swift_options(&self) -> &SwiftOptions {
+        &self.swift_options
+    }
+
+    /// The options used to access WebDAV locations.
+    pub fn webdav_options(&self) -> &WebDavOptions {
+        &self.webdav_options
+    }
+
+    /// The multipart limits of the store.
+    pub fn limits(&self) -> ProviderLimits {
+        self.limits
+    }
+
+    /// The profile of the S3-compatible store, if any.
+    pub fn compat_profile(&self) -> Option<S3CompatProfile> {
+        self.compat_profile
+    }
+
+    /// Whether the store accepts an additional checksum algorithm. Stores without a profile are
+    /// assumed to accept all algorithms.
+    pub fn supports_checksum(&self, algorithm: &ChecksumAlgorithm) -> bool {
+        self.compat_profile
+            .is_none_or(|profile| profile.supports_checksum(algorithm))
+    }
+
+    /// Whether the store rejected an additional checksum, in which case later requests are sent
+    /// without additional checksums.
+    pub fn checksums_rejected(&self) -> bool {
+        self.checksums_rejected.load(Ordering::Relaxed)
+    }
+
+    /// Record that the store rejected an additional checksum. This is shared by all clones of
+    /// this client.
+    pub fn reject_checksums(&self) {
+        self.checksums_rejected.store(true, Ordering::Relaxed);
+    }
+
     /// Apply the SSP config override.
     fn ssp_override<T, E, B>(
         &self,
@@ -178,6 +476,7 @@ impl S3Client {
         region: Option<&str>,
         endpoint_url: Option<&str>,
         secret: Option<&str>,
+        credential_process: Option<&str>,
         overrides: CredentialOverrides,
         force_path_style: bool,
         no_request_checksum: bool,
@@ -194,12 +493,28 @@ impl S3Client {
         let loader = match (provider, profile, secret) {
             (CredentialProvider::DefaultEnvironment, _, _) => loader,
             (CredentialProvider::NoCredentials, _, _) => loader.no_credentials(),
+            // Static credentials are only taken from the overrides below.
+            (CredentialProvider::Static, _, _) => {
+                if !overrides.any() {
+                    return Err(ParseError(
+                        "access-key-id and secret-access-key must be specified if using static credential provider"
+                            .to_string(),
+                    ));
+                }
+                loader.no_credentials()
+            }
             (CredentialProvider::AwsProfile, Some(profile), _) => loader.profile_name(profile),
             (CredentialProvider::AwsSecret, _, Some(secret)) => {
-                let credentials = SecretsManagerCredentials::new(secret)
-                    .await?
-                    .into_credentials();
-                loader.credentials_provider(credentials)
+                loader.credentials_provider(SecretsManagerProvider::new(secret).await?)
+            }
+            (CredentialProvider::Process, _, _) => {
+                let command = credential_process.ok_or_else(|| {
+                    ParseError(
+                        "credential process must be specified if using process credential provider"
+                            .to_string(),
+                    )
+                })?;
+                loader.credentials_provider(CredentialProcessProvider::new(command.to_string()))
             }
             (CredentialProvider::AwsProfile, None, _) => {
                 return Err(ParseError(
@@ -218,15 +533,18 @@ impl S3Client {
 
         let s3_config = if overrides.any() {
             // Allow no credentials to be set with only overrides.
-            let base = if let Some(creds_provider) = sdk_config.credentials_provider() {
-                creds_provider.provide_credentials().await.ok()
-            } else {
-                None
+            let provider = OverrideProvider {
+                overrides,
+                base: sdk_config.credentials_provider(),
             };
 
-            let merged = overrides.merge_with(base.as_ref())?;
+            // Check that the overrides are valid before any requests are made.
+            provider
+                .load_credentials()
+                .await
+                .map_err(|err| ParseError(DisplayErrorContext(err).to_string()))?;
             let mut builder = config::Builder::from(&sdk_config)
-                .credentials_provider(merged)
+                .credentials_provider(provider)
                 .force_path_style(force_path_style);
             if no_request_checksum {
                 builder =
@@ -258,6 +576,7 @@ impl S3Client {
             None,
             None,
             None,
+            None,
             no_overrides,
             false,
             false,
@@ -265,16 +584,564 @@ impl S3Client {
         .await
     }
 
-    s3_wrapper_call!(get_object, disable_all);
-    s3_wrapper_call!(put_object, disable_all);
+    /// A wrapper around `get_object` that overrides SSP, adds any additional request parameters
+    /// and resolves the query.
+    pub async fn get_object<F>(
+        &self,
+        configure: F,
+    ) -> result::Result<
+        operation::get_object::GetObjectOutput,
+        SdkError<operation::get_object::GetObjectError>,
+    >
+    where
+        F: FnOnce(
+            operation::get_object::builders::GetObjectFluentBuilder,
+        ) -> operation::get_object::builders::GetObjectFluentBuilder,
+    {
+        let builder = configure(self.client().get_object());
+        let mut customize = self.ssp_override(
+            builder.customize(),
+            self.stalled_stream_protection.disable_all(),
+        );
+
+        if !self.get_parameters.is_empty() {
+            let get_parameters = self.get_parameters.clone();
+            customize = customize.mutate_request(move |request| {
+                get_parameters
+                    .iter()
+                    .for_each(|parameter| parameter.apply(request));
+            });
+        }
+
+        customize.send().await
+    }
+
+    /// A wrapper around `head_object` that overrides SSP and resolves the query, returning a
+    /// cached output if one exists.
+    pub async fn head_object<F>(
+        &self,
+        configure: F,
+    ) -> result::Result<
+        operation::head_object::HeadObjectOutput,
+        SdkError<operation::head_object::HeadObjectError>,
+    >
+    where
+        F: FnOnce(
+            operation::head_object::builders::HeadObjectFluentBuilder,
+        ) -> operation::head_object::builders::HeadObjectFluentBuilder,
+    {
+        let builder = configure(self.client().head_object());
+        let key = CacheKey::new(
+            builder.get_bucket().as_deref(),
+            builder.get_key().as_deref(),
+            builder.get_version_id().as_deref(),
+            *builder.get_part_number(),
+            builder
+                .get_checksum_mode()
+                .iter()
+                .map(|mode| mode.as_str().to_string())
+                .collect(),
+        );
+        if let Some(output) = self
+            .metadata_cache
+            .as_ref()
+            .and_then(|cache| cache.head_object(&key))
+        {
+            return Ok(output);
+        }
+
+        let output = self
+            .ssp_override(
+                builder.customize(),
+                self.stalled_stream_protection.disable_all(),
+            )
+            .send()
+            .await?;
+
+        if let Some(cache) = &self.metadata_cache {
+            cache.insert_head_object(key, output.clone());
+        }
+        Ok(output)
+    }
+
+    /// A wrapper around `get_object_attributes` that overrides SSP and resolves the query,
+    /// returning a cached output if one exists.
+    pub async fn get_object_attributes<F>(
+        &self,
+        configure: F,
+    ) -> result::Result<
+        operation::get_object_attributes::GetObjectAttributesOutput,
+        SdkError<operation::get_object_attributes::GetObjectAttributesError>,
+    >
+    where
+        F: FnOnce(
+            operation::get_object_attributes::builders::GetObjectAttributesFluentBuilder,
+        )
+            -> operation::get_object_attributes::builders::GetObjectAttributesFluentBuilder,
+    {
+        let builder = configure(self.client().get_object_attributes());
+        let options = builder
+            .get_object_attributes()
+            .iter()
+            .flatten()
+            .map(|attribute| attribute.as_str().to_string())
+            .chain(
+                builder
+                    .get_max_parts()
+                    .map(|max| format!("max-parts={}", max)),
+            )
+            .chain(
+                builder
+                    .get_part_number_marker()
+                    .as_ref()
+                    .map(|marker| format!("part-number-marker={}", marker)),
+            )
+            .collect();
+        let key = CacheKey::new(
+            builder.get_bucket().as_deref(),
+            builder.get_key().as_deref(),
+            builder.get_version_id().as_deref(),
+            None,
+            options,
+        );
+        if let Some(output) = self
+            .metadata_cache
+            .as_ref()
+            .and_then(|cache| cache.get_object_attributes(&key))
+        {
+            return Ok(output);
+        }
+
+        let output = self
+            .ssp_override(
+                builder.customize(),
+                self.stalled_stream_protection.disable_all(),
+            )
+            .send()
+            .await?;
+
+        if let Some(cache) = &self.metadata_cache {
+            cache.insert_get_object_attributes(key, output.clone());
+        }
+        Ok(output)
+    }
+
+    s3_wrapper_call!(put_object, disable_all, invalidate);
     s3_wrapper_call!(upload_part, disable_all);
-    s3_wrapper_call!(head_object, disable_all);
-    s3_wrapper_call!(complete_multipart_upload, disable_all);
+    s3_wrapper_call!(complete_multipart_upload, disable_all, invalidate);
     s3_wrapper_call!(create_multipart_upload, disable_all);
     s3_wrapper_call!(get_object_tagging, disable_all);
-    s3_wrapper_call!(get_object_attributes, disable_all);
-    s3_wrapper_call!(copy_object, disable_copy_object);
+    s3_wrapper_call!(get_object_acl, disable_all);
+    s3_wrapper_call!(select_object_content, disable_all);
+    s3_wrapper_call!(copy_object, disable_copy_object, invalidate);
     s3_wrapper_call!(upload_part_copy, disable_copy_object);
+    s3_wrapper_call!(list_parts, disable_all);
+    s3_wrapper_call!(abort_multipart_upload, disable_all);
+    s3_wrapper_call!(list_objects_v2, disable_all);
+    s3_wrapper_call!(restore_object, disable_all, invalidate);
+    s3_wrapper_call!(delete_object, disable_all, invalidate);
+    s3_wrapper_call!(head_bucket, disable_all);
+    s3_wrapper_call!(create_bucket, disable_all);
+    s3_wrapper_call!(get_bucket_versioning, disable_all);
+    s3_wrapper_call!(get_bucket_encryption, disable_all);
+    s3_wrapper_call!(get_bucket_ownership_controls, disable_all);
+    s3_wrapper_call!(get_public_access_block, disable_all);
+
+    /// Create a bucket if it does not exist, returning whether it was created. The bucket is
+    /// created in the region of this client with the default S3 settings, which block public
+    /// access and disable ACLs.
+    pub async fn create_bucket_if_missing(&self, bucket: &str) -> Result<bool> {
+        match self.head_bucket(|b| b.bucket(bucket)).await {
+            Ok(_) => return Ok(false),
+            Err(err)
+                if matches!(
+                    err.as_service_error(),
+                    Some(operation::head_bucket::HeadBucketError::NotFound(_))
+                ) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        // Buckets in `us-east-1` must not specify a location constraint.
+        let configuration = self
+            .client()
+            .config()
+            .region()
+            .filter(|region| region.as_ref() != "us-east-1")
+            .map(|region| {
+                types::CreateBucketConfiguration::builder()
+                    .location_constraint(types::BucketLocationConstraint::from(region.as_ref()))
+                    .build()
+            });
+
+        match self
+            .create_bucket(|b| {
+                b.bucket(bucket)
+                    .set_create_bucket_configuration(configuration)
+            })
+            .await
+        {
+            Ok(_) => Ok(true),
+            // Another copy may have created the bucket at the same time.
+            Err(err)
+                if matches!(
+                    err.as_service_error(),
+                    Some(operation::create_bucket::CreateBucketError::BucketAlreadyOwnedByYou(_))
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// List all objects under a prefix, following continuation tokens.
+    pub async fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<types::Object>> {
+        let mut objects = vec![];
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .list_objects_v2(|b| {
+                    b.bucket(bucket)
+                        .prefix(prefix)
+                        .set_continuation_token(continuation_token)
+                })
+                .await?;
+
+            objects.extend(output.contents().iter().cloned());
+
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated().unwrap_or_default() => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => return Ok(objects),
+            }
+        }
+    }
+
+    /// Whether a sums file was previously found to not exist by a client sharing the cache.
+    pub fn is_missing_sums(&self, bucket: &str, key: &str) -> bool {
+        self.metadata_cache
+            .as_ref()
+            .is_some_and(|cache| cache.is_missing_sums(bucket, key))
+    }
+
+    /// Record that a sums file does not exist, so that it is not requested again until written.
+    pub fn insert_missing_sums(&self, bucket: &str, key: &str) {
+        if let Some(cache) = &self.metadata_cache {
+            cache.insert_missing_sums(bucket, key);
+        }
+    }
+
+    /// Invalidate cached metadata for an object whose state may have changed outside of this
+    /// client, such as an archived object being restored.
+    pub fn invalidate(&self, bucket: &str, key: &str) {
+        if let Some(cache) = &self.metadata_cache {
+            cache.invalidate(bucket, key);
+        }
+    }
+}
+
+/// Options for connecting to SFTP locations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SftpOptions {
+    identity_file: Option<PathBuf>,
+    known_hosts: SftpKnownHosts,
+}
+
+impl SftpOptions {
+    /// Create SFTP options.
+    pub fn new(identity_file: Option<PathBuf>, known_hosts: SftpKnownHosts) -> Self {
+        Self {
+            identity_file,
+            known_hosts,
+        }
+    }
+
+    /// The private key used to authenticate, if not using the defaults of the `ssh` client.
+    pub fn identity_file(&self) -> Option<&Path> {
+        self.identity_file.as_deref()
+    }
+
+    /// How to check the host keys of servers.
+    pub fn known_hosts(&self) -> SftpKnownHosts {
+        self.known_hosts
+    }
+}
+
+/// Options for accessing GDS locations using the ICA API.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct GdsOptions {
+    base_url: Option<String>,
+    access_token: Option<String>,
+}
+
+impl GdsOptions {
+    /// Create GDS options.
+    pub fn new(base_url: Option<String>, access_token: Option<String>) -> Self {
+        Self {
+            base_url,
+            access_token,
+        }
+    }
+
+    /// The base URL of the ICA API, if not using the default.
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// The access token used to authorize requests.
+    pub fn access_token(&self) -> Option<&str> {
+        self.access_token.as_deref()
+    }
+}
+
+/// Options for accessing OpenStack Swift locations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SwiftOptions {
+    storage_url: Option<String>,
+    auth_token: Option<String>,
+}
+
+impl SwiftOptions {
+    /// Create Swift options.
+    pub fn new(storage_url: Option<String>, auth_token: Option<String>) -> Self {
+        Self {
+            storage_url,
+            auth_token,
+        }
+    }
+
+    /// The storage URL of the account, e.g. `https://swift.example.org/v1/AUTH_project`.
+    pub fn storage_url(&self) -> Option<&str> {
+        self.storage_url.as_deref()
+    }
+
+    /// The auth token used to authorize requests.
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+}
+
+/// Options for accessing WebDAV locations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct WebDavOptions {
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl WebDavOptions {
+    /// Create WebDAV options.
+    pub fn new(user: Option<String>, password: Option<String>) -> Self {
+        Self { user, password }
+    }
+
+    /// The user used to authorize requests.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The password used to authorize requests, such as a Nextcloud app password.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+/// An additional header or query parameter sent with requests to read objects. This is useful
+/// for endpoints that transform data on read, such as S3 Object Lambda access points. Headers
+/// are parsed from `<name>:<value>` and query parameters from `?<name>=<value>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestParameter {
+    /// A request header.
+    Header(String, String),
+    /// A query parameter appended to the request uri.
+    Query(String, String),
+}
+
+impl RequestParameter {
+    /// Add the parameter to an outgoing request.
+    pub fn apply(&self, request: &mut Request) {
+        match self {
+            RequestParameter::Header(name, value) => {
+                // Headers are validated when parsing so this should not fail.
+                let _ = request
+                    .headers_mut()
+                    .try_insert(name.to_string(), value.to_string());
+            }
+            RequestParameter::Query(name, value) => {
+                let uri = request.uri();
+                let separator = if uri.contains('?') { '&' } else { '?' };
+                let uri = format!(
+                    "{}{}{}={}",
+                    uri,
+                    separator,
+                    encode_query(name),
+                    encode_query(value)
+                );
+                let _ = request.set_uri(uri);
+            }
+        }
+    }
+}
+
+impl FromStr for RequestParameter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(query) = s.strip_prefix('?') {
+            let Some((name, value)) = query.split_once('=') else {
+                return Err(ParseError(format!(
+                    "invalid query parameter `{}`, expected `?<name>=<value>`",
+                    s
+                )));
+            };
+            if name.is_empty() {
+                return Err(ParseError(format!(
+                    "query parameter `{}` is missing a name",
+                    s
+                )));
+            }
+
+            return Ok(Self::Query(name.to_string(), value.to_string()));
+        }
+
+        let Some((name, value)) = s.split_once(':') else {
+            return Err(ParseError(format!(
+                "invalid header `{}`, expected `<name>:<value>`",
+                s
+            )));
+        };
+        let (name, value) = (name.trim(), value.trim());
+
+        Headers::new()
+            .try_insert(name.to_string(), value.to_string())
+            .map_err(|err| ParseError(format!("invalid header `{}`: {}", s, err)))?;
+
+        Ok(Self::Header(name.to_string(), value.to_string()))
+    }
+}
+
+impl Display for RequestParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestParameter::Header(name, value) => write!(f, "{}:{}", name, value),
+            RequestParameter::Query(name, value) => write!(f, "?{}={}", name, value),
+        }
+    }
+}
+
+/// Percent-encode a query string component, leaving only unreserved characters.
+fn encode_query(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The URL scheme of SFTP locations.
+pub const SFTP_SCHEME: &str = "sftp";
+
+/// Whether the location is an SFTP URL.
+pub fn is_sftp_url(location: &str) -> bool {
+    location
+        .strip_prefix(SFTP_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// The error returned for SFTP locations on platforms without the system `ssh` client.
+#[cfg(not(unix))]
+pub fn sftp_unsupported_error() -> Error {
+    Error::SftpError("SFTP locations are only supported on unix platforms".to_string())
+}
+
+/// Builds the copy and sums objects of SFTP locations. This is registered in the provider
+/// registry for the `sftp` scheme, and connects with the SFTP options of the client. SFTP
+/// locations are only supported on unix platforms.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SftpFactory;
+
+#[cfg(unix)]
+impl SftpFactory {
+    fn client(client: Option<&S3Client>) -> sftp::SftpClient {
+        sftp::SftpClient::new(
+            client
+                .map(|client| client.sftp_options().clone())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl registry::ProviderFactory for SftpFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn copy::ObjectCopy + Send + Sync>> {
+        self.copy_with_client(None, source, destination).await
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn sums::ObjectSums + Send + Sync>> {
+        self.sums_with_client(None, url).await
+    }
+
+    #[cfg(unix)]
+    async fn copy_with_client(
+        &self,
+        client: Option<&S3Client>,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn copy::ObjectCopy + Send + Sync>> {
+        let mut builder = copy::sftp::SftpBuilder::default().with_client(Self::client(client));
+        if let Some(source) = source {
+            let (host, path) = Provider::try_from(source)?.into_sftp()?;
+            builder = builder.with_source(&host, &path);
+        }
+        if let Some(destination) = destination {
+            let (host, path) = Provider::try_from(destination)?.into_sftp()?;
+            builder = builder.with_destination(&host, &path);
+        }
+
+        Ok(Box::new(builder.build()))
+    }
+
+    #[cfg(not(unix))]
+    async fn copy_with_client(
+        &self,
+        _client: Option<&S3Client>,
+        _source: Option<&str>,
+        _destination: Option<&str>,
+    ) -> Result<Box<dyn copy::ObjectCopy + Send + Sync>> {
+        Err(sftp_unsupported_error())
+    }
+
+    #[cfg(unix)]
+    async fn sums_with_client(
+        &self,
+        client: Option<&S3Client>,
+        url: &str,
+    ) -> Result<Box<dyn sums::ObjectSums + Send + Sync>> {
+        let (host, path) = Provider::try_from(url)?.into_sftp()?;
+        Ok(Box::new(
+            sums::sftp::SftpBuilder::default()
+                .with_host(host)
+                .with_path(path)
+                .with_client(Self::client(client))
+                .build()?,
+        ))
+    }
+
+    #[cfg(not(unix))]
+    async fn sums_with_client(
+        &self,
+        _client: Option<&S3Client>,
+        _url: &str,
+    ) -> Result<Box<dyn sums::ObjectSums + Send + Sync>> {
+        Err(sftp_unsupported_error())
+    }
 }
 
 /// The type of provider for the object.
@@ -282,6 +1149,14 @@ impl S3Client {
 pub enum Provider {
     File { file: String },
     S3 { bucket: String, key: String },
+    Gcs { bucket: String, key: String },
+    Azure { container: String, blob: String },
+    Http { url: String },
+    Sftp { host: String, path: String },
+    Gds { volume: String, path: String },
+    Swift { container: String, object: String },
+    WebDav { url: String },
+    Custom { scheme: String, url: String },
 }
 
 impl Provider {
@@ -290,6 +1165,31 @@ impl Provider {
         format!("s3://{}/{}", bucket, key)
     }
 
+    /// Format a GCS url.
+    pub fn format_gcs(bucket: &str, key: &str) -> String {
+        format!("{}://{}/{}", gcs::GCS_SCHEME, bucket, key)
+    }
+
+    /// Format an Azure url.
+    pub fn format_azure(container: &str, blob: &str) -> String {
+        format!("{}://{}/{}", azure::AZURE_SCHEME, container, blob)
+    }
+
+    /// Format an SFTP url, where the host can include a user and port.
+    pub fn format_sftp(host: &str, path: &str) -> String {
+        format!("{}://{}{}", SFTP_SCHEME, host, path)
+    }
+
+    /// Format a GDS url.
+    pub fn format_gds(volume: &str, path: &str) -> String {
+        format!("{}://{}{}", gds::GDS_SCHEME, volume, path)
+    }
+
+    /// Format a Swift url.
+    pub fn format_swift(container: &str, object: &str) -> String {
+        format!("{}://{}/{}", swift::SWIFT_SCHEME, container, object)
+    }
+
     /// Format a file url.
     pub fn format_file(file: &str) -> String {
         format!("file://{}", file)
@@ -300,6 +1200,14 @@ impl Provider {
         match self {
             Provider::File { file } => Self::format_file(file),
             Provider::S3 { bucket, key } => Self::format_s3(bucket, key),
+            Provider::Gcs { bucket, key } => Self::format_gcs(bucket, key),
+            Provider::Azure { container, blob } => Self::format_azure(container, blob),
+            Provider::Http { url } => url.to_string(),
+            Provider::Sftp { host, path } => Self::format_sftp(host, path),
+            Provider::Gds { volume, path } => Self::format_gds(volume, path),
+            Provider::Swift { container, object } => Self::format_swift(container, object),
+            Provider::WebDav { url } => url.to_string(),
+            Provider::Custom { url, .. } => url.to_string(),
         }
     }
 
@@ -309,6 +1217,26 @@ impl Provider {
             return Err(ParseError(format!("{} is not an S3 url", s)));
         };
 
+        let (bucket, key) = Self::split_bucket_key(s)?;
+        Ok(Self::S3 { bucket, key })
+    }
+
+    /// Parse from a GCS url, e.g. `gs://bucket/object`. GCS objects are accessed through the
+    /// S3-compatible XML API, so they use an S3 client for the GCS endpoint.
+    pub fn parse_gcs_url(s: &str) -> Result<Self> {
+        let Some(s) = s
+            .strip_prefix(gcs::GCS_SCHEME)
+            .and_then(|s| s.strip_prefix("://"))
+        else {
+            return Err(ParseError(format!("{} is not a GCS url", s)));
+        };
+
+        let (bucket, key) = Self::split_bucket_key(s)?;
+        Ok(Self::Gcs { bucket, key })
+    }
+
+    /// Split the `<bucket>/<key>` part of an S3 or GCS url.
+    fn split_bucket_key(s: &str) -> Result<(String, String)> {
         let split = s.split_once("/");
         let Some((bucket, key)) = split else {
             return Err(ParseError(format!("failed to parse {}", s)));
@@ -321,20 +1249,191 @@ impl Provider {
             return Err(ParseError(format!("{} is missing a key", s)));
         }
 
-        Ok(Self::S3 {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-        })
+        Ok((bucket.to_string(), key.to_string()))
     }
 
-    /// Convert the provider into an S3 bucket and key.
+    /// Convert the provider into an S3 bucket and key. GCS locations are accessed through the
+    /// S3 API, so they are converted too.
     pub fn into_s3(self) -> Result<(String, String)> {
         match self {
-            Provider::S3 { bucket, key } => Ok((bucket, key)),
+            Provider::S3 { bucket, key } | Provider::Gcs { bucket, key } => Ok((bucket, key)),
             _ => Err(ParseError("not an S3 provider".to_string())),
         }
     }
 
+    /// Parse from an Azure url, e.g. `az://container/blob`.
+    pub fn parse_azure_url(s: &str) -> Result<Self> {
+        let Some((container, blob)) = s
+            .strip_prefix(azure::AZURE_SCHEME)
+            .and_then(|s| s.strip_prefix("://"))
+            .and_then(|s| s.split_once("/"))
+        else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if container.is_empty() {
+            return Err(ParseError(format!("{} is missing a container", s)));
+        }
+        if blob.is_empty() {
+            return Err(ParseError(format!("{} is missing a blob", s)));
+        }
+
+        Ok(Self::Azure {
+            container: container.to_string(),
+            blob: blob.to_string(),
+        })
+    }
+
+    /// Convert the provider into an Azure container and blob.
+    pub fn into_azure(self) -> Result<(String, String)> {
+        match self {
+            Provider::Azure { container, blob } => Ok((container, blob)),
+            _ => Err(ParseError("not an Azure provider".to_string())),
+        }
+    }
+
+    /// Parse from an HTTP or HTTPS url, e.g. `https://example.com/genome.fa`.
+    pub fn parse_http_url(s: &str) -> Result<Self> {
+        let Some((_, path)) = s.split_once("://") else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if path.split('/').next().is_none_or(str::is_empty) {
+            return Err(ParseError(format!("{} is missing a host", s)));
+        }
+
+        Ok(Self::Http { url: s.to_string() })
+    }
+
+    /// Convert the provider into an HTTP url.
+    pub fn into_http(self) -> Result<String> {
+        match self {
+            Provider::Http { url } => Ok(url),
+            _ => Err(ParseError("not an HTTP provider".to_string())),
+        }
+    }
+
+    /// Parse from an SFTP url, e.g. `sftp://user@host:2222/path/to/file`. The path is absolute.
+    pub fn parse_sftp_url(s: &str) -> Result<Self> {
+        let Some((host, path)) = s
+            .strip_prefix(SFTP_SCHEME)
+            .and_then(|s| s.strip_prefix("://"))
+            .and_then(|s| s.find('/').map(|index| s.split_at(index)))
+        else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if host.is_empty() {
+            return Err(ParseError(format!("{} is missing a host", s)));
+        }
+        if path.len() <= 1 || path.ends_with('/') {
+            return Err(ParseError(format!("{} is missing a file", s)));
+        }
+
+        Ok(Self::Sftp {
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// Convert the provider into an SFTP host and path.
+    pub fn into_sftp(self) -> Result<(String, String)> {
+        match self {
+            Provider::Sftp { host, path } => Ok((host, path)),
+            _ => Err(ParseError("not an SFTP provider".to_string())),
+        }
+    }
+
+    /// Parse from a GDS url, e.g. `gds://volume/path/to/file`. The path is absolute.
+    pub fn parse_gds_url(s: &str) -> Result<Self> {
+        let Some((volume, path)) = s
+            .strip_prefix(gds::GDS_SCHEME)
+            .and_then(|s| s.strip_prefix("://"))
+            .and_then(|s| s.find('/').map(|index| s.split_at(index)))
+        else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if volume.is_empty() {
+            return Err(ParseError(format!("{} is missing a volume", s)));
+        }
+        if path.len() <= 1 || path.ends_with('/') {
+            return Err(ParseError(format!("{} is missing a file", s)));
+        }
+
+        Ok(Self::Gds {
+            volume: volume.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// Convert the provider into a GDS volume and path.
+    pub fn into_gds(self) -> Result<(String, String)> {
+        match self {
+            Provider::Gds { volume, path } => Ok((volume, path)),
+            _ => Err(ParseError("not a GDS provider".to_string())),
+        }
+    }
+
+    /// Parse from a Swift url, e.g. `swift://container/path/to/object`.
+    pub fn parse_swift_url(s: &str) -> Result<Self> {
+        let Some((container, object)) = s
+            .strip_prefix(swift::SWIFT_SCHEME)
+            .and_then(|s| s.strip_prefix("://"))
+            .and_then(|s| s.split_once("/"))
+        else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if container.is_empty() {
+            return Err(ParseError(format!("{} is missing a container", s)));
+        }
+        if object.is_empty() {
+            return Err(ParseError(format!("{} is missing an object", s)));
+        }
+
+        Ok(Self::Swift {
+            container: container.to_string(),
+            object: object.to_string(),
+        })
+    }
+
+    /// Convert the provider into a Swift container and object.
+    pub fn into_swift(self) -> Result<(String, String)> {
+        match self {
+            Provider::Swift { container, object } => Ok((container, object)),
+            _ => Err(ParseError("not a Swift provider".to_string())),
+        }
+    }
+
+    /// Parse from a WebDAV url, e.g. `davs://cloud.example.org/remote.php/dav/files/user/file`.
+    pub fn parse_webdav_url(s: &str) -> Result<Self> {
+        let Some((host, path)) = webdav::WEBDAV_SCHEMES
+            .iter()
+            .find_map(|scheme| s.strip_prefix(scheme)?.strip_prefix("://"))
+            .and_then(|s| s.split_once("/"))
+        else {
+            return Err(ParseError(format!("failed to parse {}", s)));
+        };
+
+        if host.is_empty() {
+            return Err(ParseError(format!("{} is missing a host", s)));
+        }
+        if path.is_empty() || path.ends_with('/') {
+            return Err(ParseError(format!("{} is missing a file", s)));
+        }
+
+        Ok(Self::WebDav { url: s.to_string() })
+    }
+
+    /// Convert the provider into a WebDAV url.
+    pub fn into_webdav(self) -> Result<String> {
+        match self {
+            Provider::WebDav { url } => Ok(url),
+            _ => Err(ParseError("not a WebDAV provider".to_string())),
+        }
+    }
+
     /// Parse from a string a file name which can optionally be prefixed with `file://`
     pub fn parse_file_url(s: &str) -> Self {
         Self::File {
@@ -355,15 +1454,100 @@ impl Provider {
         matches!(self, Provider::File { .. })
     }
 
-    /// Check if the provider is an S3 provider.
+    /// Check if the provider is accessed through the S3 API, which includes GCS.
     pub fn is_s3(&self) -> bool {
-        matches!(self, Provider::S3 { .. })
+        matches!(self, Provider::S3 { .. } | Provider::Gcs { .. })
+    }
+
+    /// Check if the provider is a GCS provider.
+    pub fn is_gcs(&self) -> bool {
+        matches!(self, Provider::Gcs { .. })
+    }
+
+    /// Check if the provider is an Azure provider.
+    pub fn is_azure(&self) -> bool {
+        matches!(self, Provider::Azure { .. })
+    }
+
+    /// Check if the provider is an HTTP provider.
+    pub fn is_http(&self) -> bool {
+        matches!(self, Provider::Http { .. })
+    }
+
+    /// Check if the provider is an SFTP provider.
+    pub fn is_sftp(&self) -> bool {
+        matches!(self, Provider::Sftp { .. })
+    }
+
+    /// Check if the provider is a GDS provider.
+    pub fn is_gds(&self) -> bool {
+        matches!(self, Provider::Gds { .. })
+    }
+
+    /// Check if the provider is a Swift provider.
+    pub fn is_swift(&self) -> bool {
+        matches!(self, Provider::Swift { .. })
+    }
+
+    /// Check if the provider is a WebDAV provider.
+    pub fn is_webdav(&self) -> bool {
+        matches!(self, Provider::WebDav { .. })
+    }
+
+    /// Convert the provider into the scheme and url of a custom provider.
+    pub fn into_custom(self) -> Result<(String, String)> {
+        match self {
+            Provider::Custom { scheme, url } => Ok((scheme, url)),
+            _ => Err(ParseError("not a custom provider".to_string())),
+        }
+    }
+
+    /// The scheme of the factory in the provider registry that builds this provider, which is
+    /// set for custom providers and for built-in providers other than files, S3 and GCS.
+    pub fn registered_scheme(&self) -> Option<&str> {
+        match self {
+            Provider::Azure { .. } => Some(azure::AZURE_SCHEME),
+            Provider::Http { url } => http::HTTP_SCHEMES
+                .into_iter()
+                .find(|scheme| url.starts_with(&format!("{}://", scheme))),
+            Provider::Sftp { .. } => Some(SFTP_SCHEME),
+            Provider::Gds { .. } => Some(gds::GDS_SCHEME),
+            Provider::Swift { .. } => Some(swift::SWIFT_SCHEME),
+            Provider::WebDav { url } => webdav::WEBDAV_SCHEMES
+                .into_iter()
+                .find(|scheme| url.starts_with(&format!("{}://", scheme))),
+            Provider::Custom { scheme, .. } => Some(scheme),
+            _ => None,
+        }
+    }
+
+    /// Convert the provider into the scheme and url that its factory in the provider registry
+    /// builds it from.
+    pub fn into_registered(self) -> Result<(String, String)> {
+        let scheme = self
+            .registered_scheme()
+            .ok_or_else(|| ParseError("not a registered provider".to_string()))?
+            .to_string();
+        Ok((scheme, self.format()))
+    }
+
+    /// Check if the provider is a custom provider from the provider registry.
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Provider::Custom { .. })
     }
 
     /// Check whether two providers refer to the same object.
     pub fn is_same_location(&self, other: &Provider) -> bool {
         match (self, other) {
-            (source @ Provider::S3 { .. }, destination @ Provider::S3 { .. }) => {
+            (source @ Provider::S3 { .. }, destination @ Provider::S3 { .. })
+            | (source @ Provider::Gcs { .. }, destination @ Provider::Gcs { .. })
+            | (source @ Provider::Azure { .. }, destination @ Provider::Azure { .. })
+            | (source @ Provider::Http { .. }, destination @ Provider::Http { .. })
+            | (source @ Provider::Sftp { .. }, destination @ Provider::Sftp { .. })
+            | (source @ Provider::Gds { .. }, destination @ Provider::Gds { .. })
+            | (source @ Provider::Swift { .. }, destination @ Provider::Swift { .. })
+            | (source @ Provider::WebDav { .. }, destination @ Provider::WebDav { .. })
+            | (source @ Provider::Custom { .. }, destination @ Provider::Custom { .. }) => {
                 source == destination
             }
             (Provider::File { file: self_file }, Provider::File { file: other_file }) => {
@@ -386,6 +1570,32 @@ impl TryFrom<&str> for Provider {
     fn try_from(url: &str) -> Result<Self> {
         if url.starts_with("s3://") {
             Self::parse_s3_url(url)
+        } else if azure::is_azure_url(url) {
+            Self::parse_azure_url(url)
+        } else if http::is_http_url(url) {
+            Self::parse_http_url(url)
+        } else if is_sftp_url(url) {
+            Self::parse_sftp_url(url)
+        } else if gds::is_gds_url(url) {
+            Self::parse_gds_url(url)
+        } else if swift::is_swift_url(url) {
+            Self::parse_swift_url(url)
+        } else if webdav::is_webdav_url(url) {
+            Self::parse_webdav_url(url)
+        } else if gcs::is_gcs_url(url) {
+            Self::parse_gcs_url(url)
+        } else if drs::is_drs_url(url) {
+            // DRS URIs are resolved into their access URL before reaching here.
+            Err(ParseError(format!(
+                "{} is a DRS uri, which is only supported as the source of `copy` or the input \
+                of `generate`",
+                url
+            )))
+        } else if let Some(scheme) = registry::ProviderRegistry::scheme_of(url) {
+            Ok(Self::Custom {
+                scheme,
+                url: url.to_string(),
+            })
         } else {
             Ok(Self::parse_file_url(url))
         }
@@ -468,7 +1678,64 @@ impl SecretsManagerCredentials {
     }
 }
 
+/// How long credentials from Secrets Manager are used before the secret is fetched again, so that
+/// long transfers pick up rotated credentials.
+const SECRET_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// A credentials provider that fetches credentials from a Secrets Manager secret. The credentials
+/// expire after a refresh interval so that the secret is periodically fetched again.
+#[derive(Debug)]
+pub struct SecretsManagerProvider {
+    secret_id: String,
+    initial: Mutex<Option<aws_credential_types::Credentials>>,
+}
+
+impl SecretsManagerProvider {
+    /// Create the provider by fetching the secret, which checks that the secret is valid
+    /// before any requests are made.
+    pub async fn new(secret_id: &str) -> Result<Self> {
+        let initial = Self::fetch(secret_id).await?;
+        Ok(Self {
+            secret_id: secret_id.to_string(),
+            initial: Mutex::new(Some(initial)),
+        })
+    }
+
+    /// Fetch the secret and set the refresh expiry.
+    async fn fetch(secret_id: &str) -> Result<aws_credential_types::Credentials> {
+        let mut credentials = SecretsManagerCredentials::new(secret_id)
+            .await?
+            .into_credentials();
+        *credentials.expiry_mut() = Some(SystemTime::now() + SECRET_REFRESH_INTERVAL);
+        Ok(credentials)
+    }
+
+    async fn load_credentials(&self) -> provider::Result {
+        let initial = self
+            .initial
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        match initial {
+            Some(initial) => Ok(initial),
+            None => Self::fetch(&self.secret_id)
+                .await
+                .map_err(CredentialsError::provider_error),
+        }
+    }
+}
+
+impl ProvideCredentials for SecretsManagerProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(self.load_credentials())
+    }
+}
+
 /// Credential overrides from CLI args or environment variables.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct CredentialOverrides {
     access_key_id: Option<String>,
     secret_access_key: Option<String>,
@@ -496,6 +1763,11 @@ impl CredentialOverrides {
             || self.session_token.is_some()
     }
 
+    /// Returns true if both the access key id and secret access key are overridden.
+    pub fn has_keys(&self) -> bool {
+        self.access_key_id.is_some() && self.secret_access_key.is_some()
+    }
+
     /// Merge overrides with base credentials. Each override takes precedence over the corresponding
     /// field in the base credentials.
     pub fn merge_with(
@@ -524,26 +1796,158 @@ impl CredentialOverrides {
             .as_deref()
             .or_else(|| base.and_then(|base| base.session_token()));
 
-        // There's no need to preserve base.expiry() as overrides imply no expiry and control given
-        // to user supplied credentials.
-        Ok(construct_credentials(
-            access_key_id,
-            secret_access_key,
-            session_token,
-        ))
+        let mut credentials =
+            construct_credentials(access_key_id, secret_access_key, session_token);
+        // An overridden session token implies no expiry and control given to user supplied
+        // credentials. Otherwise, keep the base expiry so that the credentials are refreshed.
+        if self.session_token.is_none() {
+            *credentials.expiry_mut() = base.and_then(|base| base.expiry());
+        }
+
+        Ok(credentials)
+    }
+}
+
+/// A credentials provider that applies overrides to the credentials of a base provider. The base
+/// provider is resolved again whenever credentials are requested, so that refreshed base
+/// credentials are used for long transfers.
+struct OverrideProvider {
+    overrides: CredentialOverrides,
+    base: Option<SharedCredentialsProvider>,
+}
+
+impl OverrideProvider {
+    async fn load_credentials(&self) -> provider::Result {
+        let base = match &self.base {
+            Some(base) => match base.provide_credentials().await {
+                Ok(base) => Some(base),
+                // The base provider is only needed for the session token if both keys are
+                // overridden.
+                Err(_) if self.overrides.has_keys() => None,
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+
+        self.overrides
+            .merge_with(base.as_ref())
+            .map_err(CredentialsError::provider_error)
+    }
+}
+
+impl Debug for OverrideProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideProvider")
+            .field("base", &self.base)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProvideCredentials for OverrideProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(self.load_credentials())
+    }
+}
+
+/// Read a credential from a file, removing surrounding whitespace such as a trailing newline.
+pub fn read_credential_file(path: &str) -> Result<String> {
+    let credential = std::fs::read_to_string(path)?.trim().to_string();
+    if credential.is_empty() {
+        return Err(ParseError(format!("credential file `{}` is empty", path)));
+    }
+
+    Ok(credential)
+}
+
+/// Get the AWS partition of a region. Server-side copies are only possible between buckets in
+/// the same partition.
+pub fn region_partition(region: &str) -> &'static str {
+    if region.starts_with("cn-") {
+        "aws-cn"
+    } else if region.starts_with("us-gov-") {
+        "aws-us-gov"
+    } else if region.starts_with("us-isob-") {
+        "aws-iso-b"
+    } else if region.starts_with("us-iso-") {
+        "aws-iso"
+    } else if region.starts_with("eu-isoe-") {
+        "aws-iso-e"
+    } else if region.starts_with("us-isof-") {
+        "aws-iso-f"
+    } else {
+        "aws"
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::io::{CredentialOverrides, Provider, SecretsManagerCredentials};
+    use crate::cli::CredentialProvider;
+    use crate::io::{
+        CredentialOverrides, OverrideProvider, Provider, RequestParameter, S3Client,
+        SecretsManagerCredentials, read_credential_file, region_partition,
+    };
     use anyhow::Result;
+    use aws_config::Region;
     use aws_credential_types::Credentials;
+    use aws_credential_types::provider::error::CredentialsError;
+    use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider, future};
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::create_bucket::CreateBucketOutput;
+    use aws_sdk_s3::operation::head_bucket::{HeadBucketError, HeadBucketOutput};
+    use aws_sdk_s3::types::BucketLocationConstraint;
+    use aws_sdk_s3::types::error::NotFound;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use aws_smithy_runtime_api::http::Request;
+    use aws_smithy_types::body::SdkBody;
     use serde_json::json;
     use std::env;
+    use std::sync::Arc;
     use std::time::{Duration, SystemTime};
     use tempfile::{NamedTempFile, tempdir};
 
+    #[test]
+    fn test_parse_request_parameter() -> Result<()> {
+        assert_eq!(
+            "Accept: text/plain".parse::<RequestParameter>()?,
+            RequestParameter::Header("Accept".to_string(), "text/plain".to_string())
+        );
+        assert_eq!(
+            "?transform=gzip".parse::<RequestParameter>()?,
+            RequestParameter::Query("transform".to_string(), "gzip".to_string())
+        );
+
+        assert!("Accept".parse::<RequestParameter>().is_err());
+        assert!("Bad Header:value".parse::<RequestParameter>().is_err());
+        assert!("?transform".parse::<RequestParameter>().is_err());
+        assert!("?=gzip".parse::<RequestParameter>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_request_parameter() -> Result<()> {
+        let mut request = Request::new(SdkBody::empty());
+        request.set_uri("https://bucket.s3.amazonaws.com/key?x-id=GetObject")?;
+
+        "Accept: text/plain"
+            .parse::<RequestParameter>()?
+            .apply(&mut request);
+        "?filter=a b&c"
+            .parse::<RequestParameter>()?
+            .apply(&mut request);
+
+        assert_eq!(request.headers().get("Accept"), Some("text/plain"));
+        assert_eq!(
+            request.uri(),
+            "https://bucket.s3.amazonaws.com/key?x-id=GetObject&filter=a%20b%26c"
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     pub async fn test_parse_url() -> Result<()> {
         let s3 = provider_s3("s3://bucket/key")?;
@@ -568,6 +1972,71 @@ mod tests {
         let s3 = provider_s3("s3://");
         assert!(s3.is_err());
 
+        let gcs = Provider::try_from("gs://bucket/dir/object")?;
+        assert!(gcs.is_s3() && gcs.is_gcs());
+        assert_eq!(gcs.format(), "gs://bucket/dir/object");
+        assert_eq!(
+            gcs.into_s3()?,
+            ("bucket".to_string(), "dir/object".to_string())
+        );
+        assert!(Provider::try_from("gs://bucket/").is_err());
+        assert!(Provider::try_from("drs://drs.example.org/object").is_err());
+
+        let azure = Provider::try_from("az://container/dir/blob")?.into_azure()?;
+        assert_eq!(azure, ("container".to_string(), "dir/blob".to_string()));
+        assert!(Provider::try_from("az://container/").is_err());
+        assert!(Provider::try_from("az://container").is_err());
+
+        let http = Provider::try_from("https://example.com/genome.fa")?;
+        assert_eq!(http.format(), "https://example.com/genome.fa");
+        assert_eq!(http.into_http()?, "https://example.com/genome.fa");
+        assert!(Provider::try_from("https:///genome.fa").is_err());
+
+        let sftp = Provider::try_from("sftp://user@host:2222/data/run/file.fastq.gz")?;
+        assert_eq!(
+            sftp.format(),
+            "sftp://user@host:2222/data/run/file.fastq.gz"
+        );
+        assert_eq!(
+            sftp.into_sftp()?,
+            (
+                "user@host:2222".to_string(),
+                "/data/run/file.fastq.gz".to_string()
+            )
+        );
+        assert!(Provider::try_from("sftp://host").is_err());
+        assert!(Provider::try_from("sftp://host/").is_err());
+        assert!(Provider::try_from("sftp:///file").is_err());
+
+        let gds = Provider::try_from("gds://run/data/reads.bam")?;
+        assert_eq!(gds.format(), "gds://run/data/reads.bam");
+        assert_eq!(
+            gds.into_gds()?,
+            ("run".to_string(), "/data/reads.bam".to_string())
+        );
+        assert!(Provider::try_from("gds://run").is_err());
+        assert!(Provider::try_from("gds://run/data/").is_err());
+        assert!(Provider::try_from("gds:///reads.bam").is_err());
+
+        let swift = Provider::try_from("swift://container/dir/object")?;
+        assert_eq!(swift.format(), "swift://container/dir/object");
+        assert_eq!(
+            swift.into_swift()?,
+            ("container".to_string(), "dir/object".to_string())
+        );
+        assert!(Provider::try_from("swift://container").is_err());
+        assert!(Provider::try_from("swift://container/").is_err());
+
+        let webdav = Provider::try_from("davs://cloud.example.org/remote.php/dav/files/user/a")?;
+        assert_eq!(
+            webdav.format(),
+            "davs://cloud.example.org/remote.php/dav/files/user/a"
+        );
+        assert!(webdav.is_webdav());
+        assert!(Provider::try_from("dav://localhost:8080/file")?.is_webdav());
+        assert!(Provider::try_from("davs://cloud.example.org").is_err());
+        assert!(Provider::try_from("davs://cloud.example.org/dir/").is_err());
+
         Ok(())
     }
 
@@ -580,7 +2049,7 @@ mod tests {
         assert_eq!(merged.access_key_id(), "access_key");
         assert_eq!(merged.secret_access_key(), "secret_access_key");
         assert_eq!(merged.session_token(), Some("session_token"));
-        assert!(merged.expiry().is_none());
+        assert_eq!(merged.expiry(), base.expiry());
 
         let overrides = CredentialOverrides::new(
             Some("override_access_key".to_string()),
@@ -603,7 +2072,7 @@ mod tests {
         assert_eq!(merged.access_key_id(), "override_access_key");
         assert_eq!(merged.secret_access_key(), "secret_access_key");
         assert_eq!(merged.session_token(), Some("session_token"));
-        assert!(merged.expiry().is_none());
+        assert_eq!(merged.expiry(), base.expiry());
 
         let overrides = CredentialOverrides::new(
             Some("override_access_key".to_string()),
@@ -626,6 +2095,67 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn read_credential_from_file() -> Result<()> {
+        let file = NamedTempFile::new()?;
+        std::fs::write(file.path(), "access_key\n")?;
+        let path = file.path().to_string_lossy();
+
+        assert_eq!(read_credential_file(&path)?, "access_key");
+
+        std::fs::write(file.path(), "\n")?;
+        assert!(read_credential_file(&path).is_err());
+        assert!(read_credential_file("does-not-exist").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn static_provider_requires_overrides() {
+        let result = S3Client::create_s3_client(
+            &CredentialProvider::Static,
+            None,
+            Some("us-east-1"),
+            None,
+            None,
+            None,
+            CredentialOverrides::new(None, None, None),
+            false,
+            false,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn region_partitions() {
+        assert_eq!(region_partition("us-east-1"), "aws");
+        assert_eq!(region_partition("ap-southeast-2"), "aws");
+        assert_eq!(region_partition("cn-north-1"), "aws-cn");
+        assert_eq!(region_partition("us-gov-west-1"), "aws-us-gov");
+        assert_eq!(region_partition("us-iso-east-1"), "aws-iso");
+        assert_eq!(region_partition("us-isob-east-1"), "aws-iso-b");
+    }
+
+    #[tokio::test]
+    async fn process_provider_requires_command() {
+        let result = S3Client::create_s3_client(
+            &CredentialProvider::Process,
+            None,
+            Some("us-east-1"),
+            None,
+            None,
+            None,
+            CredentialOverrides::new(None, None, None),
+            false,
+            false,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn secrets_manager_deserialize() {
         let json = json!({
@@ -668,6 +2198,41 @@ mod tests {
         assert!(SecretsManagerCredentials::deserialize_from(&json!({}).to_string()).is_err());
     }
 
+    #[derive(Debug)]
+    struct FailingProvider;
+
+    impl ProvideCredentials for FailingProvider {
+        fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+        where
+            Self: 'a,
+        {
+            future::ProvideCredentials::ready(Err(CredentialsError::not_loaded("expired")))
+        }
+    }
+
+    #[tokio::test]
+    async fn override_provider_propagates_base_errors() {
+        let provider = OverrideProvider {
+            overrides: CredentialOverrides::new(None, None, Some("session_token".to_string())),
+            base: Some(SharedCredentialsProvider::new(FailingProvider)),
+        };
+        assert!(matches!(
+            provider.load_credentials().await,
+            Err(CredentialsError::CredentialsNotLoaded(_))
+        ));
+
+        let provider = OverrideProvider {
+            overrides: CredentialOverrides::new(
+                Some("access_key".to_string()),
+                Some("secret_access_key".to_string()), // pragma: allowlist secret
+                None,
+            ),
+            base: Some(SharedCredentialsProvider::new(FailingProvider)),
+        };
+        let credentials = provider.load_credentials().await.unwrap();
+        assert_eq!(credentials.access_key_id(), "access_key");
+    }
+
     fn provider_s3(url: &str) -> Result<(String, String)> {
         Ok(Provider::try_from(url)?.into_s3()?)
     }
@@ -748,4 +2313,37 @@ mod tests {
         let destination = Provider::try_from("/tmp/file").unwrap();
         assert!(!source.is_same_location(&destination));
     }
+
+    #[tokio::test]
+    async fn create_bucket_if_missing() -> Result<()> {
+        let head = mock!(Client::head_bucket)
+            .match_requests(|req| req.bucket() == Some("missing"))
+            .then_error(|| HeadBucketError::NotFound(NotFound::builder().build()));
+        let exists = mock!(Client::head_bucket)
+            .match_requests(|req| req.bucket() == Some("exists"))
+            .then_output(|| HeadBucketOutput::builder().build());
+        let create = mock!(Client::create_bucket)
+            .match_requests(|req| {
+                req.bucket() == Some("missing")
+                    && req
+                        .create_bucket_configuration()
+                        .and_then(|config| config.location_constraint())
+                        == Some(&BucketLocationConstraint::ApSoutheast2)
+            })
+            .then_output(|| CreateBucketOutput::builder().build());
+
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&head, &exists, &create],
+            |config| config.region(Region::new("ap-southeast-2"))
+        );
+        let client = S3Client::new(Arc::new(client), false, false);
+
+        assert!(client.create_bucket_if_missing("missing").await?);
+        assert!(!client.create_bucket_if_missing("exists").await?);
+        assert_eq!(create.num_calls(), 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,325 @@
+//! A pool of S3 clients shared between the source and destination of a run.
+//!
+
+use crate::cli::CredentialProvider;
+use crate::error::Result;
+use crate::io::connector::ConnectorHttpClient;
+use crate::io::fault::{FaultInjection, FaultInterceptor};
+use crate::io::gcs::{GoogHashInterceptor, is_gcs_endpoint};
+use crate::io::trace::ApiTrace;
+use crate::io::{CredentialOverrides, S3Client};
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::interceptors::BeforeTransmitInterceptorContextMut;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The header which confirms that the requester is charged for a request.
+const REQUEST_PAYER_HEADER: &str = "x-amz-request-payer";
+
+/// The header with the account id that must own the bucket of a request.
+const EXPECTED_BUCKET_OWNER_HEADER: &str = "x-amz-expected-bucket-owner";
+
+/// The header with the account id that must own the source bucket of a copy.
+const SOURCE_EXPECTED_BUCKET_OWNER_HEADER: &str = "x-amz-source-expected-bucket-owner";
+
+/// The header with the source of a copy.
+const COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+
+/// The options that determine how an S3 client is constructed. Clients created with equal keys
+/// are interchangeable.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ClientKey {
+    pub(crate) provider: CredentialProvider,
+    pub(crate) profile: Option<String>,
+    pub(crate) region: Option<String>,
+    pub(crate) endpoint_url: Option<String>,
+    pub(crate) secret: Option<String>,
+    pub(crate) credential_process: Option<String>,
+    pub(crate) overrides: CredentialOverrides,
+    pub(crate) force_path_style: bool,
+    pub(crate) no_request_checksum: bool,
+    pub(crate) requester_pays: bool,
+    pub(crate) expected_bucket_owner: Option<String>,
+    pub(crate) source_expected_bucket_owner: Option<String>,
+    pub(crate) fault_injection: Option<FaultInjection>,
+}
+
+/// A pool of S3 clients keyed by their credentials, region and endpoint. Requesting a client
+/// with the same options as an existing client returns the existing client, so its resolved
+/// credentials and open connections are reused rather than repeating credential resolution
+/// and TLS handshakes. Cloning the pool shares the underlying clients.
+#[derive(Clone, Default)]
+pub struct ClientPool {
+    clients: Arc<Mutex<HashMap<ClientKey, Arc<Client>>>>,
+    trace: Option<ApiTrace>,
+    http_client: Option<ConnectorHttpClient>,
+}
+
+impl ClientPool {
+    /// Create a new empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record or replay the API calls of every client in the pool.
+    pub fn set_trace(mut self, trace: Option<ApiTrace>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Send the requests of every client in the pool through an HTTP client with custom
+    /// connector options.
+    pub fn set_http_client(mut self, http_client: Option<ConnectorHttpClient>) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Get an existing client for the options or create a new one.
+    pub async fn get_or_create(&self, key: ClientKey) -> Result<Arc<Client>> {
+        // Hold the lock while creating the client so that concurrent requests for the same key
+        // do not create duplicate clients.
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let mut client = S3Client::create_s3_client(
+            &key.provider,
+            key.profile.as_deref(),
+            key.region.as_deref(),
+            key.endpoint_url.as_deref(),
+            key.secret.as_deref(),
+            key.credential_process.as_deref(),
+            key.overrides.clone(),
+            key.force_path_style,
+            key.no_request_checksum,
+        )
+        .await?;
+        // GCS can also be used through a remote with a custom endpoint rather than the `gs`
+        // remote, so any client for the GCS endpoint reads the GCS-native hashes.
+        if key.endpoint_url.as_deref().is_some_and(is_gcs_endpoint) {
+            client = Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .interceptor(GoogHashInterceptor)
+                    .build(),
+            );
+        }
+        if key.requester_pays {
+            client = Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .interceptor(RequesterPaysInterceptor)
+                    .build(),
+            );
+        }
+        if key.expected_bucket_owner.is_some() || key.source_expected_bucket_owner.is_some() {
+            client = Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .interceptor(ExpectedBucketOwnerInterceptor {
+                        owner: key.expected_bucket_owner.clone(),
+                        source_owner: key.source_expected_bucket_owner.clone(),
+                    })
+                    .build(),
+            );
+        }
+        if let Some(fault_injection) = key.fault_injection {
+            client = Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .interceptor(FaultInterceptor::new(fault_injection))
+                    .build(),
+            );
+        }
+        if let Some(http_client) = &self.http_client {
+            client = Client::from_conf(
+                client
+                    .config()
+                    .to_builder()
+                    .http_client(http_client.clone())
+                    .build(),
+            );
+        }
+        if let Some(trace) = &self.trace {
+            client = trace.apply(client);
+        }
+        let client = Arc::new(client);
+
+        Ok(clients.entry(key).or_insert(client).clone())
+    }
+
+    /// The number of distinct clients in the pool.
+    pub async fn len(&self) -> usize {
+        self.clients.lock().await.len()
+    }
+
+    /// Whether the pool has no clients.
+    pub async fn is_empty(&self) -> bool {
+        self.clients.lock().await.is_empty()
+    }
+}
+
+/// An interceptor which confirms that the requester is charged for every request, so that
+/// buckets with requester pays enabled can be accessed.
+#[derive(Debug, Clone)]
+pub struct RequesterPaysInterceptor;
+
+impl Intercept for RequesterPaysInterceptor {
+    fn name(&self) -> &'static str {
+        "RequesterPaysInterceptor"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> std::result::Result<(), BoxError> {
+        context
+            .request_mut()
+            .headers_mut()
+            .insert(REQUEST_PAYER_HEADER, "requester");
+        Ok(())
+    }
+}
+
+/// An interceptor which sends the account id that must own the bucket with every request, and
+/// the account id that must own the source bucket with copy requests. S3 rejects requests to
+/// buckets owned by other accounts with an access denied error.
+#[derive(Debug, Clone)]
+pub struct ExpectedBucketOwnerInterceptor {
+    owner: Option<String>,
+    source_owner: Option<String>,
+}
+
+impl Intercept for ExpectedBucketOwnerInterceptor {
+    fn name(&self) -> &'static str {
+        "ExpectedBucketOwnerInterceptor"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> std::result::Result<(), BoxError> {
+        let headers = context.request_mut().headers_mut();
+        if let Some(owner) = &self.owner {
+            headers.insert(EXPECTED_BUCKET_OWNER_HEADER, owner.clone());
+        }
+        if let Some(source_owner) = &self.source_owner
+            && headers.contains_key(COPY_SOURCE_HEADER)
+        {
+            headers.insert(SOURCE_EXPECTED_BUCKET_OWNER_HEADER, source_owner.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn reuses_clients_with_same_options() -> Result<()> {
+        let pool = ClientPool::new();
+
+        let first = pool.get_or_create(client_key("us-east-1")).await?;
+        let second = pool.get_or_create(client_key("us-east-1")).await?;
+        let other_region = pool.get_or_create(client_key("ap-southeast-2")).await?;
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &other_region));
+        assert_eq!(pool.len().await, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sends_expected_bucket_owner() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint_url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(std::sync::Mutex::new(vec![]));
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let requests = server_requests.clone();
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    while let Ok(n) = stream.read(&mut request).await
+                        && n > 0
+                    {
+                        requests
+                            .lock()
+                            .unwrap()
+                            .push(String::from_utf8_lossy(&request[..n]).to_lowercase());
+                        stream
+                            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                            .await?;
+                    }
+                    anyhow::Ok(())
+                });
+            }
+        });
+
+        let client = ClientPool::new()
+            .get_or_create(ClientKey {
+                endpoint_url: Some(endpoint_url),
+                force_path_style: true,
+                expected_bucket_owner: Some("111122223333".to_string()),
+                source_expected_bucket_owner: Some("444455556666".to_string()),
+                ..client_key("us-east-1")
+            })
+            .await?;
+        let _ = client
+            .head_object()
+            .bucket("bucket")
+            .key("key")
+            .send()
+            .await;
+        let _ = client
+            .copy_object()
+            .bucket("bucket")
+            .key("key")
+            .copy_source("source/key")
+            .send()
+            .await;
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains("x-amz-expected-bucket-owner: 111122223333"));
+        assert!(!requests[0].contains("x-amz-source-expected-bucket-owner"));
+        assert!(requests[1].contains("x-amz-expected-bucket-owner: 111122223333"));
+        assert!(requests[1].contains("x-amz-source-expected-bucket-owner: 444455556666"));
+
+        Ok(())
+    }
+
+    fn client_key(region: &str) -> ClientKey {
+        ClientKey {
+            provider: CredentialProvider::NoCredentials,
+            profile: None,
+            region: Some(region.to_string()),
+            endpoint_url: Some("http://localhost:9000".to_string()),
+            secret: None,
+            credential_process: None,
+            overrides: CredentialOverrides::new(None, None, None),
+            force_path_style: false,
+            no_request_checksum: false,
+            requester_pays: false,
+            expected_bucket_owner: None,
+            source_expected_bucket_owner: None,
+            fault_injection: None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Profiles of the known incompatibilities of S3-compatible stores. A profile enables the
+//! compatibility options that a store needs, limits the additional checksums that are sent to
+//! it, and plans multipart copies using its limits, so that copies avoid requests that the store
+//! rejects.
+//!
+
+use crate::io::copy::limits::ProviderLimits;
+use aws_sdk_s3::types::ChecksumAlgorithm;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// A profile of an S3-compatible store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum S3CompatProfile {
+    /// Cloudflare R2, which does not support `GetObjectAttributes` or CRC64NVME checksums.
+    R2,
+    /// Backblaze B2, which does not support `GetObjectAttributes` or additional checksums, and
+    /// uses decimal multipart limits.
+    B2,
+    /// MinIO, which uses path-style addressing.
+    Minio,
+    /// Any other S3-compatible store, which disables all optional S3 features.
+    Generic,
+}
+
+impl S3CompatProfile {
+    /// The limits of B2 multipart uploads, which are defined in decimal units.
+    pub const B2_LIMITS: ProviderLimits =
+        ProviderLimits::new(10000, 5000000, 5000000000, 10000000000000);
+
+    /// The limits of R2 multipart uploads, where objects are limited to 5 TiB.
+    pub const R2_LIMITS: ProviderLimits =
+        ProviderLimits::new(10000, 5242880, 5368709120, 5497558138880);
+
+    /// Whether the store requires path-style addressing.
+    pub fn force_path_style(&self) -> bool {
+        matches!(self, Self::Minio | Self::Generic)
+    }
+
+    /// Whether the store does not support `GetObjectAttributes`.
+    pub fn no_get_object_attributes(&self) -> bool {
+        matches!(self, Self::R2 | Self::B2 | Self::Generic)
+    }
+
+    /// Whether the store does not return additional checksums with `ChecksumMode::Enabled`.
+    pub fn no_checksum_mode(&self) -> bool {
+        matches!(self, Self::B2 | Self::Generic)
+    }
+
+    /// Whether the store rejects the checksums that the AWS SDK sends with uploads by default.
+    pub fn no_request_checksum(&self) -> bool {
+        matches!(self, Self::B2 | Self::Generic)
+    }
+
+    /// The multipart limits of the store.
+    pub fn limits(&self) -> ProviderLimits {
+        match self {
+            Self::R2 => Self::R2_LIMITS,
+            Self::B2 => Self::B2_LIMITS,
+            Self::Minio | Self::Generic => ProviderLimits::S3,
+        }
+    }
+
+    /// Whether the store accepts an additional checksum algorithm on uploads and copies.
+    pub fn supports_checksum(&self, algorithm: &ChecksumAlgorithm) -> bool {
+        match self {
+            Self::R2 => matches!(
+                algorithm,
+                ChecksumAlgorithm::Crc32
+                    | ChecksumAlgorithm::Crc32C
+                    | ChecksumAlgorithm::Sha1
+                    | ChecksumAlgorithm::Sha256
+            ),
+            Self::Minio => matches!(
+                algorithm,
+                ChecksumAlgorithm::Crc32
+                    | ChecksumAlgorithm::Crc32C
+                    | ChecksumAlgorithm::Crc64Nvme
+                    | ChecksumAlgorithm::Sha1
+                    | ChecksumAlgorithm::Sha256
+            ),
+            Self::B2 | Self::Generic => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profile_checksums() {
+        assert!(S3CompatProfile::R2.supports_checksum(&ChecksumAlgorithm::Sha256));
+        assert!(!S3CompatProfile::R2.supports_checksum(&ChecksumAlgorithm::Crc64Nvme));
+        assert!(S3CompatProfile::Minio.supports_checksum(&ChecksumAlgorithm::Crc64Nvme));
+        assert!(!S3CompatProfile::B2.supports_checksum(&ChecksumAlgorithm::Crc32));
+        assert!(!S3CompatProfile::Generic.supports_checksum(&ChecksumAlgorithm::Sha1));
+    }
+
+    #[test]
+    fn profile_limits() {
+        assert_eq!(S3CompatProfile::Minio.limits(), ProviderLimits::S3);
+        assert_eq!(S3CompatProfile::B2.limits().min_part_size(), 5000000);
+        assert!(
+            S3CompatProfile::R2.limits().max_object_size() < ProviderLimits::S3.max_object_size()
+        );
+    }
+}
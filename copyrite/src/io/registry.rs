@@ -0,0 +1,404 @@
+//! A registry of providers for custom URL schemes. Downstream crates can register a factory for
+//! a scheme, and URLs with that scheme are then copied and checked using the objects it builds,
+//! without needing changes to the built-in providers.
+//!
+
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::Error::ParseError;
+use crate::error::{ApiError, Result};
+use crate::io::copy::ObjectCopy;
+use crate::io::sums::{ObjectSnapshot, ObjectSums, ObjectTags};
+use crate::io::{S3Client, SFTP_SCHEME, SftpFactory, azure, drs, gcs, gds, http, swift, webdav};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::io::AsyncRead;
+
+/// The schemes of the built-in providers, which cannot be registered.
+pub const BUILT_IN_SCHEMES: [&str; 12] = [
+    "file",
+    "s3",
+    azure::AZURE_SCHEME,
+    http::HTTP_SCHEMES[0],
+    http::HTTP_SCHEMES[1],
+    SFTP_SCHEME,
+    gds::GDS_SCHEME,
+    swift::SWIFT_SCHEME,
+    webdav::WEBDAV_SCHEMES[0],
+    webdav::WEBDAV_SCHEMES[1],
+    gcs::GCS_SCHEME,
+    drs::DRS_SCHEME,
+];
+
+/// Builds the copy and sums objects for the URLs of a custom scheme.
+#[async_trait::async_trait]
+pub trait ProviderFactory: Send + Sync {
+    /// Build an object copy. Either the source or destination is set, and both are URLs with the
+    /// registered scheme.
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>>;
+
+    /// Build the object sums for a URL with the registered scheme.
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>>;
+
+    /// Build an object copy with the client that the copy is made with, which carries the
+    /// options of the built-in providers. By default, the client is not used.
+    async fn copy_with_client(
+        &self,
+        _client: Option<&S3Client>,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        self.copy(source, destination).await
+    }
+
+    /// Build the object sums for a URL with the client that the sums are read with. By
+    /// default, the client is not used.
+    async fn sums_with_client(
+        &self,
+        _client: Option<&S3Client>,
+        url: &str,
+    ) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        self.sums(url).await
+    }
+}
+
+/// The global registry of custom providers, keyed by their URL scheme. Built-in providers
+/// that are accessed through the registry are registered from the start.
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderRegistry;
+
+impl ProviderRegistry {
+    fn factories() -> &'static RwLock<HashMap<String, Arc<dyn ProviderFactory>>> {
+        static FACTORIES: OnceLock<RwLock<HashMap<String, Arc<dyn ProviderFactory>>>> =
+            OnceLock::new();
+        FACTORIES.get_or_init(|| RwLock::new(Self::built_in()))
+    }
+
+    /// The factories of the built-in providers. Every built-in scheme is registered except for
+    /// files, S3 and GCS, which share the S3 client and are built directly.
+    fn built_in() -> HashMap<String, Arc<dyn ProviderFactory>> {
+        let http: Arc<dyn ProviderFactory> = Arc::new(http::HttpFactory);
+        let webdav: Arc<dyn ProviderFactory> = Arc::new(webdav::WebDavFactory);
+        HashMap::from([
+            (
+                azure::AZURE_SCHEME.to_string(),
+                Arc::new(azure::AzureFactory) as Arc<dyn ProviderFactory>,
+            ),
+            (http::HTTP_SCHEMES[0].to_string(), http.clone()),
+            (http::HTTP_SCHEMES[1].to_string(), http),
+            (SFTP_SCHEME.to_string(), Arc::new(SftpFactory)),
+            (gds::GDS_SCHEME.to_string(), Arc::new(gds::GdsFactory)),
+            (
+                swift::SWIFT_SCHEME.to_string(),
+                Arc::new(swift::SwiftFactory),
+            ),
+            (webdav::WEBDAV_SCHEMES[0].to_string(), webdav.clone()),
+            (webdav::WEBDAV_SCHEMES[1].to_string(), webdav),
+            (
+                drs::DRS_SCHEME.to_string(),
+                Arc::new(drs::DrsFactory::default()),
+            ),
+        ])
+    }
+
+    /// Register a factory for a scheme, e.g. `mem` for `mem://` URLs. This replaces any factory
+    /// previously registered for the scheme. The schemes of built-in providers cannot be
+    /// registered.
+    pub fn register(
+        scheme: impl Into<String>,
+        factory: impl ProviderFactory + 'static,
+    ) -> Result<()> {
+        let scheme = scheme.into().to_lowercase();
+        if BUILT_IN_SCHEMES.contains(&scheme.as_str()) {
+            return Err(ParseError(format!(
+                "the {} scheme is used by a built-in provider",
+                scheme
+            )));
+        }
+        if scheme.is_empty()
+            || !scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            return Err(ParseError(format!("{} is not a valid scheme", scheme)));
+        }
+
+        Self::factories()
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(scheme, Arc::new(factory));
+        Ok(())
+    }
+
+    /// Remove the factory for a scheme, returning it if it was registered. The factories of
+    /// built-in providers cannot be removed.
+    pub fn unregister(scheme: &str) -> Option<Arc<dyn ProviderFactory>> {
+        let scheme = scheme.to_lowercase();
+        if BUILT_IN_SCHEMES.contains(&scheme.as_str()) {
+            return None;
+        }
+
+        Self::factories()
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&scheme)
+    }
+
+    /// Get the factory for a scheme.
+    pub fn get(scheme: &str) -> Option<Arc<dyn ProviderFactory>> {
+        Self::factories()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(&scheme.to_lowercase())
+            .cloned()
+    }
+
+    /// Get the registered scheme of a URL, if any.
+    pub fn scheme_of(url: &str) -> Option<String> {
+        let (scheme, _) = url.split_once("://")?;
+        Self::get(scheme).map(|_| scheme.to_lowercase())
+    }
+
+    /// Get the factory for a scheme or return an error if it is not registered.
+    pub fn try_get(scheme: &str) -> Result<Arc<dyn ProviderFactory>> {
+        Self::get(scheme).ok_or_else(|| {
+            ParseError(format!(
+                "no provider is registered for the {} scheme",
+                scheme
+            ))
+        })
+    }
+}
+
+/// Object sums built by a custom provider.
+#[derive(Clone)]
+pub struct CustomSums {
+    inner: Box<dyn ObjectSums + Send + Sync>,
+}
+
+impl CustomSums {
+    /// Create the custom sums.
+    pub fn new(inner: Box<dyn ObjectSums + Send + Sync>) -> Self {
+        Self { inner }
+    }
+
+    /// Get an existing sums file for the object, used when this is a mapped sums location.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        self.inner.clone().sums_file().await
+    }
+}
+
+impl Debug for CustomSums {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomSums")
+            .field("location", &self.inner.location())
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for CustomSums {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        self.inner.sums_file().await
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.reader().await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        self.inner.reader_from(offset).await
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.range_reader(start, end).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        self.inner.file_size().await
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        self.inner.write_sums_file(sums_file).await
+    }
+
+    async fn object_tags(&mut self) -> Result<Option<ObjectTags>> {
+        self.inner.object_tags().await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        self.inner.object_version().await
+    }
+
+    async fn object_snapshot(&mut self) -> Result<Option<ObjectSnapshot>> {
+        self.inner.object_snapshot().await
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        self.inner.write_snapshot(snapshot).await
+    }
+
+    fn location(&self) -> String {
+        self.inner.location()
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        self.inner.api_errors()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::file::Checksum;
+    use crate::checksum::standard::StandardCtx;
+    use crate::io::Provider;
+    use crate::io::copy::ObjectCopyBuilder;
+    use crate::io::copy::file::FileBuilder as CopyFileBuilder;
+    use crate::io::sums::ObjectSumsBuilder;
+    use crate::io::sums::file::FileBuilder as SumsFileBuilder;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// A provider for `<scheme>://` URLs which are files under a root directory.
+    struct RootedFactory {
+        scheme: String,
+        root: PathBuf,
+    }
+
+    impl RootedFactory {
+        fn path(&self, url: &str) -> Result<String> {
+            let path = url
+                .strip_prefix(&format!("{}://", self.scheme))
+                .ok_or_else(|| ParseError(format!("{} is not a {} url", url, self.scheme)))?;
+            Ok(self.root.join(path).to_string_lossy().to_string())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ProviderFactory for RootedFactory {
+        async fn copy(
+            &self,
+            source: Option<&str>,
+            destination: Option<&str>,
+        ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+            let mut builder = CopyFileBuilder::default();
+            if let Some(source) = source {
+                builder = builder.with_source(&self.path(source)?);
+            }
+            if let Some(destination) = destination {
+                builder = builder.with_destination(&self.path(destination)?);
+            }
+            Ok(Box::new(builder.build()))
+        }
+
+        async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+            Ok(Box::new(
+                SumsFileBuilder::default()
+                    .with_file(self.path(url)?)
+                    .build()?,
+            ))
+        }
+    }
+
+    #[test]
+    fn register_schemes() -> Result<()> {
+        let tmp = TempDir::new()?;
+        let factory = || RootedFactory {
+            scheme: "registered".to_string(),
+            root: tmp.path().to_path_buf(),
+        };
+
+        assert!(ProviderRegistry::register("s3", factory()).is_err());
+        assert!(ProviderRegistry::unregister("swift").is_none());
+        for scheme in BUILT_IN_SCHEMES {
+            assert_eq!(
+                ProviderRegistry::get(scheme).is_some(),
+                !["file", "s3", gcs::GCS_SCHEME].contains(&scheme)
+            );
+        }
+        assert!(ProviderRegistry::register("not a scheme", factory()).is_err());
+
+        assert!(ProviderRegistry::scheme_of("registered://file").is_none());
+        ProviderRegistry::register("Registered", factory())?;
+        assert_eq!(
+            ProviderRegistry::scheme_of("registered://file"),
+            Some("registered".to_string())
+        );
+        assert_eq!(
+            Provider::try_from("registered://file")?,
+            Provider::Custom {
+                scheme: "registered".to_string(),
+                url: "registered://file".to_string()
+            }
+        );
+
+        assert!(ProviderRegistry::unregister("registered").is_some());
+        assert!(ProviderRegistry::get("registered").is_none());
+        assert!(Provider::try_from("registered://file")?.is_file());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn build_custom_objects() -> Result<()> {
+        let tmp = TempDir::new()?;
+        ProviderRegistry::register(
+            "rooted",
+            RootedFactory {
+                scheme: "rooted".to_string(),
+                root: tmp.path().to_path_buf(),
+            },
+        )?;
+        tokio::fs::write(tmp.path().join("source"), b"data").await?;
+
+        let mut sums = ObjectSumsBuilder::default()
+            .build("rooted://source".to_string())
+            .await?;
+        assert_eq!(sums.file_size().await?, Some(4));
+        assert_eq!(sums.location(), tmp.path().join("source").to_string_lossy());
+
+        let sums_file = SumsFile::new(
+            Some(4),
+            BTreeMap::from([(
+                Ctx::Regular(StandardCtx::md5()),
+                Checksum::new("8d777f385d3dfec8815d20f7496026dc".to_string()),
+            )]),
+        );
+        sums.write_sums_file(&sums_file).await?;
+        assert!(tmp.path().join("source.sums").exists());
+
+        let source = ObjectCopyBuilder::default()
+            .set_source(Some(Provider::try_from("rooted://source")?))
+            .build()
+            .await?;
+        let destination = ObjectCopyBuilder::default()
+            .set_destination(Some(Provider::try_from("rooted://destination")?))
+            .build()
+            .await?;
+        let state = source.initialize_state().await?;
+        let data = source.download(None).await?;
+        destination.upload(data, None, &state).await?;
+        assert_eq!(
+            tokio::fs::read(tmp.path().join("destination")).await?,
+            b"data"
+        );
+
+        ProviderRegistry::unregister("rooted");
+        Ok(())
+    }
+}
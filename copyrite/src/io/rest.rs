@@ -0,0 +1,309 @@
+//! Helpers shared by the clients of providers that are accessed with REST requests over HTTP,
+//! such as Swift and WebDAV. These send requests, retrying transient failures, convert
+//! unsuccessful responses into the error of the provider, and stream ranges of objects.
+//!
+
+use crate::error::{Error, Result};
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use reqwest::header::{RANGE, RETRY_AFTER};
+use reqwest::{Body, RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::time::Duration;
+use std::{io, mem};
+use tokio::io::AsyncRead;
+use tokio::time::sleep;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// A reader of a response body, or of a request body that is uploaded.
+pub type RestReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// The number of times a request is retried after a transient failure.
+pub const DEFAULT_REST_RETRIES: usize = 3;
+
+/// The initial backoff between retries of a request, which doubles after each retry.
+const DEFAULT_REST_BACKOFF: Duration = Duration::from_millis(200);
+
+/// The longest time to wait before retrying a request, including when the server asks for a
+/// longer wait using `Retry-After`.
+const MAX_REST_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Sends requests for a provider, converting failures into the error of the provider, e.g.
+/// `Rest::new(SwiftError)`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rest {
+    error: fn(String) -> Error,
+    retries: usize,
+    backoff: Duration,
+}
+
+impl Rest {
+    /// Create the helper with the error variant of the provider.
+    pub const fn new(error: fn(String) -> Error) -> Self {
+        Self {
+            error,
+            retries: DEFAULT_REST_RETRIES,
+            backoff: DEFAULT_REST_BACKOFF,
+        }
+    }
+
+    /// Set the initial backoff between retries.
+    pub const fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Send a request, retrying transient failures and mapping transport errors to the error of
+    /// the provider. Requests with a streaming body cannot be cloned, so they are only sent once.
+    pub async fn send(&self, request: RequestBuilder, operation: &str) -> Result<Response> {
+        let mut request = Some(request);
+        self.retry(operation, || {
+            let next = request.as_ref().and_then(RequestBuilder::try_clone);
+            let request = mem::replace(&mut request, next)?;
+            Some(async move { Ok(request.send().await) })
+        })
+        .await
+    }
+
+    /// Send a request with `send`, retrying connection failures, timeouts, server errors and
+    /// throttling with an exponential backoff. `send` is called for each attempt and returns
+    /// `None` if the request cannot be sent again. Errors from preparing a request, such as
+    /// authorizing it, are returned without retrying.
+    pub async fn retry<F, Fut>(&self, operation: &str, mut send: F) -> Result<Response>
+    where
+        F: FnMut() -> Option<Fut>,
+        Fut: Future<Output = Result<reqwest::Result<Response>>>,
+    {
+        let mut attempt = 0;
+        let mut backoff = self.backoff;
+        let mut next = send();
+        loop {
+            let Some(future) = next.take() else {
+                return Err((self.error)(format!("{} could not be sent", operation)));
+            };
+
+            let result = future.await?;
+            let (transient, delay) = match &result {
+                Ok(response) => (
+                    Self::is_transient(response.status()),
+                    Self::retry_after(response),
+                ),
+                Err(err) => (
+                    err.is_connect() || err.is_timeout() || err.is_request(),
+                    None,
+                ),
+            };
+            if transient && attempt < self.retries {
+                next = send();
+                if next.is_some() {
+                    sleep(delay.unwrap_or(backoff).min(MAX_REST_BACKOFF)).await;
+                    backoff *= 2;
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            return result.map_err(|err| (self.error)(format!("{} failed: {}", operation, err)));
+        }
+    }
+
+    /// Whether a response status could succeed if the request is retried.
+    fn is_transient(status: StatusCode) -> bool {
+        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+    }
+
+    /// The wait requested by the server using `Retry-After` in seconds.
+    fn retry_after(response: &Response) -> Option<Duration> {
+        response
+            .headers()
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+            .map(Duration::from_secs)
+    }
+
+    /// Convert an unsuccessful response into an error.
+    pub fn check(&self, response: Response, operation: &str) -> Result<Response> {
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err((self.error)(format!(
+                "{} failed with {}",
+                operation,
+                response.status()
+            )))
+        }
+    }
+
+    /// Send a request and check the response, returning `None` if the resource does not exist.
+    pub async fn send_optional(
+        &self,
+        request: RequestBuilder,
+        operation: &str,
+    ) -> Result<Option<Response>> {
+        let response = self.send(request, operation).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        self.check(response, operation).map(Some)
+    }
+
+    /// Send a `GET` request that reads from `start` up to, but not including, `end`, or to the
+    /// end of the resource if `end` is not set. Returns `None` if the resource does not exist.
+    /// Reading a range requires the server to support range requests, as the whole resource
+    /// cannot be used in place of a range.
+    pub async fn get_range(
+        &self,
+        mut request: RequestBuilder,
+        operation: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Option<RestReader>> {
+        let range = match end {
+            Some(end) if end <= start => return Ok(Some(Box::new(tokio::io::empty()))),
+            Some(end) => Some(format!("bytes={}-{}", start, end - 1)),
+            None if start > 0 => Some(format!("bytes={}-", start)),
+            None => None,
+        };
+        if let Some(range) = &range {
+            request = request.header(RANGE, range);
+        }
+
+        let Some(response) = self.send_optional(request, operation).await? else {
+            return Ok(None);
+        };
+        if range.is_some() && response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err((self.error)(format!(
+                "{} failed because the server does not support range requests",
+                operation
+            )));
+        }
+
+        Ok(Some(Self::reader(response)))
+    }
+
+    /// Send a `GET` request that reads the whole resource into memory, returning `None` if it
+    /// does not exist.
+    pub async fn get_bytes(
+        &self,
+        request: RequestBuilder,
+        operation: &str,
+    ) -> Result<Option<Bytes>> {
+        let Some(response) = self.send_optional(request, operation).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(response.bytes().await.map_err(Error::from)?))
+    }
+
+    /// Stream the body of a response.
+    pub fn reader(response: Response) -> RestReader {
+        let stream = Body::from(response)
+            .into_data_stream()
+            .map_err(io::Error::other);
+        Box::new(StreamReader::new(stream))
+    }
+
+    /// Create a request body that streams from a reader.
+    pub fn body(reader: RestReader) -> Body {
+        Body::wrap(StreamBody::new(
+            ReaderStream::new(reader).map_ok(Frame::data),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error::SwiftError;
+    use reqwest::{Client, Method};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one response for each request, returning the URL of the server.
+    async fn serve(responses: Vec<&'static str>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/object", listener.local_addr()?);
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).await?;
+                stream.write_all(response.as_bytes()).await?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(url)
+    }
+
+    #[tokio::test]
+    async fn send_retries_transient_errors() -> anyhow::Result<()> {
+        let url = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 429 Too Many Requests\r\nretry-after: 0\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-length: 3\r\nconnection: close\r\n\r\nabc",
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        ])
+        .await?;
+        let rest = Rest::new(SwiftError).with_backoff(Duration::ZERO);
+        let request = || Client::new().request(Method::GET, &url);
+
+        let response = rest.send(request(), "GET object").await?;
+        assert_eq!(response.text().await?, "abc");
+
+        // The last response is returned once the retries are exhausted.
+        let response = rest.send(request(), "GET object").await?;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(
+            rest.check(response, "GET object"),
+            Err(SwiftError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_range() -> anyhow::Result<()> {
+        let url = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\ncontent-length: 3\r\nconnection: close\r\n\r\nabc",
+            "HTTP/1.1 200 OK\r\ncontent-length: 6\r\nconnection: close\r\n\r\nabcdef",
+            "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        ])
+        .await?;
+        let rest = Rest::new(SwiftError);
+        let request = || Client::new().request(Method::GET, &url);
+
+        let mut data = String::new();
+        rest.get_range(request(), "GET object", 0, Some(3))
+            .await?
+            .expect("object exists")
+            .read_to_string(&mut data)
+            .await?;
+        assert_eq!(data, "abc");
+
+        let result = rest.get_range(request(), "GET object", 0, Some(3)).await;
+        assert!(matches!(result, Err(SwiftError(_))));
+        assert!(
+            rest.get_range(request(), "GET object", 0, None)
+                .await?
+                .is_none()
+        );
+        assert!(
+            rest.get_range(request(), "GET object", 3, Some(3))
+                .await?
+                .is_some()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+//! Summarize the contents of CSV and JSON objects using S3 Select, so that copies can be
+//! compared semantically without downloading them.
+//!
+
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::ParseError;
+use crate::error::{Error, Result};
+use crate::io::S3Client;
+use aws_sdk_s3::types::{
+    CompressionType, CsvInput, CsvOutput, ExpressionType, FileHeaderInfo, InputSerialization,
+    JsonInput, JsonType, OutputSerialization, SelectObjectContentEventStream,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The format of an object that can be queried with S3 Select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectFormat {
+    /// CSV with a header row.
+    Csv,
+    /// Newline-delimited JSON records.
+    JsonLines,
+    /// A single JSON document.
+    JsonDocument,
+}
+
+/// The format and compression of an object that can be queried with S3 Select.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectQuery {
+    format: SelectFormat,
+    compression: CompressionType,
+}
+
+impl SelectQuery {
+    /// Determine the query from the extension of a key, e.g. `data.csv.gz`. Returns `None` if
+    /// the object is not CSV or JSON.
+    pub fn from_key(key: &str) -> Option<Self> {
+        let (key, compression) = if let Some(key) = key.strip_suffix(".gz") {
+            (key, CompressionType::Gzip)
+        } else if let Some(key) = key.strip_suffix(".bz2") {
+            (key, CompressionType::Bzip2)
+        } else {
+            (key, CompressionType::None)
+        };
+
+        let (_, extension) = key.rsplit_once('.')?;
+        let format = match extension.to_lowercase().as_str() {
+            "csv" => SelectFormat::Csv,
+            "jsonl" | "ndjson" => SelectFormat::JsonLines,
+            "json" => SelectFormat::JsonDocument,
+            _ => return None,
+        };
+
+        Some(Self {
+            format,
+            compression,
+        })
+    }
+
+    /// The format of the object.
+    pub fn format(&self) -> SelectFormat {
+        self.format
+    }
+
+    /// The expression that counts the rows of the object.
+    pub fn count_expression() -> String {
+        "SELECT COUNT(*) FROM S3Object".to_string()
+    }
+
+    /// The expression that selects the columns of the object, in order.
+    pub fn columns_expression(columns: &[String]) -> Result<String> {
+        if let Some(column) = columns
+            .iter()
+            .find(|column| column.is_empty() || column.contains('"'))
+        {
+            return Err(ParseError(format!("invalid column name: `{}`", column)));
+        }
+
+        let columns = columns
+            .iter()
+            .map(|column| format!("s.\"{}\"", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(format!("SELECT {} FROM S3Object s", columns))
+    }
+
+    /// How S3 Select reads the object.
+    fn input_serialization(&self) -> InputSerialization {
+        let builder = InputSerialization::builder().compression_type(self.compression.clone());
+        match self.format {
+            SelectFormat::Csv => builder
+                .csv(
+                    CsvInput::builder()
+                        .file_header_info(FileHeaderInfo::Use)
+                        .build(),
+                )
+                .build(),
+            SelectFormat::JsonLines => builder
+                .json(JsonInput::builder().r#type(JsonType::Lines).build())
+                .build(),
+            SelectFormat::JsonDocument => builder
+                .json(JsonInput::builder().r#type(JsonType::Document).build())
+                .build(),
+        }
+    }
+
+    /// Run an expression against the object, returning the records as CSV.
+    async fn select(
+        &self,
+        client: &S3Client,
+        bucket: &str,
+        key: &str,
+        expression: String,
+    ) -> Result<Vec<u8>> {
+        let mut output = client
+            .select_object_content(|b| {
+                b.bucket(bucket)
+                    .key(key)
+                    .expression(expression)
+                    .expression_type(ExpressionType::Sql)
+                    .input_serialization(self.input_serialization())
+                    .output_serialization(
+                        OutputSerialization::builder()
+                            .csv(CsvOutput::builder().build())
+                            .build(),
+                    )
+            })
+            .await?;
+
+        let mut records = vec![];
+        while let Some(event) =
+            output.payload.recv().await.map_err(|err| {
+                Error::aws_error(format!("failed to read S3 Select results: {err}"))
+            })?
+        {
+            match event {
+                SelectObjectContentEventStream::Records(event) => {
+                    if let Some(payload) = event.payload() {
+                        records.extend_from_slice(payload.as_ref());
+                    }
+                }
+                SelectObjectContentEventStream::End(_) => break,
+                _ => {}
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Summarize the object by counting its rows and hashing the selected columns, if any.
+    pub async fn summarize(
+        &self,
+        client: &S3Client,
+        bucket: &str,
+        key: &str,
+        columns: &[String],
+    ) -> Result<SelectSummary> {
+        let count = self
+            .select(client, bucket, key, Self::count_expression())
+            .await?;
+        let rows = parse_count(&count)?;
+
+        let columns_sha256 = if columns.is_empty() {
+            None
+        } else {
+            let records = self
+                .select(client, bucket, key, Self::columns_expression(columns)?)
+                .await?;
+            let mut ctx = StandardCtx::sha256();
+            ctx.update(Arc::from(records.as_slice()))?;
+            let digest = ctx.finalize()?;
+            Some(ctx.digest_to_string(&digest))
+        };
+
+        Ok(SelectSummary {
+            rows,
+            columns_sha256,
+        })
+    }
+}
+
+/// Parse the output of a count expression.
+fn parse_count(records: &[u8]) -> Result<u64> {
+    String::from_utf8_lossy(records)
+        .trim()
+        .parse()
+        .map_err(|err| ParseError(format!("failed to parse S3 Select row count: {}", err)))
+}
+
+/// A summary of the contents of an object from S3 Select.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SelectSummary {
+    /// The number of rows in the object.
+    pub(crate) rows: u64,
+    /// The SHA-256 of the selected columns, encoded as CSV.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) columns_sha256: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query_from_key() {
+        let query = |format, compression| {
+            Some(SelectQuery {
+                format,
+                compression,
+            })
+        };
+
+        assert_eq!(
+            SelectQuery::from_key("prefix/data.csv"),
+            query(SelectFormat::Csv, CompressionType::None)
+        );
+        assert_eq!(
+            SelectQuery::from_key("prefix/data.JSONL.gz"),
+            query(SelectFormat::JsonLines, CompressionType::Gzip)
+        );
+        assert_eq!(
+            SelectQuery::from_key("data.json.bz2"),
+            query(SelectFormat::JsonDocument, CompressionType::Bzip2)
+        );
+        assert_eq!(SelectQuery::from_key("data.bam"), None);
+        assert_eq!(SelectQuery::from_key("csv"), None);
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            SelectQuery::columns_expression(&["id".to_string(), "sample name".to_string()])
+                .unwrap(),
+            "SELECT s.\"id\", s.\"sample name\" FROM S3Object s"
+        );
+        assert!(SelectQuery::columns_expression(&["a\"b".to_string()]).is_err());
+        assert_eq!(parse_count(b"42\n").unwrap(), 42);
+        assert!(parse_count(b"").is_err());
+    }
+}
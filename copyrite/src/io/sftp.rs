@@ -0,0 +1,212 @@
+//! Support for SFTP servers, such as those exposed by sequencing instruments, through the system
+//! `ssh` client. `sftp://[user@]host[:port]/path` locations are connected to using the usual
+//! `ssh` configuration, with an optional identity file and host key checking set by the
+//! credentials options. Connections are shared between all objects on the same host.
+//!
+
+use crate::cli::SftpKnownHosts;
+use crate::error::Error::SftpError;
+use crate::error::Result;
+use crate::io::SftpOptions;
+use bytes::Bytes;
+use openssh::{KnownHosts, SessionBuilder};
+use openssh_sftp_client::error::SftpErrorKind;
+use openssh_sftp_client::file::{File, TokioCompatFile};
+use openssh_sftp_client::{Error, Sftp};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Mutex;
+
+/// The size of the buffer used when writing to a file.
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// An SFTP file reader.
+pub type SftpReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// Open SFTP sessions, keyed by the host and connection options.
+type Sessions = Mutex<HashMap<(String, SftpOptions), Arc<Sftp>>>;
+
+/// The SFTP sessions of the current process.
+static SESSIONS: OnceLock<Sessions> = OnceLock::new();
+
+impl From<SftpKnownHosts> for KnownHosts {
+    fn from(known_hosts: SftpKnownHosts) -> Self {
+        match known_hosts {
+            SftpKnownHosts::Strict => KnownHosts::Strict,
+            SftpKnownHosts::Add => KnownHosts::Add,
+            SftpKnownHosts::Accept => KnownHosts::Accept,
+        }
+    }
+}
+
+/// Whether the error is from a file that does not exist.
+fn is_not_found(err: &Error) -> bool {
+    matches!(err, Error::SftpError(SftpErrorKind::NoSuchFile, _))
+}
+
+/// Convert an SFTP error into an error for the location.
+fn sftp_error(host: &str, path: &str, err: impl ToString) -> crate::error::Error {
+    SftpError(format!(
+        "{}: {}",
+        crate::io::Provider::format_sftp(host, path),
+        err.to_string()
+    ))
+}
+
+/// A client for reading and writing SFTP locations.
+#[derive(Debug, Clone, Default)]
+pub struct SftpClient {
+    options: SftpOptions,
+}
+
+impl SftpClient {
+    /// Create a new client with connection options.
+    pub fn new(options: SftpOptions) -> Self {
+        Self { options }
+    }
+
+    /// Get the session for a host, connecting to it if this is the first use.
+    async fn connect(&self, host: &str) -> Result<Arc<Sftp>> {
+        let mut sessions = SESSIONS.get_or_init(Default::default).lock().await;
+        let key = (host.to_string(), self.options.clone());
+        if let Some(sftp) = sessions.get(&key) {
+            return Ok(sftp.clone());
+        }
+
+        let mut builder = SessionBuilder::default();
+        builder.known_hosts_check(self.options.known_hosts().into());
+        if let Some(identity_file) = self.options.identity_file() {
+            builder.keyfile(identity_file);
+        }
+        let session = builder
+            .connect_mux(format!("ssh://{}", host))
+            .await
+            .map_err(|err| SftpError(format!("failed to connect to {}: {}", host, err)))?;
+        let sftp = Sftp::from_session(session, Default::default())
+            .await
+            .map_err(|err| SftpError(format!("failed to start SFTP on {}: {}", host, err)))?;
+
+        let sftp = Arc::new(sftp);
+        sessions.insert(key, sftp.clone());
+        Ok(sftp)
+    }
+
+    /// Open a file for reading, or `None` if it does not exist.
+    async fn open(&self, host: &str, path: &str) -> Result<Option<File>> {
+        let sftp = self.connect(host).await?;
+        match sftp.open(path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(sftp_error(host, path, err)),
+        }
+    }
+
+    /// Get the size of a file, or `None` if it does not exist.
+    pub async fn size(&self, host: &str, path: &str) -> Result<Option<u64>> {
+        let sftp = self.connect(host).await?;
+        let metadata = match sftp.fs().metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(sftp_error(host, path, err)),
+        };
+
+        metadata
+            .len()
+            .map(Some)
+            .ok_or_else(|| sftp_error(host, path, "the server did not report a size"))
+    }
+
+    /// Read a file from `start` up to, but not including, `end`, or to the end of the file if
+    /// `end` is not set. Returns `None` if the file does not exist.
+    pub async fn read(
+        &self,
+        host: &str,
+        path: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Option<SftpReader>> {
+        let Some(mut file) = self.open(host, path).await? else {
+            return Ok(None);
+        };
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+
+        let reader = Box::pin(TokioCompatFile::new(file));
+        match end {
+            Some(end) => Ok(Some(Box::new(reader.take(end.saturating_sub(start))))),
+            None => Ok(Some(Box::new(reader))),
+        }
+    }
+
+    /// Read a whole file into memory, or `None` if it does not exist.
+    pub async fn read_bytes(&self, host: &str, path: &str) -> Result<Option<Bytes>> {
+        let sftp = self.connect(host).await?;
+        match sftp.fs().read(path).await {
+            Ok(data) => Ok(Some(data.freeze())),
+            Err(err) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(sftp_error(host, path, err)),
+        }
+    }
+
+    /// Write the data to a file, returning the number of bytes written. If `offset` is set, the
+    /// data is written at that offset of the existing file, otherwise the file is replaced.
+    pub async fn write(
+        &self,
+        host: &str,
+        path: &str,
+        mut data: impl AsyncRead + Unpin,
+        offset: Option<u64>,
+    ) -> Result<u64> {
+        let sftp = self.connect(host).await?;
+        let mut file = sftp
+            .options()
+            .write(true)
+            .create(true)
+            .truncate(offset.is_none())
+            .open(path)
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+        file.seek(SeekFrom::Start(offset.unwrap_or_default()))
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+
+        let mut buffer = vec![0; WRITE_BUFFER_SIZE];
+        let mut written = 0;
+        loop {
+            let n = data.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buffer[..n])
+                .await
+                .map_err(|err| sftp_error(host, path, err))?;
+            written += n as u64;
+        }
+
+        file.close()
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+        Ok(written)
+    }
+
+    /// Set the size of a file, truncating or extending it.
+    pub async fn set_len(&self, host: &str, path: &str, size: u64) -> Result<()> {
+        let sftp = self.connect(host).await?;
+        let mut file = sftp
+            .options()
+            .write(true)
+            .create(true)
+            .open(path)
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+        file.set_len(size)
+            .await
+            .map_err(|err| sftp_error(host, path, err))?;
+        file.close()
+            .await
+            .map_err(|err| sftp_error(host, path, err))
+    }
+}
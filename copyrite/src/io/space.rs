@@ -0,0 +1,177 @@
+//! Check the free space and file size limits of local destinations before copying to them.
+//!
+
+use crate::error::Error::InsufficientSpace;
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The space that a copy requires on the file system of a local destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredSpace {
+    destination: PathBuf,
+    file_system: u64,
+    bytes: u64,
+}
+
+impl RequiredSpace {
+    /// Determine the space required to copy `size` bytes to a local destination. An existing
+    /// destination file is overwritten, so its size does not need to be available again.
+    /// Returns `None` if the free space cannot be determined on this platform.
+    pub async fn new(destination: &Path, size: u64) -> Result<Option<Self>> {
+        let Some(space) = FileSystemSpace::of(destination)? else {
+            return Ok(None);
+        };
+        let existing = fs::metadata(destination)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+
+        Ok(Some(Self {
+            destination: destination.to_path_buf(),
+            file_system: space.id,
+            bytes: size.saturating_sub(existing),
+        }))
+    }
+
+    /// The local destination.
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
+    /// The number of bytes required.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// The maximum size of a file on FAT file systems.
+pub const FAT_MAX_FILE_SIZE: u64 = 4294967295;
+
+/// The magic number of FAT file systems reported by `statfs`.
+#[cfg(target_os = "linux")]
+const MSDOS_SUPER_MAGIC: u64 = 0x4d44;
+
+/// The nearest existing ancestor of a path, because the destination and its directories may not
+/// have been created yet.
+#[cfg(unix)]
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .map(|path| {
+            if path.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                path
+            }
+        })
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("."))
+}
+
+/// The maximum size of a file on the file system that contains the path, if the file system
+/// limits it. Only FAT file systems are detected, which limit files to 4 GiB, e.g. on removable
+/// drives used to ship data.
+#[cfg(target_os = "linux")]
+pub fn max_file_size(path: &Path) -> Result<Option<u64>> {
+    let stat = rustix::fs::statfs(existing_ancestor(path)).map_err(std::io::Error::from)?;
+    Ok((stat.f_type as u64 == MSDOS_SUPER_MAGIC).then_some(FAT_MAX_FILE_SIZE))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn max_file_size(_path: &Path) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// The identity and available space of a file system.
+struct FileSystemSpace {
+    id: u64,
+    available: u64,
+}
+
+impl FileSystemSpace {
+    /// Get the file system that contains the path.
+    #[cfg(unix)]
+    fn of(path: &Path) -> Result<Option<Self>> {
+        let stat = rustix::fs::statvfs(existing_ancestor(path)).map_err(std::io::Error::from)?;
+        Ok(Some(Self {
+            id: stat.f_fsid,
+            available: stat.f_bavail.saturating_mul(stat.f_frsize),
+        }))
+    }
+
+    #[cfg(not(unix))]
+    fn of(_path: &Path) -> Result<Option<Self>> {
+        Ok(None)
+    }
+}
+
+/// Check that the file systems of local destinations have enough available space for all the
+/// required copies. The free space is read again on each call, so this can be repeated while
+/// copying to catch space that is used up by other processes.
+pub fn check_space<'a>(required: impl IntoIterator<Item = &'a RequiredSpace>) -> Result<()> {
+    let mut file_systems: BTreeMap<u64, (&Path, u64)> = BTreeMap::new();
+    for required in required {
+        let (_, bytes) = file_systems
+            .entry(required.file_system)
+            .or_insert((&required.destination, 0));
+        *bytes = bytes.saturating_add(required.bytes);
+    }
+
+    for (destination, bytes) in file_systems.into_values() {
+        let Some(space) = FileSystemSpace::of(destination)? else {
+            continue;
+        };
+        if bytes > space.available {
+            return Err(InsufficientSpace(format!(
+                "{} bytes are required on the file system of {} but only {} bytes are available",
+                bytes,
+                destination.display(),
+                space.available
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn required_space() -> Result<()> {
+        let tmp = TempDir::new()?;
+        let existing = tmp.path().join("existing");
+        fs::write(&existing, vec![0; 10]).await?;
+
+        let overwrite = RequiredSpace::new(&existing, 4).await?.unwrap();
+        assert_eq!(overwrite.bytes(), 0);
+
+        let new = RequiredSpace::new(&tmp.path().join("dir/new"), 4)
+            .await?
+            .unwrap();
+        assert_eq!(new.bytes(), 4);
+        check_space([&overwrite, &new])?;
+
+        let too_large = RequiredSpace::new(&tmp.path().join("large"), u64::MAX)
+            .await?
+            .unwrap();
+        let err = check_space([&new, &too_large]).unwrap_err();
+        assert!(matches!(err, InsufficientSpace(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_file_size_unlimited() -> Result<()> {
+        let tmp = TempDir::new()?;
+
+        // Temporary directories are not on FAT file systems.
+        assert_eq!(max_file_size(&tmp.path().join("dir/new"))?, None);
+
+        Ok(())
+    }
+}
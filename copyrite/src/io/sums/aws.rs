@@ -4,13 +4,13 @@
 use crate::checksum::Ctx;
 use crate::checksum::aws_etag::{AWSETagCtx, PartMode};
 use crate::checksum::file::Checksum;
-use crate::checksum::file::SumsFile;
+use crate::checksum::file::{ObjectVersion, SumsFile};
 use crate::checksum::standard::StandardCtx;
 use crate::error::Error::ParseError;
 use crate::error::{ApiError, Error, Result};
 use crate::io::Provider;
 use crate::io::S3Client;
-use crate::io::sums::ObjectSums;
+use crate::io::sums::{AclGrant, ObjectAcl, ObjectSnapshot, ObjectSums, ObjectTags};
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesOutput;
 use aws_sdk_s3::operation::head_object::HeadObjectOutput;
@@ -130,12 +130,14 @@ impl S3 {
 
     /// Get an existing sums file if it exists.
     pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_key = SumsFile::format_sums_file(&self.key);
+        if self.client.is_missing_sums(&self.bucket, &sums_key) {
+            return Ok(None);
+        }
+
         let result = self
             .client
-            .get_object(|b| {
-                b.bucket(&self.bucket)
-                    .key(SumsFile::format_sums_file(&self.key))
-            })
+            .get_object(|b| b.bucket(&self.bucket).key(&sums_key))
             .await;
 
         match result {
@@ -145,6 +147,7 @@ impl S3 {
                 Ok(Some(sums))
             }
             Err(err) if matches!(err.as_service_error(), Some(GetObjectError::NoSuchKey(_))) => {
+                self.client.insert_missing_sums(&self.bucket, &sums_key);
                 Ok(None)
             }
             Err(err) => Err(err.into()),
@@ -478,6 +481,180 @@ impl S3 {
         }
     }
 
+    /// Get the object tags and metadata. Tags that cannot be read are recorded as a recoverable
+    /// api error, returning `None`.
+    pub async fn tags(&mut self) -> Result<Option<ObjectTags>> {
+        let metadata = self
+            .head_object(None)
+            .await?
+            .metadata()
+            .cloned()
+            .unwrap_or_default();
+
+        let tagging = self
+            .client
+            .get_object_tagging(|b| {
+                b.bucket(&self.bucket)
+                    .key(SumsFile::format_target_file(&self.key))
+            })
+            .await;
+
+        match tagging {
+            Ok(tagging) => {
+                let tags = tagging
+                    .tag_set()
+                    .iter()
+                    .map(|tag| (tag.key().to_string(), tag.value().to_string()))
+                    .collect();
+
+                Ok(Some(ObjectTags::new(tags, metadata.into_iter().collect())))
+            }
+            Err(ref err) => {
+                self.api_errors.insert(ApiError::from(err));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Get a snapshot of the headers, tags, ACL, storage class and version of the object. Tags
+    /// and ACLs that cannot be read are recorded as a recoverable api error, and left out of the
+    /// snapshot.
+    pub async fn snapshot(&mut self) -> Result<ObjectSnapshot> {
+        let head = self.head_object(None).await?.clone();
+        let tags = self.tags().await?.map(|tags| tags.tags);
+
+        let acl = self
+            .client
+            .get_object_acl(|b| {
+                b.bucket(&self.bucket)
+                    .key(SumsFile::format_target_file(&self.key))
+            })
+            .await;
+        let acl = match acl {
+            Ok(acl) => Some(ObjectAcl {
+                owner: acl
+                    .owner()
+                    .and_then(|owner| owner.id())
+                    .map(ToString::to_string),
+                grants: acl
+                    .grants()
+                    .iter()
+                    .filter_map(|grant| {
+                        let grantee = grant.grantee()?;
+                        let grantee = grantee.id().or(grantee.uri()).or(grantee.email_address())?;
+                        Some(AclGrant {
+                            grantee: grantee.to_string(),
+                            permission: grant.permission()?.as_str().to_string(),
+                        })
+                    })
+                    .collect(),
+            }),
+            Err(ref err) => {
+                self.api_errors.insert(ApiError::from(err));
+                None
+            }
+        };
+
+        let headers = [
+            (
+                "Cache-Control",
+                head.cache_control().map(ToString::to_string),
+            ),
+            (
+                "Content-Disposition",
+                head.content_disposition().map(ToString::to_string),
+            ),
+            (
+                "Content-Encoding",
+                head.content_encoding().map(ToString::to_string),
+            ),
+            (
+                "Content-Language",
+                head.content_language().map(ToString::to_string),
+            ),
+            ("Content-Type", head.content_type().map(ToString::to_string)),
+            ("Expires", head.expires_string().map(ToString::to_string)),
+            (
+                "x-amz-website-redirect-location",
+                head.website_redirect_location().map(ToString::to_string),
+            ),
+            (
+                "x-amz-server-side-encryption",
+                head.server_side_encryption()
+                    .map(|sse| sse.as_str().to_string()),
+            ),
+            (
+                "x-amz-server-side-encryption-aws-kms-key-id",
+                head.ssekms_key_id().map(ToString::to_string),
+            ),
+            (
+                "x-amz-object-lock-mode",
+                head.object_lock_mode()
+                    .map(|mode| mode.as_str().to_string()),
+            ),
+            (
+                "x-amz-object-lock-retain-until-date",
+                head.object_lock_retain_until_date()
+                    .map(ToString::to_string),
+            ),
+            (
+                "x-amz-object-lock-legal-hold",
+                head.object_lock_legal_hold_status()
+                    .map(|status| status.as_str().to_string()),
+            ),
+            (
+                "x-amz-replication-status",
+                head.replication_status()
+                    .map(|status| status.as_str().to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(header, value)| Some((header.to_string(), value?)))
+        .collect();
+
+        Ok(ObjectSnapshot {
+            location: self.location(),
+            size: head.content_length().map(u64::try_from).transpose()?,
+            e_tag: head
+                .e_tag()
+                .map(|e_tag| e_tag.trim_matches('"').to_string()),
+            version_id: head.version_id().map(ToString::to_string),
+            last_modified: head.last_modified().map(ToString::to_string),
+            // S3 does not return the storage class of standard objects.
+            storage_class: Some(
+                head.storage_class()
+                    .map(|class| class.as_str())
+                    .unwrap_or("STANDARD")
+                    .to_string(),
+            ),
+            headers,
+            metadata: head
+                .metadata()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            tags,
+            acl,
+        })
+    }
+
+    /// Write a metadata snapshot next to the object using `PutObject`.
+    pub async fn put_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let key = ObjectSnapshot::format_snapshot_file(&self.key);
+        let body = ByteStream::from(snapshot.to_json_string()?.into_bytes());
+        self.client
+            .put_object(move |b| {
+                b.checksum_algorithm(ChecksumAlgorithm::Crc64Nvme)
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .content_type("application/json")
+                    .body(body)
+            })
+            .await?;
+        Ok(())
+    }
+
     /// Get the inner values not including the S3 client.
     pub fn into_inner(self) -> (String, String) {
         (self.bucket, self.key)
@@ -496,6 +673,28 @@ impl S3 {
         Ok(Box::new(response.body.into_async_read()))
     }
 
+    /// Get a reader to a range of the object using a ranged `GetObject`.
+    pub async fn object_range_reader(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<impl AsyncRead + Send + Unpin + 'static> {
+        if start >= end {
+            return Ok(ByteStream::from_static(&[]).into_async_read());
+        }
+
+        let response = self
+            .client
+            .get_object(|b| {
+                b.bucket(&self.bucket)
+                    .key(SumsFile::format_target_file(&self.key))
+                    .range(format!("bytes={}-{}", start, end - 1))
+            })
+            .await?;
+
+        Ok(response.body.into_async_read())
+    }
+
     /// Get the object file size.
     async fn size(&mut self) -> Result<Option<u64>> {
         Ok(self
@@ -537,6 +736,14 @@ impl ObjectSums for S3 {
         Ok(Box::new(self.object_reader().await?))
     }
 
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send + 'static>> {
+        Ok(Box::new(self.object_range_reader(start, end).await?))
+    }
+
     async fn file_size(&mut self) -> Result<Option<u64>> {
         self.size().await
     }
@@ -545,6 +752,27 @@ impl ObjectSums for S3 {
         self.put_sums(sums_file).await
     }
 
+    async fn object_tags(&mut self) -> Result<Option<ObjectTags>> {
+        self.tags().await
+    }
+
+    async fn object_snapshot(&mut self) -> Result<Option<ObjectSnapshot>> {
+        Ok(Some(self.snapshot().await?))
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        self.put_snapshot(snapshot).await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        let head = self.head_object(None).await?;
+        Ok(Some(ObjectVersion::new(
+            head.e_tag()
+                .map(|e_tag| e_tag.trim_matches('"').to_string()),
+            head.version_id().map(ToString::to_string),
+        )))
+    }
+
     fn location(&self) -> String {
         Provider::format_s3(&self.bucket, &self.key)
     }
@@ -557,14 +785,21 @@ impl ObjectSums for S3 {
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
-    use crate::checksum::standard::test::EXPECTED_MD5_SUM;
+    use crate::checksum::test::EXPECTED_MD5_SUM;
     use crate::task::generate::test::generate_for;
     use crate::test::{TEST_FILE_NAME, TEST_FILE_SIZE};
     use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::get_object_acl::GetObjectAclError;
+    use aws_sdk_s3::operation::get_object_tagging::{
+        GetObjectTaggingError, GetObjectTaggingOutput,
+    };
     use aws_sdk_s3::operation::head_object::builders::HeadObjectOutputBuilder;
     use aws_sdk_s3::types;
     use aws_sdk_s3::types::GetObjectAttributesParts;
+    use aws_sdk_s3::types::Tag;
     use aws_smithy_mocks::{Rule, RuleMode, mock, mock_client};
+    use aws_smithy_types::error::ErrorMetadata;
+    use std::collections::BTreeMap;
     use std::sync::Arc;
 
     const EXPECTED_SHA256_SUM: &str = "Kf+9U8vkMXmrL6YtvZWMDsMLNAq1DOfHheinpLR3Hjk="; // pragma: allowlist secret
@@ -831,6 +1066,150 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_object_tags() -> Result<()> {
+        let head_object = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| {
+                HeadObjectOutput::builder()
+                    .metadata("project", "copyrite")
+                    .build()
+            });
+        let get_object_tagging = mock!(Client::get_object_tagging)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| {
+                GetObjectTaggingOutput::builder()
+                    .tag_set(Tag::builder().key("tag").value("value").build().unwrap())
+                    .build()
+                    .unwrap()
+            });
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&head_object, &get_object_tagging]
+        );
+
+        let mut s3 = S3Builder::default()
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .with_bucket("bucket".to_string())
+            .with_key("key".to_string())
+            .build()?;
+
+        assert_eq!(
+            s3.object_tags().await?,
+            Some(ObjectTags::new(
+                BTreeMap::from_iter(vec![("tag".to_string(), "value".to_string())]),
+                BTreeMap::from_iter(vec![("project".to_string(), "copyrite".to_string())]),
+            ))
+        );
+        assert!(s3.api_errors().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_object_snapshot() -> Result<()> {
+        let head_object = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| {
+                HeadObjectOutput::builder()
+                    .content_length(5)
+                    .content_type("text/plain")
+                    .e_tag("\"etag\"")
+                    .version_id("version")
+                    .object_lock_legal_hold_status(types::ObjectLockLegalHoldStatus::On)
+                    .metadata("project", "copyrite")
+                    .build()
+            });
+        let get_object_tagging = mock!(Client::get_object_tagging)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| {
+                GetObjectTaggingOutput::builder()
+                    .tag_set(Tag::builder().key("tag").value("value").build().unwrap())
+                    .build()
+                    .unwrap()
+            });
+        let get_object_acl = mock!(Client::get_object_acl)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_error(|| {
+                GetObjectAclError::generic(ErrorMetadata::builder().code("AccessDenied").build())
+            });
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&head_object, &get_object_tagging, &get_object_acl]
+        );
+
+        let mut s3 = S3Builder::default()
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .with_bucket("bucket".to_string())
+            .with_key("key".to_string())
+            .build()?;
+
+        let snapshot = s3.object_snapshot().await?.unwrap();
+        assert_eq!(
+            snapshot,
+            ObjectSnapshot {
+                location: "s3://bucket/key".to_string(),
+                size: Some(5),
+                e_tag: Some("etag".to_string()),
+                version_id: Some("version".to_string()),
+                last_modified: None,
+                storage_class: Some("STANDARD".to_string()),
+                headers: BTreeMap::from_iter(vec![
+                    ("Content-Type".to_string(), "text/plain".to_string()),
+                    ("x-amz-object-lock-legal-hold".to_string(), "ON".to_string()),
+                ]),
+                metadata: BTreeMap::from_iter(vec![(
+                    "project".to_string(),
+                    "copyrite".to_string()
+                )]),
+                tags: Some(BTreeMap::from_iter(vec![(
+                    "tag".to_string(),
+                    "value".to_string()
+                )])),
+                acl: None,
+            }
+        );
+        let errors = s3.api_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors.iter().all(|err| err.is_access_denied()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_object_tags_access_denied() -> Result<()> {
+        let head_object = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| HeadObjectOutput::builder().build());
+        let get_object_tagging = mock!(Client::get_object_tagging)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_error(|| {
+                GetObjectTaggingError::generic(
+                    ErrorMetadata::builder().code("AccessDenied").build(),
+                )
+            });
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&head_object, &get_object_tagging]
+        );
+
+        let mut s3 = S3Builder::default()
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .with_bucket("bucket".to_string())
+            .with_key("key".to_string())
+            .build()?;
+
+        assert!(s3.object_tags().await?.is_none());
+        let errors = s3.api_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors.iter().all(|err| err.is_access_denied()));
+
+        Ok(())
+    }
+
     fn head_object_rule(content_length: i64) -> Rule {
         mock!(Client::head_object)
             .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
@@ -0,0 +1,218 @@
+//! Azure Blob Storage sums file logic.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::file::{Checksum, ObjectVersion, SumsFile};
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::{AzureError, ParseError};
+use crate::error::{ApiError, Result};
+use crate::io::Provider;
+use crate::io::azure::{AzureClient, BlobProperties};
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use reqwest::Body;
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build an Azure sums object.
+#[derive(Debug, Default)]
+pub struct AzureBuilder {
+    client: Option<AzureClient>,
+    container: Option<String>,
+    blob: Option<String>,
+}
+
+impl AzureBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: AzureClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the container.
+    pub fn with_container(mut self, container: String) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Set the blob.
+    pub fn with_blob(mut self, blob: String) -> Self {
+        self.blob = Some(blob);
+        self
+    }
+
+    fn get_components(self) -> Result<(AzureClient, String, String)> {
+        let error_fn =
+            || ParseError("client, container and blob are required in `AzureBuilder`".to_string());
+
+        Ok((
+            self.client.ok_or_else(error_fn)?,
+            self.container.ok_or_else(error_fn)?,
+            self.blob.ok_or_else(error_fn)?,
+        ))
+    }
+
+    /// Build the Azure sums object.
+    pub fn build(self) -> Result<Azure> {
+        let (client, container, blob) = self.get_components()?;
+        Ok(Azure::new(client, container, blob))
+    }
+}
+
+/// An Azure blob.
+#[derive(Debug, Clone)]
+pub struct Azure {
+    client: AzureClient,
+    container: String,
+    blob: String,
+    properties: Option<BlobProperties>,
+}
+
+impl Azure {
+    /// Create a new Azure blob.
+    pub fn new(client: AzureClient, container: String, blob: String) -> Self {
+        Self {
+            client,
+            container,
+            blob,
+            properties: None,
+        }
+    }
+
+    /// Get the properties of the blob. This caches the result so that subsequent calls do not
+    /// repeat the request.
+    pub async fn properties(&mut self) -> Result<&BlobProperties> {
+        if self.properties.is_none() {
+            let properties = self
+                .client
+                .get_properties(&self.container, &self.blob)
+                .await?
+                .ok_or_else(|| AzureError(format!("{} does not exist", self.location())))?;
+            self.properties = Some(properties);
+        }
+
+        Ok(self.properties.get_or_insert_default())
+    }
+
+    /// Get an existing sums file if it exists.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_blob = SumsFile::format_sums_file(&self.blob);
+        let Some(data) = self
+            .client
+            .get_blob_bytes(&self.container, &sums_blob)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    /// Create a sums file from the properties of the blob, which contains its size and the MD5
+    /// of the `Content-MD5` property if the blob has one.
+    pub async fn sums_from_properties(&mut self) -> Result<SumsFile> {
+        let properties = self.properties().await?;
+        let mut sums_file = SumsFile::default().with_size(Some(properties.size));
+        if let Some(md5) = properties.md5() {
+            sums_file.add_checksum(Ctx::Regular(StandardCtx::md5()), Checksum::new(md5));
+        }
+
+        Ok(sums_file)
+    }
+
+    /// Write the sums file next to the blob.
+    pub async fn put_sums(&self, sums_file: &SumsFile) -> Result<()> {
+        let sums_blob = SumsFile::format_sums_file(&self.blob);
+        self.put_json(&sums_blob, sums_file.to_json_string()?).await
+    }
+
+    /// Write a JSON document next to the blob.
+    async fn put_json(&self, blob: &str, json: String) -> Result<()> {
+        let length = u64::try_from(json.len())?;
+        self.client
+            .put_blob(
+                &self.container,
+                blob,
+                Body::from(json),
+                length,
+                &Default::default(),
+            )
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for Azure {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        let properties_sums = self.sums_from_properties().await?;
+
+        match self.get_existing_sums().await? {
+            Some(existing) if properties_sums.checksums.is_empty() => Ok(Some(existing)),
+            Some(existing) => Ok(Some(properties_sums.merge(existing)?)),
+            None if properties_sums.checksums.is_empty() => Ok(None),
+            None => Ok(Some(properties_sums)),
+        }
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get_blob(&self.container, &self.blob, 0, None)
+            .await?
+            .ok_or_else(|| AzureError(format!("{} does not exist", self.location())))?;
+        Ok(reader)
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        let reader = self
+            .client
+            .get_blob(&self.container, &self.blob, offset, None)
+            .await?;
+        Ok(reader.map(|reader| reader as Box<dyn AsyncRead + Unpin + Send>))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get_blob(&self.container, &self.blob, start, Some(end))
+            .await?
+            .ok_or_else(|| AzureError(format!("{} does not exist", self.location())))?;
+        Ok(reader)
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        Ok(Some(self.properties().await?.size))
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        self.put_sums(sums_file).await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        let properties = self.properties().await?;
+        Ok(Some(ObjectVersion::new(
+            properties.e_tag.clone(),
+            properties.version_id.clone(),
+        )))
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let snapshot_blob = ObjectSnapshot::format_snapshot_file(&self.blob);
+        self.put_json(&snapshot_blob, snapshot.to_json_string()?)
+            .await
+    }
+
+    fn location(&self) -> String {
+        Provider::format_azure(&self.container, &self.blob)
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
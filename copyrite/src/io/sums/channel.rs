@@ -1,20 +1,126 @@
 //! A shared reader implementation which makes use on channels.
 //!
 
+use crate::error::Error::{ConcurrencyError, MemoryError, ParseError};
 use crate::error::Result;
 use crate::io::sums::{ReaderStream, SharedReader};
 use async_stream::stream;
 use futures_util::Stream;
+use parse_size::parse_size;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
-use tokio::sync::mpsc;
+use tokio::select;
+use tokio::sync::{Semaphore, mpsc};
+use tokio_util::sync::CancellationToken;
+
+/// The initial chunk size when automatically tuning reads from a local file.
+pub const AUTO_FILE_CHUNK_SIZE: usize = 256 * 1024;
+/// The initial chunk size when automatically tuning reads from a stream, such as S3 or stdin.
+pub const AUTO_STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+/// The smallest chunk size chosen when automatically tuning.
+pub const MIN_AUTO_CHUNK_SIZE: usize = 64 * 1024;
+/// The largest chunk size chosen when automatically tuning.
+pub const MAX_AUTO_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reads which fill a chunk faster than this grow the chunk size when automatically tuning.
+const FAST_READ: Duration = Duration::from_millis(1);
+/// Reads which only partially fill a chunk slower than this shrink the chunk size.
+const SLOW_READ: Duration = Duration::from_millis(50);
+
+/// The number of bytes read by the channel reader before passing them into the channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkSize {
+    /// Start from a size based on the source type and adjust it using the read latency.
+    #[default]
+    Auto,
+    /// Always use the same size.
+    Fixed(usize),
+}
+
+impl FromStr for ChunkSize {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+
+        let size = parse_size(s)
+            .map_err(|err| ParseError(format!("invalid chunk size `{}`: {}", s, err)))?;
+        match usize::try_from(size)? {
+            0 => Err(ParseError(
+                "the chunk size must be greater than 0".to_string(),
+            )),
+            size => Ok(Self::Fixed(size)),
+        }
+    }
+}
+
+/// A subscriber to the channel reader.
+#[derive(Debug)]
+struct Subscriber {
+    tx: mpsc::Sender<Arc<[u8]>>,
+    /// The bytes sent to the subscriber which it has not finished with yet.
+    buffered: Arc<AtomicUsize>,
+    /// Permits for the bytes that can still be buffered for the subscriber, if the buffer is
+    /// limited.
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl Subscriber {
+    /// Reserve space for a chunk in the buffer of the subscriber, waiting for it to finish with
+    /// earlier chunks if the buffer is full.
+    async fn reserve(&self, size: usize) -> Result<()> {
+        let Some(permits) = &self.permits else {
+            return Ok(());
+        };
+
+        select! {
+            permit = permits.acquire_many(u32::try_from(size)?) => {
+                permit
+                    .map_err(|err| ConcurrencyError(err.to_string()))?
+                    .forget();
+                Ok(())
+            }
+            _ = self.tx.closed() => Err(ConcurrencyError(
+                "channel reader subscriber closed before reading to the end".to_string(),
+            )),
+        }
+    }
+
+    /// Release space that was reserved but not used.
+    fn release(&self, size: usize) {
+        if let Some(permits) = &self.permits {
+            permits.add_permits(size);
+        }
+    }
+}
 
 /// The shared reader implementation using channels.
+///
+/// Each subscriber has its own bounded queue of `capacity` chunks. The reader waits for space in
+/// every queue before reading the next chunk, so a slow checksum applies backpressure to the
+/// reader rather than lagging behind and dropping chunks. The chunks themselves are shared between
+/// queues without copying.
+///
+/// Because chunks are shared, the memory used by the reader is bounded by the subscriber with
+/// the most bytes buffered, which is at most `capacity` queued chunks, plus the chunk that it is
+/// processing and the chunk being read. This is the value of `max_buffered_bytes`. Setting a
+/// maximum buffer size further limits the bytes buffered for each subscriber, including the
+/// chunk being read, so that large objects can be read with a fixed amount of memory.
 #[derive(Debug)]
 pub struct ChannelReader<R> {
     inner: BufReader<R>,
-    txs: Vec<mpsc::Sender<Arc<[u8]>>>,
+    subscribers: Vec<Subscriber>,
     capacity: usize,
+    chunk_size: usize,
+    auto: bool,
+    max_buffer_bytes: Option<usize>,
+    buffer_high_water_bytes: usize,
+    stop: Option<CancellationToken>,
 }
 
 impl<R> ChannelReader<R>
@@ -25,8 +131,77 @@ where
     pub fn new(inner: R, capacity: usize) -> Self {
         Self {
             inner: BufReader::new(inner),
-            txs: vec![],
+            subscribers: vec![],
             capacity,
+            chunk_size: AUTO_STREAM_CHUNK_SIZE,
+            auto: true,
+            max_buffer_bytes: None,
+            buffer_high_water_bytes: 0,
+            stop: None,
+        }
+    }
+
+    /// Stop reading before the next chunk once the token is cancelled, ending the streams of
+    /// subscribers as if the end of the reader was reached.
+    pub fn with_stop(mut self, stop: CancellationToken) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Limit the bytes buffered for each subscriber. Chunks are no larger than this limit. This
+    /// must be set before subscribing.
+    pub fn set_max_buffer_bytes(mut self, max_buffer_bytes: Option<usize>) -> Self {
+        self.max_buffer_bytes = max_buffer_bytes;
+        self
+    }
+
+    /// The most bytes that can be buffered by the reader at any time, from the channel
+    /// capacity, the largest chunk size and the maximum buffer size.
+    pub fn max_buffered_bytes(&self) -> usize {
+        let chunk_size = if self.auto {
+            MAX_AUTO_CHUNK_SIZE.max(self.chunk_size)
+        } else {
+            self.chunk_size
+        };
+        let unlimited = self.capacity.saturating_add(2).saturating_mul(chunk_size);
+
+        self.max_buffer_bytes
+            .map_or(unlimited, |max| unlimited.min(max))
+    }
+
+    /// The most bytes that were buffered for a single subscriber while reading, which is useful
+    /// for tuning the channel capacity and `--max-buffer-bytes`.
+    pub fn buffer_high_water_bytes(&self) -> usize {
+        self.buffer_high_water_bytes
+    }
+
+    /// Set the chunk size. If the chunk size is automatic, `local` determines whether it starts
+    /// from the size used for local files or for streams.
+    pub fn with_chunk_size(mut self, chunk_size: ChunkSize, local: bool) -> Self {
+        (self.chunk_size, self.auto) = match chunk_size {
+            ChunkSize::Auto if local => (AUTO_FILE_CHUNK_SIZE, true),
+            ChunkSize::Auto => (AUTO_STREAM_CHUNK_SIZE, true),
+            ChunkSize::Fixed(chunk_size) => (chunk_size, false),
+        };
+        self
+    }
+
+    /// Get the current chunk size. After reading, this is the size that automatic tuning
+    /// settled on.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Adjust the chunk size from the latency of the last read. Larger chunks reduce the
+    /// per-chunk overhead for sources that fill them quickly, and smaller chunks avoid large
+    /// allocations for sources that can only return partial chunks.
+    fn tune(chunk_size: usize, n: usize, elapsed: Duration) -> usize {
+        if n == chunk_size && elapsed < FAST_READ {
+            (chunk_size * 2).min(MAX_AUTO_CHUNK_SIZE)
+        } else if n < chunk_size / 2 && elapsed > SLOW_READ {
+            (chunk_size / 2).max(MIN_AUTO_CHUNK_SIZE)
+        } else {
+            chunk_size
         }
     }
 
@@ -38,27 +213,68 @@ where
     /// Subscribe to the channel returning a stream of elements polled from the sender channel
     pub fn subscribe_stream(&mut self) -> impl Stream<Item = Result<Arc<[u8]>>> + 'static {
         let (tx, mut rx) = mpsc::channel(self.capacity);
-        self.txs.push(tx);
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let permits = self
+            .max_buffer_bytes
+            .map(|max_buffer_bytes| Arc::new(Semaphore::new(max_buffer_bytes)));
+        self.subscribers.push(Subscriber {
+            tx,
+            buffered: buffered.clone(),
+            permits: permits.clone(),
+        });
 
         stream! {
             let mut msg = rx.recv().await;
             // Poll the channel until the end is reached.
             while let Some(buf) = msg {
+                let len = buf.len();
                 yield Ok(buf);
+
+                // The consumer has finished with a chunk once it polls for the next one.
+                buffered.fetch_sub(len, Ordering::Relaxed);
+                if let Some(permits) = &permits {
+                    permits.add_permits(len);
+                }
                 msg = rx.recv().await;
             }
         }
     }
 
-    /// Send data to the channel until the end of the reader is reached. Returns the size of the file.
+    /// Send data to the channel until the end of the reader is reached, or reading is stopped.
+    /// Returns the number of bytes read.
     pub async fn send_to_end(&mut self) -> Result<u64> {
-        let txs = self.txs.drain(..);
+        let subscribers: Vec<_> = self.subscribers.drain(..).collect();
+        if self.max_buffer_bytes == Some(0) {
+            return Err(MemoryError(
+                "the maximum buffer size must be greater than 0".to_string(),
+            ));
+        }
 
         let mut size = 0;
         loop {
+            if self
+                .stop
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+            {
+                break;
+            }
+
+            // Reserve space for the chunk in every buffer before reading it.
+            let chunk_size = self
+                .max_buffer_bytes
+                .map_or(self.chunk_size, |max| self.chunk_size.min(max));
+            for subscriber in &subscribers {
+                subscriber.reserve(chunk_size).await?;
+            }
+
             // Read data into a buffer.
-            let mut buf = vec![0; 1000];
+            let mut buf = vec![0; chunk_size];
+            let now = Instant::now();
             let n = self.inner.read(&mut buf).await?;
+            for subscriber in &subscribers {
+                subscriber.release(chunk_size - n);
+            }
 
             // Stop if there is no more data.
             if n == 0 {
@@ -66,12 +282,17 @@ where
             }
 
             size += n;
+            if self.auto {
+                self.chunk_size = Self::tune(chunk_size, n, now.elapsed());
+            }
 
             // Send the buffer. An Arc allows sharing the buffer across multiple receivers without
             // copying it.
             let buf: Arc<[u8]> = Arc::from(&buf[0..n]);
-            for tx in txs.as_ref() {
-                tx.send(buf.clone()).await?;
+            for subscriber in &subscribers {
+                let buffered = subscriber.buffered.fetch_add(n, Ordering::Relaxed) + n;
+                self.buffer_high_water_bytes = self.buffer_high_water_bytes.max(buffered);
+                subscriber.tx.send(buf.clone()).await?;
             }
         }
 
@@ -92,6 +313,14 @@ where
     fn as_stream(&mut self) -> ReaderStream {
         Box::pin(self.subscribe_stream())
     }
+
+    fn chunk_size(&self) -> Option<usize> {
+        Some(self.chunk_size)
+    }
+
+    fn buffer_high_water_bytes(&self) -> Option<usize> {
+        Some(self.buffer_high_water_bytes)
+    }
 }
 
 #[cfg(test)]
@@ -102,6 +331,7 @@ pub(crate) mod test {
     use futures_util::StreamExt;
     use rand::Rng;
     use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[tokio::test]
     async fn test_stream() -> Result<()> {
@@ -128,6 +358,154 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn stop_reading() -> Result<()> {
+        let stop = CancellationToken::new();
+        let mut reader = channel_reader(Cursor::new(vec![1; 100]))
+            .await
+            .with_stop(stop.clone());
+        let stream = reader.as_stream();
+
+        stop.cancel();
+        assert_eq!(reader.read_chunks().await?, 0);
+        assert!(stream.collect::<Vec<_>>().await.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_chunk_size() -> Result<()> {
+        assert_eq!(ChunkSize::from_str("auto")?, ChunkSize::Auto);
+        assert_eq!(ChunkSize::from_str("1MiB")?, ChunkSize::Fixed(1048576));
+        assert_eq!(ChunkSize::from_str("1000")?, ChunkSize::Fixed(1000));
+        assert!(ChunkSize::from_str("0").is_err());
+        assert!(ChunkSize::from_str("fast").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tune_chunk_size() {
+        let fast = Duration::ZERO;
+        let slow = SLOW_READ * 2;
+        let size = AUTO_FILE_CHUNK_SIZE;
+
+        assert_eq!(
+            ChannelReader::<Cursor<Vec<u8>>>::tune(size, size, fast),
+            size * 2
+        );
+        assert_eq!(
+            ChannelReader::<Cursor<Vec<u8>>>::tune(MAX_AUTO_CHUNK_SIZE, MAX_AUTO_CHUNK_SIZE, fast),
+            MAX_AUTO_CHUNK_SIZE
+        );
+        assert_eq!(
+            ChannelReader::<Cursor<Vec<u8>>>::tune(size, 10, slow),
+            size / 2
+        );
+        assert_eq!(
+            ChannelReader::<Cursor<Vec<u8>>>::tune(MIN_AUTO_CHUNK_SIZE, 10, slow),
+            MIN_AUTO_CHUNK_SIZE
+        );
+        assert_eq!(
+            ChannelReader::<Cursor<Vec<u8>>>::tune(size, size, slow),
+            size
+        );
+    }
+
+    #[tokio::test]
+    async fn fixed_chunk_size() -> Result<()> {
+        let mut reader = channel_reader(Cursor::new(vec![1; 2500]))
+            .await
+            .with_chunk_size(ChunkSize::Fixed(1000), true);
+        let stream = reader.as_stream();
+        reader.read_chunks().await?;
+
+        let chunks: Vec<_> = stream
+            .map(|value| Ok(value?.len()))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_>>()?;
+        assert_eq!(chunks, [1000, 1000, 500]);
+        assert_eq!(reader.chunk_size(), 1000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn slow_consumer_applies_backpressure() -> Result<()> {
+        let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut reader = ChannelReader::new(Cursor::new(data.clone()), 1)
+            .with_chunk_size(ChunkSize::Fixed(100), true);
+
+        let received = Arc::new(AtomicUsize::new(0));
+        let slow = {
+            let received = received.clone();
+            let mut stream = reader.as_stream();
+            tokio::spawn(async move {
+                let mut result = vec![];
+                while let Some(chunk) = stream.next().await {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    result.extend_from_slice(&chunk?);
+                    received.fetch_add(1, Ordering::SeqCst);
+                }
+                Ok::<_, anyhow::Error>(result)
+            })
+        };
+        let fast = reader.as_stream().map(|chunk| Ok(chunk?.to_vec()));
+        let fast =
+            tokio::spawn(
+                async move { Ok::<_, anyhow::Error>(fast.collect::<Vec<Result<_>>>().await) },
+            );
+
+        assert_eq!(reader.read_chunks().await?, 10000);
+        // The reader can only be ahead of the slow consumer by the queue capacity and the chunk
+        // that is waiting to be sent.
+        assert!(received.load(Ordering::SeqCst) >= 100 - 2);
+
+        assert_eq!(slow.await??, data);
+        let fast = fast
+            .await??
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        assert_eq!(fast, data);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn max_buffer_bytes_bounds_buffering() -> Result<()> {
+        let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut reader = ChannelReader::new(Cursor::new(data.clone()), 10)
+            .with_chunk_size(ChunkSize::Fixed(1000), true)
+            .set_max_buffer_bytes(Some(300));
+        assert_eq!(reader.max_buffered_bytes(), 300);
+
+        let mut stream = reader.as_stream();
+        let slow = tokio::spawn(async move {
+            let mut result = vec![];
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                assert!(chunk.len() <= 300);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                result.extend_from_slice(&chunk);
+            }
+            Ok::<_, anyhow::Error>(result)
+        });
+
+        assert_eq!(reader.read_chunks().await?, 10000);
+        assert_eq!(slow.await??, data);
+        let high_water = reader.buffer_high_water_bytes();
+        assert!(high_water > 0 && high_water <= 300);
+
+        let reader =
+            ChannelReader::new(Cursor::new(data), 1).with_chunk_size(ChunkSize::Fixed(1000), true);
+        assert_eq!(reader.max_buffered_bytes(), 3000);
+
+        Ok(())
+    }
+
     pub(crate) async fn channel_reader<R>(inner: R) -> ChannelReader<R>
     where
         R: AsyncRead + Unpin,
@@ -0,0 +1,227 @@
+//! Reading and writing sums from an external catalog service, such as a file manager API.
+//!
+
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::{ApiError, Result};
+use crate::io::RequestParameter;
+use crate::io::sums::{ObjectSnapshot, ObjectSums, ObjectTags};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+
+/// A source of sums outside the object store, such as an organizational file catalog. Sums are
+/// looked up and stored by the location of the object.
+#[async_trait::async_trait]
+pub trait SumsSource: Debug {
+    /// Get the sums for the object location, if the source has any.
+    async fn get(&self, location: &str) -> Result<Option<SumsFile>>;
+
+    /// Store the sums for the object location.
+    async fn put(&self, location: &str, sums_file: &SumsFile) -> Result<()>;
+}
+
+/// A sums source backed by an HTTP API. Sums are fetched with `GET <url>?location=<location>`,
+/// where a `404` means that there are no sums, and stored with `PUT <url>?location=<location>`.
+/// Both use the JSON format of a `.sums` file as the body.
+#[derive(Debug, Clone)]
+pub struct HttpSumsSource {
+    client: Client,
+    url: String,
+    parameters: Vec<RequestParameter>,
+}
+
+impl HttpSumsSource {
+    /// Create a new HTTP sums source. The parameters are sent with every request, which can be
+    /// used to add authentication headers.
+    pub fn new(url: String, parameters: Vec<RequestParameter>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            parameters,
+        }
+    }
+
+    /// Add the location and any additional parameters to a request.
+    fn prepare(&self, request: RequestBuilder, location: &str) -> RequestBuilder {
+        let request = request.query(&[("location", location)]);
+
+        self.parameters
+            .iter()
+            .fold(request, |request, parameter| match parameter {
+                RequestParameter::Header(name, value) => request.header(name, value),
+                RequestParameter::Query(name, value) => request.query(&[(name, value)]),
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl SumsSource for HttpSumsSource {
+    async fn get(&self, location: &str) -> Result<Option<SumsFile>> {
+        let response = self
+            .prepare(self.client.get(&self.url), location)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let body = response.error_for_status()?.bytes().await?;
+        Ok(Some(SumsFile::read_from_slice(&body).await?))
+    }
+
+    async fn put(&self, location: &str, sums_file: &SumsFile) -> Result<()> {
+        self.prepare(self.client.put(&self.url), location)
+            .header("content-type", "application/json")
+            .body(sums_file.to_json_string()?)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Object sums which are combined with a sums source. Existing sums from the object take
+/// precedence over the sums source, and written sums are stored in both.
+#[derive(Debug, Clone)]
+pub struct ExternalSums<T> {
+    inner: T,
+    source: Arc<dyn SumsSource + Send + Sync>,
+}
+
+impl<T> ExternalSums<T> {
+    /// Create new external sums wrapping the object sums.
+    pub fn new(inner: T, source: Arc<dyn SumsSource + Send + Sync>) -> Self {
+        Self { inner, source }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> ObjectSums for ExternalSums<T>
+where
+    T: ObjectSums + Clone + Send + Sync,
+{
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        let external = self.source.get(&self.inner.location()).await?;
+
+        match (external, self.inner.sums_file().await?) {
+            (Some(external), Some(existing)) => Ok(Some(external.merge(existing)?)),
+            (external, existing) => Ok(existing.or(external)),
+        }
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.reader().await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        self.inner.reader_from(offset).await
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.range_reader(start, end).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        self.inner.file_size().await
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        self.inner.write_sums_file(sums_file).await?;
+        self.source.put(&self.inner.location(), sums_file).await
+    }
+
+    async fn object_tags(&mut self) -> Result<Option<ObjectTags>> {
+        self.inner.object_tags().await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        self.inner.object_version().await
+    }
+
+    async fn object_snapshot(&mut self) -> Result<Option<ObjectSnapshot>> {
+        self.inner.object_snapshot().await
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        self.inner.write_snapshot(snapshot).await
+    }
+
+    fn location(&self) -> String {
+        self.inner.location()
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        self.inner.api_errors()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::file::Checksum;
+    use crate::io::sums::file::File;
+    use anyhow::Result;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    /// An in-memory sums source.
+    #[derive(Debug, Default)]
+    struct MemorySumsSource(Mutex<HashMap<String, SumsFile>>);
+
+    #[async_trait::async_trait]
+    impl SumsSource for MemorySumsSource {
+        async fn get(&self, location: &str) -> crate::error::Result<Option<SumsFile>> {
+            Ok(self.0.lock().unwrap().get(location).cloned())
+        }
+
+        async fn put(&self, location: &str, sums_file: &SumsFile) -> crate::error::Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(location.to_string(), sums_file.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn external_sums_round_trip() -> Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("file").to_string_lossy().to_string();
+        tokio::fs::write(&path, b"data").await?;
+
+        let source = Arc::new(MemorySumsSource::default());
+        let sums = |sum: &str| {
+            let mut sums_file = SumsFile::default().with_size(Some(4));
+            sums_file.add_checksum(
+                Ctx::from_str("md5").unwrap(),
+                Checksum::new(sum.to_string()),
+            );
+            sums_file
+        };
+        source.put(&path, &sums("external")).await?;
+
+        let mut external = ExternalSums::new(File::new(path.clone()), source.clone());
+        assert_eq!(external.sums_file().await?, Some(sums("external")));
+
+        // Sums written to the object are also stored in the source, and take precedence.
+        external.write_sums_file(&sums("written")).await?;
+        assert_eq!(source.get(&path).await?, Some(sums("written")));
+        assert_eq!(external.sums_file().await?, Some(sums("written")));
+
+        Ok(())
+    }
+}
@@ -2,13 +2,15 @@
 //!
 
 use crate::checksum::file::SumsFile;
-use crate::error::Error::ParseError;
+use crate::error::Error::{InsufficientSpace, ParseError};
 use crate::error::{ApiError, Result};
-use crate::io::sums::ObjectSums;
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::fs::Metadata;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
 use tokio::fs;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, copy};
 
 /// Build a file based sums object.
 #[derive(Debug, Default)]
@@ -40,6 +42,70 @@ impl From<String> for File {
     }
 }
 
+/// A temporary file that input such as stdin is spooled to so that its size is known before
+/// computing checksums. The file is removed when this is dropped.
+#[derive(Debug)]
+pub struct SpoolFile {
+    path: PathBuf,
+    size: u64,
+}
+
+impl SpoolFile {
+    /// Spool the reader to a new temporary file in the directory. If `max_size` is set, this
+    /// fails and removes the file once more than `max_size` bytes are read.
+    pub async fn spool(
+        reader: impl AsyncRead + Unpin,
+        directory: &Path,
+        max_size: Option<u64>,
+    ) -> Result<Self> {
+        let path = directory.join(format!(
+            "copyrite-spool-{}-{:016x}",
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+
+        // Construct the spool before writing so that the file is cleaned up on errors.
+        let mut spool = Self { path, size: 0 };
+        let mut file = fs::File::create_new(&spool.path).await?;
+        // Read one byte more than the maximum to detect input that is too large.
+        let mut reader = reader.take(max_size.map_or(u64::MAX, |max_size| max_size + 1));
+        spool.size = copy(&mut reader, &mut file).await?;
+        file.flush().await?;
+
+        if let Some(max_size) = max_size
+            && spool.size > max_size
+        {
+            return Err(InsufficientSpace(format!(
+                "spooled input exceeds the maximum temporary usage of {} bytes",
+                max_size
+            )));
+        }
+
+        Ok(spool)
+    }
+
+    /// The number of bytes spooled.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The path of the spooled file.
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Open a reader over the spooled data.
+    pub async fn reader(&self) -> Result<fs::File> {
+        Ok(fs::File::open(&self.path).await?)
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
 /// A file object.
 #[derive(Debug, Clone)]
 pub struct File {
@@ -74,12 +140,52 @@ impl File {
         Ok(fs::File::open(&path).await?)
     }
 
-    /// Get the size of the target file.
+    /// Get the size of the target file. Block devices report a length of zero, so their size
+    /// is found by seeking to the end. The size of named pipes and character devices is not
+    /// known, so they are streamed.
     pub async fn size(&self) -> Result<Option<u64>> {
-        Ok(fs::metadata(SumsFile::format_target_file(&self.file))
-            .await
-            .ok()
-            .map(|metadata| metadata.len()))
+        let path = SumsFile::format_target_file(&self.file);
+        let Ok(metadata) = fs::metadata(&path).await else {
+            return Ok(None);
+        };
+
+        if Self::is_block_device(&metadata) {
+            let mut file = fs::File::open(&path).await?;
+            Ok(Some(file.seek(SeekFrom::End(0)).await?))
+        } else if Self::is_stream(&metadata) {
+            Ok(None)
+        } else {
+            Ok(Some(metadata.len()))
+        }
+    }
+
+    /// Whether the file is a block device, such as a raw disk.
+    #[cfg(unix)]
+    fn is_block_device(metadata: &Metadata) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+
+        metadata.file_type().is_block_device()
+    }
+
+    /// Whether the file is a block device, such as a raw disk.
+    #[cfg(not(unix))]
+    fn is_block_device(_metadata: &Metadata) -> bool {
+        false
+    }
+
+    /// Whether the file can only be read sequentially, such as a named pipe or character device.
+    #[cfg(unix)]
+    fn is_stream(metadata: &Metadata) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+
+        let file_type = metadata.file_type();
+        file_type.is_fifo() || file_type.is_char_device()
+    }
+
+    /// Whether the file can only be read sequentially, such as a named pipe or character device.
+    #[cfg(not(unix))]
+    fn is_stream(_metadata: &Metadata) -> bool {
+        false
     }
 
     /// Write the sums file to the configured location.
@@ -88,6 +194,13 @@ impl File {
         fs::write(&path, sums_file.to_json_string()?).await?;
         Ok(())
     }
+
+    /// Write a metadata snapshot next to the file.
+    pub async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let path = ObjectSnapshot::format_snapshot_file(&self.file);
+        fs::write(&path, snapshot.to_json_string()?).await?;
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -100,6 +213,20 @@ impl ObjectSums for File {
         Ok(Box::new(self.sums_reader().await?))
     }
 
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        let path = SumsFile::format_target_file(&self.file);
+        if Self::is_stream(&fs::metadata(&path).await?) {
+            return Ok(None);
+        }
+
+        let mut file = fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(Some(Box::new(file)))
+    }
+
     async fn file_size(&mut self) -> Result<Option<u64>> {
         self.size().await
     }
@@ -108,6 +235,10 @@ impl ObjectSums for File {
         self.write_sums(sums_file).await
     }
 
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        File::write_snapshot(self, snapshot).await
+    }
+
     fn location(&self) -> String {
         self.file.to_string()
     }
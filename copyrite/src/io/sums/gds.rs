@@ -0,0 +1,190 @@
+//! GDS sums file logic. The `ETag` of a file is used as an existing checksum, and sums files are
+//! written as another file next to it.
+//!
+
+use crate::checksum::file::SumsFile;
+use crate::error::Error::{GdsError, ParseError};
+use crate::error::{ApiError, Result};
+use crate::io::Provider;
+use crate::io::gds::{GdsClient, GdsFile};
+use crate::io::http::HttpClient;
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build a GDS sums object.
+#[derive(Debug, Default)]
+pub struct GdsBuilder {
+    client: Option<GdsClient>,
+    volume: Option<String>,
+    path: Option<String>,
+}
+
+impl GdsBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: GdsClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the volume.
+    pub fn with_volume(mut self, volume: String) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    /// Set the path.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    fn get_components(self) -> Result<(GdsClient, String, String)> {
+        let error_fn = || ParseError("volume and path are required in `GdsBuilder`".to_string());
+
+        Ok((
+            self.client.unwrap_or_default(),
+            self.volume.ok_or_else(error_fn)?,
+            self.path.ok_or_else(error_fn)?,
+        ))
+    }
+
+    /// Build the GDS sums object.
+    pub fn build(self) -> Result<Gds> {
+        let (client, volume, path) = self.get_components()?;
+        Ok(Gds::new(client, volume, path))
+    }
+}
+
+/// A file in a GDS volume.
+#[derive(Debug, Clone)]
+pub struct Gds {
+    client: GdsClient,
+    http: HttpClient,
+    volume: String,
+    path: String,
+    file: Option<GdsFile>,
+}
+
+impl Gds {
+    /// Create a new GDS file.
+    pub fn new(client: GdsClient, volume: String, path: String) -> Self {
+        Self {
+            client,
+            http: HttpClient::default(),
+            volume,
+            path,
+            file: None,
+        }
+    }
+
+    /// Get the file from the API. This caches the result so that subsequent calls do not
+    /// repeat the request.
+    pub async fn file(&mut self) -> Result<&GdsFile> {
+        if self.file.is_none() {
+            let file = self
+                .client
+                .file(&self.volume, &self.path)
+                .await?
+                .ok_or_else(|| self.not_found_error())?;
+            self.file = Some(file);
+        }
+
+        self.file.as_ref().ok_or_else(|| self.not_found_error())
+    }
+
+    /// Get an existing sums file if one is stored next to the file.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_path = SumsFile::format_sums_file(&self.path);
+        let Some(file) = self.client.file(&self.volume, &sums_path).await? else {
+            return Ok(None);
+        };
+        let Some(data) = self.http.get_optional_bytes(file.presigned_url()?).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    /// Write a JSON document as a file next to this file.
+    async fn put_json(&self, path: &str, json: String) -> Result<()> {
+        self.client
+            .create_file(&self.volume, path)
+            .await?
+            .put(json.into_bytes())
+            .await
+    }
+
+    /// Read the file from `start` up to, but not including, `end`.
+    async fn read(
+        &mut self,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let url = self.file().await?.presigned_url()?.to_string();
+        let reader = self
+            .http
+            .get(&url, start, end)
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        Ok(reader)
+    }
+
+    fn not_found_error(&self) -> crate::error::Error {
+        GdsError(format!("{} does not exist", self.location()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for Gds {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        let mut sums_file = self.file().await?.sums()?;
+        if let Some(existing) = self.get_existing_sums().await? {
+            sums_file = sums_file.merge(existing)?;
+        }
+
+        Ok((!sums_file.checksums.is_empty()).then_some(sums_file))
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(0, None).await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        Ok(Some(self.read(offset, None).await?))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(start, Some(end)).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        Ok(Some(self.file().await?.size()?))
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        let sums_path = SumsFile::format_sums_file(&self.path);
+        self.put_json(&sums_path, sums_file.to_json_string()?).await
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let snapshot_path = ObjectSnapshot::format_snapshot_file(&self.path);
+        self.put_json(&snapshot_path, snapshot.to_json_string()?)
+            .await
+    }
+
+    fn location(&self) -> String {
+        Provider::format_gds(&self.volume, &self.path)
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
@@ -0,0 +1,153 @@
+//! HTTP sums file logic. HTTP locations are read-only, so sums files can be read from next to
+//! the object but not written.
+//!
+
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::Error::{HttpError, ParseError};
+use crate::error::{ApiError, Result};
+use crate::io::http::{HttpClient, HttpProperties};
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build an HTTP sums object.
+#[derive(Debug, Default)]
+pub struct HttpBuilder {
+    client: Option<HttpClient>,
+    url: Option<String>,
+}
+
+impl HttpBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the url.
+    pub fn with_url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Build the HTTP sums object.
+    pub fn build(self) -> Result<Http> {
+        let url = self
+            .url
+            .ok_or_else(|| ParseError("a url is required in `HttpBuilder`".to_string()))?;
+        Ok(Http::new(self.client.unwrap_or_default(), url))
+    }
+}
+
+/// An HTTP object.
+#[derive(Debug, Clone)]
+pub struct Http {
+    client: HttpClient,
+    url: String,
+    properties: Option<HttpProperties>,
+}
+
+impl Http {
+    /// Create a new HTTP object.
+    pub fn new(client: HttpClient, url: String) -> Self {
+        Self {
+            client,
+            url,
+            properties: None,
+        }
+    }
+
+    /// Get the properties of the object. This caches the result so that subsequent calls do
+    /// not repeat the request.
+    pub async fn properties(&mut self) -> Result<&HttpProperties> {
+        if self.properties.is_none() {
+            let properties = self
+                .client
+                .head(&self.url)
+                .await?
+                .ok_or_else(|| HttpError(format!("{} does not exist", self.url)))?;
+            self.properties = Some(properties);
+        }
+
+        Ok(self.properties.get_or_insert_default())
+    }
+
+    /// Get an existing sums file if one is published next to the object.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_url = SumsFile::format_sums_file(&self.url);
+        let Some(data) = self.client.get_optional_bytes(&sums_url).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    fn read_only_error(&self) -> crate::error::Error {
+        HttpError(format!(
+            "cannot write next to {}, HTTP locations are read-only",
+            self.url
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for Http {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        self.get_existing_sums().await
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get(&self.url, 0, None)
+            .await?
+            .ok_or_else(|| HttpError(format!("{} does not exist", self.url)))?;
+        Ok(reader)
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        let reader = self.client.get(&self.url, offset, None).await?;
+        Ok(reader.map(|reader| reader as Box<dyn AsyncRead + Unpin + Send>))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get(&self.url, start, Some(end))
+            .await?
+            .ok_or_else(|| HttpError(format!("{} does not exist", self.url)))?;
+        Ok(reader)
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        Ok(self.properties().await?.size)
+    }
+
+    async fn write_sums_file(&self, _sums_file: &SumsFile) -> Result<()> {
+        Err(self.read_only_error())
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        let properties = self.properties().await?;
+        Ok(Some(ObjectVersion::new(properties.e_tag.clone(), None)))
+    }
+
+    async fn write_snapshot(&self, _snapshot: &ObjectSnapshot) -> Result<()> {
+        Err(self.read_only_error())
+    }
+
+    fn location(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
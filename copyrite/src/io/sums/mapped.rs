@@ -0,0 +1,385 @@
+//! Reading and writing sums files which are stored at a different location than their object,
+//! such as in a separate bucket.
+//!
+
+use crate::checksum::file::{ObjectVersion, SUMS_FILE_ENDING, SumsFile};
+use crate::error::Error::ParseError;
+use crate::error::{ApiError, Result};
+use crate::io::azure::AzureClient;
+use crate::io::gds::GdsClient;
+use crate::io::registry::{CustomSums, ProviderRegistry};
+#[cfg(unix)]
+use crate::io::sftp::SftpClient;
+use crate::io::sums::aws::{S3, S3Builder};
+use crate::io::sums::azure::{Azure, AzureBuilder};
+use crate::io::sums::file::{File, FileBuilder};
+use crate::io::sums::gds::{Gds, GdsBuilder};
+use crate::io::sums::http::{Http, HttpBuilder};
+#[cfg(unix)]
+use crate::io::sums::sftp::{Sftp, SftpBuilder};
+use crate::io::sums::{ObjectSnapshot, ObjectSums, ObjectTags};
+use crate::io::{Provider, S3Client};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::fs;
+use tokio::io::AsyncRead;
+
+/// The separator between an object and its sums file in an input, e.g.
+/// `s3://bucket/key#s3://sums-bucket/key.sums`.
+pub const SUMS_URL_SEPARATOR: char = '#';
+
+/// A map from object locations to the location of their sums file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SumsMap {
+    sums: HashMap<String, String>,
+}
+
+impl SumsMap {
+    /// Read a sums map from a file.
+    pub async fn read(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path).await?.parse()
+    }
+
+    /// Add the sums file location for an object.
+    pub fn insert(&mut self, object: &str, sums: &str) {
+        self.sums.insert(
+            SumsFile::format_target_file(object),
+            SumsFile::format_sums_file(sums),
+        );
+    }
+
+    /// Get the sums file location for an object, if it is mapped.
+    pub fn get(&self, object: &str) -> Option<&str> {
+        self.sums
+            .get(&SumsFile::format_target_file(object))
+            .map(String::as_str)
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.sums.is_empty()
+    }
+
+    /// Split an input of the form `<object>#<sums>` into the object and the sums file location,
+    /// adding the pair to the map. The sums location must end in `.sums` so that objects
+    /// containing the separator are not split. Returns the object location.
+    pub fn split_input(&mut self, input: &str) -> String {
+        match input.rsplit_once(SUMS_URL_SEPARATOR) {
+            Some((object, sums)) if !object.is_empty() && sums.ends_with(SUMS_FILE_ENDING) => {
+                self.insert(object, sums);
+                object.to_string()
+            }
+            _ => input.to_string(),
+        }
+    }
+}
+
+impl FromStr for SumsMap {
+    type Err = crate::error::Error;
+
+    /// Parse a CSV file with a header row containing `object` and `sums` columns.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| ParseError("sums map is empty".to_string()))?;
+        let header: Vec<&str> = header.split(',').map(str::trim).collect();
+        let (Some(object), Some(sums), 2) = (
+            header.iter().position(|column| *column == "object"),
+            header.iter().position(|column| *column == "sums"),
+            header.len(),
+        ) else {
+            return Err(ParseError(
+                "sums map must only contain `object` and `sums` columns".to_string(),
+            ));
+        };
+
+        let mut map = Self::default();
+        for (line, row) in lines {
+            let values: Vec<&str> = row.split(',').map(str::trim).collect();
+            if values.len() != 2 || values.iter().any(|value| value.is_empty()) {
+                return Err(ParseError(format!(
+                    "line {} of sums map must contain an object and a sums file",
+                    line
+                )));
+            }
+
+            map.insert(values[object], values[sums]);
+        }
+
+        Ok(map)
+    }
+}
+
+/// The location of a sums file that is not next to its object.
+#[derive(Debug, Clone)]
+pub enum SumsLocation {
+    File(File),
+    S3(Box<S3>),
+    Azure(Box<Azure>),
+    Http(Box<Http>),
+    #[cfg(unix)]
+    Sftp(Box<Sftp>),
+    Gds(Box<Gds>),
+    Custom(Box<CustomSums>),
+}
+
+impl SumsLocation {
+    /// Create the sums location from a URL. S3 locations require a client, and Azure locations
+    /// use the client configured by the environment. HTTP locations can only be read, and SFTP
+    /// and GDS locations use the options of the client for each. Custom locations, and Swift and
+    /// WebDAV locations, are built by the factory registered for their scheme.
+    pub async fn build(url: &str, client: Option<S3Client>) -> Result<Self> {
+        match Provider::try_from(url)? {
+            Provider::File { file } => {
+                Ok(Self::File(FileBuilder::default().with_file(file).build()?))
+            }
+            Provider::S3 { bucket, key } | Provider::Gcs { bucket, key } => {
+                let client = client.ok_or_else(|| {
+                    ParseError("an S3 client is required for S3 sums files".to_string())
+                })?;
+                Ok(Self::S3(Box::new(
+                    S3Builder::default()
+                        .with_key(key)
+                        .with_bucket(bucket)
+                        .with_client(client)
+                        .build()?,
+                )))
+            }
+            Provider::Azure { container, blob } => Ok(Self::Azure(Box::new(
+                AzureBuilder::default()
+                    .with_container(container)
+                    .with_blob(blob)
+                    .with_client(AzureClient::from_env()?)
+                    .build()?,
+            ))),
+            Provider::Http { url } => Ok(Self::Http(Box::new(
+                HttpBuilder::default().with_url(url).build()?,
+            ))),
+            #[cfg(unix)]
+            Provider::Sftp { host, path } => {
+                let options = client
+                    .map(|client| client.sftp_options().clone())
+                    .unwrap_or_default();
+                Ok(Self::Sftp(Box::new(
+                    SftpBuilder::default()
+                        .with_host(host)
+                        .with_path(path)
+                        .with_client(SftpClient::new(options))
+                        .build()?,
+                )))
+            }
+            #[cfg(not(unix))]
+            Provider::Sftp { .. } => Err(crate::io::sftp_unsupported_error()),
+            Provider::Gds { volume, path } => {
+                let options = client
+                    .map(|client| client.gds_options().clone())
+                    .unwrap_or_default();
+                Ok(Self::Gds(Box::new(
+                    GdsBuilder::default()
+                        .with_volume(volume)
+                        .with_path(path)
+                        .with_client(GdsClient::new(options))
+                        .build()?,
+                )))
+            }
+            provider @ (Provider::Swift { .. }
+            | Provider::WebDav { .. }
+            | Provider::Custom { .. }) => {
+                let (scheme, url) = provider.into_registered()?;
+                Ok(Self::Custom(Box::new(CustomSums::new(
+                    ProviderRegistry::try_get(&scheme)?
+                        .sums_with_client(client.as_ref(), &url)
+                        .await?,
+                ))))
+            }
+        }
+    }
+
+    /// Read the sums file, if it exists.
+    pub async fn get(&self) -> Result<Option<SumsFile>> {
+        match self {
+            Self::File(file) => file.get_existing_sums().await,
+            Self::S3(s3) => s3.get_existing_sums().await,
+            Self::Azure(azure) => azure.get_existing_sums().await,
+            Self::Http(http) => http.get_existing_sums().await,
+            #[cfg(unix)]
+            Self::Sftp(sftp) => sftp.get_existing_sums().await,
+            Self::Gds(gds) => gds.get_existing_sums().await,
+            Self::Custom(custom) => custom.get_existing_sums().await,
+        }
+    }
+
+    /// Write the sums file.
+    pub async fn put(&self, sums_file: &SumsFile) -> Result<()> {
+        match self {
+            Self::File(file) => file.write_sums(sums_file).await,
+            Self::S3(s3) => s3.put_sums(sums_file).await,
+            Self::Azure(azure) => azure.put_sums(sums_file).await,
+            Self::Http(http) => http.write_sums_file(sums_file).await,
+            #[cfg(unix)]
+            Self::Sftp(sftp) => sftp.write_sums_file(sums_file).await,
+            Self::Gds(gds) => gds.write_sums_file(sums_file).await,
+            Self::Custom(custom) => custom.write_sums_file(sums_file).await,
+        }
+    }
+}
+
+/// Object sums where the sums file is read from and written to a mapped location. Sums from
+/// the object itself, such as S3 metadata, are still used, with the mapped sums file taking
+/// precedence.
+#[derive(Debug, Clone)]
+pub struct MappedSums<T> {
+    inner: T,
+    sums: SumsLocation,
+}
+
+impl<T> MappedSums<T> {
+    /// Create new mapped sums wrapping the object sums.
+    pub fn new(inner: T, sums: SumsLocation) -> Self {
+        Self { inner, sums }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> ObjectSums for MappedSums<T>
+where
+    T: ObjectSums + Clone + Send + Sync,
+{
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        match (self.sums.get().await?, self.inner.sums_file().await?) {
+            (Some(mapped), Some(existing)) => Ok(Some(mapped.merge(existing)?)),
+            (mapped, existing) => Ok(mapped.or(existing)),
+        }
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.reader().await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        self.inner.reader_from(offset).await
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.inner.range_reader(start, end).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        self.inner.file_size().await
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        self.sums.put(sums_file).await
+    }
+
+    async fn object_tags(&mut self) -> Result<Option<ObjectTags>> {
+        self.inner.object_tags().await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        self.inner.object_version().await
+    }
+
+    async fn object_snapshot(&mut self) -> Result<Option<ObjectSnapshot>> {
+        self.inner.object_snapshot().await
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        self.inner.write_snapshot(snapshot).await
+    }
+
+    fn location(&self) -> String {
+        self.inner.location()
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        self.inner.api_errors()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::file::Checksum;
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parse_sums_map() -> Result<()> {
+        let map = SumsMap::from_str(
+            "sums, object\ns3://sums/key.sums, s3://bucket/key\n\n/sums/file, /data/file\n",
+        )?;
+        assert_eq!(map.get("s3://bucket/key"), Some("s3://sums/key.sums"));
+        assert_eq!(map.get("/data/file.sums"), Some("/sums/file.sums"));
+        assert_eq!(map.get("/data/other"), None);
+
+        assert!(SumsMap::from_str("").is_err());
+        assert!(SumsMap::from_str("object\n/data/file").is_err());
+        assert!(SumsMap::from_str("object,sums\n/data/file").is_err());
+        assert!(SumsMap::from_str("object,sums\n/data/file,").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_input() {
+        let mut map = SumsMap::default();
+        assert_eq!(
+            map.split_input("s3://bucket/key#s3://sums/key.sums"),
+            "s3://bucket/key"
+        );
+        assert_eq!(map.get("s3://bucket/key"), Some("s3://sums/key.sums"));
+
+        // Objects containing the separator are not split unless a sums file follows it.
+        assert_eq!(map.split_input("/data/a#b"), "/data/a#b");
+        assert_eq!(map.split_input("#b.sums"), "#b.sums");
+        assert_eq!(map.get("/data/a"), None);
+    }
+
+    #[tokio::test]
+    async fn mapped_sums() -> Result<()> {
+        let tmp = tempdir()?;
+        let object = tmp.path().join("object");
+        let sums = tmp.path().join("sums").join("object.sums");
+        fs::create_dir_all(sums.parent().unwrap()).await?;
+        fs::write(&object, "data").await?;
+
+        let file = FileBuilder::default()
+            .with_file(object.to_string_lossy().to_string())
+            .build()?;
+        let mut mapped = MappedSums::new(
+            file,
+            SumsLocation::build(&sums.to_string_lossy(), None).await?,
+        );
+        assert_eq!(mapped.sums_file().await?, None);
+        assert_eq!(mapped.file_size().await?, Some(4));
+
+        let sums_file = SumsFile::new(
+            Some(4),
+            BTreeMap::from_iter([(Ctx::from_str("md5")?, Checksum::new("md5".to_string()))]),
+        );
+        mapped.write_sums_file(&sums_file).await?;
+
+        assert!(fs::try_exists(&sums).await?);
+        assert!(!fs::try_exists(tmp.path().join("object.sums")).await?);
+        assert_eq!(mapped.sums_file().await?, Some(sums_file));
+
+        Ok(())
+    }
+}
@@ -1,22 +1,36 @@
 //! Implementations for reading data using IO and from cloud storage.
 //!
 
-use crate::checksum::file::SumsFile;
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::Error::CheckError;
 use crate::error::{ApiError, Result};
 use crate::io::Provider;
 use crate::io::S3Client;
+use crate::io::registry::{CustomSums, ProviderRegistry};
 use crate::io::sums::aws::S3Builder;
+use crate::io::sums::external::ExternalSums;
 use crate::io::sums::file::FileBuilder;
+use crate::io::sums::mapped::{MappedSums, SumsLocation};
 use dyn_clone::DynClone;
 use futures_util::Stream;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::pin::Pin;
 use std::sync::Arc;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 pub mod aws;
+pub mod azure;
 pub mod channel;
+pub mod external;
 pub mod file;
+pub mod gds;
+pub mod http;
+pub mod mapped;
+#[cfg(unix)]
+pub mod sftp;
+pub mod swift;
+pub mod webdav;
 
 /// The type returned when converting a shared reader into a stream.
 pub type ReaderStream = Pin<Box<dyn Stream<Item = Result<Arc<[u8]>>> + Send>>;
@@ -32,6 +46,17 @@ pub trait SharedReader {
     /// Convert the shared reader into a stream of the resulting bytes of reading
     /// the chunks.
     fn as_stream(&mut self) -> ReaderStream;
+
+    /// The chunk size used by the reader, if it reads in chunks.
+    fn chunk_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// The most bytes that were buffered for a single consumer while reading, if the reader
+    /// buffers chunks.
+    fn buffer_high_water_bytes(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// Read operations on file based or cloud sums files.
@@ -43,12 +68,50 @@ pub trait ObjectSums: DynClone {
     /// Get a reader to the sums files.
     async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>>;
 
+    /// Get a reader to the target file starting at an offset, if the object supports it.
+    async fn reader_from(
+        &mut self,
+        _offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        Ok(None)
+    }
+
+    /// Get a reader to the bytes of the target file from `start` up to, but not including, `end`.
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self.reader_from(start).await?.ok_or_else(|| {
+            CheckError("reading a range of the object is not supported".to_string())
+        })?;
+        Ok(Box::new(reader.take(end.saturating_sub(start))))
+    }
+
     /// Get the file size of the target file.
     async fn file_size(&mut self) -> Result<Option<u64>>;
 
     /// Write data to the configured location.
     async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()>;
 
+    /// Get the tags and metadata of the target file, if the object supports them.
+    async fn object_tags(&mut self) -> Result<Option<ObjectTags>> {
+        Ok(None)
+    }
+
+    /// Get the current `ETag` and version of the target file, if the object has them.
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        Ok(None)
+    }
+
+    /// Get a snapshot of all the metadata of the target file, if the object supports it.
+    async fn object_snapshot(&mut self) -> Result<Option<ObjectSnapshot>> {
+        Ok(None)
+    }
+
+    /// Write a metadata snapshot next to the sums file of the target file.
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()>;
+
     /// Get the location of the object.
     fn location(&self) -> String;
 
@@ -58,6 +121,93 @@ pub trait ObjectSums: DynClone {
 
 dyn_clone::clone_trait_object!(ObjectSums);
 
+/// The tags and user-defined metadata of an object.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectTags {
+    /// The object tags.
+    pub(crate) tags: BTreeMap<String, String>,
+    /// The user-defined object metadata.
+    pub(crate) metadata: BTreeMap<String, String>,
+}
+
+impl ObjectTags {
+    /// Create new object tags.
+    pub fn new(tags: BTreeMap<String, String>, metadata: BTreeMap<String, String>) -> Self {
+        Self { tags, metadata }
+    }
+}
+
+/// The ending of metadata snapshot files, which are written next to the sums file.
+pub const SNAPSHOT_FILE_ENDING: &str = ".metadata.json";
+
+/// A snapshot of the metadata of an object, which records attributes that may not be possible
+/// to apply at a destination, such as ACLs or object lock settings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectSnapshot {
+    /// The location of the object that the snapshot was taken from.
+    pub(crate) location: String,
+    /// The size of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) size: Option<u64>,
+    /// The `ETag` of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) e_tag: Option<String>,
+    /// The version id of the object, if the bucket is versioned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) version_id: Option<String>,
+    /// The time that the object was last modified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_modified: Option<String>,
+    /// The storage class of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) storage_class: Option<String>,
+    /// System-defined headers such as `Content-Type` and object lock settings.
+    pub(crate) headers: BTreeMap<String, String>,
+    /// The user-defined object metadata.
+    pub(crate) metadata: BTreeMap<String, String>,
+    /// The object tags, if they could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tags: Option<BTreeMap<String, String>>,
+    /// The access control list of the object, if it could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) acl: Option<ObjectAcl>,
+}
+
+impl ObjectSnapshot {
+    /// Format the location of the snapshot file for a target file.
+    pub fn format_snapshot_file(target: &str) -> String {
+        format!(
+            "{}{}",
+            SumsFile::format_target_file(target),
+            SNAPSHOT_FILE_ENDING
+        )
+    }
+
+    /// Serialize the snapshot to a pretty JSON string.
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The access control list of an object.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectAcl {
+    /// The id of the object owner.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) owner: Option<String>,
+    /// The permissions granted on the object.
+    pub(crate) grants: Vec<AclGrant>,
+}
+
+/// A permission granted to a grantee by an access control list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AclGrant {
+    /// The grantee, which is a canonical user id, group URI or email address.
+    pub(crate) grantee: String,
+    /// The permission, e.g. `READ` or `FULL_CONTROL`.
+    pub(crate) permission: String,
+}
+
 /// Build object sums from object URLs.
 #[derive(Debug, Default)]
 pub struct ObjectSumsBuilder {
@@ -65,25 +215,72 @@ pub struct ObjectSumsBuilder {
 }
 
 impl ObjectSumsBuilder {
-    pub async fn build(self, url: String) -> Result<Box<dyn ObjectSums + Send>> {
+    pub async fn build(self, url: String) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let sums_location = match self
+            .client
+            .as_ref()
+            .and_then(|client| client.sums_map())
+            .and_then(|sums_map| sums_map.get(&url))
+        {
+            Some(sums) => Some(SumsLocation::build(sums, self.client.clone()).await?),
+            None => None,
+        };
+
         match Provider::try_from(url.as_str())? {
             Provider::File { file } => {
-                Ok(Box::new(FileBuilder::default().with_file(file).build()?))
+                let sums = FileBuilder::default().with_file(file).build()?;
+                Ok(self.wrap(sums, sums_location))
             }
-            Provider::S3 { bucket, key } => {
-                let client = self.client.ok_or_else(|| {
+            Provider::S3 { bucket, key } | Provider::Gcs { bucket, key } => {
+                let client = self.client.clone().ok_or_else(|| {
                     crate::error::Error::ParseError(
                         "an S3 client is required for S3 providers".to_string(),
                     )
                 })?;
-                Ok(Box::new(
-                    S3Builder::default()
-                        .with_key(key)
-                        .with_bucket(bucket)
-                        .with_client(client)
-                        .build()?,
-                ))
+                let sums = S3Builder::default()
+                    .with_key(key)
+                    .with_bucket(bucket)
+                    .with_client(client)
+                    .build()?;
+                Ok(self.wrap(sums, sums_location))
+            }
+            provider @ (Provider::Azure { .. }
+            | Provider::Http { .. }
+            | Provider::Sftp { .. }
+            | Provider::Gds { .. }
+            | Provider::Swift { .. }
+            | Provider::WebDav { .. }
+            | Provider::Custom { .. }) => {
+                let (scheme, url) = provider.into_registered()?;
+                let sums = ProviderRegistry::try_get(&scheme)?
+                    .sums_with_client(self.client.as_ref(), &url)
+                    .await?;
+                Ok(self.wrap(CustomSums::new(sums), sums_location))
+            }
+        }
+    }
+
+    /// Wrap the object sums with a mapped sums file location and the sums source, if any.
+    fn wrap<T>(
+        &self,
+        sums: T,
+        sums_location: Option<SumsLocation>,
+    ) -> Box<dyn ObjectSums + Send + Sync>
+    where
+        T: ObjectSums + Clone + Send + Sync + 'static,
+    {
+        let sums_source = self
+            .client
+            .as_ref()
+            .and_then(|client| client.sums_source().cloned());
+
+        match (sums_location, sums_source) {
+            (Some(location), Some(source)) => {
+                Box::new(ExternalSums::new(MappedSums::new(sums, location), source))
             }
+            (Some(location), None) => Box::new(MappedSums::new(sums, location)),
+            (None, Some(source)) => Box::new(ExternalSums::new(sums, source)),
+            (None, None) => Box::new(sums),
         }
     }
 
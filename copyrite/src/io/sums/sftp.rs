@@ -0,0 +1,177 @@
+//! SFTP sums file logic, where sums files are written next to the file on the server.
+//!
+
+use crate::checksum::file::SumsFile;
+use crate::error::Error::{ParseError, SftpError};
+use crate::error::{ApiError, Result};
+use crate::io::Provider;
+use crate::io::sftp::SftpClient;
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build an SFTP sums object.
+#[derive(Debug, Default)]
+pub struct SftpBuilder {
+    client: Option<SftpClient>,
+    host: Option<String>,
+    path: Option<String>,
+}
+
+impl SftpBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: SftpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the host.
+    pub fn with_host(mut self, host: String) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Set the path.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    fn get_components(self) -> Result<(SftpClient, String, String)> {
+        let error_fn = || ParseError("host and path are required in `SftpBuilder`".to_string());
+
+        Ok((
+            self.client.unwrap_or_default(),
+            self.host.ok_or_else(error_fn)?,
+            self.path.ok_or_else(error_fn)?,
+        ))
+    }
+
+    /// Build the SFTP sums object.
+    pub fn build(self) -> Result<Sftp> {
+        let (client, host, path) = self.get_components()?;
+        Ok(Sftp::new(client, host, path))
+    }
+}
+
+/// A file on an SFTP server.
+#[derive(Debug, Clone)]
+pub struct Sftp {
+    client: SftpClient,
+    host: String,
+    path: String,
+    size: Option<u64>,
+}
+
+impl Sftp {
+    /// Create a new SFTP file.
+    pub fn new(client: SftpClient, host: String, path: String) -> Self {
+        Self {
+            client,
+            host,
+            path,
+            size: None,
+        }
+    }
+
+    /// Get the size of the file. This caches the result so that subsequent calls do not
+    /// repeat the request.
+    pub async fn size(&mut self) -> Result<u64> {
+        if let Some(size) = self.size {
+            return Ok(size);
+        }
+
+        let size = self
+            .client
+            .size(&self.host, &self.path)
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        self.size = Some(size);
+        Ok(size)
+    }
+
+    /// Get an existing sums file if it exists.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_path = SumsFile::format_sums_file(&self.path);
+        let Some(data) = self.client.read_bytes(&self.host, &sums_path).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    /// Write a JSON document next to the file.
+    async fn put_json(&self, path: &str, json: String) -> Result<()> {
+        self.client
+            .write(&self.host, path, json.as_bytes(), None)
+            .await?;
+        Ok(())
+    }
+
+    fn not_found_error(&self) -> crate::error::Error {
+        SftpError(format!("{} does not exist", self.location()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for Sftp {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        self.get_existing_sums().await
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .read(&self.host, &self.path, 0, None)
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        Ok(reader)
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        let reader = self
+            .client
+            .read(&self.host, &self.path, offset, None)
+            .await?;
+        Ok(reader.map(|reader| reader as Box<dyn AsyncRead + Unpin + Send>))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .read(&self.host, &self.path, start, Some(end))
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        Ok(reader)
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        Ok(Some(self.size().await?))
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        let sums_path = SumsFile::format_sums_file(&self.path);
+        self.put_json(&sums_path, sums_file.to_json_string()?).await
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let snapshot_path = ObjectSnapshot::format_snapshot_file(&self.path);
+        self.put_json(&snapshot_path, snapshot.to_json_string()?)
+            .await
+    }
+
+    fn location(&self) -> String {
+        Provider::format_sftp(&self.host, &self.path)
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
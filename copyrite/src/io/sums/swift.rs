@@ -0,0 +1,209 @@
+//! OpenStack Swift sums file logic. The `ETag` of an object, or the `ETag`s of the segments of a
+//! large object, are used as existing checksums, and sums files are written as another object
+//! next to it.
+//!
+
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::Error::{ParseError, SwiftError};
+use crate::error::{ApiError, Result};
+use crate::io::Provider;
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use crate::io::swift::{ObjectHeaders, SwiftClient, SwiftObject};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build a Swift sums object.
+#[derive(Debug, Default)]
+pub struct SwiftBuilder {
+    client: Option<SwiftClient>,
+    container: Option<String>,
+    object: Option<String>,
+}
+
+impl SwiftBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: SwiftClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the container.
+    pub fn with_container(mut self, container: String) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Set the object.
+    pub fn with_object(mut self, object: String) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    fn get_components(self) -> Result<(SwiftClient, String, String)> {
+        let error_fn =
+            || ParseError("container and object are required in `SwiftBuilder`".to_string());
+
+        Ok((
+            self.client.unwrap_or_default(),
+            self.container.ok_or_else(error_fn)?,
+            self.object.ok_or_else(error_fn)?,
+        ))
+    }
+
+    /// Build the Swift sums object.
+    pub fn build(self) -> Result<Swift> {
+        let (client, container, object) = self.get_components()?;
+        Ok(Swift::new(client, container, object))
+    }
+}
+
+/// An object in a Swift container.
+#[derive(Debug, Clone)]
+pub struct Swift {
+    client: SwiftClient,
+    container: String,
+    object: String,
+    properties: Option<SwiftObject>,
+}
+
+impl Swift {
+    /// Create a new Swift object.
+    pub fn new(client: SwiftClient, container: String, object: String) -> Self {
+        Self {
+            client,
+            container,
+            object,
+            properties: None,
+        }
+    }
+
+    /// Get the properties of the object. This caches the result so that subsequent calls do
+    /// not repeat the request.
+    pub async fn properties(&mut self) -> Result<&SwiftObject> {
+        if self.properties.is_none() {
+            let properties = self
+                .client
+                .head_object(&self.container, &self.object)
+                .await?
+                .ok_or_else(|| self.not_found_error())?;
+            self.properties = Some(properties);
+        }
+
+        self.properties
+            .as_ref()
+            .ok_or_else(|| self.not_found_error())
+    }
+
+    /// Get an existing sums file if one is stored next to the object.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_object = SumsFile::format_sums_file(&self.object);
+        let Some(data) = self
+            .client
+            .get_object_bytes(&self.container, &sums_object)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    /// Write a JSON document as an object next to this object.
+    async fn put_json(&self, object: &str, json: String) -> Result<()> {
+        let length = u64::try_from(json.len())?;
+        self.client
+            .put_object(
+                &self.container,
+                object,
+                json.into(),
+                length,
+                &ObjectHeaders::default(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Read the object from `start` up to, but not including, `end`.
+    async fn read(
+        &self,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get_object(&self.container, &self.object, start, end)
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        Ok(reader)
+    }
+
+    fn not_found_error(&self) -> crate::error::Error {
+        SwiftError(format!("{} does not exist", self.location()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for Swift {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        let properties = self.properties().await?.clone();
+        let segments = self
+            .client
+            .segments(&self.container, &self.object, &properties)
+            .await?;
+
+        let mut sums_file = properties.sums(segments.as_deref())?;
+        if let Some(existing) = self.get_existing_sums().await? {
+            sums_file = sums_file.merge(existing)?;
+        }
+
+        Ok((!sums_file.checksums.is_empty()).then_some(sums_file))
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(0, None).await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        Ok(Some(self.read(offset, None).await?))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(start, Some(end)).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        Ok(Some(self.properties().await?.size))
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        let sums_object = SumsFile::format_sums_file(&self.object);
+        self.put_json(&sums_object, sums_file.to_json_string()?)
+            .await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        let e_tag = self.properties().await?.e_tag.clone();
+        Ok(Some(ObjectVersion::new(e_tag, None)))
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let snapshot_object = ObjectSnapshot::format_snapshot_file(&self.object);
+        self.put_json(&snapshot_object, snapshot.to_json_string()?)
+            .await
+    }
+
+    fn location(&self) -> String {
+        Provider::format_swift(&self.container, &self.object)
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
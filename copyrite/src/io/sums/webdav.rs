@@ -0,0 +1,170 @@
+//! WebDAV sums file logic. The checksums that Nextcloud and ownCloud store with a file are used
+//! as existing checksums, and sums files are written as another file next to it.
+//!
+
+use crate::checksum::file::{ObjectVersion, SumsFile};
+use crate::error::Error::{ParseError, WebDavError};
+use crate::error::{ApiError, Result};
+use crate::io::sums::{ObjectSnapshot, ObjectSums};
+use crate::io::webdav::{WebDavClient, WebDavProperties};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Build a WebDAV sums object.
+#[derive(Debug, Default)]
+pub struct WebDavBuilder {
+    client: Option<WebDavClient>,
+    url: Option<String>,
+}
+
+impl WebDavBuilder {
+    /// Set the client.
+    pub fn with_client(mut self, client: WebDavClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the url.
+    pub fn with_url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Build the WebDAV sums object.
+    pub fn build(self) -> Result<WebDav> {
+        let url = self
+            .url
+            .ok_or_else(|| ParseError("url is required in `WebDavBuilder`".to_string()))?;
+        Ok(WebDav::new(self.client.unwrap_or_default(), url))
+    }
+}
+
+/// A file on a WebDAV server.
+#[derive(Debug, Clone)]
+pub struct WebDav {
+    client: WebDavClient,
+    url: String,
+    properties: Option<WebDavProperties>,
+}
+
+impl WebDav {
+    /// Create a new WebDAV file.
+    pub fn new(client: WebDavClient, url: String) -> Self {
+        Self {
+            client,
+            url,
+            properties: None,
+        }
+    }
+
+    /// Get the properties of the file. This caches the result so that subsequent calls do not
+    /// repeat the request.
+    pub async fn properties(&mut self) -> Result<&WebDavProperties> {
+        if self.properties.is_none() {
+            let properties = self
+                .client
+                .propfind(&self.url)
+                .await?
+                .ok_or_else(|| self.not_found_error())?;
+            self.properties = Some(properties);
+        }
+
+        self.properties
+            .as_ref()
+            .ok_or_else(|| self.not_found_error())
+    }
+
+    /// Get an existing sums file if one is stored next to the file.
+    pub async fn get_existing_sums(&self) -> Result<Option<SumsFile>> {
+        let sums_url = SumsFile::format_sums_file(&self.url);
+        let Some(data) = self.client.get_bytes(&sums_url).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SumsFile::read_from_slice(&data).await?))
+    }
+
+    /// Write a JSON document as a file at the url.
+    async fn put_json(&self, url: &str, json: String) -> Result<()> {
+        let length = u64::try_from(json.len())?;
+        self.client.put(url, json.into(), length, &[]).await
+    }
+
+    /// Read the file from `start` up to, but not including, `end`.
+    async fn read(
+        &self,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader = self
+            .client
+            .get(&self.url, start, end)
+            .await?
+            .ok_or_else(|| self.not_found_error())?;
+        Ok(reader)
+    }
+
+    fn not_found_error(&self) -> crate::error::Error {
+        WebDavError(format!("{} does not exist", self.url))
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectSums for WebDav {
+    async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
+        let mut sums_file = self.properties().await?.sums()?;
+        if let Some(existing) = self.get_existing_sums().await? {
+            sums_file = sums_file.merge(existing)?;
+        }
+
+        Ok((!sums_file.checksums.is_empty()).then_some(sums_file))
+    }
+
+    async fn reader(&mut self) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(0, None).await
+    }
+
+    async fn reader_from(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<Box<dyn AsyncRead + Unpin + Send>>> {
+        Ok(Some(self.read(offset, None).await?))
+    }
+
+    async fn range_reader(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+        self.read(start, Some(end)).await
+    }
+
+    async fn file_size(&mut self) -> Result<Option<u64>> {
+        let url = self.url.clone();
+        Ok(Some(self.properties().await?.size(&url)?))
+    }
+
+    async fn write_sums_file(&self, sums_file: &SumsFile) -> Result<()> {
+        let sums_url = SumsFile::format_sums_file(&self.url);
+        self.put_json(&sums_url, sums_file.to_json_string()?).await
+    }
+
+    async fn object_version(&mut self) -> Result<Option<ObjectVersion>> {
+        let e_tag = self.properties().await?.e_tag.clone();
+        Ok(Some(ObjectVersion::new(e_tag, None)))
+    }
+
+    async fn write_snapshot(&self, snapshot: &ObjectSnapshot) -> Result<()> {
+        let snapshot_url = ObjectSnapshot::format_snapshot_file(&self.url);
+        self.put_json(&snapshot_url, snapshot.to_json_string()?)
+            .await
+    }
+
+    fn location(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn api_errors(&self) -> HashSet<ApiError> {
+        HashSet::new()
+    }
+}
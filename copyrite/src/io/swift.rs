@@ -0,0 +1,711 @@
+//! Support for OpenStack Swift locations, e.g. `swift://container/path/to/object`. Objects are
+//! accessed in the account at the storage URL, and requests are authorized with an auth token,
+//! as printed by `swift auth`. Large objects are made of segments, which are either listed in
+//! the manifest of a static large object (SLO), or are all the objects under the prefix of a
+//! dynamic large object (DLO).
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::aws_etag::AWSETagCtx;
+use crate::checksum::file::{Checksum, SumsFile};
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::SwiftError;
+use crate::error::Result;
+use crate::io::azure::encode_path;
+use crate::io::copy::ObjectCopy;
+use crate::io::registry::ProviderFactory;
+use crate::io::rest::{Rest, RestReader};
+use crate::io::sums::ObjectSums;
+use crate::io::{Provider, S3Client, SwiftOptions, copy, sums};
+use bytes::Bytes;
+use reqwest::{Body, Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The URL scheme of Swift locations.
+pub const SWIFT_SCHEME: &str = "swift";
+
+/// The prefix of the headers containing user-defined metadata.
+const METADATA_PREFIX: &str = "x-object-meta-";
+
+/// The suffix of the container that segments of uploaded large objects are stored in, which
+/// follows the convention of the `swift` client.
+pub const SEGMENTS_CONTAINER_SUFFIX: &str = "_segments";
+
+/// A Swift object reader.
+pub type ObjectReader = RestReader;
+
+/// Sends requests to Swift, converting failures into Swift errors.
+const REST: Rest = Rest::new(SwiftError);
+
+/// Whether the location is a Swift URL.
+pub fn is_swift_url(location: &str) -> bool {
+    location
+        .strip_prefix(SWIFT_SCHEME)
+        .is_some_and(|location| location.starts_with("://"))
+}
+
+/// The kind of large object that an object is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LargeObject {
+    /// A static large object, where the manifest lists the segments.
+    Static,
+    /// A dynamic large object, where the segments are the objects under a `container/prefix`.
+    Dynamic(String),
+}
+
+/// The properties of an object.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SwiftObject {
+    pub(crate) size: u64,
+    pub(crate) e_tag: Option<String>,
+    pub(crate) large_object: Option<LargeObject>,
+    pub(crate) metadata: HashMap<String, String>,
+}
+
+impl SwiftObject {
+    /// Read the properties from the headers of a `HEAD` object response.
+    fn from_response(response: &Response) -> Result<Self> {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        let size = header("content-length")
+            .ok_or_else(|| SwiftError("missing object size".to_string()))?
+            .parse()
+            .map_err(|err| SwiftError(format!("invalid object size: {}", err)))?;
+        let large_object = if header("x-static-large-object")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            Some(LargeObject::Static)
+        } else {
+            header("x-object-manifest").map(LargeObject::Dynamic)
+        };
+        let metadata = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = name.as_str().strip_prefix(METADATA_PREFIX)?;
+                Some((name.to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+
+        Ok(Self {
+            size,
+            e_tag: header("etag").map(|e_tag| e_tag.trim_matches('"').to_string()),
+            large_object,
+            metadata,
+        })
+    }
+
+    /// Convert the `ETag` of the object into sums. The `ETag` of a regular object is its MD5.
+    /// The `ETag` of a large object is the MD5 of the concatenated `ETag`s of its segments, which
+    /// is not a checksum of the data. Instead, the MD5s of the segments are combined into an AWS
+    /// `ETag` style checksum with the segment sizes as the part sizes. This is only done if the
+    /// segments still match the `ETag` of the object, and none of them are nested large objects
+    /// or ranges of other objects.
+    pub fn sums(&self, segments: Option<&[Segment]>) -> Result<SumsFile> {
+        let mut sums_file = SumsFile::default().with_size(Some(self.size));
+        let Some(e_tag) = self.e_tag.as_deref() else {
+            return Ok(sums_file);
+        };
+        let invalid_e_tag =
+            |err: hex::FromHexError| SwiftError(format!("invalid `ETag` `{}`: {}", e_tag, err));
+
+        let (ctx, digest) = match (&self.large_object, segments) {
+            (None, _) => (Ctx::Regular(StandardCtx::md5()), hex::decode(e_tag)),
+            (Some(_), Some(segments)) if Segment::matches(segments, e_tag, self.size)? => {
+                let part_checksums = segments
+                    .iter()
+                    .map(|segment| Ok((segment.bytes, hex::decode(&segment.hash)?)))
+                    .collect::<std::result::Result<Vec<_>, hex::FromHexError>>()
+                    .map_err(invalid_e_tag)?;
+                let (ctx, digest) =
+                    AWSETagCtx::from_part_checksums(StandardCtx::md5(), part_checksums)?;
+                (Ctx::AWSEtag(ctx), Ok(digest))
+            }
+            _ => return Ok(sums_file),
+        };
+        let digest = digest.map_err(invalid_e_tag)?;
+
+        let checksum = Checksum::new(ctx.digest_to_string(&digest));
+        sums_file.add_checksum(ctx, checksum);
+        Ok(sums_file)
+    }
+}
+
+/// A segment of a large object, as listed in the manifest of a static large object or the
+/// container of a dynamic large object.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Segment {
+    pub(crate) name: String,
+    pub(crate) hash: String,
+    pub(crate) bytes: u64,
+    #[serde(default)]
+    pub(crate) sub_slo: bool,
+    pub(crate) range: Option<String>,
+}
+
+impl Segment {
+    /// Whether the segments are whole regular objects that make up a large object with the
+    /// `ETag` and size.
+    fn matches(segments: &[Segment], e_tag: &str, size: u64) -> Result<bool> {
+        if segments.is_empty()
+            || segments
+                .iter()
+                .any(|segment| segment.sub_slo || segment.range.is_some())
+            || segments.iter().map(|segment| segment.bytes).sum::<u64>() != size
+        {
+            return Ok(false);
+        }
+
+        let concat = segments
+            .iter()
+            .map(|segment| segment.hash.as_str())
+            .collect::<String>();
+        let mut ctx = StandardCtx::md5();
+        ctx.update(Arc::from(concat.as_bytes()))?;
+
+        Ok(hex::encode(ctx.finalize()?) == e_tag)
+    }
+}
+
+/// A segment in the manifest of a static large object that is uploaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestSegment {
+    pub(crate) path: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) size_bytes: u64,
+}
+
+/// The headers set on an object when it is written.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectHeaders {
+    pub(crate) e_tag: Option<String>,
+    pub(crate) metadata: Option<HashMap<String, String>>,
+}
+
+impl ObjectHeaders {
+    /// Add the headers to a request.
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(e_tag) = &self.e_tag {
+            request = request.header("etag", e_tag);
+        }
+        for (key, value) in self.metadata.iter().flatten() {
+            request = request.header(format!("{}{}", METADATA_PREFIX, key), value);
+        }
+
+        request
+    }
+}
+
+/// A client for the object storage API of a Swift account.
+#[derive(Debug, Clone, Default)]
+pub struct SwiftClient {
+    client: Client,
+    options: SwiftOptions,
+}
+
+impl SwiftClient {
+    /// Create a client with the storage URL and auth token of the options.
+    pub fn new(options: SwiftOptions) -> Self {
+        Self {
+            client: Client::new(),
+            options,
+        }
+    }
+
+    /// Create a request for a path in the account.
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let storage_url = self.options.storage_url().ok_or_else(|| {
+            SwiftError(
+                "--swift-storage-url or OS_STORAGE_URL must be set to access Swift locations"
+                    .to_string(),
+            )
+        })?;
+
+        let mut request = self.client.request(
+            method,
+            format!("{}/{}", storage_url.trim_end_matches('/'), path),
+        );
+        if let Some(auth_token) = self.options.auth_token() {
+            request = request.header("x-auth-token", auth_token);
+        }
+
+        Ok(request)
+    }
+
+    /// Create a request for an object.
+    fn object_request(
+        &self,
+        method: Method,
+        container: &str,
+        object: &str,
+    ) -> Result<RequestBuilder> {
+        self.request(
+            method,
+            &format!("{}/{}", encode_path(container), encode_path(object)),
+        )
+    }
+
+    /// Get the properties of an object, or `None` if it does not exist.
+    pub async fn head_object(&self, container: &str, object: &str) -> Result<Option<SwiftObject>> {
+        let request = self.object_request(Method::HEAD, container, object)?;
+        REST.send_optional(request, "HEAD object")
+            .await?
+            .map(|response| SwiftObject::from_response(&response))
+            .transpose()
+    }
+
+    /// Read an object from `start` up to, but not including, `end`, or to the end of the object
+    /// if `end` is not set. Large objects are read as the concatenation of their segments.
+    /// Returns `None` if the object does not exist.
+    pub async fn get_object(
+        &self,
+        container: &str,
+        object: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Option<ObjectReader>> {
+        let request = self.object_request(Method::GET, container, object)?;
+        REST.get_range(request, "GET object", start, end).await
+    }
+
+    /// Read a whole object into memory, or `None` if it does not exist.
+    pub async fn get_object_bytes(&self, container: &str, object: &str) -> Result<Option<Bytes>> {
+        let request = self.object_request(Method::GET, container, object)?;
+        REST.get_bytes(request, "GET object").await
+    }
+
+    /// Get the segments of a large object, or `None` if the object is not a large object.
+    pub async fn segments(
+        &self,
+        container: &str,
+        object: &str,
+        properties: &SwiftObject,
+    ) -> Result<Option<Vec<Segment>>> {
+        match &properties.large_object {
+            None => Ok(None),
+            Some(LargeObject::Static) => {
+                let request = self
+                    .object_request(Method::GET, container, object)?
+                    .query(&[("multipart-manifest", "get")]);
+                let response = REST.send(request, "GET manifest").await?;
+                let response = REST.check(response, "GET manifest")?;
+                let segments = response
+                    .json()
+                    .await
+                    .map_err(|err| SwiftError(format!("invalid manifest: {}", err)))?;
+
+                Ok(Some(segments))
+            }
+            Some(LargeObject::Dynamic(manifest)) => {
+                let (container, prefix) = manifest.split_once('/').unwrap_or((manifest, ""));
+                Ok(Some(self.list(container, prefix).await?))
+            }
+        }
+    }
+
+    /// List the objects under a prefix in name order, which is the order of the segments of a
+    /// dynamic large object.
+    pub async fn list(&self, container: &str, prefix: &str) -> Result<Vec<Segment>> {
+        let mut objects: Vec<Segment> = vec![];
+        loop {
+            let mut query = vec![("format", "json"), ("prefix", prefix)];
+            if let Some(last) = objects.last() {
+                query.push(("marker", last.name.as_str()));
+            }
+
+            let request = self
+                .request(Method::GET, &encode_path(container))?
+                .query(&query);
+            let response = REST.send(request, "GET container").await?;
+            let response = REST.check(response, "GET container")?;
+            // An empty listing can be returned without a body.
+            if response.status() == StatusCode::NO_CONTENT {
+                return Ok(objects);
+            }
+
+            let page: Vec<Segment> = response
+                .json()
+                .await
+                .map_err(|err| SwiftError(format!("invalid container listing: {}", err)))?;
+            if page.is_empty() {
+                return Ok(objects);
+            }
+            objects.extend(page);
+        }
+    }
+
+    /// Create a container if it does not exist.
+    pub async fn put_container(&self, container: &str) -> Result<()> {
+        let request = self.request(Method::PUT, &encode_path(container))?;
+        let response = REST.send(request, "PUT container").await?;
+        REST.check(response, "PUT container")?;
+        Ok(())
+    }
+
+    /// Write an object in a single request, returning its `ETag`. The `ETag` of the headers is
+    /// checked by the server against the MD5 of the data.
+    pub async fn put_object(
+        &self,
+        container: &str,
+        object: &str,
+        body: Body,
+        content_length: u64,
+        headers: &ObjectHeaders,
+    ) -> Result<Option<String>> {
+        let request = self
+            .object_request(Method::PUT, container, object)?
+            .header("content-length", content_length);
+
+        let response = REST
+            .send(headers.apply(request).body(body), "PUT object")
+            .await?;
+        let response = REST.check(response, "PUT object")?;
+        Ok(response
+            .headers()
+            .get("etag")
+            .and_then(|e_tag| e_tag.to_str().ok())
+            .map(|e_tag| e_tag.trim_matches('"').to_string()))
+    }
+
+    /// Write the manifest of a static large object, which joins the segments in order.
+    pub async fn put_manifest(
+        &self,
+        container: &str,
+        object: &str,
+        segments: &[ManifestSegment],
+        headers: &ObjectHeaders,
+    ) -> Result<()> {
+        let request = self
+            .object_request(Method::PUT, container, object)?
+            .query(&[("multipart-manifest", "put")])
+            .json(segments);
+
+        let response = REST.send(headers.apply(request), "PUT manifest").await?;
+        REST.check(response, "PUT manifest")?;
+        Ok(())
+    }
+}
+
+/// Builds the copy and sums objects of Swift locations. This is registered in the provider
+/// registry for the `swift` scheme, and accesses the account with the Swift options of the
+/// client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwiftFactory;
+
+impl SwiftFactory {
+    fn client(client: Option<&S3Client>) -> SwiftClient {
+        SwiftClient::new(
+            client
+                .map(|client| client.swift_options().clone())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderFactory for SwiftFactory {
+    async fn copy(
+        &self,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        self.copy_with_client(None, source, destination).await
+    }
+
+    async fn sums(&self, url: &str) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        self.sums_with_client(None, url).await
+    }
+
+    async fn copy_with_client(
+        &self,
+        client: Option<&S3Client>,
+        source: Option<&str>,
+        destination: Option<&str>,
+    ) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        let mut builder = copy::swift::SwiftBuilder::default().with_client(Self::client(client));
+        if let Some(source) = source {
+            let (container, object) = Provider::try_from(source)?.into_swift()?;
+            builder = builder.with_source(&container, &object);
+        }
+        if let Some(destination) = destination {
+            let (container, object) = Provider::try_from(destination)?.into_swift()?;
+            builder = builder.with_destination(&container, &object);
+        }
+
+        Ok(Box::new(builder.build()))
+    }
+
+    async fn sums_with_client(
+        &self,
+        client: Option<&S3Client>,
+        url: &str,
+    ) -> Result<Box<dyn ObjectSums + Send + Sync>> {
+        let (container, object) = Provider::try_from(url)?.into_swift()?;
+        Ok(Box::new(
+            sums::swift::SwiftBuilder::default()
+                .with_container(container)
+                .with_object(object)
+                .with_client(Self::client(client))
+                .build()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one response for each request, returning the storage URL and the requests that
+    /// were received.
+    async fn serve(
+        responses: Vec<String>,
+    ) -> anyhow::Result<(String, tokio::task::JoinHandle<anyhow::Result<Vec<String>>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let storage_url = format!("http://{}/v1/AUTH_project", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = [0; 4096];
+                let n = stream.read(&mut request).await?;
+                requests.push(String::from_utf8_lossy(&request[..n]).to_string());
+                stream.write_all(response.as_bytes()).await?;
+            }
+
+            anyhow::Ok(requests)
+        });
+
+        Ok((storage_url, server))
+    }
+
+    fn json_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+            connection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn client(storage_url: String) -> SwiftClient {
+        SwiftClient::new(SwiftOptions::new(
+            Some(storage_url),
+            Some("token".to_string()),
+        ))
+    }
+
+    fn md5(data: &[u8]) -> anyhow::Result<String> {
+        let mut ctx = StandardCtx::md5();
+        ctx.update(Arc::from(data))?;
+        Ok(hex::encode(ctx.finalize()?))
+    }
+
+    /// Segments of 5, 5 and 3 bytes, with the `ETag` of the large object that they make up.
+    fn segments() -> anyhow::Result<(Vec<Segment>, String)> {
+        let segments = [&b"aaaaa"[..], b"bbbbb", b"ccc"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, data)| {
+                Ok(Segment {
+                    name: format!("object/{:08}", i + 1),
+                    hash: md5(data)?,
+                    bytes: u64::try_from(data.len())?,
+                    sub_slo: false,
+                    range: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let e_tag = md5(segments
+            .iter()
+            .map(|segment| segment.hash.as_str())
+            .collect::<String>()
+            .as_bytes())?;
+
+        Ok((segments, e_tag))
+    }
+
+    #[test]
+    fn swift_urls() {
+        assert!(is_swift_url("swift://container/object"));
+        assert!(!is_swift_url("swiftcontainer/object"));
+        assert!(!is_swift_url("s3://bucket/key"));
+    }
+
+    #[test]
+    fn large_object_sums() -> anyhow::Result<()> {
+        let (segments, e_tag) = segments()?;
+        let object = SwiftObject {
+            size: 13,
+            e_tag: Some(e_tag),
+            large_object: Some(LargeObject::Static),
+            ..Default::default()
+        };
+
+        let mut expected = AWSETagCtx::new(
+            StandardCtx::md5(),
+            crate::checksum::aws_etag::PartMode::PartSizes(vec![5]),
+            None,
+        );
+        expected.update(Arc::from(&b"aaaaabbbbbccc"[..]))?;
+        let expected_digest = expected.finalize()?;
+
+        let sums = object.sums(Some(&segments))?;
+        assert_eq!(sums.size, Some(13));
+        let (ctx, checksum) = sums.checksums.iter().next().unwrap();
+        assert_eq!(ctx.to_string(), "md5-aws-5b");
+        assert_eq!(
+            checksum.as_str(),
+            Ctx::AWSEtag(expected).digest_to_string(&expected_digest)
+        );
+
+        // Segments that no longer match the `ETag`, such as a modified dynamic large object,
+        // are not used.
+        let mut modified = segments.clone();
+        modified[2].hash = md5(b"ddd")?;
+        assert!(object.sums(Some(&modified))?.checksums.is_empty());
+        assert!(object.sums(None)?.checksums.is_empty());
+
+        let regular = SwiftObject {
+            size: 3,
+            e_tag: Some(md5(b"ccc")?),
+            ..Default::default()
+        };
+        let sums = regular.sums(None)?;
+        assert_eq!(sums.checksums.keys().next().unwrap().to_string(), "md5");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn registered_factory() -> anyhow::Result<()> {
+        let (storage_url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\n".to_string(),
+        ])
+        .await?;
+        let s3 = aws_sdk_s3::Client::from_conf(
+            aws_sdk_s3::Config::builder()
+                .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+                .build(),
+        );
+        let client = S3Client::new(Arc::new(s3), false, false).with_swift_options(
+            SwiftOptions::new(Some(storage_url), Some("token".to_string())),
+        );
+
+        let copy = crate::io::copy::ObjectCopyBuilder::default()
+            .set_client(Some(client))
+            .set_source(Some(Provider::try_from("swift://container/dir/object")?))
+            .build()
+            .await?;
+        assert_eq!(copy.initialize_state().await?.size(), 5);
+
+        let requests = server.await??;
+        assert!(requests[0].starts_with("HEAD /v1/AUTH_project/container/dir/object HTTP/1.1"));
+        assert!(requests[0].contains("x-auth-token: token"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn static_large_object() -> anyhow::Result<()> {
+        let (segments, e_tag) = segments()?;
+        let manifest = serde_json::to_string(
+            &segments
+                .iter()
+                .map(|segment| {
+                    serde_json::json!({
+                        "name": format!("/container_segments/{}", segment.name),
+                        "hash": segment.hash,
+                        "bytes": segment.bytes,
+                        "content_type": "application/octet-stream",
+                    })
+                })
+                .collect::<Vec<_>>(),
+        )?;
+        let (storage_url, server) = serve(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: 13\r\netag: \"{}\"\r\n\
+                x-static-large-object: True\r\nx-object-meta-project: umccr\r\n\
+                connection: close\r\n\r\n",
+                e_tag
+            ),
+            json_response(&manifest),
+        ])
+        .await?;
+
+        let client = client(storage_url);
+        let object = client
+            .head_object("container", "dir/object")
+            .await?
+            .expect("object");
+        assert_eq!(object.size, 13);
+        assert_eq!(object.large_object, Some(LargeObject::Static));
+        assert_eq!(object.metadata["project"], "umccr");
+
+        let listed = client
+            .segments("container", "dir/object", &object)
+            .await?
+            .expect("segments");
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0].name, "/container_segments/object/00000001");
+        assert!(!object.sums(Some(&listed))?.checksums.is_empty());
+
+        let requests = server.await??;
+        assert!(requests[0].starts_with("HEAD /v1/AUTH_project/container/dir/object HTTP/1.1"));
+        assert!(requests[0].contains("x-auth-token: token"));
+        assert!(requests[1].starts_with(
+            "GET /v1/AUTH_project/container/dir/object?multipart-manifest=get HTTP/1.1"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dynamic_large_object() -> anyhow::Result<()> {
+        let (segments, _) = segments()?;
+        let page = serde_json::to_string(
+            &segments
+                .iter()
+                .map(|segment| {
+                    serde_json::json!({
+                        "name": segment.name,
+                        "hash": segment.hash,
+                        "bytes": segment.bytes,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        )?;
+        let (storage_url, server) = serve(vec![json_response(&page), json_response("[]")]).await?;
+
+        let object = SwiftObject {
+            large_object: Some(LargeObject::Dynamic("segments/object/".to_string())),
+            ..Default::default()
+        };
+        let listed = client(storage_url)
+            .segments("container", "object", &object)
+            .await?
+            .expect("segments");
+        assert_eq!(listed, segments);
+
+        let requests = server.await??;
+        assert!(
+            requests[0]
+                .starts_with("GET /v1/AUTH_project/segments?format=json&prefix=object%2F HTTP/1.1")
+        );
+        assert!(requests[1].starts_with(
+            "GET /v1/AUTH_project/segments?format=json&prefix=object%2F&marker=object%2F00000003 \
+            HTTP/1.1"
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+//! Record S3 API traces during a run and replay them offline.
+//!
+//! A trace is a JSON lines file with one event per HTTP response. Object data is not recorded,
+//! so replaying a trace reproduces how a copy was planned from the metadata of the source and
+//! destination, without access to either.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use aws_credential_types::Credentials;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::interceptors::{
+    AfterDeserializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse, Metadata};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::http::StatusCode;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use bytes::Bytes;
+use futures_util::stream;
+use http_body::Frame;
+use http_body_util::StreamBody;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The size of the chunks of zeros used in place of object data when replaying.
+const REPLAY_CHUNK_SIZE: u64 = 1 << 20;
+
+/// A recorded response to an S3 request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// The name of the operation, e.g. `HeadObject`.
+    pub(crate) operation: String,
+    /// The HTTP method of the request.
+    pub(crate) method: String,
+    /// The URI of the request.
+    pub(crate) uri: String,
+    /// The range requested, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) range: Option<String>,
+    /// The HTTP status of the response.
+    pub(crate) status: u16,
+    /// The headers of the response.
+    pub(crate) headers: BTreeMap<String, String>,
+    /// The body of the response, if it was not streamed object data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) body: Option<String>,
+}
+
+impl TraceEvent {
+    /// The key which replayed requests are matched with.
+    fn key(&self) -> RequestKey {
+        RequestKey {
+            method: self.method.to_string(),
+            uri: self.uri.to_string(),
+            range: self.range.clone(),
+        }
+    }
+}
+
+/// Identifies a request so that it can be matched with a recorded response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestKey {
+    method: String,
+    uri: String,
+    range: Option<String>,
+}
+
+impl From<&HttpRequest> for RequestKey {
+    fn from(request: &HttpRequest) -> Self {
+        Self {
+            method: request.method().to_string(),
+            uri: request.uri().to_string(),
+            range: request.headers().get("range").map(str::to_string),
+        }
+    }
+}
+
+impl Storable for RequestKey {
+    type Storer = StoreReplace<Self>;
+}
+
+/// Whether to record or replay a trace.
+#[derive(Debug, Clone)]
+pub enum ApiTrace {
+    /// Record every response to a trace file.
+    Record(TraceRecorder),
+    /// Serve responses from a trace file instead of sending requests.
+    Replay(TraceReplay),
+}
+
+impl ApiTrace {
+    /// Start recording to a trace file, replacing it if it exists.
+    pub fn record(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::Record(TraceRecorder {
+            file: Arc::new(Mutex::new(File::create(path)?)),
+        }))
+    }
+
+    /// Read a trace file to replay.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut events: HashMap<RequestKey, VecDeque<TraceEvent>> = HashMap::new();
+        for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let event: TraceEvent = serde_json::from_str(&line).map_err(|err| {
+                ParseError(format!(
+                    "invalid event on line {} of trace {}: {}",
+                    n + 1,
+                    path.display(),
+                    err
+                ))
+            })?;
+            events.entry(event.key()).or_default().push_back(event);
+        }
+
+        Ok(Self::Replay(TraceReplay {
+            events: Arc::new(Mutex::new(events)),
+        }))
+    }
+
+    /// Create a trace from the command line options, if any.
+    pub fn from_options(
+        record: Option<&PathBuf>,
+        replay: Option<&PathBuf>,
+    ) -> Result<Option<Self>> {
+        match (record, replay) {
+            (Some(_), Some(_)) => Err(ParseError(
+                "a trace cannot be recorded and replayed at the same time".to_string(),
+            )),
+            (Some(record), None) => Ok(Some(Self::record(record)?)),
+            (None, Some(replay)) => Ok(Some(Self::replay(replay)?)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Apply the trace to a client. Replayed clients use placeholder credentials so that no
+    /// real credentials are required.
+    pub fn apply(&self, client: Client) -> Client {
+        let builder = client.config().to_builder();
+        let builder = match self {
+            Self::Record(recorder) => builder.interceptor(recorder.clone()),
+            Self::Replay(replay) => builder
+                .http_client(replay.clone())
+                .credentials_provider(Credentials::new("replay", "replay", None, None, "replay")),
+        };
+
+        Client::from_conf(builder.build())
+    }
+}
+
+/// An interceptor which appends each response to a trace file.
+#[derive(Debug, Clone)]
+pub struct TraceRecorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl TraceRecorder {
+    /// Write an event as a single line.
+    fn write(&self, event: &TraceEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|err| ParseError(format!("failed to write trace: {}", err)))?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+}
+
+impl Intercept for TraceRecorder {
+    fn name(&self) -> &'static str {
+        "TraceRecorder"
+    }
+
+    fn read_before_transmit(
+        &self,
+        context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> std::result::Result<(), BoxError> {
+        // The request is not available after it is sent, so keep what is needed to match it.
+        cfg.interceptor_state()
+            .store_put(RequestKey::from(context.request()));
+        Ok(())
+    }
+
+    fn read_after_deserialization(
+        &self,
+        context: &AfterDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> std::result::Result<(), BoxError> {
+        let Some(key) = cfg.load::<RequestKey>().cloned() else {
+            return Ok(());
+        };
+        let response = context.response();
+
+        let event = TraceEvent {
+            operation: cfg
+                .load::<Metadata>()
+                .map(|metadata| metadata.name().to_string())
+                .unwrap_or_default(),
+            method: key.method,
+            uri: key.uri,
+            range: key.range,
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            // Streamed object data has already been taken from the response at this point.
+            body: response
+                .body()
+                .bytes()
+                .map(|body| String::from_utf8_lossy(body).to_string()),
+        };
+        self.write(&event)?;
+
+        Ok(())
+    }
+}
+
+/// An HTTP client which responds with recorded events. Requests are matched by their method,
+/// URI and range, and repeated requests receive the recorded responses in order, with the last
+/// response repeated if there are no more.
+#[derive(Debug, Clone)]
+pub struct TraceReplay {
+    events: Arc<Mutex<HashMap<RequestKey, VecDeque<TraceEvent>>>>,
+}
+
+impl TraceReplay {
+    /// Get the next recorded event for a request.
+    fn next_event(&self, key: &RequestKey) -> Option<TraceEvent> {
+        let mut events = self.events.lock().ok()?;
+        let queue = events.get_mut(key)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+
+    /// Build a response from an event. Object data is replaced with zeros of the recorded
+    /// length.
+    fn response(event: TraceEvent) -> std::result::Result<HttpResponse, BoxError> {
+        let body = match event.body {
+            Some(body) => SdkBody::from(body),
+            None => {
+                let length: u64 = event
+                    .headers
+                    .get("content-length")
+                    .and_then(|length| length.parse().ok())
+                    .unwrap_or_default();
+                let chunks = (0..length)
+                    .step_by(usize::try_from(REPLAY_CHUNK_SIZE)?)
+                    .map(move |start| {
+                        let size = REPLAY_CHUNK_SIZE.min(length - start);
+                        Ok::<_, Infallible>(Frame::data(Bytes::from(vec![
+                            0;
+                            usize::try_from(size)
+                                .unwrap_or_default()
+                        ])))
+                    });
+                SdkBody::from_body_1_x(StreamBody::new(stream::iter(chunks)))
+            }
+        };
+
+        let mut response = HttpResponse::new(StatusCode::try_from(event.status)?, body);
+        for (name, value) in event.headers {
+            response.headers_mut().insert(name, value);
+        }
+
+        Ok(response)
+    }
+}
+
+impl HttpConnector for TraceReplay {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let key = RequestKey::from(&request);
+        let response = self
+            .next_event(&key)
+            .ok_or_else(|| {
+                BoxError::from(format!(
+                    "no recorded response for {} {}",
+                    key.method, key.uri
+                ))
+            })
+            .and_then(Self::response)
+            .map_err(|err| ConnectorError::other(err, None));
+
+        HttpConnectorFuture::ready(response)
+    }
+}
+
+impl HttpClient for TraceReplay {
+    fn http_connector(
+        &self,
+        _settings: &HttpConnectorSettings,
+        _components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aws_sdk_s3::config::{BehaviorVersion, Region};
+    use tempfile::tempdir;
+
+    fn event(status: u16, e_tag: &str) -> TraceEvent {
+        TraceEvent {
+            operation: "HeadObject".to_string(),
+            method: "HEAD".to_string(),
+            uri: "https://bucket.s3.us-east-1.amazonaws.com/key".to_string(),
+            range: None,
+            status,
+            headers: BTreeMap::from([
+                ("content-length".to_string(), "5".to_string()),
+                ("etag".to_string(), e_tag.to_string()),
+            ]),
+            body: None,
+        }
+    }
+
+    fn client(trace: &ApiTrace) -> Client {
+        let config = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .build();
+        trace.apply(Client::from_conf(config))
+    }
+
+    #[tokio::test]
+    async fn replay_serves_recorded_events_in_order() -> anyhow::Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("trace.jsonl");
+        let lines = [event(200, "\"first\""), event(200, "\"second\"")]
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        std::fs::write(&path, lines.join("\n"))?;
+
+        let client = client(&ApiTrace::replay(&path)?);
+        let head = |key: &str| client.head_object().bucket("bucket").key(key).send();
+
+        assert_eq!(head("key").await?.e_tag(), Some("\"first\""));
+        assert_eq!(head("key").await?.e_tag(), Some("\"second\""));
+        // The last response is repeated.
+        assert_eq!(head("key").await?.e_tag(), Some("\"second\""));
+        assert!(head("missing").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_writes_events() -> anyhow::Result<()> {
+        let tmp = tempdir()?;
+        let replay = tmp.path().join("replay.jsonl");
+        std::fs::write(&replay, serde_json::to_string(&event(200, "\"e_tag\""))?)?;
+        let recorded = tmp.path().join("recorded.jsonl");
+
+        // Record the responses of a replayed client, which should reproduce the trace.
+        let client = ApiTrace::record(&recorded)?.apply(client(&ApiTrace::replay(&replay)?));
+        client
+            .head_object()
+            .bucket("bucket")
+            .key("key")
+            .send()
+            .await?;
+
+        let ApiTrace::Replay(trace) = ApiTrace::replay(&recorded)? else {
+            panic!("expected a replay");
+        };
+        let event = trace.next_event(&event(200, "").key()).unwrap();
+        assert_eq!(event.operation, "HeadObject");
+        assert_eq!(
+            event.headers.get("etag").map(String::as_str),
+            Some("\"e_tag\"")
+        );
+
+        Ok(())
+    }
+}
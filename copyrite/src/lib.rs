@@ -3,6 +3,7 @@ pub mod error;
 pub mod task;
 
 pub mod cli;
+pub mod config;
 pub mod io;
 pub mod stats;
 #[doc(hidden)]
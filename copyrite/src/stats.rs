@@ -2,16 +2,71 @@
 //!
 
 use crate::checksum::Ctx;
+use crate::checksum::aws_etag::PartRange;
 use crate::checksum::file::{Checksum, SumsFile};
 use crate::cli::CopyMode;
 use crate::error::{ApiError, Error};
+use crate::io::select::SelectSummary;
+use crate::io::sums::ObjectTags;
+use crate::task::audit::{AuditObject, AuditRestore, AuditStatus};
 use crate::task::check::{CheckTask, CheckTaskError, GroupBy};
+use crate::task::compat::{ChecksumCompatibility, Target};
+use crate::task::concat::ConcatOutput;
 use crate::task::copy::{CopyTask, CopyTaskError};
+use crate::task::diff::RunDiff;
+use crate::task::doctor::BucketConfig;
 use crate::task::generate::{GenerateTask, GenerateTaskError, GenerateTaskResult};
-use serde::{Deserialize, Serialize};
+use crate::task::manifest::ScheduleStrategy;
+use crate::task::parts::PartManifest;
+use crate::task::split::SplitManifest;
+use crate::task::sync::SyncDelta;
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::{BTreeMap, HashSet};
 use std::time::Duration;
 
+/// The number of decimal places that floats are written with, so that the output is stable and
+/// can be diffed between runs.
+pub const FLOAT_PRECISION: i32 = 6;
+
+/// Round a float to the canonical output precision.
+pub fn canonical_f64(value: f64) -> f64 {
+    let scale = 10f64.powi(FLOAT_PRECISION);
+    (value * scale).round() / scale
+}
+
+/// Serialize a float with the canonical output precision.
+pub(crate) fn serialize_f64<S>(value: &f64, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(canonical_f64(*value))
+}
+
+/// Serialize an optional float with the canonical output precision.
+pub(crate) fn serialize_option_f64<S>(
+    value: &Option<f64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    value.map(canonical_f64).serialize(serializer)
+}
+
+/// Serialize a set in sorted order, so that the output does not depend on the hash order.
+pub(crate) fn serialize_sorted<S, T>(
+    set: &HashSet<T>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + Ord,
+{
+    let mut values: Vec<_> = set.iter().collect();
+    values.sort();
+    values.serialize(serializer)
+}
+
 /// The result type for stats.
 pub type Result<T> = std::result::Result<T, Box<T>>;
 
@@ -19,6 +74,7 @@ pub type Result<T> = std::result::Result<T, Box<T>>;
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct GenerateStats {
     /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
     pub(crate) elapsed_seconds: f64,
     /// The stats for individual file objects.
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -27,7 +83,10 @@ pub struct GenerateStats {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) check_stats: Option<Box<CheckStats>>,
     /// The API errors if there was permission issues for object attributes.
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     pub(crate) recoverable_errors: HashSet<ApiError>,
     /// An unrecoverable error occurred, causing the execution to stop.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -94,6 +153,7 @@ impl GenerateStats {
             }
             Err(err) => {
                 self.push_task(err.task);
+                self.unrecoverable_error = Some(err.error);
                 Err(Box::new(self))
             }
         }
@@ -177,6 +237,66 @@ impl From<&CheckStats> for Option<CopySuccessReason> {
     fn from(stats: &CheckStats) -> Self {
         Option::<ChecksumPair>::from(stats)
             .map(|checksum_match| CopySuccessReason::new(Some(checksum_match), "checksums match"))
+            .or_else(|| {
+                (!stats.byte_comparisons.is_empty()
+                    && stats
+                        .byte_comparisons
+                        .iter()
+                        .all(|comparison| comparison.equal))
+                .then(|| CopySuccessReason::message("bytes match"))
+            })
+    }
+}
+
+/// What was decided while planning or performing a copy.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionKind {
+    /// Whether the copy is server-side or download-upload.
+    CopyMode,
+    /// Whether the copy uses a single part or multipart upload, and the part size.
+    PartSize,
+    /// The additional checksum set on the destination.
+    Checksum,
+    /// Whether the copy was skipped.
+    Skip,
+}
+
+/// A choice made by a copy and the reason it was made, so that the behavior of a run can be
+/// explained from its stats without tracing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// What was decided.
+    pub(crate) kind: DecisionKind,
+    /// The choice that was made.
+    pub(crate) choice: String,
+    /// Why the choice was made.
+    pub(crate) reason: String,
+}
+
+impl Decision {
+    /// Create a new decision.
+    pub fn new(kind: DecisionKind, choice: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            choice: choice.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// What was decided.
+    pub fn kind(&self) -> DecisionKind {
+        self.kind
+    }
+
+    /// The choice that was made.
+    pub fn choice(&self) -> &str {
+        &self.choice
+    }
+
+    /// Why the choice was made.
+    pub fn reason(&self) -> &str {
+        &self.reason
     }
 }
 
@@ -204,6 +324,13 @@ pub struct GenerateFileStats {
     pub(crate) updated: bool,
     /// The set of checksums that were generated.
     pub(crate) checksums_generated: ChecksumStats,
+    /// The chunk size that the reader used, which is useful for tuning `--reader-chunk-size`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) reader_chunk_size: Option<usize>,
+    /// The most bytes that the reader buffered for a checksum, which is useful for tuning
+    /// `--channel-capacity` and `--max-buffer-bytes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) buffer_high_water_bytes: Option<usize>,
 }
 
 impl GenerateFileStats {
@@ -213,14 +340,32 @@ impl GenerateFileStats {
             input,
             updated,
             checksums_generated,
+            reader_chunk_size: None,
+            buffer_high_water_bytes: None,
         }
     }
 
+    /// Set the chunk size that the reader used.
+    pub fn with_reader_chunk_size(mut self, reader_chunk_size: Option<usize>) -> Self {
+        self.reader_chunk_size = reader_chunk_size;
+        self
+    }
+
+    /// Set the most bytes that the reader buffered for a checksum.
+    pub fn with_buffer_high_water_bytes(mut self, buffer_high_water_bytes: Option<usize>) -> Self {
+        self.buffer_high_water_bytes = buffer_high_water_bytes;
+        self
+    }
+
     /// Create generate stats from a task.
     pub fn from_task(task: GenerateTask) -> Self {
+        let reader_chunk_size = task.reader_chunk_size();
+        let buffer_high_water_bytes = task.buffer_high_water_bytes();
         let (_, object, updated, checksums_generated) = task.into_inner();
 
         Self::new(object.location(), updated, checksums_generated.into())
+            .with_reader_chunk_size(reader_chunk_size)
+            .with_buffer_high_water_bytes(buffer_high_water_bytes)
     }
 }
 
@@ -228,6 +373,7 @@ impl GenerateFileStats {
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CheckStats {
     /// The time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
     pub(crate) elapsed_seconds: f64,
     /// Whether the check compared for equality of comparability. Equality ensures that there is
     /// at least one checksum with the same value. Comparability only ensures that there is at
@@ -237,7 +383,7 @@ pub struct CheckStats {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub(crate) compared: Vec<CheckComparison>,
     /// Comparison groups. Files in the same group are considered equal or comparable depending
-    /// on the comparison type.
+    /// on the comparison type. Locations within a group, and the groups themselves, are sorted.
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub(crate) groups: Vec<Vec<String>>,
     /// The set of sums that were updated if using `--update`.
@@ -246,8 +392,34 @@ pub struct CheckStats {
     /// Any generate stats computed if using `--missing`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) generate_stats: Option<GenerateStats>,
+    /// The tags and metadata of the objects if using `--compare-tags`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<TagComparison>,
+    /// Whether all objects have the same tags and metadata if using `--compare-tags`. This is
+    /// unknown if the tags of any object could not be read, unless the tags that were read
+    /// already differ.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tags_match: Option<bool>,
+    /// Pairs of sums files which agree on some values but disagree on others, such as a matching
+    /// checksum with a different size.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) conflicts: Vec<CheckConflict>,
+    /// The byte by byte comparisons of each input with the first input if using
+    /// `--byte-compare`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) byte_comparisons: Vec<ByteComparison>,
+    /// The approximate range where the objects diverge if using `--locate-difference` on a
+    /// copy that failed its check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) difference_range: Option<DifferenceRange>,
+    /// Whether enough replicas agreed if using `--quorum`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) quorum: Option<QuorumStats>,
     /// The API errors if there was permission issues for object attributes.
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     pub(crate) api_errors: HashSet<ApiError>,
     /// An unrecoverable error occurred, causing the execution to stop.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -288,11 +460,47 @@ impl CheckStats {
             groups,
             updated,
             generate_stats,
+            tags: vec![],
+            tags_match: None,
+            conflicts: vec![],
+            byte_comparisons: vec![],
+            difference_range: None,
+            quorum: None,
             api_errors,
             unrecoverable_error: None,
         }
     }
 
+    /// Set the compared tags and whether they match across all objects.
+    pub fn with_tags(mut self, tags: Vec<TagComparison>) -> Self {
+        let read: Vec<_> = tags.iter().filter_map(|tag| tag.tags.as_ref()).collect();
+        self.tags_match = match read.first() {
+            Some(first) if read.iter().any(|tags| tags != first) => Some(false),
+            _ if read.is_empty() || read.len() < tags.len() => None,
+            _ => Some(true),
+        };
+        self.tags = tags;
+        self
+    }
+
+    /// Set the conflicts found between sums files.
+    pub fn with_conflicts(mut self, conflicts: Vec<CheckConflict>) -> Self {
+        self.conflicts = conflicts;
+        self
+    }
+
+    /// Set the byte by byte comparisons of the inputs.
+    pub fn with_byte_comparisons(mut self, byte_comparisons: Vec<ByteComparison>) -> Self {
+        self.byte_comparisons = byte_comparisons;
+        self
+    }
+
+    /// Determine whether at least `required` replicas agree from the groups of the check.
+    pub fn with_quorum(mut self, required: u64) -> Self {
+        self.quorum = Some(QuorumStats::new(required, &self.groups));
+        self
+    }
+
     /// Create check stats from a generate task.
     pub fn from_generate_task(group_by: GroupBy, generate_stats: GenerateStats) -> Self {
         Self::new(
@@ -308,6 +516,8 @@ impl CheckStats {
     /// Create check stats from a task.
     pub fn from_task(task: CheckTask, generate_stats: Option<GenerateStats>) -> Self {
         let group_by = task.group_by();
+        let tags = task.tags().to_vec();
+        let conflicts = task.conflicts().to_vec();
         let (objects, compared, updated, api_errors) = task.into_inner();
 
         Self::new(
@@ -318,6 +528,8 @@ impl CheckStats {
             generate_stats,
             api_errors,
         )
+        .with_tags(tags)
+        .with_conflicts(conflicts)
     }
 
     /// Set the number of elapsed seconds.
@@ -327,10 +539,21 @@ impl CheckStats {
     }
 }
 
+/// Whether a count is zero, so that it can be left out of the output.
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Whether a flag is false, so that it can be left out of the output.
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 /// Represents stats from a `copy` operation.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CopyStats {
     /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
     pub(crate) elapsed_seconds: f64,
     /// The source of the copy.
     pub(crate) source: String,
@@ -338,6 +561,21 @@ pub struct CopyStats {
     pub(crate) destination: String,
     /// The total bytes transferred to the destination.
     pub(crate) bytes_transferred: u64,
+    /// The total bytes kept from the existing destination by a differential copy.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) bytes_reused: u64,
+    /// Whether the destination validated the uploaded data using a `Content-MD5` computed from
+    /// the source sums. This only applies to single part uploads.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) content_md5_validated: bool,
+    /// The additional checksum that the destination rejected, so the copy was sent without it
+    /// and verified using the other checksums of the object instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rejected_checksum: Option<String>,
+    /// The local file that the source is hard-linked to, if it was detected when copying a
+    /// directory. If hard links were deduplicated, the source is the destination of that file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) hard_link_of: Option<String>,
     /// Whether the copy was skipped because the destination already has the file with
     /// matching sums.
     pub(crate) skipped: bool,
@@ -354,13 +592,24 @@ pub struct CopyStats {
     /// message is populated.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) success_reason: Option<CopySuccessReason>,
+    /// The choices made by the copy, in order, e.g. why it was skipped, the copy mode, the part
+    /// size and the additional checksum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) decisions: Vec<Decision>,
     /// The number of retries if there was permission issues for copying metadata or tags.
     pub(crate) n_retries: u64,
+    /// The number of times a downloaded part was fetched again because it was truncated or did
+    /// not match the checksum of the source part. These are also counted in `n_retries`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) n_download_retries: u64,
     /// Stats from checking sums to ensure that the copy was successful.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) check_stats: Option<CheckStats>,
     /// The API errors if there was permission issues for copying metadata or tags.
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     pub(crate) api_errors: HashSet<ApiError>,
     /// An unrecoverable error occurred, causing the execution to stop.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -399,17 +648,39 @@ impl CopyStats {
             source,
             destination,
             bytes_transferred: 0,
+            bytes_reused: 0,
+            content_md5_validated: false,
+            rejected_checksum: None,
+            hard_link_of: None,
             skipped,
             sums_mismatch,
             copy_mode,
             success_reason: Option::<CopySuccessReason>::from(&check_stats),
+            decisions: vec![],
             n_retries: 0,
+            n_download_retries: 0,
             api_errors: Default::default(),
             check_stats: Some(check_stats),
             unrecoverable_error: None,
         }
     }
 
+    /// Create copy stats for a copy that failed before any data was copied.
+    pub fn from_error(
+        source: String,
+        destination: String,
+        copy_mode: CopyMode,
+        error: Error,
+    ) -> Self {
+        Self {
+            source,
+            destination,
+            copy_mode,
+            unrecoverable_error: Some(error),
+            ..Default::default()
+        }
+    }
+
     /// Create copy stats from a task.
     pub fn from_task(
         copy_task: CopyTask,
@@ -423,11 +694,17 @@ impl CopyStats {
             source: copy_task.source().format(),
             destination: copy_task.destination().format(),
             bytes_transferred: copy_task.bytes_transferred(),
+            bytes_reused: copy_task.bytes_reused(),
+            content_md5_validated: copy_task.content_md5_validated(),
+            rejected_checksum: copy_task.rejected_checksum(),
+            hard_link_of: None,
             skipped,
             sums_mismatch,
             copy_mode: copy_task.copy_mode(),
             success_reason: reason,
+            decisions: copy_task.decisions().to_vec(),
             n_retries: copy_task.n_retries(),
+            n_download_retries: copy_task.n_download_retries(),
             api_errors: copy_task.api_errors(),
             check_stats,
             unrecoverable_error: None,
@@ -439,6 +716,690 @@ impl CopyStats {
         self.elapsed_seconds = elapsed.as_secs_f64();
         self
     }
+
+    /// Set the local file that the source is hard-linked to.
+    pub fn set_hard_link_of(mut self, hard_link_of: Option<String>) -> Self {
+        self.hard_link_of = hard_link_of;
+        self
+    }
+}
+
+/// Records the transition of an object to another storage class from a `transition` command.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TransitionStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The location of the object.
+    pub(crate) location: String,
+    /// The storage class of the object before the transition.
+    pub(crate) from_storage_class: String,
+    /// The storage class of the object after the transition.
+    pub(crate) to_storage_class: String,
+    /// The ETag of the object, which is confirmed to be the same after the transition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) e_tag: Option<String>,
+    /// Whether the object was skipped because it already has the storage class.
+    pub(crate) skipped: bool,
+    /// A checksum that was confirmed to be the same after the transition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) checksum_match: Option<ChecksumPair>,
+    /// Stats from the copy that performed the transition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) copy_stats: Option<CopyStats>,
+    /// An unrecoverable error occurred, causing the transition of this object to stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) unrecoverable_error: Option<Error>,
+}
+
+impl TransitionStats {
+    /// Create transition stats for an object before it is transitioned.
+    pub fn new(location: String, from_storage_class: String, to_storage_class: String) -> Self {
+        Self {
+            location,
+            from_storage_class,
+            to_storage_class,
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+}
+
+/// Records the audit of an object from an `audit` command once it has completed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditStats {
+    /// The location of the object.
+    pub(crate) location: String,
+    /// The storage class of the object.
+    pub(crate) storage_class: String,
+    /// The status of the object at the end of the audit.
+    pub(crate) status: AuditStatus,
+    /// The restore requested for the object, if it was archived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) restore: Option<AuditRestore>,
+    /// A checksum that was confirmed when verifying the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) checksum_match: Option<ChecksumPair>,
+    /// The S3 Select summary that matched the copy of the object, if it was compared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) select_match: Option<SelectSummary>,
+    /// Why the object failed or was skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+}
+
+impl From<&AuditObject> for AuditStats {
+    fn from(object: &AuditObject) -> Self {
+        Self {
+            location: object.location(),
+            storage_class: object.storage_class().to_string(),
+            status: object.status(),
+            restore: object.restore().cloned(),
+            checksum_match: object.checksum_match().cloned(),
+            select_match: object.select_match().cloned(),
+            message: object.message().map(str::to_string),
+        }
+    }
+}
+
+/// Reports the configuration of a destination from a `doctor` command.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DoctorStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The destination that was inspected.
+    pub(crate) destination: String,
+    /// The configuration of the destination bucket, if the destination is on S3.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) bucket: Option<BucketConfig>,
+    /// The API errors from settings that could not be read, e.g. due to missing permissions.
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    pub(crate) api_errors: HashSet<ApiError>,
+}
+
+impl DoctorStats {
+    /// Create doctor stats for a destination.
+    pub fn new(
+        destination: String,
+        bucket: Option<BucketConfig>,
+        api_errors: HashSet<ApiError>,
+    ) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            destination,
+            bucket,
+            api_errors,
+        }
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+}
+
+/// Reports the result of a `concat` command.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConcatStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The sources that were concatenated, in order.
+    pub(crate) sources: Vec<String>,
+    /// The destination object.
+    pub(crate) destination: String,
+    /// The total size of the destination.
+    pub(crate) size: u64,
+    /// The number of parts copied to the destination.
+    pub(crate) n_parts: usize,
+    /// The multipart ETag of the destination, computed from the ETags of the copied parts.
+    pub(crate) e_tag: String,
+    /// The full-object checksum of the destination, which matched the combined checksums of
+    /// the sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) checksum_match: Option<ChecksumPair>,
+}
+
+impl ConcatStats {
+    /// Create concat stats from the output of a concat task.
+    pub fn new(sources: Vec<String>, destination: String, output: ConcatOutput) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            sources,
+            destination,
+            size: output.size,
+            n_parts: output.n_parts,
+            e_tag: output.e_tag,
+            checksum_match: output.checksum_match,
+        }
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+}
+
+/// Summarizes the scheduling of a `manifest` command once all rows have been copied.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ManifestStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The order in which rows were copied.
+    pub(crate) schedule_strategy: ScheduleStrategy,
+    /// The number of rows copied at the same time.
+    pub(crate) object_concurrency: u64,
+    /// The number of rows in the manifest.
+    pub(crate) n_rows: u64,
+    /// The number of rows that were copied or skipped successfully.
+    pub(crate) n_copied: u64,
+    /// The number of rows that failed to copy.
+    pub(crate) n_failed: u64,
+    /// The total bytes transferred by all rows.
+    pub(crate) bytes_transferred: u64,
+    /// The bytes transferred per second of elapsed time.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) bytes_per_second: f64,
+    /// The fraction of the time that the object concurrency slots were busy copying, where 1
+    /// means that every slot was copying for the whole run.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) utilization: f64,
+    /// The path that the snapshot manifest of the destinations was written to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot_manifest: Option<String>,
+    #[serde(skip)]
+    busy_seconds: f64,
+}
+
+impl ManifestStats {
+    /// Create manifest stats for a schedule.
+    pub fn new(schedule_strategy: ScheduleStrategy, object_concurrency: u64, n_rows: u64) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            schedule_strategy,
+            object_concurrency,
+            n_rows,
+            n_copied: 0,
+            n_failed: 0,
+            bytes_transferred: 0,
+            bytes_per_second: 0.0,
+            utilization: 0.0,
+            snapshot_manifest: None,
+            busy_seconds: 0.0,
+        }
+    }
+
+    /// Add the stats of a copied row.
+    pub fn push_copy(&mut self, stats: &Result<CopyStats>) {
+        let copy = match stats {
+            Ok(stats) => {
+                self.n_copied += 1;
+                stats
+            }
+            Err(stats) => {
+                self.n_failed += 1;
+                stats.as_ref()
+            }
+        };
+
+        self.bytes_transferred += copy.bytes_transferred;
+        self.busy_seconds += copy.elapsed_seconds;
+    }
+
+    /// Set the number of elapsed seconds, which computes the throughput and utilization.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        if self.elapsed_seconds > 0.0 {
+            self.bytes_per_second = self.bytes_transferred as f64 / self.elapsed_seconds;
+            self.utilization = (self.busy_seconds
+                / (self.elapsed_seconds * self.object_concurrency as f64))
+                .min(1.0);
+        }
+        self
+    }
+
+    /// Set the path that the snapshot manifest was written to.
+    pub fn with_snapshot_manifest(mut self, snapshot_manifest: String) -> Self {
+        self.snapshot_manifest = Some(snapshot_manifest);
+        self
+    }
+}
+
+/// Summarizes a recursive `copy` of a prefix or directory once every object has been copied.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PrefixCopyStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The source prefix or directory.
+    pub(crate) source: String,
+    /// The destination prefix or directory.
+    pub(crate) destination: String,
+    /// The number of objects found under the source.
+    pub(crate) n_objects: u64,
+    /// The number of objects that were copied or skipped successfully.
+    pub(crate) n_copied: u64,
+    /// The number of objects that failed to copy or check.
+    pub(crate) n_failed: u64,
+    /// The total bytes transferred by all objects.
+    pub(crate) bytes_transferred: u64,
+    /// The path that the snapshot manifest of the destinations was written to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot_manifest: Option<String>,
+}
+
+impl PrefixCopyStats {
+    /// Create recursive copy stats for the objects under a source.
+    pub fn new(source: String, destination: String, n_objects: u64) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            source,
+            destination,
+            n_objects,
+            n_copied: 0,
+            n_failed: 0,
+            bytes_transferred: 0,
+            snapshot_manifest: None,
+        }
+    }
+
+    /// Add the stats of a copied object.
+    pub fn push_copy(&mut self, stats: &Result<CopyStats>) {
+        let copy = match stats {
+            Ok(stats) => {
+                self.n_copied += 1;
+                stats
+            }
+            Err(stats) => {
+                self.n_failed += 1;
+                stats.as_ref()
+            }
+        };
+
+        self.bytes_transferred += copy.bytes_transferred;
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+
+    /// Set the path that the snapshot manifest was written to.
+    pub fn with_snapshot_manifest(mut self, snapshot_manifest: String) -> Self {
+        self.snapshot_manifest = Some(snapshot_manifest);
+        self
+    }
+}
+
+/// Summarizes a `sync` command once every changed object has been copied.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The source prefix or directory.
+    pub(crate) source: String,
+    /// The destination prefix or directory.
+    pub(crate) destination: String,
+    /// The number of objects found under the source.
+    pub(crate) n_objects: u64,
+    /// The number of objects that did not exist at the destination.
+    pub(crate) n_missing: u64,
+    /// The number of objects with a different size at the destination.
+    pub(crate) n_size_mismatch: u64,
+    /// The number of objects with the same size but different checksums at the destination.
+    pub(crate) n_checksum_mismatch: u64,
+    /// The number of objects that were already the same at the destination.
+    pub(crate) n_unchanged: u64,
+    /// The number of changed objects that were copied successfully.
+    pub(crate) n_copied: u64,
+    /// The number of changed objects that failed to copy.
+    pub(crate) n_failed: u64,
+    /// The total bytes transferred by all copies.
+    pub(crate) bytes_transferred: u64,
+    /// Whether this was a dry run, in which nothing was copied or deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) dry_run: bool,
+    /// The destination objects that were deleted because they do not exist at the source, or
+    /// that would be deleted in a dry run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) deleted: Vec<String>,
+    /// The orphaned sums files at the destination that were deleted because their object no
+    /// longer exists, or that would be deleted in a dry run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) deleted_sums_files: Vec<String>,
+    /// The path that the snapshot manifest of the destinations was written to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot_manifest: Option<String>,
+}
+
+impl SyncStats {
+    /// Create sync stats for a source and destination.
+    pub fn new(source: String, destination: String) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            source,
+            destination,
+            n_objects: 0,
+            n_missing: 0,
+            n_size_mismatch: 0,
+            n_checksum_mismatch: 0,
+            n_unchanged: 0,
+            n_copied: 0,
+            n_failed: 0,
+            bytes_transferred: 0,
+            dry_run: false,
+            deleted: vec![],
+            deleted_sums_files: vec![],
+            snapshot_manifest: None,
+        }
+    }
+
+    /// Set whether this is a dry run.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Record a destination object that was deleted.
+    pub fn push_deleted(&mut self, location: String) {
+        self.deleted.push(location);
+    }
+
+    /// Set the orphaned sums files that were deleted.
+    pub fn with_deleted_sums_files(mut self, deleted_sums_files: Vec<String>) -> Self {
+        self.deleted_sums_files = deleted_sums_files;
+        self
+    }
+
+    /// Add the delta of an object under the source.
+    pub fn push_delta(&mut self, delta: SyncDelta) {
+        self.n_objects += 1;
+        match delta {
+            SyncDelta::Missing => self.n_missing += 1,
+            SyncDelta::SizeMismatch => self.n_size_mismatch += 1,
+            SyncDelta::ChecksumMismatch => self.n_checksum_mismatch += 1,
+            SyncDelta::Unchanged => self.n_unchanged += 1,
+            SyncDelta::SameSize => {}
+        }
+    }
+
+    /// Add the stats of a copied object.
+    pub fn push_copy(&mut self, stats: &Result<CopyStats>) {
+        let copy = match stats {
+            Ok(stats) => {
+                self.n_copied += 1;
+                stats
+            }
+            Err(stats) => {
+                self.n_failed += 1;
+                stats.as_ref()
+            }
+        };
+
+        self.bytes_transferred += copy.bytes_transferred;
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+
+    /// Set the path that the snapshot manifest was written to.
+    pub fn with_snapshot_manifest(mut self, snapshot_manifest: String) -> Self {
+        self.snapshot_manifest = Some(snapshot_manifest);
+        self
+    }
+}
+
+/// Reports the part manifest written by a `sums export-parts` command. The parts themselves
+/// are only written to the manifest.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportPartsStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The object that the parts belong to.
+    pub(crate) location: String,
+    /// The path that the part manifest was written to.
+    pub(crate) output: String,
+    /// The size of the object.
+    pub(crate) size: u64,
+    /// The size of each part, except for the last part.
+    pub(crate) part_size: u64,
+    /// The number of parts.
+    pub(crate) n_parts: u64,
+    /// The multipart ETag of the object, computed from the part MD5s.
+    pub(crate) etag: String,
+}
+
+impl ExportPartsStats {
+    /// Create export stats from the written manifest.
+    pub fn new(
+        output: String,
+        part_size: u64,
+        manifest: PartManifest,
+    ) -> crate::error::Result<Self> {
+        Ok(Self {
+            elapsed_seconds: 0.0,
+            location: manifest.location,
+            output,
+            size: manifest.size,
+            part_size,
+            n_parts: u64::try_from(manifest.parts.len())?,
+            etag: manifest.etag,
+        })
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+}
+
+/// Reports the pieces written by a `split` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SplitStats {
+    /// Time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The location of the manifest listing the pieces.
+    pub(crate) manifest: String,
+    /// The contents of the manifest.
+    #[serde(flatten)]
+    pub(crate) split: SplitManifest,
+}
+
+impl SplitStats {
+    /// Create split stats from the manifest.
+    pub fn new(manifest: String, split: SplitManifest) -> Self {
+        Self {
+            elapsed_seconds: 0.0,
+            manifest,
+            split,
+        }
+    }
+
+    /// Set the number of elapsed seconds.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        self
+    }
+}
+
+/// The tags and metadata of an object read during a `check`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagComparison {
+    /// The location of the object.
+    pub(crate) location: String,
+    /// The tags and metadata of the object, or none if they could not be read.
+    #[serde(flatten)]
+    pub(crate) tags: Option<ObjectTags>,
+}
+
+impl TagComparison {
+    /// Create a new tag comparison.
+    pub fn new(location: String, tags: Option<ObjectTags>) -> Self {
+        Self { location, tags }
+    }
+}
+
+/// A pair of sums files that are inconsistent with each other, found during a `check`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CheckConflict {
+    /// The locations of the two sums files.
+    pub(crate) locations: Vec<String>,
+    /// The kind of inconsistency.
+    pub(crate) kind: ConflictKind,
+    /// The recorded sizes of the two sums files.
+    pub(crate) sizes: Vec<Option<u64>>,
+    /// The checksum that has the same value in both sums files.
+    pub(crate) matching: Ctx,
+    /// The checksum that has a different value in each sums file, if the sizes match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mismatched: Option<Ctx>,
+}
+
+impl CheckConflict {
+    /// Create a new check conflict.
+    pub fn new(
+        locations: Vec<String>,
+        kind: ConflictKind,
+        sizes: Vec<Option<u64>>,
+        matching: Ctx,
+        mismatched: Option<Ctx>,
+    ) -> Self {
+        Self {
+            locations,
+            kind,
+            sizes,
+            matching,
+            mismatched,
+        }
+    }
+}
+
+/// A byte by byte comparison of two objects, found during a `check` with `--byte-compare`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ByteComparison {
+    /// The locations of the two objects.
+    pub(crate) locations: Vec<String>,
+    /// Whether the objects have the same bytes.
+    pub(crate) equal: bool,
+    /// The number of bytes that were compared before finding a difference or reaching the end.
+    pub(crate) bytes_compared: u64,
+    /// The offset of the first byte that differs, if any. If one object is a prefix of the other,
+    /// this is the size of the shorter object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) first_difference: Option<u64>,
+}
+
+impl ByteComparison {
+    /// Create a new byte comparison.
+    pub fn new(locations: Vec<String>, bytes_compared: u64, first_difference: Option<u64>) -> Self {
+        Self {
+            locations,
+            equal: first_difference.is_none(),
+            bytes_compared,
+            first_difference,
+        }
+    }
+}
+
+/// The range of bytes where two objects first diverge.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DifferenceRange {
+    /// The offset of the first byte of the range.
+    pub(crate) start: u64,
+    /// The offset after the last byte of the range.
+    pub(crate) end: u64,
+    /// The number of part checksums, or chunks of bytes, that were compared to locate the
+    /// difference.
+    pub(crate) ranges_compared: u64,
+}
+
+impl DifferenceRange {
+    /// Create a new difference range.
+    pub fn new(start: u64, end: u64, ranges_compared: u64) -> Self {
+        Self {
+            start,
+            end,
+            ranges_compared,
+        }
+    }
+}
+
+/// Whether a quorum of replicas agree, found during a `check` with `--quorum`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QuorumStats {
+    /// The number of replicas that must agree.
+    pub(crate) required: u64,
+    /// Whether at least the required number of replicas agree.
+    pub(crate) reached: bool,
+    /// The replicas in the largest group of equal sums files. This is empty if there is more than
+    /// one largest group, in which case the quorum is not reached.
+    pub(crate) agreeing: Vec<String>,
+    /// The replicas that do not agree with the largest group.
+    pub(crate) outliers: Vec<String>,
+}
+
+impl QuorumStats {
+    /// Determine the quorum from the groups of equal locations.
+    pub fn new(required: u64, groups: &[Vec<String>]) -> Self {
+        let largest = groups.iter().map(Vec::len).max().unwrap_or_default();
+        let mut largest_groups = groups.iter().filter(|group| group.len() == largest);
+        let agreeing = match (largest_groups.next(), largest_groups.next()) {
+            (Some(group), None) => group.clone(),
+            _ => vec![],
+        };
+
+        let mut outliers: Vec<_> = groups
+            .iter()
+            .flatten()
+            .filter(|location| !agreeing.contains(location))
+            .cloned()
+            .collect();
+        outliers.sort();
+
+        Self {
+            required,
+            reached: !agreeing.is_empty() && agreeing.len() as u64 >= required,
+            agreeing,
+            outliers,
+        }
+    }
+
+    /// Whether at least the required number of replicas agree.
+    pub fn is_reached(&self) -> bool {
+        self.reached
+    }
+}
+
+/// The kind of inconsistency between two sums files.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// A checksum has the same value, but the recorded sizes differ. The files are not
+    /// considered equal.
+    SizeMismatch,
+    /// The sizes and a checksum match, but another checksum of the same kind has a different
+    /// value. The files are still considered equal by the matching checksum.
+    ChecksumMismatch,
 }
 
 /// The specific comparison that a `check` performed.
@@ -457,3 +1418,208 @@ impl CheckComparison {
         Self { locations, reason }
     }
 }
+
+/// Stats from running a `sums infer-etag` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InferEtagStats {
+    /// The ETag that the part sizes were inferred from.
+    pub(crate) etag: String,
+    /// The size of the object.
+    pub(crate) size: u64,
+    /// The number of parts in the ETag.
+    pub(crate) n_parts: u64,
+    /// The smallest part size that produces the number of parts.
+    pub(crate) min_part_size: u64,
+    /// The largest part size that produces the number of parts, if there is more than one part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_part_size: Option<u64>,
+    /// The candidate checksum contexts, in order of preference.
+    pub(crate) candidates: Vec<String>,
+}
+
+/// Stats from running a `checksum parse` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChecksumParseStats {
+    /// The checksum that was parsed.
+    pub(crate) spec: String,
+    /// The normalized canonical form of the checksum.
+    pub(crate) canonical: String,
+    /// The file size that the part layout was computed for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) file_size: Option<u64>,
+    /// The number of parts that the file is split into.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) n_parts: Option<u64>,
+    /// The parts that the file is split into, as ranges of parts with the same size.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) parts: Option<Vec<PartRange>>,
+}
+
+/// Stats from running a `stats diff` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatsDiffStats {
+    /// The fraction that throughput can decrease by before it is reported as a regression.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) regression_threshold: f64,
+    /// The differences between the runs.
+    #[serde(flatten)]
+    pub(crate) diff: RunDiff,
+}
+
+/// Stats from running a `sums gc` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SumsGcStats {
+    /// The prefix or directory that was scanned.
+    pub(crate) location: String,
+    /// The sums files whose target object does not exist.
+    pub(crate) orphaned: Vec<String>,
+    /// Whether the orphaned sums files were deleted.
+    pub(crate) deleted: bool,
+}
+
+/// Stats from running a `sums compat` command.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompatStats {
+    /// The object that the sums are for.
+    pub(crate) input: String,
+    /// The provider that the object is migrated to.
+    pub(crate) target: Target,
+    /// The size of the object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) size: Option<u64>,
+    /// The compatibility of each checksum, in order of preference.
+    pub(crate) checksums: Vec<ChecksumCompatibility>,
+    /// The most preferred checksum that the target reproduces natively, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) trusted: Option<String>,
+}
+
+impl CompatStats {
+    /// Create new compat stats for the sums of an object.
+    pub fn new(input: String, target: Target, sums: &SumsFile) -> Self {
+        let checksums = target.compatibility(sums);
+        let trusted = checksums
+            .iter()
+            .find(|checksum| checksum.is_native())
+            .map(|checksum| checksum.checksum.to_string());
+
+        Self {
+            input,
+            target,
+            size: sums.size,
+            checksums,
+            trusted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::{json, to_value};
+
+    #[test]
+    fn stable_output() -> Result<()> {
+        let api_errors =
+            HashSet::from_iter(["c", "a", "b"].map(|code| {
+                ApiError::new(code.to_string(), "HeadObject".to_string(), "".to_string())
+            }));
+        let stats = CheckStats::new(GroupBy::Equality, vec![], vec![], vec![], None, api_errors)
+            .with_elapsed(Duration::from_nanos(1_234_567_890));
+
+        let value = to_value(&stats)?;
+        assert_eq!(value["elapsed_seconds"], json!(1.234568));
+        assert_eq!(
+            value["api_errors"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|err| err["code"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn tags_match() {
+        let tags = |tags: Option<&str>| {
+            tags.map(|value| {
+                ObjectTags::new(
+                    BTreeMap::from([("key".to_string(), value.to_string())]),
+                    BTreeMap::new(),
+                )
+            })
+        };
+        let tags_match = |values: &[Option<&str>]| {
+            let tags = values
+                .iter()
+                .map(|value| TagComparison::new("location".to_string(), tags(*value)))
+                .collect();
+            CheckStats::new(
+                GroupBy::Equality,
+                vec![],
+                vec![],
+                vec![],
+                None,
+                HashSet::new(),
+            )
+            .with_tags(tags)
+            .tags_match
+        };
+
+        assert_eq!(tags_match(&[]), None);
+        assert_eq!(tags_match(&[Some("a"), Some("a")]), Some(true));
+        assert_eq!(tags_match(&[Some("a"), Some("b")]), Some(false));
+        assert_eq!(tags_match(&[Some("a"), None]), None);
+        assert_eq!(tags_match(&[Some("a"), Some("b"), None]), Some(false));
+        assert_eq!(tags_match(&[None, None]), None);
+    }
+
+    #[test]
+    fn quorum_outliers() {
+        let groups = vec![
+            vec!["a".to_string(), "c".to_string()],
+            vec!["b".to_string()],
+        ];
+        let quorum = QuorumStats::new(2, &groups);
+        assert!(quorum.is_reached());
+        assert_eq!(quorum.agreeing, ["a", "c"]);
+        assert_eq!(quorum.outliers, ["b"]);
+        assert!(!QuorumStats::new(3, &groups).is_reached());
+
+        let tied = vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["c".to_string(), "d".to_string()],
+        ];
+        let quorum = QuorumStats::new(2, &tied);
+        assert!(!quorum.is_reached());
+        assert!(quorum.agreeing.is_empty());
+        assert_eq!(quorum.outliers, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn manifest_stats_efficiency() -> Result<()> {
+        let copy = |bytes_transferred, elapsed_seconds| CopyStats {
+            bytes_transferred,
+            elapsed_seconds,
+            ..Default::default()
+        };
+
+        let mut stats = ManifestStats::new(ScheduleStrategy::LargestFirst, 2, 3);
+        stats.push_copy(&Ok(copy(300, 4.0)));
+        stats.push_copy(&Ok(copy(100, 1.0)));
+        stats.push_copy(&Err(Box::new(copy(0, 1.0))));
+        let stats = stats.with_elapsed(Duration::from_secs(4));
+
+        let value = to_value(&stats)?;
+        assert_eq!(value["schedule_strategy"], json!("largest-first"));
+        assert_eq!(value["n_copied"], json!(2));
+        assert_eq!(value["n_failed"], json!(1));
+        assert_eq!(value["bytes_per_second"], json!(100.0));
+        assert_eq!(value["utilization"], json!(0.75));
+
+        Ok(())
+    }
+}
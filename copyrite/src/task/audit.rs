@@ -0,0 +1,989 @@
+//! Audit objects under an S3 prefix, restoring archived objects in batches before verifying
+//! them against their sums files. Progress is saved to a state file so that an audit can be
+//! resumed while restores complete over the following hours or days.
+//!
+
+use crate::checksum::file::{SUMS_FILE_ENDING, SumsFile};
+use crate::error::Error::{CheckError, ParseError};
+use crate::error::{Error, Result};
+use crate::io::select::{SelectQuery, SelectSummary};
+use crate::io::sums::ObjectSumsBuilder;
+use crate::io::sums::aws::S3;
+use crate::io::{Provider, S3Client};
+use crate::stats::ChecksumPair;
+use crate::task::generate::GenerateTask;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::operation::restore_object::RestoreObjectError;
+use aws_sdk_s3::types::{RestoreRequest, Tier};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_slice, to_vec_pretty};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+
+/// Storage classes which must be restored before objects can be read.
+const ARCHIVED_STORAGE_CLASSES: [&str; 2] = ["GLACIER", "DEEP_ARCHIVE"];
+
+/// The error code returned when a restore has already been requested for an object.
+const RESTORE_ALREADY_IN_PROGRESS: &str = "RestoreAlreadyInProgress";
+
+/// The status of an object in an audit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    /// The object has not been restored or verified yet.
+    Pending,
+    /// A restore was requested and the object is waiting for it to complete.
+    Restoring,
+    /// The object was read and its checksums match its sums file.
+    Verified,
+    /// The object has no sums file to verify against.
+    Skipped,
+    /// The object could not be restored or verified.
+    Failed,
+}
+
+impl AuditStatus {
+    /// Whether the audit of an object with this status has finished.
+    pub fn is_complete(&self) -> bool {
+        matches!(self, Self::Verified | Self::Skipped | Self::Failed)
+    }
+}
+
+/// The retrieval tier of a restore, which trades off how quickly archived objects become
+/// readable against the cost of restoring them. `DEEP_ARCHIVE` objects cannot use `Expedited`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreTier {
+    /// Restores usually complete within minutes, at the highest cost.
+    Expedited,
+    /// Restores usually complete within hours.
+    #[default]
+    Standard,
+    /// Restores can take up to two days, at the lowest cost.
+    Bulk,
+}
+
+impl From<RestoreTier> for Tier {
+    fn from(tier: RestoreTier) -> Self {
+        match tier {
+            RestoreTier::Expedited => Tier::Expedited,
+            RestoreTier::Standard => Tier::Standard,
+            RestoreTier::Bulk => Tier::Bulk,
+        }
+    }
+}
+
+/// The status of a restore request for an object.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreStatus {
+    /// The restore was requested and has not completed yet.
+    Requested,
+    /// A restore had already been requested for the object, e.g. by another audit.
+    AlreadyInProgress,
+    /// The object was already in an active tier, so it did not need restoring.
+    AlreadyActive,
+    /// The restore completed and the object can be read.
+    Completed,
+}
+
+/// A restore requested for an object, which is saved in the state so that a resumed audit
+/// shows how and when the object was restored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRestore {
+    tier: RestoreTier,
+    days: i32,
+    requested_at: String,
+    status: RestoreStatus,
+}
+
+impl AuditRestore {
+    fn new(tier: RestoreTier, days: i32, status: RestoreStatus) -> Self {
+        Self {
+            tier,
+            days,
+            requested_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            status,
+        }
+    }
+
+    /// The retrieval tier of the restore.
+    pub fn tier(&self) -> RestoreTier {
+        self.tier
+    }
+
+    /// The number of days that the restored object remains available.
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    /// When the restore was requested, as an RFC 3339 timestamp.
+    pub fn requested_at(&self) -> &str {
+        &self.requested_at
+    }
+
+    /// The status of the restore.
+    pub fn status(&self) -> RestoreStatus {
+        self.status
+    }
+}
+
+/// An object being audited.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditObject {
+    bucket: String,
+    key: String,
+    storage_class: String,
+    status: AuditStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    restore: Option<AuditRestore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum_match: Option<ChecksumPair>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    select_match: Option<SelectSummary>,
+}
+
+impl AuditObject {
+    /// The location of the object.
+    pub fn location(&self) -> String {
+        Provider::format_s3(&self.bucket, &self.key)
+    }
+
+    /// The storage class of the object when it was listed.
+    pub fn storage_class(&self) -> &str {
+        &self.storage_class
+    }
+
+    /// The status of the object.
+    pub fn status(&self) -> AuditStatus {
+        self.status
+    }
+
+    /// A message describing why the object failed or was skipped.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The restore requested for the object, if it was archived.
+    pub fn restore(&self) -> Option<&AuditRestore> {
+        self.restore.as_ref()
+    }
+
+    /// A checksum that was confirmed when verifying the object.
+    pub fn checksum_match(&self) -> Option<&ChecksumPair> {
+        self.checksum_match.as_ref()
+    }
+
+    /// The S3 Select summary that matched the copy of the object, if it was compared.
+    pub fn select_match(&self) -> Option<&SelectSummary> {
+        self.select_match.as_ref()
+    }
+
+    /// Whether the object must be restored before it can be read.
+    pub fn is_archived(&self) -> bool {
+        ARCHIVED_STORAGE_CLASSES.contains(&self.storage_class.as_str())
+    }
+
+    fn complete(&mut self, status: AuditStatus, message: Option<String>) {
+        self.status = status;
+        self.message = message;
+    }
+}
+
+/// The saved state of an audit.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditState {
+    prefix: String,
+    objects: Vec<AuditObject>,
+}
+
+impl AuditState {
+    /// The objects in the audit.
+    pub fn objects(&self) -> &[AuditObject] {
+        &self.objects
+    }
+
+    /// Read the state from a file, returning `None` if it does not exist.
+    pub async fn read(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        match fs::read(path).await {
+            Ok(contents) => Ok(Some(from_slice(&contents)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Write the state to a file. The state is written to a temporary file first so that an
+    /// interrupted write does not lose the progress of the audit.
+    pub async fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, to_vec_pretty(self)?).await?;
+        fs::rename(&tmp, path).await?;
+
+        Ok(())
+    }
+}
+
+/// Compares audited objects with their copies under another prefix using S3 Select.
+#[derive(Debug, Clone)]
+struct SelectCompare {
+    bucket: String,
+    prefix: String,
+    columns: Vec<String>,
+}
+
+/// Build an audit task.
+#[derive(Debug, Default)]
+pub struct AuditTaskBuilder {
+    prefix: Option<String>,
+    client: Option<S3Client>,
+    state_file: Option<PathBuf>,
+    batch_size: usize,
+    restore_days: i32,
+    restore_tier: RestoreTier,
+    capacity: usize,
+    older_than: Option<Duration>,
+    assert_immutable: bool,
+    select_compare: Option<String>,
+    select_columns: Vec<String>,
+}
+
+impl AuditTaskBuilder {
+    /// Set the prefix of the objects to audit, e.g. `s3://bucket/prefix/`.
+    pub fn with_prefix(mut self, prefix: String) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Set the client.
+    pub fn with_client(mut self, client: S3Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the file that the progress of the audit is saved to.
+    pub fn with_state_file(mut self, state_file: PathBuf) -> Self {
+        self.state_file = Some(state_file);
+        self
+    }
+
+    /// Set the maximum number of restores to request in each step.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the number of days that restored objects remain available.
+    pub fn with_restore_days(mut self, restore_days: i32) -> Self {
+        self.restore_days = restore_days;
+        self
+    }
+
+    /// Set the retrieval tier of restores. This only applies to restores requested from now
+    /// on, as the tier of earlier restores is recorded in the state file.
+    pub fn with_restore_tier(mut self, restore_tier: RestoreTier) -> Self {
+        self.restore_tier = restore_tier;
+        self
+    }
+
+    /// Set the reader capacity used when verifying objects.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Only audit objects that have not been verified within this duration, according to the
+    /// `last-verified-at` field of their sums file. This applies when listing the objects, so it
+    /// does not affect an audit that is resumed from a state file.
+    pub fn with_older_than(mut self, older_than: Duration) -> Self {
+        self.older_than = Some(older_than);
+        self
+    }
+
+    /// Fail objects whose `ETag` or version differs from the one recorded in their sums file
+    /// when they were last verified, as they were modified after being verified.
+    pub fn with_assert_immutable(mut self, assert_immutable: bool) -> Self {
+        self.assert_immutable = assert_immutable;
+        self
+    }
+
+    /// Compare verified CSV and JSON objects with the objects at the same relative key under
+    /// another S3 prefix, e.g. a replica, by counting their rows using S3 Select.
+    pub fn set_select_compare(mut self, select_compare: Option<String>) -> Self {
+        self.select_compare = select_compare;
+        self
+    }
+
+    /// Set the columns to hash when comparing objects using S3 Select, in addition to counting
+    /// their rows.
+    pub fn with_select_columns(mut self, select_columns: Vec<String>) -> Self {
+        self.select_columns = select_columns;
+        self
+    }
+
+    /// Build the audit task, resuming from the state file if it exists and otherwise listing
+    /// the objects under the prefix.
+    pub async fn build(self) -> Result<AuditTask> {
+        let prefix = self
+            .prefix
+            .ok_or_else(|| ParseError("a prefix is required to audit".to_string()))?;
+        let client = self
+            .client
+            .ok_or_else(|| ParseError("an S3 client is required to audit".to_string()))?;
+        let state_file = self
+            .state_file
+            .ok_or_else(|| ParseError("a state file is required to audit".to_string()))?;
+        let select_compare = self
+            .select_compare
+            .map(|compare| {
+                let (bucket, prefix) = Provider::try_from(compare.as_str())?.into_s3()?;
+                if !self.select_columns.is_empty() {
+                    SelectQuery::columns_expression(&self.select_columns)?;
+                }
+
+                Ok::<_, Error>(SelectCompare {
+                    bucket,
+                    prefix,
+                    columns: self.select_columns,
+                })
+            })
+            .transpose()?;
+
+        let state = match AuditState::read(&state_file).await? {
+            Some(state) if state.prefix != prefix => {
+                return Err(ParseError(format!(
+                    "state file {} belongs to an audit of {}, not {}",
+                    state_file.display(),
+                    state.prefix,
+                    prefix
+                )));
+            }
+            Some(state) => state,
+            None => {
+                let state = Self::list(&client, prefix, self.older_than).await?;
+                state.write(&state_file).await?;
+                state
+            }
+        };
+
+        Ok(AuditTask {
+            client,
+            state_file,
+            state,
+            batch_size: self.batch_size,
+            restore_days: self.restore_days,
+            restore_tier: self.restore_tier,
+            capacity: self.capacity,
+            assert_immutable: self.assert_immutable,
+            select_compare,
+        })
+    }
+
+    /// List the objects under the prefix. Sums files are read when verifying the objects that
+    /// they belong to, so they are not audited themselves. Objects verified more recently than
+    /// `older_than` are skipped.
+    async fn list(
+        client: &S3Client,
+        prefix: String,
+        older_than: Option<Duration>,
+    ) -> Result<AuditState> {
+        let (bucket, key_prefix) = Provider::try_from(prefix.as_str())?.into_s3()?;
+        let listed = client.list_objects(&bucket, &key_prefix).await?;
+
+        let keys: HashSet<&str> = listed.iter().filter_map(|object| object.key()).collect();
+        let mut objects: Vec<AuditObject> = listed
+            .iter()
+            .filter_map(|object| {
+                let key = object.key()?;
+                if key.ends_with(SUMS_FILE_ENDING) {
+                    return None;
+                }
+
+                let has_sums = keys.contains(format!("{}{}", key, SUMS_FILE_ENDING).as_str());
+                let (status, message) = if has_sums {
+                    (AuditStatus::Pending, None)
+                } else {
+                    (AuditStatus::Skipped, Some("no sums file".to_string()))
+                };
+
+                Some(AuditObject {
+                    bucket: bucket.to_string(),
+                    key: key.to_string(),
+                    storage_class: object
+                        .storage_class()
+                        .map(|class| class.as_str())
+                        .unwrap_or("STANDARD")
+                        .to_string(),
+                    status,
+                    message,
+                    restore: None,
+                    checksum_match: None,
+                    select_match: None,
+                })
+            })
+            .collect();
+
+        if let Some(older_than) = older_than {
+            let cutoff = SystemTime::now()
+                .checked_sub(older_than)
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            for object in objects
+                .iter_mut()
+                .filter(|object| object.status == AuditStatus::Pending)
+            {
+                if let Some(verified_at) = Self::last_verified_at(client, object).await
+                    && verified_at >= cutoff
+                {
+                    object.complete(
+                        AuditStatus::Skipped,
+                        Some(format!(
+                            "verified recently at {}",
+                            humantime::format_rfc3339_seconds(verified_at)
+                        )),
+                    );
+                }
+            }
+        }
+
+        Ok(AuditState { prefix, objects })
+    }
+
+    /// When an object was last verified according to its sums file. Sums files that cannot be
+    /// read, e.g. because they are archived themselves, are treated as never verified.
+    async fn last_verified_at(client: &S3Client, object: &AuditObject) -> Option<SystemTime> {
+        S3::new(
+            client.clone(),
+            object.bucket.to_string(),
+            object.key.to_string(),
+        )
+        .get_existing_sums()
+        .await
+        .ok()
+        .flatten()?
+        .last_verified_at()
+    }
+}
+
+/// Restores archived objects in batches and verifies objects once they can be read.
+#[derive(Debug)]
+pub struct AuditTask {
+    client: S3Client,
+    state_file: PathBuf,
+    state: AuditState,
+    batch_size: usize,
+    restore_days: i32,
+    restore_tier: RestoreTier,
+    capacity: usize,
+    assert_immutable: bool,
+    select_compare: Option<SelectCompare>,
+}
+
+impl AuditTask {
+    /// The current state of the audit.
+    pub fn state(&self) -> &AuditState {
+        &self.state
+    }
+
+    /// Whether all objects have finished being audited.
+    pub fn is_complete(&self) -> bool {
+        self.state
+            .objects
+            .iter()
+            .all(|object| object.status.is_complete())
+    }
+
+    /// Perform one step of the audit. This requests restores for up to the batch size of
+    /// pending archived objects, checks whether earlier restores have completed, and verifies
+    /// any objects that can be read. The state is saved after each object is verified. Returns
+    /// the indices of objects which completed during this step.
+    pub async fn step(&mut self) -> Result<Vec<usize>> {
+        self.request_restores().await?;
+        self.state.write(&self.state_file).await?;
+
+        let mut completed = vec![];
+        for index in 0..self.state.objects.len() {
+            let object = &self.state.objects[index];
+            let readable = match object.status {
+                AuditStatus::Pending => !object.is_archived(),
+                AuditStatus::Restoring => match self.is_restored(object).await {
+                    Ok(restored) => {
+                        if restored && let Some(restore) = &mut self.state.objects[index].restore {
+                            restore.status = RestoreStatus::Completed;
+                        }
+                        restored
+                    }
+                    Err(err) => {
+                        self.state.objects[index]
+                            .complete(AuditStatus::Failed, Some(err.to_string()));
+                        completed.push(index);
+                        continue;
+                    }
+                },
+                _ => false,
+            };
+            if !readable {
+                continue;
+            }
+
+            let object = &mut self.state.objects[index];
+            let result = match Self::verify(
+                &self.client,
+                object,
+                self.capacity,
+                self.assert_immutable,
+            )
+            .await
+            {
+                Ok(checksum_match) => {
+                    object.checksum_match = checksum_match;
+                    match &self.select_compare {
+                        Some(compare) => {
+                            Self::compare_select(&self.client, compare, &self.state.prefix, object)
+                                .await
+                        }
+                        None => Ok(None),
+                    }
+                }
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(select_match) => {
+                    object.select_match = select_match;
+                    object.complete(AuditStatus::Verified, None);
+                }
+                Err(err) => object.complete(AuditStatus::Failed, Some(err.to_string())),
+            }
+            completed.push(index);
+            self.state.write(&self.state_file).await?;
+        }
+
+        Ok(completed)
+    }
+
+    /// Request restores for pending archived objects, up to the batch size.
+    async fn request_restores(&mut self) -> Result<()> {
+        let pending = self
+            .state
+            .objects
+            .iter_mut()
+            .filter(|object| object.status == AuditStatus::Pending && object.is_archived())
+            .take(self.batch_size);
+
+        for object in pending {
+            let result = self
+                .client
+                .restore_object(|b| {
+                    b.bucket(&object.bucket).key(&object.key).restore_request(
+                        RestoreRequest::builder()
+                            .days(self.restore_days)
+                            .tier(self.restore_tier.into())
+                            .build(),
+                    )
+                })
+                .await;
+
+            let status = match result {
+                Ok(_) => RestoreStatus::Requested,
+                Err(err) => match err.as_service_error() {
+                    // The object can already be read, so it is verified like a restored object
+                    // on the next poll.
+                    Some(RestoreObjectError::ObjectAlreadyInActiveTierError(_)) => {
+                        RestoreStatus::AlreadyActive
+                    }
+                    Some(service) if service.code() == Some(RESTORE_ALREADY_IN_PROGRESS) => {
+                        RestoreStatus::AlreadyInProgress
+                    }
+                    _ => {
+                        object.complete(AuditStatus::Failed, Some(Error::from(err).to_string()));
+                        continue;
+                    }
+                },
+            };
+            object.status = AuditStatus::Restoring;
+            object.restore = Some(AuditRestore::new(
+                self.restore_tier,
+                self.restore_days,
+                status,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the restore of an object has completed.
+    async fn is_restored(&self, object: &AuditObject) -> Result<bool> {
+        // The restore status changes outside of this client, so it must not be cached.
+        self.client.invalidate(&object.bucket, &object.key);
+        let head = self
+            .client
+            .head_object(|b| b.bucket(&object.bucket).key(&object.key))
+            .await?;
+
+        Ok(match head.restore() {
+            Some(restore) => is_restore_complete(restore),
+            // Objects which no longer need restoring, e.g. because they were already in an
+            // active tier, can be read directly.
+            None => head
+                .storage_class()
+                .is_none_or(|class| !ARCHIVED_STORAGE_CLASSES.contains(&class.as_str())),
+        })
+    }
+
+    /// Verify an object against its sums file, recording the time that it was verified and the
+    /// version of the object in the sums file.
+    async fn verify(
+        client: &S3Client,
+        object: &AuditObject,
+        capacity: usize,
+        assert_immutable: bool,
+    ) -> Result<Option<ChecksumPair>> {
+        let location = object.location();
+        let mut sums = ObjectSumsBuilder::default()
+            .set_client(Some(client.clone()))
+            .build(location.to_string())
+            .await?;
+        let mut existing = sums.sums_file().await?.unwrap_or_else(SumsFile::default);
+        let version = sums.object_version().await?;
+
+        if assert_immutable {
+            existing.assert_unmodified(&location, version.as_ref())?;
+        }
+
+        let checksum_match =
+            GenerateTask::verify_existing(location, &existing, Some(client.clone()), capacity)
+                .await?;
+        if checksum_match.is_some() {
+            existing.set_last_verified_at(Some(SystemTime::now()));
+            existing.set_verified_version(version);
+            sums.write_sums_file(&existing).await?;
+        }
+
+        Ok(checksum_match)
+    }
+
+    /// Compare a CSV or JSON object with the object at the same relative key under the compare
+    /// prefix using S3 Select. Returns `None` if the object cannot be queried with S3 Select.
+    async fn compare_select(
+        client: &S3Client,
+        compare: &SelectCompare,
+        prefix: &str,
+        object: &AuditObject,
+    ) -> Result<Option<SelectSummary>> {
+        let Some(query) = SelectQuery::from_key(&object.key) else {
+            return Ok(None);
+        };
+
+        let (_, prefix) = Provider::try_from(prefix)?.into_s3()?;
+        let key = format!(
+            "{}{}",
+            compare.prefix,
+            object.key.strip_prefix(&prefix).unwrap_or(&object.key)
+        );
+        let copy = Provider::format_s3(&compare.bucket, &key);
+
+        let source = query
+            .summarize(client, &object.bucket, &object.key, &compare.columns)
+            .await?;
+        let destination = query
+            .summarize(client, &compare.bucket, &key, &compare.columns)
+            .await?;
+
+        if source.rows != destination.rows {
+            return Err(CheckError(format!(
+                "{} has {} rows but {} has {} rows",
+                object.location(),
+                source.rows,
+                copy,
+                destination.rows
+            )));
+        }
+        if source.columns_sha256 != destination.columns_sha256 {
+            return Err(CheckError(format!(
+                "the selected columns of {} do not match {}",
+                object.location(),
+                copy
+            )));
+        }
+
+        Ok(Some(source))
+    }
+}
+
+/// Whether the `x-amz-restore` header of an object shows that its restore has completed, e.g.
+/// `ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT"`.
+pub fn is_restore_complete(restore: &str) -> bool {
+    restore.contains("ongoing-request=\"false\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::file::ObjectVersion;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::get_object::GetObjectOutput;
+    use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesOutput;
+    use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+    use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+    use aws_sdk_s3::operation::restore_object::RestoreObjectOutput;
+    use aws_sdk_s3::primitives::ByteStream;
+    use aws_sdk_s3::types::{Object, ObjectStorageClass, StorageClass};
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn restore_header() {
+        assert!(is_restore_complete(
+            "ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\""
+        ));
+        assert!(!is_restore_complete("ongoing-request=\"true\""));
+    }
+
+    #[tokio::test]
+    async fn audit_restores_in_batches_and_resumes() -> Result<()> {
+        let list = mock!(Client::list_objects_v2).then_output(|| {
+            let archived = |key: &str| {
+                Object::builder()
+                    .key(key)
+                    .storage_class(ObjectStorageClass::DeepArchive)
+                    .build()
+            };
+            ListObjectsV2Output::builder()
+                .contents(archived("prefix/a"))
+                .contents(Object::builder().key("prefix/a.sums").build())
+                .contents(archived("prefix/b"))
+                .contents(Object::builder().key("prefix/b.sums").build())
+                .contents(archived("prefix/c"))
+                .is_truncated(false)
+                .build()
+        });
+        let restore = mock!(Client::restore_object)
+            .match_requests(|req| {
+                req.restore_request()
+                    .is_some_and(|request| request.tier() == Some(&Tier::Bulk))
+            })
+            .then_output(|| RestoreObjectOutput::builder().build());
+        let head = mock!(Client::head_object).then_output(|| {
+            HeadObjectOutput::builder()
+                .storage_class(StorageClass::DeepArchive)
+                .restore("ongoing-request=\"true\"")
+                .build()
+        });
+
+        let tmp = TempDir::new()?;
+        let state_file = tmp.path().join("audit.json");
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&list, &restore, &head]);
+        let client = S3Client::new(Arc::new(client), false, false);
+        let build = || {
+            AuditTaskBuilder::default()
+                .with_prefix("s3://bucket/prefix/".to_string())
+                .with_client(client.clone())
+                .with_state_file(state_file.clone())
+                .with_batch_size(1)
+                .with_restore_days(1)
+                .with_restore_tier(RestoreTier::Bulk)
+                .build()
+        };
+
+        let mut task = build().await?;
+        let statuses = |task: &AuditTask| {
+            task.state()
+                .objects()
+                .iter()
+                .map(|object| object.status())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses(&task),
+            [
+                AuditStatus::Pending,
+                AuditStatus::Pending,
+                AuditStatus::Skipped
+            ]
+        );
+
+        assert!(task.step().await?.is_empty());
+        assert_eq!(restore.num_calls(), 1);
+        assert!(!task.is_complete());
+
+        // Resuming from the state file does not list or restore the objects again.
+        let mut task = build().await?;
+        assert_eq!(list.num_calls(), 1);
+        assert_eq!(
+            statuses(&task),
+            [
+                AuditStatus::Restoring,
+                AuditStatus::Pending,
+                AuditStatus::Skipped
+            ]
+        );
+        let restored = task.state().objects()[0].restore().unwrap();
+        assert_eq!(restored.tier(), RestoreTier::Bulk);
+        assert_eq!(restored.days(), 1);
+        assert_eq!(restored.status(), RestoreStatus::Requested);
+        assert!(task.state().objects()[1].restore().is_none());
+
+        task.step().await?;
+        assert_eq!(restore.num_calls(), 2);
+        assert_eq!(
+            statuses(&task),
+            [
+                AuditStatus::Restoring,
+                AuditStatus::Restoring,
+                AuditStatus::Skipped
+            ]
+        );
+
+        let other = AuditTaskBuilder::default()
+            .with_prefix("s3://bucket/other/".to_string())
+            .with_client(client.clone())
+            .with_state_file(state_file.clone())
+            .build()
+            .await;
+        assert!(other.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn audit_skips_recently_verified() -> Result<()> {
+        let list = mock!(Client::list_objects_v2).then_output(|| {
+            ListObjectsV2Output::builder()
+                .contents(Object::builder().key("prefix/a").build())
+                .contents(Object::builder().key("prefix/a.sums").build())
+                .contents(Object::builder().key("prefix/b").build())
+                .contents(Object::builder().key("prefix/b.sums").build())
+                .is_truncated(false)
+                .build()
+        });
+        let sums = |key: &'static str, verified_at: SystemTime| {
+            mock!(Client::get_object)
+                .match_requests(move |req| req.key() == Some(key))
+                .then_output(move || {
+                    let mut sums = SumsFile::default();
+                    sums.set_last_verified_at(Some(verified_at));
+                    GetObjectOutput::builder()
+                        .body(ByteStream::from(
+                            sums.to_json_string().unwrap().into_bytes(),
+                        ))
+                        .build()
+                })
+        };
+        let recent = sums("prefix/a.sums", SystemTime::now());
+        let old = sums(
+            "prefix/b.sums",
+            SystemTime::now() - Duration::from_secs(100 * 24 * 60 * 60),
+        );
+
+        let tmp = TempDir::new()?;
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&list, &recent, &old]);
+        let task = AuditTaskBuilder::default()
+            .with_prefix("s3://bucket/prefix/".to_string())
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .with_state_file(tmp.path().join("audit.json"))
+            .with_older_than(Duration::from_secs(90 * 24 * 60 * 60))
+            .build()
+            .await?;
+
+        let objects = task.state().objects();
+        assert_eq!(objects[0].status(), AuditStatus::Skipped);
+        assert!(
+            objects[0]
+                .message()
+                .unwrap()
+                .starts_with("verified recently")
+        );
+        assert_eq!(objects[1].status(), AuditStatus::Pending);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn audit_select_compare_options() -> Result<()> {
+        let list = mock!(Client::list_objects_v2)
+            .then_output(|| ListObjectsV2Output::builder().is_truncated(false).build());
+
+        let tmp = TempDir::new()?;
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&list]);
+        let client = S3Client::new(Arc::new(client), false, false);
+        let build = |compare: &str, columns: Vec<&str>| {
+            AuditTaskBuilder::default()
+                .with_prefix("s3://bucket/prefix/".to_string())
+                .with_client(client.clone())
+                .with_state_file(tmp.path().join("audit.json"))
+                .set_select_compare(Some(compare.to_string()))
+                .with_select_columns(columns.into_iter().map(str::to_string).collect())
+                .build()
+        };
+
+        assert!(build("s3://replica/prefix/", vec!["id"]).await.is_ok());
+        assert!(build("replica/prefix/", vec![]).await.is_err());
+        assert!(build("s3://replica/prefix/", vec!["a\"b"]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn audit_assert_immutable() -> Result<()> {
+        let list = mock!(Client::list_objects_v2).then_output(|| {
+            ListObjectsV2Output::builder()
+                .contents(Object::builder().key("prefix/a").build())
+                .contents(Object::builder().key("prefix/a.sums").build())
+                .is_truncated(false)
+                .build()
+        });
+        let sums = mock!(Client::get_object)
+            .match_requests(|req| req.key() == Some("prefix/a.sums"))
+            .then_output(|| {
+                let mut sums = SumsFile::default();
+                sums.set_verified_version(Some(ObjectVersion::new(Some("old".to_string()), None)));
+                GetObjectOutput::builder()
+                    .body(ByteStream::from(
+                        sums.to_json_string().unwrap().into_bytes(),
+                    ))
+                    .build()
+            });
+        let attributes = mock!(Client::get_object_attributes)
+            .then_output(|| GetObjectAttributesOutput::builder().build());
+        let head = mock!(Client::head_object).then_output(|| {
+            HeadObjectOutput::builder()
+                .e_tag("\"d41d8cd98f00b204e9800998ecf8427e\"")
+                .content_length(0)
+                .build()
+        });
+
+        let tmp = TempDir::new()?;
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&list, &sums, &attributes, &head]
+        );
+        let mut task = AuditTaskBuilder::default()
+            .with_prefix("s3://bucket/prefix/".to_string())
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .with_state_file(tmp.path().join("audit.json"))
+            .with_assert_immutable(true)
+            .build()
+            .await?;
+
+        task.step().await?;
+        let object = &task.state().objects()[0];
+        assert_eq!(object.status(), AuditStatus::Failed);
+        assert!(
+            object
+                .message()
+                .unwrap()
+                .contains("was modified since it was last verified")
+        );
+
+        Ok(())
+    }
+}
@@ -3,19 +3,25 @@
 
 use crate::checksum::Ctx;
 use crate::checksum::file::{Checksum, SumsFile};
+use crate::error::Error::Cancelled;
 use crate::error::{ApiError, Error, Result};
 use crate::io::S3Client;
 use crate::io::sums::{ObjectSums, ObjectSumsBuilder};
-use crate::stats::{CheckComparison, ChecksumPair};
+use crate::stats::{CheckComparison, CheckConflict, ChecksumPair, ConflictKind, TagComparison};
 use crate::task::ClientInput;
 use clap::ValueEnum;
-use futures_util::future::join_all;
+use futures_util::{StreamExt, TryStreamExt, stream};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::{fmt, mem, result};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+
+/// The default number of inputs to prefetch sizes and sums for at the same time.
+pub const DEFAULT_PREFETCH_CONCURRENCY: usize = 16;
 
 /// Build a check task.
 #[derive(Debug, Default)]
@@ -24,6 +30,10 @@ pub struct CheckTaskBuilder {
     sums_files: Vec<(String, SumsFile)>,
     group_by: GroupBy,
     update: bool,
+    compare_tags: bool,
+    prefetch_concurrency: Option<usize>,
+    assert_immutable: bool,
+    cancellation_token: CancellationToken,
 }
 
 impl CheckTaskBuilder {
@@ -60,10 +70,81 @@ impl CheckTaskBuilder {
         self
     }
 
+    /// Read the tags and metadata of the inputs so that they can be compared.
+    pub fn with_compare_tags(mut self, compare_tags: bool) -> Self {
+        self.compare_tags = compare_tags;
+        self
+    }
+
+    /// Set the number of inputs to prefetch sizes, sums and tags for at the same time. Defaults
+    /// to `DEFAULT_PREFETCH_CONCURRENCY`.
+    pub fn with_prefetch_concurrency(mut self, prefetch_concurrency: usize) -> Self {
+        self.prefetch_concurrency = Some(prefetch_concurrency);
+        self
+    }
+
+    /// Fail if the `ETag` or version of an input differs from the one recorded in its sums file
+    /// when it was last verified, as the input was modified after being verified.
+    pub fn with_assert_immutable(mut self, assert_immutable: bool) -> Self {
+        self.assert_immutable = assert_immutable;
+        self
+    }
+
+    /// Set the token used to cancel the check while it is running.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Read the tags and metadata of each input, which are none for inputs that do not support
+    /// them or whose tags could not be read.
+    async fn read_tags(
+        inputs: Vec<(String, Option<S3Client>)>,
+        concurrency: usize,
+    ) -> Result<(Vec<TagComparison>, HashSet<ApiError>)> {
+        let results: Vec<_> = stream::iter(inputs)
+            .map(|(location, client)| async move {
+                let mut sums = ObjectSumsBuilder::default()
+                    .set_client(client)
+                    .build(location)
+                    .await?;
+
+                let tags = TagComparison::new(sums.location(), sums.object_tags().await?);
+                Ok::<_, Error>((tags, sums.api_errors()))
+            })
+            .buffered(concurrency)
+            .try_collect()
+            .await?;
+
+        let mut tags = Vec::with_capacity(results.len());
+        let mut errors = HashSet::new();
+        for (object_tags, api_errors) in results {
+            tags.push(object_tags);
+            errors.extend(api_errors);
+        }
+
+        Ok((tags, errors))
+    }
+
     /// Build a check task.
     pub async fn build(self) -> Result<CheckTask> {
         let group_by = self.group_by;
         let update = self.update;
+        let assert_immutable = self.assert_immutable;
+        let concurrency = self
+            .prefetch_concurrency
+            .unwrap_or(DEFAULT_PREFETCH_CONCURRENCY)
+            .max(1);
+
+        let tag_inputs = if self.compare_tags {
+            self.inputs
+                .iter()
+                .map(|input| (input.location().to_string(), input.client()))
+                .collect()
+        } else {
+            vec![]
+        };
+        let (tags, tag_errors) = Self::read_tags(tag_inputs, concurrency).await?;
 
         // Locations already provided as in-memory sums files are not re-read from their source.
         let mut sums_by_location: BTreeMap<String, SumsFile> =
@@ -82,8 +163,9 @@ impl CheckTaskBuilder {
             existing_states.push((location, sums, None));
         }
 
-        let (read_objects, errors): (Vec<_>, Vec<_>) =
-            join_all(to_read.into_iter().map(|input| async move {
+        // Prefetch the size and sums of up to `concurrency` inputs at the same time.
+        let (read_objects, errors): (Vec<_>, Vec<_>) = stream::iter(to_read)
+            .map(|input| async move {
                 let (location, client) = input.into_inner();
                 let mut sums = ObjectSumsBuilder::default()
                     .set_client(client)
@@ -95,36 +177,41 @@ impl CheckTaskBuilder {
                     .sums_file()
                     .await?
                     .unwrap_or_else(|| SumsFile::new(file_size, Default::default()));
+                if assert_immutable {
+                    let version = sums.object_version().await?;
+                    existing.assert_unmodified(&sums.location(), version.as_ref())?;
+                }
 
                 let errors = sums.api_errors();
-                Ok((
+                Ok::<_, Error>((
                     (
                         SumsKey((existing, sums.location())),
                         BTreeSet::from_iter(vec![State::ObjectSums(sums)]),
                     ),
                     errors,
                 ))
-            }))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>>>()?
+            })
+            .buffered(concurrency)
+            .try_collect::<Vec<_>>()
+            .await?
             .into_iter()
             .unzip();
 
         let mut objects = BTreeMap::from_iter(read_objects);
-        let errors = HashSet::from_iter(
+        let mut errors = HashSet::from_iter(
             errors
                 .into_iter()
                 .flat_map(|err| err.into_iter().collect::<Vec<_>>()),
         );
+        errors.extend(tag_errors);
 
         for (location, sums, client) in existing_states {
             objects.insert(
                 SumsKey((sums.clone(), location.to_string())),
                 BTreeSet::from_iter(vec![State::ExistingSums {
                     location,
-                    sums,
-                    client,
+                    sums: Box::new(sums),
+                    client: client.map(Box::new),
                 }]),
             );
         }
@@ -134,6 +221,8 @@ impl CheckTaskBuilder {
             group_by,
             update,
             recoverable_errors: errors,
+            tags,
+            cancellation_token: self.cancellation_token,
             ..Default::default()
         })
     }
@@ -156,8 +245,8 @@ pub enum State {
     ObjectSums(Box<dyn ObjectSums + Send>),
     ExistingSums {
         location: String,
-        sums: SumsFile,
-        client: Option<S3Client>,
+        sums: Box<SumsFile>,
+        client: Option<Box<S3Client>>,
     },
 }
 
@@ -174,7 +263,7 @@ impl State {
     pub async fn sums_file(&mut self) -> Result<Option<SumsFile>> {
         match self {
             State::ObjectSums(object) => object.sums_file().await,
-            State::ExistingSums { sums, .. } => Ok(Some(sums.clone())),
+            State::ExistingSums { sums, .. } => Ok(Some(*sums.clone())),
         }
     }
 
@@ -195,7 +284,7 @@ impl State {
                 location, client, ..
             } => {
                 ObjectSumsBuilder::default()
-                    .set_client(client.clone())
+                    .set_client(client.as_deref().cloned())
                     .build(location.to_string())
                     .await?
                     .write_sums_file(sums)
@@ -283,6 +372,9 @@ impl CheckObjects {
         for state in self.0.values() {
             groups.push(state.iter().map(|state| state.location()).collect());
         }
+        // Locations are already sorted within each group, so sorting the groups gives a stable
+        // order that does not depend on the checksum values.
+        groups.sort();
         groups
     }
 }
@@ -293,6 +385,106 @@ impl Hash for CheckObjects {
     }
 }
 
+/// A checksum with a different value in two groups of sums files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumDiff {
+    ctx: Ctx,
+    values: [Checksum; 2],
+    parts: Vec<usize>,
+}
+
+/// The differences between two groups of sums files that were not found to be equal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckDiff {
+    locations: [Vec<String>; 2],
+    sizes: [Option<u64>; 2],
+    matched: Vec<Ctx>,
+    differed: Vec<ChecksumDiff>,
+}
+
+impl CheckDiff {
+    /// Compare two groups of sums files. Part numbers are only reported if the part checksums
+    /// of both sides are known, such as when they were generated in the same run.
+    pub fn new(locations: [Vec<String>; 2], a: &SumsFile, b: &SumsFile) -> Self {
+        let mut matched = vec![];
+        let mut differed = vec![];
+        for (a_ctx, a_checksum) in &a.checksums {
+            let Some((b_ctx, b_checksum)) = b.checksums.get_key_value(a_ctx) else {
+                continue;
+            };
+            if a_checksum == b_checksum {
+                matched.push(a_ctx.clone());
+                continue;
+            }
+
+            let parts = match (a_ctx.part_checksums(), b_ctx.part_checksums()) {
+                (Some(a_parts), Some(b_parts)) if !a_parts.is_empty() && !b_parts.is_empty() => (0
+                    ..a_parts.len().max(b_parts.len()))
+                    .filter(|part| a_parts.get(*part) != b_parts.get(*part))
+                    .map(|part| part + 1)
+                    .collect(),
+                _ => vec![],
+            };
+            differed.push(ChecksumDiff {
+                ctx: a_ctx.clone(),
+                values: [a_checksum.clone(), b_checksum.clone()],
+                parts,
+            });
+        }
+
+        Self {
+            locations,
+            sizes: [a.size, b.size],
+            matched,
+            differed,
+        }
+    }
+}
+
+impl Display for CheckDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let [a, b] = &self.locations;
+        writeln!(f, "{} differs from {}", a.join(", "), b.join(", "))?;
+
+        let size =
+            |size: Option<u64>| size.map_or_else(|| "unknown".to_string(), |s| s.to_string());
+        if self.sizes[0] != self.sizes[1] {
+            writeln!(
+                f,
+                "  size: {} != {}",
+                size(self.sizes[0]),
+                size(self.sizes[1])
+            )?;
+        }
+
+        if self.matched.is_empty() && self.differed.is_empty() {
+            writeln!(f, "  no checksums in common")?;
+        }
+        for ctx in &self.matched {
+            writeln!(f, "  {}: matched", ctx)?;
+        }
+        for diff in &self.differed {
+            writeln!(
+                f,
+                "  {}: {} != {}",
+                diff.ctx,
+                diff.values[0].as_str(),
+                diff.values[1].as_str()
+            )?;
+            if !diff.parts.is_empty() {
+                let parts = diff
+                    .parts
+                    .iter()
+                    .map(|part| part.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(f, "    differing parts: {}", parts.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// The check type error with the task information when the error occurred.
 pub struct CheckTaskError {
     pub task: CheckTask,
@@ -329,6 +521,9 @@ pub struct CheckTask {
     compared_directly: Vec<CheckComparison>,
     updated: Vec<String>,
     recoverable_errors: HashSet<ApiError>,
+    tags: Vec<TagComparison>,
+    conflicts: Vec<CheckConflict>,
+    cancellation_token: CancellationToken,
 }
 
 impl CheckTask {
@@ -338,6 +533,55 @@ impl CheckTask {
         hasher.finish()
     }
 
+    /// Find the conflict between two sums files, if any. Sums files conflict if they have a
+    /// checksum with the same value but different sizes, or if they have the same size and a
+    /// checksum with the same value but another checksum of the same kind differs.
+    fn find_conflict(
+        (a, a_location): &(SumsFile, String),
+        (b, b_location): &(SumsFile, String),
+    ) -> Option<CheckConflict> {
+        let mut matching = None;
+        let mut mismatched = None;
+        for (ctx, checksum) in &a.checksums {
+            match b.checksums.get(ctx) {
+                Some(other) if other == checksum => {
+                    matching.get_or_insert(ctx);
+                }
+                Some(_) => {
+                    mismatched.get_or_insert(ctx);
+                }
+                None => {}
+            }
+        }
+
+        let matching = matching?.clone();
+        let (kind, mismatched) = match (a.size == b.size, mismatched) {
+            (false, _) => (ConflictKind::SizeMismatch, None),
+            (true, Some(mismatched)) => (ConflictKind::ChecksumMismatch, Some(mismatched.clone())),
+            (true, None) => return None,
+        };
+
+        Some(CheckConflict::new(
+            vec![a_location.to_string(), b_location.to_string()],
+            kind,
+            vec![a.size, b.size],
+            matching,
+            mismatched,
+        ))
+    }
+
+    /// Record the conflicts between each pair of input sums files before they are merged.
+    fn find_conflicts(&mut self) {
+        let objects = self.objects.0.keys().collect::<Vec<_>>();
+        for (i, a) in objects.iter().enumerate() {
+            self.conflicts.extend(
+                objects[i + 1..]
+                    .iter()
+                    .filter_map(|b| Self::find_conflict(&a.0, &b.0)),
+            );
+        }
+    }
+
     /// Groups sums files based on a comparison function.
     async fn merge_fn<F>(&mut self, compare: F)
     where
@@ -415,6 +659,7 @@ impl CheckTask {
 
     async fn do_check(&mut self) -> Result<()> {
         let update = self.update && matches!(self.group_by, GroupBy::Equality);
+        self.find_conflicts();
         match self.group_by {
             GroupBy::Equality => self.merge_same().await,
             GroupBy::Comparability => self.merge_comparable().await,
@@ -427,9 +672,23 @@ impl CheckTask {
                     let mut location = location.clone();
                     let current = location.sums_file().await?;
 
+                    // When an object was verified, and its version at the time, belong to
+                    // that object rather than the other objects it is the same as.
+                    let mut file = file.clone();
+                    file.set_last_verified_at(current.as_ref().and_then(|c| c.last_verified_at()));
+                    file.set_verified_version(
+                        current.as_ref().and_then(|c| c.verified_version()).cloned(),
+                    );
+                    file.set_rejected_checksums(
+                        current
+                            .as_ref()
+                            .map(|c| c.rejected_checksums().clone())
+                            .unwrap_or_default(),
+                    );
+
                     self.recoverable_errors.extend(location.api_errors());
-                    if current.as_ref() != Some(file) {
-                        location.write_sums_file(file).await?;
+                    if current.as_ref() != Some(&file) {
+                        location.write_sums_file(&file).await?;
                         updated_sums.push(location.location());
                     }
                 }
@@ -441,9 +700,17 @@ impl CheckTask {
         Ok(())
     }
 
-    /// Runs the check task, returning the list of matching files.
+    /// Runs the check task, returning the list of matching files. If the task is cancelled, the
+    /// error contains the comparisons and updates made before cancellation.
     pub async fn run(mut self) -> CheckTaskResult {
-        match self.do_check().await {
+        let cancellation_token = self.cancellation_token.clone();
+        let result = select! {
+            biased;
+            _ = cancellation_token.cancelled() => Err(Cancelled("check".to_string())),
+            result = self.do_check() => result,
+        };
+
+        match result {
             Ok(_) => Ok(self),
             Err(err) => Err((self, err).into()),
         }
@@ -476,6 +743,45 @@ impl CheckTask {
         self.compared_directly.as_slice()
     }
 
+    /// Get the tags and metadata read from the inputs.
+    pub fn tags(&self) -> &[TagComparison] {
+        self.tags.as_slice()
+    }
+
+    /// Explain the differences between each pair of groups that were not found to be equal. This
+    /// is empty when comparing for comparability, because the checksum values are cleared.
+    pub fn explain(&self) -> Vec<CheckDiff> {
+        if !matches!(self.group_by, GroupBy::Equality) {
+            return vec![];
+        }
+
+        let groups = self
+            .objects
+            .0
+            .iter()
+            .map(|(SumsKey((sums, _)), states)| {
+                (states.iter().map(State::location).collect::<Vec<_>>(), sums)
+            })
+            .collect::<Vec<_>>();
+
+        let mut diffs = vec![];
+        for (i, (a_locations, a)) in groups.iter().enumerate() {
+            for (b_locations, b) in &groups[i + 1..] {
+                diffs.push(CheckDiff::new(
+                    [a_locations.clone(), b_locations.clone()],
+                    a,
+                    b,
+                ));
+            }
+        }
+        diffs
+    }
+
+    /// Get the conflicts found between the input sums files.
+    pub fn conflicts(&self) -> &[CheckConflict] {
+        self.conflicts.as_slice()
+    }
+
     /// Get the api errors.
     pub fn api_errors(self) -> HashSet<ApiError> {
         self.recoverable_errors.clone()
@@ -498,15 +804,16 @@ impl CheckTask {
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
-    use crate::checksum::file::Checksum;
-    use crate::checksum::standard::test::EXPECTED_MD5_SUM;
+    use crate::checksum::file::{Checksum, ObjectVersion};
+    use crate::checksum::test::EXPECTED_MD5_SUM;
     use crate::error::Error;
     use crate::io::sums::file::FileBuilder;
     use crate::test::TEST_FILE_SIZE;
     use anyhow::Result;
     use aws_sdk_s3::Client;
-    use aws_sdk_s3::operation::get_object::GetObjectError;
+    use aws_sdk_s3::operation::get_object::{GetObjectError, GetObjectOutput};
     use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
+    use aws_sdk_s3::primitives::ByteStream;
     use aws_sdk_s3::types::error::{NoSuchKey, NotFound};
     use aws_smithy_mocks::{RuleMode, mock, mock_client};
     use std::collections::BTreeMap;
@@ -624,6 +931,165 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_check_conflicts() -> Result<()> {
+        let sums = |size, md5: &str, sha1: &str| -> Result<SumsFile> {
+            Ok(SumsFile::new(
+                Some(size),
+                BTreeMap::from_iter(vec![
+                    ("md5".parse()?, Checksum::new(md5.to_string())),
+                    ("sha1".parse()?, Checksum::new(sha1.to_string())),
+                ]),
+            ))
+        };
+
+        let check = CheckTaskBuilder::default()
+            .with_sums_files(vec![
+                ("a".to_string(), sums(1, "123", "456")?),
+                ("b".to_string(), sums(2, "123", "456")?),
+                ("c".to_string(), sums(1, "123", "789")?),
+                ("d".to_string(), sums(1, "012", "345")?),
+            ])
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap();
+
+        let conflict = |locations: [&str; 2], kind, sizes: [u64; 2], mismatched| {
+            CheckConflict::new(
+                locations.map(str::to_string).to_vec(),
+                kind,
+                sizes.map(Some).to_vec(),
+                "md5".parse().unwrap(),
+                mismatched,
+            )
+        };
+        assert_eq!(
+            check.conflicts(),
+            &[
+                conflict(
+                    ["a", "c"],
+                    ConflictKind::ChecksumMismatch,
+                    [1, 1],
+                    Some("sha1".parse()?)
+                ),
+                conflict(["a", "b"], ConflictKind::SizeMismatch, [1, 2], None),
+                conflict(["c", "b"], ConflictKind::SizeMismatch, [1, 2], None),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_explain() -> Result<()> {
+        let sums = |size, md5: &str, sha1: &str| -> Result<SumsFile> {
+            Ok(SumsFile::new(
+                Some(size),
+                BTreeMap::from_iter(vec![
+                    ("md5".parse()?, Checksum::new(md5.to_string())),
+                    ("sha1".parse()?, Checksum::new(sha1.to_string())),
+                ]),
+            ))
+        };
+
+        let check = CheckTaskBuilder::default()
+            .with_sums_files(vec![
+                ("a".to_string(), sums(1, "123", "456")?),
+                ("b".to_string(), sums(1, "123", "456")?),
+                ("c".to_string(), sums(2, "123", "789")?),
+            ])
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap();
+
+        let diffs = check.explain();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            diffs[0].to_string(),
+            "a, b differs from c\n  size: 1 != 2\n  md5: matched\n  sha1: 456 != 789\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_assert_immutable() -> Result<()> {
+        let head = mock!(Client::head_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key"))
+            .then_output(|| {
+                HeadObjectOutput::builder()
+                    .e_tag(EXPECTED_MD5_SUM)
+                    .content_length(100)
+                    .build()
+            });
+        let get_sums = mock!(Client::get_object)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.key() == Some("key.sums"))
+            .then_output(|| {
+                let mut sums = SumsFile::default();
+                sums.set_verified_version(Some(ObjectVersion::new(
+                    Some("modified".to_string()),
+                    None,
+                )));
+                GetObjectOutput::builder()
+                    .body(ByteStream::from(
+                        sums.to_json_string().unwrap().into_bytes(),
+                    ))
+                    .build()
+            });
+        let client = S3Client::new(
+            Arc::new(mock_client!(
+                aws_sdk_s3,
+                RuleMode::MatchAny,
+                &[&head, &get_sums]
+            )),
+            true,
+            true,
+        );
+        let build = |assert_immutable| {
+            CheckTaskBuilder::default()
+                .with_inputs(vec![ClientInput::new(
+                    "s3://bucket/key".to_string(),
+                    Some(client.clone()),
+                )])
+                .with_assert_immutable(assert_immutable)
+                .build()
+        };
+
+        assert!(build(false).await.is_ok());
+        let err = build(true).await.unwrap_err();
+        assert!(matches!(err, Error::CheckError(_)));
+        assert!(err.to_string().contains("s3://bucket/key was modified"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_prefetch_concurrency() -> Result<()> {
+        let tmp = tempdir()?;
+        let files = write_test_files_one_group(tmp).await?;
+
+        for concurrency in [1, 2, DEFAULT_PREFETCH_CONCURRENCY] {
+            let check = CheckTaskBuilder::default()
+                .with_input_files(files.iter().map(|name| name.to_string()).collect())
+                .with_prefetch_concurrency(concurrency)
+                .build()
+                .await?
+                .run()
+                .await
+                .unwrap();
+
+            let groups = check.objects.to_groups();
+            assert_eq!(groups.len(), 1);
+            assert_eq!(groups[0].len(), files.len());
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_check_comparable() -> Result<()> {
         let tmp = tempdir()?;
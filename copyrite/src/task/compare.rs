@@ -0,0 +1,246 @@
+//! Compare two objects byte by byte, for when their checksums are missing or not trusted, and
+//! locate where objects that are not equal diverge.
+//!
+
+use crate::checksum::file::SumsFile;
+use crate::error::Result;
+use crate::io::sums::ObjectSumsBuilder;
+use crate::stats::{ByteComparison, DifferenceRange};
+use crate::task::ClientInput;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The number of bytes read from each object before comparing them.
+pub const BYTE_COMPARE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Compare the bytes of two objects by reading them at the same time.
+pub async fn compare_bytes(a: ClientInput, b: ClientInput) -> Result<ByteComparison> {
+    let locations = vec![a.location().to_string(), b.location().to_string()];
+    let reader = |input: ClientInput| async move {
+        let (location, client) = input.into_inner();
+        ObjectSumsBuilder::default()
+            .set_client(client)
+            .build(location)
+            .await?
+            .reader()
+            .await
+    };
+    let (mut a, mut b) = tokio::try_join!(reader(a), reader(b))?;
+
+    let mut a_buf = vec![0; BYTE_COMPARE_CHUNK_SIZE];
+    let mut b_buf = vec![0; BYTE_COMPARE_CHUNK_SIZE];
+    let mut offset = 0;
+    loop {
+        let (a_n, b_n) = tokio::try_join!(fill(&mut a, &mut a_buf), fill(&mut b, &mut b_buf))?;
+
+        // Objects of different sizes first differ at the end of the shorter one.
+        let n = a_n.min(b_n);
+        let difference = a_buf[..n]
+            .iter()
+            .zip(&b_buf[..n])
+            .position(|(a, b)| a != b)
+            .or((a_n != b_n).then_some(n));
+        if let Some(position) = difference {
+            return Ok(ByteComparison::new(
+                locations,
+                offset + u64::try_from(position)?,
+                Some(offset + u64::try_from(position)?),
+            ));
+        }
+
+        offset += u64::try_from(n)?;
+        if n == 0 {
+            return Ok(ByteComparison::new(locations, offset, None));
+        }
+    }
+}
+
+/// Find the range of bytes where two objects first diverge. If the sums files of both objects
+/// have the part checksums of a checksum with the same part sizes, the first part that differs
+/// is the range, and no data is read. Otherwise, the bytes are compared with `compare_bytes` to
+/// find the first byte that differs. Returns `None` if the objects are equal.
+pub async fn locate_difference(a: ClientInput, b: ClientInput) -> Result<Option<DifferenceRange>> {
+    let sums = |input: ClientInput| async move {
+        let (location, client) = input.into_inner();
+        let mut sums = ObjectSumsBuilder::default()
+            .set_client(client)
+            .build(location)
+            .await?;
+        Result::Ok((sums.sums_file().await?, sums.file_size().await?))
+    };
+    let ((a_sums, a_size), (b_sums, b_size)) = tokio::try_join!(sums(a.clone()), sums(b.clone()))?;
+
+    if let (Some(a_sums), Some(b_sums)) = (&a_sums, &b_sums)
+        && let Some(range) = locate_part_difference(a_sums, b_sums)
+    {
+        return Ok(Some(range));
+    }
+
+    let comparison = compare_bytes(a, b).await?;
+    let ranges_compared = comparison
+        .bytes_compared
+        .div_ceil(u64::try_from(BYTE_COMPARE_CHUNK_SIZE)?)
+        .max(1);
+    Ok(comparison.first_difference.map(|start| {
+        // Objects of different sizes diverge from the end of the shorter one.
+        let end = match (a_size, b_size) {
+            (Some(a_size), Some(b_size)) if start >= a_size.min(b_size) => a_size.max(b_size),
+            _ => start + 1,
+        };
+        DifferenceRange::new(start, end, ranges_compared)
+    }))
+}
+
+/// Find the first part that differs using the part checksums of a checksum that both sums files
+/// have with the same part sizes. Returns `None` if there are no such part checksums, or if they
+/// are all equal.
+fn locate_part_difference(a: &SumsFile, b: &SumsFile) -> Option<DifferenceRange> {
+    a.checksums.keys().find_map(|a_ctx| {
+        let (b_ctx, _) = b.checksums.get_key_value(a_ctx)?;
+        let (a_parts, b_parts) = (a_ctx.part_checksums()?, b_ctx.part_checksums()?);
+        if a_parts.is_empty()
+            || a_parts.len() != b_parts.len()
+            || a_parts
+                .iter()
+                .zip(&b_parts)
+                .any(|((a_size, _), (b_size, _))| a_size != b_size)
+        {
+            return None;
+        }
+
+        let mut start = 0;
+        for (ranges_compared, ((size, a_part), (_, b_part))) in
+            (1..).zip(a_parts.iter().zip(&b_parts))
+        {
+            if a_part != b_part {
+                return Some(DifferenceRange::new(start, start + size, ranges_compared));
+            }
+            start += size;
+        }
+
+        None
+    })
+}
+
+/// Read from the reader until the buffer is full or the end is reached, returning the number
+/// of bytes read.
+async fn fill(reader: &mut (impl AsyncRead + Unpin), buf: &mut [u8]) -> Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        let read = reader.read(&mut buf[n..]).await?;
+        if read == 0 {
+            break;
+        }
+        n += read;
+    }
+
+    Ok(n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::aws_etag::AWSETagCtx;
+    use crate::checksum::file::Checksum;
+    use crate::checksum::standard::StandardCtx;
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn compare_bytes_offsets() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = (0..3 * BYTE_COMPARE_CHUNK_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let write = |name: &str, data: Vec<u8>| {
+            let path = tmp.path().join(name);
+            async move {
+                fs::write(&path, data).await?;
+                anyhow::Ok(ClientInput::new(path.to_string_lossy().to_string(), None))
+            }
+        };
+
+        let mut changed = data.clone();
+        changed[BYTE_COMPARE_CHUNK_SIZE + 5] ^= 1;
+        let a = write("a", data.clone()).await?;
+        let b = write("b", data.clone()).await?;
+        let c = write("c", changed).await?;
+        let d = write("d", data[..100].to_vec()).await?;
+
+        let equal = compare_bytes(a.clone(), b).await?;
+        assert!(equal.equal);
+        assert_eq!(equal.bytes_compared, data.len() as u64);
+        assert_eq!(equal.first_difference, None);
+
+        let changed = compare_bytes(a.clone(), c).await?;
+        assert!(!changed.equal);
+        assert_eq!(
+            changed.first_difference,
+            Some(BYTE_COMPARE_CHUNK_SIZE as u64 + 5)
+        );
+
+        let shorter = compare_bytes(a, d).await?;
+        assert_eq!(shorter.first_difference, Some(100));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn locate_difference_range() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = (0..100000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let write = |name: &str, data: Vec<u8>| {
+            let path = tmp.path().join(name);
+            async move {
+                fs::write(&path, data).await?;
+                anyhow::Ok(ClientInput::new(path.to_string_lossy().to_string(), None))
+            }
+        };
+
+        let mut changed = data.clone();
+        changed[70000] ^= 1;
+        let a = write("a", data.clone()).await?;
+        let b = write("b", data.clone()).await?;
+        let c = write("c", changed).await?;
+        let d = write("d", data[..100].to_vec()).await?;
+
+        assert_eq!(locate_difference(a.clone(), b).await?, None);
+
+        let range = locate_difference(a.clone(), c)
+            .await?
+            .expect("a difference");
+        assert_eq!((range.start, range.end), (70000, 70001));
+
+        let range = locate_difference(a, d).await?.expect("a difference");
+        assert_eq!((range.start, range.end), (100, 100000));
+
+        Ok(())
+    }
+
+    #[test]
+    fn locate_part_difference_range() -> Result<()> {
+        let sums = |parts: Vec<(u64, Vec<u8>)>| -> Result<SumsFile> {
+            let (ctx, digest) = AWSETagCtx::from_part_checksums(StandardCtx::md5(), parts)?;
+            let ctx = Ctx::AWSEtag(ctx);
+            let checksum = Checksum::new(ctx.digest_to_string(&digest));
+            let mut sums_file = SumsFile::default();
+            sums_file.add_checksum(ctx, checksum);
+            Ok(sums_file)
+        };
+
+        let a = sums(vec![(10, vec![1; 16]), (10, vec![2; 16]), (5, vec![3; 16])])?;
+        let b = sums(vec![(10, vec![1; 16]), (10, vec![4; 16]), (5, vec![3; 16])])?;
+        let c = sums(vec![(20, vec![1; 16]), (5, vec![3; 16])])?;
+
+        assert_eq!(locate_part_difference(&a, &a), None);
+        assert_eq!(
+            locate_part_difference(&a, &b),
+            Some(DifferenceRange::new(10, 20, 2))
+        );
+        assert_eq!(locate_part_difference(&a, &c), None);
+        assert_eq!(locate_part_difference(&a, &SumsFile::default()), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Determine which checksums of a sums file a target provider can reproduce natively, which
+//! is useful for choosing the checksum to trust after migrating objects.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::file::SumsFile;
+use crate::checksum::standard::StandardCtx;
+use crate::io::copy::limits::ProviderLimits;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// The provider that objects are migrated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// Objects in S3.
+    S3,
+    /// Local files.
+    File,
+}
+
+/// How a target can reproduce a checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reproduction {
+    /// The target stores the checksum as metadata when the object is copied with the same part
+    /// layout, so it can be compared without reading the object.
+    Native,
+    /// The target does not store the checksum, so the object must be read to compute it.
+    Computed,
+}
+
+/// The compatibility of a single checksum with a target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumCompatibility {
+    /// The checksum.
+    pub(crate) checksum: String,
+    /// How the target can reproduce the checksum.
+    pub(crate) reproduction: Reproduction,
+    /// The reason for the reproduction.
+    pub(crate) reason: String,
+}
+
+impl ChecksumCompatibility {
+    fn new(ctx: &Ctx, reproduction: Reproduction, reason: String) -> Self {
+        Self {
+            checksum: ctx.to_string(),
+            reproduction,
+            reason,
+        }
+    }
+
+    /// Whether the checksum is reproduced natively.
+    pub fn is_native(&self) -> bool {
+        self.reproduction == Reproduction::Native
+    }
+}
+
+impl Target {
+    /// Determine how the target reproduces each checksum of a sums file. The checksums are
+    /// returned in order of preference.
+    pub fn compatibility(&self, sums: &SumsFile) -> Vec<ChecksumCompatibility> {
+        sums.checksums
+            .keys()
+            .map(|ctx| self.reproduce(ctx, sums.size))
+            .collect()
+    }
+
+    /// Determine how the target reproduces a checksum of an object that is `size` bytes.
+    pub fn reproduce(&self, ctx: &Ctx, size: Option<u64>) -> ChecksumCompatibility {
+        // Part number checksums need the size to determine their part size.
+        let mut ctx = ctx.clone();
+        ctx.set_file_size(size);
+
+        match self {
+            Target::S3 => Self::reproduce_s3(&ctx, size),
+            Target::File => ChecksumCompatibility::new(
+                &ctx,
+                Reproduction::Computed,
+                "local files do not store checksums".to_string(),
+            ),
+        }
+    }
+
+    fn reproduce_s3(ctx: &Ctx, size: Option<u64>) -> ChecksumCompatibility {
+        let computed =
+            |reason: String| ChecksumCompatibility::new(ctx, Reproduction::Computed, reason);
+        let native = |reason: String| ChecksumCompatibility::new(ctx, Reproduction::Native, reason);
+        let limits = ProviderLimits::S3;
+        let single_part = size.is_none_or(|size| size <= limits.max_part_size());
+
+        match ctx {
+            Ctx::Regular(StandardCtx::MD5(_)) if single_part => {
+                native("the ETag of a single part upload".to_string())
+            }
+            Ctx::Regular(StandardCtx::MD5(_)) => computed(format!(
+                "objects larger than {} bytes require a multipart upload, which has a multipart ETag",
+                limits.max_part_size()
+            )),
+            Ctx::Regular(
+                StandardCtx::CRC32(..) | StandardCtx::CRC32C(..) | StandardCtx::CRC64NVME(..),
+            ) => native("a full object additional checksum".to_string()),
+            Ctx::Regular(regular) if regular.is_aws_additional_ctx() && single_part => {
+                native("an additional checksum of a single part upload".to_string())
+            }
+            Ctx::Regular(regular) if regular.is_aws_additional_ctx() => computed(format!(
+                "objects larger than {} bytes require a multipart upload, which only stores a composite {} checksum",
+                limits.max_part_size(),
+                regular
+            )),
+            Ctx::Regular(_) => computed("not supported as an S3 checksum".to_string()),
+            Ctx::AWSEtag(etag) => {
+                if etag.is_part_number() && size.is_none() {
+                    return computed("the part size is not known without the size".to_string());
+                }
+                let part_sizes = etag.get_part_sizes();
+
+                let regular = etag.clone().ctx();
+                if matches!(regular, StandardCtx::CRC64NVME(..)) {
+                    return computed(
+                        "CRC64NVME is only stored as a full object checksum".to_string(),
+                    );
+                }
+                if !regular.is_aws_ctx() {
+                    return computed("not supported as an S3 checksum".to_string());
+                }
+
+                let [part_size] = part_sizes.as_slice() else {
+                    return computed("copies use the same size for all parts".to_string());
+                };
+                let n_parts = size.map(|size| size.div_ceil(*part_size));
+                if *part_size > limits.max_part_size() {
+                    return computed(format!(
+                        "the part size is larger than the maximum of {} bytes",
+                        limits.max_part_size()
+                    ));
+                }
+                if *part_size < limits.min_part_size() && n_parts.is_none_or(|n_parts| n_parts > 1)
+                {
+                    return computed(format!(
+                        "the part size is smaller than the minimum of {} bytes",
+                        limits.min_part_size()
+                    ));
+                }
+                if n_parts.is_some_and(|n_parts| n_parts > limits.max_parts()) {
+                    return computed(format!(
+                        "the object has more than the maximum of {} parts",
+                        limits.max_parts()
+                    ));
+                }
+
+                if matches!(regular, StandardCtx::MD5(_)) {
+                    native(format!(
+                        "the ETag of a multipart upload with {} byte parts",
+                        part_size
+                    ))
+                } else {
+                    native(format!(
+                        "a composite additional checksum of a multipart upload with {} byte parts",
+                        part_size
+                    ))
+                }
+            }
+            Ctx::Uncompressed(_) => {
+                computed("checksums of uncompressed contents are not stored".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::file::Checksum;
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn reproduction(target: Target, size: u64, ctx: &str) -> Result<Reproduction> {
+        Ok(target
+            .reproduce(&Ctx::from_str(ctx)?, Some(size))
+            .reproduction)
+    }
+
+    #[test]
+    fn s3_compatibility() -> Result<()> {
+        let small = |ctx| reproduction(Target::S3, 100 * MIB, ctx);
+        assert_eq!(small("md5")?, Reproduction::Native);
+        assert_eq!(small("sha256")?, Reproduction::Native);
+        assert_eq!(small("crc64nvme")?, Reproduction::Native);
+        assert_eq!(small("md5-aws-8mib")?, Reproduction::Native);
+        assert_eq!(small("sha256-aws-10")?, Reproduction::Native);
+        assert_eq!(small("md5-aws-1")?, Reproduction::Native);
+
+        assert_eq!(small("md5-aws-1mib")?, Reproduction::Computed);
+        assert_eq!(small("md5-aws-8mib-16mib")?, Reproduction::Computed);
+        assert_eq!(small("crc64nvme-aws-8mib")?, Reproduction::Computed);
+        assert_eq!(small("md5-uncompressed")?, Reproduction::Computed);
+        assert_eq!(small("xxhash3-aws-8mib")?, Reproduction::Native);
+
+        // Objects larger than a single part only have multipart ETags and composite checksums.
+        let large = |ctx| reproduction(Target::S3, 6 * 1024 * MIB, ctx);
+        assert_eq!(large("md5")?, Reproduction::Computed);
+        assert_eq!(large("sha256")?, Reproduction::Computed);
+        assert_eq!(large("crc32")?, Reproduction::Native);
+        assert_eq!(large("md5-aws-8mib")?, Reproduction::Native);
+        assert_eq!(large("md5-aws-6gib")?, Reproduction::Computed);
+        assert_eq!(large("md5-aws-20000")?, Reproduction::Computed);
+
+        // Small objects can be uploaded as a single part of any size.
+        assert_eq!(
+            reproduction(Target::S3, MIB, "md5-aws-1mib")?,
+            Reproduction::Native
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_compatibility() -> Result<()> {
+        let sums = SumsFile::new(
+            Some(MIB),
+            BTreeMap::from_iter([
+                (Ctx::from_str("md5")?, Checksum::new("".to_string())),
+                (
+                    Ctx::from_str("md5-aws-8mib")?,
+                    Checksum::new("".to_string()),
+                ),
+            ]),
+        );
+
+        assert!(
+            Target::File
+                .compatibility(&sums)
+                .iter()
+                .all(|compatibility| !compatibility.is_native())
+        );
+        assert!(
+            Target::S3
+                .compatibility(&sums)
+                .iter()
+                .all(ChecksumCompatibility::is_native)
+        );
+
+        Ok(())
+    }
+}
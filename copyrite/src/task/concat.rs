@@ -0,0 +1,524 @@
+//! Concatenate S3 objects into a single destination object using server-side part copies.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::combine::crc64nvme_combine;
+use crate::checksum::file::Checksum;
+use crate::error::Error::{CopyError, ParseError};
+use crate::error::{Error, Result};
+use crate::io::copy::aws::S3;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::{Provider, S3Client};
+use crate::stats::ChecksumPair;
+use crate::task::generate::GenerateTaskBuilder;
+use aws_sdk_s3::types::{
+    ChecksumAlgorithm, ChecksumMode, ChecksumType, CompletedMultipartUpload, CompletedPart,
+};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use std::str::FromStr;
+
+/// Build a concat task.
+#[derive(Debug, Default)]
+pub struct ConcatTaskBuilder {
+    sources: Vec<String>,
+    destination: Option<String>,
+    client: Option<S3Client>,
+    concurrency: usize,
+    capacity: usize,
+}
+
+impl ConcatTaskBuilder {
+    /// Set the sources to concatenate, in order.
+    pub fn with_sources(mut self, sources: Vec<String>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Set the destination.
+    pub fn with_destination(mut self, destination: String) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Set the client.
+    pub fn with_client(mut self, client: S3Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the number of parts to copy at the same time.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the reader capacity used if source checksums must be computed.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Build the concat task.
+    pub fn build(self) -> Result<ConcatTask> {
+        if self.sources.is_empty() {
+            return Err(ParseError(
+                "at least one source is required to concatenate".to_string(),
+            ));
+        }
+        let destination = self
+            .destination
+            .ok_or_else(|| ParseError("a destination is required to concatenate".to_string()))?;
+        let client = self
+            .client
+            .ok_or_else(|| ParseError("an S3 client is required to concatenate".to_string()))?;
+
+        let into_s3 = |location: &str| {
+            Provider::try_from(location)?.into_s3().map_err(|_| {
+                ParseError(format!("only S3 objects can be concatenated: {}", location))
+            })
+        };
+
+        Ok(ConcatTask {
+            sources: self
+                .sources
+                .iter()
+                .map(|source| into_s3(source))
+                .collect::<Result<_>>()?,
+            destination: into_s3(&destination)?,
+            client,
+            concurrency: self.concurrency.max(1),
+            capacity: self.capacity,
+        })
+    }
+}
+
+/// A range of a source object which is copied as one part of the destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcatPart {
+    source: usize,
+    start: u64,
+    end: u64,
+}
+
+impl ConcatPart {
+    /// The size of the part.
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Format the range of the part as a `CopySourceRange`.
+    fn format_range(&self) -> String {
+        format!("bytes={}-{}", self.start, self.end - 1)
+    }
+}
+
+/// The result of concatenating objects.
+#[derive(Debug, Clone)]
+pub struct ConcatOutput {
+    /// The total size of the destination.
+    pub size: u64,
+    /// The number of parts in the destination.
+    pub n_parts: usize,
+    /// The multipart ETag of the destination, computed from the ETags of the copied parts.
+    pub e_tag: String,
+    /// The full-object CRC64NVME of the destination, confirmed to match the combined CRC64NVME
+    /// of the sources.
+    pub checksum_match: Option<ChecksumPair>,
+}
+
+/// Concatenates S3 objects into a destination using `UploadPartCopy`, so that the data does
+/// not leave S3. The destination has a full-object CRC64NVME checksum which is compared to
+/// the combined checksums of the sources.
+#[derive(Debug)]
+pub struct ConcatTask {
+    sources: Vec<(String, String)>,
+    destination: (String, String),
+    client: S3Client,
+    concurrency: usize,
+    capacity: usize,
+}
+
+impl ConcatTask {
+    /// Plan the parts of the destination from the sizes of the sources and the limits of the
+    /// destination. Sources larger than the maximum part size are split into multiple parts, and
+    /// empty sources are skipped.
+    pub fn plan(sizes: &[u64], limits: &ProviderLimits) -> Result<Vec<ConcatPart>> {
+        let mut parts = vec![];
+        for (source, size) in sizes.iter().enumerate() {
+            // Split evenly so that no part is smaller than the minimum part size.
+            let n_parts = size.div_ceil(limits.max_part_size());
+            let part_size = size.checked_div(n_parts).unwrap_or_default();
+
+            let mut start = 0;
+            for n in 0..n_parts {
+                let end = if n == n_parts - 1 {
+                    *size
+                } else {
+                    start + part_size
+                };
+                parts.push(ConcatPart { source, start, end });
+                start = end;
+            }
+        }
+
+        if parts.is_empty() {
+            return Err(CopyError("all sources are empty".to_string()));
+        }
+        if u64::try_from(parts.len())? > limits.max_parts() {
+            return Err(CopyError(format!(
+                "concatenating requires {} parts, which is more than the maximum of {}",
+                parts.len(),
+                limits.max_parts()
+            )));
+        }
+        if let Some(part) = parts[..parts.len() - 1]
+            .iter()
+            .find(|part| part.size() < limits.min_part_size())
+        {
+            return Err(CopyError(format!(
+                "source {} is {} bytes, but every source except the last must be at least {} bytes",
+                part.source + 1,
+                part.size(),
+                limits.min_part_size()
+            )));
+        }
+
+        Ok(parts)
+    }
+
+    /// Get the size of each source, and its full-object CRC64NVME if S3 has one.
+    async fn heads(&self) -> Result<Vec<(u64, Option<u64>)>> {
+        let mut heads = vec![];
+        for (bucket, key) in &self.sources {
+            let head = self
+                .client
+                .head_object(|mut b| {
+                    b = b.bucket(bucket).key(key);
+                    if !self.client.no_checksum_mode() {
+                        b = b.checksum_mode(ChecksumMode::Enabled);
+                    }
+                    b
+                })
+                .await?;
+
+            let crc64nvme = head
+                .checksum_crc64_nvme
+                .as_deref()
+                .map(Self::decode_crc64nvme)
+                .transpose()?;
+            heads.push((
+                u64::try_from(head.content_length.unwrap_or_default())?,
+                crc64nvme,
+            ));
+        }
+
+        Ok(heads)
+    }
+
+    /// Decode a base64 CRC64NVME returned by S3.
+    fn decode_crc64nvme(crc64nvme: &str) -> Result<u64> {
+        let bytes = BASE64_STANDARD
+            .decode(crc64nvme)
+            .ok()
+            .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+            .ok_or_else(|| ParseError(format!("invalid CRC64NVME `{}`", crc64nvme)))?;
+
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Get the CRC64NVME of a source that S3 does not have a full-object CRC64NVME for, using
+    /// sums files if possible and otherwise reading the source.
+    async fn crc64nvme(&self, bucket: &str, key: &str) -> Result<u64> {
+        let ctx = Ctx::from_str("crc64nvme")?;
+        let location = Provider::format_s3(bucket, key);
+        let task = GenerateTaskBuilder::default()
+            .with_input_file_name(location.to_string())
+            .with_context(vec![ctx.clone()])
+            .with_capacity(self.capacity)
+            .with_client(self.client.clone())
+            .build()
+            .await?
+            .run()
+            .await?;
+
+        let checksum =
+            task.sums_file().checksums.get(&ctx).ok_or_else(|| {
+                CopyError(format!("failed to compute the CRC64NVME of {}", location))
+            })?;
+        u64::from_str_radix(checksum.as_str(), 16).map_err(|err| ParseError(err.to_string()))
+    }
+
+    /// Copy a single part to the destination.
+    async fn copy_part(
+        &self,
+        upload_id: &str,
+        part_number: i32,
+        part: &ConcatPart,
+    ) -> Result<CompletedPart> {
+        let (bucket, key) = &self.sources[part.source];
+        let (destination_bucket, destination_key) = &self.destination;
+
+        let result = self
+            .client
+            .upload_part_copy(|b| {
+                b.bucket(destination_bucket)
+                    .key(destination_key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .copy_source(format!("{}/{}", bucket, key))
+                    .copy_source_range(part.format_range())
+            })
+            .await?
+            .copy_part_result
+            .ok_or_else(|| Error::aws_error("missing copy part result".to_string()))?;
+
+        Ok(CompletedPart::builder()
+            .part_number(part_number)
+            .set_e_tag(result.e_tag)
+            .set_checksum_crc64_nvme(result.checksum_crc64_nvme)
+            .build())
+    }
+
+    /// Copy the parts and complete the upload, returning the ETags of the parts and the
+    /// full-object CRC64NVME of the destination.
+    async fn upload(
+        &self,
+        upload_id: &str,
+        parts: &[ConcatPart],
+    ) -> Result<(Vec<String>, Option<String>)> {
+        let completed: Vec<CompletedPart> =
+            stream::iter(parts.iter().enumerate())
+                .map(|(n, part)| async move {
+                    self.copy_part(upload_id, i32::try_from(n + 1)?, part).await
+                })
+                .buffered(self.concurrency)
+                .try_collect()
+                .await?;
+
+        let e_tags = completed
+            .iter()
+            .map(|part| {
+                part.e_tag()
+                    .map(str::to_string)
+                    .ok_or_else(|| Error::aws_error("missing part ETag".to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (bucket, key) = &self.destination;
+        let output = self
+            .client
+            .complete_multipart_upload(|b| {
+                b.bucket(bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .checksum_type(ChecksumType::FullObject)
+                    .multipart_upload(
+                        CompletedMultipartUpload::builder()
+                            .set_parts(Some(completed))
+                            .build(),
+                    )
+            })
+            .await?;
+
+        Ok((e_tags, output.checksum_crc64_nvme))
+    }
+
+    /// Concatenate the sources into the destination. The upload is aborted if any part fails
+    /// to copy.
+    pub async fn run(self) -> Result<ConcatOutput> {
+        let heads = self.heads().await?;
+        let sizes = heads.iter().map(|(size, _)| *size).collect::<Vec<_>>();
+        let parts = Self::plan(&sizes, &self.client.limits())?;
+
+        let (bucket, key) = &self.destination;
+        let upload_id = self
+            .client
+            .create_multipart_upload(|b| {
+                b.bucket(bucket)
+                    .key(key)
+                    .checksum_algorithm(ChecksumAlgorithm::Crc64Nvme)
+                    .checksum_type(ChecksumType::FullObject)
+            })
+            .await?
+            .upload_id
+            .ok_or_else(|| Error::aws_error("missing upload id".to_string()))?;
+
+        let (e_tags, crc64nvme) = match self.upload(&upload_id, &parts).await {
+            Ok(result) => result,
+            Err(err) => {
+                let _ = self
+                    .client
+                    .abort_multipart_upload(|b| b.bucket(bucket).key(key).upload_id(&upload_id))
+                    .await;
+                return Err(err);
+            }
+        };
+
+        // The full-object checksum of the destination must be the combination of the
+        // checksums of the sources.
+        let checksum_match = match crc64nvme {
+            Some(crc64nvme) => {
+                let destination = format!("{:016x}", Self::decode_crc64nvme(&crc64nvme)?);
+
+                let mut combined = None;
+                for ((bucket, key), (size, crc)) in self.sources.iter().zip(&heads) {
+                    let crc = match crc {
+                        Some(crc) => *crc,
+                        None => self.crc64nvme(bucket, key).await?,
+                    };
+                    combined = Some(match combined {
+                        Some(combined) => crc64nvme_combine(combined, crc, *size),
+                        None => crc,
+                    });
+                }
+                let expected = format!("{:016x}", combined.unwrap_or_default());
+
+                if destination != expected {
+                    return Err(CopyError(format!(
+                        "CRC64NVME of {} is {} but the combined CRC64NVME of the sources is {}",
+                        Provider::format_s3(bucket, key),
+                        destination,
+                        expected
+                    )));
+                }
+
+                Some(ChecksumPair::new(
+                    Ctx::from_str("crc64nvme")?,
+                    Checksum::new(expected),
+                ))
+            }
+            None => None,
+        };
+
+        Ok(ConcatOutput {
+            size: sizes.iter().sum(),
+            n_parts: parts.len(),
+            e_tag: S3::multipart_e_tag(&e_tags)?,
+            checksum_match,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::aws_etag::{GIB, MIB};
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadOutput;
+    use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadOutput;
+    use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+    use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyOutput;
+    use aws_sdk_s3::types::CopyPartResult;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use md5::{Digest, Md5};
+    use std::sync::Arc;
+
+    #[test]
+    fn plan_parts() -> Result<()> {
+        let parts = ConcatTask::plan(&[5 * MIB, 0, 11 * GIB, 1], &ProviderLimits::S3)?;
+        assert_eq!(
+            parts.iter().map(|part| part.source).collect::<Vec<_>>(),
+            [0, 2, 2, 2, 3]
+        );
+        assert_eq!(
+            parts.iter().map(ConcatPart::size).sum::<u64>(),
+            5 * MIB + 11 * GIB + 1
+        );
+        assert!(
+            parts
+                .iter()
+                .all(|part| part.size() <= ProviderLimits::S3.max_part_size())
+        );
+        assert_eq!(
+            parts[1].format_range(),
+            format!("bytes=0-{}", parts[1].end - 1)
+        );
+
+        assert!(ConcatTask::plan(&[1, 5 * MIB], &ProviderLimits::S3).is_err());
+        assert!(ConcatTask::plan(&[0, 0], &ProviderLimits::S3).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concat_copies_parts_and_checks_crc() -> Result<()> {
+        let first = vec![1; 5 * MIB as usize];
+        let second = b"second".to_vec();
+        let crc = |data: &[u8]| {
+            let mut digest = crc64fast_nvme::Digest::new();
+            digest.write(data);
+            digest.sum64()
+        };
+        let mut whole = first.clone();
+        whole.extend(&second);
+        let crc_first = crc(&first);
+        let crc_second = crc(&second);
+        let crc_whole = BASE64_STANDARD.encode(crc(&whole).to_be_bytes());
+        let to_base64 = |crc: u64| BASE64_STANDARD.encode(crc.to_be_bytes());
+
+        let head_first = mock!(Client::head_object)
+            .match_requests(|req| req.key() == Some("a"))
+            .then_output(move || {
+                HeadObjectOutput::builder()
+                    .content_length(5 * MIB as i64)
+                    .checksum_crc64_nvme(to_base64(crc_first))
+                    .build()
+            });
+        let head_second = mock!(Client::head_object)
+            .match_requests(|req| req.key() == Some("b"))
+            .then_output(move || {
+                HeadObjectOutput::builder()
+                    .content_length(6)
+                    .checksum_crc64_nvme(to_base64(crc_second))
+                    .build()
+            });
+        let create = mock!(Client::create_multipart_upload).then_output(|| {
+            CreateMultipartUploadOutput::builder()
+                .upload_id("upload")
+                .build()
+        });
+        let copy = mock!(Client::upload_part_copy).then_output(|| {
+            UploadPartCopyOutput::builder()
+                .copy_part_result(
+                    CopyPartResult::builder()
+                        .e_tag(format!("\"{}\"", hex::encode(Md5::digest(b"part"))))
+                        .build(),
+                )
+                .build()
+        });
+        let complete = mock!(Client::complete_multipart_upload).then_output(move || {
+            CompleteMultipartUploadOutput::builder()
+                .checksum_crc64_nvme(crc_whole.clone())
+                .build()
+        });
+
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&head_first, &head_second, &create, &copy, &complete]
+        );
+        let output = ConcatTaskBuilder::default()
+            .with_sources(vec![
+                "s3://bucket/a".to_string(),
+                "s3://bucket/b".to_string(),
+            ])
+            .with_destination("s3://bucket/c".to_string())
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .build()?
+            .run()
+            .await?;
+
+        assert_eq!(output.size, 5 * MIB + 6);
+        assert_eq!(output.n_parts, 2);
+        assert_eq!(copy.num_calls(), 2);
+        assert_eq!(
+            output.checksum_match.map(|pair| pair.value),
+            Some(Checksum::new(format!("{:016x}", crc(&whole))))
+        );
+
+        Ok(())
+    }
+}
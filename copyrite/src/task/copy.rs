@@ -4,13 +4,27 @@
 use crate::checksum::Ctx;
 use crate::checksum::aws_etag::PREFERRED_PART_SIZES;
 use crate::checksum::file::SumsFile;
-use crate::cli::{CopyMode, MetadataCopy};
-use crate::error::Error::CopyError;
+use crate::checksum::standard::StandardCtx;
+use crate::cli::{CopyMode, Directive, MetadataCopy};
+use crate::error::Error::{Cancelled, CopyError, PlanningError};
 use crate::error::{ApiError, Error, Result};
 use crate::io::Provider;
 use crate::io::S3Client;
-use crate::io::copy::{CopyResult, CopyState, MultiPartOptions, ObjectCopy, ObjectCopyBuilder};
+use crate::io::copy::differential::{Differential, PartContent};
+use crate::io::copy::fallback::Fallback;
+use crate::io::copy::filter::MetadataFilter;
+use crate::io::copy::limits::ProviderLimits;
+use crate::io::copy::validate::PartValidation;
+use crate::io::copy::{
+    CompleteOptions, CopyResult, CopyState, MultiPartOptions, ObjectCopy, ObjectCopyBuilder,
+};
 use crate::io::sums::ObjectSumsBuilder;
+use crate::stats::{Decision, DecisionKind};
+use crate::task::progress::{Phase, Progress};
+use crate::task::ui::ui_println;
+use aws_sdk_s3::types::StorageClass;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
 use console::style;
 use futures_util::future::join_all;
 use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
@@ -18,7 +32,11 @@ use std::cmp::min;
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter, Write};
 use std::future::Future;
+use std::sync::Arc;
 use std::{fmt, result};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::AbortOnDropHandle;
 
 pub const DEFAULT_MULTIPART_THRESHOLD: u64 = 8 * 1024 * 1024; // 8mib
 
@@ -31,12 +49,25 @@ pub struct CopyTaskBuilder {
     part_size: Option<u64>,
     metadata_mode: MetadataCopy,
     tag_mode: MetadataCopy,
+    tag_filter: MetadataFilter,
+    metadata_filter: MetadataFilter,
+    tagging_directive: Option<Directive>,
+    metadata_directive: Option<Directive>,
     copy_mode: CopyMode,
     source_client: Option<S3Client>,
     destination_client: Option<S3Client>,
     concurrency: Option<usize>,
+    differential: bool,
+    source_fallbacks: Vec<String>,
+    download_retries: Option<usize>,
+    complete_options: CompleteOptions,
+    storage_class: Option<StorageClass>,
+    default_checksum: Option<Ctx>,
     api_errors: HashSet<ApiError>,
+    decisions: Vec<Decision>,
     ui: bool,
+    progress: Vec<Arc<dyn Progress>>,
+    cancellation_token: CancellationToken,
 }
 
 /// Settings that determine the part size and additional checksums to use.
@@ -45,14 +76,15 @@ pub struct CopySettings {
     part_size: Option<u64>,
     ctx: Ctx,
     object_size: u64,
+    content_md5: Option<String>,
+    part_size_reason: String,
+    checksum_reason: String,
 }
 
 #[derive(Debug)]
 struct ObjectInfo {
     size: u64,
-    max_parts: u64,
-    max_part_size: u64,
-    min_part_size: u64,
+    limits: ProviderLimits,
 }
 
 impl CopySettings {
@@ -62,9 +94,50 @@ impl CopySettings {
             part_size,
             ctx,
             object_size,
+            content_md5: None,
+            part_size_reason: String::new(),
+            checksum_reason: String::new(),
         }
     }
 
+    /// Set the reasons that the part size and additional checksum were chosen.
+    pub fn with_reasons(
+        mut self,
+        part_size_reason: impl Into<String>,
+        checksum_reason: impl Into<String>,
+    ) -> Self {
+        self.part_size_reason = part_size_reason.into();
+        self.checksum_reason = checksum_reason.into();
+        self
+    }
+
+    /// The decisions about the part size and additional checksum.
+    pub fn decisions(&self) -> Vec<Decision> {
+        let part_size = match self.part_size {
+            Some(part_size) => format!("multipart with {} byte parts", part_size),
+            None => "single part".to_string(),
+        };
+
+        vec![
+            Decision::new(
+                DecisionKind::PartSize,
+                part_size,
+                self.part_size_reason.as_str(),
+            ),
+            Decision::new(
+                DecisionKind::Checksum,
+                self.ctx.to_string(),
+                self.checksum_reason.as_str(),
+            ),
+        ]
+    }
+
+    /// Set the base64 encoded MD5 of the object, which is used to validate single part uploads.
+    pub fn with_content_md5(mut self, content_md5: Option<String>) -> Self {
+        self.content_md5 = content_md5;
+        self
+    }
+
     /// Get the inner values.
     pub fn into_inner(self) -> (Option<u64>, Ctx, u64) {
         (self.part_size, self.ctx, self.object_size)
@@ -96,6 +169,30 @@ impl CopyTaskBuilder {
         self
     }
 
+    /// Set the filter applied to tags before writing them to the destination.
+    pub fn with_tag_filter(mut self, tag_filter: MetadataFilter) -> Self {
+        self.tag_filter = tag_filter;
+        self
+    }
+
+    /// Set the filter applied to metadata before writing it to the destination.
+    pub fn with_metadata_filter(mut self, metadata_filter: MetadataFilter) -> Self {
+        self.metadata_filter = metadata_filter;
+        self
+    }
+
+    /// Set the tagging directive for server-side copies.
+    pub fn with_tagging_directive(mut self, tagging_directive: Option<Directive>) -> Self {
+        self.tagging_directive = tagging_directive;
+        self
+    }
+
+    /// Set the metadata directive for server-side copies.
+    pub fn with_metadata_directive(mut self, metadata_directive: Option<Directive>) -> Self {
+        self.metadata_directive = metadata_directive;
+        self
+    }
+
     /// Set UI mode.
     pub fn with_ui(mut self, ui: bool) -> Self {
         self.ui = ui;
@@ -138,20 +235,87 @@ impl CopyTaskBuilder {
         self
     }
 
+    /// Only upload the parts that differ from an existing destination object.
+    pub fn with_differential(mut self, differential: bool) -> Self {
+        self.differential = differential;
+        self
+    }
+
+    /// Read parts from these replicas of the source if reading from the source fails repeatedly.
+    pub fn with_source_fallbacks(mut self, source_fallbacks: Vec<String>) -> Self {
+        self.source_fallbacks = source_fallbacks;
+        self
+    }
+
+    /// Validate the size and checksum of downloaded parts, fetching a part again up to
+    /// `download_retries` times if it fails validation. Parts are streamed without validation
+    /// if this is not set.
+    pub fn with_download_retries(mut self, download_retries: Option<usize>) -> Self {
+        self.download_retries = download_retries;
+        self
+    }
+
+    /// Set the options for completing multipart uploads at the destination.
+    pub fn with_complete_options(mut self, complete_options: CompleteOptions) -> Self {
+        self.complete_options = complete_options;
+        self
+    }
+
+    /// Set the storage class of the destination object.
+    pub fn set_storage_class(mut self, storage_class: Option<StorageClass>) -> Self {
+        self.storage_class = storage_class;
+        self
+    }
+
+    /// Set the additional checksum to use when the source has no sums to match. Defaults to
+    /// `Ctx::default()` if not set.
+    pub fn set_default_checksum(mut self, default_checksum: Option<Ctx>) -> Self {
+        self.default_checksum = default_checksum;
+        self
+    }
+
+    /// The additional checksum to use when the source has no sums to match.
+    fn default_checksum(&self) -> Ctx {
+        self.default_checksum.clone().unwrap_or_default()
+    }
+
+    /// The reason for using the default checksum.
+    fn default_checksum_reason(&self) -> &'static str {
+        if self.default_checksum.is_some() {
+            "the source has no existing sums, so the configured default checksum is used"
+        } else {
+            "the source has no existing sums, so the default checksum is used"
+        }
+    }
+
+    /// Record decisions made before the copy, e.g. why it was not skipped. These come before
+    /// the decisions made by the copy task.
+    pub fn with_decisions(mut self, decisions: Vec<Decision>) -> Self {
+        self.decisions = decisions;
+        self
+    }
+
+    /// Register an observer that is notified of the progress of the copy. This can be called
+    /// multiple times to register multiple observers.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress.push(progress);
+        self
+    }
+
+    /// Set the token used to cancel the copy while it is running.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
     /// Return whether multipart is available.
-    fn is_multipart(
-        object_size: u64,
-        part_size: u64,
-        max_parts: u64,
-        max_part_size: u64,
-        min_part_size: u64,
-    ) -> bool {
-        if part_size > max_part_size || part_size < min_part_size {
+    fn is_multipart(object_size: u64, part_size: u64, limits: &ProviderLimits) -> bool {
+        if part_size > limits.max_part_size() || part_size < limits.min_part_size() {
             return false;
         }
 
         // This should allow up to and including max_parts, i.e. 10000 for S3.
-        object_size.div_ceil(part_size) <= max_parts
+        object_size.div_ceil(part_size) <= limits.max_parts()
     }
 
     /// Return whether single part is available.
@@ -160,21 +324,22 @@ impl CopyTaskBuilder {
     }
 
     /// Find the best preferred multipart part size for an object, if a valid one exists.
-    fn preferred_multipart_part_size(
-        object_size: u64,
-        max_parts: u64,
-        max_part_size: u64,
-        min_part_size: u64,
-    ) -> Option<u64> {
-        PREFERRED_PART_SIZES.iter().copied().find(|part_size| {
-            Self::is_multipart(
-                object_size,
-                *part_size,
-                max_parts,
-                max_part_size,
-                min_part_size,
-            )
-        })
+    fn preferred_multipart_part_size(object_size: u64, limits: &ProviderLimits) -> Option<u64> {
+        PREFERRED_PART_SIZES
+            .iter()
+            .copied()
+            .find(|part_size| Self::is_multipart(object_size, *part_size, limits))
+    }
+
+    /// Get the base64 encoded MD5 of an object from its sums file, if it has one.
+    fn content_md5(sums: &SumsFile) -> Option<String> {
+        sums.checksums
+            .iter()
+            .find_map(|(ctx, checksum)| match ctx {
+                Ctx::Regular(StandardCtx::MD5(_)) => hex::decode(checksum.as_str()).ok(),
+                _ => None,
+            })
+            .map(|md5| BASE64_STANDARD.encode(md5))
     }
 
     /// Determine the settings from an existing sums file.
@@ -190,50 +355,54 @@ impl CopyTaskBuilder {
             .checksums
             .keys()
             .find_map(|ctx| {
-                ctx.is_preferred_multipart(&destination)
+                ctx.is_preferred_multipart(destination.is_s3())
                     .map(|part_size| (part_size, ctx.clone()))
             })
-            .take_if(|(part_size, _)| {
-                Self::is_multipart(
-                    info.size,
-                    *part_size,
-                    info.max_parts,
-                    info.max_part_size,
-                    info.min_part_size,
-                )
-            });
+            .take_if(|(part_size, _)| Self::is_multipart(info.size, *part_size, &info.limits));
         if let Some((part_size, ctx)) = ctx {
-            return Ok(CopySettings::new(Some(part_size), ctx, info.size));
+            let reason = format!("matches the multipart checksum `{}` of the source", ctx);
+            return Ok(CopySettings::new(Some(part_size), ctx, info.size)
+                .with_reasons(reason.as_str(), reason.as_str()));
         }
 
         // Otherwise, check if a preferred single part checksum exists.
         let ctx = sums
             .checksums
             .keys()
-            .find(|ctx| ctx.is_preferred_single_part(&destination))
-            .take_if(|_| Self::is_single_part(info.size, info.max_part_size));
+            .find(|ctx| ctx.is_preferred_single_part(destination.is_s3()))
+            .take_if(|_| Self::is_single_part(info.size, info.limits.max_part_size()));
         if let Some(ctx) = ctx {
-            return Ok(CopySettings::new(None, ctx.clone(), info.size));
+            let reason = format!("matches the single part checksum `{}` of the source", ctx);
+            return Ok(CopySettings::new(None, ctx.clone(), info.size)
+                .with_reasons(reason.as_str(), reason.as_str()));
         }
 
         // If none of the above apply, fall back based on the object size, keeping the best
         // available checksum as the additional context to set on the copy.
-        let additional_ctx = sums.checksums.keys().next().cloned().unwrap_or_default();
-        if Self::is_single_part(info.size, info.max_part_size) {
-            Ok(CopySettings::new(None, additional_ctx, info.size))
-        } else if let Some(part_size) = Self::preferred_multipart_part_size(
-            info.size,
-            info.max_parts,
-            info.max_part_size,
-            info.min_part_size,
-        ) {
-            Ok(CopySettings::new(
-                Some(part_size),
-                additional_ctx,
-                info.size,
-            ))
+        let (additional_ctx, checksum_reason) = match sums.checksums.keys().next() {
+            Some(ctx) => (
+                ctx.clone(),
+                "the best checksum in the existing sums of the source",
+            ),
+            None => (self.default_checksum(), self.default_checksum_reason()),
+        };
+        if Self::is_single_part(info.size, info.limits.max_part_size()) {
+            Ok(
+                CopySettings::new(None, additional_ctx, info.size).with_reasons(
+                    "no checksum of the source can be matched and the object fits in a single part",
+                    checksum_reason,
+                ),
+            )
+        } else if let Some(part_size) = Self::preferred_multipart_part_size(info.size, &info.limits)
+        {
+            Ok(
+                CopySettings::new(Some(part_size), additional_ctx, info.size).with_reasons(
+                    "no checksum of the source can be matched, so the preferred part size for the object size is used",
+                    checksum_reason,
+                ),
+            )
         } else {
-            Err(CopyError(format!(
+            Err(PlanningError(format!(
                 "failed to find a valid part size for object size `{}`",
                 info.size
             )))
@@ -261,19 +430,29 @@ impl CopyTaskBuilder {
         state: &CopyState,
     ) -> Result<(Self, CopySettings)> {
         let size = state.size();
-        let max_part_size = destination_copy.max_part_size();
-        let max_parts = destination_copy.max_parts();
-        let min_part_size = destination_copy.min_part_size();
+        let limits = destination_copy.limits();
 
-        // Refuse objects larger than the destination's maximum object.
-        let max_object_size = destination_copy.max_object_size();
+        // Refuse objects larger than the destination's maximum object before anything is
+        // transferred.
+        let max_object_size = limits.max_object_size();
         if size > max_object_size {
-            return Err(CopyError(format!(
-                "object size `{}` exceeds the maximum: `{}`",
-                size, max_object_size
+            return Err(PlanningError(format!(
+                "object size `{}` exceeds the maximum object size of `{}` for {}",
+                size,
+                max_object_size,
+                destination.format()
             )));
         }
 
+        // Destinations that cannot combine parts are copied with a single request.
+        if limits.max_parts() == 1 && Self::is_single_part(size, limits.max_part_size()) {
+            let settings = CopySettings::new(None, self.default_checksum(), size).with_reasons(
+                "the destination cannot combine parts",
+                self.default_checksum_reason(),
+            );
+            return Ok((self, settings));
+        }
+
         // Only use the sums file if the size is not set at the source.
         let sums = if self.part_size.is_none() {
             let mut object = ObjectSumsBuilder::default()
@@ -290,16 +469,9 @@ impl CopyTaskBuilder {
 
         // If there are existing sums, try the best part size.
         let settings = if let Some(sums) = sums {
-            let settings = self.use_settings_from_sums(
-                &sums,
-                ObjectInfo {
-                    size,
-                    max_parts,
-                    max_part_size,
-                    min_part_size,
-                },
-                destination,
-            )?;
+            let settings = self
+                .use_settings_from_sums(&sums, ObjectInfo { size, limits }, destination)?
+                .with_content_md5(Self::content_md5(&sums));
             if self.part_size.is_none() {
                 return Ok((self, settings));
             } else {
@@ -310,9 +482,13 @@ impl CopyTaskBuilder {
         };
 
         // Use the additional sum from the settings if available or the default.
-        let additional_ctx = settings
-            .map(|settings| settings.into_inner().1)
-            .unwrap_or_default();
+        let (additional_ctx, checksum_reason) = match settings {
+            Some(settings) => (settings.ctx, settings.checksum_reason),
+            None => (
+                self.default_checksum(),
+                self.default_checksum_reason().to_string(),
+            ),
+        };
 
         let threshold = self
             .multipart_threshold
@@ -322,13 +498,14 @@ impl CopyTaskBuilder {
         if let Some(part_size) = self.part_size
             && size > threshold
         {
-            return if Self::is_multipart(size, part_size, max_parts, max_part_size, min_part_size) {
+            return if Self::is_multipart(size, part_size, &limits) {
                 Ok((
                     self,
-                    CopySettings::new(Some(part_size), additional_ctx, size),
+                    CopySettings::new(Some(part_size), additional_ctx, size)
+                        .with_reasons("the part size was set explicitly", checksum_reason),
                 ))
             } else {
-                Err(CopyError(format!(
+                Err(PlanningError(format!(
                     "invalid part size `{}` and threshold `{}` for the object size `{}`",
                     part_size, threshold, size
                 )))
@@ -336,19 +513,22 @@ impl CopyTaskBuilder {
         }
 
         let err_fn = || {
-            CopyError(format!(
+            PlanningError(format!(
                 "failed to find a valid part size for the threshold `{}` with object size `{}`",
                 threshold, size
             ))
         };
         // Use multipart if the size reaches the threshold.
         if size > threshold {
-            return if let Some(part_size) =
-                Self::preferred_multipart_part_size(size, max_parts, max_part_size, min_part_size)
-            {
+            return if let Some(part_size) = Self::preferred_multipart_part_size(size, &limits) {
+                let reason = format!(
+                    "the object size exceeds the multipart threshold of `{}`",
+                    threshold
+                );
                 Ok((
                     self,
-                    CopySettings::new(Some(part_size), additional_ctx, size),
+                    CopySettings::new(Some(part_size), additional_ctx, size)
+                        .with_reasons(reason, checksum_reason),
                 ))
             } else {
                 Err(err_fn())
@@ -356,8 +536,15 @@ impl CopyTaskBuilder {
         }
 
         // Otherwise use single part if possible.
-        if Self::is_single_part(size, max_part_size) {
-            return Ok((self, CopySettings::new(None, additional_ctx, size)));
+        if Self::is_single_part(size, limits.max_part_size()) {
+            let reason = format!(
+                "the object size does not exceed the multipart threshold of `{}`",
+                threshold
+            );
+            return Ok((
+                self,
+                CopySettings::new(None, additional_ctx, size).with_reasons(reason, checksum_reason),
+            ));
         }
 
         // This condition may occur if the size is greater than the possible single part upload
@@ -365,6 +552,31 @@ impl CopyTaskBuilder {
         Err(err_fn())
     }
 
+    /// Apply an explicit tagging or metadata directive to the copy state. A `copy` directive
+    /// conflicts with filtering or suppressing the values, which require replacing them.
+    fn apply_directive(
+        name: &str,
+        directive: Option<Directive>,
+        state: &mut CopyState,
+        replace: fn(&CopyState) -> bool,
+        set_replace: fn(&mut CopyState, bool),
+        mode: MetadataCopy,
+    ) -> Result<()> {
+        match directive {
+            Some(Directive::Copy) if replace(state) || matches!(mode, MetadataCopy::Suppress) => {
+                Err(CopyError(format!(
+                    "the {} directive cannot be `copy` when filtering or suppressing {}",
+                    name, name
+                )))
+            }
+            Some(Directive::Replace) => {
+                set_replace(state, true);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Build a copy task.
     pub async fn build(self) -> Result<CopyTask> {
         if self.source.is_empty() || self.destination.is_empty() {
@@ -376,15 +588,40 @@ impl CopyTaskBuilder {
 
         let is_same_provider =
             (source.is_file() && destination.is_file()) || (source.is_s3() && destination.is_s3());
-        let copy_mode = if is_same_provider {
-            if self.copy_mode.is_download_upload() {
-                CopyMode::DownloadUpload
-            } else {
-                CopyMode::ServerSide
-            }
+        // Fallback sources can only be read from when downloading.
+        let (copy_mode, reason) = if !self.source_fallbacks.is_empty() {
+            (
+                CopyMode::DownloadUpload,
+                "source fallbacks can only be read by downloading",
+            )
+        } else if !is_same_provider {
+            (
+                CopyMode::DownloadUpload,
+                "server-side copies are only possible between S3 locations or local files",
+            )
+        } else if self.copy_mode.is_download_upload() {
+            (CopyMode::DownloadUpload, "download-upload was requested")
         } else {
-            CopyMode::DownloadUpload
+            (
+                CopyMode::ServerSide,
+                "the source and destination support server-side copies",
+            )
         };
+        // A copy mode that was already decided by the caller, e.g. because of the credentials,
+        // is not decided again unless it is overridden.
+        let mut decisions = self.decisions.clone();
+        if copy_mode.is_download_upload() != self.copy_mode.is_download_upload()
+            || !decisions
+                .iter()
+                .any(|decision| decision.kind() == DecisionKind::CopyMode)
+        {
+            decisions.retain(|decision| decision.kind() != DecisionKind::CopyMode);
+            decisions.push(Decision::new(
+                DecisionKind::CopyMode,
+                copy_mode.to_string(),
+                reason,
+            ));
+        }
 
         let source_copy = ObjectCopyBuilder::default()
             .with_copy_metadata(self.metadata_mode)
@@ -396,6 +633,8 @@ impl CopyTaskBuilder {
         let mut destination_builder = ObjectCopyBuilder::default()
             .with_copy_metadata(self.metadata_mode)
             .with_copy_tags(self.tag_mode)
+            .with_complete_options(self.complete_options)
+            .set_storage_class(self.storage_class.clone())
             .set_client(self.destination_client.clone())
             .set_destination(Some(destination.clone()));
         if copy_mode.is_server_side() {
@@ -403,7 +642,46 @@ impl CopyTaskBuilder {
         }
         let destination_copy = destination_builder.build().await?;
 
-        let state = source_copy.initialize_state().await?;
+        let mut state = source_copy.initialize_state().await?;
+
+        let source_copy = if self.source_fallbacks.is_empty() {
+            source_copy
+        } else {
+            let mut fallbacks = Vec::with_capacity(self.source_fallbacks.len());
+            for fallback in &self.source_fallbacks {
+                let fallback_copy = ObjectCopyBuilder::default()
+                    .with_copy_metadata(self.metadata_mode)
+                    .with_copy_tags(self.tag_mode)
+                    .set_client(self.source_client.clone())
+                    .set_source(Some(Provider::try_from(fallback.as_str())?))
+                    .build()
+                    .await?;
+                fallbacks.push((fallback.clone(), fallback_copy));
+            }
+
+            // Replicas must contain the same object, which is also verified by the checksums
+            // after copying.
+            Box::new(Fallback::try_new(source_copy, &state, fallbacks).await?)
+        };
+
+        state.filter_tags(&self.tag_filter);
+        state.filter_metadata(&self.metadata_filter);
+        Self::apply_directive(
+            "tagging",
+            self.tagging_directive,
+            &mut state,
+            CopyState::replace_tags,
+            CopyState::set_replace_tags,
+            self.tag_mode,
+        )?;
+        Self::apply_directive(
+            "metadata",
+            self.metadata_directive,
+            &mut state,
+            CopyState::replace_metadata,
+            CopyState::set_replace_metadata,
+            self.metadata_mode,
+        )?;
 
         let concurrency = self
             .concurrency
@@ -412,46 +690,95 @@ impl CopyTaskBuilder {
         let (this, settings) = self
             .use_settings(destination.clone(), destination_copy.as_ref(), &state)
             .await?;
+        decisions.extend(settings.decisions());
+
+        // Differential copies compare multipart uploads against the existing destination, which
+        // must be able to keep the parts that have not changed.
+        let differential = if this.differential
+            && copy_mode.is_download_upload()
+            && settings.part_size.is_some()
+            && destination_copy.supports_reuse()
+        {
+            let size = ObjectSumsBuilder::default()
+                .set_client(this.destination_client.clone())
+                .build(this.destination.to_string())
+                .await?
+                .file_size()
+                .await
+                .ok()
+                .flatten();
+
+            match size {
+                Some(size) => {
+                    let existing = ObjectCopyBuilder::default()
+                        .set_client(this.destination_client.clone())
+                        .set_source(Some(destination.clone()))
+                        .build()
+                        .await?;
+                    Some(Differential::new(existing, size))
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+        state.set_differential(differential.is_some());
+        // Single part uploads send the MD5 so that the destination validates the data.
+        state.set_content_md5(
+            settings
+                .content_md5
+                .filter(|_| settings.part_size.is_none()),
+        );
 
-        let pb = if this.ui {
-            println!("{} Copying...", style("[2/3]").bold().dim(),);
-            println!(
+        let mut progress = this.progress;
+        ui_println(
+            this.ui,
+            format!("{} Copying...", style("[2/3]").bold().dim()),
+        );
+        ui_println(
+            this.ui,
+            format!(
                 "  {} Source - {}",
                 style("·").bold(),
-                style(this.source).green(),
-            );
-            println!(
+                style(&this.source).green(),
+            ),
+        );
+        ui_println(
+            this.ui,
+            format!(
                 "  {} Destination - {}",
                 style("·").bold(),
-                style(this.destination).green(),
-            );
-            let part_mode = if let Some(part_size) = settings.part_size {
-                format!(
-                    "{} with {} part size",
-                    style("multipart").cyan(),
-                    style(HumanBytes(part_size)).cyan()
-                )
-            } else {
-                format!("{}", style("single part").cyan())
-            };
-
-            println!(
+                style(&this.destination).green(),
+            ),
+        );
+        let part_mode = if let Some(part_size) = settings.part_size {
+            format!(
+                "{} with {} part size",
+                style("multipart").cyan(),
+                style(HumanBytes(part_size)).cyan()
+            )
+        } else {
+            format!("{}", style("single part").cyan())
+        };
+        ui_println(
+            this.ui,
+            format!(
                 "  {} Mode - {} {}",
                 style("·").bold(),
                 style(copy_mode).green(),
                 style(part_mode).green(),
-            );
+            ),
+        );
 
+        if this.ui {
             let pb = ProgressBar::new(settings.object_size);
             pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
                 .unwrap()
                 .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
                 .progress_chars("#>-"));
 
-            Some(pb)
-        } else {
-            None
-        };
+            progress.push(Arc::new(pb));
+        }
 
         let copy_task = CopyTask {
             additional_sums: settings.ctx,
@@ -463,18 +790,52 @@ impl CopyTaskBuilder {
             object_size: settings.object_size,
             concurrency,
             state,
+            differential,
+            part_validation: this.download_retries.map(PartValidation::new),
             ordered_upload: destination.is_file(),
             destination,
             bytes_transferred: 0,
+            bytes_reused: 0,
+            content_md5_validated: false,
+            checksum_rejected: false,
+            decisions,
             n_retries: 0,
+            n_download_retries: 0,
             recoverable_errors: this.api_errors,
-            pb,
+            progress,
+            cancellation_token: this.cancellation_token,
+            upload_id: None,
         };
 
         Ok(copy_task)
     }
 }
 
+/// Record an additional checksum that the destination rejected in its sums file, so that the
+/// sums file shows that the checksum is not stored with the object.
+pub async fn write_rejected_checksum(
+    destination: &str,
+    client: S3Client,
+    rejected_checksum: &str,
+) -> Result<()> {
+    let mut object = ObjectSumsBuilder::default()
+        .set_client(Some(client))
+        .build(destination.to_string())
+        .await?;
+
+    let mut sums_file = match object.sums_file().await? {
+        Some(sums_file) => sums_file,
+        None => SumsFile::default().with_size(object.file_size().await?),
+    };
+    let mut rejected_checksums = sums_file.rejected_checksums().clone();
+    if rejected_checksums.insert(rejected_checksum.to_string()) {
+        sums_file.set_rejected_checksums(rejected_checksums);
+        object.write_sums_file(&sums_file).await?;
+    }
+
+    Ok(())
+}
+
 /// The copy error with the task information when the error occurred.
 pub struct CopyTaskError {
     pub task: CopyTask,
@@ -514,20 +875,38 @@ pub struct CopyTask {
     object_size: u64,
     concurrency: usize,
     state: CopyState,
+    differential: Option<Differential>,
+    part_validation: Option<PartValidation>,
     ordered_upload: bool,
     bytes_transferred: u64,
+    bytes_reused: u64,
+    content_md5_validated: bool,
+    checksum_rejected: bool,
+    decisions: Vec<Decision>,
     n_retries: u64,
+    n_download_retries: u64,
     recoverable_errors: HashSet<ApiError>,
-    pb: Option<ProgressBar>,
+    progress: Vec<Arc<dyn Progress>>,
+    cancellation_token: CancellationToken,
+    upload_id: Option<String>,
 }
 
 impl CopyTask {
-    fn update_bytes(&mut self, bytes_transferred: u64) {
+    fn update_bytes(&mut self, bytes_transferred: u64, bytes_reused: u64) {
         self.bytes_transferred += bytes_transferred;
-        if let Some(pb) = self.pb.as_ref() {
-            pb.set_position(min(self.bytes_transferred, self.object_size));
+        self.bytes_reused += bytes_reused;
+        self.progress.bytes_transferred(
+            min(self.bytes_transferred + self.bytes_reused, self.object_size),
+            self.object_size,
+        );
+    }
+
+    fn complete_part(&self, part_number: Option<u64>, n_parts: u64) {
+        if let Some(part_number) = part_number {
+            self.progress.part_completed(part_number, n_parts);
         }
     }
+
     async fn run_multipart<FnC, FutC, FnR, FutR, R>(
         &mut self,
         part_size: u64,
@@ -577,10 +956,11 @@ impl CopyTask {
 
                 let state = self.state.clone();
 
+                // Parts are aborted if the copy is cancelled.
                 let copy_fn = download_fn.clone();
-                copy_tasks.push(tokio::spawn(async move {
+                copy_tasks.push(AbortOnDropHandle::new(tokio::spawn(async move {
                     (options.clone(), copy_fn(options, state).await)
-                }));
+                })));
 
                 start += part_size;
                 end += part_size;
@@ -590,12 +970,17 @@ impl CopyTask {
                 // If the uploads should be ordered, then wait for each task to finish before uploading.
                 for result in join_all(copy_tasks).await {
                     let (options, result) = result?;
+                    let part_number = options.part_number;
                     let result = upload_fn.clone()(result?, options, self.state.clone()).await?;
 
+                    self.complete_part(part_number, n_parts);
                     upload_id = result.upload_id;
+                    self.upload_id.clone_from(&upload_id);
                     push_part(&mut parts, result.part);
-                    self.update_bytes(result.bytes_transferred);
+                    self.update_bytes(result.bytes_transferred, result.bytes_reused);
                     self.n_retries += result.n_retries;
+                    self.checksum_rejected |= result.checksum_rejected;
+                    self.n_download_retries += result.n_download_retries;
                     self.recoverable_errors.extend(result.api_errors);
                 }
             } else {
@@ -606,17 +991,22 @@ impl CopyTask {
 
                     let upload_fn = upload_fn.clone();
                     let state = self.state.clone();
-                    upload_tasks.push(tokio::spawn(async move {
-                        upload_fn(result?, options, state).await
-                    }));
+                    let part_number = options.part_number;
+                    upload_tasks.push(AbortOnDropHandle::new(tokio::spawn(async move {
+                        Ok::<_, Error>((part_number, upload_fn(result?, options, state).await?))
+                    })));
                 }
 
                 for result in join_all(upload_tasks).await {
-                    let result = result??;
+                    let (part_number, result) = result??;
+                    self.complete_part(part_number, n_parts);
                     upload_id = result.upload_id;
+                    self.upload_id.clone_from(&upload_id);
                     push_part(&mut parts, result.part);
-                    self.update_bytes(result.bytes_transferred);
+                    self.update_bytes(result.bytes_transferred, result.bytes_reused);
                     self.n_retries += result.n_retries;
+                    self.checksum_rejected |= result.checksum_rejected;
+                    self.n_download_retries += result.n_download_retries;
                     self.recoverable_errors.extend(result.api_errors);
                 }
             }
@@ -624,6 +1014,7 @@ impl CopyTask {
 
         // Complete the upload. The completion step is the only operation that needs the
         // accumulated parts.
+        self.progress.phase(Phase::Completing);
         let options = MultiPartOptions {
             part_number: None,
             start,
@@ -633,8 +1024,9 @@ impl CopyTask {
         };
         let result = download_fn(options.clone(), self.state.clone()).await?;
         let upload = upload_fn(result, options, self.state.clone()).await?;
-        self.update_bytes(upload.bytes_transferred);
+        self.update_bytes(upload.bytes_transferred, upload.bytes_reused);
         self.n_retries += upload.n_retries;
+        self.checksum_rejected |= upload.checksum_rejected;
         self.recoverable_errors.extend(upload.api_errors);
 
         Ok(())
@@ -642,13 +1034,15 @@ impl CopyTask {
 
     async fn do_copy(&mut self) -> Result<()> {
         self.state.set_additional_ctx(self.additional_sums.clone());
+        self.progress.phase(Phase::Copying);
 
         match (self.copy_mode, self.part_size) {
             (CopyMode::ServerSide, None) => {
                 let copy = self.destination_copy.copy(None, &self.state).await?;
 
-                self.update_bytes(copy.bytes_transferred);
+                self.update_bytes(copy.bytes_transferred, copy.bytes_reused);
                 self.n_retries += copy.n_retries;
+                self.checksum_rejected |= copy.checksum_rejected;
                 self.recoverable_errors.extend(copy.api_errors);
             }
             (CopyMode::ServerSide, Some(part_size)) => {
@@ -669,35 +1063,93 @@ impl CopyTask {
                     .upload(data, None, &self.state)
                     .await?;
 
-                self.update_bytes(upload.bytes_transferred);
+                self.update_bytes(upload.bytes_transferred, upload.bytes_reused);
+                self.content_md5_validated = upload.content_md5_validated;
                 self.n_retries += upload.n_retries;
+                self.checksum_rejected |= upload.checksum_rejected;
                 self.recoverable_errors.extend(upload.api_errors);
             }
             (CopyMode::DownloadUpload, Some(part_size)) => {
+                let differential = self.differential.clone();
+                let part_validation = self.part_validation;
                 let source = self.source_copy.clone();
+                let validation_source = self.source_copy.clone();
                 let destination = self.destination_copy.clone();
 
+                // For differential copies, unchanged parts are reused from the existing
+                // destination instead of being uploaded. Changed parts are validated while they
+                // are uploaded if requested, which counts the number of times they were
+                // downloaded again.
                 self.run_multipart(
                     part_size,
-                    |option, _| async move { source.download(Some(option)).await },
-                    |data, options, state| async move {
-                        destination.upload(data, Some(options), &state).await
+                    move |option, state| async move {
+                        match differential {
+                            Some(differential) => {
+                                differential
+                                    .download(source.as_ref(), option.clone(), &state)
+                                    .await
+                            }
+                            None => Ok(PartContent::Changed(
+                                source.download(Some(option.clone())).await?,
+                            )),
+                        }
+                    },
+                    move |data, options, state| async move {
+                        match (data, part_validation) {
+                            (PartContent::Unchanged, _) => destination.reuse(options, &state).await,
+                            (PartContent::Changed(data), Some(part_validation))
+                                if options.part_number.is_some() =>
+                            {
+                                part_validation
+                                    .upload(
+                                        validation_source.as_ref(),
+                                        data,
+                                        &options,
+                                        &state,
+                                        |data| {
+                                            destination.upload(data, Some(options.clone()), &state)
+                                        },
+                                    )
+                                    .await
+                            }
+                            (PartContent::Changed(data), _) => {
+                                destination.upload(data, Some(options), &state).await
+                            }
+                        }
                     },
                 )
                 .await?
             }
         };
 
-        if let Some(pb) = self.pb.as_ref() {
-            pb.finish_with_message("done");
-        }
+        self.progress.phase(Phase::Finished);
 
         Ok(())
     }
 
-    /// Runs the copy task and return the output.
+    /// Runs the copy task and return the output. If the task is cancelled, the error contains
+    /// the bytes transferred by the parts that completed before cancellation, and any multipart
+    /// upload in progress is aborted unless aborting is disabled.
     pub async fn run(mut self) -> CopyTaskResult {
-        match self.do_copy().await {
+        let cancellation_token = self.cancellation_token.clone();
+        let result = select! {
+            biased;
+            _ = cancellation_token.cancelled() => Err(Cancelled(format!(
+                "copy from {} to {}",
+                self.source.format(),
+                self.destination.format()
+            ))),
+            result = self.do_copy() => result,
+        };
+
+        let result = match (result, &self.upload_id) {
+            (Err(err @ Cancelled(_)), Some(upload_id)) => {
+                Err(self.destination_copy.abort_upload(upload_id, err).await)
+            }
+            (result, _) => result,
+        };
+
+        match result {
             Ok(_) => Ok(self),
             Err(err) => Err((self, err).into()),
         }
@@ -723,6 +1175,28 @@ impl CopyTask {
         self.bytes_transferred
     }
 
+    /// Get the total bytes kept from the existing destination by a differential copy.
+    pub fn bytes_reused(&self) -> u64 {
+        self.bytes_reused
+    }
+
+    /// Whether the destination validated the uploaded data using the `Content-MD5`.
+    pub fn content_md5_validated(&self) -> bool {
+        self.content_md5_validated
+    }
+
+    /// The additional checksum that was not stored with the copy because the destination
+    /// rejected additional checksums.
+    pub fn rejected_checksum(&self) -> Option<String> {
+        self.checksum_rejected
+            .then(|| self.additional_sums.to_string())
+    }
+
+    /// The choices made while planning the copy.
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
     /// Get the api errors.
     pub fn api_errors(&self) -> HashSet<ApiError> {
         self.recoverable_errors.clone()
@@ -732,6 +1206,12 @@ impl CopyTask {
     pub fn n_retries(&self) -> u64 {
         self.n_retries
     }
+
+    /// Get the number of times a downloaded part was fetched again because it failed
+    /// validation.
+    pub fn n_download_retries(&self) -> u64 {
+        self.n_download_retries
+    }
 }
 
 #[cfg(test)]
@@ -742,6 +1222,7 @@ pub(crate) mod test {
     use crate::io::sums::aws::test::{
         mock_multi_part_etag_only_rule, mock_single_part_etag_only_rule,
     };
+    use crate::task::progress::test::RecordProgress;
     use crate::test::{TEST_FILE_SIZE, TestFileBuilder};
     use anyhow::Result;
     use aws_sdk_s3::Client;
@@ -752,7 +1233,9 @@ pub(crate) mod test {
     use aws_smithy_mocks::{Rule, RuleMode, mock, mock_client};
     use std::str::FromStr;
     use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use tempfile::tempdir;
+    use tokio::fs;
     use tokio::fs::File;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
@@ -787,20 +1270,8 @@ pub(crate) mod test {
             unimplemented!()
         }
 
-        fn max_part_size(&self) -> u64 {
-            5368709120
-        }
-
-        fn max_parts(&self) -> u64 {
-            10000
-        }
-
-        fn min_part_size(&self) -> u64 {
-            5242880
-        }
-
-        fn max_object_size(&self) -> u64 {
-            self.max_object_size
+        fn limits(&self) -> ProviderLimits {
+            ProviderLimits::new(10000, 5242880, 5368709120, self.max_object_size)
         }
 
         async fn initialize_state(&self) -> error::Result<CopyState> {
@@ -814,19 +1285,42 @@ pub(crate) mod test {
         assert!(CopyTaskBuilder::is_multipart(
             exactly_max,
             10141870,
-            10000,
-            5368709120,
-            5242880
+            &ProviderLimits::S3
         ));
         assert!(!CopyTaskBuilder::is_multipart(
             exactly_max + 1,
             10141870,
-            10000,
-            5368709120,
-            5242880
+            &ProviderLimits::S3
         ));
     }
 
+    #[test]
+    fn apply_directive_replaces_or_conflicts() {
+        let apply = |directive, state: &mut CopyState, mode| {
+            CopyTaskBuilder::apply_directive(
+                "tagging",
+                directive,
+                state,
+                CopyState::replace_tags,
+                CopyState::set_replace_tags,
+                mode,
+            )
+        };
+
+        let mut state = CopyState::new(1, Some("a=b".to_string()), None);
+        apply(Some(Directive::Copy), &mut state, MetadataCopy::Copy).unwrap();
+        assert!(!state.replace_tags());
+        apply(Some(Directive::Replace), &mut state, MetadataCopy::Copy).unwrap();
+        assert!(state.replace_tags());
+        assert_eq!(state.tags(), Some("a=b".to_string()));
+
+        // Filtered or suppressed tags cannot be copied.
+        assert!(apply(Some(Directive::Copy), &mut state, MetadataCopy::Copy).is_err());
+        let mut state = CopyState::new(1, None, None);
+        assert!(apply(Some(Directive::Copy), &mut state, MetadataCopy::Suppress).is_err());
+        apply(None, &mut state, MetadataCopy::Suppress).unwrap();
+    }
+
     #[test]
     fn invalid_settings_fallback_to_multipart() {
         let builder = CopyTaskBuilder::default();
@@ -842,9 +1336,7 @@ pub(crate) mod test {
         let size = 6 * 1024 * 1024 * 1024;
         let info = ObjectInfo {
             size,
-            max_parts: 10000,
-            max_part_size: 5368709120,
-            min_part_size: 5242880,
+            limits: ProviderLimits::S3,
         };
 
         let settings = builder
@@ -855,6 +1347,25 @@ pub(crate) mod test {
         assert!(settings.part_size.is_some());
     }
 
+    #[test]
+    fn content_md5_from_sums() {
+        let mut sums = SumsFile::default();
+        sums.add_checksum(
+            Ctx::from_str("md5-aws-8mib").unwrap(),
+            Checksum::new("d41d8cd98f00b204e9800998ecf8427e-8388608b".to_string()),
+        ); // pragma: allowlist secret
+        assert_eq!(CopyTaskBuilder::content_md5(&sums), None);
+
+        sums.add_checksum(
+            Ctx::from_str("md5").unwrap(),
+            Checksum::new("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+        ); // pragma: allowlist secret
+        assert_eq!(
+            CopyTaskBuilder::content_md5(&sums).as_deref(),
+            Some("1B2M2Y8AsgTpgAmY7PhCfg==")
+        );
+    }
+
     #[tokio::test]
     async fn use_settings_rejects_too_large() {
         let builder = CopyTaskBuilder::default();
@@ -868,7 +1379,51 @@ pub(crate) mod test {
             .use_settings(destination, &destination_copy, &state)
             .await;
 
-        assert!(result.is_err());
+        assert!(matches!(result, Err(PlanningError(_))));
+    }
+
+    #[tokio::test]
+    async fn use_settings_default_checksum() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        fs::write(&source, b"test").await?;
+
+        let destination = Provider::try_from("s3://bucket/key")?;
+        let destination_copy = TestDestination {
+            max_object_size: 100,
+        };
+        let state = CopyState::new(4, None, None);
+
+        let (_, settings) = CopyTaskBuilder::default()
+            .with_source(source.to_string_lossy().to_string())
+            .set_default_checksum(Some(Ctx::from_str("crc32c")?))
+            .use_settings(destination.clone(), &destination_copy, &state)
+            .await?;
+        let decisions = settings.decisions();
+        assert_eq!(
+            decisions
+                .iter()
+                .map(|decision| (decision.kind(), decision.choice()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DecisionKind::PartSize, "single part"),
+                (DecisionKind::Checksum, "crc32c")
+            ]
+        );
+        assert!(
+            decisions[1]
+                .reason()
+                .contains("configured default checksum")
+        );
+        assert_eq!(settings.into_inner().1, Ctx::from_str("crc32c")?);
+
+        let (_, settings) = CopyTaskBuilder::default()
+            .with_source(source.to_string_lossy().to_string())
+            .use_settings(destination, &destination_copy, &state)
+            .await?;
+        assert_eq!(settings.into_inner().1, Ctx::default());
+
+        Ok(())
     }
 
     #[test]
@@ -904,12 +1459,21 @@ pub(crate) mod test {
             object_size,
             concurrency: 4,
             state: CopyState::new(object_size, None, None),
+            differential: None,
+            part_validation: None,
             // Exercise the concurrent (non-ordered) upload branch.
             ordered_upload: false,
             bytes_transferred: 0,
+            bytes_reused: 0,
+            content_md5_validated: false,
+            checksum_rejected: false,
+            decisions: vec![],
             n_retries: 0,
+            n_download_retries: 0,
             recoverable_errors: HashSet::new(),
-            pb: None,
+            progress: vec![],
+            cancellation_token: Default::default(),
+            upload_id: None,
         };
 
         let uploaded_clone = uploaded.clone();
@@ -978,6 +1542,209 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_copy_cancelled() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+        fs::write(&source, b"test").await?;
+
+        let cancellation_token = CancellationToken::new();
+        let copy = CopyTaskBuilder::default()
+            .with_concurrency(10)
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .with_cancellation_token(cancellation_token.clone())
+            .build()
+            .await?;
+
+        cancellation_token.cancel();
+        let err = copy.run().await.err().unwrap();
+
+        assert!(matches!(err.error, Cancelled(_)));
+        assert_eq!(err.task.bytes_transferred(), 0);
+        assert!(!destination.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_progress() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+        fs::write(&source, b"aaaabbbbccccdd").await?;
+
+        let progress = Arc::new(RecordProgress::default());
+        CopyTaskBuilder::default()
+            .with_concurrency(10)
+            .with_copy_mode(CopyMode::DownloadUpload)
+            .with_multipart_threshold(Some(0))
+            .with_part_size(Some(4))
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .with_progress(progress.clone())
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *progress.phases.lock().unwrap(),
+            vec![Phase::Copying, Phase::Completing, Phase::Finished]
+        );
+        let mut parts = progress.parts.lock().unwrap().clone();
+        parts.sort_unstable();
+        assert_eq!(parts, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+        assert_eq!(progress.bytes.lock().unwrap().last(), Some(&(14, 14)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_differential() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+
+        fs::write(&source, b"aaaabbbbccccdd").await?;
+        fs::write(&destination, b"aaaaxxxxccccddeeee").await?;
+
+        let copy = CopyTaskBuilder::default()
+            .with_concurrency(10)
+            .with_copy_mode(CopyMode::DownloadUpload)
+            .with_multipart_threshold(Some(0))
+            .with_part_size(Some(4))
+            .with_differential(true)
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(copy.bytes_transferred(), 4);
+        assert_eq!(copy.bytes_reused(), 10);
+        assert_eq!(fs::read(destination).await?, b"aaaabbbbccccdd");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_download_retries() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+
+        fs::write(&source, b"aaaabbbbccccdd").await?;
+
+        let copy = CopyTaskBuilder::default()
+            .with_concurrency(10)
+            .with_copy_mode(CopyMode::DownloadUpload)
+            .with_multipart_threshold(Some(0))
+            .with_part_size(Some(4))
+            .with_download_retries(Some(2))
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(copy.bytes_transferred(), 14);
+        assert_eq!(copy.n_download_retries(), 0);
+        assert_eq!(fs::read(destination).await?, b"aaaabbbbccccdd");
+
+        Ok(())
+    }
+
+    /// A source which returns corrupted data the first time part 2 is downloaded.
+    #[derive(Clone)]
+    struct CorruptSource {
+        inner: Box<dyn ObjectCopy + Send + Sync>,
+        corrupted: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectCopy for CorruptSource {
+        async fn copy(
+            &self,
+            multi_part: Option<MultiPartOptions>,
+            state: &CopyState,
+        ) -> error::Result<CopyResult> {
+            self.inner.copy(multi_part, state).await
+        }
+
+        async fn download(
+            &self,
+            multi_part: Option<MultiPartOptions>,
+        ) -> error::Result<crate::io::copy::CopyContent> {
+            let part_number = multi_part.as_ref().and_then(|options| options.part_number);
+            if part_number != Some(2) || self.corrupted.swap(true, Ordering::SeqCst) {
+                return self.inner.download(multi_part).await;
+            }
+
+            let inner = self.inner.clone();
+            crate::io::copy::CopyContent::builder(Box::new(&b"x"[..]))
+                .with_reopen(move || {
+                    let (inner, multi_part) = (inner.clone(), multi_part.clone());
+                    async move { inner.download(multi_part).await }
+                })
+                .build()
+        }
+
+        async fn upload(
+            &self,
+            data: crate::io::copy::CopyContent,
+            multi_part: Option<MultiPartOptions>,
+            state: &CopyState,
+        ) -> error::Result<CopyResult> {
+            self.inner.upload(data, multi_part, state).await
+        }
+
+        fn limits(&self) -> ProviderLimits {
+            self.inner.limits()
+        }
+
+        async fn initialize_state(&self) -> error::Result<CopyState> {
+            self.inner.initialize_state().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_download_retries_corrupted_part() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let destination = tmp.path().join("destination");
+
+        fs::write(&source, b"aaaabbbbccccdd").await?;
+
+        let mut task = CopyTaskBuilder::default()
+            .with_concurrency(10)
+            .with_copy_mode(CopyMode::DownloadUpload)
+            .with_multipart_threshold(Some(0))
+            .with_part_size(Some(4))
+            .with_download_retries(Some(2))
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .build()
+            .await?;
+        task.source_copy = Box::new(CorruptSource {
+            inner: task.source_copy.clone(),
+            corrupted: Default::default(),
+        });
+        let copy = task.run().await.unwrap();
+
+        assert_eq!(copy.n_download_retries(), 1);
+        assert_eq!(copy.n_retries(), 0);
+        assert_eq!(fs::read(destination).await?, b"aaaabbbbccccdd");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn copy_settings() -> Result<()> {
         let test_file = TestFileBuilder::new()?.generate_test_defaults()?;
@@ -0,0 +1,284 @@
+//! Compare the stats output of two runs of a command, such as recurring copies.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use crate::stats::{serialize_f64, serialize_option_f64};
+use serde::{Deserialize, Serialize};
+use serde_json::{Deserializer, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tokio::fs;
+
+/// The default fraction that throughput can decrease by before it is reported as a regression.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 0.1;
+
+/// A summary of the stats output of one run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// The file that the stats were read from.
+    pub(crate) path: String,
+    /// The number of stats entries, e.g. one per copied object.
+    pub(crate) objects: u64,
+    /// The number of entries that completed without being skipped.
+    pub(crate) completed: u64,
+    /// The number of entries that were skipped.
+    pub(crate) skipped: u64,
+    /// The number of entries with an unrecoverable error.
+    pub(crate) failed: u64,
+    /// The total bytes transferred.
+    pub(crate) bytes_transferred: u64,
+    /// The total time taken in seconds.
+    #[serde(serialize_with = "serialize_f64")]
+    pub(crate) elapsed_seconds: f64,
+    /// The bytes transferred per second, if any time was taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_option_f64")]
+    pub(crate) throughput: Option<f64>,
+    /// The distinct unrecoverable and API errors.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) errors: BTreeSet<String>,
+}
+
+impl RunSummary {
+    /// Read the stats output of a run. This accepts one JSON stats block per line, pretty
+    /// printed stats blocks, or a JSON array of stats blocks.
+    pub async fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read(path).await?;
+        let mut entries = vec![];
+        for value in Deserializer::from_slice(&contents).into_iter::<Value>() {
+            match value.map_err(|err| {
+                ParseError(format!("invalid stats in {}: {}", path.display(), err))
+            })? {
+                Value::Array(values) => entries.extend(values),
+                value => entries.push(value),
+            }
+        }
+
+        Ok(Self::from_entries(path.display().to_string(), &entries))
+    }
+
+    /// Summarise stats entries.
+    pub fn from_entries(path: String, entries: &[Value]) -> Self {
+        let mut summary = Self {
+            path,
+            ..Default::default()
+        };
+
+        for entry in entries {
+            summary.objects += 1;
+            summary.bytes_transferred += entry["bytes_transferred"].as_u64().unwrap_or_default();
+            summary.elapsed_seconds += entry["elapsed_seconds"].as_f64().unwrap_or_default();
+
+            let error = entry
+                .get("unrecoverable_error")
+                .filter(|error| !error.is_null());
+            if let Some(error) = error {
+                summary.failed += 1;
+                summary.errors.insert(Self::format_error(error));
+            } else if entry["skipped"].as_bool().unwrap_or_default() {
+                summary.skipped += 1;
+            } else {
+                summary.completed += 1;
+            }
+
+            if let Some(api_errors) = entry["api_errors"].as_array() {
+                summary
+                    .errors
+                    .extend(api_errors.iter().map(Self::format_error));
+            }
+        }
+
+        summary.throughput = (summary.elapsed_seconds > 0.0)
+            .then(|| summary.bytes_transferred as f64 / summary.elapsed_seconds);
+        summary
+    }
+
+    /// Format an error from the stats, e.g. `{"CopyError": "message"}` as
+    /// `CopyError: message`. API errors are formatted by their call and code only, because
+    /// their messages usually contain details such as request ids that differ between runs.
+    fn format_error(error: &Value) -> String {
+        match error {
+            Value::String(error) => error.to_string(),
+            Value::Object(error) if error.contains_key("code") && error.contains_key("call") => {
+                format!(
+                    "{}: {}",
+                    error["call"].as_str().unwrap_or_default(),
+                    error["code"].as_str().unwrap_or_default()
+                )
+            }
+            Value::Object(error) if error.len() == 1 => {
+                let (kind, message) = error.iter().next().expect("one entry");
+                match message {
+                    Value::String(message) => format!("{}: {}", kind, message),
+                    message => format!("{}: {}", kind, message),
+                }
+            }
+            error => error.to_string(),
+        }
+    }
+
+    /// The number of objects by outcome.
+    fn counts(&self) -> BTreeMap<&'static str, u64> {
+        BTreeMap::from([
+            ("objects", self.objects),
+            ("completed", self.completed),
+            ("skipped", self.skipped),
+            ("failed", self.failed),
+        ])
+    }
+}
+
+/// The differences between two runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunDiff {
+    /// The earlier run.
+    pub(crate) before: RunSummary,
+    /// The later run.
+    pub(crate) after: RunSummary,
+    /// The relative change in throughput, e.g. `-0.25` if the later run was 25% slower.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_option_f64")]
+    pub(crate) throughput_change: Option<f64>,
+    /// Whether throughput decreased by more than the regression threshold.
+    pub(crate) throughput_regression: bool,
+    /// Errors in the later run that did not occur in the earlier run.
+    pub(crate) new_errors: Vec<String>,
+    /// Errors in the earlier run that no longer occur in the later run.
+    pub(crate) resolved_errors: Vec<String>,
+    /// The change in each object count that differs between the runs.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) changed_counts: BTreeMap<String, i64>,
+}
+
+impl RunDiff {
+    /// Compare two runs. Throughput is a regression if it decreased by more than `threshold`,
+    /// as a fraction of the earlier throughput.
+    pub fn new(before: RunSummary, after: RunSummary, threshold: f64) -> Self {
+        let throughput_change = match (before.throughput, after.throughput) {
+            (Some(before), Some(after)) if before > 0.0 => Some((after - before) / before),
+            _ => None,
+        };
+        let throughput_regression = throughput_change.is_some_and(|change| change < -threshold);
+
+        let new_errors = after.errors.difference(&before.errors).cloned().collect();
+        let resolved_errors = before.errors.difference(&after.errors).cloned().collect();
+
+        let after_counts = after.counts();
+        let changed_counts = before
+            .counts()
+            .into_iter()
+            .filter_map(|(name, count)| {
+                let change = after_counts[name] as i64 - count as i64;
+                (change != 0).then(|| (name.to_string(), change))
+            })
+            .collect();
+
+        Self {
+            before,
+            after,
+            throughput_change,
+            throughput_regression,
+            new_errors,
+            resolved_errors,
+            changed_counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn read_stats() -> Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("run.json");
+        let lines = [
+            json!({"elapsed_seconds": 1.0, "bytes_transferred": 100, "skipped": false}),
+            json!({"elapsed_seconds": 1.0, "bytes_transferred": 0, "skipped": true}),
+            json!({
+                "elapsed_seconds": 0.0,
+                "bytes_transferred": 0,
+                "skipped": false,
+                "unrecoverable_error": {"CopyError": "source does not exist"}
+            }),
+        ];
+        fs::write(
+            &path,
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+        .await?;
+
+        let summary = RunSummary::read(&path).await?;
+        assert_eq!(summary.objects, 3);
+        assert_eq!(summary.completed, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.throughput, Some(50.0));
+        assert_eq!(
+            summary.errors,
+            BTreeSet::from(["CopyError: source does not exist".to_string()])
+        );
+
+        // Pretty printed stats and arrays are also accepted.
+        fs::write(&path, serde_json::to_string_pretty(&json!(lines))?).await?;
+        assert_eq!(RunSummary::read(&path).await?.objects, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_runs() {
+        let before = RunSummary::from_entries(
+            "a".to_string(),
+            &[json!({"elapsed_seconds": 1.0, "bytes_transferred": 100, "skipped": false})],
+        );
+        let after = RunSummary::from_entries(
+            "b".to_string(),
+            &[
+                json!({
+                    "elapsed_seconds": 2.0,
+                    "bytes_transferred": 100,
+                    "skipped": false,
+                    "api_errors": [
+                        {"code": "SlowDown", "call": "UploadPart", "message": "request id 1"},
+                    ]
+                }),
+                json!({
+                    "elapsed_seconds": 0.0,
+                    "skipped": false,
+                    "unrecoverable_error": {"CopyError": "failed"}
+                }),
+            ],
+        );
+
+        let diff = RunDiff::new(before, after, DEFAULT_REGRESSION_THRESHOLD);
+        assert_eq!(diff.throughput_change, Some(-0.5));
+        assert!(diff.throughput_regression);
+        assert_eq!(
+            diff.new_errors,
+            vec![
+                "CopyError: failed".to_string(),
+                "UploadPart: SlowDown".to_string()
+            ]
+        );
+        assert!(diff.resolved_errors.is_empty());
+        assert_eq!(
+            diff.changed_counts,
+            BTreeMap::from([("failed".to_string(), 1), ("objects".to_string(), 1)])
+        );
+
+        let diff = RunDiff::new(diff.after.clone(), diff.after, DEFAULT_REGRESSION_THRESHOLD);
+        assert_eq!(diff.throughput_change, Some(0.0));
+        assert!(!diff.throughput_regression);
+        assert!(diff.changed_counts.is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+//! Inspect the configuration of a destination before copying to it.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::{ApiError, Result};
+use crate::io::{Provider, S3Client};
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Error codes returned when a bucket setting has not been configured, rather than when it
+/// could not be read.
+const NOT_CONFIGURED_CODES: [&str; 3] = [
+    "ServerSideEncryptionConfigurationNotFoundError",
+    "OwnershipControlsNotFoundError",
+    "NoSuchPublicAccessBlockConfiguration",
+];
+
+/// The default encryption of a bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DefaultEncryption {
+    /// The server-side encryption algorithm, e.g. `AES256` or `aws:kms`.
+    pub(crate) algorithm: String,
+    /// The KMS key used for `aws:kms` encryption.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) kms_key_id: Option<String>,
+    /// Whether an S3 Bucket Key is used to reduce KMS requests.
+    pub(crate) bucket_key_enabled: bool,
+}
+
+/// The public access block settings of a bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublicAccessBlock {
+    /// Whether public ACLs are rejected.
+    pub(crate) block_public_acls: bool,
+    /// Whether public ACLs are ignored.
+    pub(crate) ignore_public_acls: bool,
+    /// Whether public bucket policies are rejected.
+    pub(crate) block_public_policy: bool,
+    /// Whether access is restricted for buckets with public policies.
+    pub(crate) restrict_public_buckets: bool,
+}
+
+/// The configuration of a bucket that affects how copied objects are stored. Settings that
+/// are not configured, or that could not be read, are `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BucketConfig {
+    /// The name of the bucket.
+    pub(crate) bucket: String,
+    /// The versioning status, either `Enabled`, `Suspended` or `Disabled` if it was never
+    /// enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) versioning: Option<String>,
+    /// The default encryption applied to new objects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) default_encryption: Option<DefaultEncryption>,
+    /// The object ownership setting, e.g. `BucketOwnerEnforced` when ACLs are disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) object_ownership: Option<String>,
+    /// The public access block settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) public_access_block: Option<PublicAccessBlock>,
+}
+
+/// Build a doctor task.
+#[derive(Debug, Default)]
+pub struct DoctorTaskBuilder {
+    destination: Option<String>,
+    client: Option<S3Client>,
+}
+
+impl DoctorTaskBuilder {
+    /// Set the destination to inspect.
+    pub fn with_destination(mut self, destination: String) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Set the client used to access the destination.
+    pub fn with_client(mut self, client: S3Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the doctor task.
+    pub fn build(self) -> Result<DoctorTask> {
+        let destination = self
+            .destination
+            .ok_or_else(|| ParseError("a destination is required".to_string()))?;
+        let client = self
+            .client
+            .ok_or_else(|| ParseError("an S3 client is required".to_string()))?;
+
+        let bucket = match Provider::try_from(destination.as_str())? {
+            Provider::S3 { bucket, .. } | Provider::Gcs { bucket, .. } => Some(bucket),
+            Provider::File { .. }
+            | Provider::Azure { .. }
+            | Provider::Http { .. }
+            | Provider::Sftp { .. }
+            | Provider::Gds { .. }
+            | Provider::Swift { .. }
+            | Provider::WebDav { .. }
+            | Provider::Custom { .. } => None,
+        };
+
+        Ok(DoctorTask {
+            client,
+            bucket,
+            api_errors: HashSet::new(),
+        })
+    }
+}
+
+/// Reads the configuration of a destination bucket.
+#[derive(Debug)]
+pub struct DoctorTask {
+    client: S3Client,
+    bucket: Option<String>,
+    api_errors: HashSet<ApiError>,
+}
+
+impl DoctorTask {
+    /// Read the configuration of the destination bucket. Returns `None` for local
+    /// and Azure destinations. Settings that cannot be read are recorded as API errors.
+    pub async fn bucket_config(&mut self) -> Option<BucketConfig> {
+        let bucket = self.bucket.clone()?;
+
+        let versioning = self
+            .client
+            .get_bucket_versioning(|b| b.bucket(&bucket))
+            .await
+            .map(|output| {
+                Some(
+                    output
+                        .status()
+                        .map(|status| status.as_str())
+                        .unwrap_or("Disabled")
+                        .to_string(),
+                )
+            });
+        let versioning = self.record(versioning);
+
+        let default_encryption = self
+            .client
+            .get_bucket_encryption(|b| b.bucket(&bucket))
+            .await
+            .map(|output| {
+                let rule = output
+                    .server_side_encryption_configuration()?
+                    .rules()
+                    .first()?;
+                let default = rule.apply_server_side_encryption_by_default()?;
+
+                Some(DefaultEncryption {
+                    algorithm: default.sse_algorithm().as_str().to_string(),
+                    kms_key_id: default.kms_master_key_id().map(str::to_string),
+                    bucket_key_enabled: rule.bucket_key_enabled().unwrap_or_default(),
+                })
+            });
+        let default_encryption = self.record(default_encryption);
+
+        let object_ownership = self
+            .client
+            .get_bucket_ownership_controls(|b| b.bucket(&bucket))
+            .await
+            .map(|output| {
+                output
+                    .ownership_controls()?
+                    .rules()
+                    .first()
+                    .map(|rule| rule.object_ownership().as_str().to_string())
+            });
+        let object_ownership = self.record(object_ownership);
+
+        let public_access_block = self
+            .client
+            .get_public_access_block(|b| b.bucket(&bucket))
+            .await
+            .map(|output| {
+                output
+                    .public_access_block_configuration()
+                    .map(|config| PublicAccessBlock {
+                        block_public_acls: config.block_public_acls().unwrap_or_default(),
+                        ignore_public_acls: config.ignore_public_acls().unwrap_or_default(),
+                        block_public_policy: config.block_public_policy().unwrap_or_default(),
+                        restrict_public_buckets: config
+                            .restrict_public_buckets()
+                            .unwrap_or_default(),
+                    })
+            });
+        let public_access_block = self.record(public_access_block);
+
+        Some(BucketConfig {
+            bucket,
+            versioning,
+            default_encryption,
+            object_ownership,
+            public_access_block,
+        })
+    }
+
+    /// The errors from settings that could not be read.
+    pub fn api_errors(&self) -> HashSet<ApiError> {
+        self.api_errors.clone()
+    }
+
+    /// Record the error from reading a setting, unless the setting is not configured.
+    fn record<T, E>(&mut self, result: std::result::Result<Option<T>, SdkError<E>>) -> Option<T>
+    where
+        E: ProvideErrorMetadata,
+        ApiError: for<'a> From<&'a SdkError<E>>,
+    {
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                if !err
+                    .code()
+                    .is_some_and(|code| NOT_CONFIGURED_CODES.contains(&code))
+                {
+                    self.api_errors.insert(ApiError::from(&err));
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::get_bucket_encryption::GetBucketEncryptionError;
+    use aws_sdk_s3::operation::get_bucket_ownership_controls::GetBucketOwnershipControlsOutput;
+    use aws_sdk_s3::operation::get_bucket_versioning::GetBucketVersioningOutput;
+    use aws_sdk_s3::operation::get_public_access_block::GetPublicAccessBlockError;
+    use aws_sdk_s3::types::{
+        BucketVersioningStatus, ObjectOwnership, OwnershipControls, OwnershipControlsRule,
+    };
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use aws_smithy_types::error::ErrorMetadata;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn bucket_config_records_settings_and_errors() -> Result<()> {
+        let versioning = mock!(Client::get_bucket_versioning).then_output(|| {
+            GetBucketVersioningOutput::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build()
+        });
+        let encryption = mock!(Client::get_bucket_encryption).then_error(|| {
+            GetBucketEncryptionError::generic(
+                ErrorMetadata::builder()
+                    .code("ServerSideEncryptionConfigurationNotFoundError")
+                    .build(),
+            )
+        });
+        let ownership = mock!(Client::get_bucket_ownership_controls).then_output(|| {
+            GetBucketOwnershipControlsOutput::builder()
+                .ownership_controls(
+                    OwnershipControls::builder()
+                        .rules(
+                            OwnershipControlsRule::builder()
+                                .object_ownership(ObjectOwnership::BucketOwnerEnforced)
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+        });
+        let public_access_block = mock!(Client::get_public_access_block).then_error(|| {
+            GetPublicAccessBlockError::generic(
+                ErrorMetadata::builder().code("AccessDenied").build(),
+            )
+        });
+
+        let client = mock_client!(
+            aws_sdk_s3,
+            RuleMode::MatchAny,
+            &[&versioning, &encryption, &ownership, &public_access_block]
+        );
+        let mut task = DoctorTaskBuilder::default()
+            .with_destination("s3://bucket/key".to_string())
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .build()?;
+
+        let config = task.bucket_config().await;
+        assert_eq!(
+            config,
+            Some(BucketConfig {
+                bucket: "bucket".to_string(),
+                versioning: Some("Enabled".to_string()),
+                default_encryption: None,
+                object_ownership: Some("BucketOwnerEnforced".to_string()),
+                public_access_block: None,
+            })
+        );
+
+        let api_errors = task.api_errors();
+        assert_eq!(api_errors.len(), 1);
+        assert!(api_errors.iter().all(ApiError::is_access_denied));
+
+        Ok(())
+    }
+}
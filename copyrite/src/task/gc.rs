@@ -0,0 +1,258 @@
+//! Find and remove sums files whose target objects no longer exist.
+//!
+
+use crate::checksum::file::{SUMS_FILE_ENDING, SumsFile};
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use crate::io::{Provider, S3Client};
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Build a sums garbage collection task.
+#[derive(Debug, Default)]
+pub struct GcTaskBuilder {
+    location: Option<String>,
+    client: Option<S3Client>,
+    delete: bool,
+}
+
+impl GcTaskBuilder {
+    /// Set the S3 prefix or local directory to scan for sums files.
+    pub fn with_location(mut self, location: String) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Set the client, which is required for S3 prefixes.
+    pub fn set_client(mut self, client: Option<S3Client>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Delete the orphaned sums files rather than only listing them.
+    pub fn with_delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Build the garbage collection task.
+    pub fn build(self) -> Result<GcTask> {
+        let location = self
+            .location
+            .ok_or_else(|| ParseError("a location is required to collect sums".to_string()))?;
+        let provider = Provider::try_from(location.as_str())?;
+        if provider.is_s3() && self.client.is_none() {
+            return Err(ParseError(
+                "an S3 client is required to collect sums under an S3 prefix".to_string(),
+            ));
+        }
+
+        Ok(GcTask {
+            provider,
+            client: self.client,
+            delete: self.delete,
+        })
+    }
+}
+
+/// Scans a prefix or directory for sums files that no longer have a target object.
+#[derive(Debug)]
+pub struct GcTask {
+    provider: Provider,
+    client: Option<S3Client>,
+    delete: bool,
+}
+
+impl GcTask {
+    /// Find the orphaned sums files, deleting them if configured. Returns the location of each
+    /// orphaned sums file.
+    pub async fn run(self) -> Result<Vec<String>> {
+        match (&self.provider, &self.client) {
+            (Provider::S3 { bucket, key } | Provider::Gcs { bucket, key }, Some(client)) => {
+                self.collect_s3(client, bucket, key).await
+            }
+            (Provider::File { file }, _) => self.collect_files(Path::new(file)).await,
+            (Provider::Azure { .. }, _) => Err(ParseError(
+                "collecting sums under an Azure prefix is not supported".to_string(),
+            )),
+            (Provider::Http { .. }, _) => Err(ParseError(
+                "collecting sums under an HTTP location is not supported".to_string(),
+            )),
+            (Provider::Sftp { .. }, _) => Err(ParseError(
+                "collecting sums under an SFTP location is not supported".to_string(),
+            )),
+            (Provider::Gds { .. }, _) => Err(ParseError(
+                "collecting sums under a GDS location is not supported".to_string(),
+            )),
+            (Provider::Swift { .. }, _) => Err(ParseError(
+                "collecting sums under a Swift location is not supported".to_string(),
+            )),
+            (Provider::WebDav { .. }, _) => Err(ParseError(
+                "collecting sums under a WebDAV location is not supported".to_string(),
+            )),
+            (Provider::Custom { scheme, .. }, _) => Err(ParseError(format!(
+                "collecting sums under a {} location is not supported",
+                scheme
+            ))),
+            _ => Err(ParseError(
+                "an S3 client is required to collect sums under an S3 prefix".to_string(),
+            )),
+        }
+    }
+
+    /// Find orphaned sums files under an S3 prefix.
+    async fn collect_s3(
+        &self,
+        client: &S3Client,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>> {
+        let listed = client.list_objects(bucket, prefix).await?;
+        let keys: HashSet<&str> = listed.iter().filter_map(|object| object.key()).collect();
+
+        let mut orphaned = vec![];
+        for sums_key in keys.iter().filter(|key| key.ends_with(SUMS_FILE_ENDING)) {
+            let target = SumsFile::format_target_file(sums_key);
+            if keys.contains(target.as_str()) {
+                continue;
+            }
+            // A target outside of the listed prefix, e.g. when the prefix ends part way through
+            // the `.sums` ending, is not listed so it must be checked directly.
+            if !target.starts_with(prefix) && Self::s3_exists(client, bucket, &target).await? {
+                continue;
+            }
+
+            if self.delete {
+                client
+                    .delete_object(|b| b.bucket(bucket).key(*sums_key))
+                    .await?;
+            }
+            orphaned.push(if self.provider.is_gcs() {
+                Provider::format_gcs(bucket, sums_key)
+            } else {
+                Provider::format_s3(bucket, sums_key)
+            });
+        }
+
+        orphaned.sort();
+        Ok(orphaned)
+    }
+
+    /// Check whether an S3 object exists.
+    async fn s3_exists(client: &S3Client, bucket: &str, key: &str) -> Result<bool> {
+        match client.head_object(|b| b.bucket(bucket).key(key)).await {
+            Ok(_) => Ok(true),
+            Err(err) if matches!(err.as_service_error(), Some(HeadObjectError::NotFound(_))) => {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Find orphaned sums files in a local directory and its subdirectories.
+    async fn collect_files(&self, directory: &Path) -> Result<Vec<String>> {
+        let mut orphaned = vec![];
+        let mut directories = vec![directory.to_path_buf()];
+
+        while let Some(current) = directories.pop() {
+            let mut entries = fs::read_dir(&current).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                let path = entry.path();
+
+                if file_type.is_dir() {
+                    directories.push(path);
+                } else if file_type.is_file()
+                    && path.to_string_lossy().ends_with(SUMS_FILE_ENDING)
+                    && !PathBuf::from(SumsFile::format_target_file(&path.to_string_lossy()))
+                        .exists()
+                {
+                    if self.delete {
+                        fs::remove_file(&path).await?;
+                    }
+                    orphaned.push(path.to_string_lossy().to_string());
+                }
+            }
+        }
+
+        orphaned.sort();
+        Ok(orphaned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::delete_object::DeleteObjectOutput;
+    use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+    use aws_sdk_s3::types::Object;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn gc_files() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::create_dir(tmp.path().join("dir")).await?;
+        fs::write(tmp.path().join("a"), "a").await?;
+        fs::write(tmp.path().join("a.sums"), "{}").await?;
+        fs::write(tmp.path().join("b.sums"), "{}").await?;
+        fs::write(tmp.path().join("dir/c.sums"), "{}").await?;
+
+        let build = |delete| {
+            GcTaskBuilder::default()
+                .with_location(tmp.path().to_string_lossy().to_string())
+                .with_delete(delete)
+                .build()
+        };
+
+        let expected = vec![
+            tmp.path().join("b.sums").to_string_lossy().to_string(),
+            tmp.path().join("dir/c.sums").to_string_lossy().to_string(),
+        ];
+        assert_eq!(build(false)?.run().await?, expected);
+        assert!(tmp.path().join("b.sums").exists());
+
+        assert_eq!(build(true)?.run().await?, expected);
+        assert!(!tmp.path().join("b.sums").exists());
+        assert!(!tmp.path().join("dir/c.sums").exists());
+        assert!(tmp.path().join("a.sums").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gc_s3() -> Result<()> {
+        let list = mock!(Client::list_objects_v2).then_output(|| {
+            ListObjectsV2Output::builder()
+                .contents(Object::builder().key("prefix/a").build())
+                .contents(Object::builder().key("prefix/a.sums").build())
+                .contents(Object::builder().key("prefix/b.sums").build())
+                .is_truncated(false)
+                .build()
+        });
+        let delete = mock!(Client::delete_object)
+            .match_requests(|req| {
+                req.bucket() == Some("bucket") && req.key() == Some("prefix/b.sums")
+            })
+            .then_output(|| DeleteObjectOutput::builder().build());
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&list, &delete]);
+
+        let orphaned = GcTaskBuilder::default()
+            .with_location("s3://bucket/prefix/".to_string())
+            .set_client(Some(S3Client::new(Arc::new(client), false, false)))
+            .with_delete(true)
+            .build()?
+            .run()
+            .await?;
+
+        assert_eq!(orphaned, vec!["s3://bucket/prefix/b.sums".to_string()]);
+        assert_eq!(delete.num_calls(), 1);
+
+        Ok(())
+    }
+}
@@ -3,24 +3,34 @@
 
 use crate::checksum::Ctx;
 use crate::checksum::file::{Checksum, SumsFile};
-use crate::error::Error::GenerateError;
+use crate::checksum::parallel::{
+    PARALLEL_CRC_THRESHOLD, is_parallel_crc, parallel_crc, parallel_crc_range,
+};
+use crate::error::Error::{Cancelled, CopyError, GenerateError};
 use crate::error::{ApiError, Error, Result};
-use crate::io::S3Client;
-use crate::io::sums::channel::ChannelReader;
+use crate::io::sums::channel::{ChannelReader, ChunkSize};
 use crate::io::sums::{ObjectSums, ObjectSumsBuilder, SharedReader};
+use crate::io::{Provider, S3Client};
+use crate::stats::ChecksumPair;
 use crate::task::check::{CheckObjects, SumsKey};
-use crate::task::generate::Task::{ChecksumTask, ReadTask};
+use crate::task::generate::Task::{ChecksumTask, CrcTask, ReadTask};
+use crate::task::progress::{Phase, Progress};
 use futures_util::future::join_all;
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Debug, Formatter};
+use std::fs;
 use std::result;
+use std::sync::Arc;
+use tokio::select;
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// Define the kind of task that is running.
 #[derive(Debug)]
 pub enum Task {
-    ReadTask(u64),
-    ChecksumTask(Box<(Ctx, Vec<u8>)>),
+    ReadTask(u64, Option<usize>, Option<usize>),
+    CrcTask(Vec<(Ctx, Vec<u8>)>, u64),
+    ChecksumTask(Box<(Ctx, Vec<u8>, Option<String>)>),
 }
 
 /// Build a generate task.
@@ -32,8 +42,15 @@ pub struct GenerateTaskBuilder {
     ctxs: Vec<Ctx>,
     reader: Option<Box<dyn SharedReader + Send>>,
     capacity: usize,
+    chunk_size: ChunkSize,
+    max_buffer_bytes: Option<usize>,
     write: bool,
     client: Option<S3Client>,
+    file_size: Option<u64>,
+    incremental: bool,
+    progress: Vec<Arc<dyn Progress>>,
+    cancellation_token: CancellationToken,
+    deadline: CancellationToken,
 }
 
 impl GenerateTaskBuilder {
@@ -67,12 +84,40 @@ impl GenerateTaskBuilder {
         self
     }
 
+    /// Set the size of the data read from a reader set with `with_reader`. This allows
+    /// contexts that depend on the size, such as part-number AWS contexts, to be used with
+    /// readers.
+    pub fn with_file_size(mut self, file_size: u64) -> Self {
+        self.file_size = Some(file_size);
+        self
+    }
+
+    /// Save the intermediate state of checksums in the sums file, and resume from a saved
+    /// state if the file has only been appended to since it was last generated.
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
     /// Set the reader capacity.
     pub fn with_capacity(mut self, capacity: usize) -> Self {
         self.capacity = capacity;
         self
     }
 
+    /// Set the chunk size of the reader. This does not apply to readers set with `with_reader`.
+    pub fn with_chunk_size(mut self, chunk_size: ChunkSize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Limit the bytes that the reader buffers for each checksum. This does not apply to readers
+    /// set with `with_reader`.
+    pub fn set_max_buffer_bytes(mut self, max_buffer_bytes: Option<usize>) -> Self {
+        self.max_buffer_bytes = max_buffer_bytes;
+        self
+    }
+
     /// Set the S3 client to use.
     pub fn with_client(self, client: S3Client) -> Self {
         self.set_client(Some(client))
@@ -95,6 +140,28 @@ impl GenerateTaskBuilder {
         self
     }
 
+    /// Register an observer that is notified of the progress of the task. This can be called
+    /// multiple times to register multiple observers.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress.push(progress);
+        self
+    }
+
+    /// Set the token used to cancel reading the object while the task is running.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Set the token which is cancelled when the deadline of the command is reached. When
+    /// writing incremental sums for an object read by the task, reading stops at the deadline
+    /// and the checksums of the data read so far are written, so that the next incremental run
+    /// continues from them. Otherwise, the task is cancelled without writing a sums file.
+    pub fn with_deadline(mut self, deadline: CancellationToken) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
     /// Build a generate task.
     pub async fn build(mut self) -> Result<GenerateTask> {
         let mut sums = ObjectSumsBuilder::default()
@@ -108,7 +175,7 @@ impl GenerateTaskBuilder {
             None
         };
 
-        let mode = if self.overwrite {
+        let mut mode = if self.overwrite {
             OverwriteMode::Overwrite
         } else if self.verify {
             OverwriteMode::Verify
@@ -116,33 +183,232 @@ impl GenerateTaskBuilder {
             OverwriteMode::None
         };
 
-        let reader: Box<dyn SharedReader + Send> = if let Some(reader) = self.reader {
-            reader
-        } else {
-            let file_size = sums.file_size().await?;
+        // The file size must be known before hashing contexts that use part numbers.
+        let file_size = match self.reader {
+            Some(_) => self.file_size,
+            None => sums.file_size().await?,
+        };
+        if file_size.is_some() {
             self.ctxs
                 .iter_mut()
                 .for_each(|ctx| ctx.set_file_size(file_size));
-            let reader = sums.reader().await?;
+        } else if self.ctxs.iter().any(Ctx::is_part_number) {
+            // Streamed inputs such as named pipes do not have a known size.
+            return Err(GenerateError(format!(
+                "part number checksums require the input size, which is not known for {}",
+                self.input_file_name
+            )));
+        }
 
-            let reader = ChannelReader::new(reader, self.capacity);
-            Box::new(reader)
-        };
+        let local = Provider::try_from(self.input_file_name.as_str()).is_ok_and(|p| p.is_file());
+        // Progress can only be saved for readers that are created here, as other readers cannot
+        // be stopped at a chunk boundary.
+        let checkpoint = self.incremental && self.write && self.reader.is_none();
+        let mut ctxs = HashSet::from_iter(self.ctxs);
+        let mut offset = 0;
+        let mut reader = self.reader;
+        if self.incremental
+            && let Some(existing) = existing_output.as_ref()
+            && let (Some(existing_size), Some(file_size)) = (existing.size, file_size)
+            && existing_size != file_size
+        {
+            // The existing checksums no longer describe the file, so all of them are
+            // recomputed, continuing from their saved state if possible.
+            ctxs.extend(existing.checksums.keys().map(|ctx| {
+                let mut ctx = ctx.clone();
+                ctx.set_file_size(Some(file_size));
+                ctx
+            }));
+            mode = OverwriteMode::Overwrite;
+
+            // Only the appended bytes need to be read if the file has grown.
+            if file_size > existing_size
+                && let Some(resumed) = Self::resume(existing, &ctxs)?
+                && let Some(appended) = sums.reader_from(existing_size).await?
+            {
+                ctxs = resumed;
+                offset = existing_size;
+                reader = Some(Box::new(
+                    ChannelReader::new(appended, self.capacity)
+                        .with_chunk_size(self.chunk_size, local)
+                        .set_max_buffer_bytes(self.max_buffer_bytes)
+                        .with_stop(self.deadline.clone()),
+                ));
+            }
+        }
+
+        let mut checksums = Self::checksums_to_generate(&mode, existing_output.as_ref(), ctxs);
+
+        // CRC checksums of large local files are computed from ranges read on separate threads,
+        // which are then combined.
+        let mut tasks = vec![];
+        if let Some(file_size) = file_size
+            && let Some(path) = Self::parallel_crc_path(&self.input_file_name, file_size)
+            && reader.is_none()
+            && !self.incremental
+        {
+            let (crcs, rest): (HashSet<Ctx>, HashSet<Ctx>) = checksums
+                .into_iter()
+                .partition(|ctx| matches!(ctx, Ctx::Regular(ctx) if is_parallel_crc(ctx)));
+            checksums = rest;
 
-        let task = GenerateTask {
-            tasks: Default::default(),
+            if !crcs.is_empty() {
+                tasks.push(Self::spawn_parallel_crc(path, crcs, file_size));
+            }
+        }
+
+        // All other contexts, including those used for verification, share a single read of the
+        // object. The reader is consumed here so no other task can read the object again.
+        if !checksums.is_empty() {
+            let reader: Box<dyn SharedReader + Send> = match reader {
+                Some(reader) => reader,
+                None => {
+                    let reader = ChannelReader::new(sums.reader().await?, self.capacity)
+                        .with_chunk_size(self.chunk_size, local)
+                        .set_max_buffer_bytes(self.max_buffer_bytes);
+                    if checkpoint {
+                        Box::new(reader.with_stop(self.deadline.clone()))
+                    } else {
+                        Box::new(reader)
+                    }
+                }
+            };
+
+            tasks.extend(Self::spawn_tasks(reader, checksums, self.incremental));
+        }
+
+        Ok(GenerateTask {
+            tasks,
+            offset,
             overwrite: mode,
             existing_output,
-            reader: Some(reader),
             write: self.write,
             object_sums: sums,
             updated: false,
             output: Default::default(),
             checksums_generated: Default::default(),
-        };
+            reader_chunk_size: None,
+            buffer_high_water_bytes: None,
+            progress: self.progress,
+            cancellation_token: self.cancellation_token,
+            deadline: self.deadline,
+            checkpoint,
+            file_size,
+        })
+    }
+
+    /// Determine the checksums to generate from the requested checksums and existing sums.
+    fn checksums_to_generate(
+        mode: &OverwriteMode,
+        existing: Option<&SumsFile>,
+        mut checksums: HashSet<Ctx>,
+    ) -> HashSet<Ctx> {
+        match mode {
+            // If verifying, add existing checksums into the set that needs to be generated.
+            OverwriteMode::Verify => {
+                for name in existing.iter().flat_map(|file| file.checksums.keys()) {
+                    checksums.insert(name.clone());
+                }
+            }
+            // Otherwise, if unspecified, remove existing checksums to not re-compute them.
+            OverwriteMode::None => {
+                for name in existing.iter().flat_map(|file| file.checksums.keys()) {
+                    checksums.remove(name);
+                }
+            }
+            // If it's overwriting, just use the checksums as specified on the command line.
+            OverwriteMode::Overwrite => {}
+        }
+
+        checksums
+    }
+
+    /// Restore the contexts from the state saved in an existing sums file. Returns `None` if
+    /// any of the contexts do not have a saved state.
+    fn resume(existing: &SumsFile, ctxs: &HashSet<Ctx>) -> Result<Option<HashSet<Ctx>>> {
+        let mut resumed = HashSet::new();
+        for ctx in ctxs {
+            let Some(state) = existing.state.get(ctx) else {
+                return Ok(None);
+            };
+            resumed.insert(ctx.resume(state)?);
+        }
+
+        Ok(Some(resumed))
+    }
 
-        let task = task.add_tasks(HashSet::from_iter(self.ctxs))?;
-        Ok(task)
+    /// Get the path of a local file if it is large enough for its CRC checksums to be computed
+    /// in parallel.
+    fn parallel_crc_path(input_file_name: &str, file_size: u64) -> Option<String> {
+        if file_size < PARALLEL_CRC_THRESHOLD {
+            return None;
+        }
+
+        // Only regular files can be read at multiple offsets at the same time.
+        Provider::try_from(input_file_name)
+            .and_then(Provider::into_file)
+            .ok()
+            .filter(|file| fs::metadata(file).is_ok_and(|metadata| metadata.is_file()))
+    }
+
+    /// Spawn a task which computes CRC checksums of a local file in parallel.
+    fn spawn_parallel_crc(
+        path: String,
+        crcs: HashSet<Ctx>,
+        file_size: u64,
+    ) -> JoinHandle<Result<Task>> {
+        let crcs = crcs
+            .into_iter()
+            .filter_map(|ctx| match ctx {
+                Ctx::Regular(ctx) => Some(ctx),
+                _ => None,
+            })
+            .collect();
+
+        tokio::spawn(async move {
+            let digests =
+                parallel_crc(path, crcs, file_size, parallel_crc_range(file_size)).await?;
+            Ok(CrcTask(
+                digests
+                    .into_iter()
+                    .map(|(ctx, digest)| (Ctx::Regular(ctx), digest))
+                    .collect(),
+                file_size,
+            ))
+        })
+    }
+
+    /// Spawn a task for each checksum which consumes a stream from the reader, and a single
+    /// task which drives the reader. If `save_state` is set, the intermediate state of each
+    /// checksum is also returned.
+    fn spawn_tasks(
+        mut reader: Box<dyn SharedReader + Send>,
+        checksums: HashSet<Ctx>,
+        save_state: bool,
+    ) -> Vec<JoinHandle<Result<Task>>> {
+        let mut tasks: Vec<JoinHandle<Result<Task>>> = checksums
+            .into_iter()
+            .map(|mut ctx| {
+                let stream = reader.as_stream();
+                tokio::spawn(async move {
+                    ctx.consume(stream).await?;
+                    let state = if save_state { ctx.state() } else { None };
+                    let digest = ctx.finalize()?;
+                    Ok(ChecksumTask(Box::new((ctx, digest, state))))
+                })
+            })
+            .collect();
+
+        tasks.push(tokio::spawn(async move {
+            let size = reader.read_chunks().await?;
+            Ok(ReadTask(
+                size,
+                reader.chunk_size(),
+                reader.buffer_high_water_bytes(),
+            ))
+        }));
+
+        tasks
     }
 }
 
@@ -157,14 +423,21 @@ enum OverwriteMode {
 /// Execute the generate checksums tasks.
 pub struct GenerateTask {
     tasks: Vec<JoinHandle<Result<Task>>>,
+    offset: u64,
     overwrite: OverwriteMode,
     existing_output: Option<SumsFile>,
-    reader: Option<Box<dyn SharedReader + Send>>,
     write: bool,
     object_sums: Box<dyn ObjectSums + Send>,
     updated: bool,
     output: SumsFile,
     checksums_generated: BTreeMap<Ctx, Checksum>,
+    reader_chunk_size: Option<usize>,
+    buffer_high_water_bytes: Option<usize>,
+    progress: Vec<Arc<dyn Progress>>,
+    cancellation_token: CancellationToken,
+    deadline: CancellationToken,
+    checkpoint: bool,
+    file_size: Option<u64>,
 }
 
 /// The generate error with the task information when the error occurred.
@@ -195,81 +468,12 @@ impl From<GenerateTaskError> for Error {
 pub type GenerateTaskResult = result::Result<GenerateTask, GenerateTaskError>;
 
 impl GenerateTask {
-    /// Spawns a task which reads from the buffered reader.
-    pub fn add_reader_task(mut self) -> Result<Self> {
-        let mut reader = self.reader.take().expect("reader already taken");
-        self.tasks.push(tokio::spawn(async move {
-            Ok(ReadTask(reader.read_chunks().await?))
-        }));
-        Ok(self)
-    }
-
-    /// Spawns a task which generates checksums.
-    pub fn add_generate_task(mut self, mut ctx: Ctx) -> Self {
-        let stream = self
-            .reader
-            .as_mut()
-            .map(|reader| reader.as_stream())
-            .expect("missing reader");
-        self.tasks.push(tokio::spawn(async move {
-            let stream = ctx.generate(stream);
-
-            let digest = stream.await?;
-
-            Ok(ChecksumTask(Box::new((ctx, digest))))
-        }));
-
-        self
-    }
-
-    fn add_generate_tasks(mut self, checksums: HashSet<Ctx>) -> Self {
-        for checksum in checksums {
-            self = self.add_generate_task(checksum);
-        }
-        self
-    }
-
-    /// Spawns tasks for a series of checksums.
-    pub fn add_tasks(mut self, mut checksums: HashSet<Ctx>) -> Result<Self> {
-        let existing = self.existing_output.as_ref();
-
-        match self.overwrite {
-            // If verifying, add existing checksums into the set that needs to be generated.
-            OverwriteMode::Verify => {
-                existing
-                    .map(|file| {
-                        for name in file.checksums.keys() {
-                            checksums.insert(name.clone());
-                        }
-                        Ok::<_, Error>(())
-                    })
-                    .transpose()?;
-            }
-            // Otherwise, if unspecified, remove existing checksums to not re-compute them.
-            OverwriteMode::None => {
-                existing
-                    .map(|file| {
-                        for name in file.checksums.keys() {
-                            checksums.remove(name);
-                        }
-                        Ok::<_, Error>(())
-                    })
-                    .transpose()?;
-            }
-            // If it's overwriting, just use the checksums as specified on the command line.
-            _ => {}
-        }
-
-        // Only perform generate tasks if there is something to do.
-        if !checksums.is_empty() {
-            self = self.add_generate_tasks(checksums).add_reader_task()?;
-        }
-
-        Ok(self)
-    }
-
     async fn do_generate(&mut self) -> Result<()> {
         let mut file_size = 0;
+        let mut reader_chunk_size = None;
+        let mut buffer_high_water_bytes = None;
+        let mut state = BTreeMap::new();
+        self.progress.phase(Phase::Reading);
         let tasks: Vec<_> = self.tasks.drain(..).collect();
         let checksums = join_all(tasks)
             .await
@@ -277,24 +481,56 @@ impl GenerateTask {
             .map(|val| {
                 let task = val??;
                 match task {
-                    ReadTask(size) => {
+                    ReadTask(size, chunk_size, high_water_bytes) => {
+                        // Data before the offset was hashed in a previous run.
+                        file_size = self.offset + size;
+                        reader_chunk_size = chunk_size;
+                        buffer_high_water_bytes = high_water_bytes;
+                        Ok(vec![])
+                    }
+                    CrcTask(digests, size) => {
                         file_size = size;
-                        Ok(None)
+                        Ok(digests
+                            .into_iter()
+                            .map(|(ctx, digest)| {
+                                let checksum = ctx.digest_to_string(&digest);
+                                (ctx, Checksum::new(checksum))
+                            })
+                            .collect())
                     }
                     ChecksumTask(ctx) => {
-                        let (ctx, digest) = *ctx;
+                        let (ctx, digest, ctx_state) = *ctx;
+                        if let Some(ctx_state) = ctx_state {
+                            state.insert(ctx.clone(), ctx_state);
+                        }
 
                         let checksum = ctx.digest_to_string(&digest);
-                        Ok(Some((ctx, Checksum::new(checksum))))
+                        Ok(vec![(ctx, Checksum::new(checksum))])
                     }
                 }
             })
-            .collect::<Result<Vec<Option<(Ctx, Checksum)>>>>()?
+            .collect::<Result<Vec<Vec<(Ctx, Checksum)>>>>()?
             .into_iter()
             .flatten();
 
+        // Reading stops early at the deadline when saving progress, which is only worth saving
+        // if more data was read.
+        let partial_size = self
+            .file_size
+            .filter(|size| self.checkpoint && self.deadline.is_cancelled() && *size > file_size);
+        if partial_size.is_some() && file_size == self.offset {
+            return Err(Cancelled(format!(
+                "generate for {} reached the timeout",
+                self.object_sums.location()
+            )));
+        }
+
         self.checksums_generated = BTreeMap::from_iter(checksums);
-        let new_file = SumsFile::new(Some(file_size), self.checksums_generated.clone());
+        self.reader_chunk_size = reader_chunk_size;
+        self.buffer_high_water_bytes = buffer_high_water_bytes;
+        self.progress.bytes_transferred(file_size, file_size);
+        let new_file =
+            SumsFile::new(Some(file_size), self.checksums_generated.clone()).with_state(state);
 
         let output = match self.existing_output.clone() {
             Some(file) if !matches!(self.overwrite, OverwriteMode::Overwrite) => {
@@ -310,6 +546,7 @@ impl GenerateTask {
         }
 
         if self.write {
+            self.progress.phase(Phase::Writing);
             let current = self.object_sums.sums_file().await?;
 
             if current.as_ref() != Some(&output) {
@@ -320,12 +557,46 @@ impl GenerateTask {
 
         self.output = output;
 
+        if let Some(expected_size) = partial_size {
+            return Err(Cancelled(format!(
+                "generate for {} reached the timeout after reading {} of {} bytes, the progress \
+                was saved and continues with the next `--incremental` run",
+                self.object_sums.location(),
+                file_size,
+                expected_size
+            )));
+        }
+
+        self.progress.phase(Phase::Finished);
+
         Ok(())
     }
 
-    /// Runs the generate task, returning an output file.
+    /// Runs the generate task, returning an output file. If the task is cancelled, or the
+    /// deadline is reached without saving progress, the tasks reading the object are aborted and
+    /// no sums file is written.
     pub async fn run(mut self) -> GenerateTaskResult {
-        match self.do_generate().await {
+        let cancellation_token = self.cancellation_token.clone();
+        let deadline = self.deadline.clone();
+        let checkpoint = self.checkpoint;
+        let abort_handles: Vec<_> = self.tasks.iter().map(JoinHandle::abort_handle).collect();
+        let result = select! {
+            biased;
+            _ = cancellation_token.cancelled() => {
+                abort_handles.iter().for_each(|handle| handle.abort());
+                Err(Cancelled(format!("generate for {}", self.object_sums.location())))
+            }
+            _ = deadline.cancelled(), if !checkpoint => {
+                abort_handles.iter().for_each(|handle| handle.abort());
+                Err(Cancelled(format!(
+                    "generate for {} reached the timeout",
+                    self.object_sums.location()
+                )))
+            }
+            result = self.do_generate() => result,
+        };
+
+        match result {
             Ok(_) => Ok(self),
             Err(err) => Err((self, err).into()),
         }
@@ -348,6 +619,16 @@ impl GenerateTask {
         )
     }
 
+    /// Get the chunk size that the reader used, if the object was read.
+    pub fn reader_chunk_size(&self) -> Option<usize> {
+        self.reader_chunk_size
+    }
+
+    /// Get the most bytes that the reader buffered for a checksum, if the object was read.
+    pub fn buffer_high_water_bytes(&self) -> Option<usize> {
+        self.buffer_high_water_bytes
+    }
+
     /// Get the api errors.
     pub fn api_errors(&self) -> HashSet<ApiError> {
         self.object_sums.api_errors()
@@ -357,6 +638,62 @@ impl GenerateTask {
     pub fn sums_file(&self) -> &SumsFile {
         &self.output
     }
+
+    /// Regenerate the checksums in an existing sums file by reading the object again, and
+    /// confirm that none of them have changed. Returns one of the matching checksums, or `None`
+    /// if there were no checksums to compare.
+    pub async fn verify_existing(
+        location: String,
+        existing: &SumsFile,
+        client: Option<S3Client>,
+        capacity: usize,
+    ) -> Result<Option<ChecksumPair>> {
+        if existing.checksums.is_empty() {
+            return Ok(None);
+        }
+
+        let task = GenerateTaskBuilder::default()
+            .with_input_file_name(location.to_string())
+            .with_context(existing.checksums.keys().cloned().collect())
+            .with_overwrite(true)
+            .with_capacity(capacity)
+            .set_client(client)
+            .build()
+            .await?
+            .run()
+            .await?;
+
+        let generated = task.sums_file();
+        if generated.size != existing.size {
+            return Err(CopyError(format!(
+                "size of {} changed from {:?} to {:?}",
+                location, existing.size, generated.size
+            )));
+        }
+
+        let mut checksum_match = None;
+        for (ctx, checksum) in &existing.checksums {
+            match generated.checksums.get(ctx) {
+                Some(generated) if generated == checksum => {
+                    checksum_match
+                        .get_or_insert_with(|| ChecksumPair::new(ctx.clone(), checksum.clone()));
+                }
+                generated => {
+                    return Err(CopyError(format!(
+                        "{} checksum of {} changed from {} to {}",
+                        ctx,
+                        location,
+                        checksum.as_str(),
+                        generated
+                            .map(|checksum| checksum.as_str())
+                            .unwrap_or("none")
+                    )));
+                }
+            }
+        }
+
+        Ok(checksum_match)
+    }
 }
 
 /// Holds a file name and checksum context.
@@ -442,17 +779,18 @@ impl From<Vec<SumCtxPair>> for SumCtxPairs {
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
-    use crate::checksum::aws_etag::test::expected_md5_1gib;
     use crate::checksum::standard::StandardCtx;
-    use crate::checksum::standard::test::{
+    use crate::checksum::test::expected_md5_1gib;
+    use crate::checksum::test::{
         EXPECTED_CRC32_BE_SUM, EXPECTED_CRC32C_BE_SUM, EXPECTED_CRC64NVME_BE_SUM, EXPECTED_MD5_SUM,
         EXPECTED_SHA1_SUM, EXPECTED_SHA256_SUM, EXPECTED_SHA512_SUM, EXPECTED_XXHASH3_SUM,
         EXPECTED_XXHASH64_SUM, EXPECTED_XXHASH128_SUM,
     };
     use crate::io::sums::channel::test::channel_reader;
-    use crate::io::sums::file::FileBuilder;
+    use crate::io::sums::file::{FileBuilder, SpoolFile};
     use crate::task::check::test::write_test_files_not_comparable;
     use crate::task::check::{CheckTaskBuilder, GroupBy};
+    use crate::task::progress::test::RecordProgress;
     use crate::test::{TEST_FILE_SIZE, TestFileBuilder};
     use anyhow::Result;
     use std::path::Path;
@@ -564,6 +902,62 @@ pub(crate) mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn test_generate_cancelled() -> Result<()> {
+        let tmp = tempdir()?;
+        let name = tmp.path().join("input");
+        tokio::fs::write(&name, b"test").await?;
+        let name = name.to_string_lossy().to_string();
+
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+        let err = GenerateTaskBuilder::default()
+            .with_input_file_name(name.to_string())
+            .with_context(vec!["md5".parse()?])
+            .with_capacity(10)
+            .with_cancellation_token(cancellation_token)
+            .write()
+            .build()
+            .await?
+            .run()
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(err.error, Cancelled(_)));
+        assert!(!Path::new(&format!("{name}.sums")).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_progress() -> Result<()> {
+        let tmp = tempdir()?;
+        let name = tmp.path().join("input");
+        tokio::fs::write(&name, b"test").await?;
+
+        let progress = Arc::new(RecordProgress::default());
+        let task = GenerateTaskBuilder::default()
+            .with_input_file_name(name.to_string_lossy().to_string())
+            .with_context(vec!["md5".parse()?])
+            .with_capacity(10)
+            .with_progress(progress.clone())
+            .write()
+            .build()
+            .await?
+            .run()
+            .await;
+
+        assert!(task.is_ok());
+        assert_eq!(
+            *progress.phases.lock().unwrap(),
+            vec![Phase::Reading, Phase::Writing, Phase::Finished]
+        );
+        assert_eq!(*progress.bytes.lock().unwrap(), vec![(4, 4)]);
+
+        Ok(())
+    }
+
     pub(crate) async fn generate_for(
         name: &str,
         tasks: Vec<&str>,
@@ -589,6 +983,7 @@ pub(crate) mod test {
             .with_overwrite(overwrite)
             .with_verify(verify)
             .with_reader(reader)
+            .with_file_size(TEST_FILE_SIZE)
             .with_context(tasks)
             .build()
             .await?
@@ -657,6 +1052,238 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_checksums_to_generate() -> Result<()> {
+        let existing = SumsFile::new(
+            Some(1),
+            BTreeMap::from_iter(vec![("md5".parse()?, Checksum::new("123".to_string()))]),
+        );
+        let requested = || -> Result<HashSet<Ctx>> {
+            Ok(HashSet::from_iter(vec!["md5".parse()?, "sha1".parse()?]))
+        };
+
+        let checksums = GenerateTaskBuilder::checksums_to_generate(
+            &OverwriteMode::None,
+            Some(&existing),
+            requested()?,
+        );
+        assert_eq!(checksums, HashSet::from_iter(vec!["sha1".parse()?]));
+
+        let checksums = GenerateTaskBuilder::checksums_to_generate(
+            &OverwriteMode::Verify,
+            Some(&existing),
+            HashSet::from_iter(vec!["sha1".parse()?]),
+        );
+        assert_eq!(checksums, requested()?);
+
+        let checksums = GenerateTaskBuilder::checksums_to_generate(
+            &OverwriteMode::Overwrite,
+            Some(&existing),
+            requested()?,
+        );
+        assert_eq!(checksums, requested()?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_spooled_part_number() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = (0..3000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let name = tmp.path().join("name").to_string_lossy().to_string();
+        tokio::fs::write(&name, &data).await?;
+
+        let expected = GenerateTaskBuilder::default()
+            .with_input_file_name(name)
+            .with_context(vec!["md5-aws-3".parse()?])
+            .with_capacity(10)
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap()
+            .into_inner()
+            .0;
+
+        let spool = SpoolFile::spool(data.as_slice(), tmp.path(), None).await?;
+        let spooled = GenerateTaskBuilder::default()
+            .with_context(vec!["md5-aws-3".parse()?])
+            .with_reader(channel_reader(spool.reader().await?).await)
+            .with_file_size(spool.size())
+            .build()
+            .await?
+            .run()
+            .await
+            .unwrap()
+            .into_inner()
+            .0;
+
+        assert_eq!(spooled.size, Some(3000));
+        assert_eq!(spooled.checksums, expected.checksums);
+
+        // Part number checksums cannot be computed without the size.
+        let unknown_size = GenerateTaskBuilder::default()
+            .with_context(vec!["md5-aws-3".parse()?])
+            .with_reader(channel_reader(spool.reader().await?).await)
+            .build()
+            .await;
+        assert!(unknown_size.is_err());
+
+        let path = spool.path().to_path_buf();
+        drop(spool);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spool_max_size() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = vec![1u8; 100];
+
+        let spool = SpoolFile::spool(data.as_slice(), tmp.path(), Some(100)).await?;
+        assert_eq!(spool.size(), 100);
+        drop(spool);
+
+        let result = SpoolFile::spool(data.as_slice(), tmp.path(), Some(99)).await;
+        assert!(matches!(result, Err(Error::InsufficientSpace(_))));
+        // The partially spooled file is removed.
+        assert_eq!(std::fs::read_dir(tmp.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_incremental() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = (0..3000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let name = tmp.path().join("name").to_string_lossy().to_string();
+        let expected_name = tmp.path().join("expected").to_string_lossy().to_string();
+        tokio::fs::write(&name, &data[..1000]).await?;
+        tokio::fs::write(&expected_name, &data).await?;
+
+        let generate = |name: String, checksums: Vec<&str>, incremental: bool| {
+            let ctxs = checksums
+                .into_iter()
+                .map(|ctx| ctx.parse().unwrap())
+                .collect();
+            async move {
+                GenerateTaskBuilder::default()
+                    .with_input_file_name(name)
+                    .with_context(ctxs)
+                    .with_incremental(incremental)
+                    .with_capacity(10)
+                    .write()
+                    .build()
+                    .await
+                    .unwrap()
+                    .run()
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .0
+            }
+        };
+
+        let first = generate(name.clone(), vec!["md5", "sha256"], true).await;
+        assert_eq!(first.state.len(), 2);
+
+        // Modify the bytes that were already hashed, so that the result only matches if the
+        // existing state was resumed rather than the whole file being read again.
+        let mut modified = vec![0; 1000];
+        modified.extend_from_slice(&data[1000..]);
+        tokio::fs::write(&name, &modified).await?;
+
+        let resumed = generate(name.clone(), vec![], true).await;
+        let expected = generate(expected_name.clone(), vec!["md5", "sha256"], false).await;
+        assert_eq!(resumed.size, Some(3000));
+        assert_eq!(resumed.checksums, expected.checksums);
+        assert!(expected.state.is_empty());
+
+        // Checksums without a saved state cause the whole file to be read again.
+        generate(name.clone(), vec!["crc64nvme"], true).await;
+        tokio::fs::write(&name, [data.as_slice(), &data[..10]].concat()).await?;
+        let expected_name = tmp
+            .path()
+            .join("expected_appended")
+            .to_string_lossy()
+            .to_string();
+        tokio::fs::write(&expected_name, [data.as_slice(), &data[..10]].concat()).await?;
+
+        let regenerated = generate(name, vec![], true).await;
+        let expected = generate(expected_name, vec!["md5", "sha256", "crc64nvme"], false).await;
+        assert_eq!(regenerated.size, Some(3010));
+        assert_eq!(regenerated.checksums, expected.checksums);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_deadline() -> Result<()> {
+        let tmp = tempdir()?;
+        let data = (0..4 * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let name = tmp.path().join("name").to_string_lossy().to_string();
+        let expected_name = tmp.path().join("expected").to_string_lossy().to_string();
+        tokio::fs::write(&name, &data).await?;
+        tokio::fs::write(&expected_name, &data).await?;
+
+        let generate = |name: String, incremental: bool, deadline: CancellationToken| async move {
+            GenerateTaskBuilder::default()
+                .with_input_file_name(name)
+                .with_context(vec!["md5".parse()?, "sha256".parse()?])
+                .with_incremental(incremental)
+                .with_capacity(1)
+                .with_chunk_size(ChunkSize::Fixed(4096))
+                .with_deadline(deadline)
+                .write()
+                .build()
+                .await?
+                .run()
+                .await
+                .map_err(Error::from)
+        };
+
+        // Without saving progress, reaching the deadline does not write a sums file.
+        let deadline = CancellationToken::new();
+        deadline.cancel();
+        let err = generate(name.clone(), false, deadline.clone()).await.err();
+        assert!(matches!(err, Some(Cancelled(_))));
+        assert!(!Path::new(&format!("{name}.sums")).exists());
+
+        // Nothing is saved if the deadline was reached before reading.
+        let err = generate(name.clone(), true, deadline).await.err();
+        assert!(matches!(err, Some(Cancelled(_))));
+        assert!(!Path::new(&format!("{name}.sums")).exists());
+
+        // Reaching the deadline while reading saves the progress, which the next incremental
+        // run continues from.
+        let deadline = CancellationToken::new();
+        let cancel = deadline.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            cancel.cancel();
+        });
+        if let Err(err) = generate(name.clone(), true, deadline).await {
+            assert!(matches!(err, Cancelled(_)));
+            let partial =
+                SumsFile::read_from_slice(&tokio::fs::read(format!("{name}.sums")).await?).await?;
+            assert!(partial.size < Some(data.len() as u64));
+            assert_eq!(partial.state.len(), 2);
+        }
+
+        let resumed = generate(name, true, CancellationToken::new()).await?;
+        let expected = generate(expected_name, false, CancellationToken::new()).await?;
+        assert_eq!(resumed.sums_file().size, Some(data.len() as u64));
+        assert_eq!(
+            resumed.sums_file().checksums,
+            expected.sums_file().checksums
+        );
+
+        Ok(())
+    }
+
     async fn write_test_files(tmp: &Path) -> Result<String, Error> {
         let name = tmp.join("name").to_string_lossy().to_string();
         let existing = SumsFile::new(
@@ -0,0 +1,208 @@
+//! Run user-supplied commands before and after each object is copied.
+//!
+
+use crate::checksum::file::SumsFile;
+use crate::error::Error::CopyError;
+use crate::error::Result;
+use std::fmt::{Display, Formatter};
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// The prefix of the environment variables that describe the object to a hook.
+pub const HOOK_ENV_PREFIX: &str = "COPYRITE_HOOK_";
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before the object is copied. A failure prevents the copy.
+    PreCopy,
+    /// After the object is copied and checked. A failure fails the copy.
+    PostCopy,
+}
+
+impl Display for HookStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookStage::PreCopy => write!(f, "pre-copy"),
+            HookStage::PostCopy => write!(f, "post-copy"),
+        }
+    }
+}
+
+/// The object that a hook runs for.
+#[derive(Debug, Clone, Default)]
+pub struct HookObject {
+    source: String,
+    destination: String,
+    size: Option<u64>,
+    sums: Option<SumsFile>,
+}
+
+impl HookObject {
+    /// Create the object for a copy from the source to the destination.
+    pub fn new(source: String, destination: String) -> Self {
+        Self {
+            source,
+            destination,
+            ..Default::default()
+        }
+    }
+
+    /// Set the size of the object.
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Set the sums of the object, which are exposed as checksum environment variables.
+    pub fn set_sums(mut self, sums: Option<SumsFile>) -> Self {
+        self.sums = sums;
+        self
+    }
+
+    /// The environment variables that describe the object. Each checksum is named after its
+    /// context, e.g. `sha256` is `COPYRITE_HOOK_CHECKSUM_SHA256` and `md5-aws-8mib` is
+    /// `COPYRITE_HOOK_CHECKSUM_MD5_AWS_8MIB`.
+    pub fn env(&self, stage: HookStage) -> Vec<(String, String)> {
+        let mut env = vec![
+            (format!("{HOOK_ENV_PREFIX}STAGE"), stage.to_string()),
+            (format!("{HOOK_ENV_PREFIX}SOURCE"), self.source.to_string()),
+            (
+                format!("{HOOK_ENV_PREFIX}DESTINATION"),
+                self.destination.to_string(),
+            ),
+        ];
+
+        let size = self
+            .size
+            .or_else(|| self.sums.as_ref().and_then(|sums| sums.size));
+        if let Some(size) = size {
+            env.push((format!("{HOOK_ENV_PREFIX}SIZE"), size.to_string()));
+        }
+
+        if let Some(sums) = &self.sums {
+            env.extend(sums.checksums.iter().map(|(ctx, checksum)| {
+                let name: String = ctx
+                    .to_string()
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                (
+                    format!("{HOOK_ENV_PREFIX}CHECKSUM_{name}"),
+                    checksum.as_str().to_string(),
+                )
+            }));
+        }
+
+        env
+    }
+}
+
+/// A shell command that runs for each copied object.
+#[derive(Debug, Clone)]
+pub struct Hook {
+    stage: HookStage,
+    command: String,
+}
+
+impl Hook {
+    /// Create a hook from a shell command.
+    pub fn new(stage: HookStage, command: String) -> Self {
+        Self { stage, command }
+    }
+
+    /// Run the hook for an object, failing if the command exits unsuccessfully. The output of
+    /// the command is written to stderr so that it does not mix with the stats on stdout.
+    pub async fn run(&self, object: &HookObject) -> Result<()> {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+
+        let output = command
+            .arg(&self.command)
+            .envs(object.env(self.stage))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|err| {
+                CopyError(format!(
+                    "failed to run {} command `{}`: {}",
+                    self.stage, self.command, err
+                ))
+            })?;
+
+        eprint!("{}", String::from_utf8_lossy(&output.stdout));
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+        if !output.status.success() {
+            return Err(CopyError(format!(
+                "{} command `{}` failed for {} with {}",
+                self.stage, self.command, object.source, output.status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::file::Checksum;
+    use crate::checksum::standard::StandardCtx;
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn run_hook() -> Result<()> {
+        let tmp = tempdir()?;
+        let output = tmp.path().join("output");
+        let sums = SumsFile::new(
+            Some(3),
+            BTreeMap::from([(
+                Ctx::Regular(StandardCtx::md5()),
+                Checksum::new("abc".to_string()),
+            )]),
+        );
+        let object =
+            HookObject::new("source".to_string(), "destination".to_string()).set_sums(Some(sums));
+
+        Hook::new(
+            HookStage::PostCopy,
+            format!(
+                "echo \"$COPYRITE_HOOK_STAGE $COPYRITE_HOOK_SOURCE $COPYRITE_HOOK_DESTINATION \
+                $COPYRITE_HOOK_SIZE $COPYRITE_HOOK_CHECKSUM_MD5\" > {}",
+                output.display()
+            ),
+        )
+        .run(&object)
+        .await?;
+        assert_eq!(
+            fs::read_to_string(&output).await?,
+            "post-copy source destination 3 abc\n"
+        );
+
+        let err = Hook::new(HookStage::PreCopy, "exit 1".to_string())
+            .run(&object)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("pre-copy command `exit 1` failed"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! Read manifests which list objects to copy, with optional per-row copy options.
+//!
+
+use crate::cli::MetadataCopy;
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use clap::ValueEnum;
+use parse_size::parse_size;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::Path;
+use std::result;
+use std::str::FromStr;
+use tokio::fs;
+
+/// A row of a manifest. Options which are not set use the values passed on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestRow {
+    /// The source to copy from.
+    pub source: String,
+    /// The destination to copy to.
+    pub destination: String,
+    /// The part size for multipart copies.
+    pub part_size: Option<u64>,
+    /// The number of simultaneous copy tasks for multipart copies.
+    pub concurrency: Option<usize>,
+    /// The storage class of the destination.
+    pub storage_class: Option<String>,
+    /// How tags are copied.
+    pub tag_mode: Option<MetadataCopy>,
+}
+
+/// The order in which the rows of a manifest are copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduleStrategy {
+    /// Copy the largest objects first, interleaved with the smallest objects so that small
+    /// copies keep connections busy while the large copies run.
+    LargestFirst,
+    /// Copy the rows in the order of the manifest.
+    #[default]
+    Fifo,
+    /// Copy the smallest objects first.
+    SmallestFirst,
+}
+
+impl ScheduleStrategy {
+    /// Order rows by the size of their sources, returning the indices of the rows in the order
+    /// that they should be copied. Rows with an unknown size are copied last, in the order of
+    /// the manifest.
+    pub fn order(&self, sizes: &[Option<u64>]) -> Vec<usize> {
+        let (mut known, unknown): (Vec<_>, Vec<_>) =
+            (0..sizes.len()).partition(|index| sizes[*index].is_some());
+
+        let known = match self {
+            Self::Fifo => return (0..sizes.len()).collect(),
+            Self::SmallestFirst => {
+                known.sort_by_key(|index| sizes[*index]);
+                known
+            }
+            Self::LargestFirst => {
+                known.sort_by_key(|index| Reverse(sizes[*index]));
+
+                let mut interleaved = Vec::with_capacity(known.len());
+                let mut known = known.into_iter();
+                while let Some(largest) = known.next() {
+                    interleaved.push(largest);
+                    interleaved.extend(known.next_back());
+                }
+                interleaved
+            }
+        };
+
+        known.into_iter().chain(unknown).collect()
+    }
+}
+
+/// A manifest of objects to copy. This is a CSV file with a header row, which must contain
+/// `source` and `destination` columns. The `part_size`, `concurrency`, `storage_class` and
+/// `tag_mode` columns are optional, and empty values use the default options. Values are not
+/// quoted, so they cannot contain commas.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    rows: Vec<ManifestRow>,
+}
+
+impl Manifest {
+    /// Read a manifest from a file.
+    pub async fn read(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path).await?.parse()
+    }
+
+    /// The rows of the manifest.
+    pub fn rows(&self) -> &[ManifestRow] {
+        &self.rows
+    }
+
+    /// Parse an optional value from a row.
+    fn parse_value<T>(
+        value: Option<&str>,
+        line: usize,
+        column: &str,
+        parse: impl FnOnce(&str) -> result::Result<T, String>,
+    ) -> Result<Option<T>> {
+        value
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                parse(value).map_err(|err| {
+                    ParseError(format!(
+                        "invalid {} `{}` on line {} of manifest: {}",
+                        column, value, line, err
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| ParseError("manifest is empty".to_string()))?;
+        let header: Vec<&str> = header.split(',').map(str::trim).collect();
+        let column = |name: &str| header.iter().position(|column| *column == name);
+
+        for name in &header {
+            if !matches!(
+                *name,
+                "source"
+                    | "destination"
+                    | "part_size"
+                    | "concurrency"
+                    | "storage_class"
+                    | "tag_mode"
+            ) {
+                return Err(ParseError(format!("unknown manifest column `{}`", name)));
+            }
+        }
+        let (Some(source), Some(destination)) = (column("source"), column("destination")) else {
+            return Err(ParseError(
+                "manifest must contain `source` and `destination` columns".to_string(),
+            ));
+        };
+        let part_size = column("part_size");
+        let concurrency = column("concurrency");
+        let storage_class = column("storage_class");
+        let tag_mode = column("tag_mode");
+
+        let rows = lines
+            .map(|(line, row)| {
+                let values: Vec<&str> = row.split(',').map(str::trim).collect();
+                if values.len() != header.len() {
+                    return Err(ParseError(format!(
+                        "line {} of manifest has {} values but the header has {} columns",
+                        line,
+                        values.len(),
+                        header.len()
+                    )));
+                }
+                let value = |index: Option<usize>| index.map(|index| values[index]);
+
+                let required = |index: usize, name: &str| {
+                    Some(values[index].to_string())
+                        .filter(|value| !value.is_empty())
+                        .ok_or_else(|| {
+                            ParseError(format!("missing {} on line {} of manifest", name, line))
+                        })
+                };
+
+                Ok(ManifestRow {
+                    source: required(source, "source")?,
+                    destination: required(destination, "destination")?,
+                    part_size: Self::parse_value(value(part_size), line, "part size", |value| {
+                        parse_size(value).map_err(|err| err.to_string())
+                    })?,
+                    concurrency: Self::parse_value(
+                        value(concurrency),
+                        line,
+                        "concurrency",
+                        |value| usize::from_str(value).map_err(|err| err.to_string()),
+                    )?,
+                    storage_class: value(storage_class)
+                        .filter(|value| !value.is_empty())
+                        .map(str::to_string),
+                    tag_mode: Self::parse_value(value(tag_mode), line, "tag mode", |value| {
+                        MetadataCopy::from_str(value, true)
+                    })?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rows })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn parse_manifest_with_overrides() -> Result<()> {
+        let manifest: Manifest = "source,destination,part_size,concurrency,storage_class,tag_mode
+            s3://bucket/a,s3://bucket/b,16mib,4,GLACIER_IR,suppress
+
+            s3://bucket/c,s3://bucket/d,,,,"
+            .parse()?;
+
+        assert_eq!(
+            manifest.rows(),
+            [
+                ManifestRow {
+                    source: "s3://bucket/a".to_string(),
+                    destination: "s3://bucket/b".to_string(),
+                    part_size: Some(16 * 1024 * 1024),
+                    concurrency: Some(4),
+                    storage_class: Some("GLACIER_IR".to_string()),
+                    tag_mode: Some(MetadataCopy::Suppress),
+                },
+                ManifestRow {
+                    source: "s3://bucket/c".to_string(),
+                    destination: "s3://bucket/d".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_manifest_errors() {
+        assert!("".parse::<Manifest>().is_err());
+        assert!("source\ns3://bucket/a".parse::<Manifest>().is_err());
+        assert!(
+            "source,destination,unknown\na,b,c"
+                .parse::<Manifest>()
+                .is_err()
+        );
+        assert!("source,destination\na".parse::<Manifest>().is_err());
+        assert!(
+            "source,destination,concurrency\na,b,many"
+                .parse::<Manifest>()
+                .is_err()
+        );
+        assert!("source,destination\n,b".parse::<Manifest>().is_err());
+    }
+
+    #[test]
+    fn schedule_strategy_order() {
+        let sizes = [Some(5), None, Some(1), Some(10), Some(5), Some(3)];
+
+        assert_eq!(ScheduleStrategy::Fifo.order(&sizes), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            ScheduleStrategy::SmallestFirst.order(&sizes),
+            [2, 5, 0, 4, 3, 1]
+        );
+        assert_eq!(
+            ScheduleStrategy::LargestFirst.order(&sizes),
+            [3, 2, 0, 5, 4, 1]
+        );
+        assert!(ScheduleStrategy::LargestFirst.order(&[]).is_empty());
+    }
+}
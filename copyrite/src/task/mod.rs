@@ -1,9 +1,27 @@
 //! Task definitions for different commands.
 //!
 
+pub mod audit;
 pub mod check;
+pub mod compare;
+pub mod compat;
+pub mod concat;
 pub mod copy;
+pub mod diff;
+pub mod doctor;
+pub mod gc;
 pub mod generate;
+pub mod hook;
+pub mod manifest;
+pub mod parts;
+pub mod prefix;
+pub mod progress;
+pub mod snapshot;
+pub mod split;
+pub mod sync;
+pub mod transition;
+pub mod ui;
+pub mod watch;
 
 use crate::io::S3Client;
 
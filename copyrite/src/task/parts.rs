@@ -0,0 +1,192 @@
+//! Export the MD5 and byte range of each part of an object, in the part manifest format used
+//! by legacy genomics submission portals, e.g. EGA-style part manifests.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::aws_etag::{AWSETagCtx, PartMode};
+use crate::checksum::standard::StandardCtx;
+use crate::error::Error::GenerateError;
+use crate::error::Result;
+use crate::io::S3Client;
+use crate::task::generate::GenerateTaskBuilder;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The MD5 of a part of an object.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartMd5 {
+    /// The part number, starting at 1.
+    pub(crate) part_number: u64,
+    /// The offset of the first byte of the part.
+    pub(crate) start: u64,
+    /// The offset of the last byte of the part, inclusive.
+    pub(crate) end: u64,
+    /// The size of the part.
+    pub(crate) size: u64,
+    /// The hex-encoded MD5 of the part.
+    pub(crate) md5: String,
+}
+
+/// The MD5s of the parts of an object, along with the multipart ETag that they produce.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartManifest {
+    /// The object that the parts belong to.
+    pub(crate) location: String,
+    /// The size of the object.
+    pub(crate) size: u64,
+    /// The multipart ETag of the object, computed from the part MD5s.
+    pub(crate) etag: String,
+    /// The parts of the object, in order.
+    pub(crate) parts: Vec<PartMd5>,
+}
+
+impl PartManifest {
+    /// Create a part manifest from an `md5-aws-*` context that has computed its part checksums,
+    /// and the checksum that it produced.
+    pub fn from_ctx(location: String, ctx: &Ctx, checksum: &str) -> Result<Self> {
+        let part_checksums = ctx
+            .part_checksums()
+            .filter(|_| matches!(ctx.clone().standard_ctx(), StandardCtx::MD5(_)))
+            .ok_or_else(|| GenerateError(format!("{} does not compute part MD5s", ctx)))?;
+        let digest = checksum
+            .split_once('-')
+            .map_or(checksum, |(digest, _)| digest);
+
+        let n_parts = part_checksums.len();
+        let mut start = 0;
+        let parts = part_checksums
+            .into_iter()
+            .zip(1..)
+            .map(|((size, md5), part_number)| {
+                let part = PartMd5 {
+                    part_number,
+                    start,
+                    end: (start + size).saturating_sub(1),
+                    size,
+                    md5,
+                };
+                start += size;
+                part
+            })
+            .collect();
+
+        Ok(Self {
+            location,
+            size: start,
+            etag: format!("{}-{}", digest, n_parts),
+            parts,
+        })
+    }
+
+    /// Compute the part MD5s of an object by reading it, using a fixed part size.
+    pub async fn generate(
+        location: String,
+        part_size: u64,
+        client: Option<S3Client>,
+        capacity: usize,
+    ) -> Result<Self> {
+        let ctx = Ctx::AWSEtag(AWSETagCtx::new(
+            StandardCtx::md5(),
+            PartMode::PartSizes(vec![part_size]),
+            None,
+        ));
+
+        // Existing sums are overwritten so that the parts are always read from the object.
+        let task = GenerateTaskBuilder::default()
+            .with_input_file_name(location.to_string())
+            .with_context(vec![ctx.clone()])
+            .with_overwrite(true)
+            .with_capacity(capacity)
+            .set_client(client)
+            .build()
+            .await?
+            .run()
+            .await?;
+
+        let (_, _, _, generated) = task.into_inner();
+        let (ctx, checksum) = generated.get_key_value(&ctx).ok_or_else(|| {
+            GenerateError(format!("failed to compute the part MD5s of {}", location))
+        })?;
+
+        Self::from_ctx(location, ctx, checksum.as_str())
+    }
+
+    /// Format the manifest as tab-separated lines of the part number, first byte, last byte
+    /// and MD5 of each part, preceded by comment lines describing the object.
+    pub fn to_legacy_string(&self) -> String {
+        let mut output = format!(
+            "# file: {}\n# size: {}\n# parts: {}\n# etag: {}\n",
+            self.location,
+            self.size,
+            self.parts.len(),
+            self.etag
+        );
+        for part in &self.parts {
+            // Writing to a string cannot fail.
+            let _ = writeln!(
+                output,
+                "{}\t{}\t{}\t{}",
+                part.part_number, part.start, part.end, part.md5
+            );
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use md5::{Digest, Md5};
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn part_manifest_ranges() -> Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("file");
+        fs::write(&path, b"0123456789").await?;
+        let location = path.to_string_lossy().to_string();
+
+        let manifest = PartManifest::generate(location.clone(), 4, None, 10).await?;
+
+        let md5 = |data: &[u8]| hex::encode(Md5::digest(data));
+        let part = |part_number, start, end, data: &[u8]| PartMd5 {
+            part_number,
+            start,
+            end,
+            size: end - start + 1,
+            md5: md5(data),
+        };
+        assert_eq!(manifest.size, 10);
+        assert_eq!(
+            manifest.parts,
+            vec![
+                part(1, 0, 3, b"0123"),
+                part(2, 4, 7, b"4567"),
+                part(3, 8, 9, b"89"),
+            ]
+        );
+
+        let concat: Vec<u8> = [&b"0123"[..], b"4567", b"89"]
+            .iter()
+            .flat_map(Md5::digest)
+            .collect();
+        assert_eq!(manifest.etag, format!("{}-3", md5(&concat)));
+
+        assert_eq!(
+            manifest.to_legacy_string(),
+            format!(
+                "# file: {}\n# size: 10\n# parts: 3\n# etag: {}\n1\t0\t3\t{}\n2\t4\t7\t{}\n3\t8\t9\t{}\n",
+                location,
+                manifest.etag,
+                md5(b"0123"),
+                md5(b"4567"),
+                md5(b"89")
+            )
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! List the objects under an S3 prefix or local directory for recursive copies.
+//!
+
+use crate::checksum::file::SUMS_FILE_ENDING;
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use crate::io::{Provider, S3Client};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::Metadata;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::fs;
+
+/// Identifies the underlying file of a path by its device and inode, so that hard links to the
+/// same file can be detected.
+pub(crate) type FileId = (u64, u64);
+
+/// A regular file listed under a local directory.
+#[derive(Debug)]
+pub(crate) struct ListedFile {
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) id: Option<FileId>,
+}
+
+/// An object listed under a prefix or directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixObject {
+    /// The path relative to the prefix or directory, using `/` as the separator.
+    pub path: String,
+    /// The size of the object.
+    pub size: u64,
+    /// The path of the local file that this file is hard-linked to, which is the first path in
+    /// sorted order with the same device and inode.
+    pub hard_link_of: Option<String>,
+}
+
+/// Build a prefix listing task.
+#[derive(Debug, Default)]
+pub struct PrefixTaskBuilder {
+    location: Option<String>,
+    client: Option<S3Client>,
+}
+
+impl PrefixTaskBuilder {
+    /// Set the S3 prefix or local directory to list.
+    pub fn with_location(mut self, location: String) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Set the client, which is required for S3 prefixes.
+    pub fn set_client(mut self, client: Option<S3Client>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Build the prefix listing task.
+    pub fn build(self) -> Result<PrefixTask> {
+        let location = self
+            .location
+            .ok_or_else(|| ParseError("a location is required to list a prefix".to_string()))?;
+        let provider = Provider::try_from(location.as_str())?;
+        if provider.is_s3() && self.client.is_none() {
+            return Err(ParseError(
+                "an S3 client is required to list an S3 prefix".to_string(),
+            ));
+        }
+
+        Ok(PrefixTask {
+            provider,
+            client: self.client,
+        })
+    }
+}
+
+/// Lists the objects under a prefix or directory, excluding sums files.
+#[derive(Debug)]
+pub struct PrefixTask {
+    provider: Provider,
+    client: Option<S3Client>,
+}
+
+impl PrefixTask {
+    /// Whether the location refers to an S3 prefix or a local directory rather than a single
+    /// object. S3 locations are prefixes if they end with a `/`.
+    pub async fn is_prefix(location: &str) -> Result<bool> {
+        Ok(match Provider::try_from(location)? {
+            Provider::S3 { key, .. } | Provider::Gcs { key, .. } => {
+                key.is_empty() || key.ends_with('/')
+            }
+            Provider::File { file } => fs::metadata(&file)
+                .await
+                .is_ok_and(|metadata| metadata.is_dir()),
+            _ => false,
+        })
+    }
+
+    /// List the objects, returning the path of each one relative to the prefix or directory,
+    /// using `/` as the separator. The paths are sorted.
+    pub async fn list(self) -> Result<Vec<String>> {
+        Ok(self.list_sizes().await?.into_keys().collect())
+    }
+
+    /// List the objects with their sizes, keyed by the path of each one relative to the prefix
+    /// or directory. A directory that does not exist has no objects, the same as an empty
+    /// prefix.
+    pub async fn list_sizes(self) -> Result<BTreeMap<String, u64>> {
+        Ok(self
+            .list_objects()
+            .await?
+            .into_iter()
+            .map(|object| (object.path, object.size))
+            .collect())
+    }
+
+    /// List the objects sorted by path. Local files which are hard links to an earlier file in
+    /// the listing record the path of that file.
+    pub async fn list_objects(self) -> Result<Vec<PrefixObject>> {
+        let mut objects = match (&self.provider, &self.client) {
+            (Provider::S3 { bucket, key } | Provider::Gcs { bucket, key }, Some(client)) => {
+                Self::list_s3(client, bucket, key).await?
+            }
+            (Provider::File { file }, _) => {
+                Self::with_hard_links(Self::list_files(Path::new(file)).await?)
+            }
+            (Provider::S3 { .. } | Provider::Gcs { .. }, None) => {
+                return Err(ParseError(
+                    "an S3 client is required to list an S3 prefix".to_string(),
+                ));
+            }
+            (provider, _) => {
+                return Err(ParseError(format!(
+                    "recursive copies from {} are not supported",
+                    provider.format()
+                )));
+            }
+        };
+
+        objects.retain(|object| !object.path.ends_with(SUMS_FILE_ENDING));
+        objects.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(objects)
+    }
+
+    /// Find the hard links between listed files, linking each file to the first path in sorted
+    /// order with the same device and inode.
+    fn with_hard_links(mut files: Vec<ListedFile>) -> Vec<PrefixObject> {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut originals: HashMap<FileId, String> = HashMap::new();
+        files
+            .into_iter()
+            .map(|file| {
+                let hard_link_of = file.id.and_then(|id| match originals.get(&id) {
+                    Some(original) => Some(original.to_string()),
+                    None => {
+                        originals.insert(id, file.path.to_string());
+                        None
+                    }
+                });
+
+                PrefixObject {
+                    path: file.path,
+                    size: file.size,
+                    hard_link_of,
+                }
+            })
+            .collect()
+    }
+
+    /// The device and inode of a file. This is only available on Unix platforms.
+    #[cfg(unix)]
+    pub(crate) fn file_id(metadata: &Metadata) -> Option<FileId> {
+        use std::os::unix::fs::MetadataExt;
+
+        Some((metadata.dev(), metadata.ino()))
+    }
+
+    /// The device and inode of a file. This is only available on Unix platforms.
+    #[cfg(not(unix))]
+    pub(crate) fn file_id(_metadata: &Metadata) -> Option<FileId> {
+        None
+    }
+
+    /// List the keys under an S3 prefix, skipping directory markers.
+    async fn list_s3(client: &S3Client, bucket: &str, prefix: &str) -> Result<Vec<PrefixObject>> {
+        Ok(client
+            .list_objects(bucket, prefix)
+            .await?
+            .iter()
+            .filter_map(|object| {
+                let key = object.key()?.strip_prefix(prefix)?;
+                let size = u64::try_from(object.size().unwrap_or_default()).unwrap_or_default();
+                Some((key, size))
+            })
+            .filter(|(key, _)| !key.is_empty() && !key.ends_with('/'))
+            .map(|(key, size)| PrefixObject {
+                path: key.to_string(),
+                size,
+                hard_link_of: None,
+            })
+            .collect())
+    }
+
+    /// List the regular files in a local directory and its subdirectories, excluding sums
+    /// files.
+    pub(crate) async fn list_files(directory: &Path) -> Result<Vec<ListedFile>> {
+        let mut files = vec![];
+        let mut directories = vec![directory.to_path_buf()];
+
+        while let Some(current) = directories.pop() {
+            let mut entries = match fs::read_dir(&current).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound && current == directory => {
+                    return Ok(files);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                let path = entry.path();
+
+                if file_type.is_dir() {
+                    directories.push(path);
+                } else if file_type.is_file() && !path.to_string_lossy().ends_with(SUMS_FILE_ENDING)
+                {
+                    let relative = path
+                        .strip_prefix(directory)
+                        .unwrap_or(&path)
+                        .components()
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    let metadata = entry.metadata().await?;
+                    files.push(ListedFile {
+                        path: relative,
+                        size: metadata.len(),
+                        modified: metadata.modified().ok(),
+                        id: Self::file_id(&metadata),
+                    });
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+    use aws_sdk_s3::types::Object;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn list_files() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::create_dir_all(tmp.path().join("dir/nested")).await?;
+        fs::write(tmp.path().join("a"), "a").await?;
+        fs::write(tmp.path().join("a.sums"), "{}").await?;
+        fs::write(tmp.path().join("dir/b"), "b").await?;
+        fs::write(tmp.path().join("dir/nested/c"), "c").await?;
+
+        let location = tmp.path().to_string_lossy().to_string();
+        assert!(PrefixTask::is_prefix(&location).await?);
+        assert!(!PrefixTask::is_prefix(&tmp.path().join("a").to_string_lossy()).await?);
+
+        let objects = PrefixTaskBuilder::default()
+            .with_location(location)
+            .build()?
+            .list()
+            .await?;
+        assert_eq!(objects, vec!["a", "dir/b", "dir/nested/c"]);
+
+        let missing = PrefixTaskBuilder::default()
+            .with_location(tmp.path().join("missing").to_string_lossy().to_string())
+            .build()?
+            .list_sizes()
+            .await?;
+        assert!(missing.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn list_hard_links() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::create_dir_all(tmp.path().join("dir")).await?;
+        fs::write(tmp.path().join("b"), "b").await?;
+        fs::hard_link(tmp.path().join("b"), tmp.path().join("a")).await?;
+        fs::hard_link(tmp.path().join("b"), tmp.path().join("dir/c")).await?;
+        fs::write(tmp.path().join("d"), "b").await?;
+
+        let objects = PrefixTaskBuilder::default()
+            .with_location(tmp.path().to_string_lossy().to_string())
+            .build()?
+            .list_objects()
+            .await?;
+
+        let object = |path: &str, hard_link_of: Option<&str>| PrefixObject {
+            path: path.to_string(),
+            size: 1,
+            hard_link_of: hard_link_of.map(str::to_string),
+        };
+        assert_eq!(
+            objects,
+            vec![
+                object("a", None),
+                object("b", Some("a")),
+                object("d", None),
+                object("dir/c", Some("a")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_s3() -> Result<()> {
+        let list = mock!(Client::list_objects_v2)
+            .match_requests(|req| req.bucket() == Some("bucket") && req.prefix() == Some("prefix/"))
+            .then_output(|| {
+                ListObjectsV2Output::builder()
+                    .contents(Object::builder().key("prefix/").build())
+                    .contents(Object::builder().key("prefix/b").build())
+                    .contents(Object::builder().key("prefix/a").build())
+                    .contents(Object::builder().key("prefix/a.sums").build())
+                    .contents(Object::builder().key("prefix/dir/c").size(3).build())
+                    .is_truncated(false)
+                    .build()
+            });
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&list]);
+
+        assert!(PrefixTask::is_prefix("s3://bucket/prefix/").await?);
+        assert!(!PrefixTask::is_prefix("s3://bucket/prefix/a").await?);
+
+        let objects = PrefixTaskBuilder::default()
+            .with_location("s3://bucket/prefix/".to_string())
+            .set_client(Some(S3Client::new(Arc::new(client), false, false)))
+            .build()?
+            .list_sizes()
+            .await?;
+        assert_eq!(
+            objects,
+            BTreeMap::from([
+                ("a".to_string(), 0),
+                ("b".to_string(), 0),
+                ("dir/c".to_string(), 3)
+            ])
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Report the progress of tasks to library users and the terminal UI.
+//!
+
+use indicatif::ProgressBar;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// A phase that a task moves through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading the object to generate checksums.
+    Reading,
+    /// Writing the generated sums file.
+    Writing,
+    /// Copying the object data to the destination.
+    Copying,
+    /// Completing a multipart upload from the copied parts.
+    Completing,
+    /// The task finished successfully.
+    Finished,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Reading => write!(f, "reading"),
+            Phase::Writing => write!(f, "writing"),
+            Phase::Copying => write!(f, "copying"),
+            Phase::Completing => write!(f, "completing"),
+            Phase::Finished => write!(f, "finished"),
+        }
+    }
+}
+
+/// Observe the progress of a task. Each method has an empty default so that observers only
+/// need to implement the events they are interested in. Observers are called from the task
+/// while it runs, so they should return quickly.
+pub trait Progress: Send + Sync {
+    /// The task moved to a new phase.
+    fn phase(&self, _phase: Phase) {}
+
+    /// The total bytes processed so far, out of the size of the object.
+    fn bytes_transferred(&self, _bytes: u64, _total: u64) {}
+
+    /// A part of a multipart copy completed, out of the total number of parts.
+    fn part_completed(&self, _part_number: u64, _n_parts: u64) {}
+}
+
+/// The terminal UI shows bytes as a progress bar.
+impl Progress for ProgressBar {
+    fn phase(&self, phase: Phase) {
+        if phase == Phase::Finished {
+            self.finish_with_message("done");
+        }
+    }
+
+    fn bytes_transferred(&self, bytes: u64, total: u64) {
+        self.set_length(total);
+        self.set_position(bytes);
+    }
+}
+
+/// Notify every registered observer.
+impl Progress for Vec<Arc<dyn Progress>> {
+    fn phase(&self, phase: Phase) {
+        self.iter().for_each(|progress| progress.phase(phase));
+    }
+
+    fn bytes_transferred(&self, bytes: u64, total: u64) {
+        self.iter()
+            .for_each(|progress| progress.bytes_transferred(bytes, total));
+    }
+
+    fn part_completed(&self, part_number: u64, n_parts: u64) {
+        self.iter()
+            .for_each(|progress| progress.part_completed(part_number, n_parts));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records the events that a task reports.
+    #[derive(Debug, Default)]
+    pub(crate) struct RecordProgress {
+        pub(crate) phases: Mutex<Vec<Phase>>,
+        pub(crate) bytes: Mutex<Vec<(u64, u64)>>,
+        pub(crate) parts: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl Progress for RecordProgress {
+        fn phase(&self, phase: Phase) {
+            self.phases.lock().unwrap().push(phase);
+        }
+
+        fn bytes_transferred(&self, bytes: u64, total: u64) {
+            self.bytes.lock().unwrap().push((bytes, total));
+        }
+
+        fn part_completed(&self, part_number: u64, n_parts: u64) {
+            self.parts.lock().unwrap().push((part_number, n_parts));
+        }
+    }
+}
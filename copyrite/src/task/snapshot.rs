@@ -0,0 +1,308 @@
+//! Export a manifest of the destinations of a multi-object copy once it completes. The manifest
+//! records the size, checksums and version of each object, and is digested and optionally signed
+//! so that it can be archived alongside the data.
+//!
+
+use crate::checksum::file::ObjectVersion;
+use crate::error::Result;
+use crate::io::S3Client;
+use crate::io::sums::ObjectSumsBuilder;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::fs;
+
+/// The version of the snapshot manifest format.
+pub const SNAPSHOT_MANIFEST_VERSION: u64 = 1;
+
+/// The prefix of the digest of a snapshot manifest.
+const DIGEST_PREFIX: &str = "sha256:";
+
+/// The prefix of the signature of a snapshot manifest.
+const SIGNATURE_PREFIX: &str = "hmac-sha256:";
+
+/// The state of an object at the time that the snapshot was taken.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotObject {
+    /// The location of the object.
+    pub location: String,
+    /// The size of the object, if it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The checksums of the object, keyed by the checksum name.
+    pub checksums: BTreeMap<String, String>,
+    /// The `ETag` and version id of the object.
+    #[serde(flatten)]
+    pub version: ObjectVersion,
+}
+
+/// The fields of a snapshot manifest which are covered by the digest and signature.
+#[derive(Serialize)]
+struct SnapshotContents<'a> {
+    version: u64,
+    created: &'a str,
+    objects: &'a [SnapshotObject],
+}
+
+/// A manifest of the state of a set of objects after a copy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotManifest {
+    /// The version of the manifest format.
+    pub version: u64,
+    /// When the snapshot was taken, formatted as RFC 3339.
+    pub created: String,
+    /// The objects in the snapshot, ordered by location.
+    pub objects: Vec<SnapshotObject>,
+    /// The SHA-256 digest of the version, creation time and objects.
+    pub digest: String,
+    /// The HMAC-SHA256 of the version, creation time and objects, if a key was used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl SnapshotManifest {
+    /// Create a snapshot manifest from its objects, signing it if there is a key.
+    pub fn new(
+        created: String,
+        mut objects: Vec<SnapshotObject>,
+        key: Option<&str>,
+    ) -> Result<Self> {
+        objects.sort_by(|a, b| a.location.cmp(&b.location));
+        objects.dedup_by(|a, b| a.location == b.location);
+
+        let mut manifest = Self {
+            version: SNAPSHOT_MANIFEST_VERSION,
+            created,
+            objects,
+            digest: String::new(),
+            signature: None,
+        };
+        let contents = manifest.contents()?;
+        manifest.digest = format!(
+            "{}{}",
+            DIGEST_PREFIX,
+            hex::encode(Sha256::digest(&contents))
+        );
+        manifest.signature = key.map(|key| Self::sign(key, &contents));
+
+        Ok(manifest)
+    }
+
+    /// The canonical encoding of the fields covered by the digest and signature.
+    fn contents(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&SnapshotContents {
+            version: self.version,
+            created: &self.created,
+            objects: &self.objects,
+        })?)
+    }
+
+    fn mac(key: &str, contents: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(key.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(contents);
+        mac
+    }
+
+    fn sign(key: &str, contents: &[u8]) -> String {
+        format!(
+            "{}{}",
+            SIGNATURE_PREFIX,
+            hex::encode(Self::mac(key, contents).finalize().into_bytes())
+        )
+    }
+
+    /// Check that the digest matches the contents of the manifest, and that the signature
+    /// matches if there is a key. A manifest without a signature does not verify with a key. The
+    /// signature is compared in constant time.
+    pub fn verify(&self, key: Option<&str>) -> Result<bool> {
+        let contents = self.contents()?;
+        let digest = format!(
+            "{}{}",
+            DIGEST_PREFIX,
+            hex::encode(Sha256::digest(&contents))
+        );
+        if digest != self.digest {
+            return Ok(false);
+        }
+
+        Ok(match key {
+            Some(key) => self
+                .signature
+                .as_deref()
+                .and_then(|signature| signature.strip_prefix(SIGNATURE_PREFIX))
+                .and_then(|signature| hex::decode(signature).ok())
+                .is_some_and(|signature| {
+                    Self::mac(key, &contents).verify_slice(&signature).is_ok()
+                }),
+            None => true,
+        })
+    }
+
+    /// Read a snapshot manifest from a file.
+    pub async fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path).await?)?)
+    }
+
+    /// Write the snapshot manifest to a file as pretty JSON.
+    pub async fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, serde_json::to_string_pretty(self)?).await?)
+    }
+}
+
+/// Build a snapshot manifest by reading the current state of each object.
+#[derive(Debug, Default)]
+pub struct SnapshotManifestBuilder {
+    client: Option<S3Client>,
+    key: Option<String>,
+    locations: Vec<String>,
+}
+
+impl SnapshotManifestBuilder {
+    /// Set the client used to read the objects.
+    pub fn set_client(mut self, client: Option<S3Client>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Set the key used to sign the manifest.
+    pub fn set_key(mut self, key: Option<String>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Set the locations of the objects in the snapshot.
+    pub fn with_locations(mut self, locations: Vec<String>) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    /// Read the state of an object.
+    async fn object(&self, location: String) -> Result<SnapshotObject> {
+        let mut sums = ObjectSumsBuilder::default()
+            .set_client(self.client.clone())
+            .build(location.to_string())
+            .await?;
+
+        let size = sums.file_size().await?;
+        let checksums = sums
+            .sums_file()
+            .await?
+            .map(|sums_file| {
+                sums_file
+                    .checksums
+                    .into_iter()
+                    .map(|(ctx, checksum)| (ctx.to_string(), checksum.as_str().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let version = sums.object_version().await?.unwrap_or_default();
+
+        Ok(SnapshotObject {
+            location,
+            size,
+            checksums,
+            version,
+        })
+    }
+
+    /// Build the snapshot manifest.
+    pub async fn build(self) -> Result<SnapshotManifest> {
+        let created = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+
+        let mut objects = Vec::with_capacity(self.locations.len());
+        for location in &self.locations {
+            objects.push(self.object(location.to_string()).await?);
+        }
+
+        SnapshotManifest::new(created, objects, self.key.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn build_snapshot_manifest() -> Result<()> {
+        let tmp = TempDir::new()?;
+        let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+        fs::write(&a, b"data").await?;
+        fs::write(&b, b"more data").await?;
+        let (a, b) = (
+            a.to_string_lossy().to_string(),
+            b.to_string_lossy().to_string(),
+        );
+
+        let manifest = SnapshotManifestBuilder::default()
+            .set_key(Some("key".to_string()))
+            .with_locations(vec![b.to_string(), a.to_string(), b.to_string()])
+            .build()
+            .await?;
+
+        assert_eq!(manifest.version, SNAPSHOT_MANIFEST_VERSION);
+        assert_eq!(
+            manifest
+                .objects
+                .iter()
+                .map(|object| (object.location.as_str(), object.size))
+                .collect::<Vec<_>>(),
+            vec![(a.as_str(), Some(4)), (b.as_str(), Some(9))]
+        );
+        assert!(manifest.digest.starts_with(DIGEST_PREFIX));
+        assert!(
+            manifest
+                .signature
+                .as_ref()
+                .unwrap()
+                .starts_with(SIGNATURE_PREFIX)
+        );
+
+        let path = tmp.path().join("snapshot.json");
+        manifest.write(&path).await?;
+        assert_eq!(SnapshotManifest::read(&path).await?, manifest);
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_snapshot_manifest() -> Result<()> {
+        let object = SnapshotObject {
+            location: "s3://bucket/key".to_string(),
+            size: Some(4),
+            checksums: BTreeMap::from([(
+                "md5".to_string(),
+                "8d777f385d3dfec8815d20f7496026dc".to_string(),
+            )]),
+            version: ObjectVersion::new(Some("\"etag\"".to_string()), Some("v1".to_string())),
+        };
+        let created = "2026-01-01T00:00:00Z".to_string();
+
+        let unsigned = SnapshotManifest::new(created.to_string(), vec![object.clone()], None)?;
+        assert!(unsigned.signature.is_none());
+        assert!(unsigned.verify(None)?);
+        assert!(!unsigned.verify(Some("key"))?);
+
+        let signed = SnapshotManifest::new(created, vec![object], Some("key"))?;
+        assert_eq!(signed.digest, unsigned.digest);
+        assert!(signed.verify(Some("key"))?);
+        assert!(!signed.verify(Some("other key"))?);
+
+        let mut malformed = signed.clone();
+        malformed.signature = Some(format!("{}not hex", SIGNATURE_PREFIX));
+        assert!(!malformed.verify(Some("key"))?);
+
+        let mut tampered = signed.clone();
+        tampered.objects[0].size = Some(5);
+        assert!(!tampered.verify(None)?);
+        assert!(!tampered.verify(Some("key"))?);
+
+        Ok(())
+    }
+}
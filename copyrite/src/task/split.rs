@@ -0,0 +1,366 @@
+//! Split a single object into fixed-size pieces, for destinations with object size limits.
+//!
+
+use crate::checksum::Ctx;
+use crate::checksum::file::Checksum;
+use crate::error::Error::{CopyError, ParseError};
+use crate::error::Result;
+use crate::io::copy::{CopyContent, CopyState, MultiPartOptions, ObjectCopy, ObjectCopyBuilder};
+use crate::io::{Provider, S3Client};
+use crate::task::generate::GenerateTaskBuilder;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+/// The ending of the manifest written next to the pieces.
+pub const SPLIT_MANIFEST_ENDING: &str = ".manifest.json";
+
+/// A piece of the source written to the destination.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitPiece {
+    /// The location of the piece.
+    pub(crate) location: String,
+    /// The offset of the piece in the source.
+    pub(crate) offset: u64,
+    /// The size of the piece.
+    pub(crate) size: u64,
+    /// The checksums of the piece, read back from the destination.
+    #[serde(flatten)]
+    pub(crate) checksums: BTreeMap<Ctx, Checksum>,
+}
+
+/// The manifest describing how a source was split. The pieces are listed in order, so that
+/// concatenating them reproduces the source.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitManifest {
+    /// The source that was split.
+    pub(crate) source: String,
+    /// The size of the source.
+    pub(crate) size: u64,
+    /// The maximum size of each piece.
+    pub(crate) piece_size: u64,
+    /// The pieces of the source.
+    pub(crate) pieces: Vec<SplitPiece>,
+}
+
+/// Build a split task.
+#[derive(Debug, Default)]
+pub struct SplitTaskBuilder {
+    source: Option<String>,
+    destination: Option<String>,
+    source_client: Option<S3Client>,
+    destination_client: Option<S3Client>,
+    piece_size: u64,
+    checksums: Vec<Ctx>,
+    concurrency: usize,
+    capacity: usize,
+}
+
+impl SplitTaskBuilder {
+    /// Set the source.
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Set the destination, which is used as the prefix of the piece names.
+    pub fn with_destination(mut self, destination: String) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Set the source client.
+    pub fn with_source_client(mut self, client: S3Client) -> Self {
+        self.source_client = Some(client);
+        self
+    }
+
+    /// Set the destination client.
+    pub fn with_destination_client(mut self, client: S3Client) -> Self {
+        self.destination_client = Some(client);
+        self
+    }
+
+    /// Set the maximum size of each piece.
+    pub fn with_piece_size(mut self, piece_size: u64) -> Self {
+        self.piece_size = piece_size;
+        self
+    }
+
+    /// Set the checksums to record for each piece.
+    pub fn with_checksums(mut self, checksums: Vec<Ctx>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Set the number of pieces to copy at the same time.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the reader capacity used when computing checksums.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Build the split task.
+    pub fn build(self) -> Result<SplitTask> {
+        let source = self
+            .source
+            .ok_or_else(|| ParseError("a source is required to split".to_string()))?;
+        let destination = self
+            .destination
+            .ok_or_else(|| ParseError("a destination is required to split".to_string()))?;
+        if self.piece_size == 0 {
+            return Err(ParseError(
+                "the piece size must be greater than 0".to_string(),
+            ));
+        }
+        if self.checksums.iter().any(Ctx::is_part_number) {
+            return Err(ParseError(
+                "part number checksums cannot be recorded for split pieces".to_string(),
+            ));
+        }
+
+        Provider::try_from(source.as_str())?;
+        Provider::try_from(destination.as_str())?;
+
+        Ok(SplitTask {
+            source,
+            destination,
+            source_client: self.source_client,
+            destination_client: self.destination_client,
+            piece_size: self.piece_size,
+            checksums: self.checksums,
+            concurrency: self.concurrency.max(1),
+            capacity: self.capacity,
+        })
+    }
+}
+
+/// Copies ranges of a source object to separate destination objects and writes a manifest
+/// listing each piece with its checksums.
+#[derive(Debug)]
+pub struct SplitTask {
+    source: String,
+    destination: String,
+    source_client: Option<S3Client>,
+    destination_client: Option<S3Client>,
+    piece_size: u64,
+    checksums: Vec<Ctx>,
+    concurrency: usize,
+    capacity: usize,
+}
+
+impl SplitTask {
+    /// Format the location of a piece, numbering pieces from 1 with enough digits to sort
+    /// correctly.
+    pub fn format_piece(destination: &str, piece: usize, n_pieces: usize) -> String {
+        let width = n_pieces.to_string().len().max(2);
+        format!("{}.part{:0width$}", destination, piece + 1, width = width)
+    }
+
+    /// The location of the manifest for a destination.
+    pub fn format_manifest(destination: &str) -> String {
+        format!("{}{}", destination, SPLIT_MANIFEST_ENDING)
+    }
+
+    /// Build an object copy that writes to a destination location.
+    async fn destination_copy(&self, location: &str) -> Result<Box<dyn ObjectCopy + Send + Sync>> {
+        ObjectCopyBuilder::default()
+            .set_destination(Some(Provider::try_from(location)?))
+            .set_client(self.destination_client.clone())
+            .build()
+            .await
+    }
+
+    /// Copy one piece of the source and read its checksums back from the destination.
+    async fn copy_piece(
+        &self,
+        source: &(dyn ObjectCopy + Send + Sync),
+        location: String,
+        offset: u64,
+        size: u64,
+    ) -> Result<SplitPiece> {
+        let multi_part = MultiPartOptions {
+            start: offset,
+            end: offset + size,
+            ..Default::default()
+        };
+        let content = source.download(Some(multi_part)).await?;
+        self.destination_copy(&location)
+            .await?
+            .upload(content, None, &CopyState::new(size, None, None))
+            .await?;
+
+        let mut checksums = BTreeMap::new();
+        if !self.checksums.is_empty() {
+            let task = GenerateTaskBuilder::default()
+                .with_input_file_name(location.to_string())
+                .with_context(self.checksums.clone())
+                .with_capacity(self.capacity)
+                .set_client(self.destination_client.clone())
+                .build()
+                .await?
+                .run()
+                .await?;
+
+            let sums_file = task.sums_file();
+            if sums_file.size != Some(size) {
+                return Err(CopyError(format!(
+                    "size of {} is {:?} but {} bytes were written",
+                    location, sums_file.size, size
+                )));
+            }
+            checksums = sums_file.checksums.clone();
+        }
+
+        Ok(SplitPiece {
+            location,
+            offset,
+            size,
+            checksums,
+        })
+    }
+
+    /// Write the manifest to the destination.
+    async fn write_manifest(&self, location: &str, manifest: &SplitManifest) -> Result<()> {
+        let data = serde_json::to_vec_pretty(manifest)?;
+        let size = u64::try_from(data.len())?;
+
+        let reopen_data = data.clone();
+        let content = CopyContent::builder(Box::new(Cursor::new(data)))
+            .with_reopen(move || {
+                let data = reopen_data.clone();
+                async move { CopyContent::builder(Box::new(Cursor::new(data))).build() }
+            })
+            .build()?;
+
+        self.destination_copy(location)
+            .await?
+            .upload(content, None, &CopyState::new(size, None, None))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Split the source into pieces and write the manifest, returning the manifest and its
+    /// location.
+    pub async fn run(self) -> Result<(String, SplitManifest)> {
+        let source = ObjectCopyBuilder::default()
+            .set_source(Some(Provider::try_from(self.source.as_str())?))
+            .set_client(self.source_client.clone())
+            .build()
+            .await?;
+        let size = source.initialize_state().await?.size();
+        if size == 0 {
+            return Err(CopyError(format!("{} is empty", self.source)));
+        }
+
+        let destination = &self.destination;
+        let max_piece_size = self
+            .destination_copy(destination)
+            .await?
+            .limits()
+            .max_part_size();
+        if self.piece_size > max_piece_size {
+            return Err(CopyError(format!(
+                "the piece size of {} bytes is larger than the maximum single upload size of {} bytes for {}",
+                self.piece_size, max_piece_size, destination
+            )));
+        }
+
+        let n_pieces = usize::try_from(size.div_ceil(self.piece_size))?;
+        let pieces = stream::iter(0..n_pieces)
+            .map(|piece| {
+                let offset = u64::try_from(piece).unwrap_or_default() * self.piece_size;
+                let location = Self::format_piece(destination, piece, n_pieces);
+                self.copy_piece(
+                    source.as_ref(),
+                    location,
+                    offset,
+                    self.piece_size.min(size - offset),
+                )
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        let manifest = SplitManifest {
+            source: self.source.to_string(),
+            size,
+            piece_size: self.piece_size,
+            pieces,
+        };
+        let location = Self::format_manifest(destination);
+        self.write_manifest(&location, &manifest).await?;
+
+        Ok((location, manifest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use md5::{Digest, Md5};
+    use std::str::FromStr;
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[test]
+    fn format_piece() {
+        assert_eq!(SplitTask::format_piece("file", 0, 3), "file.part01");
+        assert_eq!(SplitTask::format_piece("file", 99, 100), "file.part100");
+        assert_eq!(SplitTask::format_manifest("file"), "file.manifest.json");
+    }
+
+    #[tokio::test]
+    async fn split_writes_pieces_and_manifest() -> Result<()> {
+        let tmp = tempdir()?;
+        let source = tmp.path().join("source");
+        let data = (0..2500).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        fs::write(&source, &data).await?;
+        let destination = tmp.path().join("destination").to_string_lossy().to_string();
+
+        let (location, manifest) = SplitTaskBuilder::default()
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string())
+            .with_piece_size(1000)
+            .with_checksums(vec![Ctx::from_str("md5")?])
+            .with_concurrency(2)
+            .with_capacity(10)
+            .build()?
+            .run()
+            .await?;
+
+        assert_eq!(manifest.size, 2500);
+        assert_eq!(
+            manifest
+                .pieces
+                .iter()
+                .map(|piece| (piece.offset, piece.size))
+                .collect::<Vec<_>>(),
+            [(0, 1000), (1000, 1000), (2000, 500)]
+        );
+
+        let mut joined = vec![];
+        for piece in &manifest.pieces {
+            let contents = fs::read(&piece.location).await?;
+            assert_eq!(
+                piece.checksums.get(&Ctx::from_str("md5")?),
+                Some(&Checksum::new(hex::encode(Md5::digest(&contents))))
+            );
+            joined.extend(contents);
+        }
+        assert_eq!(joined, data);
+
+        let written: SplitManifest = serde_json::from_slice(&fs::read(&location).await?)?;
+        assert_eq!(written, manifest);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Plan which objects under a prefix or directory need to be copied to bring a destination
+//! up to date with its source.
+//!
+
+use crate::checksum::file::SumsFile;
+use crate::error::Error::{CopyError, ParseError};
+use crate::error::Result;
+use crate::io::{Provider, S3Client};
+use crate::task::prefix::PrefixTaskBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use tokio::fs;
+
+/// How an object at the source differs from the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDelta {
+    /// The object does not exist at the destination.
+    Missing,
+    /// The object exists at the destination with a different size.
+    SizeMismatch,
+    /// The object exists at the destination with the same size, so its checksums still need to
+    /// be compared.
+    SameSize,
+    /// The object exists at the destination with the same size but different checksums.
+    ChecksumMismatch,
+    /// The object is the same at the source and destination.
+    Unchanged,
+}
+
+impl SyncDelta {
+    /// Whether the object needs to be copied.
+    pub fn is_changed(&self) -> bool {
+        matches!(
+            self,
+            SyncDelta::Missing | SyncDelta::SizeMismatch | SyncDelta::ChecksumMismatch
+        )
+    }
+}
+
+/// An object under the source with its path relative to the source and its delta.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncObject {
+    /// The path relative to the source, using `/` as the separator.
+    pub path: String,
+    /// The size of the source object.
+    pub size: u64,
+    /// How the object differs from the destination.
+    pub delta: SyncDelta,
+    /// The path relative to the source of the local file that this file is hard-linked to.
+    pub hard_link_of: Option<String>,
+}
+
+/// The objects under the source and how they differ from the destination, and the objects
+/// that only exist at the destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// The objects under the source, sorted by path.
+    pub objects: Vec<SyncObject>,
+    /// The paths relative to the destination of objects that do not exist at the source, sorted.
+    pub extraneous: Vec<String>,
+}
+
+impl SyncPlan {
+    /// Check that deleting the extraneous objects would not delete more than `max_delete`
+    /// objects, which guards against deleting most of the destination when the source is
+    /// unexpectedly empty or wrong.
+    pub fn check_max_delete(&self, max_delete: Option<usize>) -> Result<()> {
+        match max_delete {
+            Some(max_delete) if self.extraneous.len() > max_delete => Err(CopyError(format!(
+                "sync would delete {} objects, which is more than the maximum of {}",
+                self.extraneous.len(),
+                max_delete
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Build a sync planning task.
+#[derive(Debug, Default)]
+pub struct SyncTaskBuilder {
+    source: Option<String>,
+    destination: Option<String>,
+    source_client: Option<S3Client>,
+    destination_client: Option<S3Client>,
+}
+
+impl SyncTaskBuilder {
+    /// Set the source prefix or directory.
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Set the destination prefix or directory.
+    pub fn with_destination(mut self, destination: String) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Set the source client, which is required for S3 prefixes.
+    pub fn set_source_client(mut self, source_client: Option<S3Client>) -> Self {
+        self.source_client = source_client;
+        self
+    }
+
+    /// Set the destination client, which is required for S3 prefixes.
+    pub fn set_destination_client(mut self, destination_client: Option<S3Client>) -> Self {
+        self.destination_client = destination_client;
+        self
+    }
+
+    /// Build the sync planning task.
+    pub fn build(self) -> Result<SyncTask> {
+        let source = self
+            .source
+            .ok_or_else(|| ParseError("a source is required to sync".to_string()))?;
+        let destination = self
+            .destination
+            .ok_or_else(|| ParseError("a destination is required to sync".to_string()))?;
+
+        Ok(SyncTask {
+            source: Self::as_prefix(source),
+            destination: Self::as_prefix(destination),
+            source_client: self.source_client,
+            destination_client: self.destination_client,
+        })
+    }
+
+    /// Make sure that a location ends with a `/`, so that an S3 prefix does not match keys
+    /// which only start with the same characters.
+    fn as_prefix(location: String) -> String {
+        format!("{}/", location.trim_end_matches('/'))
+    }
+}
+
+/// Compares the listings of a source and destination prefix or directory.
+#[derive(Debug)]
+pub struct SyncTask {
+    source: String,
+    destination: String,
+    source_client: Option<S3Client>,
+    destination_client: Option<S3Client>,
+}
+
+impl SyncTask {
+    /// The source prefix or directory, ending with a `/`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The destination prefix or directory, ending with a `/`.
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// List the source and destination and find the delta of each source object from its size.
+    /// Objects with the same size at the source and destination have a `SameSize` delta, and
+    /// their checksums need to be compared to determine whether they changed.
+    pub async fn plan(&self) -> Result<SyncPlan> {
+        let source = PrefixTaskBuilder::default()
+            .with_location(self.source.to_string())
+            .set_client(self.source_client.clone())
+            .build()?
+            .list_objects()
+            .await?;
+        let destination = PrefixTaskBuilder::default()
+            .with_location(self.destination.to_string())
+            .set_client(self.destination_client.clone())
+            .build()?
+            .list_sizes()
+            .await?;
+
+        let source_paths = source
+            .iter()
+            .map(|object| object.path.as_str())
+            .collect::<HashSet<_>>();
+        let mut extraneous = destination
+            .keys()
+            .filter(|path| !source_paths.contains(path.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        extraneous.sort();
+        let objects = source
+            .into_iter()
+            .map(|object| {
+                let delta = match destination.get(&object.path) {
+                    None => SyncDelta::Missing,
+                    Some(existing) if *existing != object.size => SyncDelta::SizeMismatch,
+                    Some(_) => SyncDelta::SameSize,
+                };
+                SyncObject {
+                    path: object.path,
+                    size: object.size,
+                    delta,
+                    hard_link_of: object.hard_link_of,
+                }
+            })
+            .collect();
+
+        Ok(SyncPlan {
+            objects,
+            extraneous,
+        })
+    }
+
+    /// Delete an object relative to the destination, along with its sums file if it has one.
+    /// Returns the location of the deleted object.
+    pub async fn delete(&self, path: &str) -> Result<String> {
+        let location = format!("{}{}", self.destination, path);
+        match (
+            Provider::try_from(location.as_str())?,
+            &self.destination_client,
+        ) {
+            (Provider::S3 { bucket, key } | Provider::Gcs { bucket, key }, Some(client)) => {
+                for key in [key.to_string(), SumsFile::format_sums_file(&key)] {
+                    client
+                        .delete_object(|b| b.bucket(&bucket).key(&key))
+                        .await?;
+                }
+            }
+            (Provider::File { file }, _) => {
+                fs::remove_file(&file).await?;
+                match fs::remove_file(SumsFile::format_sums_file(&file)).await {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+            }
+            (provider, _) => {
+                return Err(CopyError(format!(
+                    "deleting objects from {} is not supported",
+                    provider.format()
+                )));
+            }
+        }
+
+        Ok(location)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn plan_files() -> Result<()> {
+        let tmp = tempdir()?;
+        let (source, destination) = (tmp.path().join("source"), tmp.path().join("destination"));
+        fs::create_dir_all(source.join("dir")).await?;
+        fs::create_dir_all(destination.join("dir")).await?;
+        fs::write(source.join("a"), "a").await?;
+        fs::write(source.join("b"), "b").await?;
+        fs::write(source.join("dir/c"), "c").await?;
+        fs::write(destination.join("a"), "x").await?;
+        fs::write(destination.join("b"), "bb").await?;
+        fs::write(destination.join("extra"), "extra").await?;
+
+        fs::write(destination.join("extra.sums"), "{}").await?;
+        fs::write(destination.join("a.sums"), "{}").await?;
+        fs::write(destination.join("dir/c.sums"), "{}").await?;
+        fs::write(destination.join("orphan.sums"), "{}").await?;
+
+        let task = SyncTaskBuilder::default()
+            .with_source(source.to_string_lossy().to_string())
+            .with_destination(destination.to_string_lossy().to_string())
+            .build()?;
+        let plan = task.plan().await?;
+
+        let object = |path: &str, delta| SyncObject {
+            path: path.to_string(),
+            size: 1,
+            delta,
+            hard_link_of: None,
+        };
+        assert_eq!(
+            plan.objects,
+            vec![
+                object("a", SyncDelta::SameSize),
+                object("b", SyncDelta::SizeMismatch),
+                object("dir/c", SyncDelta::Missing),
+            ]
+        );
+        assert_eq!(plan.extraneous, vec!["extra".to_string()]);
+        assert!(plan.check_max_delete(None).is_ok());
+        assert!(plan.check_max_delete(Some(1)).is_ok());
+        assert!(plan.check_max_delete(Some(0)).is_err());
+        assert!(!SyncDelta::SameSize.is_changed());
+        assert!(SyncDelta::Missing.is_changed());
+
+        task.delete("extra").await?;
+        assert!(!destination.join("extra").exists());
+        assert!(!destination.join("extra.sums").exists());
+        assert!(destination.join("orphan.sums").exists());
+        assert!(destination.join("a").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+//! Transition objects under an S3 prefix to another storage class.
+//!
+
+use crate::checksum::file::{SUMS_FILE_ENDING, SumsFile};
+use crate::error::Error::{CopyError, ParseError};
+use crate::error::Result;
+use crate::io::{Provider, S3Client};
+use crate::stats::ChecksumPair;
+
+/// The storage class that S3 reports for objects without an explicit storage class.
+const DEFAULT_STORAGE_CLASS: &str = "STANDARD";
+
+/// Build a transition task.
+#[derive(Debug, Default)]
+pub struct TransitionTaskBuilder {
+    prefix: Option<String>,
+    client: Option<S3Client>,
+}
+
+impl TransitionTaskBuilder {
+    /// Set the prefix of the objects to transition, e.g. `s3://bucket/prefix/`.
+    pub fn with_prefix(mut self, prefix: String) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Set the client.
+    pub fn with_client(mut self, client: S3Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the transition task.
+    pub fn build(self) -> Result<TransitionTask> {
+        let prefix = self
+            .prefix
+            .ok_or_else(|| ParseError("a prefix is required to transition".to_string()))?;
+        let client = self
+            .client
+            .ok_or_else(|| ParseError("an S3 client is required to transition".to_string()))?;
+
+        let (bucket, prefix) = Provider::try_from(prefix.as_str())?.into_s3()?;
+
+        Ok(TransitionTask {
+            client,
+            bucket,
+            prefix,
+        })
+    }
+}
+
+/// An object under the prefix and its storage class before the transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionObject {
+    bucket: String,
+    key: String,
+    storage_class: String,
+    e_tag: Option<String>,
+}
+
+impl TransitionObject {
+    /// The location of the object.
+    pub fn location(&self) -> String {
+        Provider::format_s3(&self.bucket, &self.key)
+    }
+
+    /// The storage class of the object when it was listed.
+    pub fn storage_class(&self) -> &str {
+        &self.storage_class
+    }
+
+    /// The ETag of the object when it was listed.
+    pub fn e_tag(&self) -> Option<&str> {
+        self.e_tag.as_deref()
+    }
+}
+
+/// Lists the objects under a prefix and confirms that transitioning them to another storage
+/// class did not change their contents.
+#[derive(Debug)]
+pub struct TransitionTask {
+    client: S3Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl TransitionTask {
+    /// List the objects under the prefix. Sums files are not transitioned because they are read
+    /// when checking objects.
+    pub async fn list(&self) -> Result<Vec<TransitionObject>> {
+        Ok(self
+            .client
+            .list_objects(&self.bucket, &self.prefix)
+            .await?
+            .iter()
+            .filter_map(|object| {
+                let key = object.key()?;
+                if key.ends_with(SUMS_FILE_ENDING) {
+                    return None;
+                }
+
+                Some(TransitionObject {
+                    bucket: self.bucket.to_string(),
+                    key: key.to_string(),
+                    storage_class: object
+                        .storage_class()
+                        .map(|class| class.as_str())
+                        .unwrap_or(DEFAULT_STORAGE_CLASS)
+                        .to_string(),
+                    e_tag: object.e_tag().map(str::to_string),
+                })
+            })
+            .collect())
+    }
+
+    /// Get the current ETag of an object.
+    pub async fn e_tag(&self, object: &TransitionObject) -> Result<Option<String>> {
+        Ok(self
+            .client
+            .head_object(|b| b.bucket(&object.bucket).key(&object.key))
+            .await?
+            .e_tag)
+    }
+
+    /// Confirm that the sums of an object after the transition match the sums before it. All
+    /// checksums that exist both before and after must be equal. Returns one of the matching
+    /// checksums, or `None` if there were no checksums to compare.
+    pub fn confirm_sums(
+        location: &str,
+        before: &SumsFile,
+        after: &SumsFile,
+    ) -> Result<Option<ChecksumPair>> {
+        if before.size != after.size {
+            return Err(CopyError(format!(
+                "size of {} changed from {:?} to {:?} after the transition",
+                location, before.size, after.size
+            )));
+        }
+
+        let mut checksum_match = None;
+        for (ctx, checksum) in &before.checksums {
+            match after.checksums.get(ctx) {
+                Some(after) if after != checksum => {
+                    return Err(CopyError(format!(
+                        "{} checksum of {} changed from {} to {} after the transition",
+                        ctx,
+                        location,
+                        checksum.as_str(),
+                        after.as_str()
+                    )));
+                }
+                Some(_) if checksum_match.is_none() => {
+                    checksum_match = Some(ChecksumPair::new(ctx.clone(), checksum.clone()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(checksum_match)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checksum::Ctx;
+    use crate::checksum::file::Checksum;
+    use anyhow::Result;
+    use aws_sdk_s3::Client;
+    use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+    use aws_sdk_s3::types::{Object, ObjectStorageClass};
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn list_paginates_and_skips_sums_files() -> Result<()> {
+        let first = mock!(Client::list_objects_v2)
+            .match_requests(|req| req.continuation_token().is_none())
+            .then_output(|| {
+                ListObjectsV2Output::builder()
+                    .contents(Object::builder().key("prefix/a").e_tag("\"a\"").build())
+                    .contents(Object::builder().key("prefix/a.sums").build())
+                    .is_truncated(true)
+                    .next_continuation_token("token")
+                    .build()
+            });
+        let second = mock!(Client::list_objects_v2)
+            .match_requests(|req| req.continuation_token() == Some("token"))
+            .then_output(|| {
+                ListObjectsV2Output::builder()
+                    .contents(
+                        Object::builder()
+                            .key("prefix/b")
+                            .storage_class(ObjectStorageClass::Glacier)
+                            .build(),
+                    )
+                    .is_truncated(false)
+                    .build()
+            });
+
+        let client = mock_client!(aws_sdk_s3, RuleMode::MatchAny, &[&first, &second]);
+        let task = TransitionTaskBuilder::default()
+            .with_prefix("s3://bucket/prefix/".to_string())
+            .with_client(S3Client::new(Arc::new(client), false, false))
+            .build()?;
+
+        let objects = task.list().await?;
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].location(), "s3://bucket/prefix/a");
+        assert_eq!(objects[0].storage_class(), "STANDARD");
+        assert_eq!(objects[0].e_tag(), Some("\"a\""));
+        assert_eq!(objects[1].storage_class(), "GLACIER");
+
+        Ok(())
+    }
+
+    #[test]
+    fn confirm_sums_detects_changes() -> Result<()> {
+        let sums = |md5: &str, sha256: Option<&str>| {
+            let mut sums_file = SumsFile::default().with_size(Some(4));
+            sums_file.add_checksum(
+                Ctx::from_str("md5").unwrap(),
+                Checksum::new(md5.to_string()),
+            );
+            if let Some(sha256) = sha256 {
+                sums_file.add_checksum(
+                    Ctx::from_str("sha256").unwrap(),
+                    Checksum::new(sha256.to_string()),
+                );
+            }
+            sums_file
+        };
+
+        let pair =
+            TransitionTask::confirm_sums("a", &sums("md5", Some("sha256")), &sums("md5", None))?;
+        assert_eq!(pair.map(|pair| pair.kind), Some(Ctx::from_str("md5")?));
+
+        assert!(
+            TransitionTask::confirm_sums("a", &sums("md5", None), &sums("other", None)).is_err()
+        );
+        assert!(
+            TransitionTask::confirm_sums(
+                "a",
+                &sums("md5", None),
+                &sums("md5", None).with_size(Some(5))
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+}
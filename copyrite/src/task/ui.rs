@@ -0,0 +1,105 @@
+//! Recording of the human-readable UI output, such as the steps of a copy and the reasons that
+//! it was skipped, to a log file. Lines are recorded whether or not `--ui` prints them, so the
+//! log can be attached to a ticket alongside the JSON stats when a transfer behaves unexpectedly.
+//!
+
+use crate::error::Result;
+use console::strip_ansi_codes;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// The UI log of the current process, if one is being recorded.
+static UI_LOG: OnceLock<UiLog> = OnceLock::new();
+
+/// A log file that UI output is recorded to.
+#[derive(Debug)]
+pub struct UiLog {
+    file: Mutex<File>,
+}
+
+impl UiLog {
+    /// Open a UI log, appending to the file if it already exists.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Record the UI log of this process to a file. Only the first log is used if this is
+    /// called more than once.
+    pub fn install(path: impl AsRef<Path>) -> Result<()> {
+        let _ = UI_LOG.set(Self::open(path)?);
+        Ok(())
+    }
+
+    /// Record a line with the time that it was written, removing any styling. Failing to
+    /// record a line does not fail the command.
+    pub fn record(&self, line: &str) {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+        if let Ok(mut file) = self.file.lock() {
+            for line in strip_ansi_codes(line).lines() {
+                let _ = writeln!(file, "[{}] {}", timestamp, line);
+            }
+        }
+    }
+}
+
+/// Record a line to the UI log if one is being recorded.
+pub fn ui_record(line: impl Display) {
+    if let Some(log) = UI_LOG.get() {
+        log.record(&line.to_string());
+    }
+}
+
+/// Print a line of UI output if `ui` is set, and record it to the UI log.
+pub fn ui_println(ui: bool, line: impl Display) {
+    if ui {
+        println!("{}", line);
+    }
+    ui_record(line);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use console::style;
+    use tempfile::TempDir;
+
+    #[test]
+    fn record_lines() -> anyhow::Result<()> {
+        let tmp = TempDir::new()?;
+        let path = tmp.path().join("ui.log");
+
+        let log = UiLog::open(&path)?;
+        log.record(&format!(
+            "{} Copying...",
+            style("[2/3]").bold().force_styling(true)
+        ));
+        log.record("  · Source - a\n  · Destination - b");
+        // Reopening appends to the existing log.
+        UiLog::open(&path)?.record("Done in 1 second");
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines = contents
+            .lines()
+            .map(|line| line.split_once("] ").unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "[2/3] Copying...",
+                "  · Source - a",
+                "  · Destination - b",
+                "Done in 1 second"
+            ]
+        );
+        assert!(contents.starts_with('['));
+
+        Ok(())
+    }
+}
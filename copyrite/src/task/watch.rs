@@ -0,0 +1,323 @@
+//! Watch a local directory for files that are ready to be copied.
+//!
+
+use crate::error::Error::ParseError;
+use crate::error::Result;
+use crate::task::prefix::{FileId, PrefixTask};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs;
+
+/// The longest time to wait before retrying a file that failed to copy.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(3600);
+
+/// Build a watch task.
+#[derive(Debug, Default)]
+pub struct WatchTaskBuilder {
+    directory: Option<PathBuf>,
+    stable_for: Duration,
+    max_attempts: u32,
+    retry_backoff: Duration,
+}
+
+impl WatchTaskBuilder {
+    /// Set the directory to watch.
+    pub fn with_directory(mut self, directory: PathBuf) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+
+    /// Set how long a file must remain unchanged before it is considered stable.
+    pub fn with_stable_for(mut self, stable_for: Duration) -> Self {
+        self.stable_for = stable_for;
+        self
+    }
+
+    /// Set the number of times a file is copied before it is marked as failed.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set how long to wait before retrying a file that failed to copy for the first time. The
+    /// wait doubles after each failure, up to an hour.
+    pub fn with_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Build the watch task.
+    pub async fn build(self) -> Result<WatchTask> {
+        let directory = self
+            .directory
+            .ok_or_else(|| ParseError("a directory is required to watch".to_string()))?;
+
+        if !fs::metadata(&directory).await?.is_dir() {
+            return Err(ParseError(format!(
+                "{} is not a directory",
+                directory.display()
+            )));
+        }
+
+        Ok(WatchTask {
+            directory,
+            stable_for: self.stable_for,
+            max_attempts: self.max_attempts,
+            retry_backoff: self.retry_backoff,
+            files: HashMap::new(),
+        })
+    }
+}
+
+/// The observed state of a file in the watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    modified: Option<SystemTime>,
+    id: Option<FileId>,
+    changed_at: Instant,
+    copied: bool,
+    failed: bool,
+    attempts: u32,
+    retry_at: Option<Instant>,
+}
+
+/// Tracks files in a directory and reports them once their size and modification time have not
+/// changed for the stable duration. A file is reported until it is processed, and again only if
+/// it is modified after being processed.
+#[derive(Debug)]
+pub struct WatchTask {
+    directory: PathBuf,
+    stable_for: Duration,
+    max_attempts: u32,
+    retry_backoff: Duration,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl WatchTask {
+    /// The directory being watched.
+    pub fn directory(&self) -> &Path {
+        self.directory.as_path()
+    }
+
+    /// Scan the directory, returning the paths relative to the watched directory of files that
+    /// have become stable and have not been processed yet. Files that are waiting to be retried
+    /// are not returned until their backoff has passed.
+    pub async fn scan(&mut self) -> Result<Vec<PathBuf>> {
+        let now = Instant::now();
+        let mut seen = HashMap::new();
+
+        for file in PrefixTask::list_files(&self.directory).await? {
+            let path = PathBuf::from(file.path);
+            let state = match self.files.remove(&path) {
+                Some(state)
+                    if state.size == file.size
+                        && state.modified == file.modified
+                        && state.id == file.id =>
+                {
+                    state
+                }
+                _ => FileState {
+                    size: file.size,
+                    modified: file.modified,
+                    id: file.id,
+                    changed_at: now,
+                    copied: false,
+                    failed: false,
+                    attempts: 0,
+                    retry_at: None,
+                },
+            };
+            seen.insert(path, state);
+        }
+
+        // Files that were removed since the last scan are dropped.
+        self.files = seen;
+
+        let mut stable: Vec<_> = self
+            .files
+            .iter()
+            .filter(|(_, state)| {
+                !state.copied
+                    && now.duration_since(state.changed_at) >= self.stable_for
+                    && state.retry_at.is_none_or(|retry_at| now >= retry_at)
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        stable.sort();
+
+        Ok(stable)
+    }
+
+    /// Mark a file as processed so that it is not reported again unless it changes.
+    pub fn mark_processed(&mut self, path: &Path) {
+        if let Some(state) = self.files.get_mut(path) {
+            state.copied = true;
+        }
+    }
+
+    /// Mark a file as processed after it failed to copy. It is not reported again unless it
+    /// changes, and it is not used as the original of hard links.
+    pub fn mark_failed(&mut self, path: &Path) {
+        if let Some(state) = self.files.get_mut(path) {
+            state.copied = true;
+            state.failed = true;
+        }
+    }
+
+    /// Record a failed copy of a file so that it is retried once its backoff has passed. Once
+    /// the file has failed `max_attempts` times, it is marked as failed and this returns
+    /// `false`. A file that changes is retried with a new number of attempts.
+    pub fn retry_later(&mut self, path: &Path) -> bool {
+        let Some(state) = self.files.get_mut(path) else {
+            return false;
+        };
+
+        state.attempts += 1;
+        if state.attempts >= self.max_attempts {
+            self.mark_failed(path);
+            return false;
+        }
+
+        let backoff = self
+            .retry_backoff
+            .saturating_mul(2u32.saturating_pow(state.attempts - 1))
+            .min(MAX_RETRY_BACKOFF);
+        state.retry_at = Some(Instant::now() + backoff);
+        true
+    }
+
+    /// Whether there are files that have not been processed yet.
+    pub fn has_pending(&self) -> bool {
+        self.files.values().any(|state| !state.copied)
+    }
+
+    /// Find a successfully processed file that is a hard link to the same underlying file as this
+    /// path. If there are multiple, the first path in sorted order is returned.
+    pub fn hard_link_of(&self, path: &Path) -> Option<&Path> {
+        let id = self.files.get(path)?.id?;
+
+        self.files
+            .iter()
+            .filter(|(other, state)| {
+                *other != path && state.copied && !state.failed && state.id == Some(id)
+            })
+            .map(|(other, _)| other.as_path())
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn scan_reports_stable_files_once() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::create_dir(tmp.path().join("run")).await?;
+        fs::write(tmp.path().join("run/a.fastq"), "a").await?;
+        fs::write(tmp.path().join("run/a.fastq.sums"), "{}").await?;
+
+        let mut task = WatchTaskBuilder::default()
+            .with_directory(tmp.path().to_path_buf())
+            .build()
+            .await?;
+
+        assert_eq!(task.scan().await?, vec![PathBuf::from("run/a.fastq")]);
+        assert!(task.has_pending());
+
+        task.mark_processed(Path::new("run/a.fastq"));
+        assert!(task.scan().await?.is_empty());
+        assert!(!task.has_pending());
+
+        fs::write(tmp.path().join("run/a.fastq"), "ab").await?;
+        assert_eq!(task.scan().await?, vec![PathBuf::from("run/a.fastq")]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hard_links_are_detected_once_processed() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::write(tmp.path().join("a"), "a").await?;
+        fs::hard_link(tmp.path().join("a"), tmp.path().join("b")).await?;
+        fs::write(tmp.path().join("c"), "a").await?;
+
+        let mut task = WatchTaskBuilder::default()
+            .with_directory(tmp.path().to_path_buf())
+            .build()
+            .await?;
+
+        assert_eq!(task.scan().await?.len(), 3);
+        assert_eq!(task.hard_link_of(Path::new("b")), None);
+
+        task.mark_failed(Path::new("a"));
+        assert_eq!(task.hard_link_of(Path::new("b")), None);
+
+        fs::write(tmp.path().join("a"), "ab").await?;
+        task.scan().await?;
+        task.mark_processed(Path::new("a"));
+        assert_eq!(task.hard_link_of(Path::new("b")), Some(Path::new("a")));
+        assert_eq!(task.hard_link_of(Path::new("c")), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_files_are_retried_with_backoff() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::write(tmp.path().join("a"), "a").await?;
+
+        let mut task = WatchTaskBuilder::default()
+            .with_directory(tmp.path().to_path_buf())
+            .with_max_attempts(3)
+            .with_retry_backoff(Duration::from_millis(100))
+            .build()
+            .await?;
+        let path = Path::new("a");
+
+        assert_eq!(task.scan().await?, vec![PathBuf::from("a")]);
+        assert!(task.retry_later(path));
+        assert!(task.scan().await?.is_empty());
+        assert!(task.has_pending());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(task.scan().await?, vec![PathBuf::from("a")]);
+        assert!(task.retry_later(path));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(task.scan().await?.is_empty());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(task.scan().await?, vec![PathBuf::from("a")]);
+        assert!(!task.retry_later(path));
+        assert!(task.scan().await?.is_empty());
+        assert!(!task.has_pending());
+
+        // A file that changes after failing is copied again.
+        fs::write(tmp.path().join("a"), "ab").await?;
+        assert_eq!(task.scan().await?, vec![PathBuf::from("a")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_waits_for_stable_files() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::write(tmp.path().join("a"), "a").await?;
+
+        let mut task = WatchTaskBuilder::default()
+            .with_directory(tmp.path().to_path_buf())
+            .with_stable_for(Duration::from_secs(3600))
+            .build()
+            .await?;
+
+        assert!(task.scan().await?.is_empty());
+        assert!(task.has_pending());
+
+        Ok(())
+    }
+}
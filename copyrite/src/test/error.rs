@@ -9,6 +9,8 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     #[error("error generating file: {0}")]
     FileGenerate(String),
+    #[error("container error: {0}")]
+    ContainerError(String),
     #[error("io error: {0}")]
     IoError(#[from] io::Error),
 }
@@ -0,0 +1,112 @@
+//! A MinIO container for running integration tests against a real S3-compatible API. This
+//! requires Docker, and is only available with the `minio` feature.
+//!
+
+use crate::test::error::Error::ContainerError;
+use crate::test::error::Result;
+use aws_credential_types::Credentials;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::{BehaviorVersion, Region};
+use testcontainers_modules::minio::MinIO;
+use testcontainers_modules::testcontainers::ContainerAsync;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+/// The access key of the default MinIO user.
+pub const MINIO_ACCESS_KEY: &str = "minioadmin";
+
+/// The secret key of the default MinIO user.
+pub const MINIO_SECRET_KEY: &str = "minioadmin";
+
+/// The region used with MinIO.
+pub const MINIO_REGION: &str = "us-east-1";
+
+/// The port of the MinIO S3 API within the container.
+const MINIO_API_PORT: u16 = 9000;
+
+/// A running MinIO container. The container is stopped when this is dropped.
+pub struct MinioContainer {
+    // Keep the container alive for as long as this struct exists.
+    _container: ContainerAsync<MinIO>,
+    endpoint_url: String,
+}
+
+impl MinioContainer {
+    /// Start a new MinIO container and wait for the S3 API to be ready.
+    pub async fn start() -> Result<Self> {
+        let container = MinIO::default()
+            .start()
+            .await
+            .map_err(|err| ContainerError(err.to_string()))?;
+
+        let host = container
+            .get_host()
+            .await
+            .map_err(|err| ContainerError(err.to_string()))?;
+        let port = container
+            .get_host_port_ipv4(MINIO_API_PORT)
+            .await
+            .map_err(|err| ContainerError(err.to_string()))?;
+
+        Ok(Self {
+            _container: container,
+            endpoint_url: format!("http://{}:{}", host, port),
+        })
+    }
+
+    /// The endpoint URL of the S3 API.
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint_url
+    }
+
+    /// Create an S3 client that accesses the container.
+    pub fn client(&self) -> Client {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(MINIO_REGION))
+            .endpoint_url(&self.endpoint_url)
+            .credentials_provider(Credentials::new(
+                MINIO_ACCESS_KEY,
+                MINIO_SECRET_KEY,
+                None,
+                None,
+                "minio",
+            ))
+            .force_path_style(true)
+            .build();
+
+        Client::from_conf(config)
+    }
+
+    /// Create a bucket in the container.
+    pub async fn create_bucket(&self, bucket: &str) -> Result<()> {
+        self.client()
+            .create_bucket()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(|err| ContainerError(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// The command line options which point copyrite at the container. Only path-style
+    /// addressing is enabled, so that `GetObjectAttributes` and checksum mode are exercised.
+    pub fn cli_options(&self) -> Vec<String> {
+        [
+            "--credential-provider",
+            "static",
+            "--access-key-id",
+            MINIO_ACCESS_KEY,
+            "--secret-access-key",
+            MINIO_SECRET_KEY,
+            "--region",
+            MINIO_REGION,
+            "--endpoint-url",
+            &self.endpoint_url,
+            "--force-path-style",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+}
@@ -4,6 +4,8 @@
 #![doc(hidden)]
 
 pub mod error;
+#[cfg(feature = "minio")]
+pub mod minio;
 
 use crate::test::error::Error::{FileGenerate, IoError};
 use crate::test::error::Result;
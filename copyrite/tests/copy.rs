@@ -101,6 +101,7 @@ async fn copy_test() -> Result<()> {
         config.region.as_deref(),
         config.endpoint_url.as_deref(),
         config.secret.as_deref(),
+        None,
         no_overrides,
         config.is_s3_compatible(),
         config.is_s3_compatible(),
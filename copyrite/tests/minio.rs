@@ -0,0 +1,108 @@
+//! Integration tests that run generate, check and copy end-to-end against a MinIO container.
+//! These require Docker, are ignored by default and are only compiled with the `minio`
+//! feature, e.g. `cargo test --features minio --test minio -- --ignored`.
+//!
+
+#![cfg(feature = "minio")]
+
+use anyhow::Result;
+use copyrite::checksum::file::SumsFile;
+use copyrite::cli::Command;
+use copyrite::test::TestFileBuilder;
+use copyrite::test::minio::MinioContainer;
+use serde_json::Value;
+use std::fs;
+use tempfile::TempDir;
+
+const BUCKET: &str = "copyrite";
+
+#[ignore]
+#[tokio::test]
+async fn minio_generate_check_copy() -> Result<()> {
+    let minio = MinioContainer::start().await?;
+    minio.create_bucket(BUCKET).await?;
+    let client = minio.client();
+
+    let tmp = TempDir::new()?;
+    let original = TestFileBuilder::new()?.generate_bench_defaults()?;
+    let file = tmp.path().join("file");
+    fs::copy(&original, &file)?;
+    let file = file.to_string_lossy().to_string();
+
+    // Generate sums for the local file, including the multipart ETag that a 5MiB part size
+    // produces.
+    execute(
+        &minio,
+        &[
+            "generate",
+            &file,
+            "--checksum",
+            "md5,md5-aws-5mib,crc64nvme",
+        ],
+    )
+    .await?;
+    let sums: Value = serde_json::from_slice(&fs::read(SumsFile::format_sums_file(&file))?)?;
+    assert_eq!(sums["size"].as_u64(), Some(fs::metadata(&file)?.len()));
+
+    // A multipart copy to MinIO should produce a multipart ETag with two parts.
+    let multipart = format!("s3://{}/multipart", BUCKET);
+    execute(
+        &minio,
+        &[
+            "copy",
+            &file,
+            &multipart,
+            "--multipart-threshold",
+            "5MiB",
+            "--part-size",
+            "5MiB",
+        ],
+    )
+    .await?;
+    let head = client
+        .head_object()
+        .bucket(BUCKET)
+        .key("multipart")
+        .send()
+        .await?;
+    assert!(head.e_tag().is_some_and(|e_tag| e_tag.ends_with("-2\"")));
+
+    // A single part server-side copy within MinIO.
+    let single_part = format!("s3://{}/single_part", BUCKET);
+    execute(
+        &minio,
+        &[
+            "copy",
+            &multipart,
+            &single_part,
+            "--multipart-threshold",
+            "20MiB",
+            "--part-size",
+            "20MiB",
+        ],
+    )
+    .await?;
+
+    // Copy back to a local file and confirm that every location is identical.
+    let copy = tmp.path().join("copy").to_string_lossy().to_string();
+    execute(&minio, &["copy", &single_part, &copy]).await?;
+    assert_eq!(fs::read(&file)?, fs::read(&copy)?);
+
+    execute(
+        &minio,
+        &["check", &file, &multipart, &single_part, &copy, "--update"],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Execute a copyrite command against the container.
+async fn execute(minio: &MinioContainer, args: &[&str]) -> Result<()> {
+    let mut commands = vec!["copyrite".to_string()];
+    commands.extend(args.iter().map(|arg| arg.to_string()));
+    commands.extend(minio.cli_options());
+
+    Command::parse_from_iter(commands)?.execute().await?;
+    Ok(())
+}